@@ -0,0 +1,221 @@
+//! Criterion benchmarks for parsing, smart search, token accounting, and export rendering over
+//! generated corpora. Corpus sizes (records) default to 1k/100k/1M and can be
+//! overridden with `CC_CONVO_BENCH_SIZES=1000,50000`.
+//!
+//! Track changes across work with `cargo bench -p cc-convo-core -- --save-baseline <name>`
+//! and compare with `--baseline <name>`.
+
+use cc_convo_core::{
+    build_export_document, jsonl_records, parse_session_events, render_html, render_markdown,
+    search_sessions, session_usage, short_id, ExportStyle, ParseOutput, Record, SearchOptions,
+    Session, TimeWindow, TokenUsage,
+};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use serde_json::json;
+use std::fs::{self, File};
+use std::io::{BufReader, Write};
+use std::path::PathBuf;
+
+const RECORDS_PER_FILE: usize = 1000;
+
+struct Corpus {
+    records: usize,
+    dir: PathBuf,
+    sessions: Vec<Session>,
+}
+
+impl Drop for Corpus {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+fn corpus_sizes() -> Vec<usize> {
+    std::env::var("CC_CONVO_BENCH_SIZES")
+        .ok()
+        .map(|raw| {
+            raw.split(',')
+                .filter_map(|n| n.trim().parse().ok())
+                .collect()
+        })
+        .unwrap_or_else(|| vec![1_000, 100_000, 1_000_000])
+}
+
+/// Write `records` synthetic records split across session files.
+fn generate_corpus(records: usize) -> Corpus {
+    let dir = std::env::temp_dir().join(format!("cc-convo-bench-{records}-{}", std::process::id()));
+    let project = dir.join("-home-user-bench");
+    fs::create_dir_all(&project).expect("create corpus dir");
+    let mut sessions = Vec::new();
+    let mut written = 0;
+    let mut file_no = 0;
+    while written < records {
+        let n = RECORDS_PER_FILE.min(records - written);
+        let id = format!("{file_no:08}-0000-4000-8000-000000000000");
+        let path = project.join(format!("{id}.jsonl"));
+        let mut f = std::io::BufWriter::new(File::create(&path).expect("create file"));
+        for i in 0..n {
+            writeln!(f, "{}", synthetic_record(written + i)).expect("write record");
+        }
+        f.flush().expect("flush corpus file");
+        sessions.push(Session {
+            index: file_no + 1,
+            id_short: short_id(&id),
+            id,
+            project: "-home-user-bench".to_string(),
+            path,
+            modified_iso: "2026-01-01T00:00:00Z".to_string(),
+            modified_epoch: 0,
+            size_bytes: 0,
+            stitched: Vec::new(),
+        });
+        written += n;
+        file_no += 1;
+    }
+    Corpus {
+        records,
+        dir,
+        sessions,
+    }
+}
+
+fn synthetic_record(i: usize) -> String {
+    let ts = format!("2026-01-01T00:{:02}:{:02}Z", (i / 60) % 60, i % 60);
+    match i % 4 {
+        0 => json!({"type": "user", "timestamp": ts, "message": {"role": "user",
+            "content": format!("How do I configure the nginx proxy for service {i}?")}}),
+        1 => json!({"type": "assistant", "timestamp": ts, "message": {"role": "assistant",
+            "model": "claude-sonnet-4-5", "usage": {"input_tokens": 1200, "output_tokens": 80},
+            "content": [
+                {"type": "thinking", "thinking": "Consider upstream blocks."},
+                {"type": "tool_use", "id": format!("toolu_{i}"), "name": "Bash",
+                    "input": {"command": "cat /etc/nginx/nginx.conf"}}]}}),
+        2 => json!({"type": "progress", "timestamp": ts,
+            "data": {"type": "bash_progress", "output": "worker_processes auto;"}}),
+        _ => json!({"type": "assistant", "timestamp": ts, "message": {"role": "assistant",
+            "model": "claude-sonnet-4-5", "usage": {"input_tokens": 1400, "output_tokens": 200},
+            "content": [{"type": "text",
+                "text": format!("Add a `location /api/` block with proxy_pass for service {i}.\n\n```nginx\nlocation /api/ {{\n    proxy_pass http://backend/;\n}}\n```")}]}}),
+    }
+    .to_string()
+}
+
+fn parse_all(corpus: &Corpus, detailed: bool) -> Vec<(Session, ParseOutput)> {
+    corpus
+        .sessions
+        .iter()
+        .map(|s| {
+            (
+                s.clone(),
+                parse_session_events(&s.path, detailed).expect("parse corpus"),
+            )
+        })
+        .collect()
+}
+
+fn bench_parse(c: &mut Criterion, corpus: &Corpus) {
+    let mut group = c.benchmark_group("parse");
+    group.sample_size(10);
+    group.throughput(Throughput::Elements(corpus.records as u64));
+    for detailed in [false, true] {
+        let label = if detailed { "detailed" } else { "default" };
+        group.bench_with_input(
+            BenchmarkId::new(label, corpus.records),
+            corpus,
+            |b, corpus| b.iter(|| parse_all(corpus, detailed)),
+        );
+    }
+    group.finish();
+}
+
+fn bench_search(c: &mut Criterion, corpus: &Corpus) {
+    let options = SearchOptions::smart("nginx proxy");
+    let mut group = c.benchmark_group("search_smart");
+    group.sample_size(10);
+    group.throughput(Throughput::Elements(corpus.records as u64));
+    group.bench_with_input(
+        BenchmarkId::from_parameter(corpus.records),
+        corpus,
+        |b, corpus| b.iter(|| search_sessions(&corpus.sessions, &options).expect("search")),
+    );
+    group.finish();
+}
+
+/// Token totals the way `stats` gets them, by parsing every record.
+fn usage_by_full_parse(corpus: &Corpus) -> TokenUsage {
+    let mut total = TokenUsage::default();
+    for session in &corpus.sessions {
+        let file = File::open(&session.path).expect("open corpus file");
+        for value in jsonl_records(BufReader::new(file)).flatten().flatten() {
+            if let Some(usage) = Record::from_value(&value)
+                .ok()
+                .and_then(|r| r.message().and_then(|m| m.usage))
+            {
+                total += usage;
+            }
+        }
+    }
+    total
+}
+
+fn usage_by_scan(corpus: &Corpus) -> TokenUsage {
+    let mut total = TokenUsage::default();
+    for session in &corpus.sessions {
+        let scan = session_usage(session, &TimeWindow::default()).expect("scan usage");
+        for (_, usage) in scan.into_usage() {
+            total += usage;
+        }
+    }
+    total
+}
+
+fn bench_usage(c: &mut Criterion, corpus: &Corpus) {
+    let mut group = c.benchmark_group("usage");
+    group.sample_size(10);
+    group.throughput(Throughput::Elements(corpus.records as u64));
+    group.bench_with_input(
+        BenchmarkId::new("full_parse", corpus.records),
+        corpus,
+        |b, corpus| b.iter(|| usage_by_full_parse(corpus)),
+    );
+    group.bench_with_input(
+        BenchmarkId::new("usage_scan", corpus.records),
+        corpus,
+        |b, corpus| b.iter(|| usage_by_scan(corpus)),
+    );
+    group.finish();
+}
+
+fn bench_render(c: &mut Criterion, corpus: &Corpus) {
+    let docs = parse_all(corpus, true)
+        .into_iter()
+        .map(|(s, parsed)| build_export_document(&s, &parsed.events))
+        .collect::<Vec<_>>();
+    let mut group = c.benchmark_group("render");
+    group.sample_size(10);
+    group.throughput(Throughput::Elements(corpus.records as u64));
+    group.bench_with_input(
+        BenchmarkId::new("markdown", corpus.records),
+        &docs,
+        |b, docs| b.iter(|| render_markdown(docs)),
+    );
+    group.bench_with_input(
+        BenchmarkId::new("html", corpus.records),
+        &docs,
+        |b, docs| b.iter(|| render_html(docs, ExportStyle::default())),
+    );
+    group.finish();
+}
+
+fn benches(c: &mut Criterion) {
+    for records in corpus_sizes() {
+        let corpus = generate_corpus(records);
+        bench_parse(c, &corpus);
+        bench_search(c, &corpus);
+        bench_usage(c, &corpus);
+        bench_render(c, &corpus);
+    }
+}
+
+criterion_group!(pipeline, benches);
+criterion_main!(pipeline);
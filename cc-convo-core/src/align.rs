@@ -0,0 +1,233 @@
+//! Aligning the events of two sessions, such as a session and its
+//! continuation after compaction or two regenerations of one conversation,
+//! to show where they agree and where they went apart.
+
+use crate::parse::NormalizedEvent;
+use serde::Serialize;
+
+/// Above this many cells the alignment table gets too big, and the
+/// unmatched middle is paired up by position instead.
+const MAX_TABLE_CELLS: usize = 16_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffOp {
+    /// Same role and content on both sides.
+    Same,
+    /// Same role at the same place, different content.
+    Changed,
+    /// Only in the first session.
+    Removed,
+    /// Only in the second session.
+    Added,
+}
+
+/// One row of an alignment: indices into the two event lists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct AlignedEvent {
+    pub op: DiffOp,
+    pub a: Option<usize>,
+    pub b: Option<usize>,
+}
+
+/// Align `a` with `b`, keeping both in order. Identical events are matched
+/// as a longest common subsequence; between matches, events of the same
+/// role are paired as changed and the rest are removed or added.
+pub fn align_events(a: &[NormalizedEvent], b: &[NormalizedEvent]) -> Vec<AlignedEvent> {
+    let same =
+        |x: &NormalizedEvent, y: &NormalizedEvent| x.role == y.role && x.content == y.content;
+    let mut rows = Vec::new();
+    let mut gap_a = Vec::new();
+    let mut gap_b = Vec::new();
+    for (i, j) in diff_indices(a, b, same) {
+        match (i, j) {
+            (Some(i), Some(j)) => {
+                flush_gap(a, b, &mut gap_a, &mut gap_b, &mut rows);
+                rows.push(AlignedEvent {
+                    op: DiffOp::Same,
+                    a: Some(i),
+                    b: Some(j),
+                });
+            }
+            (Some(i), None) => gap_a.push(i),
+            (None, Some(j)) => gap_b.push(j),
+            (None, None) => {}
+        }
+    }
+    flush_gap(a, b, &mut gap_a, &mut gap_b, &mut rows);
+    rows
+}
+
+/// Pair the unmatched events between two matches: each event of `a` with
+/// the next unpaired event of `b` in the same role.
+fn flush_gap(
+    a: &[NormalizedEvent],
+    b: &[NormalizedEvent],
+    gap_a: &mut Vec<usize>,
+    gap_b: &mut Vec<usize>,
+    rows: &mut Vec<AlignedEvent>,
+) {
+    let mut rest_b = gap_b.drain(..).collect::<std::collections::VecDeque<_>>();
+    for i in gap_a.drain(..) {
+        match rest_b.iter().position(|&j| b[j].role == a[i].role) {
+            Some(k) => {
+                for j in rest_b.drain(..k) {
+                    rows.push(AlignedEvent {
+                        op: DiffOp::Added,
+                        a: None,
+                        b: Some(j),
+                    });
+                }
+                rows.push(AlignedEvent {
+                    op: DiffOp::Changed,
+                    a: Some(i),
+                    b: rest_b.pop_front(),
+                });
+            }
+            None => rows.push(AlignedEvent {
+                op: DiffOp::Removed,
+                a: Some(i),
+                b: None,
+            }),
+        }
+    }
+    rows.extend(rest_b.into_iter().map(|j| AlignedEvent {
+        op: DiffOp::Added,
+        a: None,
+        b: Some(j),
+    }));
+}
+
+/// A line-by-line diff of `a` and `b`: each line with `-`, `+`, or ` `.
+pub fn diff_lines<'t>(a: &'t str, b: &'t str) -> Vec<(char, &'t str)> {
+    let a = a.lines().collect::<Vec<_>>();
+    let b = b.lines().collect::<Vec<_>>();
+    diff_indices(&a, &b, |x, y| x == y)
+        .into_iter()
+        .map(|pair| match pair {
+            (Some(i), Some(_)) => (' ', a[i]),
+            (Some(i), None) => ('-', a[i]),
+            (_, Some(j)) => ('+', b[j]),
+            (None, None) => unreachable!("every step consumes a side"),
+        })
+        .collect()
+}
+
+/// Steps of a longest-common-subsequence walk: `(Some, Some)` for a match,
+/// otherwise the index of the side that advances. Removals come before
+/// additions within a run of differences.
+fn diff_indices<T>(
+    a: &[T],
+    b: &[T],
+    same: impl Fn(&T, &T) -> bool,
+) -> Vec<(Option<usize>, Option<usize>)> {
+    let prefix = a.iter().zip(b).take_while(|(x, y)| same(x, y)).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| same(x, y))
+        .count();
+    let (n, m) = (a.len() - prefix - suffix, b.len() - prefix - suffix);
+    let mut steps = (0..prefix).map(|i| (Some(i), Some(i))).collect::<Vec<_>>();
+
+    if n.saturating_mul(m) > MAX_TABLE_CELLS {
+        steps.extend((0..n).map(|i| (Some(prefix + i), None)));
+        steps.extend((0..m).map(|j| (None, Some(prefix + j))));
+    } else {
+        // lcs[i][j]: length of the common subsequence of a[i..] and b[j..].
+        let width = m + 1;
+        let mut lcs = vec![0u32; (n + 1) * width];
+        for i in (0..n).rev() {
+            for j in (0..m).rev() {
+                lcs[i * width + j] = if same(&a[prefix + i], &b[prefix + j]) {
+                    lcs[(i + 1) * width + j + 1] + 1
+                } else {
+                    lcs[(i + 1) * width + j].max(lcs[i * width + j + 1])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < n || j < m {
+            if i < n && j < m && same(&a[prefix + i], &b[prefix + j]) {
+                steps.push((Some(prefix + i), Some(prefix + j)));
+                i += 1;
+                j += 1;
+            } else if j == m || (i < n && lcs[(i + 1) * width + j] >= lcs[i * width + j + 1]) {
+                steps.push((Some(prefix + i), None));
+                i += 1;
+            } else {
+                steps.push((None, Some(prefix + j)));
+                j += 1;
+            }
+        }
+    }
+
+    let (tail_a, tail_b) = (a.len() - suffix, b.len() - suffix);
+    steps.extend((0..suffix).map(|k| (Some(tail_a + k), Some(tail_b + k))));
+    steps
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn events(turns: &[(&str, &str)]) -> Vec<NormalizedEvent> {
+        turns
+            .iter()
+            .map(|(role, content)| NormalizedEvent {
+                role: role.to_string(),
+                source_type: role.to_string(),
+                timestamp: None,
+                content: content.to_string(),
+                uuid: None,
+                parent_uuid: None,
+                source: None,
+                model: None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn regenerated_replies_align_as_changes() {
+        let a = events(&[
+            ("user", "Fix the login test"),
+            ("assistant", "The cache races.\nAwait it."),
+            ("user", "Thanks"),
+            ("assistant", "Anything else?"),
+        ]);
+        let b = events(&[
+            ("user", "Fix the login test"),
+            ("assistant", "The cache races.\nWarm it up first."),
+            ("user", "Also check logout"),
+            ("assistant", "Logout is fine."),
+            ("user", "Thanks"),
+        ]);
+        let ops = align_events(&a, &b)
+            .into_iter()
+            .map(|row| (row.op, row.a, row.b))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            ops,
+            vec![
+                (DiffOp::Same, Some(0), Some(0)),
+                (DiffOp::Changed, Some(1), Some(1)),
+                (DiffOp::Added, None, Some(2)),
+                (DiffOp::Added, None, Some(3)),
+                (DiffOp::Same, Some(2), Some(4)),
+                (DiffOp::Removed, Some(3), None),
+            ]
+        );
+        assert_eq!(
+            diff_lines(&a[1].content, &b[1].content),
+            vec![
+                (' ', "The cache races."),
+                ('-', "Await it."),
+                ('+', "Warm it up first.")
+            ]
+        );
+        assert!(align_events(&[], &b)
+            .iter()
+            .all(|row| row.op == DiffOp::Added));
+    }
+}
@@ -0,0 +1,227 @@
+//! How smart and query search compare a query's words with event text:
+//! stopwords left out of the query, and optional Snowball stemming so that
+//! `configured` finds `configuring`, per language.
+
+use rust_stemmers::{Algorithm, Stemmer};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// Language of the stopword list and the stemmer.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    #[default]
+    English,
+    French,
+    German,
+    Spanish,
+}
+
+impl Language {
+    fn stopwords(self) -> &'static [&'static str] {
+        match self {
+            Self::English => ENGLISH_STOPWORDS,
+            Self::French => FRENCH_STOPWORDS,
+            Self::German => GERMAN_STOPWORDS,
+            Self::Spanish => SPANISH_STOPWORDS,
+        }
+    }
+
+    fn algorithm(self) -> Algorithm {
+        match self {
+            Self::English => Algorithm::English,
+            Self::French => Algorithm::French,
+            Self::German => Algorithm::German,
+            Self::Spanish => Algorithm::Spanish,
+        }
+    }
+}
+
+/// Which words of a query count, and how they are compared.
+#[derive(Debug, Clone)]
+pub struct Analysis {
+    pub language: Language,
+    /// Leave the language's stopwords, and `extra_stopwords`, out of queries.
+    pub stopwords: bool,
+    pub extra_stopwords: Vec<String>,
+    /// Compare words by stem.
+    pub stem: bool,
+}
+
+impl Default for Analysis {
+    /// English stopwords, no stemming.
+    fn default() -> Self {
+        Self {
+            language: Language::default(),
+            stopwords: true,
+            extra_stopwords: Vec::new(),
+            stem: false,
+        }
+    }
+}
+
+impl Analysis {
+    /// Every word counts and matches only as typed.
+    pub fn literal() -> Self {
+        Self {
+            stopwords: false,
+            ..Self::default()
+        }
+    }
+
+    /// Whether the analysis changes anything about a query's words.
+    pub fn is_literal(&self) -> bool {
+        !self.stopwords && !self.stem
+    }
+
+    /// Whether `word`, ignoring case and surrounding punctuation, is a
+    /// stopword. Always false with stopwords off.
+    pub fn is_stopword(&self, word: &str) -> bool {
+        if !self.stopwords {
+            return false;
+        }
+        let word = bare(word).to_lowercase();
+        self.language.stopwords().contains(&word.as_str())
+            || self
+                .extra_stopwords
+                .iter()
+                .any(|w| w.eq_ignore_ascii_case(&word))
+    }
+
+    /// The words of a query that count: stripped of surrounding punctuation,
+    /// without stopwords unless those are all there is. Returns them and the
+    /// stopwords left out.
+    pub fn content_words(&self, words: &[String]) -> (Vec<String>, Vec<String>) {
+        if self.is_literal() {
+            return (words.to_vec(), Vec::new());
+        }
+        let words = words
+            .iter()
+            .map(|w| bare(w).to_string())
+            .filter(|w| !w.is_empty())
+            .collect::<Vec<_>>();
+        let (stop, content): (Vec<_>, Vec<_>) =
+            words.iter().cloned().partition(|w| self.is_stopword(w));
+        if content.is_empty() {
+            (words, Vec::new())
+        } else {
+            (content, stop)
+        }
+    }
+
+    /// The stem of one word, lowercased; `None` without stemming or for
+    /// text of more than one word.
+    pub fn stem(&self, word: &str) -> Option<String> {
+        if !self.stem {
+            return None;
+        }
+        let word = bare(word);
+        if word.is_empty() || word.contains(|c: char| !c.is_alphanumeric()) {
+            return None;
+        }
+        let stemmer = Stemmer::create(self.language.algorithm());
+        Some(stemmer.stem(&word.to_lowercase()).into_owned())
+    }
+
+    /// The stems of every word in `text`; empty without stemming.
+    pub fn text_stems(&self, text: &str) -> HashSet<String> {
+        if !self.stem {
+            return HashSet::new();
+        }
+        let stemmer = Stemmer::create(self.language.algorithm());
+        text.split(|c: char| !c.is_alphanumeric())
+            .filter(|w| !w.is_empty())
+            .map(|w| stemmer.stem(&w.to_lowercase()).into_owned())
+            .collect()
+    }
+}
+
+/// `word` without leading or trailing punctuation.
+fn bare(word: &str) -> &str {
+    word.trim_matches(|c: char| !c.is_alphanumeric())
+}
+
+const ENGLISH_STOPWORDS: &[&str] = &[
+    "a", "about", "after", "again", "all", "am", "an", "and", "any", "are", "as", "at", "be",
+    "because", "been", "before", "being", "between", "both", "but", "by", "can", "could", "did",
+    "do", "does", "doing", "down", "during", "each", "few", "for", "from", "further", "had", "has",
+    "have", "having", "he", "her", "here", "hers", "him", "his", "how", "i", "if", "in", "into",
+    "is", "it", "its", "itself", "just", "me", "more", "most", "my", "no", "nor", "not", "now",
+    "of", "off", "on", "once", "only", "or", "other", "our", "ours", "out", "over", "own", "same",
+    "she", "should", "so", "some", "such", "than", "that", "the", "their", "theirs", "them",
+    "then", "there", "these", "they", "this", "those", "through", "to", "too", "under", "until",
+    "up", "us", "very", "was", "we", "were", "what", "when", "where", "which", "while", "who",
+    "whom", "why", "will", "with", "would", "you", "your", "yours",
+];
+
+const FRENCH_STOPWORDS: &[&str] = &[
+    "à", "au", "aux", "avec", "ce", "ces", "cette", "comment", "dans", "de", "des", "du", "elle",
+    "en", "est", "et", "eu", "il", "ils", "je", "la", "le", "les", "leur", "lui", "ma", "mais",
+    "me", "même", "mes", "moi", "mon", "ne", "nos", "notre", "nous", "on", "ou", "où", "par",
+    "pas", "pour", "pourquoi", "qu", "que", "qui", "quoi", "sa", "se", "ses", "son", "sont", "sur",
+    "ta", "te", "tes", "toi", "ton", "tu", "un", "une", "vos", "votre", "vous", "y",
+];
+
+const GERMAN_STOPWORDS: &[&str] = &[
+    "aber", "als", "am", "an", "auch", "auf", "aus", "bei", "bin", "bis", "da", "das", "dass",
+    "dem", "den", "der", "des", "die", "doch", "du", "ein", "eine", "einem", "einen", "einer",
+    "er", "es", "für", "hat", "haben", "ich", "ihr", "im", "in", "ist", "ja", "man", "mit", "nach",
+    "nicht", "noch", "nur", "oder", "sich", "sie", "sind", "so", "um", "und", "uns", "von", "vor",
+    "war", "warum", "was", "wie", "wir", "wo", "zu", "zum", "zur",
+];
+
+const SPANISH_STOPWORDS: &[&str] = &[
+    "a", "al", "como", "con", "cómo", "de", "del", "el", "ella", "en", "es", "esa", "ese", "esta",
+    "este", "está", "fue", "ha", "hay", "la", "las", "le", "lo", "los", "me", "mi", "no", "nos",
+    "o", "para", "pero", "por", "porque", "qué", "que", "se", "si", "sin", "su", "sus", "te", "tu",
+    "un", "una", "y", "ya", "yo",
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stopwords_drop_out_unless_nothing_else_is_left() {
+        let analysis = Analysis {
+            extra_stopwords: vec!["Please".to_string()],
+            ..Analysis::default()
+        };
+        let words = |query: &str| {
+            query
+                .split_whitespace()
+                .map(str::to_string)
+                .collect::<Vec<_>>()
+        };
+        let (content, stop) =
+            analysis.content_words(&words("please, how did we configure the nginx proxy?"));
+        assert_eq!(content, ["configure", "nginx", "proxy"]);
+        assert_eq!(stop, ["please", "how", "did", "we", "the"]);
+        assert_eq!(analysis.content_words(&words("the who")).0, ["the", "who"]);
+        assert_eq!(
+            Analysis::literal().content_words(&words("the proxy?")).0,
+            ["the", "proxy?"]
+        );
+    }
+
+    #[test]
+    fn stems_match_across_inflections() {
+        let analysis = Analysis {
+            stem: true,
+            ..Analysis::default()
+        };
+        let stems = analysis.text_stems("We configured proxies; it's configuring now.");
+        assert!(stems.contains(&analysis.stem("configure").expect("stem")));
+        assert!(stems.contains(&analysis.stem("Proxy").expect("stem")));
+        assert_eq!(analysis.stem("reverse proxy"), None);
+        assert_eq!(Analysis::default().stem("proxies"), None);
+
+        let german = Analysis {
+            language: Language::German,
+            stem: true,
+            ..Analysis::default()
+        };
+        assert_eq!(german.stem("Häuser"), german.stem("Haus"));
+    }
+}
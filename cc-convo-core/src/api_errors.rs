@@ -0,0 +1,179 @@
+//! API failures logged in transcripts: calls Claude Code retried and the
+//! ones it gave up on, for seeing how much session time backend trouble costs.
+
+use crate::record::{Record, RecordBody};
+use crate::text::ellipsize;
+use serde::Serialize;
+use serde_json::Value;
+
+/// What the API said went wrong.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ApiErrorKind {
+    /// 529 or `overloaded_error`.
+    Overloaded,
+    /// 429 or `rate_limit_error`.
+    RateLimit,
+    /// Any other 5xx or `api_error`.
+    Server,
+    /// Timeouts, connection failures, and 4xx responses.
+    Other,
+}
+
+impl ApiErrorKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Overloaded => "overloaded",
+            Self::RateLimit => "rate-limit",
+            Self::Server => "server",
+            Self::Other => "other",
+        }
+    }
+
+    fn classify(status: Option<u64>, detail: &str) -> Self {
+        let detail = detail.to_lowercase();
+        if status == Some(529) || detail.contains("overloaded") {
+            Self::Overloaded
+        } else if status == Some(429)
+            || detail.contains("rate_limit")
+            || detail.contains("rate limit")
+        {
+            Self::RateLimit
+        } else if status.is_some_and(|s| (500..600).contains(&s)) || detail.contains("api_error") {
+            Self::Server
+        } else {
+            Self::Other
+        }
+    }
+}
+
+/// One failed API call.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ApiError {
+    pub kind: ApiErrorKind,
+    pub status: Option<u64>,
+    pub timestamp: Option<String>,
+    /// Set when Claude Code retried the call; `None` for the error it showed
+    /// in place of a reply after giving up.
+    pub retry: Option<Retry>,
+    pub message: String,
+}
+
+/// The retry scheduled after a failed call.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Retry {
+    pub attempt: Option<u64>,
+    pub max_retries: Option<u64>,
+    pub wait_ms: Option<u64>,
+}
+
+impl ApiError {
+    /// The failure `record` logs: a `system` record with subtype `api_error`,
+    /// or an assistant record flagged `isApiErrorMessage`.
+    pub fn from_record(record: &Record) -> Option<Self> {
+        match &record.body {
+            RecordBody::System(system) if system.subtype.as_deref() == Some("api_error") => {
+                let error = system.error.as_ref().unwrap_or(&Value::Null);
+                let status = error.get("status").and_then(Value::as_u64);
+                let message = error_message(error)
+                    .map(str::to_string)
+                    .unwrap_or_else(|| error.to_string());
+                Some(Self {
+                    kind: ApiErrorKind::classify(status, &error.to_string()),
+                    status,
+                    timestamp: record.head.timestamp.clone(),
+                    retry: Some(Retry {
+                        attempt: system.retry_attempt,
+                        max_retries: system.max_retries,
+                        wait_ms: system.retry_in_ms.map(|ms| ms.max(0.0).round() as u64),
+                    }),
+                    message: ellipsize(&message, 160),
+                })
+            }
+            RecordBody::Assistant(reply) if record.head.is_api_error_message == Some(true) => {
+                let text = reply.message.content.text(false);
+                let text = text.trim();
+                let detail = text.strip_prefix("API Error:").unwrap_or(text).trim();
+                let status = detail
+                    .get(..3)
+                    .filter(|code| code.bytes().all(|b| b.is_ascii_digit()))
+                    .and_then(|code| code.parse().ok());
+                Some(Self {
+                    kind: ApiErrorKind::classify(status, detail),
+                    status,
+                    timestamp: record.head.timestamp.clone(),
+                    retry: None,
+                    message: ellipsize(detail, 160),
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Time spent waiting before the retry, if one was scheduled.
+    pub fn wait_ms(&self) -> u64 {
+        self.retry.as_ref().and_then(|r| r.wait_ms).unwrap_or(0)
+    }
+}
+
+/// The innermost `message` string of an error response.
+fn error_message(error: &Value) -> Option<&str> {
+    let nested = error.get("error").and_then(error_message);
+    nested.or_else(|| error.get("message").and_then(Value::as_str))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn retries_and_final_errors_are_classified() {
+        let retry = Record::from_value(&json!({
+            "type": "system",
+            "subtype": "api_error",
+            "level": "error",
+            "timestamp": "2026-01-06T14:31:00.000Z",
+            "error": {"status": 529, "error": {"type": "error", "error": {"type": "overloaded_error", "message": "Overloaded"}}},
+            "retryInMs": 1204.6,
+            "retryAttempt": 2,
+            "maxRetries": 10,
+        }))
+        .expect("system record");
+        let error = ApiError::from_record(&retry).expect("api error");
+        assert_eq!(error.kind, ApiErrorKind::Overloaded);
+        assert_eq!(
+            (error.status, error.message.as_str()),
+            (Some(529), "Overloaded")
+        );
+        assert_eq!(error.wait_ms(), 1205);
+        assert_eq!(error.retry.and_then(|r| r.attempt), Some(2));
+
+        let gave_up = Record::from_value(&json!({
+            "type": "assistant",
+            "isApiErrorMessage": true,
+            "message": {"model": "<synthetic>", "content": [{"type": "text", "text": "API Error: 429 {\"type\":\"error\",\"error\":{\"type\":\"rate_limit_error\"}}"}]},
+        }))
+        .expect("assistant record");
+        let error = ApiError::from_record(&gave_up).expect("api error");
+        assert_eq!(
+            (error.kind, error.status),
+            (ApiErrorKind::RateLimit, Some(429))
+        );
+        assert!(error.retry.is_none());
+
+        let timeout = Record::from_value(&json!({
+            "type": "assistant",
+            "isApiErrorMessage": true,
+            "message": {"content": "API Error: Request timed out."},
+        }))
+        .expect("assistant record");
+        let error = ApiError::from_record(&timeout).expect("api error");
+        assert_eq!((error.kind, error.status), (ApiErrorKind::Other, None));
+        assert_eq!(error.message, "Request timed out.");
+
+        let reply = Record::from_value(&json!({"type": "assistant", "message": {"content": "hi"}}))
+            .expect("assistant record");
+        assert!(ApiError::from_record(&reply).is_none());
+    }
+}
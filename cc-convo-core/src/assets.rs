@@ -0,0 +1,146 @@
+//! Images and documents pasted into a conversation, decoded from their
+//! inline base64 so an export can link to files instead of a placeholder.
+
+use crate::fs::write_atomic;
+use crate::record::MediaSource;
+use crate::semantic::fnv1a;
+use anyhow::{Context, Result};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Directory, next to an export, that its asset links point into.
+pub const ASSETS_DIR: &str = "assets";
+
+/// One decoded attachment, named by a hash of its bytes so the same image
+/// pasted twice is written once.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Asset {
+    pub name: String,
+    pub media_type: String,
+    pub bytes: Vec<u8>,
+}
+
+impl Asset {
+    /// `None` for sources without inline data (`url`, `file`) or with data
+    /// that isn't valid base64.
+    pub fn decode(source: &MediaSource) -> Option<Self> {
+        let data = source.data.as_deref()?;
+        let bytes = match source.source_type.as_deref() {
+            Some("base64") => STANDARD.decode(data.trim()).ok()?,
+            Some("text") => data.as_bytes().to_vec(),
+            _ => return None,
+        };
+        let media_type = source
+            .media_type
+            .clone()
+            .unwrap_or_else(|| "application/octet-stream".to_string());
+        Some(Self {
+            name: format!("{:016x}.{}", fnv1a(&bytes), extension_for(&media_type)),
+            media_type,
+            bytes,
+        })
+    }
+
+    /// The link an export next to the assets directory uses.
+    pub fn link(&self) -> String {
+        format!("{ASSETS_DIR}/{}", self.name)
+    }
+}
+
+fn extension_for(media_type: &str) -> &'static str {
+    match media_type {
+        "image/png" => "png",
+        "image/jpeg" | "image/jpg" => "jpg",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        "image/svg+xml" => "svg",
+        "application/pdf" => "pdf",
+        "text/plain" => "txt",
+        "text/markdown" => "md",
+        "text/html" => "html",
+        "text/csv" => "csv",
+        "application/json" => "json",
+        _ => "bin",
+    }
+}
+
+/// Decode `source` into `assets` (once per distinct file) and return its
+/// link, or `None` when there is nothing to decode or nowhere to keep it.
+pub(crate) fn attach(
+    source: Option<&MediaSource>,
+    assets: Option<&mut Vec<Asset>>,
+) -> Option<String> {
+    let assets = assets?;
+    let asset = Asset::decode(source?)?;
+    let link = asset.link();
+    if !assets.iter().any(|a| a.name == asset.name) {
+        assets.push(asset);
+    }
+    Some(link)
+}
+
+/// Write `assets` into `<dir>/assets/`, skipping files already there from an
+/// earlier export, and return the paths written.
+pub fn write_assets(dir: &Path, assets: &[Asset]) -> Result<Vec<PathBuf>> {
+    let mut written = Vec::new();
+    if assets.is_empty() {
+        return Ok(written);
+    }
+    let assets_dir = dir.join(ASSETS_DIR);
+    fs::create_dir_all(&assets_dir)
+        .with_context(|| format!("Failed to create {}", assets_dir.display()))?;
+    for asset in assets {
+        let path = assets_dir.join(&asset.name);
+        if !path.exists() {
+            write_atomic(&path, &asset.bytes)?;
+            written.push(path);
+        }
+    }
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::record::MessageContent;
+    use crate::testutil::unique_temp_path;
+    use serde::Deserialize;
+    use serde_json::json;
+
+    #[test]
+    fn inline_images_and_documents_become_linked_files() {
+        let png = STANDARD.encode(b"\x89PNG fake");
+        let content = MessageContent::deserialize(json!([
+            {"type": "text", "text": "see"},
+            {"type": "image", "source": {"type": "base64", "media_type": "image/png", "data": png}},
+            {"type": "image", "source": {"type": "base64", "media_type": "image/png", "data": png}},
+            {"type": "image", "source": {"type": "url", "url": "https://example.com/a.png"}},
+            {"type": "document", "title": "notes",
+             "source": {"type": "text", "media_type": "text/plain", "data": "hello"}},
+        ]))
+        .expect("content");
+
+        assert_eq!(
+            content.text(true),
+            "see\n[image omitted]\n[image omitted]\n[image omitted]\n[document omitted]"
+        );
+        let mut assets = Vec::new();
+        let text = content.text_with_assets(true, &mut assets);
+        assert_eq!(assets.len(), 2);
+        let (image, doc) = (assets[0].link(), assets[1].link());
+        assert!(image.ends_with(".png") && doc.ends_with(".txt"));
+        assert_eq!(
+            text,
+            format!("see\n![image]({image})\n![image]({image})\n[image omitted]\n[document: notes]({doc})")
+        );
+
+        let root = unique_temp_path("cc-convo-test-assets");
+        let written = write_assets(&root, &assets).expect("write");
+        assert_eq!(written.len(), 2);
+        assert_eq!(fs::read(root.join(&doc)).expect("doc"), b"hello");
+        assert!(write_assets(&root, &assets).expect("rewrite").is_empty());
+        fs::remove_dir_all(&root).expect("cleanup dir");
+    }
+}
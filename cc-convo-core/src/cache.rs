@@ -0,0 +1,179 @@
+//! Per-session metadata cached between runs, so listing thousands of
+//! transcripts only re-reads the files that changed since the last listing.
+
+use crate::discovery::Session;
+use crate::fs::{lock_path, write_atomic, FileLock};
+use anyhow::{Context, Result};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// Bumped whenever the cached data changes shape, to drop older caches.
+const CACHE_VERSION: u32 = 5;
+
+/// Values computed from a session's files, keyed by the session's path and
+/// valid while every file keeps its size and mtime.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetadataCache<T> {
+    version: u32,
+    entries: BTreeMap<PathBuf, CacheEntry<T>>,
+    #[serde(skip)]
+    dirty: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CacheEntry<T> {
+    files: Vec<FileStamp>,
+    value: T,
+}
+
+/// Size and modification time of one transcript file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileStamp {
+    pub path: PathBuf,
+    pub size_bytes: u64,
+    pub modified_nanos: u64,
+}
+
+impl FileStamp {
+    /// `None` if the file can't be stat'ed.
+    pub fn of(path: &Path) -> Option<Self> {
+        let metadata = fs::metadata(path).ok()?;
+        let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+        Some(Self {
+            path: path.to_path_buf(),
+            size_bytes: metadata.len(),
+            modified_nanos: u64::try_from(modified.as_nanos()).unwrap_or(u64::MAX),
+        })
+    }
+}
+
+impl<T> Default for MetadataCache<T> {
+    fn default() -> Self {
+        Self {
+            version: CACHE_VERSION,
+            entries: BTreeMap::new(),
+            dirty: false,
+        }
+    }
+}
+
+impl<T: Clone + Serialize + DeserializeOwned> MetadataCache<T> {
+    /// Read the cache at `path`. A missing, unreadable, or outdated cache is
+    /// an empty one: it only costs a rescan.
+    pub fn load(path: &Path) -> Self {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|raw| serde_json::from_str::<Self>(&raw).ok())
+            .filter(|cache| cache.version == CACHE_VERSION)
+            .unwrap_or_default()
+    }
+
+    /// The cached value for `session`, if none of its files changed since.
+    pub fn get(&self, session: &Session) -> Option<&T> {
+        let entry = self.entries.get(&session.path)?;
+        (entry.files == stamps(session)).then_some(&entry.value)
+    }
+
+    /// The cached value for `session` if none of its files changed, else the
+    /// result of `compute`, which is then cached.
+    pub fn get_or_insert_with(
+        &mut self,
+        session: &Session,
+        compute: impl FnOnce() -> Result<T>,
+    ) -> Result<T> {
+        let files = stamps(session);
+        if let Some(entry) = self.entries.get(&session.path) {
+            if entry.files == files {
+                return Ok(entry.value.clone());
+            }
+        }
+        let value = compute()?;
+        self.entries.insert(
+            session.path.clone(),
+            CacheEntry {
+                files,
+                value: value.clone(),
+            },
+        );
+        self.dirty = true;
+        Ok(value)
+    }
+
+    /// Write the cache to `path` if anything was added, dropping entries for
+    /// sessions whose file is gone. `on_wait` runs if another process holds
+    /// the lock.
+    pub fn save(&mut self, path: &Path, on_wait: impl FnOnce(&Path)) -> Result<()> {
+        if !self.dirty {
+            return Ok(());
+        }
+        self.entries.retain(|session_path, _| session_path.exists());
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        let _lock = FileLock::acquire(&lock_path(path), on_wait)?;
+        write_atomic(path, serde_json::to_string(self)?)?;
+        self.dirty = false;
+        Ok(())
+    }
+}
+
+fn stamps(session: &Session) -> Vec<FileStamp> {
+    session.paths().filter_map(FileStamp::of).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::discovery::{discover, TimeWindow};
+    use crate::testutil::{unique_temp_path, write_jsonl};
+    use std::cell::Cell;
+
+    #[test]
+    fn cached_values_last_until_a_file_changes() {
+        let root = unique_temp_path("cc-convo-test-cache");
+        let project = root.join("-home-user-p");
+        fs::create_dir_all(&project).expect("create temp dir");
+        let transcript = project.join("s1.jsonl");
+        write_jsonl(&transcript, &[r#"{"type":"user"}"#]);
+        let cache_path = root.join("cache").join("sessions.json");
+
+        let computed = Cell::new(0);
+        let lines = |session: &Session| {
+            computed.set(computed.get() + 1);
+            Ok(fs::read_to_string(&session.path)?.lines().count())
+        };
+        let sessions = discover(&root, &TimeWindow::default())
+            .expect("discover")
+            .sessions;
+        let mut cache = MetadataCache::<usize>::load(&cache_path);
+        let first = cache
+            .get_or_insert_with(&sessions[0], || lines(&sessions[0]))
+            .expect("compute");
+        assert_eq!(first, 1);
+        cache.save(&cache_path, |_| {}).expect("save");
+
+        let mut cache = MetadataCache::<usize>::load(&cache_path);
+        let again = cache
+            .get_or_insert_with(&sessions[0], || lines(&sessions[0]))
+            .expect("cached");
+        assert_eq!((again, computed.get()), (1, 1));
+        assert_eq!(cache.get(&sessions[0]), Some(&1));
+
+        write_jsonl(&transcript, &[r#"{"type":"user"}"#, r#"{"type":"user"}"#]);
+        assert_eq!(cache.get(&sessions[0]), None);
+        let changed = cache
+            .get_or_insert_with(&sessions[0], || lines(&sessions[0]))
+            .expect("recompute");
+        assert_eq!((changed, computed.get()), (2, 2));
+
+        fs::write(&cache_path, "not json").expect("corrupt cache");
+        assert!(MetadataCache::<usize>::load(&cache_path).entries.is_empty());
+
+        fs::remove_dir_all(&root).expect("cleanup dir");
+    }
+}
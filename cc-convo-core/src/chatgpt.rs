@@ -0,0 +1,320 @@
+//! Converting a ChatGPT data export into transcripts shaped like Claude
+//! Code's, so discovery, search, export, and stats work on them unchanged.
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, SecondsFormat, TimeZone, Utc};
+use serde_json::{json, Map, Value};
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+/// The file inside the export archive that holds every conversation.
+const CONVERSATIONS_FILE: &str = "conversations.json";
+
+/// One ChatGPT conversation as transcript records.
+#[derive(Debug, Clone)]
+pub struct ImportedSession {
+    pub id: String,
+    pub title: Option<String>,
+    pub updated_at: Option<DateTime<Utc>>,
+    pub records: Vec<Value>,
+}
+
+impl ImportedSession {
+    pub fn to_jsonl(&self) -> String {
+        self.records
+            .iter()
+            .map(|record| format!("{record}\n"))
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ChatgptImport {
+    pub sessions: Vec<ImportedSession>,
+    /// Conversations without an id or any user or assistant text.
+    pub skipped: usize,
+}
+
+/// Read an export: the `.zip` ChatGPT emails, or `conversations.json` taken
+/// out of it.
+pub fn read_chatgpt_export(path: &Path) -> Result<ChatgptImport> {
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let is_zip = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("zip"));
+    let raw: Value = if is_zip {
+        let mut archive = zip::ZipArchive::new(BufReader::new(file))
+            .with_context(|| format!("{} is not a zip archive", path.display()))?;
+        let name = archive
+            .file_names()
+            .find(|name| name.rsplit('/').next() == Some(CONVERSATIONS_FILE))
+            .map(str::to_string);
+        let Some(name) = name else {
+            bail!(
+                "{} has no {CONVERSATIONS_FILE}; is it a ChatGPT data export?",
+                path.display()
+            );
+        };
+        let mut body = String::new();
+        archive.by_name(&name)?.read_to_string(&mut body)?;
+        serde_json::from_str(&body)
+    } else {
+        serde_json::from_reader(BufReader::new(file))
+    }
+    .with_context(|| format!("Invalid {CONVERSATIONS_FILE} in {}", path.display()))?;
+    convert_conversations(&raw)
+}
+
+/// Convert the parsed `conversations.json` array.
+pub fn convert_conversations(raw: &Value) -> Result<ChatgptImport> {
+    let Some(conversations) = raw.as_array() else {
+        bail!("Expected {CONVERSATIONS_FILE} to hold an array of conversations");
+    };
+    let mut import = ChatgptImport::default();
+    for conversation in conversations {
+        match convert_conversation(conversation) {
+            Some(session) => import.sessions.push(session),
+            None => import.skipped += 1,
+        }
+    }
+    Ok(import)
+}
+
+fn convert_conversation(conversation: &Value) -> Option<ImportedSession> {
+    let field = |key: &str| conversation.get(key).and_then(Value::as_str);
+    let id = field("conversation_id")
+        .or_else(|| field("id"))?
+        .to_string();
+    let title = field("title")
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .map(str::to_string);
+    let created = conversation.get("create_time").and_then(epoch_to_utc);
+    let updated_at = conversation
+        .get("update_time")
+        .and_then(epoch_to_utc)
+        .or(created);
+    let mapping = conversation.get("mapping")?.as_object()?;
+
+    let mut records = Vec::new();
+    if let Some(title) = &title {
+        records.push(json!({"type": "custom-title", "customTitle": title, "sessionId": id}));
+    }
+    let mut parent: Option<&str> = None;
+    let mut at = created;
+    for node_id in active_branch(mapping, field("current_node")) {
+        let Some(message) = mapping.get(node_id).and_then(|n| n.get("message")) else {
+            continue;
+        };
+        let role = message
+            .pointer("/author/role")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        let hidden = message
+            .pointer("/metadata/is_visually_hidden_from_conversation")
+            .and_then(Value::as_bool)
+            .unwrap_or(false);
+        let text = message_text(message);
+        if !matches!(role, "user" | "assistant") || hidden || text.is_empty() {
+            continue;
+        }
+        at = message.get("create_time").and_then(epoch_to_utc).or(at);
+        let body = if role == "user" {
+            json!({"role": "user", "content": text})
+        } else {
+            let mut body =
+                json!({"role": "assistant", "content": [{"type": "text", "text": text}]});
+            if let Some(model) = message.pointer("/metadata/model_slug") {
+                body["model"] = model.clone();
+            }
+            body
+        };
+        records.push(json!({
+            "type": role,
+            "uuid": node_id,
+            "parentUuid": parent,
+            "sessionId": id,
+            "timestamp": at.map(|t| t.to_rfc3339_opts(SecondsFormat::Millis, true)),
+            "message": body,
+        }));
+        parent = Some(node_id);
+    }
+    // A conversation with nothing but system or empty messages.
+    parent?;
+    Some(ImportedSession {
+        id,
+        title,
+        updated_at,
+        records,
+    })
+}
+
+/// Node ids from the root to the message the conversation was left on, so
+/// edited prompts and regenerated answers keep only the version last shown.
+/// Without a `current_node`, follow the newest child from the root.
+fn active_branch<'a>(mapping: &'a Map<String, Value>, current: Option<&'a str>) -> Vec<&'a str> {
+    let parent_of = |id: &str| {
+        mapping
+            .get(id)
+            .and_then(|n| n.get("parent"))
+            .and_then(Value::as_str)
+    };
+    let mut branch = Vec::new();
+    let mut seen = HashSet::new();
+    match current.filter(|id| mapping.contains_key(*id)) {
+        Some(leaf) => {
+            let mut node = Some(leaf);
+            while let Some(id) = node.filter(|id| seen.insert(*id)) {
+                branch.push(id);
+                node = parent_of(id);
+            }
+            branch.reverse();
+        }
+        None => {
+            let mut node = mapping
+                .keys()
+                .map(String::as_str)
+                .find(|id| parent_of(id).is_none());
+            while let Some(id) = node.filter(|id| seen.insert(*id)) {
+                branch.push(id);
+                node = mapping
+                    .get(id)
+                    .and_then(|n| n.get("children"))
+                    .and_then(Value::as_array)
+                    .and_then(|children| children.last())
+                    .and_then(Value::as_str);
+            }
+        }
+    }
+    branch
+}
+
+/// The readable text of a message. Code the assistant ran becomes a fenced
+/// block; images, browsing results, and other attachments are dropped.
+fn message_text(message: &Value) -> String {
+    let Some(content) = message.get("content") else {
+        return String::new();
+    };
+    let text = match content.get("content_type").and_then(Value::as_str) {
+        Some("text" | "multimodal_text") => content
+            .get("parts")
+            .and_then(Value::as_array)
+            .map(|parts| {
+                parts
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .filter(|part| !part.trim().is_empty())
+                    .collect::<Vec<_>>()
+                    .join("\n\n")
+            })
+            .unwrap_or_default(),
+        Some("code") => {
+            let code = content.get("text").and_then(Value::as_str).unwrap_or("");
+            let lang = content
+                .get("language")
+                .and_then(Value::as_str)
+                .filter(|lang| *lang != "unknown")
+                .unwrap_or("");
+            format!("```{lang}\n{}\n```", code.trim_end())
+        }
+        _ => String::new(),
+    };
+    text.trim().to_string()
+}
+
+fn epoch_to_utc(value: &Value) -> Option<DateTime<Utc>> {
+    let secs = value.as_f64()?;
+    Utc.timestamp_millis_opt((secs * 1000.0).round() as i64)
+        .single()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::parse_session_reader;
+    use crate::testutil::unique_temp_path;
+    use std::io::Write;
+
+    fn conversation() -> Value {
+        let node = |id: &str, parent: Option<&str>, role: &str, text: &str, at: f64| {
+            json!({
+                "id": id,
+                "parent": parent,
+                "message": {
+                    "author": {"role": role},
+                    "create_time": at,
+                    "content": {"content_type": "text", "parts": [text]},
+                    "metadata": {"model_slug": "gpt-4o"},
+                },
+            })
+        };
+        json!({
+            "conversation_id": "c0ffee00-0000-4000-8000-000000000001",
+            "title": "Rust lifetimes",
+            "create_time": 1_767_225_600.0,
+            "update_time": 1_767_225_660.5,
+            "current_node": "a2",
+            "mapping": {
+                "root": {"id": "root", "parent": null, "message": null},
+                "sys": node("sys", Some("root"), "system", "You are ChatGPT", 1_767_225_600.0),
+                "u1": node("u1", Some("sys"), "user", "What is 'a?", 1_767_225_601.0),
+                "a1": node("a1", Some("u1"), "assistant", "An earlier draft", 1_767_225_602.0),
+                "a2": node("a2", Some("u1"), "assistant", "A lifetime name.", 1_767_225_603.0),
+            },
+        })
+    }
+
+    #[test]
+    fn converts_the_active_branch_into_parseable_records() {
+        let import =
+            convert_conversations(&json!([conversation(), {"title": "no id"}])).expect("convert");
+        assert_eq!(import.skipped, 1);
+        let session = &import.sessions[0];
+        assert_eq!(session.title.as_deref(), Some("Rust lifetimes"));
+
+        let parsed =
+            parse_session_reader(session.to_jsonl().as_bytes(), false).expect("parse jsonl");
+        let dialog = parsed
+            .events
+            .iter()
+            .map(|e| (e.role.as_str(), e.content.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            dialog,
+            vec![("user", "What is 'a?"), ("assistant", "A lifetime name.")]
+        );
+        assert_eq!(
+            parsed.events[1].timestamp.as_deref(),
+            Some("2026-01-01T00:00:03.000Z")
+        );
+        assert_eq!(parsed.events[1].parent_uuid.as_deref(), Some("u1"));
+    }
+
+    #[test]
+    fn reads_conversations_from_the_export_zip() {
+        let dir = unique_temp_path("cc-convo-chatgpt");
+        std::fs::create_dir_all(&dir).expect("create temp dir");
+        let path = dir.join("export.zip");
+        let mut writer = zip::ZipWriter::new(File::create(&path).expect("create zip"));
+        let options = zip::write::SimpleFileOptions::default()
+            .compression_method(zip::CompressionMethod::Stored);
+        writer.start_file("chat.html", options).expect("start file");
+        writer
+            .start_file("conversations.json", options)
+            .expect("start file");
+        writer
+            .write_all(json!([conversation()]).to_string().as_bytes())
+            .expect("write entry");
+        writer.finish().expect("finish zip");
+
+        let import = read_chatgpt_export(&path).expect("read export");
+        assert_eq!(import.sessions.len(), 1);
+        assert_eq!(
+            import.sessions[0].id,
+            "c0ffee00-0000-4000-8000-000000000001"
+        );
+        std::fs::remove_dir_all(&dir).expect("cleanup");
+    }
+}
@@ -0,0 +1,473 @@
+//! Converting Codex CLI rollout logs (`~/.codex/sessions/YYYY/MM/DD/
+//! rollout-*.jsonl`) into transcripts shaped like Claude Code's, so one
+//! archive holds the sessions of both assistants.
+//!
+//! Rollouts written since mid-2025 wrap every line in
+//! `{"timestamp", "type", "payload"}`; older ones start with a bare session
+//! header and log response items unwrapped. Both are read.
+
+use crate::chatgpt::ImportedSession;
+use crate::parse::jsonl_records;
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use serde_json::{json, Value};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use walkdir::WalkDir;
+
+/// Where Codex CLI keeps its rollouts.
+pub const CODEX_SESSIONS_DIR: &str = "~/.codex/sessions";
+
+/// User messages Codex adds for the model rather than typed by the user.
+const INJECTED_PREFIXES: [&str; 3] = [
+    "<environment_context>",
+    "<user_instructions>",
+    "# AGENTS.md instructions",
+];
+
+#[derive(Debug, Clone, Default)]
+pub struct CodexImport {
+    pub sessions: Vec<ImportedSession>,
+    /// Rollouts without a session id or any user or assistant message.
+    pub skipped: usize,
+}
+
+/// Read every `rollout-*.jsonl` under `path`, or `path` itself if it is a file.
+pub fn read_codex_sessions(path: &Path) -> Result<CodexImport> {
+    if !path.exists() {
+        bail!("{} does not exist; is Codex CLI installed?", path.display());
+    }
+    let mut files = WalkDir::new(path)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            let name = entry.file_name().to_string_lossy();
+            entry.file_type().is_file()
+                && (entry.path() == path
+                    || name.starts_with("rollout-") && name.ends_with(".jsonl"))
+        })
+        .map(|entry| entry.into_path())
+        .collect::<Vec<_>>();
+    files.sort();
+    let mut import = CodexImport::default();
+    for file in files {
+        let reader = File::open(&file)
+            .map(BufReader::new)
+            .with_context(|| format!("Failed to open {}", file.display()))?;
+        let fallback_id = file
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .and_then(|s| s.get(s.len().saturating_sub(36)..))
+            .filter(|id| id.len() == 36 && id.chars().filter(|c| *c == '-').count() == 4);
+        let converted = convert_rollout(reader, fallback_id)
+            .with_context(|| format!("Failed to read {}", file.display()))?;
+        match converted {
+            Some(session) => import.sessions.push(session),
+            None => import.skipped += 1,
+        }
+    }
+    Ok(import)
+}
+
+/// The conversation being rebuilt from one rollout.
+#[derive(Default)]
+struct Rollout {
+    id: Option<String>,
+    cwd: Option<String>,
+    branch: Option<String>,
+    model: Option<String>,
+    /// Of the line being read, else the last one seen.
+    timestamp: Option<String>,
+    records: Vec<Value>,
+    /// Counts model responses, which become message ids so the records of
+    /// one response read as one reply and its usage counts once.
+    responses: usize,
+    /// Indices of the assistant records of the response in progress.
+    in_response: Vec<usize>,
+    saw_dialog: bool,
+}
+
+/// Convert one rollout; `None` if it holds no user or assistant message.
+/// `fallback_id` names the session when the rollout's header doesn't.
+pub fn convert_rollout(
+    reader: impl BufRead,
+    fallback_id: Option<&str>,
+) -> Result<Option<ImportedSession>> {
+    let mut rollout = Rollout::default();
+    for line in jsonl_records(reader) {
+        let Ok(value) = line? else {
+            continue;
+        };
+        match (
+            value.get("type").and_then(Value::as_str),
+            value.get("payload"),
+        ) {
+            (Some(kind), Some(payload)) => {
+                if let Some(ts) = value.get("timestamp").and_then(Value::as_str) {
+                    rollout.timestamp = Some(ts.to_string());
+                }
+                match kind {
+                    "session_meta" => rollout.meta(payload),
+                    "turn_context" => {
+                        if let Some(model) = payload.get("model").and_then(Value::as_str) {
+                            rollout.model = Some(model.to_string());
+                        }
+                    }
+                    "response_item" => rollout.item(payload),
+                    "event_msg" => rollout.event(payload),
+                    _ => {}
+                }
+            }
+            (Some(_), None) => rollout.item(&value),
+            (None, _) if value.get("id").is_some() => {
+                if let Some(ts) = value.get("timestamp").and_then(Value::as_str) {
+                    rollout.timestamp = Some(ts.to_string());
+                }
+                rollout.meta(&value);
+            }
+            _ => {}
+        }
+    }
+    let Some(id) = rollout.id.clone().or(fallback_id.map(str::to_string)) else {
+        return Ok(None);
+    };
+    if !rollout.saw_dialog {
+        return Ok(None);
+    }
+    let updated_at = rollout
+        .records
+        .iter()
+        .rev()
+        .find_map(|r| r.get("timestamp")?.as_str())
+        .and_then(|ts| DateTime::parse_from_rfc3339(ts).ok())
+        .map(|ts| ts.with_timezone(&Utc));
+    let mut parent: Option<String> = None;
+    for (n, record) in rollout.records.iter_mut().enumerate() {
+        let uuid = format!("{id}-{n}");
+        record["uuid"] = json!(uuid);
+        record["parentUuid"] = json!(parent);
+        record["sessionId"] = json!(id);
+        if let Some(cwd) = &rollout.cwd {
+            record["cwd"] = json!(cwd);
+        }
+        if let Some(branch) = &rollout.branch {
+            record["gitBranch"] = json!(branch);
+        }
+        parent = Some(uuid);
+    }
+    Ok(Some(ImportedSession {
+        id,
+        title: None,
+        updated_at,
+        records: rollout.records,
+    }))
+}
+
+impl Rollout {
+    fn meta(&mut self, meta: &Value) {
+        let field = |key: &str| meta.get(key).and_then(Value::as_str).map(str::to_string);
+        self.id = self.id.take().or(field("id"));
+        self.cwd = field("cwd").or(self.cwd.take());
+        if let Some(branch) = meta.pointer("/git/branch").and_then(Value::as_str) {
+            self.branch = Some(branch.to_string());
+        }
+    }
+
+    fn item(&mut self, item: &Value) {
+        let field = |key: &str| item.get(key).and_then(Value::as_str);
+        match field("type").unwrap_or_default() {
+            "message" => {
+                let text = message_text(item);
+                if text.is_empty() {
+                    return;
+                }
+                match field("role") {
+                    Some("user") => {
+                        if INJECTED_PREFIXES.iter().any(|p| text.starts_with(p)) {
+                            return;
+                        }
+                        self.user(json!(text));
+                        self.saw_dialog = true;
+                    }
+                    Some("assistant") => {
+                        self.assistant(json!({"type": "text", "text": text}));
+                        self.saw_dialog = true;
+                    }
+                    _ => {}
+                }
+            }
+            "reasoning" => {
+                let summary = item
+                    .get("summary")
+                    .and_then(Value::as_array)
+                    .map(|parts| {
+                        parts
+                            .iter()
+                            .filter_map(|p| p.get("text").and_then(Value::as_str))
+                            .collect::<Vec<_>>()
+                            .join("\n\n")
+                    })
+                    .unwrap_or_default();
+                if !summary.trim().is_empty() {
+                    self.assistant(json!({"type": "thinking", "thinking": summary.trim()}));
+                }
+            }
+            "function_call" => {
+                let arguments = field("arguments").unwrap_or("{}");
+                let input = serde_json::from_str::<Value>(arguments)
+                    .unwrap_or_else(|_| json!({ "arguments": arguments }));
+                self.tool_use(field("call_id"), field("name").unwrap_or("function"), input);
+            }
+            "custom_tool_call" => {
+                let input = json!({ "input": field("input").unwrap_or_default() });
+                self.tool_use(field("call_id"), field("name").unwrap_or("tool"), input);
+            }
+            "local_shell_call" => {
+                let input = item.get("action").cloned().unwrap_or(Value::Null);
+                self.tool_use(field("call_id"), "local_shell", input);
+            }
+            "function_call_output" | "custom_tool_call_output" => {
+                let output = match item.get("output") {
+                    Some(Value::String(raw)) => serde_json::from_str::<Value>(raw)
+                        .ok()
+                        .and_then(|v| v.get("output")?.as_str().map(str::to_string))
+                        .unwrap_or_else(|| raw.clone()),
+                    Some(other) => other
+                        .get("content")
+                        .and_then(Value::as_str)
+                        .map(str::to_string)
+                        .unwrap_or_else(|| other.to_string()),
+                    None => String::new(),
+                };
+                self.user(json!([{
+                    "type": "tool_result",
+                    "tool_use_id": field("call_id"),
+                    "content": output,
+                }]));
+            }
+            _ => {}
+        }
+    }
+
+    /// A `token_count` event closes the response it counts.
+    fn event(&mut self, event: &Value) {
+        if event.get("type").and_then(Value::as_str) != Some("token_count") {
+            return;
+        }
+        let Some(last) = event.pointer("/info/last_token_usage") else {
+            return;
+        };
+        let count = |key: &str| last.get(key).and_then(Value::as_u64).unwrap_or(0);
+        let cached = count("cached_input_tokens");
+        // Codex counts cached tokens inside `input_tokens`; Claude doesn't.
+        let usage = json!({
+            "input_tokens": count("input_tokens").saturating_sub(cached),
+            "cache_read_input_tokens": cached,
+            "output_tokens": count("output_tokens"),
+        });
+        for i in self.in_response.drain(..) {
+            self.records[i]["message"]["usage"] = usage.clone();
+        }
+        self.responses += 1;
+    }
+
+    fn user(&mut self, content: Value) {
+        self.close_response();
+        self.records.push(json!({
+            "type": "user",
+            "timestamp": self.timestamp,
+            "message": {"role": "user", "content": content},
+        }));
+    }
+
+    fn assistant(&mut self, block: Value) {
+        let mut message = json!({
+            "id": format!("codex-response-{}", self.responses),
+            "role": "assistant",
+            "content": [block],
+        });
+        if let Some(model) = &self.model {
+            message["model"] = json!(model);
+        }
+        self.in_response.push(self.records.len());
+        self.records.push(json!({
+            "type": "assistant",
+            "timestamp": self.timestamp,
+            "message": message,
+        }));
+    }
+
+    fn tool_use(&mut self, call_id: Option<&str>, name: &str, input: Value) {
+        self.assistant(json!({"type": "tool_use", "id": call_id, "name": name, "input": input}));
+    }
+
+    /// Tool output or a new prompt starts the next response, even when the
+    /// rollout logged no usage for the last one.
+    fn close_response(&mut self) {
+        if !self.in_response.is_empty() {
+            self.in_response.clear();
+            self.responses += 1;
+        }
+    }
+}
+
+/// The text parts of a `message` item; images and other inputs are dropped.
+fn message_text(item: &Value) -> String {
+    let text = match item.get("content") {
+        Some(Value::String(text)) => text.clone(),
+        Some(Value::Array(parts)) => parts
+            .iter()
+            .filter(|p| {
+                matches!(
+                    p.get("type").and_then(Value::as_str),
+                    Some("input_text" | "output_text" | "text")
+                )
+            })
+            .filter_map(|p| p.get("text").and_then(Value::as_str))
+            .filter(|t| !t.trim().is_empty())
+            .collect::<Vec<_>>()
+            .join("\n\n"),
+        _ => String::new(),
+    };
+    text.trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::parse_session_reader;
+    use crate::usage::TokenUsage;
+
+    fn line(ts: &str, kind: &str, payload: Value) -> String {
+        json!({"timestamp": ts, "type": kind, "payload": payload}).to_string()
+    }
+
+    #[test]
+    fn rollouts_become_parseable_sessions_with_tools_and_usage() {
+        let rollout = [
+            line(
+                "2026-01-08T10:00:00.000Z",
+                "session_meta",
+                json!({"id": "0199aaaa-0000-7000-8000-000000000001", "cwd": "/home/user/gamma",
+                       "git": {"branch": "main"}}),
+            ),
+            line(
+                "2026-01-08T10:00:00.000Z",
+                "response_item",
+                json!({"type": "message", "role": "user", "content": [
+                    {"type": "input_text", "text": "<environment_context>\n<cwd>/home/user/gamma</cwd>\n</environment_context>"}]}),
+            ),
+            line(
+                "2026-01-08T10:00:01.000Z",
+                "turn_context",
+                json!({"cwd": "/home/user/gamma", "model": "gpt-5-codex"}),
+            ),
+            line(
+                "2026-01-08T10:00:01.000Z",
+                "response_item",
+                json!({"type": "message", "role": "user", "content": [
+                    {"type": "input_text", "text": "List the files"}]}),
+            ),
+            line(
+                "2026-01-08T10:00:01.000Z",
+                "event_msg",
+                json!({"type": "user_message", "message": "List the files"}),
+            ),
+            line(
+                "2026-01-08T10:00:02.000Z",
+                "response_item",
+                json!({"type": "reasoning", "summary": [{"type": "summary_text", "text": "Run ls."}]}),
+            ),
+            line(
+                "2026-01-08T10:00:02.000Z",
+                "response_item",
+                json!({"type": "function_call", "name": "shell", "call_id": "call_1",
+                       "arguments": "{\"command\":[\"bash\",\"-lc\",\"ls\"]}"}),
+            ),
+            line(
+                "2026-01-08T10:00:02.000Z",
+                "event_msg",
+                json!({"type": "token_count", "info": {"last_token_usage": {
+                    "input_tokens": 1200, "cached_input_tokens": 1000, "output_tokens": 30}}}),
+            ),
+            line(
+                "2026-01-08T10:00:03.000Z",
+                "response_item",
+                json!({"type": "function_call_output", "call_id": "call_1",
+                       "output": "{\"output\":\"Cargo.toml\\nsrc\\n\",\"metadata\":{\"exit_code\":0}}"}),
+            ),
+            line(
+                "2026-01-08T10:00:04.000Z",
+                "response_item",
+                json!({"type": "message", "role": "assistant", "content": [
+                    {"type": "output_text", "text": "A Cargo project."}]}),
+            ),
+            "not json".to_string(),
+        ]
+        .join("\n");
+
+        let session = convert_rollout(rollout.as_bytes(), None)
+            .expect("read")
+            .expect("session");
+        assert_eq!(session.id, "0199aaaa-0000-7000-8000-000000000001");
+        assert_eq!(
+            session.updated_at.map(|t| t.to_rfc3339()),
+            Some("2026-01-08T10:00:04+00:00".to_string())
+        );
+        let usage = session
+            .records
+            .iter()
+            .filter_map(TokenUsage::from_record)
+            .collect::<Vec<_>>();
+        assert_eq!(usage.len(), 2);
+        assert_eq!(
+            (usage[0].input_tokens, usage[0].cache_read_input_tokens),
+            (200, 1000)
+        );
+        assert_eq!(session.records[0]["cwd"], "/home/user/gamma");
+        assert_eq!(session.records[1]["message"]["model"], "gpt-5-codex");
+
+        let parsed =
+            parse_session_reader(session.to_jsonl().as_bytes(), false).expect("parse jsonl");
+        let dialog = parsed
+            .events
+            .iter()
+            .map(|e| (e.role.as_str(), e.content.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            dialog,
+            vec![
+                ("user", "List the files"),
+                ("assistant", "A Cargo project.")
+            ]
+        );
+        let detailed = parse_session_reader(session.to_jsonl().as_bytes(), true).expect("parse");
+        assert!(detailed.events.iter().any(|e| e
+            .content
+            .contains("[tool_result] call_1\n\"Cargo.toml\\nsrc")));
+    }
+
+    #[test]
+    fn legacy_rollouts_and_empty_ones() {
+        let legacy = [
+            json!({"id": "legacy-1", "timestamp": "2025-05-01T09:00:00Z", "instructions": null}),
+            json!({"record_type": "state"}),
+            json!({"type": "message", "role": "user", "content": [{"type": "input_text", "text": "hi"}]}),
+            json!({"type": "message", "role": "assistant", "content": [{"type": "output_text", "text": "hello"}]}),
+        ]
+        .map(|v| v.to_string())
+        .join("\n");
+        let session = convert_rollout(legacy.as_bytes(), None)
+            .expect("read")
+            .expect("session");
+        assert_eq!(session.id, "legacy-1");
+        assert_eq!(session.records.len(), 2);
+        assert_eq!(session.records[1]["parentUuid"], "legacy-1-0");
+
+        let empty = line("2026-01-08T10:00:00Z", "session_meta", json!({"id": "x"}));
+        assert!(convert_rollout(empty.as_bytes(), None)
+            .expect("read")
+            .is_none());
+    }
+}
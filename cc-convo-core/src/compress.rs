@@ -0,0 +1,169 @@
+//! Transcripts compressed with gzip (`.jsonl.gz`) or zstd (`.jsonl.zst`).
+//! Everything that reads a session opens it through [`open_transcript`], so
+//! an archived transcript reads like a plain `.jsonl` one; [`compress_transcript`]
+//! does the archiving.
+
+use anyhow::{anyhow, bail, Context, Result};
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::{self, BufReader, Read};
+use std::path::{Path, PathBuf};
+
+/// How a transcript file is compressed, going by its extension.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum Compression {
+    /// `.gz`, readable by any gzip tool.
+    Gzip,
+    /// `.zst`: smaller and faster than gzip.
+    Zstd,
+}
+
+impl Compression {
+    /// The compression of `path`, or `None` for a plain file.
+    pub fn of(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "gz" => Some(Self::Gzip),
+            "zst" => Some(Self::Zstd),
+            _ => None,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Gzip => "gz",
+            Self::Zstd => "zst",
+        }
+    }
+}
+
+/// Whether `path` names a transcript: `.jsonl`, plain or compressed.
+pub fn is_transcript(path: &Path) -> bool {
+    transcript_stem(path).is_some()
+}
+
+/// The file name of the transcript at `path` without `.jsonl` and any
+/// compression extension: the session id.
+pub fn transcript_stem(path: &Path) -> Option<&str> {
+    let name = path.file_name()?.to_str()?;
+    let name = match Compression::of(path) {
+        Some(compression) => name
+            .strip_suffix(compression.extension())?
+            .strip_suffix('.')?,
+        None => name,
+    };
+    name.strip_suffix(".jsonl").filter(|stem| !stem.is_empty())
+}
+
+/// The decompressed bytes of the transcript at `path`.
+pub fn open_transcript(path: &Path) -> io::Result<Box<dyn Read + Send>> {
+    let file = File::open(path)?;
+    Ok(match Compression::of(path) {
+        None => Box::new(file),
+        // Concatenated members, as `cat a.gz b.gz` makes, read as one stream.
+        Some(Compression::Gzip) => Box::new(flate2::read::MultiGzDecoder::new(file)),
+        Some(Compression::Zstd) => Box::new(zstd::stream::read::Decoder::new(file)?),
+    })
+}
+
+/// Replace the plain transcript at `path` with a compressed copy beside it
+/// that keeps its modification time, so listings and time windows see the
+/// session as before. Returns the new path.
+pub fn compress_transcript(path: &Path, compression: Compression) -> Result<PathBuf> {
+    if Compression::of(path).is_some() {
+        bail!("{} is already compressed", path.display());
+    }
+    let mut target = OsString::from(path.as_os_str());
+    target.push(".");
+    target.push(compression.extension());
+    let target = PathBuf::from(target);
+    if target.exists() {
+        bail!("{} already exists", target.display());
+    }
+    let modified = fs::metadata(path)
+        .and_then(|m| m.modified())
+        .with_context(|| format!("Failed to read the mtime of {}", path.display()))?;
+    let name = target
+        .file_name()
+        .and_then(|n| n.to_str())
+        .ok_or_else(|| anyhow!("Invalid transcript path {}", path.display()))?;
+    let tmp = target.with_file_name(format!(".{name}.{}.tmp", std::process::id()));
+    let written = write_compressed(path, &tmp, compression).and_then(|file| {
+        file.set_modified(modified)?;
+        fs::rename(&tmp, &target)
+    });
+    if let Err(err) = written {
+        let _ = fs::remove_file(&tmp);
+        return Err(anyhow!(err).context(format!("Failed to write {}", target.display())));
+    }
+    fs::remove_file(path).with_context(|| format!("Failed to remove {}", path.display()))?;
+    Ok(target)
+}
+
+/// Write `source` compressed to `dest`, returning the synced file.
+fn write_compressed(source: &Path, dest: &Path, compression: Compression) -> io::Result<File> {
+    let mut input = BufReader::new(File::open(source)?);
+    let output = File::create(dest)?;
+    let file = match compression {
+        Compression::Gzip => {
+            let mut encoder = flate2::write::GzEncoder::new(output, flate2::Compression::best());
+            io::copy(&mut input, &mut encoder)?;
+            encoder.finish()?
+        }
+        Compression::Zstd => {
+            let mut encoder = zstd::stream::write::Encoder::new(output, 19)?;
+            io::copy(&mut input, &mut encoder)?;
+            encoder.finish()?
+        }
+    };
+    file.sync_all()?;
+    Ok(file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::{unique_temp_path, write_jsonl};
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn names_of_compressed_transcripts() {
+        assert_eq!(transcript_stem(Path::new("p/s-1.jsonl")), Some("s-1"));
+        assert_eq!(transcript_stem(Path::new("p/s-1.jsonl.gz")), Some("s-1"));
+        assert_eq!(transcript_stem(Path::new("p/s-1.jsonl.zst")), Some("s-1"));
+        assert_eq!(transcript_stem(Path::new("p/s-1.json.gz")), None);
+        assert_eq!(transcript_stem(Path::new("p/.jsonl")), None);
+        assert!(!is_transcript(Path::new("p/s-1.gz")));
+    }
+
+    #[test]
+    fn compressing_keeps_content_and_mtime() {
+        let dir = unique_temp_path("cc-convo-test-compress");
+        fs::create_dir_all(&dir).expect("create temp dir");
+        for compression in [Compression::Gzip, Compression::Zstd] {
+            let path = dir.join("s.jsonl");
+            let lines = [r#"{"type":"user"}"#, r#"{"type":"assistant"}"#];
+            write_jsonl(&path, &lines);
+            let mtime = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+            File::options()
+                .write(true)
+                .open(&path)
+                .and_then(|f| f.set_modified(mtime))
+                .expect("set mtime");
+
+            let packed = compress_transcript(&path, compression).expect("compress");
+            assert!(!path.exists());
+            assert_eq!(Compression::of(&packed), Some(compression));
+            let modified = fs::metadata(&packed).and_then(|m| m.modified());
+            assert_eq!(modified.expect("mtime"), mtime);
+            let mut text = String::new();
+            open_transcript(&packed)
+                .and_then(|mut r| r.read_to_string(&mut text))
+                .expect("read back");
+            assert_eq!(text, lines.join("\n"));
+            assert!(compress_transcript(&packed, compression).is_err());
+            fs::remove_file(packed).expect("clean up");
+        }
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,302 @@
+//! Fine-tuning and eval datasets: one JSON line per session holding the
+//! conversation as alternating user and assistant messages.
+
+use crate::export::{ExportDocument, ExportFormat};
+use crate::fs::write_atomic;
+use crate::markup::{split_content_blocks, ContentBlock};
+use anyhow::{bail, Result};
+use chrono::{SecondsFormat, Utc};
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, PartialEq)]
+enum Block {
+    Text(String),
+    Thinking(String),
+    ToolUse {
+        id: String,
+        name: String,
+        input: Value,
+    },
+    ToolResult {
+        id: String,
+        output: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Turn {
+    assistant: bool,
+    blocks: Vec<Block>,
+}
+
+/// The session's dialog as turns that alternate, start with the user, and
+/// end with the assistant; `None` if it has no answered prompt. Tool calls
+/// carry the ids of the results that follow them, since the flattened
+/// content only keeps ids on the results.
+fn turns(doc: &ExportDocument) -> Option<Vec<Turn>> {
+    let mut turns: Vec<Turn> = Vec::new();
+    for event in &doc.events {
+        let assistant = match event.role.as_str() {
+            "user" => false,
+            "assistant" => true,
+            _ => continue,
+        };
+        let blocks = split_content_blocks(&event.content)
+            .into_iter()
+            .filter_map(|block| match block {
+                ContentBlock::Text(text) => {
+                    (!text.trim().is_empty()).then(|| Block::Text(text.trim().to_string()))
+                }
+                ContentBlock::Thinking(text) => Some(Block::Thinking(text.to_string())),
+                ContentBlock::ToolUse { name, input } => Some(Block::ToolUse {
+                    id: String::new(),
+                    name: name.to_string(),
+                    input: serde_json::from_str(input).unwrap_or_else(|_| json!({})),
+                }),
+                // Text results are flattened as JSON strings; unquote them.
+                ContentBlock::ToolResult { id, output } => Some(Block::ToolResult {
+                    id: id.to_string(),
+                    output: serde_json::from_str::<String>(output)
+                        .unwrap_or_else(|_| output.to_string()),
+                }),
+            })
+            .collect::<Vec<_>>();
+        if blocks.is_empty() {
+            continue;
+        }
+        match turns.last_mut() {
+            Some(last) if last.assistant == assistant => last.blocks.extend(blocks),
+            Some(_) => turns.push(Turn { assistant, blocks }),
+            // Replies before the first prompt, e.g. in a resumed session.
+            None if assistant => {}
+            None => turns.push(Turn { assistant, blocks }),
+        }
+    }
+    if turns.last().is_some_and(|t| !t.assistant) {
+        turns.pop();
+    }
+
+    let mut unmatched = 0;
+    for i in 0..turns.len() {
+        let result_ids = turns
+            .get(i + 1)
+            .map(|next| {
+                next.blocks
+                    .iter()
+                    .filter_map(|b| match b {
+                        Block::ToolResult { id, .. } => Some(id.clone()),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        let mut result_ids = result_ids.into_iter();
+        for block in &mut turns[i].blocks {
+            if let Block::ToolUse { id, .. } = block {
+                *id = result_ids.next().unwrap_or_else(|| {
+                    unmatched += 1;
+                    format!("toolu_unmatched_{unmatched}")
+                });
+            }
+        }
+    }
+    (!turns.is_empty()).then_some(turns)
+}
+
+/// `{"messages": [...]}` for OpenAI chat fine-tuning. Tool calls become
+/// `tool_calls` with `role: "tool"` results, and thinking is kept inline in
+/// `<thinking>` tags since the format has no place for it.
+pub fn openai_example(doc: &ExportDocument) -> Option<Value> {
+    let mut messages = Vec::new();
+    for turn in turns(doc)? {
+        let mut text = Vec::new();
+        let mut tool_calls = Vec::new();
+        for block in turn.blocks {
+            match block {
+                Block::Text(t) => text.push(t),
+                Block::Thinking(t) => text.push(format!("<thinking>\n{t}\n</thinking>")),
+                Block::ToolUse { id, name, input } => tool_calls.push(json!({
+                    "id": id,
+                    "type": "function",
+                    "function": {"name": name, "arguments": input.to_string()},
+                })),
+                Block::ToolResult { id, output } => messages.push(json!({
+                    "role": "tool",
+                    "tool_call_id": id,
+                    "content": output,
+                })),
+            }
+        }
+        let content = (!text.is_empty()).then(|| text.join("\n\n"));
+        if turn.assistant {
+            let mut message = json!({"role": "assistant", "content": content});
+            if !tool_calls.is_empty() {
+                message["tool_calls"] = Value::Array(tool_calls);
+            }
+            messages.push(message);
+        } else if let Some(content) = content {
+            messages.push(json!({"role": "user", "content": content}));
+        }
+    }
+    Some(json!({ "messages": messages }))
+}
+
+/// `{"messages": [...]}` in the Anthropic Messages shape, with content
+/// blocks. Thinking blocks have no signature, so strip them (`--drop-thinking`)
+/// before sending examples back to the API.
+pub fn anthropic_example(doc: &ExportDocument) -> Option<Value> {
+    let messages = turns(doc)?
+        .into_iter()
+        .map(|turn| {
+            let content = turn
+                .blocks
+                .into_iter()
+                .map(|block| match block {
+                    Block::Text(text) => json!({"type": "text", "text": text}),
+                    Block::Thinking(thinking) => json!({"type": "thinking", "thinking": thinking}),
+                    Block::ToolUse { id, name, input } => {
+                        json!({"type": "tool_use", "id": id, "name": name, "input": input})
+                    }
+                    Block::ToolResult { id, output } => {
+                        json!({"type": "tool_result", "tool_use_id": id, "content": output})
+                    }
+                })
+                .collect::<Vec<_>>();
+            let role = if turn.assistant { "assistant" } else { "user" };
+            json!({"role": role, "content": content})
+        })
+        .collect::<Vec<_>>();
+    Some(json!({ "messages": messages }))
+}
+
+/// One example per line; sessions without an answered prompt are left out.
+pub fn render_dataset(docs: &[ExportDocument], format: ExportFormat) -> Result<String> {
+    let example = match format {
+        ExportFormat::OpenaiJsonl => openai_example,
+        ExportFormat::AnthropicMessages => anthropic_example,
+        other => bail!("{} is not a dataset format", other.extension()),
+    };
+    Ok(docs
+        .iter()
+        .filter_map(example)
+        .map(|line| format!("{line}\n"))
+        .collect())
+}
+
+/// Whether a session goes to the validation file. Decided by a hash of its
+/// id, so re-running an export keeps each session on the same side.
+pub fn is_validation(session_id: &str, fraction: f64) -> bool {
+    // FNV-1a, which unlike std's hasher is stable across releases.
+    let hash = session_id.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |h, b| {
+        (h ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3)
+    });
+    ((hash % 10_000) as f64) < fraction * 10_000.0
+}
+
+/// Write `docs` as a train file and a validation file holding about
+/// `fraction` of the sessions.
+pub fn write_dataset_split(
+    output_dir: &Path,
+    docs: &[ExportDocument],
+    format: ExportFormat,
+    fraction: f64,
+) -> Result<[PathBuf; 2]> {
+    let (validation, train): (Vec<_>, Vec<_>) = docs
+        .iter()
+        .cloned()
+        .partition(|doc| is_validation(&doc.session_id, fraction));
+    let now = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true);
+    let date = now.split('T').next().unwrap_or("unknown-date");
+    let ext = format.extension();
+    let train_path = output_dir.join(format!("cc-convo-dataset-{date}-train.{ext}"));
+    let validation_path = output_dir.join(format!("cc-convo-dataset-{date}-validation.{ext}"));
+    write_atomic(&train_path, render_dataset(&train, format)?)?;
+    write_atomic(&validation_path, render_dataset(&validation, format)?)?;
+    Ok([train_path, validation_path])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::NormalizedEvent;
+
+    fn doc(events: &[(&str, &str)]) -> ExportDocument {
+        let events = events
+            .iter()
+            .map(|(role, content)| NormalizedEvent {
+                role: role.to_string(),
+                source_type: role.to_string(),
+                timestamp: None,
+                content: content.to_string(),
+                uuid: None,
+                parent_uuid: None,
+                source: None,
+                model: None,
+            })
+            .collect::<Vec<_>>();
+        ExportDocument {
+            session_id: "s".to_string(),
+            session_short: "s".to_string(),
+            project: "p".to_string(),
+            source_path: PathBuf::from("s.jsonl"),
+            modified_iso: String::new(),
+            event_count: events.len(),
+            events,
+            tree: None,
+            title: None,
+        }
+    }
+
+    #[test]
+    fn examples_alternate_and_pair_tool_calls_with_results() {
+        let doc = doc(&[
+            ("assistant", "Resumed from an earlier session"),
+            ("user", "List the files"),
+            ("system", "hook ran"),
+            (
+                "assistant",
+                "[thinking]\nUse ls.\n[tool_use] Bash\n{\n  \"command\": \"ls\"\n}",
+            ),
+            ("user", "[tool_result] toolu_1\nCargo.toml"),
+            ("assistant", "Just `Cargo.toml`."),
+            ("user", "Thanks"),
+        ]);
+
+        let anthropic = anthropic_example(&doc).expect("example");
+        assert_eq!(
+            anthropic,
+            json!({"messages": [
+                {"role": "user", "content": [{"type": "text", "text": "List the files"}]},
+                {"role": "assistant", "content": [
+                    {"type": "thinking", "thinking": "Use ls."},
+                    {"type": "tool_use", "id": "toolu_1", "name": "Bash", "input": {"command": "ls"}},
+                ]},
+                {"role": "user", "content": [
+                    {"type": "tool_result", "tool_use_id": "toolu_1", "content": "Cargo.toml"},
+                ]},
+                {"role": "assistant", "content": [{"type": "text", "text": "Just `Cargo.toml`."}]},
+            ]})
+        );
+
+        let openai = openai_example(&doc).expect("example");
+        let roles = openai["messages"]
+            .as_array()
+            .expect("messages")
+            .iter()
+            .map(|m| m["role"].as_str().unwrap_or_default())
+            .collect::<Vec<_>>();
+        assert_eq!(roles, vec!["user", "assistant", "tool", "assistant"]);
+        assert_eq!(
+            openai["messages"][1]["tool_calls"][0]["function"]["arguments"],
+            "{\"command\":\"ls\"}"
+        );
+        assert_eq!(openai["messages"][2]["tool_call_id"], "toolu_1");
+
+        assert!(anthropic_example(&super::tests::doc(&[("user", "unanswered")])).is_none());
+        let split = (0..200)
+            .filter(|i| is_validation(&format!("session-{i}"), 0.2))
+            .count();
+        assert!((20..60).contains(&split), "{split}");
+    }
+}
@@ -0,0 +1,290 @@
+//! Factoring content that repeats across a bundle's sessions, like the same
+//! file pasted into several prompts or read by several tool calls, into one
+//! shared copy in an appendix that the events link to.
+
+use crate::export::{html_escape, ExportDocument};
+use crate::markup::{render_content_html, split_content_blocks, ContentBlock};
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Shorter pieces stay in place even when they repeat.
+pub const SHARED_BLOCK_MIN_BYTES: usize = 512;
+
+/// A piece of content that occurred more than once in a bundle.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SharedBlock {
+    /// 1-based, in order of first occurrence.
+    pub id: usize,
+    pub content: String,
+    pub occurrences: usize,
+    /// Tool input or output rather than markdown.
+    pub preformatted: bool,
+}
+
+impl SharedBlock {
+    pub fn anchor(&self) -> String {
+        format!("shared-{}", self.id)
+    }
+
+    /// What an event holds in place of the block.
+    fn reference(&self) -> String {
+        format!(
+            "[shared block {}](#{}) ({} lines)",
+            self.id,
+            self.anchor(),
+            self.content.lines().count()
+        )
+    }
+}
+
+/// The shared blocks of a bundle and how much factoring them out saved.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Deduped {
+    pub blocks: Vec<SharedBlock>,
+    pub saved_bytes: usize,
+}
+
+/// A stretch of an event's content that could be shared.
+struct Candidate {
+    start: usize,
+    end: usize,
+    preformatted: bool,
+}
+
+/// Replace every block of at least `min_bytes` that occurs more than once in
+/// `docs` with a link to its shared copy. Blocks are whole text, thinking,
+/// tool input, or tool output blocks, and code fences inside text; a block
+/// repeated as a whole is preferred over the fences inside it.
+pub fn dedupe_content(docs: &mut [ExportDocument], min_bytes: usize) -> Deduped {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for event in docs.iter().flat_map(|d| &d.events) {
+        for candidate in candidates(&event.content, min_bytes) {
+            let text = &event.content[candidate.start..candidate.end];
+            *counts.entry(text.to_string()).or_default() += 1;
+        }
+    }
+    let mut deduped = Deduped::default();
+    let mut ids: HashMap<String, usize> = HashMap::new();
+    let mut replaced = 0usize;
+    for event in docs.iter_mut().flat_map(|d| &mut d.events) {
+        let mut chosen: Vec<Candidate> = Vec::new();
+        for candidate in candidates(&event.content, min_bytes) {
+            let overlaps = chosen.last().is_some_and(|c| candidate.start < c.end);
+            if !overlaps && counts[&event.content[candidate.start..candidate.end]] > 1 {
+                chosen.push(candidate);
+            }
+        }
+        if chosen.is_empty() {
+            continue;
+        }
+        let mut content = String::new();
+        let mut at = 0;
+        for candidate in chosen {
+            let text = &event.content[candidate.start..candidate.end];
+            let id = *ids.entry(text.to_string()).or_insert_with(|| {
+                deduped.blocks.push(SharedBlock {
+                    id: deduped.blocks.len() + 1,
+                    content: text.to_string(),
+                    occurrences: counts[text],
+                    preformatted: candidate.preformatted,
+                });
+                deduped.blocks.len()
+            });
+            let reference = deduped.blocks[id - 1].reference();
+            content.push_str(&event.content[at..candidate.start]);
+            content.push_str(&reference);
+            replaced += text.len() - reference.len().min(text.len());
+            at = candidate.end;
+        }
+        content.push_str(&event.content[at..]);
+        event.content = content;
+    }
+    let kept: usize = deduped.blocks.iter().map(|b| b.content.len()).sum();
+    deduped.saved_bytes = replaced.saturating_sub(kept);
+    deduped
+}
+
+/// Blocks of `content` long enough to share, by start and then longest first.
+fn candidates(content: &str, min_bytes: usize) -> Vec<Candidate> {
+    let mut found = Vec::new();
+    for block in split_content_blocks(content) {
+        let (body, preformatted) = match block {
+            ContentBlock::Text(text) | ContentBlock::Thinking(text) => (text, false),
+            ContentBlock::ToolUse { input, .. } => (input, true),
+            ContentBlock::ToolResult { output, .. } => (output, true),
+        };
+        let start = offset_in(content, body);
+        found.push(Candidate {
+            start,
+            end: start + body.len(),
+            preformatted,
+        });
+        if !preformatted {
+            found.extend(code_fences(body).into_iter().map(|(s, e)| Candidate {
+                start: start + s,
+                end: start + e,
+                preformatted: false,
+            }));
+        }
+    }
+    found.retain(|c| c.end - c.start >= min_bytes);
+    found.sort_by_key(|c| (c.start, std::cmp::Reverse(c.end)));
+    found
+}
+
+/// Where `part`, a slice borrowed from `whole`, starts in it.
+fn offset_in(whole: &str, part: &str) -> usize {
+    part.as_ptr() as usize - whole.as_ptr() as usize
+}
+
+/// Byte ranges of the closed ``` fences in `text`, fence lines included.
+fn code_fences(text: &str) -> Vec<(usize, usize)> {
+    let mut fences = Vec::new();
+    let mut open = None;
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        if line.trim_start().starts_with("```") {
+            match open.take() {
+                Some(start) => fences.push((start, offset + line.trim_end_matches('\n').len())),
+                None => open = Some(offset),
+            }
+        }
+        offset += line.len();
+    }
+    fences
+}
+
+/// The appendix of a markdown bundle.
+pub(crate) fn push_shared_markdown(out: &mut String, blocks: &[SharedBlock]) {
+    if blocks.is_empty() {
+        return;
+    }
+    out.push_str("\n\n---\n\n# Shared content\n\n");
+    for block in blocks {
+        out.push_str(&format!(
+            "<a id=\"{}\"></a>\n\n## Shared block {}\n\nOccurs {} times.\n\n",
+            block.anchor(),
+            block.id,
+            block.occurrences
+        ));
+        if block.preformatted {
+            let fence = "`".repeat(longest_backtick_run(&block.content).max(2) + 1);
+            out.push_str(&format!("{fence}\n{}\n{fence}\n\n", block.content));
+        } else {
+            out.push_str(&block.content);
+            out.push_str("\n\n");
+        }
+    }
+}
+
+fn longest_backtick_run(text: &str) -> usize {
+    text.split(|c| c != '`').map(str::len).max().unwrap_or(0)
+}
+
+/// The appendix of an HTML bundle.
+pub(crate) fn push_shared_html(body: &mut String, blocks: &[SharedBlock]) {
+    if blocks.is_empty() {
+        return;
+    }
+    body.push_str("<h1 id=\"shared\">Shared content</h1>");
+    for block in blocks {
+        let content = if block.preformatted {
+            format!("<pre>{}</pre>", html_escape(&block.content))
+        } else {
+            render_content_html(&block.content)
+        };
+        body.push_str(&format!(
+            "<div class=\"card\" id=\"{}\"><h2>Shared block {}</h2><div class=\"meta\">occurs {} times</div><div class=\"content\">{content}</div></div>",
+            block.anchor(),
+            block.id,
+            block.occurrences
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::NormalizedEvent;
+    use std::path::PathBuf;
+
+    fn doc(id: &str, contents: &[String]) -> ExportDocument {
+        let events = contents
+            .iter()
+            .map(|c| NormalizedEvent {
+                role: "user".to_string(),
+                source_type: "user".to_string(),
+                timestamp: None,
+                content: c.clone(),
+                uuid: None,
+                parent_uuid: None,
+                source: None,
+                model: None,
+            })
+            .collect::<Vec<_>>();
+        ExportDocument {
+            session_id: id.to_string(),
+            session_short: id.to_string(),
+            project: "p".to_string(),
+            source_path: PathBuf::from(format!("{id}.jsonl")),
+            modified_iso: String::new(),
+            event_count: events.len(),
+            events,
+            tree: None,
+            title: None,
+        }
+    }
+
+    #[test]
+    fn repeated_fences_and_tool_output_move_to_the_appendix() {
+        let file = format!("```rust\n{}```", "fn main() {}\n".repeat(50));
+        let log = "line of build output\n".repeat(40);
+        let mut docs = [
+            doc(
+                "a",
+                &[
+                    format!("Why does this fail?\n\n{file}\n\nThanks"),
+                    format!("[tool_result] toolu_1\n{log}"),
+                ],
+            ),
+            doc(
+                "b",
+                &[
+                    format!("Same file again:\n{file}"),
+                    format!("Checking.\n[tool_result] toolu_9\n{log}"),
+                    "short, repeated".to_string(),
+                    "short, repeated".to_string(),
+                ],
+            ),
+        ];
+        let deduped = dedupe_content(&mut docs, SHARED_BLOCK_MIN_BYTES);
+
+        assert_eq!(deduped.blocks.len(), 2);
+        assert_eq!(deduped.blocks[0].content, file);
+        assert_eq!(
+            (
+                deduped.blocks[0].occurrences,
+                deduped.blocks[0].preformatted
+            ),
+            (2, false)
+        );
+        assert!(deduped.blocks[1].preformatted);
+        assert!(deduped.saved_bytes > file.len() + log.len() - 200);
+        assert_eq!(
+            docs[0].events[0].content,
+            "Why does this fail?\n\n[shared block 1](#shared-1) (52 lines)\n\nThanks"
+        );
+        assert_eq!(
+            docs[1].events[1].content,
+            "Checking.\n[tool_result] toolu_9\n[shared block 2](#shared-2) (40 lines)\n"
+        );
+        assert_eq!(docs[1].events[2].content, "short, repeated");
+
+        let mut markdown = String::new();
+        push_shared_markdown(&mut markdown, &deduped.blocks);
+        assert!(markdown.contains("<a id=\"shared-2\"></a>\n\n## Shared block 2\n\nOccurs 2 times.\n\n```\nline of build output\n"));
+        let mut html = String::new();
+        push_shared_html(&mut html, &deduped.blocks);
+        assert!(html.contains("<div class=\"card\" id=\"shared-1\">"));
+    }
+}
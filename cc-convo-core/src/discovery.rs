@@ -0,0 +1,781 @@
+//! Locating transcript files under the Claude projects directory.
+
+use crate::compress::{is_transcript, open_transcript, transcript_stem, Compression};
+use crate::parse::{extract_message_text, jsonl_records, NormalizedEvent};
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::Serialize;
+use serde_json::Value;
+use std::cmp::Reverse;
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// How recently a transcript must have been written to count as active.
+pub const DEFAULT_ACTIVE_WITHIN_SECS: i64 = 120;
+
+/// How long a session stopped mid-turn counts as active without new writes,
+/// so a long-running tool call doesn't make it look finished.
+pub const MID_TURN_GRACE_SECS: i64 = 30 * 60;
+
+/// Bytes read from the end of a transcript to find its last dialog record.
+const TAIL_SCAN_BYTES: u64 = 256 * 1024;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Session {
+    pub index: usize,
+    pub id: String,
+    pub id_short: String,
+    pub project: String,
+    pub path: PathBuf,
+    pub modified_iso: String,
+    pub modified_epoch: i64,
+    pub size_bytes: u64,
+    /// Older files of the same session in other project dirs, linked by
+    /// `cc-convo stitch`, oldest first; `path` is the newest.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub stitched: Vec<PathBuf>,
+}
+
+impl Session {
+    /// `project/id`, which stays unique when the same transcript file was
+    /// copied into more than one project.
+    pub fn qualified_id(&self) -> String {
+        format!("{}/{}", self.project, self.id)
+    }
+
+    /// Every file of the session, oldest first.
+    pub fn paths(&self) -> impl Iterator<Item = &Path> {
+        self.stitched
+            .iter()
+            .map(PathBuf::as_path)
+            .chain([self.path.as_path()])
+    }
+
+    fn matches_id(&self, id: &str) -> bool {
+        self.id == id || self.id_short == id
+    }
+
+    /// Heuristic liveness at `now` (Unix seconds): written within
+    /// `within_secs`, or [stopped mid-turn](ends_mid_turn) and written within
+    /// [`MID_TURN_GRACE_SECS`].
+    pub fn is_active(&self, now: i64, within_secs: i64) -> bool {
+        let age = now - self.modified_epoch;
+        age <= within_secs || (age <= MID_TURN_GRACE_SECS && ends_mid_turn(&self.path))
+    }
+}
+
+/// Whether a transcript stops in the middle of a turn: its last line is
+/// half-written, its last prompt or tool result has no reply yet, or its last
+/// reply is calling a tool. Slash-command output and interrupts end a turn.
+pub fn ends_mid_turn(path: &Path) -> bool {
+    let Some(tail) = read_tail(path) else {
+        return false;
+    };
+    if tail.last().is_some_and(|&b| b != b'\n') {
+        return true;
+    }
+    for record in tail_records(&tail) {
+        let field = |key: &str| record.get(key).and_then(Value::as_str);
+        match field("type") {
+            Some("assistant") => {
+                let stop = record
+                    .get("message")
+                    .and_then(|m| m.get("stop_reason"))
+                    .and_then(Value::as_str);
+                return matches!(stop, None | Some("tool_use"));
+            }
+            Some("user") => {
+                let text = extract_message_text(&record, false);
+                let text = text.trim_start();
+                return !(text.starts_with("[Request interrupted")
+                    || text.starts_with("<command-name>")
+                    || text.starts_with("<local-command-stdout>"));
+            }
+            _ => continue,
+        }
+    }
+    false
+}
+
+/// How a session ended, when it didn't end with a normal reply.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum SessionEnd {
+    /// An API error (overloaded, server error, and the like).
+    Error,
+    /// The user pressed Esc or Ctrl-C mid-reply.
+    Interrupt,
+    /// The context window or a usage limit ran out.
+    Limit,
+}
+
+impl SessionEnd {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Error => "error",
+            Self::Interrupt => "interrupt",
+            Self::Limit => "limit",
+        }
+    }
+}
+
+/// Phrases in error text that mean a limit was hit rather than a failure.
+const LIMIT_PHRASES: &[&str] = &[
+    "prompt is too long",
+    "context window",
+    "context length",
+    "context limit",
+    "usage limit",
+    "limit reached",
+];
+
+/// Why the session at `path` stopped, judged from its last dialog record: an
+/// interrupt marker, an API error reply (`isApiErrorMessage`), a system error
+/// record, or a context-window stop reason. `None` for a normal ending.
+pub fn session_end(path: &Path) -> Option<SessionEnd> {
+    let tail = read_tail(path)?;
+    let classify = |text: &str| {
+        let text = text.to_lowercase();
+        if LIMIT_PHRASES.iter().any(|phrase| text.contains(phrase)) {
+            SessionEnd::Limit
+        } else {
+            SessionEnd::Error
+        }
+    };
+    for record in tail_records(&tail) {
+        let field = |key: &str| record.get(key).and_then(Value::as_str);
+        match field("type") {
+            Some("system")
+                if field("level") == Some("error") || field("subtype") == Some("api_error") =>
+            {
+                return Some(classify(&record.to_string()));
+            }
+            Some("assistant") => {
+                if record.get("isApiErrorMessage").and_then(Value::as_bool) == Some(true) {
+                    return Some(classify(&extract_message_text(&record, false)));
+                }
+                let stop = record
+                    .get("message")
+                    .and_then(|m| m.get("stop_reason"))
+                    .and_then(Value::as_str);
+                return (stop == Some("model_context_window_exceeded"))
+                    .then_some(SessionEnd::Limit);
+            }
+            Some("user") => {
+                let text = extract_message_text(&record, false);
+                return text
+                    .trim_start()
+                    .starts_with("[Request interrupted")
+                    .then_some(SessionEnd::Interrupt);
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// The last [`TAIL_SCAN_BYTES`] of a transcript, starting at a line boundary.
+fn read_tail(path: &Path) -> Option<Vec<u8>> {
+    if Compression::of(path).is_some() {
+        // No seeking in a compressed stream: keep the end of all of it.
+        let mut all = Vec::new();
+        open_transcript(path).ok()?.read_to_end(&mut all).ok()?;
+        let start = all.len().saturating_sub(TAIL_SCAN_BYTES as usize);
+        let cut = match start {
+            0 => 0,
+            _ => all[start..]
+                .iter()
+                .position(|&b| b == b'\n')
+                .map_or(all.len(), |i| start + i + 1),
+        };
+        all.drain(..cut);
+        return Some(all);
+    }
+    let mut file = File::open(path).ok()?;
+    let start = file
+        .metadata()
+        .map_or(0, |m| m.len().saturating_sub(TAIL_SCAN_BYTES));
+    let mut tail = Vec::new();
+    file.seek(SeekFrom::Start(start)).ok()?;
+    file.read_to_end(&mut tail).ok()?;
+    if start > 0 {
+        // The first line is cut off by the seek.
+        let cut = tail
+            .iter()
+            .position(|&b| b == b'\n')
+            .map_or(tail.len(), |i| i + 1);
+        tail.drain(..cut);
+    }
+    Some(tail)
+}
+
+/// Parseable, non-meta records of `tail`, last first.
+fn tail_records(tail: &[u8]) -> impl Iterator<Item = Value> + '_ {
+    tail.split(|&b| b == b'\n')
+        .rev()
+        .filter_map(|line| serde_json::from_slice::<Value>(line).ok())
+        .filter(|record| record.get("isMeta").and_then(Value::as_bool) != Some(true))
+}
+
+/// Inclusive time bounds; `None` leaves that side open.
+#[derive(Debug, Clone, Default)]
+pub struct TimeWindow {
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    pub by: WindowBy,
+}
+
+/// What a [`TimeWindow`] is compared against.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum WindowBy {
+    /// The transcript file's modification time.
+    #[default]
+    Mtime,
+    /// Each record's own `timestamp`. A session matches if any record falls in
+    /// the window, and only those records are kept. Resumed sessions and
+    /// restored backups have mtimes that say little about their content.
+    Event,
+}
+
+impl TimeWindow {
+    pub fn is_open(&self) -> bool {
+        self.since.is_none() && self.until.is_none()
+    }
+
+    pub fn contains(&self, at: DateTime<Utc>) -> bool {
+        self.since.is_none_or(|since| at >= since) && self.until.is_none_or(|until| at <= until)
+    }
+
+    /// Whether a record with this `timestamp` passes in [`WindowBy::Event`]
+    /// mode. Records without a readable timestamp always pass, as does
+    /// everything in mtime mode.
+    pub fn admits(&self, timestamp: Option<&str>) -> bool {
+        if self.by != WindowBy::Event {
+            return true;
+        }
+        match timestamp.and_then(|ts| DateTime::parse_from_rfc3339(ts).ok()) {
+            Some(at) => self.contains(at.with_timezone(&Utc)),
+            None => true,
+        }
+    }
+
+    /// Drop events outside the window in [`WindowBy::Event`] mode.
+    pub fn retain_events(&self, events: &mut Vec<NormalizedEvent>) {
+        if self.by == WindowBy::Event && !self.is_open() {
+            events.retain(|e| self.admits(e.timestamp.as_deref()));
+        }
+    }
+}
+
+/// Default directory depth below the transcript root. Real layouts are two or
+/// three levels deep; the limit only matters for runaway symlink chains.
+pub const DEFAULT_MAX_DEPTH: usize = 16;
+
+/// Guards for the directory walk. Symlinks are followed, so a link back up the
+/// tree is cut off by visited-directory tracking and `max_depth` bounds the rest.
+#[derive(Debug, Clone)]
+pub struct WalkLimits {
+    pub max_depth: usize,
+    /// Don't descend into directories on a different filesystem than the root.
+    pub one_file_system: bool,
+}
+
+impl Default for WalkLimits {
+    fn default() -> Self {
+        Self {
+            max_depth: DEFAULT_MAX_DEPTH,
+            one_file_system: false,
+        }
+    }
+}
+
+/// Sessions found under the transcript root, plus the paths that could not
+/// be read and were left out.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Discovery {
+    pub sessions: Vec<Session>,
+    pub skipped: Vec<SkippedPath>,
+}
+
+/// A file or directory skipped during discovery, e.g. for permission errors.
+#[derive(Debug, Clone, Serialize)]
+pub struct SkippedPath {
+    pub path: PathBuf,
+    pub error: String,
+}
+
+/// Sessions under `claude_dir`, newest first. Unreadable files are skipped;
+/// use [`discover`] to find out which.
+pub fn discover_sessions(claude_dir: &Path, time_window: &TimeWindow) -> Result<Vec<Session>> {
+    Ok(discover(claude_dir, time_window)?.sessions)
+}
+
+/// Walk `claude_dir` for `.jsonl` transcripts, recording per-path access
+/// errors instead of failing the whole walk on the first one.
+pub fn discover(claude_dir: &Path, time_window: &TimeWindow) -> Result<Discovery> {
+    discover_with(claude_dir, time_window, &WalkLimits::default())
+}
+
+/// [`discover`] with explicit depth and filesystem limits.
+pub fn discover_with(
+    claude_dir: &Path,
+    time_window: &TimeWindow,
+    limits: &WalkLimits,
+) -> Result<Discovery> {
+    if !claude_dir.exists() {
+        bail!("Claude directory does not exist: {}", claude_dir.display());
+    }
+    let mut sessions = Vec::new();
+    let mut skipped = Vec::new();
+    let mut visited_dirs = HashSet::new();
+    let walker = WalkDir::new(claude_dir)
+        .follow_links(true)
+        .max_depth(limits.max_depth)
+        .same_file_system(limits.one_file_system)
+        .into_iter()
+        .filter_entry(|entry| {
+            !entry.file_type().is_dir()
+                || dir_key(entry.path()).is_none_or(|key| visited_dirs.insert(key))
+        });
+    for entry in walker {
+        let entry = match entry {
+            Ok(entry) => entry,
+            // A symlink back to an ancestor; the ancestor is already being walked.
+            Err(err) if err.loop_ancestor().is_some() => continue,
+            Err(err) => {
+                skipped.push(SkippedPath {
+                    path: err.path().unwrap_or(claude_dir).to_path_buf(),
+                    error: io_error_text(err.io_error(), &err.to_string()),
+                });
+                continue;
+            }
+        };
+        let path = entry.path();
+        if !entry.file_type().is_file() || !is_transcript(path) {
+            continue;
+        }
+        let readable = fs::metadata(path).and_then(|m| File::open(path).map(|_| m));
+        let metadata = match readable {
+            Ok(metadata) => metadata,
+            Err(err) => {
+                skipped.push(SkippedPath {
+                    path: path.to_path_buf(),
+                    error: io_error_text(Some(&err), ""),
+                });
+                continue;
+            }
+        };
+        let modified = metadata
+            .modified()
+            .with_context(|| format!("Failed to get mtime for {}", path.display()))?;
+        let modified_dt: DateTime<Utc> = modified.into();
+        let in_window = match time_window.by {
+            WindowBy::Mtime => time_window.contains(modified_dt),
+            WindowBy::Event => has_record_in_window(path, time_window),
+        };
+        if !in_window {
+            continue;
+        }
+
+        let stem = transcript_stem(path)
+            .ok_or_else(|| anyhow!("Invalid file stem for {}", path.display()))?
+            .to_string();
+        let project = path
+            .parent()
+            .and_then(|p| p.file_name())
+            .and_then(|s| s.to_str())
+            .unwrap_or("unknown")
+            .to_string();
+        let modified_iso = modified_dt.to_rfc3339_opts(SecondsFormat::Secs, true);
+        let modified_epoch = modified_dt.timestamp();
+        let size_bytes = metadata.len();
+
+        sessions.push(Session {
+            index: 0,
+            id_short: short_id(&stem),
+            id: stem,
+            project,
+            path: path.to_path_buf(),
+            modified_iso,
+            modified_epoch,
+            size_bytes,
+            stitched: Vec::new(),
+        });
+    }
+
+    sessions.sort_by_key(|s| (Reverse(s.modified_epoch), s.path.clone()));
+    for (i, session) in sessions.iter_mut().enumerate() {
+        session.index = i + 1;
+    }
+    skipped.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(Discovery { sessions, skipped })
+}
+
+/// Whether any record in `path` has a timestamp inside `window`.
+fn has_record_in_window(path: &Path, window: &TimeWindow) -> bool {
+    if window.is_open() {
+        return true;
+    }
+    let Ok(file) = open_transcript(path) else {
+        return false;
+    };
+    jsonl_records(BufReader::new(file))
+        .map_while(|record| record.ok())
+        .filter_map(|record| record.ok())
+        .filter_map(|value| {
+            let ts = value.get("timestamp")?.as_str()?;
+            DateTime::parse_from_rfc3339(ts).ok()
+        })
+        .any(|at| window.contains(at.with_timezone(&Utc)))
+}
+
+/// Identity of a directory, so one reached through several symlinks is walked once.
+#[cfg(unix)]
+fn dir_key(path: &Path) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    fs::metadata(path).ok().map(|m| (m.dev(), m.ino()))
+}
+
+#[cfg(not(unix))]
+fn dir_key(path: &Path) -> Option<PathBuf> {
+    fs::canonicalize(path).ok()
+}
+
+fn io_error_text(err: Option<&std::io::Error>, fallback: &str) -> String {
+    match err {
+        Some(err) if err.kind() == std::io::ErrorKind::PermissionDenied => {
+            "permission denied".to_string()
+        }
+        Some(err) => err.to_string(),
+        None => fallback.to_string(),
+    }
+}
+
+/// Find a session by full id, short id, or project-qualified `project/id`.
+/// A bare id that exists in several projects is an error listing the
+/// qualified candidates rather than a silent pick of the first.
+pub fn find_session<'a>(sessions: &'a [Session], reference: &str) -> Result<&'a Session> {
+    let candidates = id_candidates(sessions, reference);
+    match candidates.as_slice() {
+        [] => bail!("Session not found: {reference}"),
+        [session] => Ok(session),
+        _ => {
+            let listed = candidates
+                .iter()
+                .map(|s| format!("  {} ({})", s.qualified_id(), s.path.display()))
+                .collect::<Vec<_>>();
+            bail!(
+                "Session id {reference} is ambiguous; it matches:\n{}\nUse project/id to pick one.",
+                listed.join("\n")
+            )
+        }
+    }
+}
+
+/// Resolve a session reference as [`find_session`] does, falling back to the
+/// `nth` most recent session (1-based, default 1) of the project whose name
+/// contains `target`, ignoring case.
+pub fn resolve_target<'a>(
+    sessions: &'a [Session],
+    target: &str,
+    nth: Option<usize>,
+) -> Result<&'a Session> {
+    if !id_candidates(sessions, target).is_empty() {
+        if nth.is_some() {
+            bail!("--nth picks among a project's sessions, but {target} is a session id");
+        }
+        return find_session(sessions, target);
+    }
+    let nth = nth.unwrap_or(1);
+    if nth == 0 {
+        bail!("--nth is 1-based; got 0");
+    }
+    let project = match_project(sessions, target)?;
+    let project_sessions = sessions
+        .iter()
+        .filter(|s| s.project == project)
+        .collect::<Vec<_>>();
+    project_sessions.get(nth - 1).copied().ok_or_else(|| {
+        anyhow!(
+            "Project {project} has {} session(s); --nth {nth} is out of range",
+            project_sessions.len()
+        )
+    })
+}
+
+fn id_candidates<'a>(sessions: &'a [Session], reference: &str) -> Vec<&'a Session> {
+    match reference.split_once('/') {
+        Some((project, id)) => sessions
+            .iter()
+            .filter(|s| s.project == project && s.matches_id(id))
+            .collect(),
+        None => sessions
+            .iter()
+            .filter(|s| s.matches_id(reference))
+            .collect(),
+    }
+}
+
+/// The one project whose directory name contains `query`, ignoring case.
+/// Project dirs encode the full path (`-home-user-repo`), so a name that ends
+/// in `-query` wins over ones that merely contain it.
+fn match_project<'a>(sessions: &'a [Session], query: &str) -> Result<&'a str> {
+    let needle = query.to_lowercase();
+    let mut matches: Vec<&str> = sessions
+        .iter()
+        .map(|s| s.project.as_str())
+        .filter(|p| p.to_lowercase().contains(&needle))
+        .collect();
+    matches.sort_unstable();
+    matches.dedup();
+    let exact = matches
+        .iter()
+        .copied()
+        .filter(|p| {
+            let lower = p.to_lowercase();
+            lower == needle || lower.ends_with(&format!("-{needle}"))
+        })
+        .collect::<Vec<_>>();
+    match (matches.as_slice(), exact.as_slice()) {
+        ([], _) => bail!("No session or project matches {query}"),
+        ([project], _) | (_, [project]) => Ok(project),
+        _ => bail!(
+            "{query} matches several projects: {}; be more specific",
+            matches.join(", ")
+        ),
+    }
+}
+
+pub fn short_id(full: &str) -> String {
+    full.chars().take(8).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::{session, unique_temp_path, write_jsonl};
+
+    #[test]
+    fn ends_mid_turn_reads_the_last_dialog_record() {
+        let dir = unique_temp_path("cc-convo-active");
+        fs::create_dir_all(&dir).expect("create dir");
+        let path = dir.join("s.jsonl");
+        let prompt = r#"{"type":"user","message":{"role":"user","content":"hi"}}"#;
+        let tool_call = r#"{"type":"assistant","message":{"stop_reason":"tool_use","content":[]}}"#;
+        let done = r#"{"type":"assistant","message":{"stop_reason":"end_turn","content":[]}}"#;
+        let snapshot = r#"{"type":"file-history-snapshot"}"#;
+        let interrupt = r#"{"type":"user","message":{"role":"user","content":[{"type":"text","text":"[Request interrupted by user]"}]}}"#;
+        let cases: [(&[&str], bool); 5] = [
+            (&[prompt], true),
+            (&[prompt, tool_call, snapshot], true),
+            (&[prompt, done, snapshot], false),
+            (&[prompt, interrupt], false),
+            (&[], false),
+        ];
+        for (lines, expected) in cases {
+            let mut body = lines.join("\n");
+            if !body.is_empty() {
+                body.push('\n');
+            }
+            fs::write(&path, body).expect("write");
+            assert_eq!(ends_mid_turn(&path), expected, "{lines:?}");
+        }
+        write_jsonl(&path, &[prompt, done]);
+        assert!(
+            ends_mid_turn(&path),
+            "no trailing newline: still being written"
+        );
+
+        let mut s = session("p", "s");
+        s.path = path;
+        s.modified_epoch = 1_000;
+        assert!(s.is_active(1_060, DEFAULT_ACTIVE_WITHIN_SECS));
+        assert!(s.is_active(1_000 + MID_TURN_GRACE_SECS, DEFAULT_ACTIVE_WITHIN_SECS));
+        assert!(!s.is_active(1_001 + MID_TURN_GRACE_SECS, DEFAULT_ACTIVE_WITHIN_SECS));
+        fs::remove_dir_all(&dir).expect("cleanup");
+    }
+
+    #[test]
+    fn session_end_classifies_the_last_dialog_record() {
+        let dir = unique_temp_path("cc-convo-ended-by");
+        fs::create_dir_all(&dir).expect("create dir");
+        let path = dir.join("s.jsonl");
+        let prompt = r#"{"type":"user","message":{"role":"user","content":"hi"}}"#;
+        let done = r#"{"type":"assistant","message":{"stop_reason":"end_turn","content":[]}}"#;
+        let interrupt = r#"{"type":"user","message":{"role":"user","content":[{"type":"text","text":"[Request interrupted by user for tool use]"}]}}"#;
+        let api_error = r#"{"type":"assistant","isApiErrorMessage":true,"message":{"content":[{"type":"text","text":"API Error: 529 Overloaded"}]}}"#;
+        let too_long = r#"{"type":"assistant","isApiErrorMessage":true,"message":{"content":[{"type":"text","text":"Prompt is too long"}]}}"#;
+        let system_error =
+            r#"{"type":"system","subtype":"api_error","level":"error","content":"Request failed"}"#;
+        let hook = r#"{"type":"system","subtype":"stop_hook_summary","level":"info"}"#;
+        let cases: [(&[&str], Option<SessionEnd>); 6] = [
+            (&[prompt, done, hook], None),
+            (&[prompt, interrupt], Some(SessionEnd::Interrupt)),
+            (&[prompt, api_error], Some(SessionEnd::Error)),
+            (&[prompt, too_long, hook], Some(SessionEnd::Limit)),
+            (&[prompt, system_error], Some(SessionEnd::Error)),
+            (&[prompt, interrupt, prompt, done], None),
+        ];
+        for (lines, expected) in cases {
+            write_jsonl(&path, lines);
+            assert_eq!(session_end(&path), expected, "{lines:?}");
+        }
+        fs::remove_dir_all(&dir).expect("cleanup");
+    }
+
+    #[test]
+    fn find_session_disambiguates_across_projects() {
+        let sessions = vec![
+            session("alpha", "0123456789abcdef"),
+            session("beta", "0123456789abcdef"),
+            session("beta", "fedcba9876543210"),
+        ];
+
+        let unique = find_session(&sessions, "fedcba98").expect("unique short id");
+        assert_eq!(unique.qualified_id(), "beta/fedcba9876543210");
+        let qualified = find_session(&sessions, "alpha/01234567").expect("qualified");
+        assert_eq!(qualified.project, "alpha");
+        let qualified = find_session(&sessions, "beta/0123456789abcdef").expect("qualified");
+        assert_eq!(qualified.project, "beta");
+
+        let err = find_session(&sessions, "01234567")
+            .expect_err("bare id is ambiguous")
+            .to_string();
+        assert!(err.contains("ambiguous"), "{err}");
+        assert!(err.contains("alpha/0123456789abcdef"), "{err}");
+        assert!(err.contains("beta/0123456789abcdef"), "{err}");
+
+        assert!(find_session(&sessions, "gamma/01234567").is_err());
+    }
+
+    #[test]
+    fn resolve_target_falls_back_to_latest_in_project() {
+        let sessions = vec![
+            session("-home-user-MyRepo", "aaaa0000aaaa"),
+            session("-home-user-other", "bbbb0000bbbb"),
+            session("-home-user-MyRepo", "cccc0000cccc"),
+            session("-home-user-myrepo-docs", "dddd0000dddd"),
+        ];
+
+        let latest = resolve_target(&sessions, "myrepo", None).expect("project match");
+        assert_eq!(latest.id, "aaaa0000aaaa");
+        let second = resolve_target(&sessions, "MYREPO", Some(2)).expect("nth");
+        assert_eq!(second.id, "cccc0000cccc");
+        assert!(resolve_target(&sessions, "myrepo", Some(3)).is_err());
+        let docs = resolve_target(&sessions, "repo-docs", None).expect("partial");
+        assert_eq!(docs.id, "dddd0000dddd");
+
+        let by_id = resolve_target(&sessions, "bbbb0000", None).expect("id first");
+        assert_eq!(by_id.project, "-home-user-other");
+        assert!(resolve_target(&sessions, "bbbb0000", Some(1)).is_err());
+
+        let err = resolve_target(&sessions, "user", None)
+            .expect_err("ambiguous project")
+            .to_string();
+        assert!(err.contains("several projects"), "{err}");
+        assert!(resolve_target(&sessions, "nothing", None).is_err());
+    }
+
+    #[test]
+    fn event_window_filters_records_by_timestamp() {
+        let window = TimeWindow {
+            since: Some("2026-01-02T00:00:00Z".parse().unwrap()),
+            until: None,
+            by: WindowBy::Event,
+        };
+        assert!(!window.admits(Some("2026-01-01T23:59:59Z")));
+        assert!(window.admits(Some("2026-01-02T00:00:00.000Z")));
+        assert!(window.admits(None));
+        let mtime_window = TimeWindow {
+            by: WindowBy::Mtime,
+            ..window.clone()
+        };
+        assert!(mtime_window.admits(Some("2020-01-01T00:00:00Z")));
+
+        let root = unique_temp_path("cc-convo-test-event-window");
+        let project = root.join("-home-user-p");
+        fs::create_dir_all(&project).expect("create temp dir");
+        write_jsonl(
+            &project.join("old.jsonl"),
+            &[r#"{"type":"user","timestamp":"2026-01-01T10:00:00Z"}"#],
+        );
+        write_jsonl(
+            &project.join("resumed.jsonl"),
+            &[
+                r#"{"type":"user","timestamp":"2026-01-01T10:00:00Z"}"#,
+                r#"{"type":"user","timestamp":"2026-01-03T10:00:00Z"}"#,
+            ],
+        );
+        let found = discover(&root, &window).expect("discover");
+        let ids = found
+            .sessions
+            .iter()
+            .map(|s| s.id.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(ids, vec!["resumed"]);
+
+        fs::remove_dir_all(&root).expect("cleanup dir");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn discover_skips_unreadable_files() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let root = unique_temp_path("cc-convo-test-discover");
+        let project = root.join("-home-user-p");
+        fs::create_dir_all(&project).expect("create temp dir");
+        write_jsonl(&project.join("readable.jsonl"), &[r#"{"type":"user"}"#]);
+        let locked = project.join("locked.jsonl");
+        write_jsonl(&locked, &[r#"{"type":"user"}"#]);
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o000)).expect("chmod");
+        if File::open(&locked).is_ok() {
+            // Running as root: permission bits are not enforced.
+            fs::remove_dir_all(&root).expect("cleanup dir");
+            return;
+        }
+
+        let found = discover(&root, &TimeWindow::default()).expect("discover");
+        let ids = found
+            .sessions
+            .iter()
+            .map(|s| s.id.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(ids, vec!["readable"]);
+        assert_eq!(found.skipped.len(), 1);
+        assert_eq!(found.skipped[0].path, locked);
+        assert_eq!(found.skipped[0].error, "permission denied");
+
+        fs::remove_dir_all(&root).expect("cleanup dir");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn discover_survives_symlink_cycles() {
+        let root = unique_temp_path("cc-convo-test-discover-loop");
+        let project = root.join("-home-user-p");
+        fs::create_dir_all(&project).expect("create temp dir");
+        write_jsonl(&project.join("s1.jsonl"), &[r#"{"type":"user"}"#]);
+        std::os::unix::fs::symlink(&root, project.join("loop")).expect("symlink");
+        std::os::unix::fs::symlink(&project, root.join("alias")).expect("symlink");
+
+        let found = discover(&root, &TimeWindow::default()).expect("discover");
+        assert_eq!(found.sessions.len(), 1);
+        assert_eq!(found.sessions[0].id, "s1");
+        assert!(found.skipped.is_empty());
+
+        let shallow = WalkLimits {
+            max_depth: 1,
+            ..WalkLimits::default()
+        };
+        let found = discover_with(&root, &TimeWindow::default(), &shallow).expect("discover");
+        assert!(found.sessions.is_empty());
+
+        fs::remove_dir_all(&root).expect("cleanup dir");
+    }
+}
@@ -0,0 +1,222 @@
+//! Encryption of exported files with age (<https://age-encryption.org>), to
+//! public keys or to a passphrase, and decryption of them again.
+
+use crate::fs::write_atomic;
+use age::secrecy::SecretString;
+use age::{x25519, Decryptor, Encryptor, Identity, IdentityFile, Recipient};
+use anyhow::{anyhow, bail, Context, Result};
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Appended to the name of every encrypted file.
+pub const AGE_EXTENSION: &str = "age";
+
+/// Who can read the encrypted files.
+pub enum Encryption {
+    /// Holders of the identity for any of these `age1...` public keys.
+    Recipients(Vec<x25519::Recipient>),
+    /// Anyone with the passphrase. age allows no other recipient with one.
+    Passphrase(SecretString),
+}
+
+impl Encryption {
+    pub fn passphrase(passphrase: String) -> Self {
+        Self::Passphrase(SecretString::from(passphrase))
+    }
+
+    pub fn encrypt(&self, plain: &[u8]) -> Result<Vec<u8>> {
+        let encryptor = match self {
+            Self::Recipients(recipients) => {
+                Encryptor::with_recipients(recipients.iter().map(|r| r as &dyn Recipient))?
+            }
+            Self::Passphrase(passphrase) => Encryptor::with_user_passphrase(passphrase.clone()),
+        };
+        let mut out = Vec::with_capacity(plain.len() + 512);
+        let mut writer = encryptor.wrap_output(&mut out)?;
+        writer.write_all(plain)?;
+        writer.finish()?;
+        Ok(out)
+    }
+
+    /// Encrypt every file under `from` into the same place under `to`, named
+    /// with [`AGE_EXTENSION`] added, skipping files `skip` picks. Returns the
+    /// encrypted files' paths in `from` and in `to`.
+    pub fn encrypt_tree(
+        &self,
+        from: &Path,
+        to: &Path,
+        skip: impl Fn(&Path) -> bool,
+    ) -> Result<Vec<(PathBuf, PathBuf)>> {
+        let mut written = Vec::new();
+        for entry in WalkDir::new(from).sort_by_file_name() {
+            let entry = entry?;
+            if !entry.file_type().is_file() || skip(entry.path()) {
+                continue;
+            }
+            let relative = entry.path().strip_prefix(from)?;
+            let target = with_age_extension(&to.join(relative));
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)
+                    .with_context(|| format!("Failed to create {}", parent.display()))?;
+            }
+            let plain = fs::read(entry.path())
+                .with_context(|| format!("Failed to read {}", entry.path().display()))?;
+            write_atomic(&target, self.encrypt(&plain)?)?;
+            written.push((entry.path().to_path_buf(), target));
+        }
+        Ok(written)
+    }
+}
+
+/// An `age1...` public key.
+pub fn parse_recipient(key: &str) -> Result<x25519::Recipient> {
+    key.trim()
+        .parse()
+        .map_err(|err| anyhow!("Invalid age recipient {key:?}: {err}"))
+}
+
+/// What can open an encrypted file.
+pub enum Decryption {
+    /// Identities read from age identity files.
+    Identities(Vec<Box<dyn Identity>>),
+    Passphrase(SecretString),
+}
+
+impl Decryption {
+    pub fn passphrase(passphrase: String) -> Self {
+        Self::Passphrase(SecretString::from(passphrase))
+    }
+
+    /// Every identity in the age identity (secret key) files at `paths`.
+    pub fn identity_files(paths: &[PathBuf]) -> Result<Self> {
+        let mut identities = Vec::new();
+        for path in paths {
+            let file = IdentityFile::from_file(path.to_string_lossy().into_owned())
+                .with_context(|| format!("Failed to read identity file {}", path.display()))?;
+            identities.extend(
+                file.into_identities()
+                    .with_context(|| format!("Unsupported identity in {}", path.display()))?,
+            );
+        }
+        Ok(Self::Identities(identities))
+    }
+
+    pub fn decrypt(&self, encrypted: &[u8]) -> Result<Vec<u8>> {
+        let decryptor = Decryptor::new(encrypted).context("Not an age-encrypted file")?;
+        let scrypt;
+        let identities: Vec<&dyn Identity> = match self {
+            Self::Identities(identities) => {
+                if decryptor.is_scrypt() {
+                    bail!("The file is encrypted to a passphrase, not to a key");
+                }
+                identities.iter().map(|i| i.as_ref()).collect()
+            }
+            Self::Passphrase(passphrase) => {
+                if !decryptor.is_scrypt() {
+                    bail!("The file is encrypted to a key; pass its identity file");
+                }
+                scrypt = age::scrypt::Identity::new(passphrase.clone());
+                vec![&scrypt]
+            }
+        };
+        let mut reader = decryptor
+            .decrypt(identities.into_iter())
+            .context("Failed to decrypt")?;
+        let mut plain = Vec::new();
+        reader.read_to_end(&mut plain)?;
+        Ok(plain)
+    }
+}
+
+/// Whether `encrypted` was encrypted to a passphrase rather than to keys.
+pub fn is_passphrase_encrypted(encrypted: &[u8]) -> Result<bool> {
+    Ok(Decryptor::new(encrypted)
+        .context("Not an age-encrypted file")?
+        .is_scrypt())
+}
+
+/// `path` with `.age` appended to its file name.
+pub fn with_age_extension(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(AGE_EXTENSION);
+    path.with_file_name(name)
+}
+
+/// A new directory under the system temp dir that only the current user can
+/// read, for plaintext that must not reach the real output dir. It is
+/// removed, with everything in it, when dropped.
+pub struct StagingDir {
+    path: PathBuf,
+}
+
+impl StagingDir {
+    pub fn new(prefix: &str) -> Result<Self> {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos());
+        let path = std::env::temp_dir().join(format!("{prefix}-{}-{nanos}", std::process::id()));
+        let mut builder = fs::DirBuilder::new();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::DirBuilderExt;
+            builder.mode(0o700);
+        }
+        builder
+            .create(&path)
+            .with_context(|| format!("Failed to create {}", path.display()))?;
+        Ok(Self { path })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for StagingDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::unique_temp_path;
+
+    #[test]
+    fn encrypted_trees_open_only_with_the_matching_identity() {
+        let identity = x25519::Identity::generate();
+        let encryption = Encryption::Recipients(vec![identity.to_public()]);
+        let root = unique_temp_path("cc-convo-test-encrypt");
+        let (plain, sealed) = (root.join("plain"), root.join("sealed"));
+        fs::create_dir_all(plain.join("assets")).expect("create temp dir");
+        fs::write(plain.join("a.md"), "# secret").expect("write");
+        fs::write(plain.join("assets/b.png"), [0u8, 1, 2]).expect("write");
+        fs::write(plain.join(".lock"), "").expect("write");
+
+        let written = encryption
+            .encrypt_tree(&plain, &sealed, |p| p.ends_with(".lock"))
+            .expect("encrypt");
+        let targets = written.iter().map(|(_, to)| to.clone()).collect::<Vec<_>>();
+        assert_eq!(
+            targets,
+            [sealed.join("a.md.age"), sealed.join("assets/b.png.age")]
+        );
+        let encrypted = fs::read(&targets[0]).expect("read");
+        assert!(!String::from_utf8_lossy(&encrypted).contains("secret"));
+        assert!(!is_passphrase_encrypted(&encrypted).expect("header"));
+
+        let key = Decryption::Identities(vec![Box::new(identity)]);
+        assert_eq!(key.decrypt(&encrypted).expect("decrypt"), b"# secret");
+        let other = Decryption::Identities(vec![Box::new(x25519::Identity::generate())]);
+        assert!(other.decrypt(&encrypted).is_err());
+        let passphrase = Decryption::passphrase("hunter2".to_string());
+        assert!(passphrase.decrypt(&encrypted).is_err());
+
+        assert!(parse_recipient("age1nope").is_err());
+        fs::remove_dir_all(&root).expect("cleanup dir");
+    }
+}
@@ -0,0 +1,123 @@
+//! Pairing each user prompt with the assistant's final answer to it.
+
+use crate::markup::{split_content_blocks, ContentBlock};
+use crate::parse::NormalizedEvent;
+use serde::{Deserialize, Serialize};
+
+/// A prompt and the answer the assistant finished with, after any tool
+/// roundtrips in between.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Exchange {
+    pub question: String,
+    pub answer: String,
+    pub asked_at: Option<String>,
+    pub answered_at: Option<String>,
+}
+
+/// Pair prompts with answers. The answer is the text of the last assistant
+/// message before the next prompt; narration before tool calls, tool
+/// results, and thinking are dropped. Tool results, interrupt markers, and
+/// slash-command output are not prompts, and prompts left unanswered are
+/// omitted.
+pub fn pair_exchanges(events: &[NormalizedEvent]) -> Vec<Exchange> {
+    let mut exchanges = Vec::new();
+    let mut open: Option<Exchange> = None;
+    for event in events {
+        match event.role.as_str() {
+            "user" => {
+                let question = event.content.trim();
+                if !is_prompt(question) {
+                    continue;
+                }
+                exchanges.extend(open.take().filter(|e| !e.answer.is_empty()));
+                open = Some(Exchange {
+                    question: question.to_string(),
+                    answer: String::new(),
+                    asked_at: event.timestamp.clone(),
+                    answered_at: None,
+                });
+            }
+            "assistant" => {
+                let Some(exchange) = open.as_mut() else {
+                    continue;
+                };
+                let text = answer_text(&event.content);
+                if !text.is_empty() {
+                    exchange.answer = text;
+                    exchange.answered_at = event.timestamp.clone();
+                }
+            }
+            _ => {}
+        }
+    }
+    exchanges.extend(open.filter(|e| !e.answer.is_empty()));
+    exchanges
+}
+
+pub(crate) fn is_prompt(text: &str) -> bool {
+    !text.is_empty()
+        && !text.starts_with("[tool_result] ")
+        && !text.starts_with("[Request interrupted")
+        && !text.starts_with("<command-")
+        && !text.starts_with("<local-command-")
+}
+
+/// The plain text blocks of an assistant message.
+fn answer_text(content: &str) -> String {
+    split_content_blocks(content)
+        .into_iter()
+        .filter_map(|block| match block {
+            ContentBlock::Text(text) => Some(text.trim()),
+            _ => None,
+        })
+        .filter(|text| !text.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(role: &str, content: &str) -> NormalizedEvent {
+        NormalizedEvent {
+            role: role.to_string(),
+            source_type: role.to_string(),
+            timestamp: Some(format!("t-{content}")),
+            content: content.to_string(),
+            uuid: None,
+            parent_uuid: None,
+            source: None,
+            model: None,
+        }
+    }
+
+    #[test]
+    fn final_answer_skips_tool_roundtrips() {
+        let events = vec![
+            event("assistant", "orphan"),
+            event("user", "Why does the build fail?"),
+            event(
+                "assistant",
+                "Let me check.\n[tool_use] Bash\n{\n  \"command\": \"cargo build\"\n}",
+            ),
+            event("user", "[tool_result] toolu_1\nerror[E0308]"),
+            event("progress", "progress:bash_progress"),
+            event("assistant", "A type mismatch in `main.rs`."),
+            event("user", "<command-name>/clear</command-name>"),
+            event("user", "Thanks. And the tests?"),
+            event("user", "[Request interrupted by user]"),
+            event("user", "Never mind"),
+        ];
+        let exchanges = pair_exchanges(&events);
+        assert_eq!(
+            exchanges,
+            vec![Exchange {
+                question: "Why does the build fail?".to_string(),
+                answer: "A type mismatch in `main.rs`.".to_string(),
+                asked_at: Some("t-Why does the build fail?".to_string()),
+                answered_at: Some("t-A type mismatch in `main.rs`.".to_string()),
+            }]
+        );
+    }
+}
@@ -0,0 +1,1304 @@
+//! Rendering sessions as markdown, JSON, or HTML export documents.
+
+use crate::dataset::render_dataset;
+use crate::dedupe::{push_shared_html, push_shared_markdown, SharedBlock};
+use crate::discovery::Session;
+use crate::exchange::{is_prompt, pair_exchanges, Exchange};
+use crate::fs::write_atomic;
+use crate::markup::render_content_html;
+use crate::obsidian::{render_obsidian_note, VaultNote};
+use crate::parse::NormalizedEvent;
+use crate::search::SearchHit;
+use crate::text::ellipsize;
+use crate::theme::{RoleIcons, Theme, SCHEME_TOGGLE_HTML};
+use crate::thread::{ConversationTree, ThreadNode};
+use crate::title::SessionTitle;
+use anyhow::{bail, Context, Result};
+use chrono::{SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::str::FromStr;
+
+/// How markdown and HTML exports look.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct ExportStyle {
+    /// Colors of HTML pages.
+    pub theme: Theme,
+    /// Put an emoji before each message's role.
+    pub icons: bool,
+    /// In bundles, put sessions under a heading per project, after a table
+    /// of contents.
+    pub group_by_project: bool,
+}
+
+impl ExportStyle {
+    pub(crate) fn role_label(self, role: &str) -> String {
+        RoleIcons::label(self.icons.then_some(RoleIcons::Emoji), role)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Markdown,
+    Json,
+    Html,
+    /// Prompt/answer pairs as JSON; see [`pair_exchanges`].
+    #[serde(rename = "qa-json")]
+    QaJson,
+    /// Prompt/answer pairs as markdown.
+    #[serde(rename = "qa-markdown")]
+    QaMarkdown,
+    /// One OpenAI chat fine-tuning example per line; see [`crate::dataset`].
+    #[serde(rename = "openai-jsonl")]
+    OpenaiJsonl,
+    /// One Anthropic Messages example per line.
+    #[serde(rename = "anthropic-messages")]
+    AnthropicMessages,
+    /// Obsidian notes with frontmatter; see [`crate::obsidian`].
+    Obsidian,
+}
+
+impl ExportFormat {
+    /// File extension, without the leading dot.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Markdown => "md",
+            Self::Json => "json",
+            Self::Html => "html",
+            Self::QaJson => "qa.json",
+            Self::QaMarkdown => "qa.md",
+            Self::OpenaiJsonl => "openai.jsonl",
+            Self::AnthropicMessages => "anthropic.jsonl",
+            Self::Obsidian => "md",
+        }
+    }
+
+    /// Formats meant for training or evals rather than reading.
+    pub fn is_dataset(self) -> bool {
+        matches!(self, Self::OpenaiJsonl | Self::AnthropicMessages)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportDocument {
+    pub session_id: String,
+    pub session_short: String,
+    pub project: String,
+    pub source_path: PathBuf,
+    pub modified_iso: String,
+    pub event_count: usize,
+    pub events: Vec<NormalizedEvent>,
+    /// Set by [`ExportDocument::threaded`]; renderers then follow the tree and
+    /// label branches instead of listing events in file order.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tree: Option<ConversationTree>,
+    /// Set by [`ExportDocument::titled`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+}
+
+impl ExportDocument {
+    pub fn threaded(mut self) -> Self {
+        self.tree = Some(ConversationTree::build(&self.events));
+        self
+    }
+
+    /// Head the document with the title `title` picks, if any.
+    pub fn titled(mut self, title: &SessionTitle) -> Self {
+        self.title = title.title().map(str::to_string);
+        self
+    }
+
+    /// Events in rendering order, with their tree node in threaded mode.
+    fn ordered_events(&self) -> Vec<(&NormalizedEvent, Option<&ThreadNode>)> {
+        match &self.tree {
+            Some(tree) => tree
+                .walk()
+                .into_iter()
+                .map(|i| (&self.events[i], Some(&tree.nodes[i])))
+                .collect(),
+            None => self.events.iter().map(|e| (e, None)).collect(),
+        }
+    }
+}
+
+pub fn build_export_document(session: &Session, events: &[NormalizedEvent]) -> ExportDocument {
+    ExportDocument {
+        session_id: session.id.clone(),
+        session_short: session.id_short.clone(),
+        project: session.project.clone(),
+        source_path: session.path.clone(),
+        modified_iso: session.modified_iso.clone(),
+        event_count: events.len(),
+        events: events.to_vec(),
+        tree: None,
+        title: None,
+    }
+}
+
+/// How a fresh export of a session differs from an earlier one.
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportDiff {
+    /// Events of the earlier export that are still there.
+    pub unchanged: usize,
+    /// Events of the earlier export that are gone, e.g. after a rewrite.
+    pub removed: usize,
+    /// Events not in the earlier export, in file order.
+    pub appended: Vec<NormalizedEvent>,
+}
+
+/// Compare two exports of one session event by event. Events are matched on
+/// all their fields, so a repeated identical event counts once per copy.
+pub fn diff_exports(previous: &ExportDocument, current: &ExportDocument) -> ExportDiff {
+    let mut remaining: HashMap<&NormalizedEvent, usize> = HashMap::new();
+    for event in &previous.events {
+        *remaining.entry(event).or_default() += 1;
+    }
+    let mut unchanged = 0;
+    let mut appended = Vec::new();
+    for event in &current.events {
+        match remaining.get_mut(event) {
+            Some(count) if *count > 0 => {
+                *count -= 1;
+                unchanged += 1;
+            }
+            _ => appended.push(event.clone()),
+        }
+    }
+    ExportDiff {
+        unchanged,
+        removed: remaining.values().sum(),
+        appended,
+    }
+}
+
+/// The default file name of a single-session export. `qualify_project` puts
+/// the project in the name, for sessions whose short id also exists in
+/// another project. Obsidian notes go in a folder per project instead.
+pub fn default_export_name(
+    doc: &ExportDocument,
+    format: ExportFormat,
+    qualify_project: bool,
+) -> PathBuf {
+    let date = export_date(doc);
+    let ext = format.extension();
+    if matches!(format, ExportFormat::Obsidian) {
+        return Path::new(&doc.project).join(format!("{date}-{}.{ext}", doc.session_short));
+    }
+    let filename = if qualify_project {
+        format!(
+            "cc-convo-{date}-{}-{}.{ext}",
+            doc.project, doc.session_short
+        )
+    } else {
+        format!("cc-convo-{date}-{}.{ext}", doc.session_short)
+    };
+    PathBuf::from(filename)
+}
+
+pub(crate) fn export_date(doc: &ExportDocument) -> &str {
+    doc.modified_iso.split('T').next().unwrap_or("unknown-date")
+}
+
+/// Placeholders a [`NameTemplate`] may use.
+const NAME_PLACEHOLDERS: &[&str] = &["date", "project", "slug", "short_id", "session_id", "index"];
+
+/// A user-chosen path, relative to the output dir, for single-session
+/// exports, such as `{project}/{date}-{short_id}.md`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NameTemplate(String);
+
+impl FromStr for NameTemplate {
+    type Err = anyhow::Error;
+
+    fn from_str(raw: &str) -> Result<Self> {
+        let path = Path::new(raw);
+        if raw.trim().is_empty() || path.is_absolute() {
+            bail!("The name template must be a relative path");
+        }
+        if path
+            .components()
+            .any(|c| !matches!(c, Component::Normal(_)))
+        {
+            bail!("The name template can't use `..` or `.` path components");
+        }
+        let mut rest = raw;
+        while let Some(open) = rest.find('{') {
+            let Some(close) = rest[open..].find('}') else {
+                bail!("Unclosed `{{` in name template {raw:?}");
+            };
+            let name = &rest[open + 1..open + close];
+            if !NAME_PLACEHOLDERS.contains(&name) {
+                bail!(
+                    "Unknown placeholder {{{name}}} in name template; use {}",
+                    NAME_PLACEHOLDERS
+                        .iter()
+                        .map(|p| format!("{{{p}}}"))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                );
+            }
+            rest = &rest[open + close + 1..];
+        }
+        Ok(Self(raw.to_string()))
+    }
+}
+
+impl fmt::Display for NameTemplate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl NameTemplate {
+    /// The export path of `doc` under this template. `index` is the session's
+    /// number in `sessions list`. The format's extension is added unless the
+    /// template already ends with it.
+    pub fn render(&self, doc: &ExportDocument, index: usize, format: ExportFormat) -> PathBuf {
+        let index = index.to_string();
+        let slug = prompt_slug(&doc.events);
+        let value = |name: &str| match name {
+            "date" => export_date(doc),
+            "project" => &doc.project,
+            "slug" => &slug,
+            "short_id" => &doc.session_short,
+            "session_id" => &doc.session_id,
+            _ => &index,
+        };
+        let mut out = String::new();
+        let mut rest = self.0.as_str();
+        while let Some(open) = rest.find('{') {
+            let close = open + rest[open..].find('}').expect("validated");
+            out.push_str(&rest[..open]);
+            // Values become one path component, whatever they hold.
+            out.push_str(&value(&rest[open + 1..close]).replace(['/', '\\'], "-"));
+            rest = &rest[close + 1..];
+        }
+        out.push_str(rest);
+        let ext = format!(".{}", format.extension());
+        if !out.ends_with(&ext) {
+            out.push_str(&ext);
+        }
+        PathBuf::from(out)
+    }
+}
+
+const SLUG_WORDS: usize = 8;
+const SLUG_MAX_LEN: usize = 60;
+
+/// The first prompt's opening words, lowercased and joined by `-`, for file
+/// names; `untitled` if the session has none.
+fn prompt_slug(events: &[NormalizedEvent]) -> String {
+    let prompt = first_prompt(events).unwrap_or_default();
+    let mut slug = String::new();
+    for word in prompt
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .take(SLUG_WORDS)
+    {
+        if slug.len() + word.len() >= SLUG_MAX_LEN {
+            break;
+        }
+        if !slug.is_empty() {
+            slug.push('-');
+        }
+        slug.push_str(&word.to_lowercase());
+    }
+    if slug.is_empty() {
+        slug.push_str("untitled");
+    }
+    slug
+}
+
+/// The text of the session's first typed prompt.
+pub(crate) fn first_prompt(events: &[NormalizedEvent]) -> Option<&str> {
+    events
+        .iter()
+        .find(|e| e.role == "user" && is_prompt(e.content.trim()))
+        .map(|e| e.content.as_str())
+}
+
+/// Write one session to `name` under `output_dir`, creating the directories
+/// `name` nests it in.
+pub fn write_single_export(
+    output_dir: &Path,
+    doc: &ExportDocument,
+    format: ExportFormat,
+    style: ExportStyle,
+    name: &Path,
+) -> Result<PathBuf> {
+    let path = output_dir.join(name);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    let body = match format {
+        ExportFormat::Markdown => render_markdown_with(std::slice::from_ref(doc), style),
+        ExportFormat::Json => serde_json::to_string_pretty(doc)?,
+        ExportFormat::Html => render_html(std::slice::from_ref(doc), style),
+        ExportFormat::QaJson => serde_json::to_string_pretty(&QaDocument::from(doc))?,
+        ExportFormat::QaMarkdown => render_qa_markdown(std::slice::from_ref(doc)),
+        ExportFormat::OpenaiJsonl | ExportFormat::AnthropicMessages => {
+            render_dataset(std::slice::from_ref(doc), format)?
+        }
+        ExportFormat::Obsidian => render_obsidian_note(&VaultNote::new(doc.clone(), name), style),
+    };
+    write_atomic(&path, body)?;
+    Ok(path)
+}
+
+/// Write `docs` to one file, with `shared` (see [`crate::dedupe`]) as an
+/// appendix of markdown and HTML bundles.
+pub fn write_bundle_export(
+    output_dir: &Path,
+    docs: &[ExportDocument],
+    shared: &[SharedBlock],
+    format: ExportFormat,
+    style: ExportStyle,
+) -> Result<PathBuf> {
+    let now = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true);
+    let date = now.split('T').next().unwrap_or("unknown-date");
+    let ext = format.extension();
+    let path = output_dir.join(format!("cc-convo-bundle-{date}.{ext}"));
+    write_atomic(&path, render_bundle(docs, shared, format, style)?)?;
+    Ok(path)
+}
+
+/// The body of a bundle of `docs`, as [`write_bundle_export`] writes it.
+pub fn render_bundle(
+    docs: &[ExportDocument],
+    shared: &[SharedBlock],
+    format: ExportFormat,
+    style: ExportStyle,
+) -> Result<String> {
+    Ok(match format {
+        ExportFormat::Markdown => {
+            let mut out = render_markdown_with(docs, style);
+            push_shared_markdown(&mut out, shared);
+            out
+        }
+        ExportFormat::Json => serde_json::to_string_pretty(docs)?,
+        ExportFormat::Html => render_html_with(docs, shared, style),
+        ExportFormat::QaJson => {
+            serde_json::to_string_pretty(&docs.iter().map(QaDocument::from).collect::<Vec<_>>())?
+        }
+        ExportFormat::QaMarkdown => render_qa_markdown(docs),
+        ExportFormat::OpenaiJsonl | ExportFormat::AnthropicMessages => {
+            render_dataset(docs, format)?
+        }
+        ExportFormat::Obsidian => bail!("Obsidian exports are one note per session"),
+    })
+}
+
+/// An exported file as the index page of a multi-file export lists it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexEntry {
+    /// Where the file is, relative to the output dir.
+    pub path: PathBuf,
+    pub session_id: String,
+    pub project: String,
+    pub modified_iso: String,
+    pub event_count: usize,
+    /// The custom title, else the summary, else the first prompt.
+    pub title: Option<String>,
+    /// Claude Code's summary, when it differs from the title.
+    pub summary: Option<String>,
+    /// The first prompt, when it differs from the title.
+    #[serde(default)]
+    pub preview: Option<String>,
+}
+
+impl IndexEntry {
+    pub fn new(doc: &ExportDocument, path: impl Into<PathBuf>, title: &SessionTitle) -> Self {
+        let summary = title
+            .summary
+            .clone()
+            .filter(|s| Some(s.as_str()) != title.title());
+        let preview = title
+            .first_prompt
+            .as_deref()
+            .filter(|p| Some(*p) != title.title())
+            .map(|p| ellipsize(p, INDEX_PREVIEW_MAX_LEN));
+        Self {
+            path: path.into(),
+            session_id: doc.session_id.clone(),
+            project: doc.project.clone(),
+            modified_iso: doc.modified_iso.clone(),
+            event_count: doc.event_count,
+            title: title.title().map(str::to_string),
+            summary,
+            preview,
+        }
+    }
+
+    fn title(&self) -> String {
+        match &self.title {
+            Some(title) => ellipsize(title, INDEX_TITLE_MAX_LEN),
+            None => self.session_id.clone(),
+        }
+    }
+
+    fn date(&self) -> &str {
+        self.modified_iso
+            .split('T')
+            .next()
+            .unwrap_or("unknown-date")
+    }
+
+    /// The path as a relative URL, `/`-separated on every platform.
+    fn href(&self) -> String {
+        self.path
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().replace(' ', "%20"))
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+}
+
+const INDEX_TITLE_MAX_LEN: usize = 80;
+const INDEX_PREVIEW_MAX_LEN: usize = 160;
+
+/// Write `index.html` (for HTML exports) or `index.md` into `output_dir`,
+/// linking every entry under a heading per project.
+pub fn write_export_index(
+    output_dir: &Path,
+    entries: &[IndexEntry],
+    format: ExportFormat,
+    style: ExportStyle,
+) -> Result<PathBuf> {
+    let (name, body) = match format {
+        ExportFormat::Html => ("index.html", render_index_html(entries, style)),
+        _ => ("index.md", render_index_markdown(entries)),
+    };
+    let path = output_dir.join(name);
+    write_atomic(&path, body)?;
+    Ok(path)
+}
+
+fn render_index_markdown(entries: &[IndexEntry]) -> String {
+    let groups = group_by_project(entries, |e| &e.project);
+    let mut out = format!(
+        "# cc-convo export index\n\n{} in {} project(s).\n",
+        session_count(entries.len()),
+        groups.len()
+    );
+    for (project, entries) in groups {
+        out.push_str(&format!("\n## `{project}`\n\n"));
+        for entry in entries {
+            out.push_str(&format!(
+                "- [{}](<{}>) {}, {} events\n",
+                entry.title().replace('[', "\\[").replace(']', "\\]"),
+                entry.href(),
+                entry.date(),
+                entry.event_count
+            ));
+            if let Some(summary) = &entry.summary {
+                out.push_str(&format!("  {summary}\n"));
+            }
+            if let Some(preview) = &entry.preview {
+                out.push_str(&format!("  Prompt: {preview}\n"));
+            }
+        }
+    }
+    out
+}
+
+fn render_index_html(entries: &[IndexEntry], style: ExportStyle) -> String {
+    let groups = group_by_project(entries, |e| &e.project);
+    let mut body = format!(
+        "<div class=\"meta\">{} in {} project(s).</div>",
+        session_count(entries.len()),
+        groups.len()
+    );
+    for (project, entries) in groups {
+        body.push_str(&format!(
+            "<div class=\"card\"><h2>{}</h2><ul>",
+            html_escape(project)
+        ));
+        for entry in entries {
+            body.push_str(&format!(
+                "<li><a href=\"{}\">{}</a> <span class=\"meta\">{}, {} events</span>",
+                html_escape(&entry.href()),
+                html_escape(&entry.title()),
+                html_escape(entry.date()),
+                entry.event_count
+            ));
+            if let Some(summary) = &entry.summary {
+                body.push_str(&format!(
+                    "<div class=\"meta\">{}</div>",
+                    html_escape(summary)
+                ));
+            }
+            if let Some(preview) = &entry.preview {
+                body.push_str(&format!(
+                    "<div class=\"meta\">Prompt: {}</div>",
+                    html_escape(preview)
+                ));
+            }
+            body.push_str("</li>");
+        }
+        body.push_str("</ul></div>");
+    }
+    html_page("cc-convo export index", &body, style)
+}
+
+pub fn render_markdown(docs: &[ExportDocument]) -> String {
+    render_markdown_with(docs, ExportStyle::default())
+}
+
+pub fn render_markdown_with(docs: &[ExportDocument], style: ExportStyle) -> String {
+    if style.group_by_project {
+        return render_grouped_markdown(docs, style);
+    }
+    if docs.len() > 1 {
+        return render_bundle_markdown(docs, style);
+    }
+    let mut out = String::new();
+    for doc in docs {
+        push_markdown_doc(&mut out, doc, style);
+    }
+    out
+}
+
+fn push_markdown_doc(out: &mut String, doc: &ExportDocument, style: ExportStyle) {
+    out.push_str("# cc-convo export\n\n");
+    if let Some(title) = &doc.title {
+        out.push_str(&format!("- Title: {title}\n"));
+    }
+    out.push_str(&format!("- Session: `{}`\n", doc.session_id));
+    out.push_str(&format!("- Project: `{}`\n", doc.project));
+    out.push_str(&format!("- Modified: `{}`\n", doc.modified_iso));
+    out.push_str(&format!("- Source: `{}`\n", doc.source_path.display()));
+    out.push_str(&format!("- Events: `{}`\n", doc.event_count));
+    if let Some(tree) = &doc.tree {
+        out.push_str(&format!("- Branch points: `{}`\n", tree.branch_points()));
+    }
+    out.push('\n');
+    push_markdown_events(out, doc, style);
+}
+
+/// Sessions after a table of contents linking each of them.
+fn render_bundle_markdown(docs: &[ExportDocument], style: ExportStyle) -> String {
+    let mut out = String::from("# cc-convo bundle\n\n## Contents\n\n");
+    for doc in docs {
+        out.push_str(&format!(
+            "- [{}](#{}) `{}` {}, {} events\n",
+            toc_label(doc).replace('[', "\\[").replace(']', "\\]"),
+            session_anchor(doc),
+            doc.project,
+            doc.modified_iso,
+            doc.event_count
+        ));
+    }
+    for doc in docs {
+        out.push_str(&format!(
+            "\n---\n\n<a id=\"{}\"></a>\n\n",
+            session_anchor(doc)
+        ));
+        push_markdown_doc(&mut out, doc, style);
+    }
+    out
+}
+
+/// Sessions under a heading per project, with a linked table of contents.
+fn render_grouped_markdown(docs: &[ExportDocument], style: ExportStyle) -> String {
+    let groups = project_groups(docs);
+    let mut out = String::from("# cc-convo bundle\n\n## Contents\n\n");
+    for (project, docs) in &groups {
+        out.push_str(&format!(
+            "- [{project}](#{}) ({})\n",
+            project_anchor(project),
+            session_count(docs.len())
+        ));
+        for doc in docs {
+            out.push_str(&format!(
+                "  - [{}](#{}) {}, {} events\n",
+                toc_label(doc).replace('[', "\\[").replace(']', "\\]"),
+                session_anchor(doc),
+                doc.modified_iso,
+                doc.event_count
+            ));
+        }
+    }
+    for (project, docs) in &groups {
+        out.push_str(&format!(
+            "\n---\n\n<a id=\"{}\"></a>\n\n# Project `{project}`\n",
+            project_anchor(project)
+        ));
+        for doc in docs {
+            out.push_str(&format!("\n<a id=\"{}\"></a>\n\n", session_anchor(doc)));
+            push_markdown_doc(&mut out, doc, style);
+        }
+    }
+    out
+}
+
+/// `docs` split by project, projects in order of their first session and
+/// sessions in their given order.
+fn project_groups(docs: &[ExportDocument]) -> Vec<(&str, Vec<&ExportDocument>)> {
+    group_by_project(docs, |doc| &doc.project)
+}
+
+fn group_by_project<T>(items: &[T], project: impl Fn(&T) -> &str) -> Vec<(&str, Vec<&T>)> {
+    let mut groups: Vec<(&str, Vec<&T>)> = Vec::new();
+    for item in items {
+        match groups.iter_mut().find(|(p, _)| *p == project(item)) {
+            Some((_, group)) => group.push(item),
+            None => groups.push((project(item), vec![item])),
+        }
+    }
+    groups
+}
+
+fn session_count(n: usize) -> String {
+    match n {
+        1 => "1 session".to_string(),
+        n => format!("{n} sessions"),
+    }
+}
+
+fn project_anchor(project: &str) -> String {
+    let slug = project
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '-'
+            }
+        })
+        .collect::<String>();
+    format!("project-{}", slug.trim_matches('-'))
+}
+
+fn session_anchor(doc: &ExportDocument) -> String {
+    format!("session-{}", doc.session_id)
+}
+
+/// A session as a table of contents lists it: its title, else its short id.
+fn toc_label(doc: &ExportDocument) -> String {
+    match &doc.title {
+        Some(title) => ellipsize(title, INDEX_TITLE_MAX_LEN),
+        None => doc.session_short.clone(),
+    }
+}
+
+/// The events of `doc` as markdown sections, one per event.
+pub(crate) fn push_markdown_events(out: &mut String, doc: &ExportDocument, style: ExportStyle) {
+    let mut footnotes = Vec::new();
+    for (event, node) in doc.ordered_events() {
+        if let Some(branch) = node.and_then(|n| n.branch) {
+            out.push_str(&format!("> Branch {} of {}\n\n", branch.index, branch.of));
+        }
+        let marker = source_note(event).map(|note| {
+            let label = format!("{}-{}", doc.session_short, footnotes.len() + 1);
+            footnotes.push(format!("[^{label}]: {note}\n"));
+            format!(" [^{label}]")
+        });
+        out.push_str(&format!(
+            "## {} {}{}\n\n",
+            style.role_label(&event.role),
+            event.timestamp.clone().unwrap_or_else(|| "-".to_string()),
+            marker.unwrap_or_default()
+        ));
+        out.push_str(&event.content);
+        out.push_str("\n\n");
+    }
+    if !footnotes.is_empty() {
+        out.push_str(&footnotes.concat());
+        out.push('\n');
+    }
+}
+
+/// Where `event` came from, as "`file` line N (uuid `x`)", when it was
+/// parsed with source refs.
+fn source_note(event: &NormalizedEvent) -> Option<String> {
+    let source = event.source.as_ref()?;
+    let mut note = match &source.file {
+        Some(file) => format!("`{}` line {}", file.display(), source.line),
+        None => format!("line {}", source.line),
+    };
+    if let Some(uuid) = &event.uuid {
+        note.push_str(&format!(" (uuid `{uuid}`)"));
+    }
+    Some(note)
+}
+
+/// A session reduced to its prompt/answer pairs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QaDocument {
+    pub session_id: String,
+    pub project: String,
+    pub modified_iso: String,
+    pub exchanges: Vec<Exchange>,
+}
+
+impl From<&ExportDocument> for QaDocument {
+    fn from(doc: &ExportDocument) -> Self {
+        Self {
+            session_id: doc.session_id.clone(),
+            project: doc.project.clone(),
+            modified_iso: doc.modified_iso.clone(),
+            exchanges: pair_exchanges(&doc.events),
+        }
+    }
+}
+
+pub fn render_qa_markdown(docs: &[ExportDocument]) -> String {
+    let mut out = String::new();
+    for (di, doc) in docs.iter().enumerate() {
+        if di > 0 {
+            out.push_str("\n\n---\n\n");
+        }
+        let qa = QaDocument::from(doc);
+        out.push_str("# cc-convo Q&A\n\n");
+        out.push_str(&format!("- Session: `{}`\n", qa.session_id));
+        out.push_str(&format!("- Project: `{}`\n", qa.project));
+        out.push_str(&format!("- Exchanges: `{}`\n\n", qa.exchanges.len()));
+        for exchange in &qa.exchanges {
+            out.push_str(&format!("## Q: {}\n\n", first_line(&exchange.question)));
+            if exchange.question.contains('\n') {
+                out.push_str(&exchange.question);
+                out.push_str("\n\n");
+            }
+            out.push_str("**A:**\n\n");
+            out.push_str(&exchange.answer);
+            out.push_str("\n\n");
+        }
+    }
+    out
+}
+
+fn first_line(text: &str) -> &str {
+    text.lines().next().unwrap_or_default()
+}
+
+pub fn render_html(docs: &[ExportDocument], style: ExportStyle) -> String {
+    render_html_with(docs, &[], style)
+}
+
+fn render_html_with(docs: &[ExportDocument], shared: &[SharedBlock], style: ExportStyle) -> String {
+    let mut body = String::new();
+    if style.group_by_project {
+        push_grouped_html(&mut body, docs, style);
+    } else if docs.len() > 1 {
+        body.push_str("<nav class=\"card\"><h2>Contents</h2><ul>");
+        for doc in docs {
+            body.push_str(&format!(
+                "<li><a href=\"#{}\">{}</a> <span class=\"meta\">{}, {}, {} events</span></li>",
+                session_anchor(doc),
+                html_escape(&toc_label(doc)),
+                html_escape(&doc.project),
+                html_escape(&doc.modified_iso),
+                doc.event_count
+            ));
+        }
+        body.push_str("</ul></nav>");
+        for doc in docs {
+            push_html_doc(&mut body, doc, style, true);
+        }
+    } else {
+        for doc in docs {
+            push_html_doc(&mut body, doc, style, false);
+        }
+    }
+    push_shared_html(&mut body, shared);
+    html_page("cc-convo export", &body, style)
+}
+
+/// Search hits as a page for review: a card per session, in the order of
+/// its best hit, with matches marked and each hit's whole event folded
+/// under its preview.
+pub fn render_search_html(query: &str, hits: &[SearchHit], style: ExportStyle) -> String {
+    let mut sessions: Vec<(&SearchHit, Vec<&SearchHit>)> = Vec::new();
+    for hit in hits {
+        match sessions
+            .iter_mut()
+            .find(|(first, _)| first.path == hit.path)
+        {
+            Some((_, group)) => group.push(hit),
+            None => sessions.push((hit, vec![hit])),
+        }
+    }
+    let mut body = format!(
+        "<div class=\"meta\">{} hit(s) in {} for <code>{}</code>.</div>",
+        hits.len(),
+        session_count(sessions.len()),
+        html_escape(query)
+    );
+    if sessions.len() > 1 {
+        body.push_str("<nav class=\"card\"><h2>Contents</h2><ul>");
+        for (at, (first, group)) in sessions.iter().enumerate() {
+            body.push_str(&format!(
+                "<li><a href=\"#hits-{}\">{}</a> <span class=\"meta\">{}, {} hit(s)</span></li>",
+                at + 1,
+                html_escape(first.title.as_deref().unwrap_or(&first.session_id)),
+                html_escape(&first.project),
+                group.len()
+            ));
+        }
+        body.push_str("</ul></nav>");
+    }
+    for (at, (first, group)) in sessions.iter().enumerate() {
+        let (heading, session) = match &first.title {
+            Some(title) => (title.as_str(), format!("session={} ", first.session_id)),
+            None => (first.session_id.as_str(), String::new()),
+        };
+        body.push_str(&format!(
+            "<div class=\"card\" id=\"hits-{}\"><h2>{}</h2><div class=\"meta\">{}project={} source={}</div>",
+            at + 1,
+            html_escape(heading),
+            html_escape(&session),
+            html_escape(&first.project),
+            html_escape(&first.path.display().to_string())
+        ));
+        for hit in group {
+            body.push_str(&format!(
+                "<div class=\"card\"><h2 class=\"role-{}\">{} {}</h2><div class=\"meta\">relevance {:.2}</div>",
+                html_escape(&hit.speaker),
+                html_escape(&style.role_label(&hit.speaker)),
+                html_escape(hit.timestamp.as_deref().unwrap_or("-")),
+                hit.relevance
+            ));
+            // The preview is cut and flattened, so mark what matched by text.
+            let matched = hit
+                .matches
+                .iter()
+                .map(|range| hit.content[range.clone()].replace('\n', " "))
+                .collect::<Vec<_>>();
+            let in_preview = matched
+                .iter()
+                .flat_map(|text| hit.preview.match_indices(text.as_str()))
+                .map(|(start, text)| start..start + text.len())
+                .collect();
+            body.push_str(&format!(
+                "<div class=\"content\"><p>{}</p></div><details><summary>Whole message</summary><pre>{}</pre></details></div>",
+                html_marked(&hit.preview, in_preview),
+                html_marked(&hit.content, hit.matches.clone())
+            ));
+        }
+        body.push_str("</div>");
+    }
+    html_page("cc-convo search", &body, style)
+}
+
+/// `text` escaped, with the byte `ranges` in `<mark>`; a range overlapping
+/// one already marked is dropped.
+fn html_marked(text: &str, mut ranges: Vec<std::ops::Range<usize>>) -> String {
+    ranges.sort_by_key(|range| (range.start, std::cmp::Reverse(range.end)));
+    let mut out = String::new();
+    let mut done = 0;
+    for range in ranges {
+        if range.start < done {
+            continue;
+        }
+        out.push_str(&html_escape(&text[done..range.start]));
+        out.push_str(&format!(
+            "<mark>{}</mark>",
+            html_escape(&text[range.clone()])
+        ));
+        done = range.end;
+    }
+    out.push_str(&html_escape(&text[done..]));
+    out
+}
+
+/// A themed page with `title` as its heading.
+fn html_page(title: &str, body: &str, style: ExportStyle) -> String {
+    format!(
+        "<!doctype html><html{}><head><meta charset=\"utf-8\"><title>{title}</title><style>{}</style></head><body>{SCHEME_TOGGLE_HTML}<h1>{title}</h1>{body}</body></html>",
+        style.theme.html_attrs(),
+        style.theme.html_css(body.contains("<pre class=\"hl-code\">"))
+    )
+}
+
+/// One session's header card and event cards; `anchored` gives the header
+/// an id for a table of contents to link to.
+fn push_html_doc(body: &mut String, doc: &ExportDocument, style: ExportStyle, anchored: bool) {
+    if anchored {
+        body.push_str(&format!(
+            "<div class=\"card\" id=\"{}\">",
+            session_anchor(doc)
+        ));
+    } else {
+        body.push_str("<div class=\"card\">");
+    }
+    // A titled session keeps its id in the metadata line.
+    let (heading, session) = match &doc.title {
+        Some(title) => (title.as_str(), format!("session={} ", doc.session_id)),
+        None => (doc.session_id.as_str(), String::new()),
+    };
+    body.push_str(&format!(
+        "<h2>{}</h2><div class=\"meta\">{}project={} modified={} source={} events={}{}</div>",
+        html_escape(heading),
+        html_escape(&session),
+        html_escape(&doc.project),
+        html_escape(&doc.modified_iso),
+        html_escape(&doc.source_path.display().to_string()),
+        doc.event_count,
+        doc.tree
+            .as_ref()
+            .map(|t| format!(" branch_points={}", t.branch_points()))
+            .unwrap_or_default()
+    ));
+    body.push_str("</div>");
+    for (event, node) in doc.ordered_events() {
+        match node {
+            Some(node) if node.depth > 0 => body.push_str(&format!(
+                "<div class=\"card\" style=\"margin-left:{}rem\">",
+                node.depth * 2
+            )),
+            _ => body.push_str("<div class=\"card\">"),
+        }
+        if let Some(branch) = node.and_then(|n| n.branch) {
+            body.push_str(&format!(
+                "<div class=\"meta\">Branch {} of {}</div>",
+                branch.index, branch.of
+            ));
+        }
+        body.push_str(&format!(
+            "<h2 class=\"role-{}\">{} {}</h2>",
+            html_escape(&event.role),
+            html_escape(&style.role_label(&event.role)),
+            html_escape(&event.timestamp.clone().unwrap_or_else(|| "-".to_string())),
+        ));
+        if let Some(note) = source_note(event) {
+            body.push_str(&format!(
+                "<div class=\"meta\">{}</div>",
+                html_escape(&note.replace('`', ""))
+            ));
+        }
+        body.push_str(&format!(
+            "<div class=\"content\">{}</div>",
+            render_content_html(&event.content)
+        ));
+        body.push_str("</div>");
+    }
+}
+
+/// Sessions under a heading per project, after a linked table of contents.
+fn push_grouped_html(body: &mut String, docs: &[ExportDocument], style: ExportStyle) {
+    let groups = project_groups(docs);
+    body.push_str("<nav class=\"card\"><h2>Contents</h2><ul>");
+    for (project, docs) in &groups {
+        body.push_str(&format!(
+            "<li><a href=\"#{}\">{}</a> ({})<ul>",
+            project_anchor(project),
+            html_escape(project),
+            session_count(docs.len())
+        ));
+        for doc in docs {
+            body.push_str(&format!(
+                "<li><a href=\"#{}\">{}</a> <span class=\"meta\">{}, {} events</span></li>",
+                session_anchor(doc),
+                html_escape(&toc_label(doc)),
+                html_escape(&doc.modified_iso),
+                doc.event_count
+            ));
+        }
+        body.push_str("</ul></li>");
+    }
+    body.push_str("</ul></nav>");
+    for (project, docs) in groups {
+        body.push_str(&format!(
+            "<h1 id=\"{}\">Project {}</h1>",
+            project_anchor(project),
+            html_escape(project)
+        ));
+        for doc in docs {
+            push_html_doc(body, doc, style, true);
+        }
+    }
+}
+
+pub fn html_escape(input: &str) -> String {
+    input
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+/// `s` as a YAML scalar: plain where it reads back as the same string,
+/// double-quoted (JSON string syntax is valid YAML) otherwise.
+pub fn yaml_string(s: &str) -> String {
+    let reserved = [
+        "", "~", "null", "true", "false", "yes", "no", "on", "off", "y", "n",
+    ];
+    let plain = !reserved.contains(&s.to_ascii_lowercase().as_str())
+        && s.parse::<f64>().is_err()
+        && !s.starts_with(|c: char| c.is_whitespace() || "-?:,[]{}#&*!|>'\"%@`".contains(c))
+        && !s.ends_with(char::is_whitespace)
+        && !s.contains(": ")
+        && !s.contains(" #")
+        && !s.ends_with(':')
+        && s.chars().all(|c| !c.is_control());
+    if plain {
+        s.to_string()
+    } else {
+        serde_json::to_string(s).expect("strings serialize")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::{parse_session_reader_with, ParseOptions};
+
+    fn doc(contents: &[&str]) -> ExportDocument {
+        let events = contents
+            .iter()
+            .map(|c| NormalizedEvent {
+                role: "user".to_string(),
+                source_type: "user".to_string(),
+                timestamp: None,
+                content: c.to_string(),
+                uuid: None,
+                parent_uuid: None,
+                source: None,
+                model: None,
+            })
+            .collect::<Vec<_>>();
+        ExportDocument {
+            session_id: "s".to_string(),
+            session_short: "s".to_string(),
+            project: "p".to_string(),
+            source_path: PathBuf::from("s.jsonl"),
+            modified_iso: String::new(),
+            event_count: events.len(),
+            events,
+            tree: None,
+            title: None,
+        }
+    }
+
+    #[test]
+    fn grouped_bundles_list_projects_in_first_seen_order() {
+        let in_project = |id: &str, project: &str| ExportDocument {
+            session_id: id.to_string(),
+            session_short: id.to_string(),
+            project: project.to_string(),
+            ..doc(&["hi"])
+        };
+        let docs = [
+            in_project("a", "web"),
+            in_project("b", "api"),
+            in_project("c", "web"),
+        ];
+        let style = ExportStyle {
+            group_by_project: true,
+            ..ExportStyle::default()
+        };
+        let markdown = render_markdown_with(&docs, style);
+        let toc = markdown.split("\n---\n").next().expect("toc");
+        assert!(toc.contains("- [web](#project-web) (2 sessions)\n  - [a](#session-a)"));
+        assert!(toc.contains("- [api](#project-api) (1 session)"));
+        let order = [
+            "# Project `web`",
+            "session-a",
+            "session-c",
+            "# Project `api`",
+            "session-b",
+        ]
+        .map(|needle| markdown.rfind(needle).expect(needle));
+        assert!(order.windows(2).all(|w| w[0] < w[1]));
+
+        let html = render_html(&docs, style);
+        assert!(html.contains("<a href=\"#session-c\">c</a>"));
+        assert!(html.contains("<div class=\"card\" id=\"session-b\">"));
+    }
+
+    #[test]
+    fn index_pages_link_files_by_project() {
+        let entry = |id: &str, project: &str, path: &str, title: SessionTitle| {
+            let doc = ExportDocument {
+                session_id: id.to_string(),
+                project: project.to_string(),
+                modified_iso: "2026-01-05T10:00:00Z".to_string(),
+                ..doc(&["hi"])
+            };
+            IndexEntry::new(&doc, path, &title)
+        };
+        let renamed = SessionTitle {
+            custom_title: Some("Fix <nav>".to_string()),
+            summary: Some("Nav fix".to_string()),
+            ..SessionTitle::default()
+        };
+        let summarized = SessionTitle {
+            summary: Some("Nav fix".to_string()),
+            first_prompt: Some("The nav overlaps the logo".to_string()),
+            ..SessionTitle::default()
+        };
+        let entries = [
+            entry("a", "web", "web/a [draft].html", renamed),
+            entry("b", "api", "b.html", SessionTitle::default()),
+            entry("c", "api", "c.html", summarized),
+        ];
+        assert_eq!(entries[2].summary, None);
+
+        let markdown = render_index_markdown(&entries);
+        assert!(markdown.starts_with("# cc-convo export index\n\n3 sessions in 2 project(s).\n"));
+        assert!(markdown
+            .contains("- [Fix <nav>](<web/a%20[draft].html>) 2026-01-05, 1 events\n  Nav fix\n"));
+        assert!(markdown
+            .contains("## `api`\n\n- [b](<b.html>) 2026-01-05, 1 events\n- [Nav fix](<c.html>)"));
+        assert!(markdown
+            .contains("(<c.html>) 2026-01-05, 1 events\n  Prompt: The nav overlaps the logo\n"));
+
+        let html = render_index_html(&entries, ExportStyle::default());
+        assert!(html.contains("<a href=\"web/a%20[draft].html\">Fix &lt;nav&gt;</a>"));
+        assert!(html.contains("<div class=\"meta\">Nav fix</div>"));
+        assert!(html.contains("<div class=\"meta\">Prompt: The nav overlaps the logo</div>"));
+    }
+
+    #[test]
+    fn bundles_open_with_a_table_of_contents() {
+        let session = |id: &str, title: Option<&str>| ExportDocument {
+            session_id: id.to_string(),
+            session_short: id.to_string(),
+            title: title.map(str::to_string),
+            ..doc(&["hi"])
+        };
+        let docs = [session("a", Some("Fix [nav]")), session("b", None)];
+        let markdown = render_markdown_with(&docs, ExportStyle::default());
+        assert!(markdown.starts_with(
+            "# cc-convo bundle\n\n## Contents\n\n- [Fix \\[nav\\]](#session-a) `p` , 1 events\n- [b](#session-b)"
+        ));
+        assert!(markdown.contains("\n---\n\n<a id=\"session-b\"></a>\n\n# cc-convo export\n"));
+        assert!(!render_markdown(&docs[..1]).contains("Contents"));
+
+        let html = render_html(&docs, ExportStyle::default());
+        assert!(html.contains("<li><a href=\"#session-a\">Fix [nav]</a>"));
+        assert!(html.contains("<div class=\"card\" id=\"session-b\">"));
+    }
+
+    #[test]
+    fn diff_reports_appended_and_removed_events() {
+        let previous = doc(&["a", "ok", "b"]);
+        let current = doc(&["a", "ok", "c", "ok"]);
+        let diff = diff_exports(&previous, &current);
+        assert_eq!(diff.unchanged, 2);
+        assert_eq!(diff.removed, 1);
+        let appended = diff
+            .appended
+            .iter()
+            .map(|e| e.content.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(appended, vec!["c", "ok"]);
+
+        let json = serde_json::to_string(&current).expect("serialize");
+        let parsed: ExportDocument = serde_json::from_str(&json).expect("deserialize");
+        assert_eq!(diff_exports(&parsed, &current).appended.len(), 0);
+    }
+
+    #[test]
+    fn name_templates_fill_placeholders_and_stay_under_the_output_dir() {
+        let mut session = doc(&[
+            "[tool_result] x",
+            "Fix the flaky login/logout test, please!",
+        ]);
+        session.modified_iso = "2026-01-05T10:00:00Z".to_string();
+        session.project = "-home-user-alpha".to_string();
+        let template: NameTemplate = "{project}/{date}-{slug}".parse().expect("template");
+        assert_eq!(
+            template.render(&session, 3, ExportFormat::Markdown),
+            PathBuf::from("-home-user-alpha/2026-01-05-fix-the-flaky-login-logout-test-please.md")
+        );
+        let template: NameTemplate = "{index}-{short_id}.json".parse().expect("template");
+        assert_eq!(
+            template.render(&session, 3, ExportFormat::Json),
+            PathBuf::from("3-s.json")
+        );
+        assert_eq!(
+            "{short_id}"
+                .parse::<NameTemplate>()
+                .expect("template")
+                .render(&doc(&[]), 1, ExportFormat::Html),
+            PathBuf::from("s.html")
+        );
+        for bad in ["/tmp/{short_id}", "../{short_id}", "{title}", "{date", ""] {
+            assert!(
+                bad.parse::<NameTemplate>().is_err(),
+                "{bad:?} should be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn source_refs_become_footnotes() {
+        let jsonl = concat!(
+            "{\"type\":\"user\",\"uuid\":\"u1\",\"message\":{\"role\":\"user\",\"content\":\"Fix it\"}}\n",
+            "\n",
+            "{\"type\":\"assistant\",\"message\":{\"role\":\"assistant\",\"content\":\"Done\"}}\n",
+        );
+        let options = ParseOptions {
+            source_refs: true,
+            ..ParseOptions::default()
+        };
+        let mut session = doc(&[]);
+        session.events = parse_session_reader_with(jsonl.as_bytes(), &options)
+            .expect("parse")
+            .events;
+        session.events[0].source.as_mut().expect("source").file = Some(PathBuf::from("s.jsonl"));
+        let markdown = render_markdown(&[session]);
+        assert!(
+            markdown.contains("## [user] - [^s-1]\n\nFix it\n"),
+            "{markdown}"
+        );
+        assert!(markdown.contains("## [assistant] - [^s-2]\n\nDone\n"));
+        assert!(markdown.ends_with("[^s-1]: `s.jsonl` line 1 (uuid `u1`)\n[^s-2]: line 3\n\n"));
+    }
+
+    #[test]
+    fn search_pages_group_hits_by_session_and_mark_matches() {
+        let hit = |session: &str, content: &str, preview: &str| SearchHit {
+            session_id: session.to_string(),
+            project: "api".to_string(),
+            path: PathBuf::from(format!("{session}.jsonl")),
+            title: None,
+            speaker: "user".to_string(),
+            timestamp: None,
+            relevance: 1.0,
+            preview: preview.to_string(),
+            content: content.to_string(),
+            matches: content
+                .match_indices("cache")
+                .map(|(at, m)| at..at + m.len())
+                .collect(),
+            explanation: None,
+        };
+        let hits = [
+            hit(
+                "s1",
+                "Warm the cache\nbefore <login>",
+                "Warm the cache before <login>",
+            ),
+            hit("s2", "No match here", "No match here"),
+            hit("s1", "...the cache again", "...the cache again"),
+        ];
+        let page = render_search_html("cache", &hits, ExportStyle::default());
+        assert!(page.contains("3 hit(s) in 2 sessions for <code>cache</code>."));
+        assert!(
+            page.contains("<a href=\"#hits-1\">s1</a> <span class=\"meta\">api, 2 hit(s)</span>")
+        );
+        assert!(page.contains(
+            "<p>Warm the <mark>cache</mark> before &lt;login&gt;</p></div><details><summary>Whole message</summary><pre>Warm the <mark>cache</mark>\nbefore &lt;login&gt;</pre>"
+        ));
+        // Hits keep their session's card, after its best hit.
+        let second = page
+            .find("...the <mark>cache</mark> again")
+            .expect("second s1 hit");
+        assert!(second < page.find("id=\"hits-2\"").expect("s2 card"));
+
+        assert_eq!(
+            html_marked("abcd", vec![1..3, 0..2, 3..4]),
+            "<mark>ab</mark>c<mark>d</mark>"
+        );
+    }
+}
@@ -0,0 +1,220 @@
+//! Grouping recurring questions across sessions into an FAQ.
+
+use crate::exchange::Exchange;
+use crate::semantic::{cosine, Embedder};
+use anyhow::{bail, Result};
+use serde::Serialize;
+
+/// A prompt/answer pair and the session it came from.
+#[derive(Debug, Clone)]
+pub struct SourcedExchange {
+    pub session_id: String,
+    pub exchange: Exchange,
+}
+
+/// Questions asked in similar words, with one answer to keep.
+#[derive(Debug, Clone, Serialize)]
+pub struct FaqEntry {
+    /// The asking closest to the group's centroid.
+    pub question: String,
+    pub asked: usize,
+    /// The latest answer, since later sessions tend to reflect the code as it
+    /// is now.
+    pub answer: String,
+    pub answered_at: Option<String>,
+    /// Other wordings of the question, in the order they were asked.
+    pub variants: Vec<String>,
+    pub sessions: Vec<String>,
+}
+
+struct Cluster {
+    centroid: Vec<f32>,
+    members: Vec<usize>,
+}
+
+/// Cluster the questions of `exchanges` greedily: each joins the group whose
+/// centroid is most similar, if at least `similarity`, or starts a new one.
+/// Groups asked fewer than `min_asked` times are dropped; the rest are
+/// ordered by how often they were asked.
+pub fn build_faq(
+    exchanges: &[SourcedExchange],
+    embedder: &mut dyn Embedder,
+    similarity: f32,
+    min_asked: usize,
+) -> Result<Vec<FaqEntry>> {
+    let questions = exchanges
+        .iter()
+        .map(|e| e.exchange.question.as_str())
+        .collect::<Vec<_>>();
+    let vectors = if questions.is_empty() {
+        Vec::new()
+    } else {
+        embedder.embed(&questions)?
+    };
+    if vectors.len() != questions.len() {
+        bail!(
+            "Embedder returned {} vectors for {} questions",
+            vectors.len(),
+            questions.len()
+        );
+    }
+
+    let mut order = (0..exchanges.len()).collect::<Vec<_>>();
+    order.sort_by(|&a, &b| {
+        exchanges[a]
+            .exchange
+            .asked_at
+            .cmp(&exchanges[b].exchange.asked_at)
+    });
+    let mut clusters: Vec<Cluster> = Vec::new();
+    for i in order {
+        let best = clusters
+            .iter()
+            .enumerate()
+            .map(|(c, cluster)| (c, cosine(&cluster.centroid, &vectors[i])))
+            .filter(|(_, score)| *score >= similarity)
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        match best {
+            Some((c, _)) => {
+                let cluster = &mut clusters[c];
+                for (sum, x) in cluster.centroid.iter_mut().zip(&vectors[i]) {
+                    *sum += x;
+                }
+                cluster.members.push(i);
+            }
+            None => clusters.push(Cluster {
+                centroid: vectors[i].clone(),
+                members: vec![i],
+            }),
+        }
+    }
+
+    let mut entries = clusters
+        .into_iter()
+        .filter(|cluster| cluster.members.len() >= min_asked.max(1))
+        .map(|cluster| {
+            let central = *cluster
+                .members
+                .iter()
+                .max_by(|&&a, &&b| {
+                    cosine(&cluster.centroid, &vectors[a])
+                        .total_cmp(&cosine(&cluster.centroid, &vectors[b]))
+                })
+                .expect("clusters have members");
+            let latest = &exchanges[*cluster.members.last().expect("clusters have members")];
+            let mut sessions = Vec::new();
+            for &m in &cluster.members {
+                if !sessions.contains(&exchanges[m].session_id) {
+                    sessions.push(exchanges[m].session_id.clone());
+                }
+            }
+            FaqEntry {
+                question: exchanges[central].exchange.question.clone(),
+                asked: cluster.members.len(),
+                answer: latest.exchange.answer.clone(),
+                answered_at: latest.exchange.answered_at.clone(),
+                variants: cluster
+                    .members
+                    .iter()
+                    .filter(|&&m| m != central)
+                    .map(|&m| exchanges[m].exchange.question.clone())
+                    .collect(),
+                sessions,
+            }
+        })
+        .collect::<Vec<_>>();
+    entries.sort_by_key(|entry| std::cmp::Reverse(entry.asked));
+    Ok(entries)
+}
+
+/// The FAQ as a markdown document titled `title`.
+pub fn render_faq_markdown(title: &str, entries: &[FaqEntry]) -> String {
+    let mut out = format!("# {title}\n\n");
+    if entries.is_empty() {
+        out.push_str("No recurring questions found.\n");
+        return out;
+    }
+    for entry in entries {
+        out.push_str(&format!(
+            "## {}\n\n",
+            entry.question.lines().next().unwrap_or_default()
+        ));
+        let times = match entry.asked {
+            1 => "once".to_string(),
+            n => format!("{n} times"),
+        };
+        out.push_str(&format!(
+            "_Asked {times} in {} session(s)",
+            entry.sessions.len()
+        ));
+        if let Some(at) = &entry.answered_at {
+            out.push_str(&format!("; answer from {at}"));
+        }
+        out.push_str("._\n\n");
+        out.push_str(entry.answer.trim());
+        out.push_str("\n\n");
+        if !entry.variants.is_empty() {
+            out.push_str("<details><summary>Also asked as</summary>\n\n");
+            for variant in &entry.variants {
+                out.push_str(&format!(
+                    "- {}\n",
+                    variant.lines().next().unwrap_or_default()
+                ));
+            }
+            out.push_str("\n</details>\n\n");
+        }
+    }
+    out.truncate(out.trim_end().len());
+    out.push('\n');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::semantic::HashingEmbedder;
+
+    fn sourced(session: &str, question: &str, answer: &str, at: &str) -> SourcedExchange {
+        SourcedExchange {
+            session_id: session.to_string(),
+            exchange: Exchange {
+                question: question.to_string(),
+                answer: answer.to_string(),
+                asked_at: Some(at.to_string()),
+                answered_at: Some(at.to_string()),
+            },
+        }
+    }
+
+    #[test]
+    fn recurring_questions_group_with_the_latest_answer() {
+        let exchanges = vec![
+            sourced(
+                "s1",
+                "How do I run the integration tests?",
+                "old answer",
+                "1",
+            ),
+            sourced("s2", "What's the weather like on Mars?", "cold", "2"),
+            sourced(
+                "s3",
+                "how do I run integration tests locally",
+                "new answer",
+                "3",
+            ),
+        ];
+        let mut embedder = HashingEmbedder::default();
+        let faq = build_faq(&exchanges, &mut embedder, 0.5, 2).expect("faq");
+        assert_eq!(faq.len(), 1);
+        assert_eq!(faq[0].asked, 2);
+        assert_eq!(faq[0].answer, "new answer");
+        assert_eq!(faq[0].sessions, vec!["s1", "s3"]);
+        assert_eq!(faq[0].variants.len(), 1);
+
+        let markdown = render_faq_markdown("FAQ", &faq);
+        assert!(markdown.contains("_Asked 2 times in 2 session(s); answer from 3._"));
+        assert!(build_faq(&[], &mut embedder, 0.5, 2)
+            .expect("empty")
+            .is_empty());
+    }
+}
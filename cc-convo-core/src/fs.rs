@@ -0,0 +1,370 @@
+//! Filesystem helpers shared by every writer of cc-convo state.
+
+use anyhow::{anyhow, Context, Result};
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// `input` with each `%NAME%` replaced by that environment variable, as
+/// Windows writes `%USERPROFILE%\.claude`, and then a leading `~` by the home
+/// directory. Unset variables stay as written.
+pub fn expand_tilde_path(input: &str) -> Result<PathBuf> {
+    expand_path_with(input, &|name| std::env::var(name).ok())
+}
+
+/// The user's home directory: `$HOME`, else `%USERPROFILE%`, else
+/// `%HOMEDRIVE%%HOMEPATH%`.
+pub fn home_dir() -> Result<PathBuf> {
+    home_dir_with(&|name| std::env::var(name).ok())
+}
+
+/// Where Claude Code may keep its project transcripts, most likely first:
+/// `$CLAUDE_CONFIG_DIR/projects` when that is set, `~/.claude/projects` (on
+/// every platform), `$XDG_CONFIG_HOME/claude/projects` or
+/// `~/.config/claude/projects`, on Windows `%APPDATA%\Claude\projects`
+/// and `%LOCALAPPDATA%\Claude\projects`, and under WSL the
+/// `.claude/projects` of each Windows user in [`WSL_USERS_DIR`].
+pub fn claude_dir_candidates() -> Vec<PathBuf> {
+    claude_dir_candidates_with(&|name| std::env::var(name).ok(), Path::new(WSL_USERS_DIR))
+}
+
+/// Where WSL mounts the home directories of the Windows users.
+pub const WSL_USERS_DIR: &str = "/mnt/c/Users";
+
+/// The first of [`claude_dir_candidates`] that is a directory, else the
+/// first candidate.
+pub fn default_claude_dir() -> Result<PathBuf> {
+    let candidates = claude_dir_candidates();
+    candidates
+        .iter()
+        .find(|dir| dir.is_dir())
+        .or(candidates.first())
+        .cloned()
+        .ok_or_else(|| {
+            anyhow!("No home directory to find Claude Code's transcripts in; pass --claude-dir")
+        })
+}
+
+type Env<'a> = &'a dyn Fn(&str) -> Option<String>;
+
+fn expand_path_with(input: &str, env: Env) -> Result<PathBuf> {
+    let input = expand_env_vars(input, env);
+    if input == "~" {
+        return home_dir_with(env);
+    }
+    match input
+        .strip_prefix("~/")
+        .or_else(|| input.strip_prefix("~\\"))
+    {
+        Some(rest) => Ok(home_dir_with(env)?.join(rest)),
+        None => Ok(PathBuf::from(input)),
+    }
+}
+
+fn expand_env_vars(input: &str, env: Env) -> String {
+    let mut out = String::with_capacity(input.len());
+    let mut rest = input;
+    while let Some(start) = rest.find('%') {
+        let after = &rest[start + 1..];
+        let value = after.find('%').and_then(|end| {
+            let name = &after[..end];
+            let valid = !name.is_empty()
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '(' | ')'));
+            valid.then(|| env(name)).flatten().map(|value| (value, end))
+        });
+        out.push_str(&rest[..start]);
+        match value {
+            Some((value, end)) => {
+                out.push_str(&value);
+                rest = &after[end + 1..];
+            }
+            None => {
+                out.push('%');
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn home_dir_with(env: Env) -> Result<PathBuf> {
+    let set = |name: &str| env(name).filter(|value| !value.is_empty());
+    set("HOME")
+        .or_else(|| set("USERPROFILE"))
+        .or_else(|| Some(format!("{}{}", set("HOMEDRIVE")?, set("HOMEPATH")?)))
+        .map(PathBuf::from)
+        .context("Neither HOME nor USERPROFILE is set")
+}
+
+fn claude_dir_candidates_with(env: Env, wsl_users: &Path) -> Vec<PathBuf> {
+    let set = |name: &str| env(name).filter(|value| !value.is_empty());
+    let mut roots = Vec::new();
+    if let Some(dir) = set("CLAUDE_CONFIG_DIR") {
+        roots.push(expand_path_with(&dir, env).unwrap_or_else(|_| PathBuf::from(dir)));
+    }
+    if let Ok(home) = home_dir_with(env) {
+        roots.push(home.join(".claude"));
+        roots.push(match set("XDG_CONFIG_HOME") {
+            Some(config) => PathBuf::from(config).join("claude"),
+            None => home.join(".config").join("claude"),
+        });
+    }
+    for name in ["APPDATA", "LOCALAPPDATA"] {
+        if let Some(dir) = set(name) {
+            roots.push(PathBuf::from(dir).join("Claude"));
+        }
+    }
+    // Claude Code installed on the Windows side of a WSL machine.
+    if set("WSL_DISTRO_NAME").is_some() {
+        roots.extend(
+            windows_homes(wsl_users)
+                .into_iter()
+                .map(|home| home.join(".claude")),
+        );
+    }
+    let mut candidates: Vec<PathBuf> = Vec::new();
+    for dir in roots.into_iter().map(|root| root.join("projects")) {
+        if !candidates.contains(&dir) {
+            candidates.push(dir);
+        }
+    }
+    candidates
+}
+
+/// The user dirs in a Windows `Users` dir, by name, without the shared ones.
+fn windows_homes(users: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(users) else {
+        return Vec::new();
+    };
+    let mut homes = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_ok_and(|t| t.is_dir()))
+        .filter(|entry| {
+            let name = entry.file_name();
+            !["Public", "Default", "Default User", "All Users"]
+                .iter()
+                .any(|shared| name == *shared)
+        })
+        .map(|entry| entry.path())
+        .collect::<Vec<_>>();
+    homes.sort();
+    homes
+}
+
+/// The sidecar lock file guarding the store at `path`: `<name>.lock` beside it.
+pub fn lock_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".lock");
+    path.with_file_name(name)
+}
+
+/// Advisory exclusive lock on a sidecar lock file, released on drop. Every
+/// writer of shared cc-convo state (exports, metadata, caches) takes one so
+/// concurrent invocations wait their turn instead of interleaving writes.
+pub struct FileLock {
+    _file: File,
+}
+
+impl FileLock {
+    /// Take the lock, calling `on_wait` first if another process holds it.
+    pub fn acquire(lock_path: &Path, on_wait: impl FnOnce(&Path)) -> Result<Self> {
+        let file = File::options()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(lock_path)
+            .with_context(|| format!("Failed to open lock file {}", lock_path.display()))?;
+        match file.try_lock() {
+            Ok(()) => {}
+            Err(fs::TryLockError::WouldBlock) => {
+                on_wait(lock_path);
+                file.lock()
+                    .with_context(|| format!("Failed to lock {}", lock_path.display()))?;
+            }
+            Err(fs::TryLockError::Error(err)) => {
+                return Err(anyhow!(err).context(format!("Failed to lock {}", lock_path.display())))
+            }
+        }
+        Ok(Self { _file: file })
+    }
+
+    /// Take the lock if no other process holds it.
+    pub fn try_acquire(lock_path: &Path) -> Result<Option<Self>> {
+        let file = File::options()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(lock_path)
+            .with_context(|| format!("Failed to open lock file {}", lock_path.display()))?;
+        match file.try_lock() {
+            Ok(()) => Ok(Some(Self { _file: file })),
+            Err(fs::TryLockError::WouldBlock) => Ok(None),
+            Err(fs::TryLockError::Error(err)) => {
+                Err(anyhow!(err).context(format!("Failed to lock {}", lock_path.display())))
+            }
+        }
+    }
+}
+
+/// Write `contents` to a temp file beside `path`, sync it, and rename it into
+/// place so readers never see a partially written file.
+pub fn write_atomic(path: &Path, contents: impl AsRef<[u8]>) -> Result<()> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let name = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .ok_or_else(|| anyhow!("Invalid output path {}", path.display()))?;
+    let tmp = dir.join(format!(".{name}.{}.tmp", std::process::id()));
+    let written = File::create(&tmp).and_then(|mut f| {
+        f.write_all(contents.as_ref())?;
+        f.sync_all()
+    });
+    if let Err(err) = written.and_then(|_| fs::rename(&tmp, path)) {
+        let _ = fs::remove_file(&tmp);
+        return Err(anyhow!(err).context(format!("Failed to write {}", path.display())));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::unique_temp_path;
+
+    fn env<'a>(vars: &'a [(&str, &str)]) -> impl Fn(&str) -> Option<String> + 'a {
+        move |name| {
+            vars.iter()
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| value.to_string())
+        }
+    }
+
+    #[test]
+    fn paths_expand_home_and_windows_variables() {
+        let unix = env(&[("HOME", "/home/me")]);
+        assert_eq!(
+            expand_path_with("~/.claude/projects", &unix).expect("expand"),
+            PathBuf::from("/home/me/.claude/projects")
+        );
+        assert_eq!(
+            expand_path_with("~", &unix).expect("expand"),
+            PathBuf::from("/home/me")
+        );
+
+        let windows = env(&[
+            ("USERPROFILE", r"C:\Users\me"),
+            ("APPDATA", r"C:\Users\me\AppData\Roaming"),
+        ]);
+        assert_eq!(
+            expand_path_with(r"%USERPROFILE%\.claude\projects", &windows).expect("expand"),
+            PathBuf::from(r"C:\Users\me\.claude\projects")
+        );
+        assert_eq!(
+            expand_path_with(r"~\.claude", &windows).expect("expand"),
+            PathBuf::from(r"C:\Users\me").join(".claude")
+        );
+        // Unset variables and lone percent signs are left alone.
+        assert_eq!(
+            expand_path_with("%NOPE%/50%/x", &windows).expect("expand"),
+            PathBuf::from("%NOPE%/50%/x")
+        );
+        assert_eq!(
+            home_dir_with(&env(&[("HOMEDRIVE", "D:"), ("HOMEPATH", r"\me")])).expect("home"),
+            PathBuf::from(r"D:\me")
+        );
+        assert!(expand_path_with("~/x", &env(&[])).is_err());
+        assert_eq!(
+            expand_path_with("rel/x", &env(&[])).expect("expand"),
+            PathBuf::from("rel/x")
+        );
+    }
+
+    #[test]
+    fn claude_dir_candidates_cover_each_platform() {
+        let no_wsl = Path::new("/nonexistent");
+        let candidates = claude_dir_candidates_with(
+            &env(&[
+                ("CLAUDE_CONFIG_DIR", "~/work-claude"),
+                ("HOME", "/home/me"),
+                ("APPDATA", "/appdata"),
+            ]),
+            no_wsl,
+        );
+        assert_eq!(
+            candidates,
+            [
+                "/home/me/work-claude/projects",
+                "/home/me/.claude/projects",
+                "/home/me/.config/claude/projects",
+                "/appdata/Claude/projects",
+            ]
+            .map(PathBuf::from)
+        );
+        assert!(claude_dir_candidates_with(&env(&[]), no_wsl).is_empty());
+
+        let users = unique_temp_path("cc-convo-test-wsl-users");
+        for user in ["me", "Public", "ann"] {
+            fs::create_dir_all(users.join(user)).expect("create user dir");
+        }
+        let wsl = env(&[("HOME", "/home/me"), ("WSL_DISTRO_NAME", "Ubuntu")]);
+        let candidates = claude_dir_candidates_with(&wsl, &users);
+        assert_eq!(
+            candidates[2..],
+            [
+                users.join("ann/.claude/projects"),
+                users.join("me/.claude/projects")
+            ]
+        );
+        assert_eq!(
+            claude_dir_candidates_with(&env(&[("HOME", "/home/me")]), &users).len(),
+            2
+        );
+        let _ = fs::remove_dir_all(users);
+    }
+
+    #[test]
+    fn write_atomic_replaces_contents_without_leaving_temp_files() {
+        let dir = unique_temp_path("cc-convo-test-atomic");
+        fs::create_dir_all(&dir).expect("create temp dir");
+        let file = dir.join("export.md");
+        write_atomic(&file, "first").expect("first write");
+        write_atomic(&file, "second").expect("second write");
+        assert_eq!(fs::read_to_string(&file).expect("read back"), "second");
+        let entries = fs::read_dir(&dir).expect("read dir").count();
+        assert_eq!(entries, 1);
+
+        assert!(write_atomic(&dir.join("missing/export.md"), "x").is_err());
+        assert_eq!(fs::read_dir(&dir).expect("read dir").count(), 1);
+
+        fs::remove_dir_all(&dir).expect("cleanup dir");
+    }
+
+    #[test]
+    fn file_lock_excludes_other_handles_until_dropped() {
+        let dir = unique_temp_path("cc-convo-test-lock");
+        fs::create_dir_all(&dir).expect("create temp dir");
+        let lock_path = dir.join("store.lock");
+
+        let held = FileLock::acquire(&lock_path, |_| {}).expect("acquire");
+        let other = File::options()
+            .write(true)
+            .open(&lock_path)
+            .expect("open lock file");
+        assert!(matches!(
+            other.try_lock(),
+            Err(fs::TryLockError::WouldBlock)
+        ));
+        drop(held);
+        other.try_lock().expect("lock is free after drop");
+        other.unlock().expect("unlock");
+        drop(FileLock::acquire(&lock_path, |_| {}).expect("reacquire"));
+
+        fs::remove_dir_all(&dir).expect("cleanup dir");
+    }
+}
@@ -0,0 +1,376 @@
+//! Long-running work over a whole corpus (exporting every session, bringing
+//! the semantic index up to date, backing up transcripts) as jobs whose
+//! progress is saved as they go, so an interrupted run resumes where it
+//! stopped and a job can run detached from the terminal that started it.
+//!
+//! A job lives in `<dir>/<id>.json`. The process running it holds
+//! `<id>.lock`, and `cancel` leaves `<id>.cancel` for that process to notice
+//! between sessions.
+
+use crate::discovery::Session;
+use crate::export::ExportFormat;
+use crate::fs::{write_atomic, FileLock};
+use anyhow::{bail, Context, Result};
+use chrono::{SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// What a job does with each of its sessions.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum JobSpec {
+    /// Write one export file per session into `output`.
+    ExportAll {
+        output: PathBuf,
+        format: ExportFormat,
+        detailed: bool,
+    },
+    /// Embed sessions that are new or changed into the index at `index`.
+    Reindex {
+        index: PathBuf,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        embed_url: Option<String>,
+        embed_model: String,
+    },
+    /// Copy transcripts into `output`, keeping their layout under the Claude dir.
+    Backup { output: PathBuf },
+}
+
+impl JobSpec {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::ExportAll { .. } => "export-all",
+            Self::Reindex { .. } => "reindex",
+            Self::Backup { .. } => "backup",
+        }
+    }
+
+    /// The directory or file the job writes.
+    pub fn target(&self) -> &Path {
+        match self {
+            Self::ExportAll { output, .. } | Self::Backup { output } => output,
+            Self::Reindex { index, .. } => index,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum JobState {
+    /// Created, not started yet.
+    Pending,
+    Running,
+    /// Stopped before finishing, by Ctrl-C or because its process died;
+    /// resumable.
+    Interrupted,
+    Cancelled,
+    Done,
+    /// Stopped by an error; resumable once the cause is fixed.
+    Failed,
+}
+
+impl JobState {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Running => "running",
+            Self::Interrupted => "interrupted",
+            Self::Cancelled => "cancelled",
+            Self::Done => "done",
+            Self::Failed => "failed",
+        }
+    }
+}
+
+/// One job and how far it got.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Job {
+    pub id: u64,
+    #[serde(flatten)]
+    pub spec: JobSpec,
+    pub state: JobState,
+    pub created: String,
+    pub updated: String,
+    pub claude_dir: PathBuf,
+    /// Qualified ids of the sessions selected when the job was created.
+    pub sessions: Vec<String>,
+    /// How many of `sessions` are finished.
+    pub done: usize,
+    /// Sessions that were gone from the Claude dir when their turn came.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub missing: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Of the process that last ran the job.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pid: Option<u32>,
+    /// Where a detached run writes its output.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub log: Option<PathBuf>,
+}
+
+impl Job {
+    /// Sessions still to do, in order.
+    pub fn remaining(&self) -> &[String] {
+        &self.sessions[self.done.min(self.sessions.len())..]
+    }
+
+    /// Whether resuming the job would do nothing.
+    pub fn is_finished(&self) -> bool {
+        matches!(self.state, JobState::Done | JobState::Cancelled)
+    }
+
+    pub fn touch(&mut self) {
+        self.updated = now();
+    }
+}
+
+fn now() -> String {
+    Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// The directory of job files.
+#[derive(Debug, Clone)]
+pub struct JobStore {
+    dir: PathBuf,
+}
+
+impl JobStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn path(&self, id: u64, extension: &str) -> PathBuf {
+        self.dir.join(format!("{id}.{extension}"))
+    }
+
+    pub fn log_path(&self, id: u64) -> PathBuf {
+        self.path(id, "log")
+    }
+
+    /// Record a new pending job over `sessions`, numbered after the last one.
+    pub fn create(&self, spec: JobSpec, claude_dir: &Path, sessions: Vec<String>) -> Result<Job> {
+        fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create {}", self.dir.display()))?;
+        let _lock = FileLock::acquire(&self.dir.join("jobs.lock"), |_| {})?;
+        let id = self.ids()?.last().map_or(1, |last| last + 1);
+        let created = now();
+        let job = Job {
+            id,
+            spec,
+            state: JobState::Pending,
+            updated: created.clone(),
+            created,
+            claude_dir: claude_dir.to_path_buf(),
+            sessions,
+            done: 0,
+            missing: Vec::new(),
+            error: None,
+            pid: None,
+            log: None,
+        };
+        self.save(&job)?;
+        Ok(job)
+    }
+
+    pub fn save(&self, job: &Job) -> Result<()> {
+        write_atomic(
+            &self.path(job.id, "json"),
+            serde_json::to_string_pretty(job)?,
+        )
+    }
+
+    /// The job numbered `id`. One left `running` by a process that no longer
+    /// holds its lock reads as interrupted.
+    pub fn load(&self, id: u64) -> Result<Job> {
+        let path = self.path(id, "json");
+        let raw = match fs::read_to_string(&path) {
+            Ok(raw) => raw,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                bail!("No job {id} in {}", self.dir.display())
+            }
+            Err(err) => {
+                return Err(err).with_context(|| format!("Failed to read {}", path.display()))
+            }
+        };
+        let mut job: Job = serde_json::from_str(&raw)
+            .with_context(|| format!("Invalid job file {}", path.display()))?;
+        if job.state == JobState::Running && !self.is_running(id)? {
+            job.state = JobState::Interrupted;
+        }
+        Ok(job)
+    }
+
+    /// Every job, oldest first; a missing directory has none.
+    pub fn list(&self) -> Result<Vec<Job>> {
+        self.ids()?.into_iter().map(|id| self.load(id)).collect()
+    }
+
+    fn ids(&self) -> Result<Vec<u64>> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => {
+                return Err(err).with_context(|| format!("Failed to read {}", self.dir.display()))
+            }
+        };
+        let mut ids = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().is_some_and(|e| e == "json") {
+                if let Some(id) = path.file_stem().and_then(|s| s.to_str()?.parse().ok()) {
+                    ids.push(id);
+                }
+            }
+        }
+        ids.sort_unstable();
+        Ok(ids)
+    }
+
+    /// Take the lock that marks job `id` as being run, or `None` if another
+    /// process is running it.
+    pub fn claim(&self, id: u64) -> Result<Option<FileLock>> {
+        FileLock::try_acquire(&self.path(id, "lock"))
+    }
+
+    pub fn is_running(&self, id: u64) -> Result<bool> {
+        if !self.path(id, "lock").exists() {
+            return Ok(false);
+        }
+        Ok(self.claim(id)?.is_none())
+    }
+
+    /// Ask the process running job `id` to stop after its current session.
+    pub fn request_cancel(&self, id: u64) -> Result<()> {
+        write_atomic(&self.path(id, "cancel"), now())
+    }
+
+    pub fn cancel_requested(&self, id: u64) -> bool {
+        self.path(id, "cancel").exists()
+    }
+
+    pub fn clear_cancel(&self, id: u64) -> Result<()> {
+        let path = self.path(id, "cancel");
+        match fs::remove_file(&path) {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+                Err(err).with_context(|| format!("Failed to remove {}", path.display()))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Copy the files of `session` into `output` at their paths relative to
+/// `claude_dir`, skipping copies already the same size (transcripts only
+/// grow). Returns the bytes copied.
+pub fn backup_session(claude_dir: &Path, session: &Session, output: &Path) -> Result<u64> {
+    let mut copied = 0;
+    for path in session.paths() {
+        let relative = path.strip_prefix(claude_dir).unwrap_or(path);
+        let relative = relative.strip_prefix("/").unwrap_or(relative);
+        let target = output.join(relative);
+        let size = fs::metadata(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?
+            .len();
+        if fs::metadata(&target).is_ok_and(|m| m.len() == size) {
+            continue;
+        }
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        let bytes = fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        write_atomic(&target, &bytes)?;
+        copied += bytes.len() as u64;
+    }
+    Ok(copied)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::{session_at, unique_temp_path};
+
+    #[test]
+    fn jobs_are_numbered_and_read_as_interrupted_once_their_runner_is_gone() {
+        let root = unique_temp_path("cc-convo-test-jobs");
+        let store = JobStore::new(root.join("jobs"));
+        assert!(store.list().expect("empty list").is_empty());
+
+        let spec = JobSpec::Backup {
+            output: root.join("backup"),
+        };
+        let sessions = vec!["p/a".to_string(), "p/b".to_string()];
+        let first = store
+            .create(spec.clone(), &root, sessions.clone())
+            .expect("create");
+        let mut second = store.create(spec, &root, sessions).expect("create");
+        assert_eq!((first.id, second.id), (1, 2));
+        assert_eq!(second.state, JobState::Pending);
+
+        let lock = store.claim(2).expect("claim").expect("free");
+        assert!(store.claim(2).expect("claim").is_none());
+        second.state = JobState::Running;
+        second.done = 1;
+        store.save(&second).expect("save");
+        assert_eq!(store.load(2).expect("load").state, JobState::Running);
+        assert_eq!(store.load(2).expect("load").remaining(), ["p/b"]);
+        drop(lock);
+        assert_eq!(store.load(2).expect("load").state, JobState::Interrupted);
+
+        assert!(!store.cancel_requested(1));
+        store.request_cancel(1).expect("cancel");
+        assert!(store.cancel_requested(1));
+        store.clear_cancel(1).expect("clear");
+        store.clear_cancel(1).expect("clear again");
+        assert!(!store.cancel_requested(1));
+
+        let ids = store
+            .list()
+            .expect("list")
+            .iter()
+            .map(|j| j.id)
+            .collect::<Vec<_>>();
+        assert_eq!(ids, [1, 2]);
+        assert!(store.load(3).is_err());
+        fs::remove_dir_all(&root).expect("cleanup dir");
+    }
+
+    #[test]
+    fn backups_copy_only_files_that_grew() {
+        let root = unique_temp_path("cc-convo-test-backup");
+        let claude_dir = root.join("projects");
+        fs::create_dir_all(claude_dir.join("p")).expect("create project");
+        let path = claude_dir.join("p/a.jsonl");
+        fs::write(&path, "{}\n").expect("write transcript");
+        let session = Session {
+            size_bytes: 3,
+            ..session_at("p", "a", &path)
+        };
+        let output = root.join("backup");
+
+        assert_eq!(
+            backup_session(&claude_dir, &session, &output).expect("backup"),
+            3
+        );
+        assert_eq!(
+            backup_session(&claude_dir, &session, &output).expect("again"),
+            0
+        );
+        fs::write(&path, "{}\n{}\n").expect("append");
+        assert_eq!(
+            backup_session(&claude_dir, &session, &output).expect("grown"),
+            6
+        );
+        assert_eq!(
+            fs::read_to_string(output.join("p/a.jsonl")).expect("copy"),
+            "{}\n{}\n"
+        );
+        fs::remove_dir_all(&root).expect("cleanup dir");
+    }
+}
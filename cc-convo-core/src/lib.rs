@@ -0,0 +1,129 @@
+//! Discovery, parsing, search, and export rendering for Claude Code transcripts.
+//!
+//! This is the engine behind the `cc-convo` CLI, usable on its own to embed
+//! conversation extraction in other tools:
+//!
+//! ```no_run
+//! use cc_convo_core::{discover_sessions, parse_session_events, render_markdown};
+//! use cc_convo_core::{build_export_document, TimeWindow};
+//!
+//! # fn main() -> anyhow::Result<()> {
+//! let claude_dir = cc_convo_core::default_claude_dir()?;
+//! let sessions = discover_sessions(&claude_dir, &TimeWindow::default())?;
+//! if let Some(latest) = sessions.first() {
+//!     let parsed = parse_session_events(&latest.path, false)?;
+//!     let doc = build_export_document(latest, &parsed.events);
+//!     println!("{}", render_markdown(&[doc]));
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod align;
+pub mod analysis;
+pub mod api_errors;
+pub mod assets;
+pub mod cache;
+pub mod chatgpt;
+pub mod codex;
+pub mod compress;
+pub mod dataset;
+pub mod dedupe;
+pub mod discovery;
+pub mod encrypt;
+pub mod exchange;
+pub mod export;
+pub mod faq;
+pub mod fs;
+pub mod jobs;
+pub mod markup;
+pub mod obsidian;
+pub mod parse;
+pub mod paste;
+pub mod prefilter;
+pub mod query;
+pub mod record;
+pub mod redact;
+pub mod schema;
+pub mod search;
+pub mod semantic;
+pub mod split;
+pub mod stitch;
+pub mod tags;
+pub mod text;
+pub mod theme;
+pub mod thread;
+pub mod title;
+pub mod tools;
+pub mod usage;
+
+#[cfg(test)]
+mod testutil;
+
+pub use align::{align_events, diff_lines, AlignedEvent, DiffOp};
+pub use analysis::{Analysis, Language};
+pub use api_errors::{ApiError, ApiErrorKind, Retry};
+pub use assets::{write_assets, Asset, ASSETS_DIR};
+pub use cache::{FileStamp, MetadataCache};
+pub use chatgpt::{convert_conversations, read_chatgpt_export, ChatgptImport, ImportedSession};
+pub use codex::{convert_rollout, read_codex_sessions, CodexImport, CODEX_SESSIONS_DIR};
+pub use compress::{
+    compress_transcript, is_transcript, open_transcript, transcript_stem, Compression,
+};
+pub use dataset::{
+    anthropic_example, is_validation, openai_example, render_dataset, write_dataset_split,
+};
+pub use dedupe::{dedupe_content, Deduped, SharedBlock, SHARED_BLOCK_MIN_BYTES};
+pub use discovery::{
+    discover, discover_sessions, discover_with, ends_mid_turn, find_session, resolve_target,
+    session_end, short_id, Discovery, Session, SessionEnd, SkippedPath, TimeWindow, WalkLimits,
+    WindowBy, DEFAULT_ACTIVE_WITHIN_SECS, DEFAULT_MAX_DEPTH, MID_TURN_GRACE_SECS,
+};
+pub use encrypt::{
+    is_passphrase_encrypted, parse_recipient, with_age_extension, Decryption, Encryption,
+    StagingDir, AGE_EXTENSION,
+};
+pub use exchange::{pair_exchanges, Exchange};
+pub use export::{
+    build_export_document, default_export_name, diff_exports, html_escape, render_bundle,
+    render_html, render_markdown, render_markdown_with, render_qa_markdown, render_search_html,
+    write_bundle_export, write_export_index, write_single_export, yaml_string, ExportDiff,
+    ExportDocument, ExportFormat, ExportStyle, IndexEntry, NameTemplate, QaDocument,
+};
+pub use faq::{build_faq, render_faq_markdown, FaqEntry, SourcedExchange};
+pub use fs::{
+    claude_dir_candidates, default_claude_dir, expand_tilde_path, home_dir, lock_path,
+    write_atomic, FileLock, WSL_USERS_DIR,
+};
+pub use jobs::{backup_session, Job, JobSpec, JobState, JobStore};
+pub use markup::drop_content_blocks;
+pub use obsidian::{write_obsidian_vault, VaultNote};
+pub use parse::{
+    extract_content_text, extract_message_text, jsonl_records, parse_session_events,
+    parse_session_events_with, parse_session_reader, parse_session_reader_with, NormalizedEvent,
+    ParseDiagnostic, ParseErrorKind, ParseOptions, ParseOutput, SourceRef, UnknownRecords,
+    KNOWN_RECORD_TYPES, MAX_PARSE_DIAGNOSTICS,
+};
+pub use paste::{detect_pastes, fold_pastes, PasteKind, PastedBlock, MIN_PASTE_LINES};
+pub use prefilter::{PrefilterStage, SessionFilter, MIN_RECORD_BYTES, PEEK_BYTES};
+pub use query::{Query, QueryField};
+pub use record::{
+    ContentBlock, ContentItem, MediaSource, Message, MessageContent, Record, RecordBody, RecordHead,
+};
+pub use redact::Redactor;
+pub use schema::{record_field_paths, SchemaBaseline, SchemaDrift, SchemaInventory};
+pub use search::{
+    build_context_preview, search_sessions, search_sessions_explained, search_sessions_with,
+    ExplainedSearch, Explanation, ScoreComponent, SearchHit, SearchMode, SearchOptions,
+    SpeakerFilter, SMART_CUTOFF,
+};
+pub use semantic::{Embedder, HashingEmbedder, VectorIndex};
+pub use split::{split_by_gap, split_by_topic, split_document, SplitBy};
+pub use stitch::{parse_session, parse_session_with, record_session_ids, StitchStore};
+pub use tags::TagStore;
+pub use text::{clean_preview, clean_preview_to, ellipsize, wrap_words, PREVIEW_WIDTH};
+pub use theme::{RoleIcons, Theme};
+pub use thread::{Branch, ConversationTree, ThreadNode};
+pub use title::{find_by_title, SessionTitle};
+pub use tools::{command_names, path_matches, tool_calls, tool_results, ToolCall, ToolResult};
+pub use usage::{session_usage, ModelPrice, Pricing, TokenUsage, UsageScan};
@@ -0,0 +1,316 @@
+//! HTML for message content: markdown with highlighted code fences, and
+//! tool calls, tool results, and thinking folded into `<details>`.
+
+use crate::export::html_escape;
+use crate::theme::HIGHLIGHT_CLASSES;
+use pulldown_cmark::{html, CodeBlockKind, Event, Options, Parser, Tag, TagEnd};
+use std::sync::OnceLock;
+use syntect::html::ClassedHTMLGenerator;
+use syntect::parsing::SyntaxSet;
+use syntect::util::LinesWithEndings;
+
+/// A piece of an event's content as [`crate::parse`] flattens it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ContentBlock<'a> {
+    Text(&'a str),
+    /// `[thinking]` followed by the reasoning text.
+    Thinking(&'a str),
+    /// `[tool_use] <name>` followed by the pretty-printed input.
+    ToolUse {
+        name: &'a str,
+        input: &'a str,
+    },
+    /// `[tool_result] <tool_use_id>` followed by the (truncated) output.
+    ToolResult {
+        id: &'a str,
+        output: &'a str,
+    },
+}
+
+/// Split flattened content back into its blocks. A block runs until the next
+/// marker line; a tool call also ends at the `}` closing its input, since
+/// text can follow it in the same message.
+pub fn split_content_blocks(content: &str) -> Vec<ContentBlock<'_>> {
+    let mut blocks = Vec::new();
+    let mut rest = content;
+    while !rest.is_empty() {
+        let (head, body) = rest.split_once('\n').unwrap_or((rest, ""));
+        if head == "[thinking]" {
+            let (block, next) = until_marker(body);
+            blocks.push(ContentBlock::Thinking(block));
+            rest = next;
+        } else if let Some(name) = head.strip_prefix("[tool_use] ") {
+            let closes_at = |i: usize| body[i..].is_empty() || body[i..].starts_with('\n');
+            let end = if !body.starts_with('{') {
+                None
+            } else if body.starts_with("{}") && closes_at(2) {
+                Some(2)
+            } else {
+                body.match_indices("\n}")
+                    .map(|(i, _)| i + 2)
+                    .find(|&i| closes_at(i))
+            };
+            let (input, next) = match end {
+                Some(i) => (&body[..i], body[i..].strip_prefix('\n').unwrap_or("")),
+                None => until_marker(body),
+            };
+            blocks.push(ContentBlock::ToolUse { name, input });
+            rest = next;
+        } else if let Some(id) = head.strip_prefix("[tool_result] ") {
+            let (output, next) = until_marker(body);
+            blocks.push(ContentBlock::ToolResult { id, output });
+            rest = next;
+        } else {
+            let (text, next) = until_marker(rest);
+            blocks.push(ContentBlock::Text(text));
+            rest = next;
+        }
+    }
+    blocks
+}
+
+/// `content` without tool calls and results (`tools`) or thinking
+/// (`thinking`), flattened again.
+pub fn drop_content_blocks(content: &str, tools: bool, thinking: bool) -> String {
+    split_content_blocks(content)
+        .into_iter()
+        .filter_map(|block| match block {
+            ContentBlock::Text(text) => Some(text.to_string()),
+            ContentBlock::Thinking(text) => (!thinking).then(|| format!("[thinking]\n{text}")),
+            ContentBlock::ToolUse { name, input } => {
+                (!tools).then(|| format!("[tool_use] {name}\n{input}"))
+            }
+            ContentBlock::ToolResult { id, output } => {
+                (!tools).then(|| format!("[tool_result] {id}\n{output}"))
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn is_marker(line: &str) -> bool {
+    line == "[thinking]" || line.starts_with("[tool_use] ") || line.starts_with("[tool_result] ")
+}
+
+/// `text` up to the first marker line, and the rest starting at it.
+fn until_marker(text: &str) -> (&str, &str) {
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        if offset > 0 && is_marker(line.trim_end_matches('\n')) {
+            return (text[..offset].trim_end_matches('\n'), &text[offset..]);
+        }
+        offset += line.len();
+    }
+    (text.trim_end_matches('\n'), "")
+}
+
+/// Event content as HTML: text blocks as markdown, the others collapsed.
+pub fn render_content_html(content: &str) -> String {
+    let mut out = String::new();
+    for block in split_content_blocks(content) {
+        match block {
+            ContentBlock::Text(text) => out.push_str(&markdown_to_html(text)),
+            ContentBlock::Thinking(text) => {
+                out.push_str("<details class=\"thinking\"><summary>Thinking</summary>");
+                out.push_str(&markdown_to_html(text));
+                out.push_str("</details>");
+            }
+            ContentBlock::ToolUse { name, input } => {
+                out.push_str(&format!(
+                    "<details class=\"tool\"><summary>Tool call: {}</summary>{}</details>",
+                    html_escape(name),
+                    highlight_code(input, "json")
+                ));
+            }
+            ContentBlock::ToolResult { id, output } => {
+                out.push_str(&format!(
+                    "<details class=\"tool\"><summary>Tool result <span class=\"meta\">{}</span></summary><pre>{}</pre></details>",
+                    html_escape(id),
+                    html_escape(output)
+                ));
+            }
+        }
+    }
+    out
+}
+
+/// CommonMark with tables and strikethrough. Raw HTML in the text is shown
+/// escaped rather than passed through, since transcripts are untrusted.
+pub fn markdown_to_html(text: &str) -> String {
+    let options = Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH;
+    let mut fence: Option<(String, String)> = None;
+    // Whether each open link or image was kept, to match its end.
+    let mut links = Vec::new();
+    let mut events = Vec::new();
+    for event in Parser::new_ext(text, options) {
+        match event {
+            Event::Start(Tag::CodeBlock(kind)) => {
+                let lang = match kind {
+                    CodeBlockKind::Fenced(info) => {
+                        info.split_whitespace().next().unwrap_or("").to_string()
+                    }
+                    CodeBlockKind::Indented => String::new(),
+                };
+                fence = Some((lang, String::new()));
+            }
+            Event::End(TagEnd::CodeBlock) => {
+                if let Some((lang, code)) = fence.take() {
+                    events.push(Event::Html(highlight_code(&code, &lang).into()));
+                }
+            }
+            Event::Text(text) if fence.is_some() => {
+                if let Some((_, code)) = fence.as_mut() {
+                    code.push_str(&text);
+                }
+            }
+            Event::Html(raw) | Event::InlineHtml(raw) => events.push(Event::Text(raw)),
+            // Transcripts are untrusted: links keep only web and mail targets,
+            // and images become links so opening an export fetches nothing.
+            Event::Start(Tag::Link {
+                link_type,
+                dest_url,
+                title,
+                id,
+            }) => {
+                let keep = is_safe_url(&dest_url, true);
+                links.push(keep);
+                if keep {
+                    events.push(Event::Start(Tag::Link {
+                        link_type,
+                        dest_url,
+                        title,
+                        id,
+                    }));
+                }
+            }
+            Event::Start(Tag::Image {
+                link_type,
+                dest_url,
+                title,
+                id,
+            }) => {
+                let keep = is_safe_url(&dest_url, false);
+                links.push(keep);
+                if keep {
+                    events.push(Event::Start(Tag::Link {
+                        link_type,
+                        dest_url,
+                        title,
+                        id,
+                    }));
+                }
+            }
+            Event::End(TagEnd::Link | TagEnd::Image) => {
+                if links.pop().unwrap_or(false) {
+                    events.push(Event::End(TagEnd::Link));
+                }
+            }
+            other => events.push(other),
+        }
+    }
+    let mut out = String::new();
+    html::push_html(&mut out, events.into_iter());
+    out
+}
+
+/// Whether `url` is an http or https address, or with `mailto` a mail one.
+fn is_safe_url(url: &str, mailto: bool) -> bool {
+    let url = url.trim_start().to_ascii_lowercase();
+    url.starts_with("https://")
+        || url.starts_with("http://")
+        || (mailto && url.starts_with("mailto:"))
+}
+
+/// `code` as a `<pre class="hl-code">` with highlighting classes, which
+/// [`crate::theme::Theme::html_css`] colors, or plain and escaped when `lang`
+/// isn't a known syntax name or file extension.
+pub fn highlight_code(code: &str, lang: &str) -> String {
+    let syntaxes = syntaxes();
+    let syntax = (!lang.is_empty())
+        .then(|| syntaxes.find_syntax_by_token(lang))
+        .flatten();
+    let highlighted = syntax.and_then(|syntax| {
+        let mut html =
+            ClassedHTMLGenerator::new_with_class_style(syntax, syntaxes, HIGHLIGHT_CLASSES);
+        for line in LinesWithEndings::from(code) {
+            html.parse_html_for_line_which_includes_newline(line).ok()?;
+        }
+        Some(html.finalize())
+    });
+    match highlighted {
+        Some(highlighted) => format!("<pre class=\"hl-code\">{highlighted}</pre>"),
+        None => format!("<pre><code>{}</code></pre>", html_escape(code)),
+    }
+}
+
+fn syntaxes() -> &'static SyntaxSet {
+    static LOADED: OnceLock<SyntaxSet> = OnceLock::new();
+    LOADED.get_or_init(SyntaxSet::load_defaults_newlines)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_recovers_flattened_blocks() {
+        let content = "Let me look.\n[tool_use] Bash\n{\n  \"command\": \"ls\"\n}\nThen this.\n[tool_result] toolu_1\nsrc\nCargo.toml\n[thinking]\nhmm";
+        assert_eq!(
+            split_content_blocks(content),
+            vec![
+                ContentBlock::Text("Let me look."),
+                ContentBlock::ToolUse {
+                    name: "Bash",
+                    input: "{\n  \"command\": \"ls\"\n}",
+                },
+                ContentBlock::Text("Then this."),
+                ContentBlock::ToolResult {
+                    id: "toolu_1",
+                    output: "src\nCargo.toml",
+                },
+                ContentBlock::Thinking("hmm"),
+            ]
+        );
+        assert_eq!(
+            drop_content_blocks(content, true, false),
+            "Let me look.\nThen this.\n[thinking]\nhmm"
+        );
+        assert_eq!(drop_content_blocks(content, false, false), content);
+    }
+
+    #[test]
+    fn markdown_escapes_raw_html_and_highlights_fences() {
+        let html = markdown_to_html(
+            "**bold** <script>alert(1)</script>\n\n```rust\nfn main() {}\n```\n\n```nope\n<b>\n```",
+        );
+        assert!(html.contains("<strong>bold</strong>"));
+        assert!(html.contains("&lt;script&gt;"));
+        assert!(!html.contains("<script>"));
+        assert!(
+            html.contains("<pre class=\"hl-code\"><span class=\"hl-source hl-rust\">"),
+            "rust fence is highlighted"
+        );
+        assert!(html.contains("<pre><code>&lt;b&gt;\n</code></pre>"));
+
+        let folded = render_content_html("[tool_result] t1\n<done>");
+        assert!(folded.starts_with("<details class=\"tool\">"));
+        assert!(folded.contains("<pre>&lt;done&gt;</pre>"));
+    }
+
+    #[test]
+    fn markdown_links_keep_only_web_and_mail_targets() {
+        let html = markdown_to_html(
+            "[docs](javascript:alert(document.domain)) [site](https://example.com/a) \
+             [mail](mailto:a@example.com) [file](file:///etc/passwd) \
+             ![t](https://evil.example/p.png) ![d](data:image/png;base64,AAAA)",
+        );
+        assert!(!html.contains("javascript:"), "{html}");
+        assert!(html.contains("docs"), "{html}");
+        assert!(html.contains("<a href=\"https://example.com/a\">site</a>"));
+        assert!(html.contains("<a href=\"mailto:a@example.com\">mail</a>"));
+        assert!(!html.contains("file://"), "{html}");
+        assert!(!html.contains("<img"), "{html}");
+        assert!(html.contains("<a href=\"https://evil.example/p.png\">t</a>"));
+        assert!(!html.contains("data:"), "{html}");
+    }
+}
@@ -0,0 +1,302 @@
+//! Exports as an Obsidian vault: a note per session with YAML frontmatter,
+//! wiki-links between a session and the one it continues, and an index note
+//! per project listing its sessions.
+
+use crate::export::{
+    export_date, first_prompt, push_markdown_events, yaml_string, ExportDocument, ExportStyle,
+};
+use crate::fs::write_atomic;
+use crate::text::clean_preview;
+use anyhow::{Context, Result};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Tag every note carries, so exported sessions can be found in the vault.
+const VAULT_TAG: &str = "cc-convo";
+
+const TITLE_MAX_LEN: usize = 60;
+
+/// One session's note.
+#[derive(Debug, Clone)]
+pub struct VaultNote {
+    pub doc: ExportDocument,
+    /// Path of the note relative to the vault, ending in `.md`.
+    pub name: PathBuf,
+    /// Tags from `sessions tag`.
+    pub tags: Vec<String>,
+    /// Models that answered, in order of first reply.
+    pub models: Vec<String>,
+}
+
+impl VaultNote {
+    pub fn new(doc: ExportDocument, name: impl Into<PathBuf>) -> Self {
+        Self {
+            doc,
+            name: name.into(),
+            tags: Vec::new(),
+            models: Vec::new(),
+        }
+    }
+
+    /// The session's title or else its first prompt, cut short, or the
+    /// session's short id.
+    fn title(&self) -> String {
+        self.doc
+            .title
+            .as_deref()
+            .or_else(|| first_prompt(&self.doc.events))
+            .map(|title| clean_preview(title).chars().take(TITLE_MAX_LEN).collect())
+            .unwrap_or_else(|| self.doc.session_short.clone())
+    }
+
+    /// `[[path|date title]]`, linking by vault path so equal file names in
+    /// two project folders can't be confused.
+    fn link(&self) -> String {
+        let target = self.name.with_extension("");
+        let target = target
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        let alias =
+            format!("{} {}", export_date(&self.doc), self.title()).replace(['|', '[', ']'], " ");
+        format!("[[{target}|{}]]", alias.trim_end())
+    }
+}
+
+/// For each note, the note it continues: a session resumed from another
+/// links its first records to records of the earlier one.
+fn continuations(notes: &[VaultNote]) -> Vec<Option<usize>> {
+    let mut owner: HashMap<&str, usize> = HashMap::new();
+    for (i, note) in notes.iter().enumerate() {
+        for uuid in note.doc.events.iter().filter_map(|e| e.uuid.as_deref()) {
+            owner.entry(uuid).or_insert(i);
+        }
+    }
+    notes
+        .iter()
+        .enumerate()
+        .map(|(i, note)| {
+            let own = note
+                .doc
+                .events
+                .iter()
+                .filter_map(|e| e.uuid.as_deref())
+                .collect::<HashSet<_>>();
+            note.doc
+                .events
+                .iter()
+                .filter_map(|e| e.parent_uuid.as_deref())
+                .filter(|parent| !own.contains(parent))
+                .find_map(|parent| owner.get(parent).copied().filter(|&j| j != i))
+        })
+        .collect()
+}
+
+/// Write `notes` under `output_dir`, and an index note per project at
+/// `<project>/<project>.md`. Returns the paths written, notes first.
+pub fn write_obsidian_vault(
+    output_dir: &Path,
+    notes: &[VaultNote],
+    style: ExportStyle,
+) -> Result<Vec<PathBuf>> {
+    let continues = continuations(notes);
+    let mut continued_in = vec![Vec::new(); notes.len()];
+    for (i, earlier) in continues.iter().enumerate() {
+        if let Some(earlier) = earlier {
+            continued_in[*earlier].push(&notes[i]);
+        }
+    }
+    let mut written = Vec::new();
+    let mut projects: BTreeMap<&str, Vec<&VaultNote>> = BTreeMap::new();
+    for (i, note) in notes.iter().enumerate() {
+        let body = render_note(
+            note,
+            continues[i].map(|j| &notes[j]),
+            &continued_in[i],
+            style,
+        );
+        written.push(write_note(&output_dir.join(&note.name), body)?);
+        projects.entry(&note.doc.project).or_default().push(note);
+    }
+    for (project, mut sessions) in projects {
+        sessions.sort_by(|a, b| a.doc.modified_iso.cmp(&b.doc.modified_iso));
+        let path = output_dir.join(project).join(format!("{project}.md"));
+        written.push(write_note(&path, render_index(project, &sessions))?);
+    }
+    Ok(written)
+}
+
+fn write_note(path: &Path, body: String) -> Result<PathBuf> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    write_atomic(path, body)?;
+    Ok(path.to_path_buf())
+}
+
+/// A note on its own, without links to other sessions.
+pub(crate) fn render_obsidian_note(note: &VaultNote, style: ExportStyle) -> String {
+    render_note(note, None, &[], style)
+}
+
+fn render_note(
+    note: &VaultNote,
+    continues: Option<&VaultNote>,
+    continued_in: &[&VaultNote],
+    style: ExportStyle,
+) -> String {
+    let doc = &note.doc;
+    let title = note.title();
+    let mut out = String::from("---\n");
+    out.push_str(&format!("title: {}\n", yaml_string(&title)));
+    out.push_str(&format!("session_id: {}\n", yaml_string(&doc.session_id)));
+    out.push_str(&format!("project: {}\n", yaml_string(&doc.project)));
+    out.push_str(&format!("date: {}\n", export_date(doc)));
+    push_yaml_list(
+        &mut out,
+        "models",
+        note.models.iter().map(|m| yaml_string(m)),
+    );
+    let tags = std::iter::once(VAULT_TAG.to_string()).chain(note.tags.iter().map(|t| tag(t)));
+    push_yaml_list(&mut out, "tags", tags);
+    if let Some(earlier) = continues {
+        out.push_str(&format!("continues: {}\n", yaml_string(&earlier.link())));
+    }
+    push_yaml_list(
+        &mut out,
+        "continued_in",
+        continued_in.iter().map(|later| yaml_string(&later.link())),
+    );
+    out.push_str(&format!(
+        "source: {}\n",
+        yaml_string(&doc.source_path.display().to_string())
+    ));
+    out.push_str("---\n\n");
+    out.push_str(&format!("# {title}\n\n"));
+    if let Some(earlier) = continues {
+        out.push_str(&format!("Continues {}.\n\n", earlier.link()));
+    }
+    push_markdown_events(&mut out, doc, style);
+    for later in continued_in {
+        out.push_str(&format!("Continued in {}.\n\n", later.link()));
+    }
+    out
+}
+
+fn render_index(project: &str, sessions: &[&VaultNote]) -> String {
+    let mut out = String::from("---\n");
+    push_yaml_list(&mut out, "tags", [format!("{VAULT_TAG}/index")].into_iter());
+    out.push_str(&format!("project: {}\n", yaml_string(project)));
+    out.push_str("---\n\n");
+    out.push_str(&format!("# {project}\n\n"));
+    for note in sessions {
+        out.push_str(&format!("- {}", note.link()));
+        if !note.models.is_empty() {
+            out.push_str(&format!(" ({})", note.models.join(", ")));
+        }
+        out.push('\n');
+    }
+    out
+}
+
+fn push_yaml_list(out: &mut String, key: &str, items: impl Iterator<Item = String>) {
+    let items = items.collect::<Vec<_>>();
+    if items.is_empty() {
+        return;
+    }
+    out.push_str(&format!("{key}:\n"));
+    for item in items {
+        out.push_str(&format!("  - {item}\n"));
+    }
+}
+
+/// `tag` as an Obsidian tag, which can't hold spaces or most punctuation.
+fn tag(tag: &str) -> String {
+    tag.chars()
+        .map(|c| {
+            if c.is_alphanumeric() || matches!(c, '-' | '_' | '/') {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::NormalizedEvent;
+    use crate::testutil::unique_temp_path;
+
+    fn note(project: &str, short: &str, events: &[(&str, Option<&str>, &str)]) -> VaultNote {
+        let events = events
+            .iter()
+            .map(|(uuid, parent, content)| NormalizedEvent {
+                role: "user".to_string(),
+                source_type: "user".to_string(),
+                timestamp: None,
+                content: content.to_string(),
+                uuid: Some(uuid.to_string()),
+                parent_uuid: parent.map(str::to_string),
+                source: None,
+                model: None,
+            })
+            .collect::<Vec<_>>();
+        let doc = ExportDocument {
+            session_id: format!("{short}-full"),
+            session_short: short.to_string(),
+            project: project.to_string(),
+            source_path: PathBuf::from(format!("/logs/{short}.jsonl")),
+            modified_iso: format!("2026-01-0{}T10:00:00Z", short.len()),
+            event_count: events.len(),
+            events,
+            tree: None,
+            title: None,
+        };
+        VaultNote::new(doc, Path::new(project).join(format!("{short}.md")))
+    }
+
+    #[test]
+    fn vault_links_continued_sessions_and_indexes_projects() {
+        let first = note("alpha", "a", &[("u1", None, "Fix the | login test")]);
+        let mut second = note("alpha", "bb", &[("u2", Some("u1"), "Keep going")]);
+        second.tags = vec!["needs review".to_string()];
+        second.models = vec!["opus".to_string()];
+        let other = note("beta", "ccc", &[("u3", None, "Unrelated")]);
+        let notes = [first, second, other];
+        assert_eq!(continuations(&notes), vec![None, Some(0), None]);
+
+        let dir = unique_temp_path("cc-convo-test-obsidian");
+        let written = write_obsidian_vault(&dir, &notes, ExportStyle::default()).expect("write");
+        let names = written
+            .iter()
+            .map(|p| p.strip_prefix(&dir).expect("under dir").to_path_buf())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            [
+                "alpha/a.md",
+                "alpha/bb.md",
+                "beta/ccc.md",
+                "alpha/alpha.md",
+                "beta/beta.md"
+            ]
+            .map(PathBuf::from)
+        );
+        let second = fs::read_to_string(dir.join("alpha/bb.md")).expect("read");
+        assert!(second.starts_with("---\ntitle: Keep going\n"), "{second}");
+        assert!(second.contains("tags:\n  - cc-convo\n  - needs-review\n"));
+        assert!(second.contains("continues: \"[[alpha/a|2026-01-01 Fix the   login test]]\"\n"));
+        let first = fs::read_to_string(dir.join("alpha/a.md")).expect("read");
+        assert!(first.contains("Continued in [[alpha/bb|2026-01-02 Keep going]]."));
+        let index = fs::read_to_string(dir.join("alpha/alpha.md")).expect("read");
+        assert!(index.ends_with(
+            "- [[alpha/a|2026-01-01 Fix the   login test]]\n- [[alpha/bb|2026-01-02 Keep going]] (opus)\n"
+        ));
+        fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,817 @@
+//! Normalizing JSONL transcript records into dialog events.
+
+use crate::assets::Asset;
+use crate::compress::open_transcript;
+use crate::record::{MessageContent, Record, RecordBody};
+use crate::text::ellipsize;
+use crate::title::SessionTitle;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+/// Top-level record types observed in the schema inventory baseline.
+pub const KNOWN_RECORD_TYPES: &[&str] = &[
+    "user",
+    "assistant",
+    "system",
+    "progress",
+    "queue-operation",
+    "file-history-snapshot",
+    "pr-link",
+    "summary",
+    "custom-title",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct NormalizedEvent {
+    pub role: String,
+    pub source_type: String,
+    pub timestamp: Option<String>,
+    pub content: String,
+    /// The record's `uuid`, when it has one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uuid: Option<String>,
+    /// `uuid` of the nearest ancestor record that produced an event, following
+    /// `parentUuid` through records (tool results, progress) that were skipped.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_uuid: Option<String>,
+    /// Where the record is, when parsed with [`ParseOptions::source_refs`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<SourceRef>,
+    /// The model that wrote an assistant event, for search filters; exports
+    /// leave it out.
+    #[serde(skip)]
+    pub model: Option<String>,
+}
+
+/// The transcript line an event came from.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct SourceRef {
+    /// Unset when parsing from a reader rather than a file.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<PathBuf>,
+    /// 1-based line number.
+    pub line: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ParseOutput {
+    pub events: Vec<NormalizedEvent>,
+    pub parse_errors: u64,
+    /// Details for the first [`MAX_PARSE_DIAGNOSTICS`] malformed lines.
+    pub diagnostics: Vec<ParseDiagnostic>,
+    /// Attachments decoded when [`ParseOptions::assets`] is set.
+    #[serde(skip)]
+    pub assets: Vec<Asset>,
+    /// What the session's title is picked from; every record counts, not
+    /// only the ones that became events.
+    #[serde(skip)]
+    pub title: SessionTitle,
+}
+
+/// Diagnostics kept per parse; `parse_errors` keeps counting past this.
+pub const MAX_PARSE_DIAGNOSTICS: usize = 100;
+
+/// Why a JSONL line could not be read as a record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ParseErrorKind {
+    InvalidUtf8,
+    /// The line ends mid-value, typically a partial write.
+    Truncated,
+    Syntax,
+    /// Valid JSON whose fields don't have the types a record should.
+    Schema,
+}
+
+impl ParseErrorKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::InvalidUtf8 => "invalid-utf8",
+            Self::Truncated => "truncated",
+            Self::Syntax => "syntax",
+            Self::Schema => "schema",
+        }
+    }
+}
+
+/// A malformed line, located precisely enough to inspect it by hand.
+#[derive(Debug, Clone, Serialize)]
+pub struct ParseDiagnostic {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<PathBuf>,
+    /// 1-based line number.
+    pub line: usize,
+    /// Offset of the start of the line from the start of the file.
+    pub byte_offset: u64,
+    pub kind: ParseErrorKind,
+    pub message: String,
+    pub snippet: String,
+}
+
+impl ParseDiagnostic {
+    fn new(line: usize, byte_offset: u64, bytes: &[u8]) -> Self {
+        let (kind, message) = match std::str::from_utf8(bytes) {
+            Err(err) => (ParseErrorKind::InvalidUtf8, err.to_string()),
+            Ok(text) => match serde_json::from_str::<Value>(text) {
+                Err(err) if err.is_eof() => (ParseErrorKind::Truncated, err.to_string()),
+                Err(err) => (ParseErrorKind::Syntax, err.to_string()),
+                Ok(_) => (
+                    ParseErrorKind::Syntax,
+                    "unexpected parse failure".to_string(),
+                ),
+            },
+        };
+        let snippet = String::from_utf8_lossy(bytes);
+        Self {
+            file: None,
+            line,
+            byte_offset,
+            kind,
+            message,
+            snippet: ellipsize(snippet.trim(), 120),
+        }
+    }
+
+    fn schema(line: usize, byte_offset: u64, bytes: &[u8], err: &serde_json::Error) -> Self {
+        Self {
+            file: None,
+            line,
+            byte_offset,
+            kind: ParseErrorKind::Schema,
+            message: err.to_string(),
+            snippet: ellipsize(String::from_utf8_lossy(bytes).trim(), 120),
+        }
+    }
+}
+
+/// Iterator over the non-blank lines of a JSONL stream, yielding each parsed
+/// record or a [`ParseDiagnostic`] for lines that are not valid UTF-8 JSON.
+pub struct JsonlRecords<R> {
+    reader: R,
+    line: usize,
+    line_start: u64,
+    offset: u64,
+    buf: Vec<u8>,
+}
+
+pub fn jsonl_records<R: BufRead>(reader: R) -> JsonlRecords<R> {
+    JsonlRecords {
+        reader,
+        line: 0,
+        line_start: 0,
+        offset: 0,
+        buf: Vec::new(),
+    }
+}
+
+impl<R> JsonlRecords<R> {
+    /// 1-based number of the line last yielded.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// A [`ParseErrorKind::Schema`] diagnostic for the line last yielded.
+    pub fn schema_diagnostic(&self, err: &serde_json::Error) -> ParseDiagnostic {
+        let line = self.buf.strip_suffix(b"\n").unwrap_or(&self.buf);
+        ParseDiagnostic::schema(self.line, self.line_start, line, err)
+    }
+}
+
+impl<R: BufRead> Iterator for JsonlRecords<R> {
+    type Item = std::io::Result<std::result::Result<Value, ParseDiagnostic>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            self.buf.clear();
+            let read = match self.reader.read_until(b'\n', &mut self.buf) {
+                Ok(0) => return None,
+                Ok(n) => n,
+                Err(err) => return Some(Err(err)),
+            };
+            self.line += 1;
+            let start = self.offset;
+            self.line_start = start;
+            self.offset += read as u64;
+            let line = self.buf.strip_suffix(b"\n").unwrap_or(&self.buf);
+            if line.iter().all(u8::is_ascii_whitespace) {
+                continue;
+            }
+            return Some(Ok(serde_json::from_slice(line)
+                .map_err(|_| ParseDiagnostic::new(self.line, start, line))));
+        }
+    }
+}
+
+/// What becomes of records whose type isn't in [`KNOWN_RECORD_TYPES`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+#[serde(rename_all = "lowercase")]
+pub enum UnknownRecords {
+    /// An event holding the record's JSON.
+    Keep,
+    /// An event naming the record's type and fields.
+    Summarize,
+    /// No event.
+    Drop,
+}
+
+/// How records become events.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// Also render thinking, tool calls and results, and non-dialog records.
+    pub detailed: bool,
+    /// `None` keeps unknown records only when `detailed`.
+    pub unknown_records: Option<UnknownRecords>,
+    /// Record each event's file and line in [`NormalizedEvent::source`].
+    pub source_refs: bool,
+    /// Keep every streamed record of an assistant message as its own event
+    /// instead of merging records that share a message id.
+    pub keep_partials: bool,
+    /// Decode inline images and documents into [`ParseOutput::assets`] and
+    /// link them from the content instead of omitting them.
+    pub assets: bool,
+    /// Render thinking blocks even when not `detailed`.
+    pub thinking: bool,
+}
+
+impl ParseOptions {
+    pub fn detailed(detailed: bool) -> Self {
+        Self {
+            detailed,
+            ..Self::default()
+        }
+    }
+
+    fn unknown_records(&self) -> UnknownRecords {
+        match self.unknown_records {
+            Some(policy) => policy,
+            None if self.detailed => UnknownRecords::Keep,
+            None => UnknownRecords::Drop,
+        }
+    }
+}
+
+pub fn parse_session_events(path: &Path, detailed: bool) -> Result<ParseOutput> {
+    parse_session_events_with(path, &ParseOptions::detailed(detailed))
+}
+
+pub fn parse_session_events_with(path: &Path, options: &ParseOptions) -> Result<ParseOutput> {
+    let f = open_transcript(path)?;
+    let mut parsed = parse_session_reader_with(BufReader::new(f), options)?;
+    for diagnostic in &mut parsed.diagnostics {
+        diagnostic.file = Some(path.to_path_buf());
+    }
+    for source in parsed.events.iter_mut().filter_map(|e| e.source.as_mut()) {
+        source.file = Some(path.to_path_buf());
+    }
+    Ok(parsed)
+}
+
+/// Parse JSONL records from any reader. Lines that are not valid UTF-8 or
+/// JSON count as parse errors instead of aborting the whole session.
+pub fn parse_session_reader(reader: impl BufRead, detailed: bool) -> Result<ParseOutput> {
+    parse_session_reader_with(reader, &ParseOptions::detailed(detailed))
+}
+
+pub fn parse_session_reader_with(
+    reader: impl BufRead,
+    options: &ParseOptions,
+) -> Result<ParseOutput> {
+    let detailed = options.detailed;
+    let mut events: Vec<NormalizedEvent> = Vec::new();
+    let mut parse_errors = 0u64;
+    let mut diagnostics = Vec::new();
+    let mut assets = Vec::new();
+    let mut title = SessionTitle::default();
+    // Record uuid -> uuid of the nearest ancestor that became an event.
+    let mut nearest_event: HashMap<String, Option<String>> = HashMap::new();
+    // Assistant message id -> index of the event its records merge into.
+    let mut message_events: HashMap<String, usize> = HashMap::new();
+
+    let mut records = jsonl_records(reader);
+    while let Some(line) = records.next() {
+        let parsed = line?.and_then(|value| {
+            Record::from_value(&value)
+                .map(|record| (value, record))
+                .map_err(|err| records.schema_diagnostic(&err))
+        });
+        let (value, record) = match parsed {
+            Ok(parsed) => parsed,
+            Err(diagnostic) => {
+                parse_errors += 1;
+                if diagnostics.len() < MAX_PARSE_DIAGNOSTICS {
+                    diagnostics.push(diagnostic);
+                }
+                continue;
+            }
+        };
+        title.observe(&record);
+        let record_type = record.record_type();
+        let head = &record.head;
+        // Compaction starts a fresh chain; `logicalParentUuid` keeps the link.
+        let parent_uuid = head
+            .parent_uuid
+            .as_deref()
+            .or(head.logical_parent_uuid.as_deref())
+            .and_then(|parent| match nearest_event.get(parent) {
+                Some(resolved) => resolved.clone(),
+                None => Some(parent.to_string()),
+            });
+        let content = match record.message() {
+            Some(message) if options.assets => {
+                Some(message.content.text_with_assets(detailed, &mut assets))
+                    .filter(|t| !t.trim().is_empty())
+            }
+            Some(message) if options.thinking && !detailed => {
+                Some(message.content.text_with_thinking()).filter(|t| !t.trim().is_empty())
+            }
+            Some(message) => Some(message.content.text(detailed)).filter(|t| !t.trim().is_empty()),
+            None if !KNOWN_RECORD_TYPES.contains(&record_type) => match options.unknown_records() {
+                UnknownRecords::Keep => Some(truncate_value(&value, 500)),
+                UnknownRecords::Summarize => Some(summarize_unknown_record(&value)),
+                UnknownRecords::Drop => None,
+            },
+            None if detailed => Some(
+                record
+                    .describe()
+                    .unwrap_or_else(|| truncate_value(&value, 500)),
+            ),
+            None => None,
+        };
+
+        let message_id = match &record.body {
+            RecordBody::Assistant(message) if !options.keep_partials => message.message.id.clone(),
+            _ => None,
+        };
+        if let (Some(content), Some(&index)) = (
+            &content,
+            message_id.as_ref().and_then(|id| message_events.get(id)),
+        ) {
+            let merged = &mut events[index];
+            merge_partial(&mut merged.content, content);
+            if let Some(uuid) = &head.uuid {
+                nearest_event.insert(uuid.clone(), merged.uuid.clone());
+            }
+            continue;
+        }
+
+        match (content, head.uuid.clone()) {
+            (Some(content), uuid) => {
+                if let Some(id) = message_id {
+                    message_events.insert(id, events.len());
+                }
+                if let Some(uuid) = &uuid {
+                    nearest_event.insert(uuid.clone(), Some(uuid.clone()));
+                }
+                events.push(NormalizedEvent {
+                    role: record_type.to_string(),
+                    source_type: record_type.to_string(),
+                    timestamp: head.timestamp.clone(),
+                    content,
+                    parent_uuid: uuid.as_ref().and(parent_uuid),
+                    uuid,
+                    source: options.source_refs.then(|| SourceRef {
+                        file: None,
+                        line: records.line(),
+                    }),
+                    model: record.message().and_then(|m| m.model.clone()),
+                });
+            }
+            (None, Some(uuid)) => {
+                nearest_event.insert(uuid, parent_uuid);
+            }
+            (None, None) => {}
+        }
+    }
+
+    Ok(ParseOutput {
+        events,
+        parse_errors,
+        diagnostics,
+        assets,
+        title,
+    })
+}
+
+/// Fold another streamed record of the same assistant message into `merged`.
+/// A record repeating text already there (a re-sent snapshot) adds nothing, a
+/// longer snapshot replaces it, and a new block is appended.
+fn merge_partial(merged: &mut String, partial: &str) {
+    if merged.contains(partial) {
+        return;
+    }
+    if partial.starts_with(merged.as_str()) {
+        *merged = partial.to_string();
+        return;
+    }
+    merged.push_str("\n\n");
+    merged.push_str(partial);
+}
+
+pub fn extract_message_text(record: &Value, detailed: bool) -> String {
+    let Some(content) = record.get("message").and_then(|m| m.get("content")) else {
+        return String::new();
+    };
+    extract_content_text(content, detailed)
+}
+
+/// Render a message's `content` as [`MessageContent::text`] does, falling
+/// back to the raw JSON for content that doesn't fit the record types.
+pub fn extract_content_text(content: &Value, detailed: bool) -> String {
+    match MessageContent::deserialize(content) {
+        Ok(content) => content.text(detailed),
+        Err(_) => truncate_value(content, 1200),
+    }
+}
+
+pub fn summarize_non_dialog_record(value: &Value) -> String {
+    Record::from_value(value)
+        .ok()
+        .and_then(|record| record.describe())
+        .unwrap_or_else(|| truncate_value(value, 300))
+}
+
+/// `type: field, field, ...`, leaving out the fields every record has.
+fn summarize_unknown_record(value: &Value) -> String {
+    const COMMON_FIELDS: &[&str] = &[
+        "type",
+        "uuid",
+        "parentUuid",
+        "logicalParentUuid",
+        "timestamp",
+        "sessionId",
+        "isMeta",
+    ];
+    let record_type = value
+        .get("type")
+        .and_then(Value::as_str)
+        .unwrap_or("unknown");
+    let fields = value
+        .as_object()
+        .into_iter()
+        .flat_map(|map| map.keys())
+        .filter(|key| !COMMON_FIELDS.contains(&key.as_str()))
+        .map(String::as_str)
+        .collect::<Vec<_>>();
+    if fields.is_empty() {
+        return record_type.to_string();
+    }
+    ellipsize(&format!("{record_type}: {}", fields.join(", ")), 300)
+}
+
+pub fn truncate_value(v: &Value, max: usize) -> String {
+    let s = serde_json::to_string(v).unwrap_or_else(|_| "<invalid-json>".to_string());
+    ellipsize(&s, max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::{unique_temp_path, write_jsonl};
+    use serde_json::json;
+    use std::fs;
+
+    #[test]
+    fn parse_default_mode_extracts_text_only() {
+        let dir = unique_temp_path("cc-convo-test-default");
+        fs::create_dir_all(&dir).expect("create temp dir");
+        let file = dir.join("session.jsonl");
+        write_jsonl(
+            &file,
+            &[
+                r#"{"type":"user","timestamp":"2026-02-21T00:00:00Z","message":{"content":[{"type":"text","text":"hello user"}]}}"#,
+                r#"{"type":"assistant","timestamp":"2026-02-21T00:00:01Z","message":{"content":[{"type":"thinking","thinking":"private"},{"type":"tool_use","name":"x","input":{"a":1}},{"type":"text","text":"hello assistant"}]}}"#,
+                r#"{"type":"progress","timestamp":"2026-02-21T00:00:02Z","data":{"type":"tool","hookName":"h"}}"#,
+            ],
+        );
+
+        let parsed = parse_session_events(&file, false).expect("parse");
+        assert_eq!(parsed.parse_errors, 0);
+        assert_eq!(parsed.events.len(), 2);
+        assert_eq!(parsed.events[0].role, "user");
+        assert_eq!(parsed.events[0].content, "hello user");
+        assert_eq!(parsed.events[1].role, "assistant");
+        assert_eq!(parsed.events[1].content, "hello assistant");
+
+        fs::remove_file(&file).expect("cleanup file");
+        fs::remove_dir_all(&dir).expect("cleanup dir");
+    }
+
+    #[test]
+    fn parse_detailed_mode_includes_operational_blocks() {
+        let dir = unique_temp_path("cc-convo-test-detailed");
+        fs::create_dir_all(&dir).expect("create temp dir");
+        let file = dir.join("session.jsonl");
+        write_jsonl(
+            &file,
+            &[
+                r#"{"type":"assistant","timestamp":"2026-02-21T00:00:01Z","message":{"content":[{"type":"thinking","thinking":"private"},{"type":"tool_use","name":"x","input":{"a":1}},{"type":"tool_result","tool_use_id":"abc","content":"done"},{"type":"text","text":"visible"}]}}"#,
+                r#"{"type":"progress","timestamp":"2026-02-21T00:00:02Z","data":{"type":"tool","hookName":"h"}}"#,
+            ],
+        );
+
+        let parsed = parse_session_events(&file, true).expect("parse");
+        assert_eq!(parsed.parse_errors, 0);
+        assert_eq!(parsed.events.len(), 2);
+        assert_eq!(parsed.events[0].role, "assistant");
+        assert!(parsed.events[0].content.contains("[thinking]"));
+        assert!(parsed.events[0].content.contains("[tool_use] x"));
+        assert!(parsed.events[0].content.contains("[tool_result] abc"));
+        assert!(parsed.events[0].content.contains("visible"));
+        assert_eq!(parsed.events[1].role, "progress");
+        assert!(parsed.events[1].content.contains("progress:tool"));
+
+        fs::remove_file(&file).expect("cleanup file");
+        fs::remove_dir_all(&dir).expect("cleanup dir");
+    }
+
+    #[test]
+    fn parent_uuid_skips_records_without_events() {
+        let input = [
+            r#"{"type":"user","uuid":"u1","parentUuid":null,"message":{"content":"run it"}}"#,
+            r#"{"type":"assistant","uuid":"a1","parentUuid":"u1","message":{"content":[{"type":"tool_use","name":"Bash","input":{}}]}}"#,
+            r#"{"type":"user","uuid":"t1","parentUuid":"a1","message":{"content":[{"type":"tool_result","tool_use_id":"x","content":"ok"}]}}"#,
+            r#"{"type":"assistant","uuid":"a2","parentUuid":"t1","message":{"content":"done"}}"#,
+        ]
+        .join("\n");
+        let parsed = parse_session_reader(input.as_bytes(), false).expect("parse");
+        let links = parsed
+            .events
+            .iter()
+            .map(|e| (e.uuid.as_deref(), e.parent_uuid.as_deref()))
+            .collect::<Vec<_>>();
+        assert_eq!(links, vec![(Some("u1"), None), (Some("a2"), Some("u1"))]);
+    }
+
+    #[test]
+    fn unknown_record_policy_applies_only_to_unknown_types() {
+        let input = [
+            r#"{"type":"user","message":{"content":"hi"}}"#,
+            r#"{"type":"pr-link","prNumber":7}"#,
+            r#"{"type":"chart-render","uuid":"c1","chartId":"c","spec":{}}"#,
+        ]
+        .join("\n");
+        let events = |detailed: bool, unknown_records: Option<UnknownRecords>| {
+            let options = ParseOptions {
+                detailed,
+                unknown_records,
+                ..ParseOptions::default()
+            };
+            parse_session_reader_with(input.as_bytes(), &options)
+                .expect("parse")
+                .events
+                .into_iter()
+                .map(|e| e.content)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(events(false, None), vec!["hi"]);
+        assert_eq!(events(true, None).len(), 3);
+        assert_eq!(
+            events(false, Some(UnknownRecords::Summarize)),
+            vec!["hi", "chart-render: chartId, spec"]
+        );
+        assert!(events(false, Some(UnknownRecords::Keep))[1].contains(r#""chartId":"c""#));
+        assert_eq!(
+            events(true, Some(UnknownRecords::Drop)),
+            vec!["hi", r#"{"prNumber":7,"type":"pr-link"}"#]
+        );
+    }
+
+    #[test]
+    fn parse_reader_reports_malformed_line_locations() {
+        let body = b"{\"type\":\"user\",\"message\":{\"content\":\"hi\"}}\n\n{not json}\n\xff\xfe\n{\"type\":\"user\",\"message\":{\"content\":7}}\n{\"type\":\"user\",\"mess";
+        let parsed = parse_session_reader(&body[..], false).expect("parse");
+        assert_eq!(parsed.events.len(), 1);
+        assert_eq!(parsed.parse_errors, 4);
+        let located = parsed
+            .diagnostics
+            .iter()
+            .map(|d| (d.line, d.byte_offset, d.kind))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            located,
+            vec![
+                (3, 44, ParseErrorKind::Syntax),
+                (4, 55, ParseErrorKind::InvalidUtf8),
+                (5, 58, ParseErrorKind::Schema),
+                (6, 98, ParseErrorKind::Truncated),
+            ]
+        );
+        assert!(parsed.diagnostics[2].message.contains("message content"));
+        assert_eq!(parsed.diagnostics[0].snippet, "{not json}");
+    }
+
+    mod props {
+        use super::*;
+        use crate::search::build_context_preview;
+        use crate::text::{clean_preview, display_width, ellipsize, grapheme_bounds};
+        use proptest::collection::vec;
+        use proptest::prelude::*;
+        use unicode_segmentation::UnicodeSegmentation;
+
+        /// Transcript-like text mixing ASCII with CJK, combining accents,
+        /// emoji sequences, and flags.
+        fn non_ascii_text() -> impl Strategy<Value = String> {
+            vec(
+                prop_oneof![
+                    Just("a"),
+                    Just(" "),
+                    Just("\n"),
+                    Just("日本語"),
+                    Just("한국어"),
+                    Just("e\u{301}"),
+                    Just("👩\u{200d}💻"),
+                    Just("👍🏽"),
+                    Just("🇯🇵"),
+                    Just("═"),
+                ],
+                0..60,
+            )
+            .prop_map(|parts| parts.concat())
+        }
+
+        fn arb_json() -> impl Strategy<Value = Value> {
+            let leaf = prop_oneof![
+                Just(Value::Null),
+                any::<bool>().prop_map(Value::Bool),
+                any::<i64>().prop_map(|n| json!(n)),
+                prop_oneof![
+                    Just("text"),
+                    Just("thinking"),
+                    Just("tool_use"),
+                    Just("tool_result"),
+                    Just("image"),
+                    Just("document"),
+                ]
+                .prop_map(|s| json!(s)),
+                ".{0,40}".prop_map(Value::String),
+            ];
+            leaf.prop_recursive(4, 64, 6, |inner| {
+                prop_oneof![
+                    vec(inner.clone(), 0..6).prop_map(Value::Array),
+                    vec(
+                        (
+                            prop_oneof![
+                                Just("type"),
+                                Just("text"),
+                                Just("thinking"),
+                                Just("name"),
+                                Just("input"),
+                                Just("content"),
+                                Just("tool_use_id"),
+                                Just("message"),
+                            ],
+                            inner
+                        ),
+                        0..6
+                    )
+                    .prop_map(|pairs| {
+                        Value::Object(pairs.into_iter().map(|(k, v)| (k.to_string(), v)).collect())
+                    }),
+                ]
+            })
+        }
+
+        fn record_line() -> impl Strategy<Value = String> {
+            (
+                prop_oneof![
+                    Just("user".to_string()),
+                    Just("assistant".to_string()),
+                    Just("system".to_string()),
+                    Just("progress".to_string()),
+                    Just("summary".to_string()),
+                    ".{0,8}",
+                ],
+                arb_json(),
+            )
+                .prop_map(|(kind, content)| {
+                    json!({"type": kind, "timestamp": "2026-01-01T00:00:00Z",
+                        "message": {"content": content}})
+                    .to_string()
+                })
+        }
+
+        /// Generous ceiling on rendered size relative to the raw input.
+        fn output_budget(input_len: usize) -> usize {
+            input_len * 16 + 4096
+        }
+
+        proptest! {
+            #[test]
+            fn parse_reader_survives_arbitrary_bytes(
+                bytes in vec(any::<u8>(), 0..2048),
+                detailed in any::<bool>(),
+            ) {
+                let parsed = parse_session_reader(bytes.as_slice(), detailed)
+                    .expect("in-memory reads cannot fail");
+                let rendered: usize = parsed.events.iter().map(|e| e.content.len()).sum();
+                prop_assert!(rendered <= output_budget(bytes.len()));
+            }
+
+            #[test]
+            fn parse_reader_survives_mutated_records(
+                lines in vec(record_line(), 1..12),
+                cut in any::<prop::sample::Index>(),
+                flip in any::<prop::sample::Index>(),
+                detailed in any::<bool>(),
+            ) {
+                let mut bytes = lines.join("\n").into_bytes();
+                let at = flip.index(bytes.len());
+                bytes[at] ^= 0x5a;
+                bytes.truncate(cut.index(bytes.len() + 1));
+                let parsed = parse_session_reader(bytes.as_slice(), detailed)
+                    .expect("in-memory reads cannot fail");
+                // The flip can turn a byte into a newline, splitting a record.
+                let line_count = bytes.split(|&b| b == b'\n').count();
+                prop_assert!(parsed.events.len() + parsed.parse_errors as usize <= line_count);
+                let rendered: usize = parsed.events.iter().map(|e| e.content.len()).sum();
+                prop_assert!(rendered <= output_budget(bytes.len()));
+            }
+
+            #[test]
+            fn extract_content_text_is_bounded(value in arb_json(), detailed in any::<bool>()) {
+                let input_len = value.to_string().len();
+                let text = extract_content_text(&value, detailed);
+                prop_assert!(text.len() <= output_budget(input_len));
+            }
+
+            #[test]
+            fn preview_builders_respect_limits(
+                text in prop_oneof!["\\PC{0,300}", non_ascii_text()],
+                query in "\\PC{0,12}",
+                context in 0usize..200,
+                case_sensitive in any::<bool>(),
+                max in 0usize..200,
+            ) {
+                let preview = build_context_preview(&text, &query, context, case_sensitive);
+                let shown = preview.strip_prefix("...").unwrap_or(&preview);
+                let shown = shown.strip_suffix("...").unwrap_or(shown);
+                // Limits under four columns leave only dots.
+                prop_assert!(text.replace('\n', " ").contains(shown) || context * 2 <= 3);
+                prop_assert!(display_width(&ellipsize(&text, max)) <= max);
+                prop_assert!(display_width(&clean_preview(&text)) <= 140);
+            }
+
+            #[test]
+            fn truncation_keeps_grapheme_clusters_whole(
+                text in non_ascii_text(),
+                query_at in any::<prop::sample::Index>(),
+                context in 0usize..40,
+                max in 4usize..120,
+            ) {
+                let cut = ellipsize(&text, max);
+                let kept = cut.strip_suffix("...").unwrap_or(&cut);
+                prop_assert!(text.starts_with(kept));
+                prop_assert!(grapheme_bounds(&text).contains(&kept.len()));
+
+                // Query a single grapheme of the text so the search always hits.
+                let graphemes = text.graphemes(true).collect::<Vec<_>>();
+                if !graphemes.is_empty() {
+                    let query = graphemes[query_at.index(graphemes.len())];
+                    let preview = build_context_preview(&text, query, context, true);
+                    let shown = preview.strip_prefix("...").unwrap_or(&preview);
+                    let shown = shown.strip_suffix("...").unwrap_or(shown);
+                    prop_assert!(shown.contains(&query.replace('\n', " ")));
+                    prop_assert!(shown.graphemes(true).count() <= context * 2 + 1);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn streamed_partials_merge_into_one_event() {
+        let input = [
+            r#"{"type":"assistant","uuid":"a1","message":{"id":"m1","content":[{"type":"text","text":"First paragraph."}]}}"#,
+            r#"{"type":"assistant","uuid":"a2","parentUuid":"a1","message":{"id":"m1","content":[{"type":"text","text":"First paragraph."}]}}"#,
+            r#"{"type":"assistant","uuid":"a3","parentUuid":"a2","message":{"id":"m1","content":[{"type":"text","text":"First paragraph. And more."}]}}"#,
+            r#"{"type":"assistant","uuid":"a4","parentUuid":"a3","message":{"id":"m1","content":[{"type":"text","text":"Second block."}]}}"#,
+            r#"{"type":"user","uuid":"u1","parentUuid":"a4","message":{"content":"thanks"}}"#,
+        ]
+        .join("\n");
+        let parse = |keep_partials| {
+            let options = ParseOptions {
+                keep_partials,
+                ..ParseOptions::default()
+            };
+            parse_session_reader_with(input.as_bytes(), &options)
+                .expect("parse")
+                .events
+        };
+
+        let merged = parse(false);
+        assert_eq!(merged.len(), 2);
+        assert_eq!(
+            merged[0].content,
+            "First paragraph. And more.\n\nSecond block."
+        );
+        assert_eq!(merged[1].parent_uuid.as_deref(), Some("a1"));
+
+        let kept = parse(true);
+        assert_eq!(kept.len(), 5);
+        assert_eq!(kept[1].content, "First paragraph.");
+    }
+}
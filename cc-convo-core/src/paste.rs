@@ -0,0 +1,255 @@
+//! Spotting stack traces, logs, and code pasted into prompts, which tend to
+//! outweigh everything typed around them.
+
+use crate::text::ellipsize;
+use serde::Serialize;
+
+/// Shorter runs read as a quoted snippet rather than a paste.
+pub const MIN_PASTE_LINES: usize = 12;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PasteKind {
+    StackTrace,
+    Log,
+    Code,
+}
+
+impl PasteKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::StackTrace => "stack trace",
+            Self::Log => "log",
+            Self::Code => "code",
+        }
+    }
+}
+
+/// A pasted block as a byte range of the message text.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PastedBlock {
+    pub kind: PasteKind,
+    pub start: usize,
+    pub end: usize,
+    pub lines: usize,
+}
+
+impl PastedBlock {
+    pub fn bytes(&self) -> usize {
+        self.end - self.start
+    }
+}
+
+/// Find pasted blocks in `text`: fenced code blocks of at least
+/// [`MIN_PASTE_LINES`] lines, and runs of that many lines in paragraphs that
+/// don't read as prose.
+pub fn detect_pastes(text: &str) -> Vec<PastedBlock> {
+    let mut lines = Vec::new();
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        lines.push((offset, line.trim_end_matches(['\n', '\r'])));
+        offset += line.len();
+    }
+    let end_of = |i: usize| lines.get(i + 1).map_or(text.len(), |(at, _)| *at);
+
+    let mut blocks = Vec::new();
+    // Line range of the current run of non-prose paragraphs.
+    let mut run: Option<(usize, usize)> = None;
+    let mut paragraph_start = None;
+    let close_run = |run: &mut Option<(usize, usize)>, blocks: &mut Vec<PastedBlock>| {
+        if let Some((first, last)) = run.take() {
+            if last - first + 1 >= MIN_PASTE_LINES {
+                let body = lines[first..=last].iter().map(|(_, l)| *l);
+                blocks.push(PastedBlock {
+                    kind: classify(body).unwrap_or(PasteKind::Code),
+                    start: lines[first].0,
+                    end: end_of(last),
+                    lines: last - first + 1,
+                });
+            }
+        }
+    };
+
+    let mut i = 0;
+    while i <= lines.len() {
+        let line = lines.get(i).map(|(_, l)| *l);
+        let fence = line.filter(|l| l.trim_start().starts_with("```"));
+        if line.is_none_or(|l| l.trim().is_empty()) || fence.is_some() {
+            if let Some(start) = paragraph_start.take() {
+                let paragraph = &lines[start..i];
+                let prose = paragraph.iter().filter(|(_, l)| is_prose_line(l)).count();
+                if prose * 2 >= paragraph.len() {
+                    close_run(&mut run, &mut blocks);
+                } else {
+                    run = Some((run.map_or(start, |(first, _)| first), i - 1));
+                }
+            }
+        } else if paragraph_start.is_none() {
+            paragraph_start = Some(i);
+        }
+        if fence.is_some() {
+            close_run(&mut run, &mut blocks);
+            let close = (i + 1..lines.len()).find(|&j| lines[j].1.trim_start().starts_with("```"));
+            let last = close.unwrap_or(lines.len() - 1);
+            let inner = last.saturating_sub(i + usize::from(close.is_some()));
+            if inner >= MIN_PASTE_LINES {
+                let body = lines[i + 1..=last].iter().map(|(_, l)| *l);
+                blocks.push(PastedBlock {
+                    kind: classify(body).unwrap_or(PasteKind::Code),
+                    start: lines[i].0,
+                    end: end_of(last),
+                    lines: last - i + 1,
+                });
+            }
+            i = last + 1;
+            continue;
+        }
+        i += 1;
+    }
+    close_run(&mut run, &mut blocks);
+    blocks
+}
+
+/// `text` with each pasted block replaced by a one-line summary of what was
+/// pasted, keeping its first line for context.
+pub fn fold_pastes(text: &str) -> String {
+    let mut out = String::new();
+    let mut at = 0;
+    for block in detect_pastes(text) {
+        out.push_str(&text[at..block.start]);
+        let first = text[block.start..block.end]
+            .lines()
+            .map(str::trim)
+            .find(|l| !l.is_empty() && !l.starts_with("```"))
+            .unwrap_or_default();
+        out.push_str(&format!(
+            "[pasted {}: {} lines, {}] {}\n",
+            block.kind.as_str(),
+            block.lines,
+            format_bytes(block.bytes()),
+            ellipsize(first, 80)
+        ));
+        at = block.end;
+    }
+    out.push_str(&text[at..]);
+    out
+}
+
+fn format_bytes(bytes: usize) -> String {
+    if bytes < 1024 {
+        format!("{bytes} B")
+    } else {
+        format!("{:.1} KB", bytes as f64 / 1024.0)
+    }
+}
+
+/// The kind the lines look like, or `None` if nothing stands out.
+fn classify<'a>(lines: impl Iterator<Item = &'a str>) -> Option<PasteKind> {
+    let (mut total, mut trace, mut log) = (0, 0, 0);
+    for line in lines.filter(|l| !l.trim().is_empty()) {
+        total += 1;
+        trace += usize::from(is_trace_line(line));
+        log += usize::from(is_log_line(line));
+    }
+    if total > 0 && trace * 10 >= total * 3 {
+        Some(PasteKind::StackTrace)
+    } else if total > 0 && log * 2 >= total {
+        Some(PasteKind::Log)
+    } else {
+        None
+    }
+}
+
+fn is_trace_line(line: &str) -> bool {
+    let trimmed = line.trim_start();
+    let frame_number = trimmed
+        .split_once(": ")
+        .is_some_and(|(n, _)| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()));
+    trimmed.starts_with("at ")
+        || trimmed.starts_with("File \"")
+        || trimmed.starts_with("Traceback (most recent call last)")
+        || trimmed.starts_with("Caused by")
+        || trimmed.starts_with("goroutine ")
+        || line.contains("panicked at")
+        || (frame_number && line.starts_with(' '))
+}
+
+fn is_log_line(line: &str) -> bool {
+    let trimmed = line.trim_start_matches(['[', ' ']);
+    let head = trimmed.as_bytes().get(..8).unwrap_or_default();
+    let timestamped = head.len() == 8
+        && head[..2].iter().all(u8::is_ascii_digit)
+        && head.iter().any(|b| matches!(b, b':' | b'-'));
+    let leveled = ["TRACE", "DEBUG", "INFO", "WARN", "ERROR", "FATAL"]
+        .iter()
+        .any(|level| trimmed.starts_with(level));
+    timestamped || leveled
+}
+
+/// Sentence-like: a few words (or a short lead-in such as "The logs:"),
+/// mostly letters, not ending like a statement or block of code.
+fn is_prose_line(line: &str) -> bool {
+    let trimmed = line.trim();
+    let lead_in = trimmed.ends_with([':', '?']) && trimmed.starts_with(char::is_alphabetic);
+    if (trimmed.split_whitespace().count() < 3 && !lead_in)
+        || is_trace_line(line)
+        || is_log_line(line)
+    {
+        return false;
+    }
+    if trimmed.ends_with(['{', '}', ';', '(', ')', ',', '=', '[', ']']) {
+        return false;
+    }
+    let symbols = trimmed
+        .chars()
+        .filter(|c| !c.is_alphanumeric() && !c.is_whitespace() && !".,'?!:-\"`".contains(*c))
+        .count();
+    let starts_like_text = trimmed
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_alphabetic() || "-*>\"'`(".contains(c));
+    starts_like_text && symbols * 10 < trimmed.chars().count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_traces_logs_and_fenced_code_but_not_prose() {
+        let trace = (0..14)
+            .map(|i| format!("    at com.acme.Service.call(Service.java:{i})"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let log = (0..12)
+            .map(|i| format!("2026-01-05T09:00:{i:02}Z INFO request served in {i}ms"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let code = (0..13)
+            .map(|i| format!("    let x{i} = compute({i});"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let prose = "Can you tell me why this keeps failing in production?\nIt started after the last deploy and I am stuck on it.";
+        let text = format!(
+            "{prose}\n\njava.lang.IllegalStateException: boom\n{trace}\n\nThe logs:\n\n{log}\n\n```rust\n{code}\n```\n\n{prose}"
+        );
+
+        let blocks = detect_pastes(&text);
+        let kinds = blocks.iter().map(|b| b.kind).collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            vec![PasteKind::StackTrace, PasteKind::Log, PasteKind::Code]
+        );
+        assert_eq!(blocks[0].lines, 15);
+        assert_eq!(blocks[2].lines, 15, "fences count toward the block");
+        assert!(detect_pastes(prose).is_empty());
+
+        let folded = fold_pastes(&text);
+        assert!(folded.starts_with(prose));
+        assert!(folded.contains("[pasted stack trace: 15 lines, "));
+        assert!(folded.contains("] java.lang.IllegalStateException: boom\n"));
+        assert!(folded.contains("[pasted code: 15 lines, "));
+        assert!(folded.ends_with(prose));
+        assert!(!folded.contains("compute(3)"));
+    }
+}
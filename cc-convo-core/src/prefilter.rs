@@ -0,0 +1,304 @@
+//! Cheap checks that rule transcripts out of a `--min-messages`,
+//! `--max-messages`, or `--model` filter before anything parses them: the
+//! file size, a peek at the first and last bytes of the file, and a raw byte
+//! scan. Each check only ever rules a file out; files that pass still get
+//! the exact test once their records are counted. A `--file` filter has no
+//! cheap check; its exact test looks at the session's tool-input paths and
+//! then, failing those, scans the files for the path.
+
+use crate::compress::{open_transcript, Compression};
+use crate::discovery::Session;
+use crate::tools::path_matches;
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+
+/// The shortest line a user or assistant record can take, `{"type":"user"}`
+/// and its newline, so a file needs this many bytes per message.
+pub const MIN_RECORD_BYTES: u64 = 16;
+
+/// Bytes read from each end of a file by the peek.
+pub const PEEK_BYTES: u64 = 64 * 1024;
+
+/// A filter on how many user and assistant messages a session has, which
+/// models answered in it, and which file it touched or discussed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SessionFilter {
+    pub min_messages: Option<u64>,
+    pub max_messages: Option<u64>,
+    /// Part of a model name, ignoring case.
+    pub model: Option<String>,
+    /// A path as [`path_matches`] compares them.
+    pub file: Option<String>,
+}
+
+/// The check that ruled a file out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrefilterStage {
+    Size,
+    Peek,
+    Scan,
+}
+
+impl SessionFilter {
+    pub fn is_empty(&self) -> bool {
+        self.min_messages.is_none()
+            && self.max_messages.is_none()
+            && self.model.is_none()
+            && self.file.is_none()
+    }
+
+    /// The exact test, on a session's counted messages and models.
+    pub fn matches(&self, messages: u64, models: &[String]) -> bool {
+        self.min_messages.is_none_or(|min| messages >= min)
+            && self.max_messages.is_none_or(|max| messages <= max)
+            && self.model.as_ref().is_none_or(|needle| {
+                let needle = needle.to_lowercase();
+                models.iter().any(|m| m.to_lowercase().contains(&needle))
+            })
+    }
+
+    /// The paths of `files`, a session's tool-input paths, that the file
+    /// filter names.
+    pub fn touched<'a>(&self, files: &'a [String]) -> Vec<&'a str> {
+        match &self.file {
+            Some(wanted) => files
+                .iter()
+                .map(String::as_str)
+                .filter(|path| path_matches(path, wanted))
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Whether the filter's path occurs anywhere in `session`'s files,
+    /// ignoring ASCII case: in a message, a tool call, or its output, with
+    /// `/` or, JSON-escaped, `\` between its parts. True without a file
+    /// filter.
+    pub fn mentions(&self, session: &Session) -> Result<bool> {
+        let Some(wanted) = &self.file else {
+            return Ok(true);
+        };
+        let wanted = wanted.replace('\\', "/");
+        let wanted = wanted.trim_start_matches("./").trim_end_matches('/');
+        let mut needles = vec![wanted.to_string()];
+        if wanted.contains('/') {
+            needles.push(wanted.replace('/', r"\\"));
+        }
+        for path in session.paths() {
+            for needle in &needles {
+                if scan(path, Some(needle.as_bytes()))?.1 {
+                    return Ok(true);
+                }
+            }
+        }
+        Ok(false)
+    }
+
+    /// The check that rules `session` out without parsing it, or `None` if
+    /// it has to be parsed to tell. `--max-messages` never rules a file out
+    /// here: bytes only bound the message count from above.
+    pub fn rule_out(&self, session: &Session) -> Result<Option<PrefilterStage>> {
+        // A compressed file's size says nothing about its records.
+        let plain = session.paths().all(|path| Compression::of(path).is_none());
+        if let (Some(min), true) = (self.min_messages, plain) {
+            if session.size_bytes < min.saturating_mul(MIN_RECORD_BYTES) {
+                return Ok(Some(PrefilterStage::Size));
+            }
+        }
+        // Bytes can't be lowercased like the exact test does outside ASCII.
+        let needle = self
+            .model
+            .as_ref()
+            .filter(|m| m.is_ascii())
+            .map(|m| m.to_ascii_lowercase());
+        let mut model_seen = needle.is_none();
+        let mut peeked_whole = true;
+        if let Some(needle) = &needle {
+            for path in session.paths() {
+                let (seen, whole) = peek(path, needle.as_bytes())?;
+                model_seen |= seen;
+                peeked_whole &= whole;
+            }
+            if !model_seen && peeked_whole {
+                return Ok(Some(PrefilterStage::Peek));
+            }
+        }
+        if self.min_messages.is_none() && model_seen {
+            return Ok(None);
+        }
+        let mut lines = 0;
+        for path in session.paths() {
+            let scanned = scan(path, needle.as_deref().map(str::as_bytes))?;
+            lines += scanned.0;
+            model_seen |= scanned.1;
+        }
+        let too_few = self.min_messages.is_some_and(|min| lines < min);
+        Ok((too_few || !model_seen).then_some(PrefilterStage::Scan))
+    }
+}
+
+/// Whether `needle` occurs in the first or last [`PEEK_BYTES`] of `path`,
+/// ignoring ASCII case, and whether those covered the whole file. A
+/// compressed file can't be peeked at; it's left to the scan.
+fn peek(path: &std::path::Path, needle: &[u8]) -> Result<(bool, bool)> {
+    if Compression::of(path).is_some() {
+        return Ok((false, false));
+    }
+    let mut file =
+        File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let len = file.metadata()?.len();
+    let mut head = Vec::new();
+    (&mut file).take(PEEK_BYTES).read_to_end(&mut head)?;
+    if contains_ignore_case(&head, needle) {
+        return Ok((true, len <= PEEK_BYTES));
+    }
+    if len <= PEEK_BYTES {
+        return Ok((false, true));
+    }
+    let tail_start = len.saturating_sub(PEEK_BYTES).max(PEEK_BYTES);
+    file.seek(SeekFrom::Start(tail_start))?;
+    let mut tail = Vec::new();
+    file.read_to_end(&mut tail)?;
+    Ok((
+        contains_ignore_case(&tail, needle),
+        tail_start == PEEK_BYTES,
+    ))
+}
+
+/// Lines in `path` (an upper bound on its records) and whether `needle`
+/// occurs anywhere in it, ignoring ASCII case.
+fn scan(path: &std::path::Path, needle: Option<&[u8]>) -> Result<(u64, bool)> {
+    let mut file =
+        open_transcript(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut buf = vec![0u8; 256 * 1024];
+    // Kept from the previous chunk so a needle split across reads is found.
+    let overlap = needle.map_or(0, |n| n.len().saturating_sub(1));
+    let mut carry = Vec::new();
+    let (mut lines, mut found, mut last) = (0u64, needle.is_none(), b'\n');
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        let chunk = &buf[..n];
+        lines += chunk.iter().filter(|&&b| b == b'\n').count() as u64;
+        last = chunk[n - 1];
+        if let (Some(needle), false) = (needle, found) {
+            carry.extend_from_slice(chunk);
+            found = contains_ignore_case(&carry, needle);
+            carry.drain(..carry.len().saturating_sub(overlap));
+        }
+    }
+    if last != b'\n' {
+        lines += 1;
+    }
+    Ok((lines, found))
+}
+
+fn contains_ignore_case(haystack: &[u8], needle: &[u8]) -> bool {
+    needle.is_empty()
+        || haystack
+            .windows(needle.len())
+            .any(|w| w.eq_ignore_ascii_case(needle))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::{session_at, unique_temp_path, write_jsonl};
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn session(path: PathBuf) -> Session {
+        Session {
+            size_bytes: fs::metadata(&path).expect("stat").len(),
+            ..session_at("p", "s", path)
+        }
+    }
+
+    #[test]
+    fn cheap_checks_only_rule_out_files_that_cannot_match() {
+        let root = unique_temp_path("cc-convo-test-prefilter");
+        fs::create_dir_all(&root).expect("create temp dir");
+        let small = root.join("small.jsonl");
+        write_jsonl(
+            &small,
+            &[
+                r#"{"type":"user","message":{"content":"hi"}}"#,
+                r#"{"type":"assistant","message":{"model":"claude-opus-4-1","content":"hello"}}"#,
+            ],
+        );
+        let small = session(small);
+        let filter = |min: Option<u64>, model: Option<&str>| SessionFilter {
+            min_messages: min,
+            max_messages: None,
+            model: model.map(str::to_string),
+            file: None,
+        };
+        assert_eq!(
+            filter(Some(100), None).rule_out(&small).expect("size"),
+            Some(PrefilterStage::Size)
+        );
+        assert_eq!(
+            filter(Some(3), None).rule_out(&small).expect("scan"),
+            Some(PrefilterStage::Scan)
+        );
+        assert_eq!(
+            filter(Some(2), Some("OPUS"))
+                .rule_out(&small)
+                .expect("keep"),
+            None
+        );
+        assert_eq!(
+            filter(None, Some("sonnet")).rule_out(&small).expect("peek"),
+            Some(PrefilterStage::Peek)
+        );
+
+        // A model only named in the middle of a large file needs the scan.
+        let large = root.join("large.jsonl");
+        let filler = format!(r#"{{"type":"progress","data":"{}"}}"#, "x".repeat(1000));
+        let mut lines = vec![filler.as_str(); 200];
+        let reply =
+            r#"{"type":"assistant","message":{"model":"claude-sonnet-4-5","content":"ok"}}"#;
+        lines.insert(100, reply);
+        write_jsonl(&large, &lines);
+        let large = session(large);
+        assert_eq!(
+            filter(None, Some("sonnet")).rule_out(&large).expect("scan"),
+            None
+        );
+        assert_eq!(
+            filter(None, Some("haiku")).rule_out(&large).expect("scan"),
+            Some(PrefilterStage::Scan)
+        );
+
+        assert!(filter(Some(2), Some("opus")).matches(2, &["claude-opus-4-1".to_string()]));
+        let at_most_one = SessionFilter {
+            max_messages: Some(1),
+            ..SessionFilter::default()
+        };
+        assert!(!at_most_one.matches(2, &[]));
+        assert!(SessionFilter::default().is_empty());
+
+        let login = SessionFilter {
+            file: Some("src/auth/login.rs".to_string()),
+            ..SessionFilter::default()
+        };
+        let files = [
+            "/app/src/auth/login.rs".to_string(),
+            "/app/README.md".to_string(),
+        ];
+        assert_eq!(login.touched(&files), ["/app/src/auth/login.rs"]);
+        assert!(!login.mentions(&small).expect("scan"));
+        assert!(SessionFilter::default().mentions(&small).expect("scan"));
+
+        let windows = root.join("windows.jsonl");
+        write_jsonl(
+            &windows,
+            &[r#"{"type":"user","message":{"content":"See C:\\app\\src\\auth\\login.rs"}}"#],
+        );
+        assert!(login.mentions(&session(windows)).expect("scan"));
+        fs::remove_dir_all(&root).expect("cleanup dir");
+    }
+}
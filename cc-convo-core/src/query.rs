@@ -0,0 +1,380 @@
+//! The query language of `search --mode query`: words, quoted phrases, and
+//! `field:value` qualifiers combined with `AND`, `OR`, `NOT`, `-`, and
+//! parentheses, e.g. `role:user project:myapp "connection reset" -docker`.
+//!
+//! Juxtaposed terms are ANDed, and `AND` binds tighter than `OR`. A prefix
+//! that isn't a known field (`http://…`, `std::fs`) is searched as text.
+
+use crate::discovery::Session;
+use crate::parse::NormalizedEvent;
+use anyhow::{bail, Result};
+
+/// What a `field:` qualifier compares against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueryField {
+    /// The event's role, exactly: `user`, `assistant`, `system`, ...
+    Role,
+    /// Part of the session's project directory name.
+    Project,
+    /// Part of the model name of an assistant event.
+    Model,
+    /// Start of the session id.
+    Session,
+}
+
+impl QueryField {
+    const ALL: [(&'static str, Self); 4] = [
+        ("role", Self::Role),
+        ("project", Self::Project),
+        ("model", Self::Model),
+        ("session", Self::Session),
+    ];
+
+    fn named(name: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, field)| *field)
+    }
+}
+
+/// A parsed query.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Query {
+    /// A word or phrase the event's text must contain.
+    Text(String),
+    Field(QueryField, String),
+    Not(Box<Query>),
+    And(Vec<Query>),
+    Or(Vec<Query>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Open,
+    Close,
+    And,
+    Or,
+    Not,
+    /// A word or phrase, with the field it qualifies.
+    Term(Option<QueryField>, String),
+}
+
+impl Query {
+    pub fn parse(query: &str) -> Result<Self> {
+        let tokens = tokenize(query)?;
+        if tokens.is_empty() {
+            bail!("Empty query");
+        }
+        let mut parser = Parser { tokens, at: 0 };
+        let parsed = parser.or()?;
+        if let Some(token) = parser.tokens.get(parser.at) {
+            bail!("Unexpected {} in query", describe(token));
+        }
+        Ok(parsed)
+    }
+
+    /// Whether `event` of `session` matches, with `has_text` saying whether
+    /// the event's text contains a word or phrase. Fields ignore case.
+    pub fn matches(
+        &self,
+        session: &Session,
+        event: &NormalizedEvent,
+        has_text: &dyn Fn(&str) -> bool,
+    ) -> bool {
+        match self {
+            Self::Text(term) => has_text(term),
+            Self::Field(field, value) => {
+                let value = value.to_lowercase();
+                match field {
+                    QueryField::Role => event.role.eq_ignore_ascii_case(&value),
+                    QueryField::Project => session.project.to_lowercase().contains(&value),
+                    QueryField::Model => event
+                        .model
+                        .as_ref()
+                        .is_some_and(|m| m.to_lowercase().contains(&value)),
+                    QueryField::Session => session.id.to_lowercase().starts_with(&value),
+                }
+            }
+            Self::Not(inner) => !inner.matches(session, event, has_text),
+            Self::And(all) => all.iter().all(|q| q.matches(session, event, has_text)),
+            Self::Or(any) => any.iter().any(|q| q.matches(session, event, has_text)),
+        }
+    }
+
+    /// The query with single words `drop` picks left out of every `AND`, as
+    /// if always matched. An `AND` of nothing but such words keeps them.
+    pub fn without_words(self, drop: &dyn Fn(&str) -> bool) -> Self {
+        match self {
+            Self::And(all) => {
+                let dropped = |q: &Self| matches!(q, Self::Text(t) if !t.contains(' ') && drop(t));
+                let mut kept = if all.iter().all(dropped) {
+                    all
+                } else {
+                    all.into_iter().filter(|q| !dropped(q)).collect()
+                };
+                if kept.len() == 1 {
+                    kept.remove(0).without_words(drop)
+                } else {
+                    Self::And(kept.into_iter().map(|q| q.without_words(drop)).collect())
+                }
+            }
+            Self::Or(any) => Self::Or(any.into_iter().map(|q| q.without_words(drop)).collect()),
+            Self::Not(inner) => Self::Not(Box::new(inner.without_words(drop))),
+            other => other,
+        }
+    }
+
+    /// Words and phrases the query looks for, leaving out negated ones; used
+    /// to score hits and center previews.
+    pub fn text_terms(&self) -> Vec<&str> {
+        let mut terms = Vec::new();
+        self.collect_terms(&mut terms);
+        terms
+    }
+
+    fn collect_terms<'a>(&'a self, terms: &mut Vec<&'a str>) {
+        match self {
+            Self::Text(term) => terms.push(term),
+            Self::And(all) | Self::Or(all) => all.iter().for_each(|q| q.collect_terms(terms)),
+            Self::Field(..) | Self::Not(_) => {}
+        }
+    }
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    at: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.at)
+    }
+
+    fn or(&mut self) -> Result<Query> {
+        let mut any = vec![self.and()?];
+        while self.peek() == Some(&Token::Or) {
+            self.at += 1;
+            any.push(self.and()?);
+        }
+        Ok(if any.len() == 1 {
+            any.remove(0)
+        } else {
+            Query::Or(any)
+        })
+    }
+
+    fn and(&mut self) -> Result<Query> {
+        let mut all = vec![self.unary()?];
+        loop {
+            match self.peek() {
+                Some(Token::And) => self.at += 1,
+                Some(Token::Open | Token::Not | Token::Term(..)) => {}
+                _ => break,
+            }
+            all.push(self.unary()?);
+        }
+        Ok(if all.len() == 1 {
+            all.remove(0)
+        } else {
+            Query::And(all)
+        })
+    }
+
+    fn unary(&mut self) -> Result<Query> {
+        let token = self.tokens.get(self.at).cloned();
+        self.at += 1;
+        match token {
+            Some(Token::Not) => Ok(Query::Not(Box::new(self.unary()?))),
+            Some(Token::Open) => {
+                let inner = self.or()?;
+                if self.peek() != Some(&Token::Close) {
+                    bail!("Missing `)` in query");
+                }
+                self.at += 1;
+                Ok(inner)
+            }
+            Some(Token::Term(Some(field), value)) => Ok(Query::Field(field, value)),
+            Some(Token::Term(None, text)) => Ok(Query::Text(text)),
+            Some(token) => bail!("Unexpected {} in query", describe(&token)),
+            None => bail!("Query ends where a term was expected"),
+        }
+    }
+}
+
+fn describe(token: &Token) -> String {
+    match token {
+        Token::Open => "`(`".to_string(),
+        Token::Close => "`)`".to_string(),
+        Token::And => "`AND`".to_string(),
+        Token::Or => "`OR`".to_string(),
+        Token::Not => "`NOT`".to_string(),
+        Token::Term(_, text) => format!("`{text}`"),
+    }
+}
+
+fn tokenize(query: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = query.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        match c {
+            '(' => {
+                chars.next();
+                tokens.push(Token::Open);
+                continue;
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::Close);
+                continue;
+            }
+            '-' => {
+                chars.next();
+                tokens.push(Token::Not);
+                continue;
+            }
+            _ => {}
+        }
+        let mut word = String::new();
+        let starts_quoted = c == '"';
+        let mut quoted = false;
+        while let Some(&c) = chars.peek() {
+            if c == '"' {
+                chars.next();
+                let mut phrase = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some(c) => phrase.push(c),
+                        None => bail!("Unclosed `\"` in query"),
+                    }
+                }
+                word.push_str(&phrase);
+                quoted = true;
+            } else if c.is_whitespace() || c == '(' || c == ')' {
+                break;
+            } else {
+                word.push(c);
+                chars.next();
+            }
+        }
+        tokens.push(match word.as_str() {
+            "AND" if !quoted => Token::And,
+            "OR" if !quoted => Token::Or,
+            "NOT" if !quoted => Token::Not,
+            _ if starts_quoted => Token::Term(None, word),
+            _ => field_term(word),
+        });
+    }
+    Ok(tokens)
+}
+
+/// `name:value` for a known field name, else the whole word as text.
+fn field_term(word: String) -> Token {
+    if let Some((name, value)) = word.split_once(':') {
+        if let Some(field) = QueryField::named(name).filter(|_| !value.is_empty()) {
+            return Token::Term(Some(field), value.to_string());
+        }
+    }
+    Token::Term(None, word)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::session;
+
+    fn event(role: &str, model: Option<&str>, content: &str) -> NormalizedEvent {
+        NormalizedEvent {
+            role: role.to_string(),
+            source_type: role.to_string(),
+            timestamp: None,
+            content: content.to_string(),
+            uuid: None,
+            parent_uuid: None,
+            source: None,
+            model: model.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn queries_parse_with_precedence_and_qualifiers() {
+        let query =
+            Query::parse(r#"role:user project:myapp "connection reset" -docker"#).expect("parse");
+        assert_eq!(
+            query,
+            Query::And(vec![
+                Query::Field(QueryField::Role, "user".to_string()),
+                Query::Field(QueryField::Project, "myapp".to_string()),
+                Query::Text("connection reset".to_string()),
+                Query::Not(Box::new(Query::Text("docker".to_string()))),
+            ])
+        );
+        assert_eq!(
+            Query::parse("a b OR NOT (c AND d) http://x").expect("parse"),
+            Query::Or(vec![
+                Query::And(vec![Query::Text("a".into()), Query::Text("b".into())]),
+                Query::And(vec![
+                    Query::Not(Box::new(Query::And(vec![
+                        Query::Text("c".into()),
+                        Query::Text("d".into()),
+                    ]))),
+                    Query::Text("http://x".into()),
+                ]),
+            ])
+        );
+        assert!(Query::parse("(a OR b").is_err());
+        assert!(Query::parse("\"open").is_err());
+        assert!(Query::parse("a OR").is_err());
+    }
+
+    #[test]
+    fn queries_match_text_and_fields() {
+        let s = session("-home-user-myapp", "abc123");
+        let reply = event(
+            "assistant",
+            Some("claude-opus-4-1"),
+            "Connection reset by peer",
+        );
+        let text = reply.content.to_lowercase();
+        let has_text = |term: &str| text.contains(&term.to_lowercase());
+        let matches = |q: &str| {
+            Query::parse(q)
+                .expect("parse")
+                .matches(&s, &reply, &has_text)
+        };
+        assert!(matches(r#"model:opus "connection reset""#));
+        assert!(matches("project:MYAPP session:abc -docker"));
+        assert!(!matches("role:user OR model:sonnet"));
+        assert!(matches("(role:user OR model:opus) peer"));
+        assert_eq!(
+            Query::parse("a -b (c OR role:user)")
+                .expect("parse")
+                .text_terms(),
+            ["a", "c"]
+        );
+    }
+
+    #[test]
+    fn dropped_words_leave_ands_but_not_phrases_or_lone_terms() {
+        let drop = |word: &str| ["the", "how"].contains(&word);
+        let without = |q: &str| Query::parse(q).expect("parse").without_words(&drop);
+        assert_eq!(
+            without(r#"how the proxy "the cache" OR (the AND how)"#),
+            Query::Or(vec![
+                Query::And(vec![
+                    Query::Text("proxy".into()),
+                    Query::Text("the cache".into()),
+                ]),
+                Query::And(vec![Query::Text("the".into()), Query::Text("how".into())]),
+            ])
+        );
+        assert_eq!(without("the proxy"), Query::Text("proxy".into()));
+        assert_eq!(without("the"), Query::Text("the".into()));
+    }
+}
@@ -0,0 +1,480 @@
+//! Typed transcript records. Reading a record checks its fields against the
+//! types this build expects, so a change in Claude Code's log format shows
+//! up as a `schema` parse error instead of text quietly missing from
+//! exports. Unknown record types, block types, and fields are accepted.
+
+use crate::assets::{attach, Asset};
+use crate::parse::truncate_value;
+use crate::text::ellipsize;
+use crate::usage::TokenUsage;
+use serde::de::{Deserializer, Error as _};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+/// Block types the parser renders; others are kept as
+/// [`ContentItem::Other`] without checking their fields.
+const RENDERED_BLOCK_TYPES: &[&str] = &[
+    "text",
+    "thinking",
+    "tool_use",
+    "tool_result",
+    "image",
+    "document",
+];
+
+/// One transcript line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    pub head: RecordHead,
+    pub body: RecordBody,
+}
+
+/// Fields any record type may carry.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordHead {
+    #[serde(rename = "type")]
+    pub record_type: Option<String>,
+    pub uuid: Option<String>,
+    pub parent_uuid: Option<String>,
+    /// Set instead of `parentUuid` on the first record after a compaction.
+    pub logical_parent_uuid: Option<String>,
+    pub timestamp: Option<String>,
+    pub session_id: Option<String>,
+    /// Working directory Claude Code ran in when the record was written.
+    pub cwd: Option<String>,
+    /// Checked-out branch of the repo at `cwd`, if any.
+    pub git_branch: Option<String>,
+    /// Set on records Claude Code injects rather than the user typing them.
+    pub is_meta: Option<bool>,
+    /// Set on the assistant record Claude Code writes in place of a reply
+    /// when an API call failed for good.
+    pub is_api_error_message: Option<bool>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum RecordBody {
+    User(MessageRecord),
+    Assistant(MessageRecord),
+    System(SystemRecord),
+    Progress(ProgressRecord),
+    QueueOperation(QueueOperationRecord),
+    Summary(SummaryRecord),
+    CustomTitle(CustomTitleRecord),
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct MessageRecord {
+    pub message: Message,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Message {
+    pub id: Option<String>,
+    pub role: Option<String>,
+    pub model: Option<String>,
+    #[serde(default)]
+    pub content: MessageContent,
+    pub usage: Option<TokenUsage>,
+    pub stop_reason: Option<String>,
+}
+
+/// A message's `content`: plain text, or a list of blocks.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MessageContent {
+    Text(String),
+    Items(Vec<ContentItem>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ContentItem {
+    /// A bare string in the block list.
+    Text(String),
+    Block(ContentBlock),
+    /// A block of a type the parser doesn't render.
+    Other {
+        block_type: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ContentBlock {
+    Text {
+        text: String,
+    },
+    Thinking {
+        #[serde(default)]
+        thinking: String,
+    },
+    ToolUse {
+        id: Option<String>,
+        name: Option<String>,
+        input: Option<Value>,
+    },
+    ToolResult {
+        tool_use_id: Option<String>,
+        is_error: Option<bool>,
+        content: Option<Value>,
+    },
+    Image {
+        source: Option<MediaSource>,
+    },
+    Document {
+        source: Option<MediaSource>,
+        title: Option<String>,
+    },
+}
+
+/// Where an image or document block's bytes are: inline `base64` or
+/// `text` data, or a `url` or uploaded `file` the transcript only names.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct MediaSource {
+    #[serde(rename = "type")]
+    pub source_type: Option<String>,
+    pub media_type: Option<String>,
+    pub data: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SystemRecord {
+    pub subtype: Option<String>,
+    pub level: Option<String>,
+    /// The failed response of an `api_error` record.
+    pub error: Option<Value>,
+    /// Delay before Claude Code retries the failed call.
+    pub retry_in_ms: Option<f64>,
+    pub retry_attempt: Option<u64>,
+    pub max_retries: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ProgressRecord {
+    pub data: Option<ProgressData>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProgressData {
+    #[serde(rename = "type")]
+    pub kind: Option<String>,
+    pub hook_name: Option<String>,
+    pub command: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct QueueOperationRecord {
+    pub operation: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct SummaryRecord {
+    pub summary: Option<String>,
+}
+
+/// The name given to a session with `/rename`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CustomTitleRecord {
+    pub custom_title: Option<String>,
+}
+
+impl Record {
+    /// Type-check a parsed JSON line. The error names the field and the
+    /// type it was expected to have.
+    pub fn from_value(value: &Value) -> Result<Self, serde_json::Error> {
+        let head = RecordHead::deserialize(value)?;
+        let body = match head.record_type {
+            Some(_) => RecordBody::deserialize(value)?,
+            None => RecordBody::Other,
+        };
+        Ok(Self { head, body })
+    }
+
+    /// The `type` field, or `unknown` without one.
+    pub fn record_type(&self) -> &str {
+        self.head.record_type.as_deref().unwrap_or("unknown")
+    }
+
+    pub fn is_meta(&self) -> bool {
+        self.head.is_meta == Some(true)
+    }
+
+    /// The message of a user or assistant record.
+    pub fn message(&self) -> Option<&Message> {
+        match &self.body {
+            RecordBody::User(record) | RecordBody::Assistant(record) => Some(&record.message),
+            _ => None,
+        }
+    }
+
+    /// One line on a non-dialog record, or `None` for types without one.
+    pub fn describe(&self) -> Option<String> {
+        match &self.body {
+            RecordBody::Progress(progress) => {
+                let data = progress.data.as_ref();
+                let field = |f: fn(&ProgressData) -> &Option<String>| {
+                    data.and_then(|d| f(d).as_deref()).unwrap_or_default()
+                };
+                let kind = data.and_then(|d| d.kind.as_deref()).unwrap_or("unknown");
+                let mut s = format!("progress:{kind}");
+                let hook = field(|d| &d.hook_name);
+                if !hook.is_empty() {
+                    s.push_str(&format!(" hook={hook}"));
+                }
+                let cmd = field(|d| &d.command);
+                if !cmd.is_empty() {
+                    s.push_str(&format!(" cmd={}", ellipsize(cmd, 120)));
+                }
+                Some(s)
+            }
+            RecordBody::System(system) => Some(format!(
+                "system:{}",
+                system.subtype.as_deref().unwrap_or("unknown")
+            )),
+            RecordBody::QueueOperation(queue) => Some(format!(
+                "queue-operation:{}",
+                queue.operation.as_deref().unwrap_or("unknown")
+            )),
+            RecordBody::Other if self.record_type() == "file-history-snapshot" => {
+                Some("file-history-snapshot".to_string())
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Message {
+    /// The blocks of the content, skipping bare strings and unrendered types.
+    pub fn blocks(&self) -> impl Iterator<Item = &ContentBlock> {
+        let items = match &self.content {
+            MessageContent::Items(items) => items.as_slice(),
+            MessageContent::Text(_) => &[],
+        };
+        items.iter().filter_map(|item| match item {
+            ContentItem::Block(block) => Some(block),
+            _ => None,
+        })
+    }
+}
+
+impl Default for MessageContent {
+    fn default() -> Self {
+        Self::Items(Vec::new())
+    }
+}
+
+impl MessageContent {
+    /// The text a reader sees: text blocks only, or with `detailed` also
+    /// thinking, tool calls and results, and placeholders for attachments.
+    pub fn text(&self, detailed: bool) -> String {
+        self.render(detailed, detailed, None)
+    }
+
+    /// Text blocks and thinking, without the rest of [`Self::text`]'s
+    /// detailed output.
+    pub fn text_with_thinking(&self) -> String {
+        self.render(false, true, None)
+    }
+
+    /// [`Self::text`], except that images and documents with inline data
+    /// are decoded into `assets` and linked, detailed or not.
+    pub fn text_with_assets(&self, detailed: bool, assets: &mut Vec<Asset>) -> String {
+        self.render(detailed, detailed, Some(assets))
+    }
+
+    fn render(
+        &self,
+        detailed: bool,
+        thinking: bool,
+        mut assets: Option<&mut Vec<Asset>>,
+    ) -> String {
+        let items = match self {
+            Self::Text(text) => return text.clone(),
+            Self::Items(items) => items,
+        };
+        let mut parts = Vec::new();
+        for item in items {
+            match item {
+                ContentItem::Text(text) => parts.push(text.clone()),
+                ContentItem::Block(block) => {
+                    if let Some(part) = block.text(detailed, thinking, assets.as_deref_mut()) {
+                        parts.push(part);
+                    }
+                }
+                ContentItem::Other { .. } => {}
+            }
+        }
+        parts.join("\n")
+    }
+}
+
+impl<'de> Deserialize<'de> for MessageContent {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match Value::deserialize(deserializer)? {
+            Value::String(text) => Ok(Self::Text(text)),
+            Value::Array(items) => items
+                .into_iter()
+                .map(|item| ContentItem::deserialize(item).map_err(D::Error::custom))
+                .collect::<Result<_, _>>()
+                .map(Self::Items),
+            Value::Null => Ok(Self::default()),
+            other => Err(D::Error::custom(format!(
+                "expected message content to be a string or a list of blocks, got {other}"
+            ))),
+        }
+    }
+}
+
+impl ContentItem {
+    /// The block's `type`; `None` for bare strings and untyped blocks.
+    pub fn block_type(&self) -> Option<&str> {
+        match self {
+            Self::Text(_) => None,
+            Self::Block(block) => Some(block.block_type()),
+            Self::Other { block_type } => block_type.as_deref(),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for ContentItem {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = Value::deserialize(deserializer)?;
+        let block_type = value.get("type").and_then(Value::as_str);
+        match (&value, block_type) {
+            (Value::String(text), _) => Ok(Self::Text(text.clone())),
+            (_, Some(kind)) if RENDERED_BLOCK_TYPES.contains(&kind) => {
+                ContentBlock::deserialize(value)
+                    .map(Self::Block)
+                    .map_err(D::Error::custom)
+            }
+            (_, kind) => Ok(Self::Other {
+                block_type: kind.map(str::to_string),
+            }),
+        }
+    }
+}
+
+impl ContentBlock {
+    pub fn block_type(&self) -> &'static str {
+        match self {
+            Self::Text { .. } => "text",
+            Self::Thinking { .. } => "thinking",
+            Self::ToolUse { .. } => "tool_use",
+            Self::ToolResult { .. } => "tool_result",
+            Self::Image { .. } => "image",
+            Self::Document { .. } => "document",
+        }
+    }
+
+    /// How the block reads in an export, if it shows at all.
+    fn text(
+        &self,
+        detailed: bool,
+        with_thinking: bool,
+        assets: Option<&mut Vec<Asset>>,
+    ) -> Option<String> {
+        match self {
+            Self::Text { text } => Some(text.clone()),
+            Self::Image { .. } | Self::Document { .. } if !detailed && assets.is_none() => None,
+            Self::Image { source } => Some(match attach(source.as_ref(), assets) {
+                Some(link) => format!("![image]({link})"),
+                None => "[image omitted]".to_string(),
+            }),
+            Self::Document { source, title } => Some(match attach(source.as_ref(), assets) {
+                Some(link) => match title {
+                    Some(title) => format!("[document: {title}]({link})"),
+                    None => format!("[document]({link})"),
+                },
+                None => "[document omitted]".to_string(),
+            }),
+            Self::Thinking { thinking } if with_thinking => Some(format!("[thinking]\n{thinking}")),
+            _ if !detailed => None,
+            Self::Thinking { .. } => None,
+            Self::ToolUse { name, input, .. } => Some(format!(
+                "[tool_use] {}\n{}",
+                name.as_deref().unwrap_or("unknown"),
+                serde_json::to_string_pretty(input.as_ref().unwrap_or(&json!({})))
+                    .unwrap_or_else(|_| "{}".to_string())
+            )),
+            Self::ToolResult {
+                tool_use_id,
+                content,
+                ..
+            } => Some(format!(
+                "[tool_result] {}\n{}",
+                tool_use_id.as_deref().unwrap_or("unknown"),
+                truncate_value(content.as_ref().unwrap_or(&Value::Null), 1200)
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_are_typed_and_mismatches_name_the_field() {
+        let record = Record::from_value(&json!({
+            "type": "assistant",
+            "uuid": "a-1",
+            "parentUuid": null,
+            "message": {
+                "model": "claude-test",
+                "content": [
+                    "bare",
+                    {"type": "text", "text": "hi"},
+                    {"type": "tool_use", "id": "toolu_1", "name": "Bash", "input": {}},
+                    {"type": "server_tool_use", "anything": [1, 2]},
+                    {"no_type": true}
+                ],
+                "usage": {"input_tokens": 3, "output_tokens": 4}
+            },
+            "newField": {"nested": 1}
+        }))
+        .expect("typed");
+        assert_eq!(record.record_type(), "assistant");
+        assert_eq!(record.head.uuid.as_deref(), Some("a-1"));
+        let message = record.message().expect("message");
+        assert_eq!(message.usage.map(|u| u.total()), Some(7));
+        assert_eq!(message.content.text(false), "bare\nhi");
+        assert_eq!(
+            message
+                .blocks()
+                .map(ContentBlock::block_type)
+                .collect::<Vec<_>>(),
+            vec!["text", "tool_use"]
+        );
+        let MessageContent::Items(items) = &message.content else {
+            panic!("blocks expected");
+        };
+        assert_eq!(items[3].block_type(), Some("server_tool_use"));
+        assert_eq!(items[4].block_type(), None);
+
+        let unknown = Record::from_value(&json!({"type": "pr-link", "url": 5})).expect("other");
+        assert_eq!(unknown.body, RecordBody::Other);
+        assert_eq!(
+            Record::from_value(&json!({"type": "system", "subtype": "compact_boundary"}))
+                .expect("system")
+                .describe()
+                .as_deref(),
+            Some("system:compact_boundary")
+        );
+
+        let err = Record::from_value(&json!({"type": "user", "message": {"content": 5}}))
+            .expect_err("content must be text or blocks");
+        assert!(err.to_string().contains("message content"), "{err}");
+        let err = Record::from_value(&json!({
+            "type": "assistant",
+            "message": {"content": [{"type": "text", "text": ["not", "a", "string"]}]}
+        }))
+        .expect_err("text must be a string");
+        assert!(err.to_string().contains("invalid type"), "{err}");
+        assert!(Record::from_value(&json!({"type": "user"})).is_err());
+    }
+}
@@ -0,0 +1,254 @@
+//! Field-path inventories of transcript records, and comparing the records
+//! on disk against the inventory this build was made for, so a change in
+//! Claude Code's log format shows up before exports quietly lose data.
+
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// The inventory bundled with this build, written by `cargo xtask schema-inventory`.
+const BUNDLED_BASELINE: &str = include_str!("schema-baseline.tsv");
+
+/// Paths below these hold per-tool or per-hook payloads whose keys vary with
+/// the tool, so new or absent keys there are not format drift.
+const FREEFORM_PREFIXES: &[&str] = &[
+    "message.content[].input.",
+    "toolUseResult.",
+    "toolUseResult[].",
+    "data.",
+    "snapshot.trackedFileBackups.",
+];
+
+/// A field counts as missing once the baseline predicts at least this many
+/// sampled records would hold it, so small samples don't raise alarms.
+pub const MIN_EXPECTED_OCCURRENCES: f64 = 5.0;
+
+/// Every field path in `record`: keys joined with `.`, array elements as
+/// `[]`, and the file names keyed under `trackedFileBackups` as `{path}`.
+pub fn record_field_paths(record: &Value) -> BTreeSet<String> {
+    let mut out = BTreeSet::new();
+    collect_field_paths(record, &mut String::new(), &mut out);
+    out
+}
+
+fn collect_field_paths(value: &Value, path: &mut String, out: &mut BTreeSet<String>) {
+    let len = path.len();
+    match value {
+        Value::Object(map) => {
+            let keyed_by_path = path.rsplit('.').next() == Some("trackedFileBackups");
+            for (key, child) in map {
+                if !path.is_empty() {
+                    path.push('.');
+                }
+                path.push_str(if keyed_by_path { "{path}" } else { key });
+                out.insert(path.clone());
+                collect_field_paths(child, path, out);
+                path.truncate(len);
+            }
+        }
+        Value::Array(items) => {
+            path.push_str("[]");
+            for item in items {
+                collect_field_paths(item, path, out);
+            }
+            path.truncate(len);
+        }
+        _ => {}
+    }
+}
+
+/// How often each record type and field path occurs in a set of records.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SchemaInventory {
+    pub records: u64,
+    pub types: BTreeMap<String, u64>,
+    pub fields: BTreeMap<String, u64>,
+}
+
+impl SchemaInventory {
+    pub fn observe(&mut self, record: &Value) {
+        self.records += 1;
+        let record_type = record
+            .get("type")
+            .and_then(Value::as_str)
+            .unwrap_or("<missing>");
+        *self.types.entry(record_type.to_string()).or_default() += 1;
+        for field in record_field_paths(record) {
+            *self.fields.entry(field).or_default() += 1;
+        }
+    }
+
+    fn percent(&self, count: u64) -> f64 {
+        count as f64 / self.records.max(1) as f64 * 100.0
+    }
+
+    /// The inventory as the tab-separated baseline [`SchemaBaseline::parse`] reads.
+    pub fn render_baseline(&self) -> String {
+        let mut out = String::from(
+            "# Record types and field paths seen by `cargo xtask schema-inventory`, with the\n\
+             # percent of records holding each. Regenerate with that command; do not edit.\n",
+        );
+        out.push_str(&format!("records\t{}\n", self.records));
+        for (kind, counts) in [("type", &self.types), ("field", &self.fields)] {
+            let mut rows = counts.iter().collect::<Vec<_>>();
+            rows.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+            for (name, count) in rows {
+                out.push_str(&format!("{kind}\t{name}\t{:.6}\n", self.percent(*count)));
+            }
+        }
+        out
+    }
+}
+
+/// Percent of records holding each record type and field path in a
+/// reference inventory.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SchemaBaseline {
+    pub records: u64,
+    pub types: BTreeMap<String, f64>,
+    pub fields: BTreeMap<String, f64>,
+}
+
+/// How sampled records differ from a baseline.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct SchemaDrift {
+    pub new_record_types: Vec<String>,
+    pub new_fields: Vec<String>,
+    /// Fields common enough in the baseline that the sample should have held them.
+    pub missing_fields: Vec<String>,
+}
+
+impl SchemaDrift {
+    pub fn is_empty(&self) -> bool {
+        self.new_record_types.is_empty()
+            && self.new_fields.is_empty()
+            && self.missing_fields.is_empty()
+    }
+}
+
+impl SchemaBaseline {
+    /// The baseline bundled with this build.
+    pub fn bundled() -> Self {
+        Self::parse(BUNDLED_BASELINE).expect("bundled schema baseline is valid")
+    }
+
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut baseline = Self::default();
+        for (i, line) in text.lines().enumerate() {
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let cols = line.split('\t').collect::<Vec<_>>();
+            let percent = |col: &str| {
+                col.parse::<f64>()
+                    .with_context(|| format!("line {}: invalid percent `{col}`", i + 1))
+            };
+            match cols.as_slice() {
+                ["records", n] => {
+                    baseline.records = n
+                        .parse()
+                        .with_context(|| format!("line {}: invalid record count `{n}`", i + 1))?;
+                }
+                ["type", name, pct] => {
+                    baseline.types.insert(name.to_string(), percent(pct)?);
+                }
+                ["field", path, pct] => {
+                    baseline.fields.insert(path.to_string(), percent(pct)?);
+                }
+                _ => bail!("line {}: expected `type|field<TAB>name<TAB>percent`", i + 1),
+            }
+        }
+        Ok(baseline)
+    }
+
+    /// Record types and fields in `sample` that the baseline never saw, and
+    /// fields it predicts at least [`MIN_EXPECTED_OCCURRENCES`] times that
+    /// the sample lacks. Tool and hook payloads are left out.
+    pub fn drift(&self, sample: &SchemaInventory) -> SchemaDrift {
+        let fixed = |path: &&String| !FREEFORM_PREFIXES.iter().any(|p| path.starts_with(p));
+        SchemaDrift {
+            new_record_types: sample
+                .types
+                .keys()
+                .filter(|t| !self.types.contains_key(*t))
+                .cloned()
+                .collect(),
+            new_fields: sample
+                .fields
+                .keys()
+                .filter(fixed)
+                .filter(|f| !self.fields.contains_key(*f))
+                .cloned()
+                .collect(),
+            missing_fields: self
+                .fields
+                .iter()
+                .filter(|(path, pct)| {
+                    **pct / 100.0 * sample.records as f64 >= MIN_EXPECTED_OCCURRENCES
+                        && !sample.fields.contains_key(*path)
+                })
+                .map(|(path, _)| path)
+                .filter(fixed)
+                .cloned()
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn drift_flags_new_types_new_fields_and_missing_common_fields() {
+        let record = json!({
+            "type": "assistant",
+            "message": {"content": [{"type": "tool_use", "input": {"anything": 1}}]},
+            "snapshot": {"trackedFileBackups": {"src/main.rs": {"version": 1}}},
+        });
+        assert_eq!(
+            record_field_paths(&record).into_iter().collect::<Vec<_>>(),
+            vec![
+                "message",
+                "message.content",
+                "message.content[].input",
+                "message.content[].input.anything",
+                "message.content[].type",
+                "snapshot",
+                "snapshot.trackedFileBackups",
+                "snapshot.trackedFileBackups.{path}",
+                "snapshot.trackedFileBackups.{path}.version",
+                "type",
+            ]
+        );
+
+        let mut sample = SchemaInventory::default();
+        for i in 0..10 {
+            sample.observe(&json!({"type": "assistant", "uuid": i, "message": {"body": "hi"}}));
+        }
+        sample.observe(&json!({"type": "telemetry", "uuid": 10}));
+
+        let baseline = SchemaBaseline::parse(&sample.render_baseline()).expect("round trip");
+        assert_eq!(baseline.records, 11);
+        assert!(baseline.drift(&sample).is_empty());
+
+        let baseline = SchemaBaseline::parse(
+            "records\t100\ntype\tassistant\t100.0\nfield\ttype\t100.0\nfield\tuuid\t100.0\n\
+             field\tmessage\t90.0\nfield\tmessage.content\t90.0\nfield\tcwd\t20.0\n",
+        )
+        .expect("parse");
+        assert_eq!(
+            baseline.drift(&sample),
+            SchemaDrift {
+                new_record_types: vec!["telemetry".to_string()],
+                new_fields: vec!["message.body".to_string()],
+                // cwd at 20% predicts only two of eleven records.
+                missing_fields: vec!["message.content".to_string()],
+            }
+        );
+        assert!(SchemaBaseline::parse("field\tonly-two-columns\n").is_err());
+        assert!(SchemaBaseline::bundled().types.contains_key("assistant"));
+    }
+}
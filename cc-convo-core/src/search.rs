@@ -0,0 +1,771 @@
+//! Smart, exact, regex, and query-language search over parsed sessions.
+//! Semantic search lives in [`crate::semantic`].
+
+use crate::analysis::Analysis;
+use crate::discovery::{Session, TimeWindow};
+use crate::parse::{NormalizedEvent, ParseOptions, UnknownRecords};
+use crate::query::Query;
+use crate::stitch::parse_session_with;
+use crate::text::{ellipsize, grapheme_bounds};
+use anyhow::{bail, Context, Result};
+use rayon::prelude::*;
+use regex::{Regex, RegexBuilder};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::ops::{ControlFlow, Range};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+#[derive(Copy, Clone, Debug)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum SearchMode {
+    Smart,
+    Exact,
+    Regex,
+    /// Field qualifiers and boolean operators; see [`crate::query`].
+    Query,
+    /// Embedding similarity via [`crate::semantic::VectorIndex`].
+    Semantic,
+}
+
+#[derive(Copy, Clone, Debug, Serialize, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+#[serde(rename_all = "lowercase")]
+pub enum SpeakerFilter {
+    User,
+    Assistant,
+    Both,
+}
+
+/// What to look for and how to score it.
+#[derive(Debug, Clone)]
+pub struct SearchOptions {
+    pub query: String,
+    pub mode: SearchMode,
+    pub speaker: SpeakerFilter,
+    pub case_sensitive: bool,
+    /// Characters of context kept on each side of the match in previews.
+    pub context_chars: usize,
+    /// Only searches events inside the window when it is event based.
+    pub time_window: TimeWindow,
+    /// Whether records of unknown types are searched, and as what text.
+    pub unknown_records: Option<UnknownRecords>,
+    /// Stopwords and stemming for smart and query mode.
+    pub analysis: Analysis,
+    /// Also search the assistant's thinking blocks.
+    pub thinking: bool,
+}
+
+impl SearchOptions {
+    /// Case-insensitive smart search over both speakers.
+    pub fn smart(query: impl Into<String>) -> Self {
+        Self {
+            query: query.into(),
+            mode: SearchMode::Smart,
+            speaker: SpeakerFilter::Both,
+            case_sensitive: false,
+            context_chars: 150,
+            time_window: TimeWindow::default(),
+            unknown_records: None,
+            analysis: Analysis::default(),
+            thinking: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchHit {
+    pub session_id: String,
+    pub project: String,
+    pub path: PathBuf,
+    /// See [`SessionTitle::title`](crate::title::SessionTitle::title).
+    pub title: Option<String>,
+    pub speaker: String,
+    pub timestamp: Option<String>,
+    pub relevance: f64,
+    pub preview: String,
+    /// The whole event text the preview was cut from.
+    #[serde(skip)]
+    pub content: String,
+    /// Byte ranges of `content` the query matched, in order.
+    #[serde(skip)]
+    pub matches: Vec<Range<usize>>,
+    /// Set by [`search_sessions_explained`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub explanation: Option<Explanation>,
+}
+
+/// Search every session, returning hits sorted by descending relevance.
+pub fn search_sessions(sessions: &[Session], args: &SearchOptions) -> Result<Vec<SearchHit>> {
+    search_sessions_with(sessions, args, |_| ControlFlow::Continue(()))
+}
+
+/// Like [`search_sessions`], calling `after_file` once each session has been
+/// searched; returning `ControlFlow::Break` stops early with the hits so far.
+///
+/// Files are searched in parallel on the current rayon thread pool, so
+/// `after_file` may be called from several threads and out of order.
+pub fn search_sessions_with(
+    sessions: &[Session],
+    args: &SearchOptions,
+    after_file: impl Fn(&Session) -> ControlFlow<()> + Sync,
+) -> Result<Vec<SearchHit>> {
+    Ok(search_all(sessions, args, after_file, false)?.hits)
+}
+
+/// Hits with an [`Explanation`] each, and the events that came closest to
+/// being hits.
+#[derive(Debug, Clone, Default)]
+pub struct ExplainedSearch {
+    pub hits: Vec<SearchHit>,
+    /// Sorted like hits; each explanation says why it was left out.
+    pub near_misses: Vec<SearchHit>,
+}
+
+/// Like [`search_sessions_with`], explaining each hit's score and keeping
+/// the near misses: events with some of the query's words that weren't hits,
+/// and hits `--speaker` left out.
+pub fn search_sessions_explained(
+    sessions: &[Session],
+    args: &SearchOptions,
+    after_file: impl Fn(&Session) -> ControlFlow<()> + Sync,
+) -> Result<ExplainedSearch> {
+    search_all(sessions, args, after_file, true)
+}
+
+fn search_all(
+    sessions: &[Session],
+    args: &SearchOptions,
+    after_file: impl Fn(&Session) -> ControlFlow<()> + Sync,
+    explain: bool,
+) -> Result<ExplainedSearch> {
+    if matches!(args.mode, SearchMode::Semantic) {
+        bail!("Semantic search needs an embedder; use semantic::VectorIndex::search");
+    }
+    let regex = if matches!(args.mode, SearchMode::Regex) {
+        Some(
+            RegexBuilder::new(&args.query)
+                .case_insensitive(!args.case_sensitive)
+                .build()
+                .with_context(|| format!("Invalid regex: {}", args.query))?,
+        )
+    } else {
+        None
+    };
+    let parsed = match args.mode {
+        SearchMode::Query => Some(Query::parse(&args.query)?),
+        _ => None,
+    };
+    let query = QueryMatcher::new(args, regex, parsed);
+
+    let stopped = AtomicBool::new(false);
+    let per_file = sessions
+        .par_iter()
+        .map(|session| {
+            if stopped.load(Ordering::Relaxed) {
+                return Ok(ExplainedSearch::default());
+            }
+            let found = search_session(session, args, &query, explain)?;
+            if after_file(session).is_break() {
+                stopped.store(true, Ordering::Relaxed);
+            }
+            Ok(found)
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    // Collecting keeps session order, so ties sort the same at any --jobs.
+    let mut all = ExplainedSearch::default();
+    for found in per_file {
+        all.hits.extend(found.hits);
+        all.near_misses.extend(found.near_misses);
+    }
+    for hits in [&mut all.hits, &mut all.near_misses] {
+        hits.sort_by(|a, b| {
+            b.relevance
+                .partial_cmp(&a.relevance)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| a.session_id.cmp(&b.session_id))
+        });
+    }
+    Ok(all)
+}
+
+/// The query prepared once and shared by every file's search.
+struct QueryMatcher {
+    normalized: String,
+    /// The query's words that count, after [`Analysis::content_words`] in
+    /// smart mode.
+    tokens: Vec<String>,
+    regex: Option<Regex>,
+    parsed: Option<Query>,
+    /// Text terms of `parsed`, normalized like `normalized`.
+    terms: Vec<String>,
+    /// Stopwords left out of `tokens` or `parsed`.
+    ignored: Vec<String>,
+    /// The stem of each single-word token and term, with stemming on.
+    stems: HashMap<String, String>,
+    /// Finds the text to highlight in a matched event.
+    highlight: Option<Regex>,
+}
+
+impl QueryMatcher {
+    fn new(args: &SearchOptions, regex: Option<Regex>, parsed: Option<Query>) -> Self {
+        let normalize = |text: &str| {
+            if args.case_sensitive {
+                text.to_string()
+            } else {
+                text.to_lowercase()
+            }
+        };
+        let normalized = normalize(&args.query);
+        let mut tokens = normalized
+            .split_whitespace()
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        let mut ignored = Vec::new();
+        if matches!(args.mode, SearchMode::Smart) {
+            (tokens, ignored) = args.analysis.content_words(&tokens);
+        }
+        let parsed = parsed.map(|parsed| {
+            let all = parsed
+                .text_terms()
+                .into_iter()
+                .map(str::to_string)
+                .collect::<Vec<_>>();
+            let kept = parsed.without_words(&|word| args.analysis.is_stopword(word));
+            let left = kept.text_terms();
+            ignored.extend(all.into_iter().filter(|t| !left.contains(&t.as_str())));
+            kept
+        });
+        let terms = parsed
+            .iter()
+            .flat_map(Query::text_terms)
+            .map(normalize)
+            .collect::<Vec<_>>();
+        let stems = tokens
+            .iter()
+            .chain(&terms)
+            .filter_map(|word| Some((word.clone(), args.analysis.stem(word)?)))
+            .collect::<HashMap<_, _>>();
+        let stem_list = stems.values().cloned().collect::<Vec<_>>();
+        let highlight = match args.mode {
+            SearchMode::Regex => regex.clone(),
+            SearchMode::Query => highlighter(&terms, &stem_list, args.case_sensitive),
+            SearchMode::Exact => {
+                highlighter(std::slice::from_ref(&normalized), &[], args.case_sensitive)
+            }
+            _ => {
+                let mut needles = tokens.clone();
+                needles.push(normalized.clone());
+                highlighter(&needles, &stem_list, args.case_sensitive)
+            }
+        };
+        Self {
+            normalized,
+            tokens,
+            regex,
+            parsed,
+            terms,
+            ignored,
+            stems,
+            highlight,
+        }
+    }
+
+    /// The words whose presence counts toward a score: the query's words,
+    /// in query mode its text terms, and none for a regex.
+    fn words(&self, mode: SearchMode) -> &[String] {
+        match mode {
+            SearchMode::Query => &self.terms,
+            SearchMode::Regex => &[],
+            _ => &self.tokens,
+        }
+    }
+
+    /// Whether `haystack` contains `word` as typed, or a word of the same
+    /// stem when `text_stems` holds the stems of its words.
+    fn has_word(&self, haystack: &str, text_stems: &HashSet<String>, word: &str) -> bool {
+        haystack.contains(word)
+            || self
+                .stems
+                .get(word)
+                .is_some_and(|stem| text_stems.contains(stem))
+    }
+}
+
+/// A regex matching any of `needles` literally, longest first so a phrase
+/// wins over the words in it, or any word starting with one of `stems`;
+/// `None` with neither.
+fn highlighter(needles: &[String], stems: &[String], case_sensitive: bool) -> Option<Regex> {
+    let mut needles = needles.iter().filter(|n| !n.is_empty()).collect::<Vec<_>>();
+    needles.sort_by_key(|n| std::cmp::Reverse(n.len()));
+    let mut parts = needles.iter().map(|n| regex::escape(n)).collect::<Vec<_>>();
+    if !stems.is_empty() {
+        let stems = stems
+            .iter()
+            .map(|s| regex::escape(s))
+            .collect::<Vec<_>>()
+            .join("|");
+        // Stems are lowercase, so they match any case.
+        parts.push(format!(r"(?i:\b(?:{stems})\w*)"));
+    }
+    if parts.is_empty() {
+        return None;
+    }
+    RegexBuilder::new(&parts.join("|"))
+        .case_insensitive(!case_sensitive)
+        .build()
+        .ok()
+}
+
+/// Why an event scored what it did, for `search --explain`.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Explanation {
+    /// The query's words (its text terms in query mode) found in the event.
+    pub matched_words: Vec<String>,
+    pub missing_words: Vec<String>,
+    /// Stopwords the query's words leave out.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub ignored_words: Vec<String>,
+    /// The parts the relevance adds up to.
+    pub components: Vec<ScoreComponent>,
+    /// Why a near miss isn't a hit; `None` for hits.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub excluded: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ScoreComponent {
+    pub name: &'static str,
+    pub score: f64,
+    pub detail: String,
+}
+
+/// The score below which a smart-mode event isn't a hit.
+pub const SMART_CUTOFF: f64 = 0.15;
+
+/// How one event scored: whether it is a hit, its relevance, and what the
+/// relevance is made of.
+struct Scored {
+    matched: bool,
+    relevance: f64,
+    components: Vec<ScoreComponent>,
+}
+
+fn score_event(
+    session: &Session,
+    event: &NormalizedEvent,
+    haystack: &str,
+    text_stems: &HashSet<String>,
+    args: &SearchOptions,
+    query: &QueryMatcher,
+    found: usize,
+) -> Scored {
+    let component = |name, score, detail: String| ScoreComponent {
+        name,
+        score,
+        detail,
+    };
+    let words = query.words(args.mode).len();
+    match args.mode {
+        SearchMode::Exact => {
+            let count = haystack.matches(&query.normalized).count();
+            let relevance = if count > 0 {
+                (0.5 + (count as f64 * 0.1)).min(1.0)
+            } else {
+                0.0
+            };
+            Scored {
+                matched: count > 0,
+                relevance,
+                components: vec![
+                    component(
+                        "phrase",
+                        relevance.min(0.5),
+                        if count > 0 { "found" } else { "not found" }.to_string(),
+                    ),
+                    component(
+                        "repeats",
+                        if count > 0 {
+                            (count as f64 * 0.1).min(0.5)
+                        } else {
+                            0.0
+                        },
+                        format!("{count} occurrence(s), 0.1 each up to 1.0"),
+                    ),
+                ],
+            }
+        }
+        SearchMode::Regex => {
+            let re = query.regex.as_ref().expect("regex compiled");
+            let count = re.find_iter(&event.content).count();
+            let relevance = if count > 0 { 0.8 } else { 0.0 };
+            Scored {
+                matched: count > 0,
+                relevance,
+                components: vec![component(
+                    "pattern",
+                    relevance,
+                    format!("{count} match(es); any match scores 0.8"),
+                )],
+            }
+        }
+        SearchMode::Smart => {
+            let phrase = if haystack.contains(&query.normalized) {
+                0.6
+            } else {
+                0.0
+            };
+            let overlap = if words == 0 {
+                0.0
+            } else {
+                (found as f64 / words as f64) * 0.4
+            };
+            let score = phrase + overlap;
+            Scored {
+                matched: score > SMART_CUTOFF,
+                relevance: score.min(1.0),
+                components: vec![
+                    component(
+                        "phrase",
+                        phrase,
+                        if phrase > 0.0 {
+                            "whole query found"
+                        } else {
+                            "whole query not found"
+                        }
+                        .to_string(),
+                    ),
+                    component(
+                        "token overlap",
+                        overlap,
+                        format!("{found} of {words} word(s) found, x 0.4"),
+                    ),
+                ],
+            }
+        }
+        SearchMode::Query => {
+            let parsed = query.parsed.as_ref().expect("query parsed");
+            let has_text = |term: &str| {
+                let term = if args.case_sensitive {
+                    term.to_string()
+                } else {
+                    term.to_lowercase()
+                };
+                query.has_word(haystack, text_stems, &term)
+            };
+            let matched = parsed.matches(session, event, &has_text);
+            let terms = 0.5 * found as f64 / words.max(1) as f64;
+            let base = if matched { 0.5 } else { 0.0 };
+            Scored {
+                matched,
+                relevance: if matched { base + terms } else { terms },
+                components: vec![
+                    component(
+                        "query",
+                        base,
+                        if matched { "matches" } else { "doesn't match" }.to_string(),
+                    ),
+                    component(
+                        "terms",
+                        terms,
+                        format!("{found} of {words} text term(s) found, x 0.5"),
+                    ),
+                ],
+            }
+        }
+        SearchMode::Semantic => unreachable!("rejected before searching"),
+    }
+}
+
+fn search_session(
+    session: &Session,
+    args: &SearchOptions,
+    query: &QueryMatcher,
+    explain: bool,
+) -> Result<ExplainedSearch> {
+    let options = ParseOptions {
+        detailed: false,
+        unknown_records: args.unknown_records,
+        thinking: args.thinking,
+        ..ParseOptions::default()
+    };
+    let mut parsed = parse_session_with(session, &options)?;
+    args.time_window.retain_events(&mut parsed.events);
+    let title = parsed.title.title().map(str::to_string);
+    let mut found = ExplainedSearch::default();
+    for event in parsed.events {
+        let speaker_ok = match args.speaker {
+            SpeakerFilter::Both => true,
+            SpeakerFilter::User => event.role == "user",
+            SpeakerFilter::Assistant => event.role == "assistant",
+        };
+        if !speaker_ok && !explain {
+            continue;
+        }
+
+        let haystack = if args.case_sensitive {
+            event.content.clone()
+        } else {
+            event.content.to_lowercase()
+        };
+        let words = match args.mode {
+            SearchMode::Smart | SearchMode::Query => query.words(args.mode),
+            _ if explain => query.words(args.mode),
+            _ => &[],
+        };
+        let text_stems = if query.stems.is_empty() {
+            HashSet::new()
+        } else {
+            args.analysis.text_stems(&haystack)
+        };
+        let (matched_words, missing_words): (Vec<_>, Vec<_>) = words
+            .iter()
+            .partition(|word| query.has_word(&haystack, &text_stems, word));
+        let scored = score_event(
+            session,
+            &event,
+            &haystack,
+            &text_stems,
+            args,
+            query,
+            matched_words.len(),
+        );
+        let excluded = if !explain {
+            None
+        } else if scored.matched && !speaker_ok {
+            Some(format!(
+                "from the {}, and --speaker is {}",
+                event.role,
+                match args.speaker {
+                    SpeakerFilter::User => "user",
+                    _ => "assistant",
+                }
+            ))
+        } else if scored.matched || !speaker_ok || matched_words.is_empty() {
+            None
+        } else {
+            Some(match args.mode {
+                SearchMode::Smart => format!(
+                    "score {:.2} is not above the {SMART_CUTOFF} cutoff",
+                    scored.relevance
+                ),
+                SearchMode::Exact => format!(
+                    "{} of {} word(s) found, but not the exact phrase",
+                    matched_words.len(),
+                    query.tokens.len()
+                ),
+                _ => format!(
+                    "{} of {} text term(s) found, but the query's operators or qualifiers rule it out",
+                    matched_words.len(),
+                    query.terms.len()
+                ),
+            })
+        };
+        let near_miss = excluded.is_some();
+        let is_hit = scored.matched && speaker_ok;
+        if !is_hit && !near_miss {
+            continue;
+        }
+
+        let around = match args.mode {
+            SearchMode::Query => query
+                .terms
+                .iter()
+                .find(|term| haystack.contains(term.as_str()))
+                .map_or("", String::as_str),
+            // Near misses lack the whole query; show a word they do have.
+            _ if near_miss => matched_words.first().map_or("", |word| word.as_str()),
+            _ => args.query.as_str(),
+        };
+        let preview = build_context_preview(
+            &event.content,
+            around,
+            args.context_chars,
+            args.case_sensitive,
+        );
+        let matches = query
+            .highlight
+            .iter()
+            .flat_map(|re| re.find_iter(&event.content))
+            .map(|m| m.range())
+            .filter(|range| !range.is_empty())
+            .collect();
+        let explanation = explain.then(|| Explanation {
+            matched_words: matched_words.into_iter().cloned().collect(),
+            missing_words: missing_words.into_iter().cloned().collect(),
+            ignored_words: query.ignored.clone(),
+            components: scored.components,
+            excluded,
+        });
+        let hit = SearchHit {
+            session_id: session.id.clone(),
+            project: session.project.clone(),
+            path: session.path.clone(),
+            title: title.clone(),
+            speaker: event.role,
+            timestamp: event.timestamp,
+            relevance: scored.relevance,
+            preview,
+            content: event.content,
+            matches,
+            explanation,
+        };
+        if near_miss {
+            found.near_misses.push(hit);
+        } else {
+            found.hits.push(hit);
+        }
+    }
+    Ok(found)
+}
+
+pub fn build_context_preview(
+    text: &str,
+    query: &str,
+    context_chars: usize,
+    case_sensitive: bool,
+) -> String {
+    if query.is_empty() {
+        return ellipsize(&text.replace('\n', " "), context_chars.saturating_mul(2));
+    }
+
+    let matched_range = if case_sensitive {
+        text.find(query).map(|start| (start, query.len()))
+    } else {
+        let escaped = regex::escape(query);
+        RegexBuilder::new(&escaped)
+            .case_insensitive(true)
+            .build()
+            .ok()
+            .and_then(|re| re.find(text).map(|m| (m.start(), m.end() - m.start())))
+    };
+
+    if let Some((match_start, match_len)) = matched_range {
+        // Widen the match to whole grapheme clusters, then take up to
+        // `context_chars` clusters on either side.
+        let bounds = grapheme_bounds(text);
+        let last = bounds.len() - 1;
+        let first_match = bounds.partition_point(|&b| b <= match_start) - 1;
+        let past_match = bounds.partition_point(|&b| b < match_start + match_len);
+        let start = first_match.saturating_sub(context_chars);
+        let end = (past_match + context_chars).min(last);
+
+        let mut preview = String::new();
+        if start > 0 {
+            preview.push_str("...");
+        }
+        preview.push_str(&text[bounds[start]..bounds[end]]);
+        if end < last {
+            preview.push_str("...");
+        }
+        preview.replace('\n', " ")
+    } else {
+        ellipsize(&text.replace('\n', " "), context_chars.saturating_mul(2))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::{session_at, unique_temp_path, write_jsonl};
+    use std::fs;
+
+    #[test]
+    fn build_context_preview_falls_back_to_ellipsized_text() {
+        let text = "alpha beta gamma delta epsilon";
+        let preview = build_context_preview(text, "notfound", 5, false);
+        assert!(preview.contains("..."));
+        assert!(preview.len() <= 13);
+    }
+
+    #[test]
+    fn build_context_preview_handles_unicode_without_panicking() {
+        let text = "prefix ══════ suffix";
+        let preview = build_context_preview(text, "notfound", 8, false);
+        assert!(preview.contains("..."));
+    }
+
+    #[test]
+    fn explained_search_scores_hits_and_keeps_near_misses() {
+        let dir = unique_temp_path("cc-convo-test-explain");
+        fs::create_dir_all(&dir).expect("create dir");
+        let path = dir.join("s.jsonl");
+        write_jsonl(
+            &path,
+            &[
+                r#"{"type":"user","message":{"role":"user","content":"Why is the login test flaky?"}}"#,
+                r#"{"type":"assistant","message":{"role":"assistant","content":"The login cache races the test."}}"#,
+                r#"{"type":"user","message":{"role":"user","content":"Does the cache expire?"}}"#,
+            ],
+        );
+        let session = Session {
+            modified_epoch: 1,
+            ..session_at("p", "s", path)
+        };
+        let options = SearchOptions {
+            speaker: SpeakerFilter::User,
+            ..SearchOptions::smart("flaky login test")
+        };
+        let found = search_sessions_explained(&[session], &options, |_| ControlFlow::Continue(()))
+            .expect("search");
+
+        assert_eq!(found.hits.len(), 1);
+        let explanation = found.hits[0].explanation.as_ref().expect("explained");
+        assert_eq!(explanation.missing_words, Vec::<String>::new());
+        let parts = explanation
+            .components
+            .iter()
+            .map(|c| (c.name, c.score))
+            .collect::<Vec<_>>();
+        assert_eq!(parts, [("phrase", 0.0), ("token overlap", 0.4)]);
+
+        // The reply would be a hit but for --speaker; the last prompt shares
+        // none of the words, so it isn't close enough to list.
+        let reasons = found
+            .near_misses
+            .iter()
+            .map(|miss| miss.explanation.as_ref().and_then(|e| e.excluded.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            reasons,
+            [Some(
+                "from the assistant, and --speaker is user".to_string()
+            )]
+        );
+        fs::remove_dir_all(&dir).expect("cleanup dir");
+    }
+
+    #[test]
+    fn thinking_is_searched_only_when_asked() {
+        let dir = unique_temp_path("cc-convo-test-search-thinking");
+        fs::create_dir_all(&dir).expect("create dir");
+        let path = dir.join("s.jsonl");
+        write_jsonl(
+            &path,
+            &[
+                r#"{"type":"user","message":{"role":"user","content":"Why does deploy hang?"}}"#,
+                r#"{"type":"assistant","message":{"role":"assistant","content":[{"type":"thinking","thinking":"The healthcheck hits port 8081, not 8080."},{"type":"text","text":"Check the healthcheck port."}]}}"#,
+            ],
+        );
+        let session = Session {
+            modified_epoch: 1,
+            ..session_at("p", "s", path)
+        };
+        let sessions = [session];
+        let search = |thinking: bool| {
+            let options = SearchOptions {
+                thinking,
+                ..SearchOptions::smart("8081")
+            };
+            search_sessions(&sessions, &options).expect("search")
+        };
+
+        assert!(search(false).is_empty());
+        let hits = search(true);
+        assert_eq!(hits.len(), 1);
+        assert!(hits[0].content.starts_with("[thinking]\n"));
+        assert!(hits[0].content.ends_with("Check the healthcheck port."));
+        fs::remove_dir_all(&dir).expect("cleanup dir");
+    }
+}
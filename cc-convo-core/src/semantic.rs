@@ -0,0 +1,362 @@
+//! Embedding-based search: message chunks are embedded once into an on-disk
+//! index and ranked against the query by cosine similarity.
+
+use crate::discovery::Session;
+use crate::fs::write_atomic;
+use crate::search::{SearchHit, SearchOptions, SpeakerFilter};
+use crate::stitch::parse_session;
+use crate::text::ellipsize;
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::ops::ControlFlow;
+use std::path::{Path, PathBuf};
+
+/// Words per chunk; longer messages are split so one topic doesn't drown
+/// in the rest of a long answer.
+pub const CHUNK_WORDS: usize = 200;
+
+/// Chunks sent to an [`Embedder`] per call.
+const EMBED_BATCH: usize = 64;
+
+/// Turns text into vectors. Implement this to plug in a model or API.
+pub trait Embedder {
+    /// Identifies the model; an index built by another model is discarded.
+    fn model_id(&self) -> String;
+
+    /// One vector per input, all of the same length.
+    fn embed(&mut self, texts: &[&str]) -> Result<Vec<Vec<f32>>>;
+}
+
+/// Offline embedder hashing words and character trigrams into a fixed-size
+/// vector. It matches inflections and shared word parts, not synonyms; use a
+/// model-backed [`Embedder`] for that.
+#[derive(Debug, Clone)]
+pub struct HashingEmbedder {
+    pub dims: usize,
+}
+
+impl Default for HashingEmbedder {
+    fn default() -> Self {
+        Self { dims: 512 }
+    }
+}
+
+impl HashingEmbedder {
+    fn embed_one(&self, text: &str) -> Vec<f32> {
+        let mut vector = vec![0.0f32; self.dims];
+        let mut add = |feature: &str, weight: f32| {
+            let hash = fnv1a(feature.as_bytes());
+            let sign = if hash & 1 == 0 { 1.0 } else { -1.0 };
+            vector[(hash >> 1) as usize % self.dims] += sign * weight;
+        };
+        for word in text
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|w| !w.is_empty())
+            .map(str::to_lowercase)
+        {
+            add(&word, 1.0);
+            let padded = format!(" {word} ").chars().collect::<Vec<_>>();
+            for gram in padded.windows(3) {
+                add(&gram.iter().collect::<String>(), 0.5);
+            }
+        }
+        normalize(&mut vector);
+        vector
+    }
+}
+
+impl Embedder for HashingEmbedder {
+    fn model_id(&self) -> String {
+        format!("hashing-{}", self.dims)
+    }
+
+    fn embed(&mut self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+        Ok(texts.iter().map(|t| self.embed_one(t)).collect())
+    }
+}
+
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &b| {
+        (hash ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+fn normalize(vector: &mut [f32]) {
+    let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|x| *x /= norm);
+    }
+}
+
+/// Cosine similarity; 0 when either vector is all zeros.
+pub fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    let dot = a.iter().zip(b).map(|(x, y)| x * y).sum::<f32>();
+    let norms =
+        a.iter().map(|x| x * x).sum::<f32>().sqrt() * b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norms > 0.0 {
+        dot / norms
+    } else {
+        0.0
+    }
+}
+
+/// Split `text` into chunks of at most `max_words` whitespace-separated words.
+pub fn chunk_text(text: &str, max_words: usize) -> Vec<String> {
+    let words = text.split_whitespace().collect::<Vec<_>>();
+    words
+        .chunks(max_words.max(1))
+        .map(|chunk| chunk.join(" "))
+        .collect()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexedChunk {
+    pub role: String,
+    pub timestamp: Option<String>,
+    pub text: String,
+    pub vector: Vec<f32>,
+}
+
+/// Embedded chunks of one transcript, valid while its mtime and size match.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexedFile {
+    pub modified_epoch: i64,
+    pub size_bytes: u64,
+    pub chunks: Vec<IndexedChunk>,
+    /// The session's title when it was indexed.
+    #[serde(default)]
+    pub title: Option<String>,
+}
+
+/// On-disk vector index keyed by transcript path.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct VectorIndex {
+    pub model: String,
+    pub files: BTreeMap<PathBuf, IndexedFile>,
+}
+
+impl VectorIndex {
+    /// Read the index at `path`. A missing file, or one built by a model
+    /// other than `model`, yields an empty index.
+    pub fn load(path: &Path, model: &str) -> Result<Self> {
+        let empty = Self {
+            model: model.to_string(),
+            files: BTreeMap::new(),
+        };
+        let raw = match fs::read_to_string(path) {
+            Ok(raw) => raw,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(empty),
+            Err(err) => {
+                return Err(err).with_context(|| format!("Failed to read {}", path.display()))
+            }
+        };
+        let index: Self = serde_json::from_str(&raw)
+            .with_context(|| format!("Invalid semantic index {}", path.display()))?;
+        Ok(if index.model == model { index } else { empty })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        write_atomic(path, serde_json::to_string(self)?)
+    }
+
+    /// Embed sessions that are new or changed since they were indexed, and
+    /// drop entries whose transcript no longer exists. `after_file` runs once
+    /// per session and can stop early; returns the number of files embedded.
+    pub fn update(
+        &mut self,
+        sessions: &[Session],
+        embedder: &mut dyn Embedder,
+        mut after_file: impl FnMut(&Session) -> ControlFlow<()>,
+    ) -> Result<usize> {
+        self.files.retain(|path, _| path.exists());
+        let mut embedded = 0;
+        for session in sessions {
+            let fresh = self.files.get(&session.path).is_some_and(|f| {
+                f.modified_epoch == session.modified_epoch && f.size_bytes == session.size_bytes
+            });
+            if !fresh {
+                let (chunks, title) = embed_session(session, embedder)?;
+                self.files.insert(
+                    session.path.clone(),
+                    IndexedFile {
+                        modified_epoch: session.modified_epoch,
+                        size_bytes: session.size_bytes,
+                        chunks,
+                        title,
+                    },
+                );
+                embedded += 1;
+            }
+            if after_file(session).is_break() {
+                break;
+            }
+        }
+        Ok(embedded)
+    }
+
+    /// Chunks of `sessions` ranked by similarity to `args.query`. Only
+    /// sessions already in the index are searched.
+    pub fn search(
+        &self,
+        sessions: &[Session],
+        args: &SearchOptions,
+        embedder: &mut dyn Embedder,
+    ) -> Result<Vec<SearchHit>> {
+        let query = embedder
+            .embed(&[args.query.as_str()])?
+            .pop()
+            .context("Embedder returned no vector for the query")?;
+        let mut hits = Vec::new();
+        for session in sessions {
+            let Some(file) = self.files.get(&session.path) else {
+                continue;
+            };
+            for chunk in &file.chunks {
+                let speaker_ok = match args.speaker {
+                    SpeakerFilter::Both => true,
+                    SpeakerFilter::User => chunk.role == "user",
+                    SpeakerFilter::Assistant => chunk.role == "assistant",
+                };
+                if !speaker_ok || !args.time_window.admits(chunk.timestamp.as_deref()) {
+                    continue;
+                }
+                let relevance = f64::from(cosine(&query, &chunk.vector));
+                if relevance <= 0.0 {
+                    continue;
+                }
+                hits.push(SearchHit {
+                    session_id: session.id.clone(),
+                    project: session.project.clone(),
+                    path: session.path.clone(),
+                    title: file.title.clone(),
+                    speaker: chunk.role.clone(),
+                    timestamp: chunk.timestamp.clone(),
+                    relevance,
+                    preview: ellipsize(&chunk.text, args.context_chars.saturating_mul(2)),
+                    content: chunk.text.clone(),
+                    // Similar meaning, not shared words: nothing to mark.
+                    matches: Vec::new(),
+                    explanation: None,
+                });
+            }
+        }
+        hits.sort_by(|a, b| {
+            b.relevance
+                .partial_cmp(&a.relevance)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| a.session_id.cmp(&b.session_id))
+        });
+        Ok(hits)
+    }
+}
+
+/// The session's chunks, embedded, and its title.
+fn embed_session(
+    session: &Session,
+    embedder: &mut dyn Embedder,
+) -> Result<(Vec<IndexedChunk>, Option<String>)> {
+    let parsed = parse_session(session, false)?;
+    let mut chunks = Vec::new();
+    for event in parsed.events {
+        for text in chunk_text(&event.content, CHUNK_WORDS) {
+            chunks.push(IndexedChunk {
+                role: event.role.clone(),
+                timestamp: event.timestamp.clone(),
+                text,
+                vector: Vec::new(),
+            });
+        }
+    }
+    for batch in chunks.chunks_mut(EMBED_BATCH) {
+        let texts = batch.iter().map(|c| c.text.as_str()).collect::<Vec<_>>();
+        let vectors = embedder
+            .embed(&texts)
+            .with_context(|| format!("Failed to embed {}", session.path.display()))?;
+        if vectors.len() != batch.len() {
+            bail!(
+                "Embedder returned {} vectors for {} chunks",
+                vectors.len(),
+                batch.len()
+            );
+        }
+        for (chunk, vector) in batch.iter_mut().zip(vectors) {
+            chunk.vector = vector;
+        }
+    }
+    Ok((chunks, parsed.title.title().map(str::to_string)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::{session_at, unique_temp_path, write_jsonl};
+
+    #[test]
+    fn hashing_embedder_prefers_related_text() {
+        let mut embedder = HashingEmbedder::default();
+        let vectors = embedder
+            .embed(&[
+                "configuring the nginx reverse proxy",
+                "nginx proxies requests to the backend",
+                "bake the bread for forty minutes",
+            ])
+            .expect("embed");
+        assert!(cosine(&vectors[0], &vectors[1]) > cosine(&vectors[0], &vectors[2]));
+        assert!((cosine(&vectors[0], &vectors[0]) - 1.0).abs() < 1e-5);
+        assert_eq!(chunk_text("a b c d e", 2), vec!["a b", "c d", "e"]);
+    }
+
+    #[test]
+    fn index_reuses_fresh_files_and_ranks_chunks() {
+        let dir = unique_temp_path("cc-convo-semantic");
+        fs::create_dir_all(&dir).expect("create dir");
+        let path = dir.join("s.jsonl");
+        write_jsonl(
+            &path,
+            &[
+                r#"{"type":"user","message":{"role":"user","content":"How do I set up the nginx proxy?"}}"#,
+                r#"{"type":"user","message":{"role":"user","content":"Recipe for sourdough bread"}}"#,
+            ],
+        );
+        let meta = fs::metadata(&path).expect("metadata");
+        let session = Session {
+            modified_epoch: 1,
+            size_bytes: meta.len(),
+            ..session_at("p", "s", &path)
+        };
+        let sessions = vec![session];
+        let mut embedder = HashingEmbedder::default();
+        let index_path = dir.join("index.json");
+
+        let mut index = VectorIndex::load(&index_path, &embedder.model_id()).expect("load");
+        let embedded = index
+            .update(&sessions, &mut embedder, |_| ControlFlow::Continue(()))
+            .expect("update");
+        assert_eq!(embedded, 1);
+        index.save(&index_path).expect("save");
+
+        let mut index = VectorIndex::load(&index_path, &embedder.model_id()).expect("reload");
+        let embedded = index
+            .update(&sessions, &mut embedder, |_| ControlFlow::Continue(()))
+            .expect("update");
+        assert_eq!(embedded, 0);
+        let hits = index
+            .search(
+                &sessions,
+                &SearchOptions::smart("nginx proxies"),
+                &mut embedder,
+            )
+            .expect("search");
+        assert!(hits[0].preview.contains("nginx"));
+
+        let other = VectorIndex::load(&index_path, "another-model").expect("load");
+        assert!(other.files.is_empty());
+        fs::remove_dir_all(&dir).expect("cleanup");
+    }
+}
@@ -0,0 +1,273 @@
+//! Dividing a long session into the separate pieces of work it holds, since
+//! Claude Code keeps appending to one file across days and unrelated tasks.
+
+use crate::exchange::is_prompt;
+use crate::export::ExportDocument;
+use crate::parse::NormalizedEvent;
+use crate::semantic::{cosine, Embedder};
+use anyhow::{anyhow, bail, Result};
+use chrono::{DateTime, Duration, Utc};
+use std::fmt;
+use std::str::FromStr;
+
+/// Prompts shorter than this ("yes", "go on") carry no topic, so they never
+/// start a part.
+const MIN_TOPIC_WORDS: usize = 4;
+
+/// Where to cut a session.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SplitBy {
+    /// Before a prompt written at least this long after the previous event.
+    Gap(Duration),
+    /// Before a prompt unlike the ones of the part so far.
+    Topic,
+}
+
+impl FromStr for SplitBy {
+    type Err = anyhow::Error;
+
+    /// `topic`, or `gap:` followed by a number and a unit: `s`, `m`, `h`, or
+    /// `d` (`gap:90m`, `gap:2h`).
+    fn from_str(raw: &str) -> Result<Self> {
+        if raw == "topic" {
+            return Ok(Self::Topic);
+        }
+        let Some(gap) = raw.strip_prefix("gap:") else {
+            bail!("Invalid split {raw:?}; use gap:<duration> (e.g. gap:2h) or topic");
+        };
+        let unit_at = gap.find(|c: char| !c.is_ascii_digit()).unwrap_or(gap.len());
+        let (amount, unit) = gap.split_at(unit_at);
+        let amount = amount
+            .parse::<i64>()
+            .map_err(|_| anyhow!("Invalid gap {gap:?}; expected a number and s, m, h, or d"))?;
+        let gap = match unit {
+            "s" => Duration::seconds(amount),
+            "m" => Duration::minutes(amount),
+            "h" => Duration::hours(amount),
+            "d" => Duration::days(amount),
+            _ => bail!("Invalid gap unit {unit:?}; use s, m, h, or d"),
+        };
+        if gap <= Duration::zero() {
+            bail!("The split gap must be greater than zero");
+        }
+        Ok(Self::Gap(gap))
+    }
+}
+
+impl fmt::Display for SplitBy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self::Gap(gap) = self else {
+            return f.write_str("topic");
+        };
+        let secs = gap.num_seconds();
+        match [(86_400, 'd'), (3_600, 'h'), (60, 'm')]
+            .into_iter()
+            .find(|(unit, _)| secs % unit == 0)
+        {
+            Some((unit, suffix)) => write!(f, "gap:{}{suffix}", secs / unit),
+            None => write!(f, "gap:{secs}s"),
+        }
+    }
+}
+
+fn parse_timestamp(event: &NormalizedEvent) -> Option<DateTime<Utc>> {
+    let raw = event.timestamp.as_deref()?;
+    DateTime::parse_from_rfc3339(raw)
+        .ok()
+        .map(|t| t.with_timezone(&Utc))
+}
+
+fn starts_prompt(event: &NormalizedEvent) -> bool {
+    event.role == "user" && is_prompt(event.content.trim())
+}
+
+/// Indices of the events that begin each part after the first: prompts
+/// written at least `gap` after the event before them.
+pub fn split_by_gap(events: &[NormalizedEvent], gap: Duration) -> Vec<usize> {
+    let mut starts = Vec::new();
+    let mut last = None;
+    for (i, event) in events.iter().enumerate() {
+        let Some(at) = parse_timestamp(event) else {
+            continue;
+        };
+        if starts_prompt(event) && last.is_some_and(|last| at - last >= gap) {
+            starts.push(i);
+        }
+        last = Some(at);
+    }
+    starts
+}
+
+/// Indices of the events that begin each part after the first: prompts
+/// whose similarity to the part's prompts so far is below `similarity`,
+/// once the part holds at least `min_prompts` of them.
+pub fn split_by_topic(
+    events: &[NormalizedEvent],
+    embedder: &mut dyn Embedder,
+    similarity: f32,
+    min_prompts: usize,
+) -> Result<Vec<usize>> {
+    let prompts = events
+        .iter()
+        .enumerate()
+        .filter(|(_, e)| {
+            starts_prompt(e) && e.content.split_whitespace().count() >= MIN_TOPIC_WORDS
+        })
+        .map(|(i, e)| (i, e.content.trim()))
+        .collect::<Vec<_>>();
+    if prompts.is_empty() {
+        return Ok(Vec::new());
+    }
+    let texts = prompts.iter().map(|(_, text)| *text).collect::<Vec<_>>();
+    let vectors = embedder.embed(&texts)?;
+    if vectors.len() != texts.len() {
+        bail!(
+            "Embedder returned {} vectors for {} prompts",
+            vectors.len(),
+            texts.len()
+        );
+    }
+
+    let mut starts = Vec::new();
+    let mut centroid = vectors[0].clone();
+    let mut in_part = 1;
+    for ((i, _), vector) in prompts.iter().zip(&vectors).skip(1) {
+        if in_part >= min_prompts.max(1) && cosine(&centroid, vector) < similarity {
+            starts.push(*i);
+            centroid = vector.clone();
+            in_part = 1;
+            continue;
+        }
+        for (sum, x) in centroid.iter_mut().zip(vector) {
+            *sum += x;
+        }
+        in_part += 1;
+    }
+    Ok(starts)
+}
+
+/// `doc` cut before each of `starts`, as one document per part. Parts keep
+/// the session id; their short ids get a `-partN` suffix and their date is
+/// that of their last event.
+pub fn split_document(doc: &ExportDocument, starts: &[usize]) -> Vec<ExportDocument> {
+    let mut bounds = vec![0];
+    bounds.extend(
+        starts
+            .iter()
+            .copied()
+            .filter(|&s| s > 0 && s < doc.events.len()),
+    );
+    bounds.push(doc.events.len());
+    bounds.dedup();
+    bounds
+        .windows(2)
+        .enumerate()
+        .map(|(n, range)| {
+            let events = doc.events[range[0]..range[1]].to_vec();
+            let modified_iso = events
+                .iter()
+                .rev()
+                .find_map(|e| e.timestamp.clone())
+                .unwrap_or_else(|| doc.modified_iso.clone());
+            ExportDocument {
+                session_id: doc.session_id.clone(),
+                session_short: format!("{}-part{}", doc.session_short, n + 1),
+                project: doc.project.clone(),
+                source_path: doc.source_path.clone(),
+                modified_iso,
+                event_count: events.len(),
+                events,
+                tree: None,
+                title: doc.title.clone(),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::semantic::HashingEmbedder;
+    use std::path::PathBuf;
+
+    fn event(role: &str, at: &str, content: &str) -> NormalizedEvent {
+        NormalizedEvent {
+            role: role.to_string(),
+            source_type: role.to_string(),
+            timestamp: Some(at.to_string()),
+            content: content.to_string(),
+            uuid: None,
+            parent_uuid: None,
+            source: None,
+            model: None,
+        }
+    }
+
+    #[test]
+    fn splits_at_prompts_after_a_gap_or_a_change_of_topic() {
+        assert_eq!(
+            "gap:90m".parse::<SplitBy>().expect("gap"),
+            SplitBy::Gap(Duration::minutes(90))
+        );
+        assert_eq!("topic".parse::<SplitBy>().expect("topic"), SplitBy::Topic);
+        assert_eq!(SplitBy::Gap(Duration::minutes(120)).to_string(), "gap:2h");
+        assert!("gap:2w".parse::<SplitBy>().is_err());
+        assert!("gap:0h".parse::<SplitBy>().is_err());
+
+        let events = vec![
+            event(
+                "user",
+                "2026-03-01T09:00:00Z",
+                "Fix the flaky login integration test",
+            ),
+            event("assistant", "2026-03-01T09:01:00Z", "Done."),
+            event(
+                "user",
+                "2026-03-01T09:05:00Z",
+                "Make the login integration test retry",
+            ),
+            event("assistant", "2026-03-01T09:06:00Z", "Retrying now."),
+            // Tool results never start a part, however late they arrive.
+            event("user", "2026-03-01T13:00:00Z", "[tool_result] toolu_1\nok"),
+            event(
+                "user",
+                "2026-03-02T10:00:00Z",
+                "Write release notes for version two",
+            ),
+            event("assistant", "2026-03-02T10:02:00Z", "Here they are."),
+        ];
+        assert_eq!(split_by_gap(&events, Duration::hours(2)), vec![5]);
+
+        let mut embedder = HashingEmbedder::default();
+        let topics = split_by_topic(&events, &mut embedder, 0.2, 1).expect("topics");
+        assert_eq!(topics, vec![5]);
+
+        let doc = ExportDocument {
+            session_id: "s".to_string(),
+            session_short: "s".to_string(),
+            project: "p".to_string(),
+            source_path: PathBuf::from("s.jsonl"),
+            modified_iso: String::new(),
+            event_count: events.len(),
+            events,
+            tree: None,
+            title: None,
+        };
+        let parts = split_document(&doc, &topics);
+        assert_eq!(
+            parts
+                .iter()
+                .map(|p| (
+                    p.session_short.as_str(),
+                    p.event_count,
+                    p.modified_iso.as_str()
+                ))
+                .collect::<Vec<_>>(),
+            vec![
+                ("s-part1", 5, "2026-03-01T13:00:00Z"),
+                ("s-part2", 2, "2026-03-02T10:02:00Z"),
+            ]
+        );
+        assert_eq!(split_document(&doc, &[]).len(), 1);
+    }
+}
@@ -0,0 +1,223 @@
+//! Stitching the files of one session back together after a move left them
+//! in several project dirs, e.g. when the repo's path changed on a new
+//! machine and Claude Code resumed the session under the new project dir.
+
+use crate::compress::open_transcript;
+use crate::discovery::Session;
+use crate::fs::{lock_path, write_atomic, FileLock};
+use crate::parse::{jsonl_records, parse_session_events_with, ParseOptions, ParseOutput};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+/// Files linked into one logical session, by the `sessionId` they share.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StitchStore {
+    pub sessions: BTreeMap<String, BTreeSet<PathBuf>>,
+}
+
+impl StitchStore {
+    /// Read the store at `path`; a missing file is an empty store.
+    pub fn load(path: &Path) -> Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(raw) => serde_json::from_str(&raw)
+                .with_context(|| format!("Invalid stitch store {}", path.display())),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err).with_context(|| format!("Failed to read {}", path.display())),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        write_atomic(path, serde_json::to_string_pretty(self)?)
+    }
+
+    /// Load, apply `edit`, and save while holding the store's lock, so
+    /// concurrent runs take turns instead of saving over each other.
+    /// `on_wait` runs if another process holds the lock.
+    pub fn update<R>(
+        path: &Path,
+        on_wait: impl FnOnce(&Path),
+        edit: impl FnOnce(&mut Self) -> Result<R>,
+    ) -> Result<R> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        let _lock = FileLock::acquire(&lock_path(path), on_wait)?;
+        let mut store = Self::load(path)?;
+        let out = edit(&mut store)?;
+        store.save(path)?;
+        Ok(out)
+    }
+
+    /// Group `sessions` by the `sessionId` values inside their files, keeping
+    /// groups whose files sit in more than one project dir.
+    pub fn scan(sessions: &[Session]) -> Result<Self> {
+        let mut by_id: BTreeMap<String, Vec<&Session>> = BTreeMap::new();
+        for session in sessions {
+            for id in record_session_ids(&session.path)? {
+                by_id.entry(id).or_default().push(session);
+            }
+        }
+        let sessions = by_id
+            .into_iter()
+            .filter(|(_, members)| {
+                members
+                    .iter()
+                    .map(|s| &s.project)
+                    .collect::<HashSet<_>>()
+                    .len()
+                    > 1
+            })
+            .map(|(id, members)| (id, members.iter().map(|s| s.path.clone()).collect()))
+            .collect();
+        Ok(Self { sessions })
+    }
+
+    /// `sessions` with each stitched group folded into its most recently
+    /// written member, which takes the shared id and lists the other files
+    /// in [`Session::stitched`]. Indices are renumbered.
+    pub fn apply(&self, sessions: Vec<Session>) -> Vec<Session> {
+        let group_of = self
+            .sessions
+            .iter()
+            .flat_map(|(id, paths)| paths.iter().map(move |path| (path.as_path(), id)))
+            .collect::<HashMap<_, _>>();
+        let mut groups: HashMap<&String, Vec<Session>> = HashMap::new();
+        let mut out = Vec::with_capacity(sessions.len());
+        for session in sessions {
+            match group_of.get(session.path.as_path()) {
+                Some(id) => groups.entry(id).or_default().push(session),
+                None => out.push(session),
+            }
+        }
+        for (id, mut members) in groups {
+            members.sort_by_key(|s| (s.modified_epoch, s.path.clone()));
+            let mut newest = members.pop().expect("groups have members");
+            if !members.is_empty() {
+                newest.id_short = crate::discovery::short_id(id);
+                newest.id = id.clone();
+                newest.size_bytes += members.iter().map(|s| s.size_bytes).sum::<u64>();
+                newest.stitched = members.into_iter().map(|s| s.path).collect();
+            }
+            out.push(newest);
+        }
+        out.sort_by_key(|s| (std::cmp::Reverse(s.modified_epoch), s.path.clone()));
+        for (i, session) in out.iter_mut().enumerate() {
+            session.index = i + 1;
+        }
+        out
+    }
+}
+
+/// Distinct `sessionId` values of the records in `path`.
+pub fn record_session_ids(path: &Path) -> Result<BTreeSet<String>> {
+    let file =
+        open_transcript(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut ids = BTreeSet::new();
+    for record in jsonl_records(BufReader::new(file)) {
+        if let Ok(value) = record? {
+            if let Some(id) = value.get("sessionId").and_then(Value::as_str) {
+                ids.insert(id.to_string());
+            }
+        }
+    }
+    Ok(ids)
+}
+
+/// Parse every file of `session`, oldest first, as one transcript. Records
+/// copied into a later file (same `uuid`) are kept once.
+pub fn parse_session(session: &Session, detailed: bool) -> Result<ParseOutput> {
+    parse_session_with(session, &ParseOptions::detailed(detailed))
+}
+
+pub fn parse_session_with(session: &Session, options: &ParseOptions) -> Result<ParseOutput> {
+    let first = session.paths().next().expect("a path");
+    let mut parsed = parse_session_events_with(first, options)?;
+    let mut seen = parsed
+        .events
+        .iter()
+        .filter_map(|e| e.uuid.clone())
+        .collect::<HashSet<_>>();
+    for path in session.paths().skip(1) {
+        let more = parse_session_events_with(path, options)?;
+        parsed.events.extend(
+            more.events
+                .into_iter()
+                .filter(|e| e.uuid.as_ref().is_none_or(|uuid| seen.insert(uuid.clone()))),
+        );
+        parsed.parse_errors += more.parse_errors;
+        parsed.diagnostics.extend(more.diagnostics);
+        for asset in more.assets {
+            if !parsed.assets.iter().any(|a| a.name == asset.name) {
+                parsed.assets.push(asset);
+            }
+        }
+        parsed.title.merge(more.title);
+    }
+    Ok(parsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::discovery::{discover_sessions, TimeWindow};
+    use crate::testutil::{unique_temp_path, write_jsonl};
+
+    #[test]
+    fn files_sharing_a_session_id_across_projects_become_one_session() {
+        let root = unique_temp_path("cc-convo-stitch");
+        let record = |uuid: &str, text: &str| {
+            format!(
+                r#"{{"type":"user","uuid":"{uuid}","sessionId":"s-1","timestamp":"2026-01-01T00:00:00Z","message":{{"role":"user","content":"{text}"}}}}"#
+            )
+        };
+        let old = root.join("-Users-me-app/s-1.jsonl");
+        let new = root.join("-home-me-app/s-1.jsonl");
+        let other = root.join("-home-me-app/other.jsonl");
+        for path in [&old, &new, &other] {
+            fs::create_dir_all(path.parent().expect("parent")).expect("create dir");
+        }
+        write_jsonl(&old, &[&record("u1", "first")]);
+        write_jsonl(&new, &[&record("u1", "first"), &record("u2", "second")]);
+        write_jsonl(
+            &other,
+            &[r#"{"type":"user","sessionId":"other","message":{"role":"user","content":"x"}}"#],
+        );
+        fs::File::options()
+            .write(true)
+            .open(&old)
+            .and_then(|f| f.set_modified(std::time::UNIX_EPOCH))
+            .expect("age the old file");
+
+        let sessions = discover_sessions(&root, &TimeWindow::default()).expect("discover");
+        let store = StitchStore::scan(&sessions).expect("scan");
+        assert_eq!(
+            store.sessions.keys().collect::<Vec<_>>(),
+            vec!["s-1"],
+            "a session in one project dir needs no stitching"
+        );
+
+        let stitched = store.apply(sessions);
+        assert_eq!(stitched.len(), 2);
+        let session = stitched.iter().find(|s| s.id == "s-1").expect("stitched");
+        assert_eq!(session.path, new);
+        assert_eq!(session.stitched, vec![old]);
+        let parsed = parse_session(session, false).expect("parse");
+        let texts = parsed
+            .events
+            .iter()
+            .map(|e| e.content.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(texts, vec!["first", "second"]);
+        fs::remove_dir_all(&root).expect("cleanup");
+    }
+}
@@ -0,0 +1,165 @@
+//! User-defined session tags, kept in a JSON sidecar next to (not inside)
+//! the transcripts, which Claude Code owns.
+
+use crate::fs::{lock_path, write_atomic, FileLock};
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+
+/// Tags by session id.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TagStore {
+    pub sessions: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl TagStore {
+    /// Read the store at `path`; a missing file is an empty store.
+    pub fn load(path: &Path) -> Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(raw) => serde_json::from_str(&raw)
+                .with_context(|| format!("Invalid tag store {}", path.display())),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err).with_context(|| format!("Failed to read {}", path.display())),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        write_atomic(path, serde_json::to_string_pretty(self)?)
+    }
+
+    /// Load, apply `edit`, and save while holding the store's lock, so
+    /// concurrent edits are not lost. `on_wait` runs if another process
+    /// holds the lock.
+    pub fn update<R>(
+        path: &Path,
+        on_wait: impl FnOnce(&Path),
+        edit: impl FnOnce(&mut Self) -> Result<R>,
+    ) -> Result<R> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        let _lock = FileLock::acquire(&lock_path(path), on_wait)?;
+        let mut store = Self::load(path)?;
+        let out = edit(&mut store)?;
+        store.save(path)?;
+        Ok(out)
+    }
+
+    /// Tag a session; returns the tags it didn't have yet.
+    pub fn add(&mut self, session_id: &str, tags: &[String]) -> Result<Vec<String>> {
+        let tags = tags
+            .iter()
+            .map(|tag| validate_tag(tag))
+            .collect::<Result<Vec<_>>>()?;
+        let set = self.sessions.entry(session_id.to_string()).or_default();
+        let mut added = Vec::new();
+        for tag in tags {
+            if set.insert(tag.clone()) {
+                added.push(tag);
+            }
+        }
+        Ok(added)
+    }
+
+    /// Untag a session; returns the tags it had.
+    pub fn remove(&mut self, session_id: &str, tags: &[String]) -> Vec<String> {
+        let Some(set) = self.sessions.get_mut(session_id) else {
+            return Vec::new();
+        };
+        let removed = tags
+            .iter()
+            .filter(|tag| set.remove(tag.trim()))
+            .map(|tag| tag.trim().to_string())
+            .collect();
+        if set.is_empty() {
+            self.sessions.remove(session_id);
+        }
+        removed
+    }
+
+    pub fn tags(&self, session_id: &str) -> Vec<String> {
+        self.sessions
+            .get(session_id)
+            .map(|set| set.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Whether the session carries every one of `tags`.
+    pub fn has_all(&self, session_id: &str, tags: &[String]) -> bool {
+        let set = self.sessions.get(session_id);
+        tags.iter()
+            .all(|tag| set.is_some_and(|set| set.contains(tag.trim())))
+    }
+
+    /// Every tag in use and how many sessions carry it.
+    pub fn counts(&self) -> BTreeMap<&str, usize> {
+        let mut counts = BTreeMap::new();
+        for tag in self.sessions.values().flatten() {
+            *counts.entry(tag.as_str()).or_default() += 1;
+        }
+        counts
+    }
+}
+
+/// Tags are single words so they read unambiguously in lists and flags.
+fn validate_tag(tag: &str) -> Result<String> {
+    let tag = tag.trim();
+    if tag.is_empty() || tag.contains(|c: char| c.is_whitespace() || c == ',') {
+        bail!("Invalid tag {tag:?}: tags are non-empty and contain no spaces or commas");
+    }
+    Ok(tag.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::unique_temp_path;
+
+    #[test]
+    fn update_persists_added_and_removed_tags() {
+        let dir = unique_temp_path("cc-convo-tags");
+        let path = dir.join("tags.json");
+        let tags = |list: &[&str]| list.iter().map(|t| t.to_string()).collect::<Vec<_>>();
+
+        let added = TagStore::update(
+            &path,
+            |_| {},
+            |store| {
+                store.add("s1", &tags(&["auth", "bug"]))?;
+                store.add("s2", &tags(&["auth"]))?;
+                store.add("s1", &tags(&["bug", "wip"]))
+            },
+        )
+        .expect("update");
+        assert_eq!(added, tags(&["wip"]));
+
+        let store = TagStore::load(&path).expect("load");
+        assert_eq!(store.tags("s1"), tags(&["auth", "bug", "wip"]));
+        assert!(store.has_all("s1", &tags(&["auth", "wip"])));
+        assert!(!store.has_all("s2", &tags(&["auth", "wip"])));
+        assert_eq!(store.counts().get("auth"), Some(&2));
+
+        let removed = TagStore::update(
+            &path,
+            |_| {},
+            |store| Ok(store.remove("s2", &tags(&["auth", "missing"]))),
+        )
+        .expect("update");
+        assert_eq!(removed, tags(&["auth"]));
+        assert!(!TagStore::load(&path)
+            .expect("load")
+            .sessions
+            .contains_key("s2"));
+
+        let mut store = TagStore::default();
+        assert!(store.add("s1", &tags(&["two words"])).is_err());
+        fs::remove_dir_all(&dir).expect("cleanup");
+    }
+}
@@ -0,0 +1,38 @@
+use crate::discovery::{short_id, Session};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub fn unique_temp_path(prefix: &str) -> PathBuf {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("clock should be after epoch")
+        .as_nanos();
+    std::env::temp_dir().join(format!("{}-{}-{}", prefix, std::process::id(), nanos))
+}
+
+/// A session for tests: `id` of `project` at `path`, numbered 1, with zero
+/// size and mtime. Set other fields with struct update syntax.
+pub fn session_at(project: &str, id: &str, path: impl Into<PathBuf>) -> Session {
+    Session {
+        index: 1,
+        id: id.to_string(),
+        id_short: short_id(id),
+        project: project.to_string(),
+        path: path.into(),
+        modified_iso: String::new(),
+        modified_epoch: 0,
+        size_bytes: 0,
+        stitched: Vec::new(),
+    }
+}
+
+/// [`session_at`] a path that needn't exist, `/p/<project>/<id>.jsonl`.
+pub fn session(project: &str, id: &str) -> Session {
+    session_at(project, id, format!("/p/{project}/{id}.jsonl"))
+}
+
+pub fn write_jsonl(path: &Path, lines: &[&str]) {
+    let body = lines.join("\n");
+    fs::write(path, body).expect("write jsonl");
+}
@@ -0,0 +1,144 @@
+//! Truncation and preview helpers that measure terminal columns and cut only
+//! between grapheme clusters, so CJK text, accents, and emoji stay whole.
+
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// Columns a preview takes unless the caller asks for another length.
+pub const PREVIEW_WIDTH: usize = 140;
+
+pub fn clean_preview(s: &str) -> String {
+    clean_preview_to(s, PREVIEW_WIDTH)
+}
+
+/// `s` on one line, cut to at most `max` columns.
+pub fn clean_preview_to(s: &str, max: usize) -> String {
+    ellipsize(s.replace('\n', " ").trim(), max)
+}
+
+/// Terminal columns `s` takes: two for CJK and most emoji, none for
+/// combining marks.
+pub fn display_width(s: &str) -> usize {
+    s.width()
+}
+
+/// `s` cut to at most `max` columns, ending in `...` when cut.
+pub fn ellipsize(s: &str, max: usize) -> String {
+    if display_width(s) <= max {
+        return s.to_string();
+    }
+    if max <= 3 {
+        return ".".repeat(max);
+    }
+    let mut out = String::new();
+    let mut width = 0;
+    for grapheme in s.graphemes(true) {
+        width += display_width(grapheme);
+        if width > max - 3 {
+            break;
+        }
+        out.push_str(grapheme);
+    }
+    out.push_str("...");
+    out
+}
+
+/// `s` wrapped between words onto lines at most `width` columns wide, a word
+/// wider than that split between graphemes. Past `max_lines`, the last line
+/// ends in `...`.
+pub fn wrap_words(s: &str, width: usize, max_lines: usize) -> Vec<String> {
+    let width = width.max(4);
+    let mut lines: Vec<String> = Vec::new();
+    let mut line = String::new();
+    for word in s.split_whitespace() {
+        let mut word = word;
+        loop {
+            let used = display_width(&line);
+            let gap = usize::from(!line.is_empty());
+            if used + gap + display_width(word) <= width {
+                if gap == 1 {
+                    line.push(' ');
+                }
+                line.push_str(word);
+                break;
+            }
+            if !line.is_empty() {
+                lines.push(std::mem::take(&mut line));
+                continue;
+            }
+            // A word alone that still doesn't fit: take what does.
+            let mut taken = 0;
+            let mut end = 0;
+            for (i, grapheme) in word.grapheme_indices(true) {
+                taken += display_width(grapheme);
+                if taken > width {
+                    break;
+                }
+                end = i + grapheme.len();
+            }
+            let end = end.max(word.graphemes(true).next().map_or(word.len(), str::len));
+            lines.push(word[..end].to_string());
+            word = &word[end..];
+            if word.is_empty() {
+                break;
+            }
+        }
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    if lines.len() > max_lines {
+        lines.truncate(max_lines.max(1));
+        if let Some(last) = lines.last_mut() {
+            *last = ellipsize(&format!("{last}..."), width);
+        }
+    }
+    lines
+}
+
+/// Byte offsets of the grapheme cluster boundaries in `s`, from `0` to
+/// `s.len()`.
+pub fn grapheme_bounds(s: &str) -> Vec<usize> {
+    s.grapheme_indices(true)
+        .map(|(i, _)| i)
+        .chain([s.len()])
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ellipsize_handles_unicode_boundaries() {
+        let s = "ab═cd";
+        let out = ellipsize(s, 4);
+        assert_eq!(out, "a...");
+        // Wide characters take two columns each.
+        assert_eq!(ellipsize("日本語のテキスト", 9), "日本語...");
+        // An emoji sequence and a decomposed accent are never split.
+        assert_eq!(ellipsize("👩‍💻👩‍💻 done", 6), "👩‍💻...");
+        assert_eq!(ellipsize("cafe\u{301} au lait", 7), "cafe\u{301}...");
+        assert_eq!(grapheme_bounds("e\u{301}x"), vec![0, 3, 4]);
+    }
+
+    #[test]
+    fn wrap_words_breaks_between_words_and_marks_the_cut() {
+        assert_eq!(
+            wrap_words("the quick brown fox jumps", 10, 5),
+            ["the quick", "brown fox", "jumps"]
+        );
+        assert_eq!(wrap_words("a b c", 10, 1), ["a b c"]);
+        assert_eq!(wrap_words("one two", 4, 1), ["o..."]);
+        assert_eq!(
+            wrap_words("the quick brown fox jumps", 10, 2),
+            ["the quick", "brown f..."]
+        );
+        assert_eq!(wrap_words("abcdefghij", 4, 5), ["abcd", "efgh", "ij"]);
+        assert_eq!(
+            wrap_words("日本語のテキスト", 6, 5),
+            ["日本語", "のテキ", "スト"]
+        );
+        assert!(wrap_words("  ", 10, 3).is_empty());
+    }
+}
@@ -0,0 +1,284 @@
+//! Color themes for HTML exports. Each theme has a light and a dark palette;
+//! the page follows the reader's `prefers-color-scheme` unless the theme
+//! fixes one, and a button flips between the two. Also the icons that can
+//! mark each message's role.
+
+use serde::{Deserialize, Serialize};
+use std::fmt::Write as _;
+use std::sync::OnceLock;
+use syntect::highlighting::ThemeSet;
+use syntect::html::{css_for_theme_with_class_style, ClassStyle};
+
+/// Class prefix of highlighted code, so syntect's classes can't collide
+/// with the page's own.
+pub(crate) const HIGHLIGHT_CLASSES: ClassStyle = ClassStyle::SpacedPrefixed { prefix: "hl-" };
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+    /// Light or dark, as the reader's system prefers.
+    #[default]
+    Auto,
+    Light,
+    Dark,
+    /// Solarized, light or dark as the reader's system prefers.
+    Solarized,
+}
+
+/// Icons marking who speaks, for picking out turns in long conversations.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RoleIcons {
+    Emoji,
+    /// For terminals that can't show emoji.
+    Ascii,
+}
+
+impl RoleIcons {
+    /// The icon for `role`: a person for the user, a robot for the assistant,
+    /// and a tool for records like `system` and `progress`.
+    pub fn icon(self, role: &str) -> &'static str {
+        match (self, role) {
+            (Self::Emoji, "user") => "\u{1f9d1}",
+            (Self::Emoji, "assistant") => "\u{1f916}",
+            (Self::Emoji, _) => "\u{1f6e0}\u{fe0f}",
+            (Self::Ascii, "user") => ">",
+            (Self::Ascii, "assistant") => "<",
+            (Self::Ascii, _) => "*",
+        }
+    }
+
+    /// `[role]`, after its icon when `icons` is set.
+    pub fn label(icons: Option<Self>, role: &str) -> String {
+        match icons {
+            Some(icons) => format!("{} [{role}]", icons.icon(role)),
+            None => format!("[{role}]"),
+        }
+    }
+}
+
+/// Page colors, as CSS custom properties.
+struct Palette {
+    bg: &'static str,
+    fg: &'static str,
+    card: &'static str,
+    muted: &'static str,
+    code: &'static str,
+    border: &'static str,
+    user: &'static str,
+    assistant: &'static str,
+    /// The syntect theme for code fences.
+    highlight: &'static str,
+}
+
+const GITHUB_LIGHT: Palette = Palette {
+    bg: "#f7f8fa",
+    fg: "#1e2430",
+    card: "#fff",
+    muted: "#5c667a",
+    code: "#f3f4f6",
+    border: "#d8dce3",
+    user: "#1a7f37",
+    assistant: "#0969da",
+    highlight: "InspiredGitHub",
+};
+
+const GITHUB_DARK: Palette = Palette {
+    bg: "#0d1117",
+    fg: "#e6edf3",
+    card: "#161b22",
+    muted: "#8b949e",
+    code: "#0d1117",
+    border: "#30363d",
+    user: "#3fb950",
+    assistant: "#58a6ff",
+    highlight: "base16-ocean.dark",
+};
+
+const SOLARIZED_LIGHT: Palette = Palette {
+    bg: "#eee8d5",
+    fg: "#586e75",
+    card: "#fdf6e3",
+    muted: "#93a1a1",
+    code: "#eee8d5",
+    border: "#93a1a1",
+    user: "#859900",
+    assistant: "#268bd2",
+    highlight: "Solarized (light)",
+};
+
+const SOLARIZED_DARK: Palette = Palette {
+    bg: "#002b36",
+    fg: "#93a1a1",
+    card: "#073642",
+    muted: "#657b83",
+    code: "#002b36",
+    border: "#586e75",
+    user: "#859900",
+    assistant: "#268bd2",
+    highlight: "Solarized (dark)",
+};
+
+impl Theme {
+    fn palettes(self) -> (&'static Palette, &'static Palette) {
+        match self {
+            Self::Solarized => (&SOLARIZED_LIGHT, &SOLARIZED_DARK),
+            _ => (&GITHUB_LIGHT, &GITHUB_DARK),
+        }
+    }
+
+    /// The scheme the page opens in, if the theme fixes one.
+    fn fixed_scheme(self) -> Option<&'static str> {
+        match self {
+            Self::Light => Some("light"),
+            Self::Dark => Some("dark"),
+            Self::Auto | Self::Solarized => None,
+        }
+    }
+
+    /// Attributes for the page's `<html>` tag.
+    pub fn html_attrs(self) -> String {
+        match self.fixed_scheme() {
+            Some(scheme) => format!(" data-scheme=\"{scheme}\""),
+            None => String::new(),
+        }
+    }
+
+    /// The page's `<style>` contents. `highlighted` adds the rules for
+    /// highlighted code fences, which only pages holding some need.
+    pub fn html_css(self, highlighted: bool) -> String {
+        let (light, dark) = self.palettes();
+        let mut css = format!(":root{{{}}}", variables(light));
+        let dark_rules = |scope: &str| {
+            let mut rules = format!("{scope}{{{}}}", variables(dark));
+            if highlighted {
+                rules.push_str(&scoped(highlight_css(dark.highlight), scope));
+            }
+            rules
+        };
+        let _ = write!(
+            css,
+            "@media (prefers-color-scheme: dark){{{}}}{}",
+            dark_rules(":root:not([data-scheme=light])"),
+            dark_rules(":root[data-scheme=dark]")
+        );
+        if highlighted {
+            css.push_str(&scoped(highlight_css(light.highlight), ":root"));
+        }
+        css.push_str(BASE_CSS);
+        css
+    }
+}
+
+/// Layout, in terms of the palette variables.
+const BASE_CSS: &str = "body{font-family:ui-sans-serif,system-ui;margin:2rem;background:var(--bg);color:var(--fg)} .card{background:var(--card);border-radius:12px;padding:16px 20px;margin:0 0 16px 0;box-shadow:0 1px 2px rgba(0,0,0,.06)} .meta{color:var(--muted);font-size:.92rem} pre{white-space:pre-wrap;word-break:break-word;margin:.4rem 0;padding:8px 10px;border-radius:6px;background:var(--code)} code{font-family:ui-monospace,monospace;font-size:.9em} .content>p:first-child{margin-top:0} details{margin:.4rem 0} summary{cursor:pointer;color:var(--muted)} table{border-collapse:collapse} td,th{border:1px solid var(--border);padding:2px 8px} h1,h2{margin:.2rem 0 .8rem} .role-user{color:var(--user)} .role-assistant{color:var(--assistant)} #scheme-toggle{position:fixed;top:1rem;right:1rem;background:var(--card);color:var(--fg);border:1px solid var(--border);border-radius:6px;cursor:pointer}";
+
+macro_rules! scheme_toggle_script {
+    () => {
+        "document.getElementById('scheme-toggle').onclick=function(){var r=document.documentElement,d=r.dataset.scheme||(matchMedia('(prefers-color-scheme: dark)').matches?'dark':'light');r.dataset.scheme=d==='dark'?'light':'dark'}"
+    };
+}
+
+/// The script of [`SCHEME_TOGGLE_HTML`], for a Content-Security-Policy hash.
+pub const SCHEME_TOGGLE_SCRIPT: &str = scheme_toggle_script!();
+
+/// A button flipping the page between its light and dark palettes.
+pub const SCHEME_TOGGLE_HTML: &str = concat!(
+    "<button id=\"scheme-toggle\" type=\"button\" title=\"Switch light/dark\">&#9680;</button><script>",
+    scheme_toggle_script!(),
+    "</script>"
+);
+
+fn variables(p: &Palette) -> String {
+    format!(
+        "--bg:{};--fg:{};--card:{};--muted:{};--code:{};--border:{};--user:{};--assistant:{}",
+        p.bg, p.fg, p.card, p.muted, p.code, p.border, p.user, p.assistant
+    )
+}
+
+/// The class rules for a bundled syntect theme.
+fn highlight_css(name: &str) -> &'static str {
+    static THEMES: OnceLock<Vec<(&'static str, String)>> = OnceLock::new();
+    let themes = THEMES.get_or_init(|| {
+        let set = ThemeSet::load_defaults();
+        [GITHUB_LIGHT, GITHUB_DARK, SOLARIZED_LIGHT, SOLARIZED_DARK]
+            .iter()
+            .map(|p| {
+                let theme = &set.themes[p.highlight];
+                let css = css_for_theme_with_class_style(theme, HIGHLIGHT_CLASSES)
+                    .expect("bundled syntect themes render as CSS");
+                (p.highlight, css)
+            })
+            .collect()
+    });
+    themes
+        .iter()
+        .find(|(n, _)| *n == name)
+        .map(|(_, css)| css.as_str())
+        .expect("a bundled highlight theme")
+}
+
+/// `css` with each selector limited to elements under `scope`, and its
+/// comments and line breaks dropped.
+fn scoped(css: &str, scope: &str) -> String {
+    let mut out = String::new();
+    let mut in_comment = false;
+    for line in css.lines() {
+        let line = line.trim();
+        if in_comment || line.starts_with("/*") {
+            in_comment = !line.ends_with("*/");
+            continue;
+        }
+        match line.strip_suffix('{') {
+            Some(selectors) => {
+                let selectors = selectors
+                    .split(',')
+                    .map(|s| format!("{scope} {}", s.trim()))
+                    .collect::<Vec<_>>();
+                out.push_str(&selectors.join(","));
+                out.push('{');
+            }
+            None => out.push_str(line),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn themes_pair_light_and_dark_palettes() {
+        let css = Theme::Auto.html_css(false);
+        assert!(css.starts_with(":root{--bg:#f7f8fa;"));
+        assert!(css.contains(
+            "@media (prefers-color-scheme: dark){:root:not([data-scheme=light]){--bg:#0d1117;"
+        ));
+        assert!(!css.contains(".hl-"), "no highlight rules without code");
+        assert_eq!(Theme::Auto.html_attrs(), "");
+        assert_eq!(Theme::Dark.html_attrs(), " data-scheme=\"dark\"");
+
+        let css = Theme::Solarized.html_css(true);
+        assert!(css.contains("--bg:#002b36"));
+        assert!(css.contains(":root[data-scheme=dark] .hl-code{"));
+        assert!(!css.contains("/*"));
+        assert_eq!(
+            scoped("/*\n * x\n */\n.a .b, .c {\n color: #fff;\n}\n", ":root"),
+            ":root .a .b,:root .c{color: #fff;}"
+        );
+    }
+
+    #[test]
+    fn role_labels_take_an_icon_when_asked() {
+        assert_eq!(RoleIcons::label(None, "user"), "[user]");
+        assert_eq!(
+            RoleIcons::label(Some(RoleIcons::Emoji), "assistant"),
+            "\u{1f916} [assistant]"
+        );
+        assert_eq!(
+            RoleIcons::label(Some(RoleIcons::Ascii), "system"),
+            "* [system]"
+        );
+    }
+}
@@ -0,0 +1,176 @@
+//! Rebuilding the conversation tree from `uuid`/`parentUuid` links.
+
+use crate::parse::NormalizedEvent;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Events linked by [`NormalizedEvent::parent_uuid`]. `nodes[i]` describes
+/// `events[i]` of the slice the tree was built from.
+///
+/// A node with several children is a branch point: Claude Code writes a new
+/// sibling when a reply is regenerated or a prompt is edited and resent.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ConversationTree {
+    pub nodes: Vec<ThreadNode>,
+    pub roots: Vec<usize>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ThreadNode {
+    pub parent: Option<usize>,
+    pub children: Vec<usize>,
+    /// Set on each child of a branch point.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub branch: Option<Branch>,
+    /// Number of branch points between this node and its root.
+    pub depth: usize,
+}
+
+/// Position of a node among the alternatives at a branch point.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Branch {
+    /// 1-based, in file order; the last one is where the session continued.
+    pub index: usize,
+    pub of: usize,
+}
+
+impl ConversationTree {
+    /// Link `events` by uuid. Events without a uuid (older transcripts) follow
+    /// the event before them, so an unlinked session stays a single chain.
+    pub fn build(events: &[NormalizedEvent]) -> Self {
+        let by_uuid: HashMap<&str, usize> = events
+            .iter()
+            .enumerate()
+            .filter_map(|(i, e)| e.uuid.as_deref().map(|u| (u, i)))
+            .collect();
+        let mut nodes = vec![ThreadNode::default(); events.len()];
+        for (i, event) in events.iter().enumerate() {
+            let parent = match (&event.uuid, &event.parent_uuid) {
+                (_, Some(parent)) => by_uuid.get(parent.as_str()).copied(),
+                (None, None) => i.checked_sub(1),
+                (Some(_), None) => None,
+            };
+            nodes[i].parent = parent.filter(|&p| p != i);
+        }
+
+        let mut tree = Self {
+            nodes,
+            roots: Vec::new(),
+        };
+        tree.link_children();
+        tree
+    }
+
+    /// Fill `children`, `roots`, branch labels, and depths from `parent`.
+    /// Nodes caught in a parent cycle are cut loose as extra roots.
+    fn link_children(&mut self) {
+        let len = self.nodes.len();
+        for i in 0..len {
+            match self.nodes[i].parent {
+                Some(p) => self.nodes[p].children.push(i),
+                None => self.roots.push(i),
+            }
+        }
+        let mut visited = vec![false; len];
+        let mut next_root = 0;
+        loop {
+            while next_root < self.roots.len() {
+                self.label_from(self.roots[next_root], &mut visited);
+                next_root += 1;
+            }
+            let Some(orphan) = (0..len).find(|&i| !visited[i]) else {
+                break;
+            };
+            if let Some(p) = self.nodes[orphan].parent.take() {
+                self.nodes[p].children.retain(|&c| c != orphan);
+            }
+            self.roots.push(orphan);
+        }
+    }
+
+    fn label_from(&mut self, root: usize, visited: &mut [bool]) {
+        self.nodes[root].depth = 0;
+        let mut stack = vec![root];
+        while let Some(node) = stack.pop() {
+            visited[node] = true;
+            let children = self.nodes[node].children.clone();
+            let branching = children.len() > 1;
+            let depth = self.nodes[node].depth + usize::from(branching);
+            for (k, &child) in children.iter().enumerate() {
+                self.nodes[child].depth = depth;
+                self.nodes[child].branch = branching.then_some(Branch {
+                    index: k + 1,
+                    of: children.len(),
+                });
+                stack.push(child);
+            }
+        }
+    }
+
+    /// Branch points, i.e. nodes with more than one child.
+    pub fn branch_points(&self) -> usize {
+        self.nodes.iter().filter(|n| n.children.len() > 1).count()
+    }
+
+    /// Node indices depth first, each branch finished before its next sibling.
+    pub fn walk(&self) -> Vec<usize> {
+        let mut order = Vec::with_capacity(self.nodes.len());
+        let mut stack = self.roots.iter().rev().copied().collect::<Vec<_>>();
+        while let Some(node) = stack.pop() {
+            order.push(node);
+            stack.extend(self.nodes[node].children.iter().rev());
+        }
+        order
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(uuid: Option<&str>, parent: Option<&str>) -> NormalizedEvent {
+        NormalizedEvent {
+            role: "user".to_string(),
+            source_type: "user".to_string(),
+            timestamp: None,
+            content: uuid.unwrap_or("-").to_string(),
+            uuid: uuid.map(str::to_string),
+            parent_uuid: parent.map(str::to_string),
+            source: None,
+            model: None,
+        }
+    }
+
+    #[test]
+    fn regenerated_reply_becomes_a_branch() {
+        let events = vec![
+            event(Some("u1"), None),
+            event(Some("a1"), Some("u1")),
+            event(Some("a1b"), Some("u1")),
+            event(Some("u2"), Some("a1b")),
+        ];
+        let tree = ConversationTree::build(&events);
+        assert_eq!(tree.roots, vec![0]);
+        assert_eq!(tree.nodes[0].children, vec![1, 2]);
+        assert_eq!(tree.branch_points(), 1);
+        assert_eq!(tree.nodes[1].branch, Some(Branch { index: 1, of: 2 }));
+        assert_eq!(tree.nodes[2].branch, Some(Branch { index: 2, of: 2 }));
+        assert_eq!(tree.nodes[3].branch, None);
+        assert_eq!(tree.nodes[3].depth, 1);
+        assert_eq!(tree.walk(), vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn unlinked_events_chain_and_cycles_are_broken() {
+        let flat = vec![event(None, None), event(None, None), event(None, None)];
+        let tree = ConversationTree::build(&flat);
+        assert_eq!(tree.roots, vec![0]);
+        assert_eq!(tree.walk(), vec![0, 1, 2]);
+        assert_eq!(tree.branch_points(), 0);
+
+        let cyclic = vec![event(Some("x"), Some("y")), event(Some("y"), Some("x"))];
+        let tree = ConversationTree::build(&cyclic);
+        assert_eq!(tree.roots.len(), 1);
+        assert_eq!(tree.walk().len(), 2);
+    }
+}
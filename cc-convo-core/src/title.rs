@@ -0,0 +1,154 @@
+//! Readable session titles, from the records Claude Code writes for them:
+//! the name given with `/rename`, else the conversation summary, else the
+//! first prompt.
+
+use crate::discovery::Session;
+use crate::record::{Record, RecordBody};
+use crate::text::clean_preview;
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+
+/// What a session's title is picked from, gathered record by record.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionTitle {
+    /// The latest `custom-title` record's name.
+    pub custom_title: Option<String>,
+    /// The first `summary` record's text.
+    pub summary: Option<String>,
+    pub first_prompt: Option<String>,
+}
+
+impl SessionTitle {
+    pub fn observe(&mut self, record: &Record) {
+        let keep = |text: &str| Some(clean_preview(text)).filter(|t| !t.is_empty());
+        match &record.body {
+            RecordBody::CustomTitle(custom) => {
+                if let Some(title) = custom.custom_title.as_deref().and_then(keep) {
+                    self.custom_title = Some(title);
+                }
+            }
+            RecordBody::Summary(summary) if self.summary.is_none() => {
+                self.summary = summary.summary.as_deref().and_then(keep);
+            }
+            RecordBody::User(message) if self.first_prompt.is_none() => {
+                self.first_prompt = keep(&message.message.content.text(false));
+            }
+            _ => {}
+        }
+    }
+
+    /// Fold in what a later file of the same session holds.
+    pub fn merge(&mut self, later: Self) {
+        self.custom_title = later.custom_title.or(self.custom_title.take());
+        self.summary = self.summary.take().or(later.summary);
+        self.first_prompt = self.first_prompt.take().or(later.first_prompt);
+    }
+
+    pub fn title(&self) -> Option<&str> {
+        self.custom_title
+            .as_deref()
+            .or(self.summary.as_deref())
+            .or(self.first_prompt.as_deref())
+    }
+}
+
+/// The session whose title contains `query`, ignoring case, for commands
+/// that take a session by name; `title_of` supplies the titles. `None` when
+/// no title matches, an error when several do.
+pub fn find_by_title<'a>(
+    sessions: &'a [Session],
+    query: &str,
+    mut title_of: impl FnMut(&Session) -> Result<Option<String>>,
+) -> Result<Option<&'a Session>> {
+    let needle = query.to_lowercase();
+    let mut matches = Vec::new();
+    for session in sessions {
+        if let Some(title) = title_of(session)? {
+            if title.to_lowercase().contains(&needle) {
+                matches.push((session, title));
+            }
+        }
+    }
+    match matches.as_slice() {
+        [] => Ok(None),
+        [(session, _)] => Ok(Some(session)),
+        _ => {
+            let listed = matches
+                .iter()
+                .map(|(s, title)| format!("  {}  {title}", s.qualified_id()))
+                .collect::<Vec<_>>();
+            bail!(
+                "{query} matches the titles of several sessions:\n{}\nBe more specific, or use an id.",
+                listed.join("\n")
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::session;
+    use serde_json::json;
+
+    fn record(value: serde_json::Value) -> Record {
+        Record::from_value(&value).expect("record")
+    }
+
+    #[test]
+    fn custom_titles_beat_summaries_beat_first_prompts() {
+        let mut title = SessionTitle::default();
+        title.observe(&record(
+            json!({"type": "user", "message": {"content": "  fix the\nlogin test "}}),
+        ));
+        title.observe(&record(
+            json!({"type": "user", "message": {"content": "and then"}}),
+        ));
+        assert_eq!(title.title(), Some("fix the login test"));
+        title.observe(&record(
+            json!({"type": "summary", "summary": "Login test triage"}),
+        ));
+        title.observe(&record(
+            json!({"type": "summary", "summary": "Older summary"}),
+        ));
+        assert_eq!(title.title(), Some("Login test triage"));
+        title.observe(&record(
+            json!({"type": "custom-title", "customTitle": "auth"}),
+        ));
+        title.observe(&record(
+            json!({"type": "custom-title", "customTitle": "auth flake"}),
+        ));
+        assert_eq!(title.title(), Some("auth flake"));
+
+        let mut earlier = SessionTitle {
+            summary: Some("first file".to_string()),
+            ..SessionTitle::default()
+        };
+        earlier.merge(SessionTitle {
+            summary: Some("second file".to_string()),
+            custom_title: Some("renamed".to_string()),
+            ..SessionTitle::default()
+        });
+        assert_eq!(earlier.summary.as_deref(), Some("first file"));
+        assert_eq!(earlier.title(), Some("renamed"));
+    }
+
+    #[test]
+    fn sessions_are_found_by_title_substring() {
+        let sessions = [session("p", "a"), session("p", "b"), session("p", "c")];
+        let title_of = |s: &Session| {
+            Ok(match s.id.as_str() {
+                "a" => Some("Auth flake".to_string()),
+                "b" => Some("Billing auth".to_string()),
+                _ => None,
+            })
+        };
+        let found = find_by_title(&sessions, "FLAKE", title_of).expect("one match");
+        assert_eq!(found.map(|s| s.id.as_str()), Some("a"));
+        let err = find_by_title(&sessions, "auth", title_of).expect_err("two matches");
+        assert!(err.to_string().contains("p/b  Billing auth"), "{err}");
+        assert!(find_by_title(&sessions, "deploy", title_of)
+            .expect("no match")
+            .is_none());
+    }
+}
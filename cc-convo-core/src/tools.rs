@@ -0,0 +1,229 @@
+//! Reading tool calls and their results out of transcript records, for
+//! seeing where an agent's tool budget goes.
+
+use crate::record::{ContentBlock, Message, Record};
+use serde_json::Value;
+
+/// Programs whose first argument names what they do, so `git status` and
+/// `git push` are told apart.
+const SUBCOMMAND_PROGRAMS: &[&str] = &[
+    "cargo", "docker", "gh", "git", "go", "kubectl", "make", "npm", "pnpm", "uv", "yarn",
+];
+
+/// Tool input fields that name a file or directory, as Read, Edit, Write,
+/// NotebookEdit, Grep, and Glob take them.
+const PATH_INPUT_KEYS: &[&str] = &["file_path", "notebook_path", "path"];
+
+/// A `tool_use` block of an assistant message.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolCall<'a> {
+    pub id: Option<&'a str>,
+    pub name: &'a str,
+    pub input: Option<&'a Value>,
+}
+
+/// A `tool_result` block of a user message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ToolResult<'a> {
+    pub tool_use_id: Option<&'a str>,
+    pub is_error: bool,
+    /// Bytes of text the tool returned.
+    pub output_bytes: usize,
+}
+
+impl<'a> ToolCall<'a> {
+    /// The file and directory paths in the call's input.
+    pub fn paths(&self) -> impl Iterator<Item = &'a str> {
+        let input = self.input;
+        PATH_INPUT_KEYS
+            .iter()
+            .filter_map(move |key| input?.get(*key)?.as_str())
+            .filter(|path| !path.trim().is_empty())
+    }
+}
+
+/// Whether `path` is `wanted`, ignoring ASCII case and taking `\` for `/`:
+/// the same path, or one ending in it at a separator when `wanted` is
+/// relative, so `src/auth/login.rs` matches `/home/me/app/src/auth/login.rs`
+/// and `C:\app\src\auth\login.rs` but not `.../oldsrc/auth/login.rs`.
+pub fn path_matches(path: &str, wanted: &str) -> bool {
+    let wanted = wanted.replace('\\', "/");
+    let wanted = wanted.trim_start_matches("./").trim_end_matches('/');
+    let path = path.replace('\\', "/");
+    let path = path.trim_end_matches('/');
+    if wanted.is_empty()
+        || path.len() < wanted.len()
+        || !path.is_char_boundary(path.len() - wanted.len())
+    {
+        return false;
+    }
+    let (head, tail) = path.split_at(path.len() - wanted.len());
+    tail.eq_ignore_ascii_case(wanted) && (head.is_empty() || head.ends_with('/'))
+}
+
+/// The tool calls in `record`.
+pub fn tool_calls(record: &Record) -> impl Iterator<Item = ToolCall<'_>> {
+    record
+        .message()
+        .into_iter()
+        .flat_map(Message::blocks)
+        .filter_map(|block| match block {
+            ContentBlock::ToolUse { id, name, input } => Some(ToolCall {
+                id: id.as_deref(),
+                name: name.as_deref().unwrap_or("<unnamed>"),
+                input: input.as_ref(),
+            }),
+            _ => None,
+        })
+}
+
+/// The tool results in `record`.
+pub fn tool_results(record: &Record) -> impl Iterator<Item = ToolResult<'_>> {
+    record
+        .message()
+        .into_iter()
+        .flat_map(Message::blocks)
+        .filter_map(|block| match block {
+            ContentBlock::ToolResult {
+                tool_use_id,
+                is_error,
+                content,
+            } => Some(ToolResult {
+                tool_use_id: tool_use_id.as_deref(),
+                is_error: *is_error == Some(true),
+                output_bytes: output_bytes(content.as_ref()),
+            }),
+            _ => None,
+        })
+}
+
+fn output_bytes(content: Option<&Value>) -> usize {
+    match content {
+        Some(Value::String(text)) => text.len(),
+        Some(Value::Array(parts)) => parts
+            .iter()
+            .filter_map(|part| part.get("text").and_then(Value::as_str))
+            .map(str::len)
+            .sum(),
+        _ => 0,
+    }
+}
+
+/// The programs a shell command line runs, one per command in its pipes and
+/// `&&`/`||`/`;` chains: `cd app && cargo test | tail` is `cd`, `cargo test`,
+/// and `tail`. Leading `VAR=value` assignments, `sudo`, and directories are
+/// dropped. Quoting is not parsed, so separators inside quotes split too.
+pub fn command_names(command: &str) -> Vec<String> {
+    command
+        .split(['\n', ';', '|', '&'])
+        .filter_map(|segment| {
+            let mut words = segment
+                .split_whitespace()
+                .skip_while(|word| word.contains('=') || *word == "sudo");
+            let program = words.next()?;
+            let program = program.rsplit('/').next().unwrap_or(program);
+            // Pieces of redirections like `2>&1`, subshell parens, comments.
+            if program.is_empty()
+                || program.contains(['>', '<', '(', ')'])
+                || program.starts_with('#')
+                || program.chars().all(|c| c.is_ascii_digit())
+            {
+                return None;
+            }
+            match words.next() {
+                Some(sub)
+                    if SUBCOMMAND_PROGRAMS.contains(&program)
+                        && sub.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+                        && !sub.starts_with('-') =>
+                {
+                    Some(format!("{program} {sub}"))
+                }
+                _ => Some(program.to_string()),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn calls_results_and_commands_are_read_from_records() {
+        let call = Record::from_value(&json!({"type":"assistant","message":{"content":[
+            {"type":"text","text":"Running the tests."},
+            {"type":"tool_use","id":"toolu_1","name":"Bash","input":{"command":"cargo test"}}
+        ]}}))
+        .expect("record");
+        let calls = tool_calls(&call).collect::<Vec<_>>();
+        assert_eq!(calls.len(), 1);
+        assert_eq!((calls[0].id, calls[0].name), (Some("toolu_1"), "Bash"));
+
+        let result = Record::from_value(&json!({"type":"user","message":{"content":[
+            {"type":"tool_result","tool_use_id":"toolu_1","is_error":true,"content":"boom"},
+            {"type":"tool_result","tool_use_id":"toolu_2","content":[{"type":"text","text":"ok"},{"type":"image"}]}
+        ]}}))
+        .expect("record");
+        let results = tool_results(&result).collect::<Vec<_>>();
+        assert_eq!(
+            results,
+            vec![
+                ToolResult {
+                    tool_use_id: Some("toolu_1"),
+                    is_error: true,
+                    output_bytes: 4
+                },
+                ToolResult {
+                    tool_use_id: Some("toolu_2"),
+                    is_error: false,
+                    output_bytes: 2
+                },
+            ]
+        );
+
+        assert_eq!(
+            command_names("cd app && RUST_LOG=1 cargo test -- --nocapture 2>&1 | tail -n 20"),
+            vec!["cd", "cargo test", "tail"]
+        );
+        assert_eq!(
+            command_names("sudo /usr/bin/git status; git -C x log"),
+            vec!["git status", "git"]
+        );
+        assert_eq!(command_names("cat src/main.rs"), vec!["cat"]);
+        assert!(command_names("  ").is_empty());
+    }
+
+    #[test]
+    fn paths_come_from_tool_inputs_and_match_by_suffix() {
+        let call = Record::from_value(&json!({"type":"assistant","message":{"content":[
+            {"type":"tool_use","name":"Edit","input":{"file_path":"/home/me/app/src/auth/login.rs","old_string":"a"}},
+            {"type":"tool_use","name":"Grep","input":{"pattern":"login","path":"src"}},
+            {"type":"tool_use","name":"Bash","input":{"command":"ls"}}
+        ]}}))
+        .expect("record");
+        let paths = tool_calls(&call)
+            .flat_map(|call| call.paths())
+            .collect::<Vec<_>>();
+        assert_eq!(paths, ["/home/me/app/src/auth/login.rs", "src"]);
+
+        assert!(path_matches(paths[0], "src/auth/login.rs"));
+        assert!(path_matches(paths[0], "./src/auth/LOGIN.rs"));
+        assert!(path_matches(paths[0], paths[0]));
+        assert!(!path_matches(paths[0], "auth/login"));
+        assert!(!path_matches(
+            "/home/me/app/oldsrc/auth/login.rs",
+            "src/auth/login.rs"
+        ));
+        assert!(path_matches("src/", "src"));
+        assert!(path_matches(
+            r"C:\app\src\auth\login.rs",
+            "src/auth/login.rs"
+        ));
+        assert!(path_matches(
+            "/app/src/auth/login.rs",
+            r".\src\auth\login.rs"
+        ));
+        assert!(!path_matches("/app/é.rs", "a/.rs"));
+    }
+}
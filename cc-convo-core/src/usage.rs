@@ -0,0 +1,323 @@
+//! Token usage accounting and estimated spend per model.
+
+use crate::compress::open_transcript;
+use crate::discovery::{Session, TimeWindow};
+use anyhow::{Context, Result};
+use memchr::memmem;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::{BufRead, BufReader};
+use std::ops::AddAssign;
+
+/// Token counts from an assistant record's `message.usage`. Missing or null
+/// counts read as zero.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenUsage {
+    #[serde(default, deserialize_with = "count")]
+    pub input_tokens: u64,
+    #[serde(default, deserialize_with = "count")]
+    pub output_tokens: u64,
+    #[serde(default, deserialize_with = "count")]
+    pub cache_creation_input_tokens: u64,
+    #[serde(default, deserialize_with = "count")]
+    pub cache_read_input_tokens: u64,
+}
+
+fn count<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    Ok(Option::<u64>::deserialize(deserializer)?.unwrap_or(0))
+}
+
+impl TokenUsage {
+    /// Usage reported by a transcript record, or `None` if it has none.
+    pub fn from_record(record: &Value) -> Option<Self> {
+        let usage = record.get("message")?.get("usage")?.as_object()?;
+        let field = |key: &str| usage.get(key).and_then(Value::as_u64).unwrap_or(0);
+        Some(Self {
+            input_tokens: field("input_tokens"),
+            output_tokens: field("output_tokens"),
+            cache_creation_input_tokens: field("cache_creation_input_tokens"),
+            cache_read_input_tokens: field("cache_read_input_tokens"),
+        })
+    }
+
+    pub fn total(&self) -> u64 {
+        self.input_tokens
+            + self.output_tokens
+            + self.cache_creation_input_tokens
+            + self.cache_read_input_tokens
+    }
+}
+
+impl AddAssign for TokenUsage {
+    fn add_assign(&mut self, other: Self) {
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.cache_creation_input_tokens += other.cache_creation_input_tokens;
+        self.cache_read_input_tokens += other.cache_read_input_tokens;
+    }
+}
+
+/// The fields of a transcript line that token accounting reads; the rest of
+/// the line is skipped over without being built into values.
+#[derive(Deserialize)]
+struct UsageLine {
+    #[serde(rename = "type")]
+    record_type: Option<String>,
+    uuid: Option<String>,
+    timestamp: Option<String>,
+    message: Option<UsageMessage>,
+}
+
+#[derive(Deserialize)]
+struct UsageMessage {
+    id: Option<String>,
+    model: Option<String>,
+    usage: Option<TokenUsage>,
+}
+
+/// Token usage per message, read from only the lines that mention `"usage"`.
+/// Gives the same totals as parsing every record, in a fraction of the time
+/// on transcripts full of progress and tool output records.
+#[derive(Debug, Default)]
+pub struct UsageScan {
+    /// Streamed replies repeat one message's usage on every content-block
+    /// record, so the last one per message id is kept.
+    by_message: HashMap<String, (String, TokenUsage)>,
+    /// Record uuids, as a stitched session's later files may repeat records.
+    seen: HashSet<String>,
+    without_id: usize,
+    /// Lines read, and those that named usage and were parsed.
+    pub lines: u64,
+    pub parsed: u64,
+}
+
+impl UsageScan {
+    /// Fold in the records of `reader` that `time_window` admits.
+    pub fn read(&mut self, mut reader: impl BufRead, time_window: &TimeWindow) -> Result<()> {
+        let finder = memmem::Finder::new(b"\"usage\"");
+        let mut line = Vec::new();
+        loop {
+            line.clear();
+            if reader.read_until(b'\n', &mut line)? == 0 {
+                return Ok(());
+            }
+            self.lines += 1;
+            if finder.find(&line).is_none() {
+                continue;
+            }
+            self.parsed += 1;
+            let Ok(record) = serde_json::from_slice::<UsageLine>(&line) else {
+                continue;
+            };
+            if !matches!(record.record_type.as_deref(), Some("user" | "assistant"))
+                || !time_window.admits(record.timestamp.as_deref())
+            {
+                continue;
+            }
+            if let Some(uuid) = record.uuid {
+                if !self.seen.insert(uuid) {
+                    continue;
+                }
+            }
+            if let Some(UsageMessage {
+                id,
+                model,
+                usage: Some(usage),
+            }) = record.message
+            {
+                self.add(id, model, usage);
+            }
+        }
+    }
+
+    /// Count `usage` for message `id` of `model`, replacing what an earlier
+    /// record of the same message reported. For readers that parse records
+    /// themselves and skip repeated uuids on their own.
+    pub fn add(&mut self, id: Option<String>, model: Option<String>, usage: TokenUsage) {
+        let key = id.unwrap_or_else(|| {
+            self.without_id += 1;
+            format!("#{}", self.without_id)
+        });
+        let model = model.unwrap_or_else(|| "<unknown>".to_string());
+        self.by_message.insert(key, (model, usage));
+    }
+
+    /// `(model, usage)` per message.
+    pub fn into_usage(self) -> Vec<(String, TokenUsage)> {
+        self.by_message.into_values().collect()
+    }
+}
+
+/// [`UsageScan`] over every file of `session`.
+pub fn session_usage(session: &Session, time_window: &TimeWindow) -> Result<UsageScan> {
+    let mut scan = UsageScan::default();
+    for path in session.paths() {
+        let file =
+            open_transcript(path).with_context(|| format!("Failed to open {}", path.display()))?;
+        scan.read(BufReader::new(file), time_window)?;
+    }
+    Ok(scan)
+}
+
+/// USD per million tokens. Cache rates default to the usual 1.25x (write)
+/// and 0.1x (read) of the input rate when not given.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ModelPrice {
+    pub input: f64,
+    pub output: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_write: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_read: Option<f64>,
+}
+
+impl ModelPrice {
+    const fn new(input: f64, output: f64) -> Self {
+        Self {
+            input,
+            output,
+            cache_write: None,
+            cache_read: None,
+        }
+    }
+
+    pub fn cost(&self, usage: &TokenUsage) -> f64 {
+        let per_token = |rate: f64| rate / 1_000_000.0;
+        usage.input_tokens as f64 * per_token(self.input)
+            + usage.output_tokens as f64 * per_token(self.output)
+            + usage.cache_creation_input_tokens as f64
+                * per_token(self.cache_write.unwrap_or(self.input * 1.25))
+            + usage.cache_read_input_tokens as f64
+                * per_token(self.cache_read.unwrap_or(self.input * 0.1))
+    }
+}
+
+/// Prices keyed by model-name prefix. The longest matching prefix wins, so
+/// `claude-opus-4-5` is priced apart from older `claude-opus-4` snapshots.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Pricing {
+    #[serde(default)]
+    pub models: BTreeMap<String, ModelPrice>,
+}
+
+impl Default for Pricing {
+    /// Published list prices at the time of writing.
+    fn default() -> Self {
+        let models = [
+            ("claude-opus-4", ModelPrice::new(15.0, 75.0)),
+            ("claude-opus-4-5", ModelPrice::new(5.0, 25.0)),
+            ("claude-sonnet-4", ModelPrice::new(3.0, 15.0)),
+            ("claude-haiku-4-5", ModelPrice::new(1.0, 5.0)),
+            ("claude-3-opus", ModelPrice::new(15.0, 75.0)),
+            ("claude-3-7-sonnet", ModelPrice::new(3.0, 15.0)),
+            ("claude-3-5-sonnet", ModelPrice::new(3.0, 15.0)),
+            ("claude-3-5-haiku", ModelPrice::new(0.8, 4.0)),
+            ("claude-3-haiku", ModelPrice::new(0.25, 1.25)),
+        ];
+        Self {
+            models: models
+                .into_iter()
+                .map(|(name, price)| (name.to_string(), price))
+                .collect(),
+        }
+    }
+}
+
+impl Pricing {
+    /// Add or replace entries from `overrides`, keeping the rest.
+    pub fn merge(&mut self, overrides: Pricing) {
+        self.models.extend(overrides.models);
+    }
+
+    pub fn price_for(&self, model: &str) -> Option<&ModelPrice> {
+        self.models
+            .iter()
+            .filter(|(prefix, _)| model.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, price)| price)
+    }
+
+    /// Estimated USD for `usage` on `model`; `None` for unpriced models.
+    pub fn cost(&self, model: &str, usage: &TokenUsage) -> Option<f64> {
+        self.price_for(model).map(|price| price.cost(usage))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn reads_usage_with_missing_cache_fields() {
+        let record = json!({"type": "assistant", "message": {
+            "usage": {"input_tokens": 10, "output_tokens": 5, "cache_read_input_tokens": 100}
+        }});
+        let usage = TokenUsage::from_record(&record).expect("usage");
+        assert_eq!(usage.input_tokens, 10);
+        assert_eq!(usage.cache_creation_input_tokens, 0);
+        assert_eq!(usage.total(), 115);
+        assert!(TokenUsage::from_record(&json!({"message": {}})).is_none());
+    }
+
+    #[test]
+    fn usage_scan_parses_only_lines_naming_usage() {
+        let lines = [
+            r#"{"type":"user","uuid":"u1","message":{"content":"no usage here","usage":null}}"#,
+            r#"{"type":"progress","uuid":"p1","data":{"output":"building"}}"#,
+            r#"{"type":"assistant","uuid":"a1","message":{"id":"m1","model":"claude-opus-4-1","usage":{"input_tokens":10,"output_tokens":1}}}"#,
+            r#"{"type":"assistant","uuid":"a2","message":{"id":"m1","model":"claude-opus-4-1","usage":{"input_tokens":10,"output_tokens":7}}}"#,
+            r#"{"type":"assistant","uuid":"a2","message":{"id":"m2","model":"claude-opus-4-1","usage":{"input_tokens":99}}}"#,
+            r#"{"type":"assistant","uuid":"a3","timestamp":"2020-01-01T00:00:00Z","message":{"usage":{"output_tokens":5}}}"#,
+            r#"{"type":"assistant","message":{"usage":{"output_tokens":3,"cache_read_input_tokens":null}}}"#,
+        ];
+        let mut scan = UsageScan::default();
+        scan.read(lines.join("\n").as_bytes(), &TimeWindow::default())
+            .expect("scan");
+        assert_eq!((scan.lines, scan.parsed), (7, 6));
+        let mut usage = scan.into_usage();
+        usage.sort_by_key(|(_, usage)| usage.total());
+        let totals = usage
+            .iter()
+            .map(|(model, usage)| (model.as_str(), usage.total()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            totals,
+            [("<unknown>", 3), ("<unknown>", 5), ("claude-opus-4-1", 17)]
+        );
+    }
+
+    #[test]
+    fn longest_prefix_and_overrides_pick_the_price() {
+        let mut pricing = Pricing::default();
+        let usage = TokenUsage {
+            input_tokens: 1_000_000,
+            output_tokens: 1_000_000,
+            cache_creation_input_tokens: 1_000_000,
+            cache_read_input_tokens: 1_000_000,
+        };
+        let opus_41 = pricing.cost("claude-opus-4-1-20250805", &usage).unwrap();
+        assert!((opus_41 - (15.0 + 75.0 + 18.75 + 1.5)).abs() < 1e-9);
+        let opus_45 = pricing.cost("claude-opus-4-5-20251101", &usage).unwrap();
+        assert!((opus_45 - (5.0 + 25.0 + 6.25 + 0.5)).abs() < 1e-9);
+        assert!(pricing.cost("gpt-4o", &usage).is_none());
+
+        let mut overrides = Pricing {
+            models: BTreeMap::new(),
+        };
+        overrides.models.insert(
+            "claude-opus-4".to_string(),
+            ModelPrice {
+                input: 1.0,
+                output: 2.0,
+                cache_write: Some(0.0),
+                cache_read: Some(0.0),
+            },
+        );
+        pricing.merge(overrides);
+        let opus_41 = pricing.cost("claude-opus-4-1", &usage).unwrap();
+        assert!((opus_41 - 3.0).abs() < 1e-9);
+        assert!(pricing.price_for("claude-sonnet-4-5").is_some());
+    }
+}
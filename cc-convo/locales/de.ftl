@@ -0,0 +1,52 @@
+# Zahlen und Datumsangaben
+number-group-separator = .
+decimal-separator = ,
+datetime-format = %d.%m.%Y %H:%M
+
+# Allgemein
+none = (keine)
+cancelled = Abgebrochen.
+redacted-secrets = {$count ->
+    [one] {$count} Geheimnis maskiert.
+   *[other] {$count} Geheimnisse maskiert.
+}
+
+# sessions list
+sessions-title = Sitzungen
+sessions-none = Keine Sitzungen gefunden.
+col-idx = Nr
+col-short-id = Kurz-ID
+col-session-id = Sitzungs-ID
+col-project = Projekt
+col-modified = Geändert
+col-size-kb = KB
+col-user = Nutzer
+col-asst = Asst
+col-other = Sonst
+col-title = Titel
+col-health = Zustand
+col-preview = Vorschau
+health-ok = ok
+
+# stats
+stats-title = Korpusstatistik
+stats-sessions = Sitzungen: {$count}
+stats-records = Einträge: {$count}
+stats-parse-errors = Parse-Fehler: {$count}
+stats-top-record-types = Häufigste Eintragstypen
+stats-top-block-types = Häufigste Inhaltsblocktypen
+stats-top-models = Häufigste Modelle
+stats-endings = Sitzungen nach Ende
+stats-by-project = Aktivität nach Projekt
+stats-token-usage = Token-Verbrauch nach Modell
+stats-tools = Werkzeugnutzung
+stats-bash-commands = Häufigste Bash-Befehle
+stats-api-errors = API-Fehler
+stats-compare = Veränderung zwischen Zeiträumen
+
+# doctor
+doctor-title = Diagnose
+status-ok = OK
+status-warn = WARNUNG
+status-fail = FEHLER
+doctor-failed = Die Diagnose hat {$count} fehlgeschlagene Prüfungen gefunden.
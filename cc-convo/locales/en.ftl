@@ -0,0 +1,51 @@
+# English output, and the fallback for messages another language lacks.
+# Numbers and dates
+number-group-separator = ,
+decimal-separator = .
+# A chrono strftime pattern, applied in UTC.
+datetime-format = %Y-%m-%dT%H:%M:%SZ
+
+# Shared
+none = (none)
+cancelled = Cancelled.
+redacted-secrets = Redacted {$count} secret(s).
+
+# sessions list
+sessions-title = Sessions
+sessions-none = No sessions found.
+col-idx = Idx
+col-short-id = ShortId
+col-session-id = SessionId
+col-project = Project
+col-modified = Modified
+col-size-kb = SizeKB
+col-user = User
+col-asst = Asst
+col-other = Other
+col-title = Title
+col-health = Health
+col-preview = Preview
+health-ok = ok
+
+# stats
+stats-title = Corpus stats
+stats-sessions = Sessions: {$count}
+stats-records = Records: {$count}
+stats-parse-errors = Parse errors: {$count}
+stats-top-record-types = Top record types
+stats-top-block-types = Top content block types
+stats-top-models = Top models
+stats-endings = Sessions by ending
+stats-by-project = Activity by project
+stats-token-usage = Token usage by model
+stats-tools = Tool usage
+stats-bash-commands = Top Bash commands
+stats-api-errors = API errors
+stats-compare = Change between periods
+
+# doctor
+doctor-title = Doctor
+status-ok = OK
+status-warn = WARN
+status-fail = FAIL
+doctor-failed = Doctor found {$count} failing checks.
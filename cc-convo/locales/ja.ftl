@@ -0,0 +1,49 @@
+# 数値と日付
+number-group-separator = ,
+decimal-separator = .
+datetime-format = %Y/%m/%d %H:%M
+
+# 共通
+none = (なし)
+cancelled = キャンセルしました。
+redacted-secrets = {$count} 件の秘密情報をマスクしました。
+
+# sessions list
+sessions-title = セッション
+sessions-none = セッションが見つかりません。
+col-idx = 番号
+col-short-id = 短縮ID
+col-session-id = セッションID
+col-project = プロジェクト
+col-modified = 更新日時
+col-size-kb = KB
+col-user = ユーザー
+col-asst = 応答
+col-other = 他
+col-title = タイトル
+col-health = 状態
+col-preview = プレビュー
+health-ok = 正常
+
+# stats
+stats-title = コーパス統計
+stats-sessions = セッション数: {$count}
+stats-records = レコード数: {$count}
+stats-parse-errors = 解析エラー: {$count}
+stats-top-record-types = レコード種別 上位
+stats-top-block-types = コンテンツブロック種別 上位
+stats-top-models = モデル 上位
+stats-endings = 終了理由別セッション
+stats-by-project = プロジェクト別アクティビティ
+stats-token-usage = モデル別トークン使用量
+stats-tools = ツールの使用状況
+stats-bash-commands = よく使われた Bash コマンド
+stats-api-errors = API エラー
+stats-compare = 期間ごとの変化
+
+# doctor
+doctor-title = 診断
+status-ok = OK
+status-warn = 警告
+status-fail = 失敗
+doctor-failed = 診断で {$count} 件のチェックが失敗しました。
@@ -0,0 +1,313 @@
+//! `sessions list --interactive`: the session table in the terminal, with a
+//! filter, a sort column, and keys to show or export the selected session.
+
+use crate::palette::palette;
+use anyhow::{Context, Result};
+use cc_convo_core::ellipsize;
+use console::{pad_str, Alignment, Key, Term};
+use std::cmp::Ordering;
+
+pub struct Column {
+    pub title: String,
+    pub width: usize,
+    pub numeric: bool,
+}
+
+/// What a row sorts by in one column.
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub enum SortKey {
+    Text(String),
+    Number(f64),
+}
+
+pub struct Row {
+    pub cells: Vec<String>,
+    pub keys: Vec<SortKey>,
+}
+
+/// What the user left the table to do, with the row it applies to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Show(usize),
+    Export(usize),
+    Quit,
+}
+
+pub struct TableView {
+    columns: Vec<Column>,
+    rows: Vec<Row>,
+    filter: String,
+    /// Typed keys go to the filter rather than to commands.
+    editing: bool,
+    sort_column: usize,
+    descending: bool,
+    /// Indices into `rows` that pass the filter, in sort order.
+    visible: Vec<usize>,
+    /// Position of the selected row in `visible`.
+    cursor: usize,
+    /// Position in `visible` of the first row on screen.
+    scroll: usize,
+}
+
+const HELP: &str = "↑↓ move  / filter  s sort  r reverse  enter show  e export  q quit";
+
+impl TableView {
+    /// Rows start sorted by `sort_column`, descending if `descending`.
+    pub fn new(columns: Vec<Column>, rows: Vec<Row>, sort_column: usize, descending: bool) -> Self {
+        let mut view = Self {
+            columns,
+            rows,
+            filter: String::new(),
+            editing: false,
+            sort_column,
+            descending,
+            visible: Vec::new(),
+            cursor: 0,
+            scroll: 0,
+        };
+        view.refresh();
+        view
+    }
+
+    /// Apply one key press; `Some` once the user picked an action.
+    pub fn handle(&mut self, key: &Key, page: usize) -> Option<Action> {
+        if self.editing {
+            match key {
+                Key::Char(c) if !c.is_control() => self.filter.push(*c),
+                Key::Backspace => {
+                    self.filter.pop();
+                }
+                Key::Enter => self.editing = false,
+                Key::Escape => {
+                    self.filter.clear();
+                    self.editing = false;
+                }
+                _ => return None,
+            }
+            self.refresh();
+            return None;
+        }
+        let last = self.visible.len().saturating_sub(1);
+        match key {
+            Key::ArrowUp | Key::Char('k') => self.cursor = self.cursor.saturating_sub(1),
+            Key::ArrowDown | Key::Char('j') => self.cursor = (self.cursor + 1).min(last),
+            Key::PageUp => self.cursor = self.cursor.saturating_sub(page.max(1)),
+            Key::PageDown => self.cursor = (self.cursor + page.max(1)).min(last),
+            Key::Home | Key::Char('g') => self.cursor = 0,
+            Key::End | Key::Char('G') => self.cursor = last,
+            Key::Char('/') => self.editing = true,
+            Key::Char('s') => {
+                self.sort_column = (self.sort_column + 1) % self.columns.len();
+                // Numbers read best largest first, text from A.
+                self.descending = self.columns[self.sort_column].numeric;
+                self.refresh();
+            }
+            Key::Char('r') => {
+                self.descending = !self.descending;
+                self.refresh();
+            }
+            Key::Enter => return self.selected().map(Action::Show),
+            Key::Char('e') => return self.selected().map(Action::Export),
+            Key::Char('q') | Key::Escape => return Some(Action::Quit),
+            _ => {}
+        }
+        None
+    }
+
+    /// The selected row, as an index into the rows the table was built with.
+    pub fn selected(&self) -> Option<usize> {
+        self.visible.get(self.cursor).copied()
+    }
+
+    fn refresh(&mut self) {
+        let selected = self.selected();
+        let filter = self.filter.as_str();
+        self.visible = (0..self.rows.len())
+            .filter(|&i| crate::fuzzy_matches(&self.rows[i].cells.join(" "), filter))
+            .collect();
+        let (column, descending) = (self.sort_column, self.descending);
+        // Stable, so equal keys keep the listing's order either way round.
+        self.visible.sort_by(|&a, &b| {
+            let order = self.rows[a].keys[column]
+                .partial_cmp(&self.rows[b].keys[column])
+                .unwrap_or(Ordering::Equal);
+            if descending {
+                order.reverse()
+            } else {
+                order
+            }
+        });
+        self.cursor = selected
+            .and_then(|row| self.visible.iter().position(|&i| i == row))
+            .unwrap_or(0);
+    }
+
+    /// The screen: a status line, the header, up to `height - 3` rows, and
+    /// the key help, each cut to `width` columns.
+    pub fn render(&mut self, width: usize, height: usize) -> Vec<String> {
+        let page = height.saturating_sub(3).max(1);
+        if self.cursor < self.scroll {
+            self.scroll = self.cursor;
+        } else if self.cursor >= self.scroll + page {
+            self.scroll = self.cursor + 1 - page;
+        }
+        let sort = &self.columns[self.sort_column].title;
+        let arrow = if self.descending { "↓" } else { "↑" };
+        let filter = if self.editing || !self.filter.is_empty() {
+            format!("/{}{}", self.filter, if self.editing { "▏" } else { "" })
+        } else {
+            "/ to filter".to_string()
+        };
+        let mut lines = vec![palette()
+            .heading
+            .apply_to(ellipsize(
+                &format!(
+                    "{filter}   {} of {} session(s), by {sort} {arrow}",
+                    self.visible.len(),
+                    self.rows.len()
+                ),
+                width,
+            ))
+            .to_string()];
+        // A gutter marks the selected row where colors are off.
+        let width = width.saturating_sub(2);
+        let header = self.line(self.columns.iter().map(|c| c.title.as_str()), width);
+        lines.push(format!("  {}", console::style(header).bold()));
+        for (at, &row) in self.visible.iter().enumerate().skip(self.scroll).take(page) {
+            let line = self.line(self.rows[row].cells.iter().map(String::as_str), width);
+            lines.push(if at == self.cursor {
+                format!("> {}", console::style(line).reverse())
+            } else {
+                format!("  {line}")
+            });
+        }
+        if self.visible.is_empty() {
+            lines.push(palette().meta.apply_to("No sessions match.").to_string());
+        }
+        lines.push(palette().meta.apply_to(ellipsize(HELP, width)).to_string());
+        lines
+    }
+
+    /// Cells padded to their columns; the last column takes what's left.
+    fn line<'a>(&self, cells: impl Iterator<Item = &'a str>, width: usize) -> String {
+        let mut line = String::new();
+        for (at, (column, cell)) in self.columns.iter().zip(cells).enumerate() {
+            if at + 1 == self.columns.len() {
+                line.push_str(cell);
+                break;
+            }
+            let align = if column.numeric {
+                Alignment::Right
+            } else {
+                Alignment::Left
+            };
+            line.push_str(&pad_str(
+                &ellipsize(cell, column.width),
+                column.width,
+                align,
+                None,
+            ));
+            line.push_str("  ");
+        }
+        pad_str(
+            &ellipsize(line.trim_end(), width),
+            width,
+            Alignment::Left,
+            None,
+        )
+        .into_owned()
+    }
+
+    pub fn run(&mut self, term: &Term) -> Result<Action> {
+        term.hide_cursor()?;
+        let result = self.run_shown(term);
+        term.show_cursor()?;
+        result
+    }
+
+    fn run_shown(&mut self, term: &Term) -> Result<Action> {
+        let mut drawn = 0;
+        loop {
+            let (rows, columns) = term.size();
+            // One column spare, so a full line never wraps and leaves a
+            // stray row behind on redraw.
+            let width = usize::from(columns).saturating_sub(1);
+            let height = usize::from(rows).saturating_sub(1).max(4);
+            let lines = self.render(width, height);
+            term.clear_last_lines(drawn)?;
+            for line in &lines {
+                term.write_line(line)?;
+            }
+            drawn = lines.len();
+            let key = term.read_key().context("Failed to read a key")?;
+            if let Some(action) = self.handle(&key, height.saturating_sub(3)) {
+                term.clear_last_lines(drawn)?;
+                return Ok(action);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn view() -> TableView {
+        let columns = ["Idx", "Project", "Title"]
+            .iter()
+            .map(|title| Column {
+                title: title.to_string(),
+                width: 10,
+                numeric: *title == "Idx",
+            })
+            .collect();
+        let rows = [
+            (1, "api", "login triage"),
+            (2, "web", "nginx proxy"),
+            (3, "api", "design doc"),
+        ]
+        .iter()
+        .map(|(idx, project, title)| Row {
+            cells: vec![idx.to_string(), project.to_string(), title.to_string()],
+            keys: vec![
+                SortKey::Number(f64::from(*idx)),
+                SortKey::Text(project.to_string()),
+                SortKey::Text(title.to_string()),
+            ],
+        })
+        .collect();
+        TableView::new(columns, rows, 0, false)
+    }
+
+    #[test]
+    fn keys_filter_sort_and_pick_rows() {
+        let mut view = view();
+        assert_eq!(view.handle(&Key::ArrowDown, 10), None);
+        assert_eq!(view.handle(&Key::Enter, 10), Some(Action::Show(1)));
+
+        // Typed text filters; `e` only exports once the filter is closed.
+        view.handle(&Key::Char('/'), 10);
+        for c in "api e".chars() {
+            assert_eq!(view.handle(&Key::Char(c), 10), None);
+        }
+        assert_eq!(view.visible, [0, 2]);
+        view.handle(&Key::Enter, 10);
+        assert_eq!(view.handle(&Key::Char('e'), 10), Some(Action::Export(0)));
+
+        // Sorting keeps the selected row selected.
+        view.handle(&Key::ArrowDown, 10);
+        view.handle(&Key::Char('s'), 10);
+        view.handle(&Key::Char('s'), 10);
+        assert_eq!(view.visible, [2, 0]);
+        assert_eq!(view.selected(), Some(2));
+        view.handle(&Key::Char('r'), 10);
+        assert_eq!(view.visible, [0, 2]);
+
+        view.handle(&Key::Char('/'), 10);
+        view.handle(&Key::Escape, 10);
+        assert_eq!(view.visible.len(), 3);
+        let screen = view.render(40, 6);
+        assert_eq!(screen.len(), 6);
+        assert_eq!(view.handle(&Key::Char('q'), 10), Some(Action::Quit));
+    }
+}
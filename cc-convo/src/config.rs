@@ -0,0 +1,88 @@
+//! Settings from the config file (`--config`, by default
+//! `~/.config/cc-convo/config.toml`). A flag given on the command line wins
+//! over the same setting here.
+
+use anyhow::{Context, Result};
+use cc_convo_core::{write_atomic, Language, Theme, UnknownRecords};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use toml_edit::{DocumentMut, Item, Table};
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Where Claude Code keeps project transcripts, as `--claude-dir` sets.
+    pub claude_dir: Option<String>,
+    /// Color theme of terminal and HTML output.
+    pub theme: Option<Theme>,
+    /// Mark each message's role with an icon, as `--icons` does.
+    pub icons: bool,
+    /// Policy for records of unknown types, as `--unknown-records` sets.
+    pub unknown_records: Option<UnknownRecords>,
+    /// The `[search]` table.
+    pub search: SearchSettings,
+    /// The `[alias]` table: command lines `alias set` saved, run in place
+    /// of their name.
+    #[serde(rename = "alias")]
+    pub aliases: BTreeMap<String, String>,
+}
+
+/// How smart and query searches, in `search` and the web UI, treat words.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SearchSettings {
+    /// Language of the stopword list and the stemmer, as `--language` sets.
+    pub language: Option<Language>,
+    /// Words left out of queries besides the language's stopwords.
+    pub stopwords: Vec<String>,
+    /// Match words by stem, as `--stem` does.
+    pub stem: bool,
+    /// Refuse `search --include-thinking`, keeping reasoning text out of
+    /// every search.
+    pub disable_thinking: bool,
+}
+
+impl Config {
+    /// Read the config at `path`; a missing file is the default config.
+    pub fn load(path: &Path) -> Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(raw) => toml::from_str(&raw)
+                .with_context(|| format!("Invalid config file {}", path.display())),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err).with_context(|| format!("Failed to read {}", path.display())),
+        }
+    }
+}
+
+/// Set alias `name` to `expansion` in the config at `path`, or remove it
+/// when `expansion` is `None`, keeping the rest of the file as written.
+/// Returns the expansion it replaced.
+pub fn set_alias(path: &Path, name: &str, expansion: Option<&str>) -> Result<Option<String>> {
+    let raw = match fs::read_to_string(path) {
+        Ok(raw) => raw,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(err) => return Err(err).with_context(|| format!("Failed to read {}", path.display())),
+    };
+    let mut doc = raw
+        .parse::<DocumentMut>()
+        .with_context(|| format!("Invalid config file {}", path.display()))?;
+    let aliases = doc
+        .entry("alias")
+        .or_insert_with(|| Item::Table(Table::new()))
+        .as_table_mut()
+        .with_context(|| format!("`alias` in {} is not a table", path.display()))?;
+    let previous = match expansion {
+        Some(expansion) => aliases.insert(name, toml_edit::value(expansion)),
+        None => aliases.remove(name),
+    };
+    if aliases.is_empty() {
+        doc.remove("alias");
+    }
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+    write_atomic(path, doc.to_string())?;
+    Ok(previous.and_then(|item| item.as_str().map(str::to_string)))
+}
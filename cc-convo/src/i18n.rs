@@ -0,0 +1,219 @@
+//! Translations of the human-readable output, so the tables and summaries
+//! read in the user's language. `--json` output never goes through here and
+//! stays the same in every language.
+
+use anyhow::{bail, Result};
+use chrono::{DateTime, Utc};
+use fluent_bundle::concurrent::FluentBundle;
+use fluent_bundle::{FluentArgs, FluentResource};
+use std::sync::OnceLock;
+use unic_langid::LanguageIdentifier;
+
+/// Languages with a message file, by their language subtag. The first one is
+/// the fallback for messages a translation lacks.
+const LOCALES: &[(&str, &str)] = &[
+    ("en", include_str!("../locales/en.ftl")),
+    ("de", include_str!("../locales/de.ftl")),
+    ("ja", include_str!("../locales/ja.ftl")),
+];
+
+static LOCALIZER: OnceLock<Localizer> = OnceLock::new();
+
+pub struct Localizer {
+    bundle: FluentBundle<FluentResource>,
+    fallback: FluentBundle<FluentResource>,
+}
+
+impl Localizer {
+    fn new(lang: &'static str) -> Self {
+        let bundle = |lang: &str| {
+            let (_, source) = LOCALES
+                .iter()
+                .find(|(code, _)| *code == lang)
+                .expect("a bundled locale");
+            let id = lang.parse::<LanguageIdentifier>().expect("a valid locale");
+            let mut bundle = FluentBundle::new_concurrent(vec![id]);
+            // Bidi isolation marks would end up in terminal output and pipes.
+            bundle.set_use_isolating(false);
+            let resource = FluentResource::try_new(source.to_string())
+                .unwrap_or_else(|(_, errors)| panic!("invalid {lang}.ftl: {errors:?}"));
+            bundle
+                .add_resource(resource)
+                .unwrap_or_else(|errors| panic!("duplicate messages in {lang}.ftl: {errors:?}"));
+            bundle
+        };
+        Self {
+            bundle: bundle(lang),
+            fallback: bundle(LOCALES[0].0),
+        }
+    }
+
+    /// The message `id` in this language, or in English if it isn't translated.
+    pub fn message(&self, id: &str, args: Option<&FluentArgs>) -> String {
+        for bundle in [&self.bundle, &self.fallback] {
+            if let Some(pattern) = bundle.get_message(id).and_then(|m| m.value()) {
+                let mut errors = Vec::new();
+                return bundle
+                    .format_pattern(pattern, args, &mut errors)
+                    .into_owned();
+            }
+        }
+        id.to_string()
+    }
+
+    /// `n` with the language's thousands separator.
+    pub fn number(&self, n: u64) -> String {
+        let separator = self.message("number-group-separator", None);
+        let digits = n.to_string();
+        let mut out = String::with_capacity(digits.len() + digits.len() / 3 * separator.len());
+        for (i, digit) in digits.chars().enumerate() {
+            if i > 0 && (digits.len() - i).is_multiple_of(3) {
+                out.push_str(&separator);
+            }
+            out.push(digit);
+        }
+        out
+    }
+
+    /// `x` with `places` decimals and the language's decimal separator.
+    pub fn decimal(&self, x: f64, places: usize) -> String {
+        let formatted = format!("{x:.places$}");
+        match formatted.split_once('.') {
+            Some((whole, fraction)) => format!(
+                "{whole}{}{fraction}",
+                self.message("decimal-separator", None)
+            ),
+            None => formatted,
+        }
+    }
+
+    /// An RFC 3339 timestamp in UTC and the language's date format; anything
+    /// else comes back unchanged.
+    pub fn datetime(&self, rfc3339: &str) -> String {
+        match DateTime::parse_from_rfc3339(rfc3339) {
+            Ok(at) => at
+                .with_timezone(&Utc)
+                .format(&self.message("datetime-format", None))
+                .to_string(),
+            Err(_) => rfc3339.to_string(),
+        }
+    }
+}
+
+/// The bundled language matching a locale name such as `de`, `de-AT`, or
+/// `ja_JP.UTF-8`.
+fn supported(locale: &str) -> Option<&'static str> {
+    let locale = locale.split(['.', '@']).next()?.replace('_', "-");
+    let id = locale.parse::<LanguageIdentifier>().ok()?;
+    LOCALES
+        .iter()
+        .map(|(code, _)| *code)
+        .find(|code| id.language.as_str() == *code)
+}
+
+/// Pick the output language: `lang` if given, else the system locale, else
+/// English. An explicit language without a translation is an error.
+pub fn init(lang: Option<&str>) -> Result<()> {
+    let lang = match lang {
+        Some(lang) => match supported(lang) {
+            Some(code) => code,
+            None => bail!(
+                "Unsupported language {lang:?}; available: {}",
+                LOCALES
+                    .iter()
+                    .map(|(code, _)| *code)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        },
+        None => sys_locale::get_locale()
+            .as_deref()
+            .and_then(supported)
+            .unwrap_or(LOCALES[0].0),
+    };
+    let _ = LOCALIZER.set(Localizer::new(lang));
+    Ok(())
+}
+
+/// The active localizer; English until [`init`] runs.
+pub fn localizer() -> &'static Localizer {
+    LOCALIZER.get_or_init(|| Localizer::new(LOCALES[0].0))
+}
+
+/// Format a message: `tr!("sessions-none")` or
+/// `tr!("redacted-secrets", count = n)`.
+macro_rules! tr {
+    ($id:literal) => {
+        $crate::i18n::localizer().message($id, None)
+    };
+    ($id:literal, $($name:ident = $value:expr),+ $(,)?) => {{
+        let mut args = ::fluent_bundle::FluentArgs::new();
+        $(args.set(stringify!($name), ::fluent_bundle::FluentValue::from($value));)+
+        $crate::i18n::localizer().message($id, Some(&args))
+    }};
+}
+pub(crate) use tr;
+
+/// `n` with the active language's thousands separator.
+pub fn number(n: u64) -> String {
+    localizer().number(n)
+}
+
+/// `x` with `places` decimals and the active language's decimal separator.
+pub fn decimal(x: f64, places: usize) -> String {
+    localizer().decimal(x, places)
+}
+
+/// An RFC 3339 timestamp in the active language's date format.
+pub fn datetime(rfc3339: &str) -> String {
+    localizer().datetime(rfc3339)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages_numbers_and_dates_follow_the_language() {
+        assert_eq!(supported("de_DE.UTF-8"), Some("de"));
+        assert_eq!(supported("ja-JP"), Some("ja"));
+        assert_eq!(supported("C"), None);
+        assert_eq!(supported("fr"), None);
+
+        let de = Localizer::new("de");
+        assert_eq!(de.number(1_234_567), "1.234.567");
+        assert_eq!(de.number(999), "999");
+        assert_eq!(de.decimal(1.26, 1), "1,3");
+        assert_eq!(de.datetime("2026-01-05T10:00:00Z"), "05.01.2026 10:00");
+        let mut args = FluentArgs::new();
+        args.set("count", 1);
+        assert_eq!(
+            de.message("redacted-secrets", Some(&args)),
+            "1 Geheimnis maskiert."
+        );
+
+        let en = Localizer::new("en");
+        assert_eq!(en.number(1_234_567), "1,234,567");
+        assert_eq!(en.datetime("2026-01-05T10:00:00Z"), "2026-01-05T10:00:00Z");
+        assert_eq!(en.datetime("not a date"), "not a date");
+
+        // Every translated message has an English original to fall back on.
+        let ids = |source: &'static str| {
+            source
+                .lines()
+                .filter_map(|line| line.split_once(" ="))
+                .map(|(id, _)| id)
+                .filter(|id| !id.starts_with([' ', '#', '.']))
+                .collect::<Vec<_>>()
+        };
+        let english = ids(LOCALES[0].1);
+        for (code, source) in &LOCALES[1..] {
+            for id in ids(source) {
+                assert!(
+                    english.contains(&id),
+                    "{code}.ftl has {id} without an English original"
+                );
+            }
+        }
+    }
+}
@@ -1,22 +1,69 @@
 use anyhow::{anyhow, bail, Context, Result};
-use chrono::{DateTime, SecondsFormat, Utc};
+use cc_convo_core::{
+    align_events, backup_session, build_export_document, build_faq, claude_dir_candidates,
+    clean_preview, clean_preview_to, command_names, compress_transcript, dedupe_content,
+    default_claude_dir, default_export_name, detect_pastes, diff_exports, diff_lines,
+    discover_with, drop_content_blocks, ellipsize, expand_tilde_path, extract_content_text,
+    find_by_title, find_session, fold_pastes, home_dir, html_escape, is_passphrase_encrypted,
+    jsonl_records, lock_path, open_transcript, pair_exchanges, parse_recipient, parse_session,
+    parse_session_reader_with, parse_session_with, read_chatgpt_export, read_codex_sessions,
+    render_faq_markdown, render_search_html, resolve_target, search_sessions,
+    search_sessions_explained, search_sessions_with, session_end, session_usage, short_id,
+    split_by_gap, split_by_topic, split_document, tool_calls, tool_results, wrap_words,
+    write_assets, write_atomic, write_bundle_export, write_dataset_split, write_export_index,
+    write_obsidian_vault, write_single_export, Analysis, ApiError, ApiErrorKind, Compression,
+    ContentBlock, ContentItem, ConversationTree, Decryption, Deduped, DiffOp, Embedder, Encryption,
+    Explanation, ExportDocument, ExportFormat, ExportStyle, FileLock, FileStamp, HashingEmbedder,
+    ImportedSession, IndexEntry, Job, JobSpec, JobState, JobStore, Language, Message,
+    MessageContent, MetadataCache, NameTemplate, NormalizedEvent, ParseDiagnostic, ParseOptions,
+    PasteKind, PrefilterStage, Pricing, Record, RecordBody, Redactor, SchemaBaseline, SchemaDrift,
+    SchemaInventory, SearchHit, SearchMode, SearchOptions, Session, SessionEnd, SessionFilter,
+    SessionTitle, SourcedExchange, SpeakerFilter, SplitBy, StagingDir, StitchStore, TagStore,
+    Theme, TimeWindow, TokenUsage, UnknownRecords, UsageScan, VaultNote, VectorIndex, WalkLimits,
+    WindowBy, AGE_EXTENSION, CODEX_SESSIONS_DIR, DEFAULT_ACTIVE_WITHIN_SECS, DEFAULT_MAX_DEPTH,
+    KNOWN_RECORD_TYPES, MAX_PARSE_DIAGNOSTICS, PREVIEW_WIDTH, SHARED_BLOCK_MIN_BYTES,
+};
+use chrono::{DateTime, Datelike, Local, NaiveDate, SecondsFormat, Utc};
 use clap::{Args, CommandFactory, Parser, Subcommand, ValueEnum};
-use clap_complete::{generate, Shell};
+use clap_complete::engine::{ArgValueCandidates, CompletionCandidate};
+use clap_complete::env::EnvCompleter;
+use clap_complete::{generate, CompleteEnv, Shell};
 use console::style;
-use dialoguer::Confirm;
+use dialoguer::{Confirm, Password};
 use indicatif::{ProgressBar, ProgressStyle};
-use regex::RegexBuilder;
-use serde::Serialize;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use std::cmp::Reverse;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::ffi::OsString;
 use std::fs::{self, File};
-use std::io::{BufRead, BufReader, Write};
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::ops::ControlFlow;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use walkdir::WalkDir;
 
+mod browse;
+mod config;
+mod i18n;
+mod markdown;
+mod output;
+mod palette;
+mod serve;
+
+#[cfg(test)]
+mod testutil;
+
+use config::Config;
+use i18n::tr;
+use output::{OutputFormat, RowWriter};
+use palette::palette;
+
+const DEFAULT_CONFIG_PATH: &str = "~/.config/cc-convo/config.toml";
+
 #[derive(Parser, Debug)]
-#[command(name = "cc-convo")]
+#[command(name = "cc-convo", version)]
 #[command(about = "Extract, search, and export Claude local conversations.")]
 struct Cli {
     #[command(flatten)]
@@ -27,20 +74,198 @@ struct Cli {
 
 #[derive(Args, Debug, Clone)]
 struct GlobalArgs {
-    #[arg(long, default_value = "~/.claude/projects")]
-    claude_dir: String,
-    #[arg(long)]
+    #[arg(
+        long,
+        help = "Where Claude Code keeps project transcripts [default: the first that exists of $CLAUDE_CONFIG_DIR/projects, ~/.claude/projects, ~/.config/claude/projects, and on Windows %APPDATA%\\Claude\\projects]."
+    )]
+    claude_dir: Option<String>,
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = OutputFormat::Table,
+        help = "How to print results: text, or json/jsonl/yaml/csv for scripts."
+    )]
+    output_format: OutputFormat,
+    #[arg(
+        long,
+        conflicts_with = "output_format",
+        help = "Shorthand for --output-format json."
+    )]
     json: bool,
     #[arg(long)]
     verbose: bool,
     #[arg(long)]
     no_color: bool,
-    #[arg(long, conflicts_with = "since_days")]
+    #[arg(long, conflicts_with_all = ["since_days", "since"])]
     since_hours: Option<u64>,
-    #[arg(long, conflicts_with = "since_hours")]
+    #[arg(long, conflicts_with_all = ["since_hours", "since"])]
     since_days: Option<u64>,
-    #[arg(long, help = "Upper bound mtime filter in ISO 8601 / RFC3339 format.")]
+    #[arg(
+        long,
+        value_name = "WHEN",
+        help = "Lower time bound: RFC3339, or a phrase like \"yesterday\" or \"last monday\"."
+    )]
+    since: Option<String>,
+    #[arg(
+        long,
+        value_name = "WHEN",
+        help = "Upper time bound: RFC3339, or a phrase like \"2 weeks ago\"."
+    )]
     until: Option<String>,
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = WindowBy::Mtime,
+        help = "Apply --since/--until to file mtimes, or to each record's own timestamp."
+    )]
+    window_by: WindowBy,
+    #[arg(
+        long,
+        help = "Emit NDJSON progress events on stderr for long-running commands."
+    )]
+    progress_json: bool,
+    #[arg(
+        long,
+        default_value_t = DEFAULT_MAX_DEPTH,
+        help = "Maximum directory depth to walk below --claude-dir."
+    )]
+    max_depth: usize,
+    #[arg(
+        long,
+        help = "Don't descend into directories on a different filesystem than --claude-dir."
+    )]
+    one_file_system: bool,
+    #[arg(
+        long,
+        value_name = "N",
+        help = "Threads for per-file work in search and stats [default: one per CPU]."
+    )]
+    jobs: Option<usize>,
+    #[arg(
+        long,
+        default_value = "~/.local/share/cc-convo/tags.json",
+        help = "Where `sessions tag` keeps session tags."
+    )]
+    tags_file: String,
+    #[arg(
+        long,
+        default_value = "~/.local/share/cc-convo/stitches.json",
+        help = "Where `stitch` records which files make up one session."
+    )]
+    stitches_file: String,
+    #[arg(
+        long,
+        default_value = "~/.cache/cc-convo/sessions.json",
+        help = "Where `sessions list` caches each transcript's counts and previews, keyed by path, size, and mtime. `status` keeps its tallies beside it."
+    )]
+    cache_file: String,
+    #[arg(
+        long,
+        default_value = "~/.local/share/cc-convo/jobs",
+        help = "Where `jobs` keeps the state of long-running jobs."
+    )]
+    jobs_dir: String,
+    #[arg(
+        long,
+        help = "Re-read every transcript instead of using or updating --cache-file."
+    )]
+    no_cache: bool,
+    #[arg(
+        long,
+        value_name = "LANG",
+        help = "Language of text output (en, de, ja) [default: from the system locale]. --json output is the same in every language."
+    )]
+    lang: Option<String>,
+    #[arg(
+        long,
+        default_value = DEFAULT_CONFIG_PATH,
+        help = "Config file with defaults for flags like --theme."
+    )]
+    config: String,
+    #[arg(
+        long,
+        value_enum,
+        help = "Color theme of terminal and HTML output [default: from the config file, else auto]."
+    )]
+    theme: Option<Theme>,
+    #[arg(
+        long,
+        help = "Mark roles with icons (emoji, or ASCII where the terminal's locale isn't UTF-8) in text output and markdown/HTML exports."
+    )]
+    icons: bool,
+    #[arg(
+        long,
+        value_enum,
+        value_name = "POLICY",
+        help = "Records of unknown types in show/export/search: keep their JSON, summarize them in a line, or drop them [default: from the config file, else keep with --detailed and drop otherwise]."
+    )]
+    unknown_records: Option<UnknownRecords>,
+    #[arg(
+        long,
+        help = "Keep each streamed record of an assistant reply as its own event instead of merging records that share a message id."
+    )]
+    keep_partials: bool,
+    /// Settings loaded from `--config`.
+    #[arg(skip)]
+    settings: Config,
+}
+
+impl GlobalArgs {
+    /// `--theme`, else the config file's, else `auto`.
+    fn theme(&self) -> Theme {
+        self.theme.or(self.settings.theme).unwrap_or_default()
+    }
+
+    /// `--claude-dir`, or the config file's `claude_dir`, or the first
+    /// existing of the usual places.
+    fn resolve_claude_dir(&self) -> Result<PathBuf> {
+        match self
+            .claude_dir
+            .as_ref()
+            .or(self.settings.claude_dir.as_ref())
+        {
+            Some(dir) => expand_tilde_path(dir),
+            None => default_claude_dir(),
+        }
+    }
+
+    /// `--icons`, or the config file's `icons`.
+    fn icons(&self) -> bool {
+        self.icons || self.settings.icons
+    }
+
+    /// `--unknown-records`, or the config file's `unknown_records`.
+    fn unknown_records(&self) -> Option<UnknownRecords> {
+        self.unknown_records.or(self.settings.unknown_records)
+    }
+
+    fn parse_options(&self, detailed: bool) -> ParseOptions {
+        ParseOptions {
+            detailed,
+            unknown_records: self.unknown_records(),
+            keep_partials: self.keep_partials,
+            ..ParseOptions::default()
+        }
+    }
+
+    /// Stopwords and stemming for search, from the config file.
+    fn search_analysis(&self) -> Analysis {
+        let search = &self.settings.search;
+        Analysis {
+            language: search.language.unwrap_or_default(),
+            extra_stopwords: search.stopwords.clone(),
+            stem: search.stem,
+            ..Analysis::default()
+        }
+    }
+
+    fn export_style(&self) -> ExportStyle {
+        ExportStyle {
+            theme: self.theme(),
+            icons: self.icons(),
+            group_by_project: false,
+        }
+    }
 }
 
 #[derive(Subcommand, Debug)]
@@ -50,10 +275,72 @@ enum Command {
         command: SessionsCommand,
     },
     Export(ExportArgs),
+    /// Re-export a session and list the events added since an earlier JSON export.
+    ExportDiff(ExportDiffArgs),
+    /// Decrypt files written by `export --encrypt`.
+    Decrypt(DecryptArgs),
+    /// Align the events of two sessions and show where they differ.
+    Diff(DiffArgs),
+    /// Cut a long session into parts at long pauses or changes of topic.
+    Split(SplitArgs),
     Search(SearchArgs),
     Stats(StatsArgs),
     Doctor(DoctorArgs),
+    Replay(ReplayArgs),
+    /// Sessions and messages per day, as a month grid or one day's sessions.
+    Calendar(CalendarArgs),
+    /// What was worked on when: every project's sessions (or messages) in time
+    /// order, by hour or day.
+    Timeline(TimelineArgs),
+    /// One entry per session, newest first, like `git log` for Claude work.
+    Log(LogArgs),
+    /// Distinct models, tools, branches, files, or tags across the sessions,
+    /// with how many sessions have each: the values the filters take.
+    Facets(FacetsArgs),
+    /// Recurring questions in a project with their latest answers, as markdown.
+    Faq(FaqArgs),
+    /// Estimated spend per repository, branch, or ticket, for chargeback.
+    CostReport(CostReportArgs),
+    /// Link the files of a session that was continued under another project dir.
+    Stitch(StitchArgs),
+    /// Delete or archive transcripts not written to within a retention window.
+    Prune(PruneArgs),
+    /// Compress transcripts not written to within a window in place; every
+    /// command still reads them.
+    Compact(CompactArgs),
+    /// Convert other assistants' exports into sessions the other commands read.
+    Import {
+        #[command(subcommand)]
+        command: ImportCommand,
+    },
+    /// Today's activity at a glance, cheap enough for a shell prompt.
+    Status(StatusArgs),
+    /// Browse sessions, read conversations, and search from a web browser.
+    Serve(ServeArgs),
+    /// Print a session's latest events and follow new ones as they are written.
+    Tail(TailArgs),
+    /// Show how one transcript line is parsed and rendered, to debug what an
+    /// export leaves out.
+    ExplainRecord(ExplainRecordArgs),
+    /// Run long operations over the whole corpus as jobs that survive
+    /// interruptions and can run in the background.
+    Jobs {
+        #[command(subcommand)]
+        command: JobsCommand,
+    },
+    /// Name a command line you rerun often; `cc-convo NAME` then runs it,
+    /// with any further arguments appended.
+    Alias {
+        #[command(subcommand)]
+        command: AliasCommand,
+    },
+    /// Set up cc-convo: find the transcripts, check a sample of them, write a
+    /// config file, and install shell completions.
+    Init(InitArgs),
+    /// Print a shell completion script.
     Completions(CompletionsArgs),
+    /// Print the manual page as roff, or write one per command with --out-dir.
+    Man(ManArgs),
     #[command(hide = true)]
     List(SessionsListArgs),
     #[command(hide = true)]
@@ -64,6 +351,146 @@ enum Command {
 enum SessionsCommand {
     List(SessionsListArgs),
     Show(SessionsShowArgs),
+    /// Label sessions with your own tags.
+    Tag {
+        #[command(subcommand)]
+        command: TagCommand,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum TagCommand {
+    Add(TagEditArgs),
+    Remove(TagEditArgs),
+    /// Every tag with its session count, or one session's tags.
+    List(TagListArgs),
+}
+
+#[derive(Args, Debug)]
+struct TagEditArgs {
+    #[arg(
+        add = ArgValueCandidates::new(session_candidates),
+        help = "Session index, id, short id, project/id, or project name."
+    )]
+    target: String,
+    #[arg(required = true)]
+    tags: Vec<String>,
+}
+
+#[derive(Args, Debug)]
+struct TagListArgs {
+    #[arg(add = ArgValueCandidates::new(session_candidates))]
+    target: Option<String>,
+}
+
+#[derive(Args, Debug)]
+struct StitchArgs {
+    #[arg(long, value_enum, default_value_t = StitchBy::SessionId)]
+    by: StitchBy,
+    #[arg(
+        long,
+        help = "Show the links that would be recorded without saving them."
+    )]
+    dry_run: bool,
+    #[arg(
+        long,
+        help = "Forget all links, so each file is its own session again."
+    )]
+    clear: bool,
+}
+
+#[derive(Args, Debug)]
+struct PruneArgs {
+    #[arg(
+        long,
+        value_name = "AGE",
+        value_parser = parse_retention,
+        help = "Prune sessions last written longer ago than this, e.g. 36h, 90d, 12w."
+    )]
+    older_than: chrono::Duration,
+    #[arg(long, help = "Filter by project name/path substring.")]
+    project: Option<String>,
+    #[arg(
+        long,
+        value_name = "DIR",
+        help = "Move the files under DIR, keeping their project dirs, instead of deleting them."
+    )]
+    archive: Option<PathBuf>,
+    #[arg(long, help = "List what would be pruned without touching any file.")]
+    dry_run: bool,
+    #[arg(long, help = "Skip the confirmation prompt; required with --json.")]
+    yes: bool,
+}
+
+#[derive(Args, Debug)]
+struct CompactArgs {
+    #[arg(
+        long,
+        value_name = "AGE",
+        value_parser = parse_retention,
+        help = "Compress sessions last written longer ago than this, e.g. 36h, 90d, 12w."
+    )]
+    older_than: chrono::Duration,
+    #[arg(long, help = "Filter by project name/path substring.")]
+    project: Option<String>,
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = Compression::Zstd,
+        help = "zstd for the smallest files, gzip for files any gzip tool opens."
+    )]
+    compression: Compression,
+    #[arg(
+        long,
+        help = "List what would be compressed without touching any file."
+    )]
+    dry_run: bool,
+}
+
+fn parse_retention(raw: &str) -> Result<chrono::Duration> {
+    parse_span(raw, raw)
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+enum StitchBy {
+    /// The `sessionId` recorded in every transcript line.
+    #[value(name = "sessionId")]
+    SessionId,
+}
+
+#[derive(Subcommand, Debug)]
+enum ImportCommand {
+    /// Conversations from a ChatGPT data export (the .zip or its conversations.json).
+    Chatgpt(ImportChatgptArgs),
+    /// Sessions from Codex CLI's rollout logs.
+    Codex(ImportCodexArgs),
+}
+
+#[derive(Args, Debug)]
+struct ImportChatgptArgs {
+    export: PathBuf,
+    #[arg(
+        long,
+        default_value = "chatgpt",
+        help = "Project directory under --claude-dir to write the sessions into."
+    )]
+    project: String,
+}
+
+#[derive(Args, Debug)]
+struct ImportCodexArgs {
+    #[arg(
+        long,
+        default_value = CODEX_SESSIONS_DIR,
+        help = "Codex sessions directory, or one rollout file."
+    )]
+    dir: String,
+    #[arg(
+        long,
+        default_value = "codex",
+        help = "Project directory under --claude-dir to write the sessions into."
+    )]
+    project: String,
 }
 
 #[derive(Args, Debug)]
@@ -74,22 +501,139 @@ struct SessionsListArgs {
     project: Option<String>,
     #[arg(long)]
     with_preview: bool,
+    #[arg(
+        long,
+        value_enum,
+        help = "Which text to show in the preview column (implies --with-preview)."
+    )]
+    preview_mode: Option<PreviewMode>,
+    #[arg(
+        long,
+        value_name = "N",
+        help = "Cut previews to N columns [default: 140] (implies --with-preview)."
+    )]
+    preview_chars: Option<usize>,
+    #[arg(
+        long,
+        value_name = "N",
+        help = "Wrap each preview onto up to N lines under its row instead of a column, for narrow terminals (implies --with-preview)."
+    )]
+    preview_lines: Option<usize>,
+    #[arg(long, help = "Scan each listed file for integrity problems.")]
+    health: bool,
+    #[arg(
+        long,
+        help = "Only sessions that look live: recently written, or stopped mid-turn."
+    )]
+    active: bool,
+    #[arg(
+        long,
+        value_name = "SECS",
+        default_value_t = DEFAULT_ACTIVE_WITHIN_SECS,
+        help = "Seconds since the last write for a session to count as active."
+    )]
+    active_within: i64,
+    #[arg(
+        long,
+        value_enum,
+        help = "Only sessions whose last turn ended with an API error, an interrupt, or a limit."
+    )]
+    ended_by: Option<SessionEnd>,
+    #[arg(
+        long = "tag",
+        action = clap::ArgAction::Append,
+        help = "Only sessions with this tag (repeat to require several)."
+    )]
+    tags: Vec<String>,
+    #[arg(
+        long,
+        conflicts_with = "preview_lines",
+        help = "Browse the list in the terminal: / filters, s and r sort, Enter shows the selected session, e exports it."
+    )]
+    interactive: bool,
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, ValueEnum)]
+enum PreviewMode {
+    #[default]
+    FirstPrompt,
+    LastAnswer,
+    Summary,
 }
 
 #[derive(Args, Debug)]
 struct SessionsShowArgs {
+    #[arg(
+        add = ArgValueCandidates::new(session_candidates),
+        help = "Session index, id, short id, project/id, part of a project name for its latest session, or part of a session title."
+    )]
     target: String,
+    #[arg(
+        long,
+        help = "With a project target, show its Nth most recent session (1 = latest)."
+    )]
+    nth: Option<usize>,
     #[arg(long)]
     detailed: bool,
+    #[arg(
+        long,
+        help = "Follow parentUuid links and show regenerated replies as labeled branches."
+    )]
+    tree: bool,
+    #[arg(long, help = "List each malformed line with its location and error.")]
+    show_parse_errors: bool,
     #[arg(long)]
     max_lines: Option<usize>,
     #[arg(long)]
     raw: bool,
+    #[arg(
+        long,
+        conflicts_with = "raw",
+        help = "Render message Markdown (headings, lists, tables, highlighted code) for the terminal."
+    )]
+    render: bool,
+    #[arg(
+        long,
+        help = "List the API errors and retries of the session and the time spent waiting on them."
+    )]
+    ops: bool,
+    #[command(flatten)]
+    redact: RedactArgs,
 }
 
-#[derive(Args, Debug)]
-struct ExportArgs {
-    #[arg(long = "session", action = clap::ArgAction::Append)]
+#[derive(Args, Debug, Clone, Default)]
+struct RedactArgs {
+    #[arg(
+        long,
+        help = "Mask API keys, bearer tokens, AWS credentials, and private keys."
+    )]
+    redact: bool,
+    #[arg(
+        long = "redact-pattern",
+        value_name = "REGEX",
+        action = clap::ArgAction::Append,
+        help = "Also mask matches of this regex (implies --redact)."
+    )]
+    patterns: Vec<String>,
+}
+
+impl RedactArgs {
+    fn redactor(&self) -> Result<Option<Redactor>> {
+        if !self.redact && self.patterns.is_empty() {
+            return Ok(None);
+        }
+        Redactor::new(&self.patterns).map(Some)
+    }
+}
+
+#[derive(Args, Debug, Clone, Default)]
+struct SelectionArgs {
+    #[arg(
+        long = "session",
+        action = clap::ArgAction::Append,
+        add = ArgValueCandidates::new(session_candidates),
+        help = "Session id, short id, or project/id."
+    )]
     sessions: Vec<String>,
     #[arg(long = "index", action = clap::ArgAction::Append)]
     indices: Vec<usize>,
@@ -97,8 +641,20 @@ struct ExportArgs {
     recent: Option<usize>,
     #[arg(long)]
     all: bool,
-    #[arg(long, help = "Select sessions that match this query before exporting.")]
+    #[arg(long, help = "Select sessions that match this query.")]
     search: Option<String>,
+    #[arg(
+        long = "tag",
+        action = clap::ArgAction::Append,
+        help = "Select sessions with this tag (repeat to require several)."
+    )]
+    tags: Vec<String>,
+}
+
+#[derive(Args, Debug)]
+struct ExportArgs {
+    #[command(flatten)]
+    selection: SelectionArgs,
     #[arg(long, value_enum, default_value_t = ExportFormat::Markdown)]
     format: ExportFormat,
     #[arg(long, default_value = "cc-convo-exports")]
@@ -107,21 +663,430 @@ struct ExportArgs {
     detailed: bool,
     #[arg(long)]
     single_file: bool,
+    #[arg(
+        long,
+        help = "Order events by parentUuid and label branched conversations."
+    )]
+    tree: bool,
     #[arg(long)]
     yes: bool,
+    #[arg(
+        long,
+        conflicts_with_all = ["sessions", "indices", "recent", "all", "search", "tags", "resume"],
+        help = "Pick the sessions to export from a list with previews, narrowed by a fuzzy filter."
+    )]
+    interactive: bool,
+    #[arg(long, help = "List each malformed line with its location and error.")]
+    show_parse_errors: bool,
+    #[arg(
+        long,
+        help = "Export the sessions left over by an interrupted export into --output."
+    )]
+    resume: bool,
+    #[arg(
+        long,
+        conflicts_with_all = ["resume", "single_file", "validation_split", "messages", "from_timestamp", "to_timestamp"],
+        help = "Skip sessions whose files haven't changed since the last --incremental export into --output, recorded in a state file there."
+    )]
+    incremental: bool,
+    #[arg(
+        long,
+        help = "Replace stack traces, logs, and code pasted into prompts with a one-line summary."
+    )]
+    fold_pastes: bool,
+    #[arg(
+        long,
+        help = "Footnote each event with the file, line, and uuid of the record it came from."
+    )]
+    source_refs: bool,
+    #[arg(
+        long,
+        help = "Decode pasted images and documents into an assets/ folder next to each file and link them."
+    )]
+    assets: bool,
+    #[arg(long, help = "Leave out tool calls and results (with --detailed).")]
+    drop_tools: bool,
+    #[arg(long, help = "Leave out thinking blocks (with --detailed).")]
+    drop_thinking: bool,
+    #[arg(
+        long,
+        value_name = "FRACTION",
+        help = "With a dataset format, also write a validation file holding about this share of the sessions."
+    )]
+    validation_split: Option<f64>,
+    #[arg(
+        long,
+        value_name = "TEMPLATE",
+        value_parser = str::parse::<NameTemplate>,
+        conflicts_with_all = ["single_file", "validation_split"],
+        help = "Path of each file under --output, e.g. \"{project}/{date}-{short_id}.md\". Placeholders: {date}, {project}, {slug}, {short_id}, {session_id}, {index}."
+    )]
+    name_template: Option<NameTemplate>,
+    #[arg(
+        long,
+        conflicts_with = "single_file",
+        help = "Don't write index.md (index.html for HTML) linking the files of a multi-file export."
+    )]
+    no_index: bool,
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = BundleOrder::Selection,
+        requires = "single_file",
+        help = "Order of the sessions in a --single-file bundle."
+    )]
+    order: BundleOrder,
+    #[arg(
+        long,
+        value_enum,
+        requires = "single_file",
+        help = "Put a bundle's sessions under a heading per group, after a table of contents."
+    )]
+    group_by: Option<BundleGroup>,
+    #[arg(
+        long,
+        requires = "single_file",
+        help = "Move blocks repeated across a markdown or HTML bundle, like a file pasted into several sessions, into a shared appendix and link to it."
+    )]
+    dedupe_content: bool,
+    #[arg(
+        long,
+        value_name = "A..B",
+        value_parser = str::parse::<MessageRange>,
+        conflicts_with = "resume",
+        help = "Export only messages A through B of a single session, counting from 1; either end may be left off."
+    )]
+    messages: Option<MessageRange>,
+    #[arg(
+        long,
+        value_name = "TIME",
+        conflicts_with = "resume",
+        help = "Export only a single session's messages from TIME on (RFC3339 or a phrase like \"2 hours ago\")."
+    )]
+    from_timestamp: Option<String>,
+    #[arg(
+        long,
+        value_name = "TIME",
+        conflicts_with = "resume",
+        help = "Export only a single session's messages up to TIME."
+    )]
+    to_timestamp: Option<String>,
+    #[arg(
+        long,
+        value_name = "age:KEY|passphrase",
+        value_parser = str::parse::<EncryptTarget>,
+        conflicts_with = "resume",
+        help = "Encrypt the written files with age, to a public key (repeatable) or to a passphrase from $CC_CONVO_PASSPHRASE or a prompt. Each file gets a .age suffix; `cc-convo decrypt` opens them."
+    )]
+    encrypt: Vec<EncryptTarget>,
+    #[command(flatten)]
+    redact: RedactArgs,
+}
+
+/// `export --messages`: events `first` through `last` of a session, counting
+/// from 1 in export order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct MessageRange {
+    first: usize,
+    last: Option<usize>,
+}
+
+impl MessageRange {
+    /// Keep the events in range; an error if the session has fewer than `first`.
+    fn retain(&self, events: &mut Vec<NormalizedEvent>) -> Result<()> {
+        if self.first > events.len() {
+            bail!(
+                "--messages starts at {} but the session has {} message(s)",
+                self.first,
+                events.len()
+            );
+        }
+        if let Some(last) = self.last {
+            events.truncate(last);
+        }
+        events.drain(..self.first - 1);
+        Ok(())
+    }
+}
+
+impl std::str::FromStr for MessageRange {
+    type Err = anyhow::Error;
+
+    fn from_str(raw: &str) -> Result<Self> {
+        let (first, last) = raw.split_once("..").unwrap_or((raw, raw));
+        let bound = |n: &str| {
+            n.trim()
+                .parse::<usize>()
+                .ok()
+                .filter(|&n| n > 0)
+                .ok_or_else(|| {
+                    anyhow!("Invalid message number {n:?} in {raw}; messages count from 1")
+                })
+        };
+        let range = Self {
+            first: if first.is_empty() { 1 } else { bound(first)? },
+            last: if last.is_empty() {
+                None
+            } else {
+                Some(bound(last)?)
+            },
+        };
+        if range.last.is_some_and(|last| last < range.first) {
+            bail!("Empty message range {raw}");
+        }
+        Ok(range)
+    }
+}
+
+/// One `export --encrypt` value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum EncryptTarget {
+    /// An `age1...` public key.
+    Key(String),
+    Passphrase,
+}
+
+impl std::str::FromStr for EncryptTarget {
+    type Err = anyhow::Error;
+
+    fn from_str(raw: &str) -> Result<Self> {
+        if raw == "passphrase" {
+            return Ok(Self::Passphrase);
+        }
+        let key = raw
+            .strip_prefix("age:")
+            .ok_or_else(|| anyhow!("Expected age:<recipient> or passphrase, got {raw:?}"))?;
+        parse_recipient(key)?;
+        Ok(Self::Key(key.trim().to_string()))
+    }
+}
+
+/// The encryption `--encrypt` asks for, reading the passphrase if it names one.
+fn export_encryption(targets: &[EncryptTarget]) -> Result<Option<Encryption>> {
+    if targets.is_empty() {
+        return Ok(None);
+    }
+    if targets.contains(&EncryptTarget::Passphrase) {
+        if targets.len() > 1 {
+            bail!("age can't encrypt to a passphrase and to keys at once; pick one");
+        }
+        return Ok(Some(Encryption::passphrase(read_passphrase(true)?)));
+    }
+    let recipients = targets
+        .iter()
+        .map(|target| match target {
+            EncryptTarget::Key(key) => parse_recipient(key),
+            EncryptTarget::Passphrase => unreachable!("handled above"),
+        })
+        .collect::<Result<_>>()?;
+    Ok(Some(Encryption::Recipients(recipients)))
+}
+
+/// Read instead of prompting for an age passphrase, for scripts.
+const PASSPHRASE_ENV_VAR: &str = "CC_CONVO_PASSPHRASE";
+
+/// `$CC_CONVO_PASSPHRASE`, else a prompt, typed twice when `confirm` is set.
+fn read_passphrase(confirm: bool) -> Result<String> {
+    if let Ok(passphrase) = std::env::var(PASSPHRASE_ENV_VAR) {
+        if passphrase.is_empty() {
+            bail!("${PASSPHRASE_ENV_VAR} is empty");
+        }
+        return Ok(passphrase);
+    }
+    if !console::Term::stderr().is_term() {
+        bail!("No terminal to ask for a passphrase; set ${PASSPHRASE_ENV_VAR}");
+    }
+    let mut prompt = Password::new().with_prompt("Passphrase");
+    if confirm {
+        prompt = prompt.with_confirmation("Repeat passphrase", "The passphrases don't match.");
+    }
+    prompt.interact().context("Failed to read the passphrase")
+}
+
+/// `--from-timestamp`/`--to-timestamp` as a window over event timestamps.
+fn export_slice_window(args: &ExportArgs) -> Result<TimeWindow> {
+    let now = Local::now();
+    let bound = |flag: &str, raw: &Option<String>| {
+        raw.as_deref()
+            .map(|raw| parse_time_bound(flag, raw, now))
+            .transpose()
+    };
+    let window = TimeWindow {
+        since: bound("--from-timestamp", &args.from_timestamp)?,
+        until: bound("--to-timestamp", &args.to_timestamp)?,
+        by: WindowBy::Event,
+    };
+    if let (Some(since), Some(until)) = (window.since, window.until) {
+        if since > until {
+            bail!("--from-timestamp is after --to-timestamp");
+        }
+    }
+    Ok(window)
 }
 
-#[derive(Copy, Clone, Debug, Serialize, ValueEnum)]
+/// Order of the sessions in an `export --single-file` bundle.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
-enum ExportFormat {
-    Markdown,
-    Json,
-    Html,
+enum BundleOrder {
+    #[default]
+    /// As selected: listing order, or the order of --sessions/--indices.
+    Selection,
+    /// Oldest first, by modification time.
+    Time,
+    /// By project, then oldest first.
+    Project,
+    /// Largest transcript first.
+    Size,
+}
+
+impl BundleOrder {
+    fn sort(self, sessions: &mut [Session]) {
+        match self {
+            Self::Selection => {}
+            Self::Time => sessions.sort_by_key(|s| s.modified_epoch),
+            Self::Project => {
+                sessions.sort_by(|a, b| {
+                    a.project
+                        .cmp(&b.project)
+                        .then(a.modified_epoch.cmp(&b.modified_epoch))
+                });
+            }
+            Self::Size => sessions.sort_by_key(|s| std::cmp::Reverse(s.size_bytes)),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum BundleGroup {
+    Project,
+}
+
+#[derive(Args, Debug)]
+struct DecryptArgs {
+    #[arg(required = true, value_name = "FILE", help = "Files ending in .age.")]
+    files: Vec<PathBuf>,
+    #[arg(
+        short,
+        long,
+        value_name = "FILE",
+        help = "age identity file with the secret key; repeatable. Without one, the passphrase comes from $CC_CONVO_PASSPHRASE or a prompt."
+    )]
+    identity: Vec<PathBuf>,
+    #[arg(
+        long,
+        value_name = "DIR",
+        help = "Write the decrypted files here instead of next to the .age files."
+    )]
+    output: Option<PathBuf>,
+    #[arg(long, help = "Overwrite decrypted files that already exist.")]
+    force: bool,
+}
+
+#[derive(Args, Debug)]
+struct ExportDiffArgs {
+    #[arg(help = "An earlier `export --format json` file of the session.")]
+    previous: PathBuf,
+    #[arg(
+        add = ArgValueCandidates::new(session_candidates),
+        help = "Session to re-export (default: the one the earlier export came from)."
+    )]
+    target: Option<String>,
+    #[arg(
+        long,
+        help = "Re-export non-dialog records too (default: match the earlier export)."
+    )]
+    detailed: bool,
+    #[arg(
+        long,
+        help = "Overwrite the earlier export with the fresh one, so the next diff starts from now."
+    )]
+    update: bool,
+    #[command(flatten)]
+    redact: RedactArgs,
+}
+
+#[derive(Args, Debug)]
+struct DiffArgs {
+    #[arg(
+        add = ArgValueCandidates::new(session_candidates),
+        help = "First session: index, id, short id, project/id, or project name."
+    )]
+    a: String,
+    #[arg(
+        add = ArgValueCandidates::new(session_candidates),
+        help = "Second session, given the same ways."
+    )]
+    b: String,
+    #[arg(long)]
+    detailed: bool,
+    #[arg(
+        long,
+        help = "Show the two sessions in columns instead of one listing."
+    )]
+    side_by_side: bool,
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 2,
+        help = "Identical events shown around each difference."
+    )]
+    context: usize,
+    #[arg(
+        long,
+        value_name = "COLUMNS",
+        help = "Width of --side-by-side output [default: the terminal's, else 120]."
+    )]
+    width: Option<usize>,
+}
+
+#[derive(Args, Debug)]
+struct SplitArgs {
+    #[arg(
+        add = ArgValueCandidates::new(session_candidates),
+        help = "Session index, id, short id, project/id, or project name."
+    )]
+    target: String,
+    #[arg(
+        long,
+        value_name = "gap:DURATION|topic",
+        value_parser = str::parse::<SplitBy>,
+        help = "Start a part at a prompt after a pause (gap:2h, gap:30m) or on a new topic."
+    )]
+    by: SplitBy,
+    #[arg(long, value_enum, default_value_t = ExportFormat::Markdown)]
+    format: ExportFormat,
+    #[arg(long, default_value = "cc-convo-exports")]
+    output: PathBuf,
+    #[arg(long)]
+    detailed: bool,
+    #[arg(long, help = "Only list the parts; write nothing.")]
+    list: bool,
+    #[arg(
+        long,
+        default_value_t = 0.3,
+        help = "With --by topic, how similar a prompt must be to the part so far to stay in it (0-1)."
+    )]
+    similarity: f32,
+    #[arg(
+        long,
+        default_value_t = 3,
+        help = "With --by topic, prompts a part holds before a new topic can end it."
+    )]
+    min_prompts: usize,
+    #[command(flatten)]
+    embed: EmbedArgs,
+    #[command(flatten)]
+    redact: RedactArgs,
 }
 
 #[derive(Args, Debug)]
 struct SearchArgs {
-    query: String,
+    #[arg(
+        required_unless_present = "file",
+        help = "What to search for; with --file and no query, the sessions are listed instead."
+    )]
+    query: Option<String>,
     #[arg(long, value_enum, default_value_t = SearchMode::Smart)]
     mode: SearchMode,
     #[arg(long, value_enum, default_value_t = SpeakerFilter::Both)]
@@ -132,27 +1097,206 @@ struct SearchArgs {
     max_results: usize,
     #[arg(long, default_value_t = 150)]
     context_chars: usize,
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = SearchFormat::Text,
+        help = "html: a standalone page of hits grouped by session, with matches marked and each whole message expandable."
+    )]
+    format: SearchFormat,
+    #[arg(long, help = "Write the --format html page here instead of to stdout.")]
+    output: Option<PathBuf>,
+    #[arg(
+        long,
+        help = "Show each hit's matched words and score parts, and the closest events that weren't hits."
+    )]
+    explain: bool,
+    #[arg(
+        long,
+        value_enum,
+        help = "Language of the stopword list and stemmer [default: from the config file, else english]."
+    )]
+    language: Option<Language>,
+    #[arg(
+        long,
+        help = "Keep stopwords like \"how\" and \"the\" as query words in smart and query mode."
+    )]
+    no_stopwords: bool,
+    #[arg(
+        long,
+        help = "Match words by stem in smart and query mode, so \"configured\" finds \"configuring\"."
+    )]
+    stem: bool,
+    #[arg(
+        long,
+        help = "Also search the assistant's thinking blocks, unless the config's [search] disable_thinking is set."
+    )]
+    include_thinking: bool,
+    #[arg(
+        long,
+        default_value = "~/.cache/cc-convo/semantic-index.json",
+        help = "Vector index used and refreshed by --mode semantic."
+    )]
+    index: String,
+    #[command(flatten)]
+    embed: EmbedArgs,
+    #[command(flatten)]
+    filter: FilterArgs,
 }
 
-#[derive(Copy, Clone, Debug, ValueEnum)]
-enum SearchMode {
-    Smart,
-    Exact,
-    Regex,
+/// `--min-messages`, `--max-messages`, and `--model` for `search` and `stats`.
+#[derive(Args, Debug, Clone, Default)]
+struct FilterArgs {
+    #[arg(
+        long,
+        value_name = "N",
+        help = "Only sessions with at least N user and assistant messages."
+    )]
+    min_messages: Option<u64>,
+    #[arg(
+        long,
+        value_name = "N",
+        help = "Only sessions with at most N user and assistant messages."
+    )]
+    max_messages: Option<u64>,
+    #[arg(
+        long,
+        value_name = "NAME",
+        help = "Only sessions answered by a model whose name contains NAME (ignoring case)."
+    )]
+    model: Option<String>,
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "Only sessions that touched PATH in a tool call or mention it; a relative PATH matches the end of absolute ones."
+    )]
+    file: Option<String>,
 }
 
-#[derive(Copy, Clone, Debug, Serialize, ValueEnum, PartialEq, Eq)]
-#[serde(rename_all = "lowercase")]
-enum SpeakerFilter {
-    User,
-    Assistant,
-    Both,
+impl FilterArgs {
+    fn filter(&self) -> SessionFilter {
+        SessionFilter {
+            min_messages: self.min_messages,
+            max_messages: self.max_messages,
+            model: self.model.clone(),
+            file: self.file.clone(),
+        }
+    }
+}
+
+#[derive(Args, Debug)]
+struct EmbedArgs {
+    #[arg(
+        long,
+        value_name = "URL",
+        help = "OpenAI-compatible embeddings endpoint (default: offline hashing)."
+    )]
+    embed_url: Option<String>,
+    #[arg(long, default_value = "text-embedding-3-small")]
+    embed_model: String,
+    #[arg(
+        long,
+        help = "Bearer token for --embed-url (defaults to $CC_CONVO_EMBED_API_KEY)."
+    )]
+    embed_api_key: Option<String>,
+}
+
+impl EmbedArgs {
+    fn embedder(&self) -> Box<dyn Embedder> {
+        match &self.embed_url {
+            Some(url) => Box::new(ApiEmbedder {
+                url: url.clone(),
+                model: self.embed_model.clone(),
+                api_key: self
+                    .embed_api_key
+                    .clone()
+                    .or_else(|| std::env::var("CC_CONVO_EMBED_API_KEY").ok()),
+            }),
+            None => Box::new(HashingEmbedder::default()),
+        }
+    }
+}
+
+impl SearchArgs {
+    fn options(&self, global: &GlobalArgs, time_window: &TimeWindow) -> SearchOptions {
+        let config = global.search_analysis();
+        SearchOptions {
+            query: self.query.clone().unwrap_or_default(),
+            mode: self.mode,
+            speaker: self.speaker,
+            case_sensitive: self.case_sensitive,
+            context_chars: self.context_chars,
+            time_window: time_window.clone(),
+            unknown_records: global.unknown_records(),
+            analysis: Analysis {
+                language: self.language.unwrap_or(config.language),
+                stopwords: !self.no_stopwords,
+                stem: self.stem || config.stem,
+                ..config
+            },
+            thinking: self.include_thinking,
+        }
+    }
 }
 
 #[derive(Args, Debug)]
 struct StatsArgs {
     #[arg(long, default_value_t = 20)]
     top: usize,
+    #[arg(long, help = "List each malformed line with its location and error.")]
+    show_parse_errors: bool,
+    #[arg(
+        long,
+        value_name = "TOML",
+        num_args = 0..=1,
+        help = "Estimate spend per model, project, and session. Pass a TOML file to override the built-in prices."
+    )]
+    pricing: Option<Option<PathBuf>>,
+    #[arg(
+        long,
+        help = "Count interrupts and corrective re-prompts per project and model."
+    )]
+    interruptions: bool,
+    #[arg(
+        long,
+        help = "Measure stack traces, logs, and code pasted into prompts."
+    )]
+    pastes: bool,
+    #[arg(
+        long,
+        help = "Sessions, messages, tokens, and last activity per project, most recently active first."
+    )]
+    by_project: bool,
+    #[arg(
+        long,
+        help = "Calls, error rates, and output sizes per tool, and the commands run through Bash."
+    )]
+    tools: bool,
+    #[arg(
+        long,
+        help = "API errors and retries by kind, error rates per model and day, and time spent waiting on retries."
+    )]
+    errors: bool,
+    #[arg(
+        long,
+        help = "Time from each prompt to the first reply, as p50/p90/p99 per model and project."
+    )]
+    latency: bool,
+    #[arg(
+        long,
+        value_name = "PERIOD vs PERIOD",
+        value_parser = str::parse::<ComparePeriods>,
+        help = "Sessions, tokens, cost, and model shares in two periods and the change between them, e.g. \"last-30d vs prior-30d\" or \"2026-09-01..2026-10-01 vs prior-4w\"."
+    )]
+    compare: Option<ComparePeriods>,
+    #[arg(
+        long,
+        conflicts_with_all = ["show_parse_errors", "interruptions", "pastes", "by_project", "tools", "errors", "latency", "compare"],
+        help = "Only token usage per model (and spend with --pricing), parsing just the lines that report usage."
+    )]
+    usage_only: bool,
+    #[command(flatten)]
+    filter: FilterArgs,
 }
 
 #[derive(Args, Debug)]
@@ -161,659 +1305,8459 @@ struct DoctorArgs {
     sample_files: usize,
     #[arg(long, default_value = "cc-convo-exports")]
     output: PathBuf,
+    #[arg(
+        long,
+        help = "Scan every transcript and write a per-file report to --output."
+    )]
+    deep: bool,
+    #[arg(long, value_enum, default_value_t = ReportFormat::Markdown)]
+    report_format: ReportFormat,
+    #[arg(long, help = "Skip checks of the local Claude Code installation.")]
+    skip_env: bool,
+    #[arg(
+        long,
+        default_value_t = 2048,
+        help = "Warn about ~/.claude subdirectories above this size."
+    )]
+    large_dir_mb: u64,
 }
 
-#[derive(Args, Debug)]
-struct CompletionsArgs {
-    #[arg(value_enum)]
-    shell: Shell,
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+enum SearchFormat {
+    Text,
+    Html,
 }
 
-#[derive(Debug, Clone, Serialize)]
-struct Session {
-    index: usize,
-    id: String,
-    id_short: String,
-    project: String,
-    path: PathBuf,
-    modified_iso: String,
-    modified_epoch: i64,
-    size_bytes: u64,
+#[derive(Copy, Clone, Debug, ValueEnum)]
+enum ReportFormat {
+    Markdown,
+    Json,
 }
 
-#[derive(Debug, Clone, Serialize)]
-struct SessionSummary {
-    session: Session,
-    user_messages: u64,
-    assistant_messages: u64,
-    other_records: u64,
-    preview: Option<String>,
+#[derive(Args, Debug)]
+struct ReplayArgs {
+    #[command(flatten)]
+    selection: SelectionArgs,
+    #[arg(long, help = "Model to re-ask the selected prompts against.")]
+    model: String,
+    #[arg(long, help = "Anthropic API key (defaults to $ANTHROPIC_API_KEY).")]
+    api_key: Option<String>,
+    #[arg(long, default_value = "https://api.anthropic.com/v1/messages")]
+    api_url: String,
+    #[arg(long, default_value_t = 4096)]
+    max_tokens: u32,
+    #[arg(long, help = "Replay at most N prompts per session.")]
+    max_prompts: Option<usize>,
+    #[arg(
+        long,
+        help = "Send the original preceding turns as conversation history."
+    )]
+    with_history: bool,
+    #[arg(long, value_enum, default_value_t = ExportFormat::Markdown)]
+    format: ExportFormat,
+    #[arg(long, default_value = "cc-convo-exports")]
+    output: PathBuf,
+    #[arg(
+        long,
+        help = "List the prompts that would be sent without calling the API."
+    )]
+    dry_run: bool,
 }
 
-#[derive(Debug, Clone, Serialize)]
-struct NormalizedEvent {
-    role: String,
-    source_type: String,
-    timestamp: Option<String>,
-    content: String,
+#[derive(Args, Debug)]
+struct TailArgs {
+    #[arg(
+        conflicts_with = "tmux",
+        add = ArgValueCandidates::new(session_candidates),
+        help = "Session index, id, short id, project/id, or part of a project name (default: the latest session)."
+    )]
+    target: Option<String>,
+    #[arg(
+        long,
+        help = "Follow the Claude process running in this tmux window, switching to its newer sessions and titling this pane."
+    )]
+    tmux: bool,
+    #[arg(long, default_value_t = 10, help = "Events to print before following.")]
+    lines: usize,
+    #[arg(
+        long,
+        default_value_t = 500,
+        help = "Milliseconds between checks for new records."
+    )]
+    poll_ms: u64,
+    #[arg(long)]
+    detailed: bool,
 }
 
-#[derive(Debug, Clone, Serialize)]
-struct SearchHit {
-    session_id: String,
-    project: String,
-    path: PathBuf,
-    speaker: String,
-    timestamp: Option<String>,
-    relevance: f64,
-    preview: String,
+#[derive(Args, Debug)]
+struct ExplainRecordArgs {
+    #[arg(long, help = "Transcript file (JSONL, or gzip/zstd-compressed JSONL).")]
+    file: PathBuf,
+    #[arg(long, help = "1-based line number of the record.")]
+    line: usize,
 }
 
-#[derive(Debug, Clone, Serialize)]
-struct ParseOutput {
-    events: Vec<NormalizedEvent>,
-    parse_errors: u64,
+#[derive(Args, Debug)]
+struct LogArgs {
+    #[arg(long, help = "Filter by project name/path substring.")]
+    project: Option<String>,
+    #[arg(long, default_value_t = 50)]
+    limit: usize,
 }
 
-#[derive(Debug, Clone)]
-struct TimeWindow {
-    since: Option<DateTime<Utc>>,
-    until: Option<DateTime<Utc>>,
+#[derive(Args, Debug)]
+struct FacetsArgs {
+    #[arg(value_enum)]
+    facet: Facet,
+    #[arg(long, help = "Filter by project name/path substring.")]
+    project: Option<String>,
+    #[arg(long, value_name = "N", help = "Show only the N most common values.")]
+    limit: Option<usize>,
 }
 
-fn main() -> Result<()> {
-    let cli = Cli::parse();
-    if cli.global.no_color {
-        console::set_colors_enabled(false);
-        console::set_colors_enabled_stderr(false);
-    }
+/// A per-session set of values that `facets` counts.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+enum Facet {
+    Models,
+    Tools,
+    Branches,
+    Files,
+    Tags,
+}
 
-    let time_window = time_window_from_global(&cli.global)?;
-    let claude_dir = expand_tilde_path(&cli.global.claude_dir)?;
+impl Facet {
+    fn title(self) -> &'static str {
+        match self {
+            Self::Models => "Models",
+            Self::Tools => "Tools",
+            Self::Branches => "Branches",
+            Self::Files => "Files",
+            Self::Tags => "Tags",
+        }
+    }
 
-    match cli.command {
-        Command::Sessions { command } => match command {
-            SessionsCommand::List(args) => {
-                cmd_sessions_list(&claude_dir, &time_window, &cli.global, args)
-            }
-            SessionsCommand::Show(args) => {
-                cmd_sessions_show(&claude_dir, &time_window, &cli.global, args)
+    /// The option that filters sessions by one of the values.
+    fn filter_hint(self) -> Option<&'static str> {
+        match self {
+            Self::Models => {
+                Some("--model NAME (search, stats) or model:NAME (search --mode query)")
             }
-        },
-        Command::List(args) => cmd_sessions_list(&claude_dir, &time_window, &cli.global, args),
-        Command::View(args) => cmd_sessions_show(&claude_dir, &time_window, &cli.global, args),
-        Command::Export(args) => cmd_export(&claude_dir, &time_window, &cli.global, args),
-        Command::Search(args) => cmd_search(&claude_dir, &time_window, &cli.global, args),
-        Command::Stats(args) => cmd_stats(&claude_dir, &time_window, &cli.global, args),
+            Self::Files => Some("--file PATH (search, stats)"),
+            Self::Tags => Some("--tag TAG (sessions list, export)"),
+            Self::Tools | Self::Branches => None,
+        }
+    }
+}
+
+#[derive(Args, Debug)]
+struct ServeArgs {
+    #[arg(
+        long,
+        value_name = "ADDR:PORT",
+        default_value = "127.0.0.1:8787",
+        help = "Address to listen on; use 0.0.0.0:8787 to share on your LAN."
+    )]
+    http: std::net::SocketAddr,
+    #[arg(long, default_value_t = 200, help = "Sessions on the list page.")]
+    limit: usize,
+}
+
+#[derive(Args, Debug)]
+struct CostReportArgs {
+    #[arg(
+        long,
+        value_enum,
+        value_delimiter = ',',
+        default_value = "repo,branch",
+        help = "What to break spend down by, outermost first."
+    )]
+    group_by: Vec<CostGroup>,
+    #[arg(
+        long,
+        value_name = "REGEX",
+        default_value = r"[A-Z][A-Z0-9]+-[0-9]+",
+        help = "Pattern of ticket ids in branch names; the first capture group, else the whole match, is the ticket."
+    )]
+    ticket_pattern: String,
+    #[arg(
+        long,
+        value_name = "TOML",
+        help = "Override the built-in per-model prices."
+    )]
+    pricing: Option<PathBuf>,
+    #[arg(long, default_value_t = 50)]
+    top: usize,
+}
+
+/// A column of `cost-report`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+enum CostGroup {
+    /// The git repository the session worked in, by its top-level directory's name.
+    Repo,
+    /// The checked-out branch.
+    Branch,
+    /// The ticket id found in the branch name.
+    Ticket,
+    /// The Claude project dir.
+    Project,
+    Model,
+}
+
+impl CostGroup {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Repo => "repo",
+            Self::Branch => "branch",
+            Self::Ticket => "ticket",
+            Self::Project => "project",
+            Self::Model => "model",
+        }
+    }
+}
+
+#[derive(Args, Debug)]
+struct FaqArgs {
+    #[arg(long, help = "Filter by project name/path substring.")]
+    project: Option<String>,
+    #[arg(long, help = "Write the FAQ here instead of stdout.")]
+    output: Option<PathBuf>,
+    #[arg(
+        long,
+        default_value_t = 2,
+        help = "Leave out questions asked fewer times than this."
+    )]
+    min_asked: usize,
+    #[arg(
+        long,
+        default_value_t = 0.5,
+        help = "Cosine similarity at which two questions count as the same (0-1)."
+    )]
+    similarity: f32,
+    #[arg(long, default_value_t = 50)]
+    max_entries: usize,
+    #[command(flatten)]
+    embed: EmbedArgs,
+    #[command(flatten)]
+    redact: RedactArgs,
+}
+
+#[derive(Args, Debug)]
+struct StatusArgs {
+    #[arg(
+        long,
+        help = "Print one `sessions=N tokens=N last_age=SECS` line for prompts and status bars."
+    )]
+    porcelain: bool,
+}
+
+#[derive(Args, Debug)]
+struct CalendarArgs {
+    #[arg(
+        long,
+        value_name = "YYYY-MM",
+        conflicts_with = "day",
+        help = "Month to show; defaults to the current one."
+    )]
+    month: Option<String>,
+    #[arg(
+        long,
+        value_name = "YYYY-MM-DD",
+        help = "List the sessions with messages on this day instead of the grid."
+    )]
+    day: Option<String>,
+}
+
+#[derive(Args, Debug)]
+struct TimelineArgs {
+    #[arg(
+        long,
+        value_name = "N",
+        help = "Cover the last N days when no --since/--until is given before the command [default: 7]."
+    )]
+    since_days: Option<u64>,
+    #[arg(long, value_enum, default_value_t = TimelineBucket::Hour, help = "Size of the time slots.")]
+    by: TimelineBucket,
+    #[arg(long, help = "Filter by project name/path substring.")]
+    project: Option<String>,
+    #[arg(
+        long,
+        help = "List each message instead of one line per session and slot."
+    )]
+    events: bool,
+}
+
+/// Time slots of `cc-convo timeline`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+enum TimelineBucket {
+    Hour,
+    Day,
+}
+
+#[derive(Subcommand, Debug)]
+enum JobsCommand {
+    /// Start a job over the sessions the time window and --project select.
+    Start {
+        #[command(subcommand)]
+        kind: JobKind,
+    },
+    /// Every job with its state and progress.
+    List,
+    /// One job in detail.
+    Status(JobIdArgs),
+    /// Continue an interrupted or failed job where it stopped.
+    Resume(JobResumeArgs),
+    /// Stop a job after its current session; it can't be resumed.
+    Cancel(JobIdArgs),
+}
+
+#[derive(Subcommand, Debug)]
+enum JobKind {
+    /// Write one export file per session.
+    ExportAll(JobExportArgs),
+    /// Bring the index of `search --mode semantic` up to date.
+    Reindex(JobReindexArgs),
+    /// Copy transcripts, keeping their layout under --claude-dir.
+    Backup(JobBackupArgs),
+}
+
+#[derive(Args, Debug)]
+struct JobExportArgs {
+    #[arg(long)]
+    output: PathBuf,
+    #[arg(long, value_enum, default_value_t = ExportFormat::Markdown)]
+    format: ExportFormat,
+    #[arg(long)]
+    detailed: bool,
+    #[command(flatten)]
+    run: JobRunArgs,
+}
+
+#[derive(Args, Debug)]
+struct JobReindexArgs {
+    #[arg(long, default_value = "~/.cache/cc-convo/semantic-index.json")]
+    index: String,
+    #[command(flatten)]
+    embed: EmbedArgs,
+    #[command(flatten)]
+    run: JobRunArgs,
+}
+
+#[derive(Args, Debug)]
+struct JobBackupArgs {
+    #[arg(long)]
+    output: PathBuf,
+    #[command(flatten)]
+    run: JobRunArgs,
+}
+
+#[derive(Args, Debug)]
+struct JobRunArgs {
+    #[arg(long, help = "Filter by project name/path substring.")]
+    project: Option<String>,
+    #[arg(
+        long,
+        help = "Run in the background, writing output to a log next to the job's state."
+    )]
+    detach: bool,
+}
+
+#[derive(Subcommand, Debug)]
+enum AliasCommand {
+    /// Save an alias in the config file, replacing one of the same name.
+    Set(AliasSetArgs),
+    /// Delete an alias from the config file.
+    Remove(AliasNameArgs),
+    /// Every alias with the command line it runs.
+    List,
+}
+
+#[derive(Args, Debug)]
+struct AliasSetArgs {
+    name: String,
+    #[arg(help = "Command line to run, quoted as one argument, e.g. \"log --since-days 7\".")]
+    expansion: String,
+}
+
+#[derive(Args, Debug)]
+struct AliasNameArgs {
+    name: String,
+}
+
+#[derive(Args, Debug)]
+struct JobIdArgs {
+    id: u64,
+}
+
+#[derive(Args, Debug)]
+struct JobResumeArgs {
+    id: u64,
+    #[arg(long, help = "Run in the background.")]
+    detach: bool,
+}
+
+#[derive(Args, Debug)]
+struct InitArgs {
+    #[arg(long, help = "Replace an existing config file.")]
+    force: bool,
+    #[arg(
+        long,
+        help = "Take the detected settings without asking, and install completions unless --no-completions."
+    )]
+    yes: bool,
+    #[arg(long, help = "Leave shell completions alone.")]
+    no_completions: bool,
+    #[arg(
+        long,
+        help = "Print the config instead of writing it; nothing is installed."
+    )]
+    print: bool,
+}
+
+#[derive(Args, Debug)]
+struct CompletionsArgs {
+    #[arg(value_enum)]
+    shell: Shell,
+    #[arg(
+        long,
+        help = "Ask cc-convo for candidates while completing, which adds session ids from the claude dir."
+    )]
+    dynamic: bool,
+}
+
+#[derive(Args, Debug)]
+struct ManArgs {
+    #[arg(
+        long,
+        value_name = "DIR",
+        help = "Write cc-convo.1 and a page per subcommand into DIR instead of printing."
+    )]
+    out_dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct SessionSummary {
+    session: Session,
+    user_messages: u64,
+    assistant_messages: u64,
+    other_records: u64,
+    tool_uses: u64,
+    images: u64,
+    models: Vec<String>,
+    /// See [`SessionCounts::files`].
+    files: Vec<String>,
+    /// See [`SessionTitle::title`].
+    title: Option<String>,
+    preview: Option<String>,
+    /// See [`Session::is_active`].
+    active: bool,
+    /// See [`session_end`].
+    ended_by: Option<SessionEnd>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    health: Option<SessionHealth>,
+}
+
+/// Integrity findings for a single transcript file.
+#[derive(Debug, Clone, Default, Serialize)]
+struct SessionHealth {
+    records: u64,
+    parse_errors: u64,
+    parse_error_lines: Vec<usize>,
+    truncated_last_line: bool,
+    missing_timestamps: u64,
+    out_of_order_timestamps: u64,
+    max_gap_secs: Option<i64>,
+    schema_anomalies: Vec<String>,
+    flags: Vec<String>,
+}
+
+fn main() -> Result<()> {
+    // Answers the completion scripts `completions --dynamic` prints.
+    CompleteEnv::with_factory(Cli::command)
+        .var(COMPLETE_ENV_VAR)
+        .complete();
+    let mut cli = Cli::parse_from(expand_alias(std::env::args_os().collect())?);
+    if cli.global.no_color {
+        console::set_colors_enabled(false);
+        console::set_colors_enabled_stderr(false);
+    }
+    i18n::init(cli.global.lang.as_deref())?;
+    cli.global.settings = Config::load(&expand_tilde_path(&cli.global.config)?)?;
+    palette::init(cli.global.theme(), cli.global.icons());
+    if cli.global.json {
+        cli.global.output_format = OutputFormat::Json;
+    }
+    output::init(cli.global.output_format);
+    // Every structured format goes through the commands' JSON reports.
+    cli.global.json = cli.global.output_format != OutputFormat::Table;
+
+    if let Some(jobs) = cli.global.jobs {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(jobs)
+            .build_global()
+            .context("Failed to start the worker thread pool")?;
+    }
+
+    let time_window = time_window_from_global(&cli.global)?;
+    let claude_dir = cli.global.resolve_claude_dir()?;
+
+    let result = match cli.command {
+        Command::Sessions { command } => match command {
+            SessionsCommand::List(args) => {
+                cmd_sessions_list(&claude_dir, &time_window, &cli.global, args)
+            }
+            SessionsCommand::Show(args) => {
+                cmd_sessions_show(&claude_dir, &time_window, &cli.global, args)
+            }
+            SessionsCommand::Tag { command } => {
+                cmd_sessions_tag(&claude_dir, &time_window, &cli.global, command)
+            }
+        },
+        Command::List(args) => cmd_sessions_list(&claude_dir, &time_window, &cli.global, args),
+        Command::View(args) => cmd_sessions_show(&claude_dir, &time_window, &cli.global, args),
+        Command::Export(args) => cmd_export(&claude_dir, &time_window, &cli.global, args),
+        Command::Decrypt(args) => cmd_decrypt(&cli.global, args),
+        Command::ExportDiff(args) => cmd_export_diff(&claude_dir, &time_window, &cli.global, args),
+        Command::Diff(args) => cmd_diff(&claude_dir, &time_window, &cli.global, args),
+        Command::Split(args) => cmd_split(&claude_dir, &time_window, &cli.global, args),
+        Command::Search(args) => cmd_search(&claude_dir, &time_window, &cli.global, args),
+        Command::Stats(args) => cmd_stats(&claude_dir, &time_window, &cli.global, args),
+        Command::CostReport(args) => cmd_cost_report(&claude_dir, &time_window, &cli.global, args),
         Command::Doctor(args) => cmd_doctor(&claude_dir, &time_window, &cli.global, args),
+        Command::Replay(args) => cmd_replay(&claude_dir, &time_window, &cli.global, args),
+        Command::Calendar(args) => cmd_calendar(&claude_dir, &time_window, &cli.global, args),
+        Command::Timeline(args) => cmd_timeline(&claude_dir, &time_window, &cli.global, args),
+        Command::Log(args) => cmd_log(&claude_dir, &time_window, &cli.global, args),
+        Command::Facets(args) => cmd_facets(&claude_dir, &time_window, &cli.global, args),
+        Command::Faq(args) => cmd_faq(&claude_dir, &time_window, &cli.global, args),
+        Command::Stitch(args) => cmd_stitch(&claude_dir, &cli.global, args),
+        Command::Prune(args) => cmd_prune(&claude_dir, &cli.global, args),
+        Command::Compact(args) => cmd_compact(&claude_dir, &cli.global, args),
+        Command::Import { command } => match command {
+            ImportCommand::Chatgpt(args) => cmd_import_chatgpt(&claude_dir, &cli.global, args),
+            ImportCommand::Codex(args) => cmd_import_codex(&claude_dir, &cli.global, args),
+        },
+        Command::Status(args) => cmd_status(&claude_dir, &cli.global, args),
+        Command::Serve(args) => serve::serve(
+            &serve::Viewer {
+                claude_dir: &claude_dir,
+                time_window: &time_window,
+                global: &cli.global,
+                limit: args.limit,
+            },
+            args.http,
+        ),
+        Command::Tail(args) => cmd_tail(&claude_dir, &time_window, &cli.global, args),
+        Command::ExplainRecord(args) => cmd_explain_record(&cli.global, args),
+        Command::Jobs { command } => cmd_jobs(&claude_dir, &time_window, &cli.global, command),
+        Command::Alias { command } => cmd_alias(&cli.global, command),
+        Command::Init(args) => cmd_init(&cli.global, args),
         Command::Completions(args) => cmd_completions(args),
+        Command::Man(args) => cmd_man(args),
+    };
+    if result.is_ok() && interrupted() {
+        std::process::exit(EXIT_INTERRUPTED);
+    }
+    result
+}
+
+/// Exit status for runs cut short by Ctrl-C, matching the shell's 128 + SIGINT.
+const EXIT_INTERRUPTED: i32 = 130;
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+static INTERRUPT_HANDLER: std::sync::Once = std::sync::Once::new();
+
+/// Route Ctrl-C into a flag that long-running loops poll so they can stop
+/// between files and flush what they have. A second Ctrl-C exits immediately.
+/// Installing it again is a no-op.
+fn install_interrupt_handler() -> Result<()> {
+    let mut installed = Ok(());
+    INTERRUPT_HANDLER.call_once(|| installed = set_interrupt_handler());
+    installed
+}
+
+fn set_interrupt_handler() -> Result<()> {
+    ctrlc::set_handler(|| {
+        if INTERRUPTED.swap(true, Ordering::SeqCst) {
+            std::process::exit(EXIT_INTERRUPTED);
+        }
+    })
+    .context("Failed to install Ctrl-C handler")
+}
+
+fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+/// One session's messages on one local calendar day.
+struct DaySpan {
+    messages: u64,
+    first: DateTime<Local>,
+    last: DateTime<Local>,
+}
+
+/// Messages one session has on a calendar day, in local time.
+#[derive(Debug, Serialize)]
+struct DayActivity<'a> {
+    session: &'a Session,
+    messages: u64,
+    first: String,
+    last: String,
+}
+
+fn cmd_calendar(
+    claude_dir: &Path,
+    time_window: &TimeWindow,
+    global: &GlobalArgs,
+    args: CalendarArgs,
+) -> Result<()> {
+    let (first_day, last_day) = if let Some(raw) = &args.day {
+        let day = NaiveDate::parse_from_str(raw, "%Y-%m-%d")
+            .with_context(|| format!("Invalid --day {raw}; expected YYYY-MM-DD"))?;
+        (day, day)
+    } else {
+        let first = match &args.month {
+            Some(raw) => NaiveDate::parse_from_str(&format!("{raw}-01"), "%Y-%m-%d")
+                .with_context(|| format!("Invalid --month {raw}; expected YYYY-MM"))?,
+            None => Local::now().date_naive().with_day(1).expect("day 1 exists"),
+        };
+        let next = first
+            .checked_add_months(chrono::Months::new(1))
+            .ok_or_else(|| anyhow!("Month out of range"))?;
+        (first, next.pred_opt().expect("month has a last day"))
+    };
+
+    let sessions = discover_sessions(claude_dir, time_window, global)?;
+    let progress = Progress::json_only(global, "calendar", "files_done", sessions.len());
+    let mut days: BTreeMap<NaiveDate, BTreeMap<usize, DaySpan>> = BTreeMap::new();
+    for (i, session) in sessions.iter().enumerate() {
+        progress.inc(&session.id_short);
+        let mut parsed = parse_session(session, false)?;
+        time_window.retain_events(&mut parsed.events);
+        for event in &parsed.events {
+            let Some(at) = event
+                .timestamp
+                .as_deref()
+                .and_then(|ts| DateTime::parse_from_rfc3339(ts).ok())
+                .map(|ts| ts.with_timezone(&Local))
+            else {
+                continue;
+            };
+            let date = at.date_naive();
+            if date < first_day || date > last_day {
+                continue;
+            }
+            let span = days.entry(date).or_default().entry(i).or_insert(DaySpan {
+                messages: 0,
+                first: at,
+                last: at,
+            });
+            span.messages += 1;
+            span.first = span.first.min(at);
+            span.last = span.last.max(at);
+        }
+    }
+    progress.finish();
+
+    if args.day.is_some() {
+        let activity = days
+            .remove(&first_day)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(i, span)| DayActivity {
+                session: &sessions[i],
+                messages: span.messages,
+                first: span.first.format("%H:%M").to_string(),
+                last: span.last.format("%H:%M").to_string(),
+            })
+            .collect::<Vec<_>>();
+        if global.json {
+            return print_json(&json!({
+                "day": first_day.to_string(),
+                "sessions": activity,
+            }));
+        }
+        println!(
+            "{}",
+            palette()
+                .heading
+                .apply_to(first_day.format("%A %Y-%m-%d").to_string())
+        );
+        if activity.is_empty() {
+            println!("No messages on this day.");
+        }
+        for a in &activity {
+            println!(
+                "{:<5} {:<10} {}-{} {:>4} msgs  {}",
+                a.session.index, a.session.id_short, a.first, a.last, a.messages, a.session.project
+            );
+        }
+        return Ok(());
+    }
+
+    let totals = days
+        .iter()
+        .map(|(date, per_session)| {
+            let messages = per_session.values().map(|span| span.messages).sum::<u64>();
+            (*date, per_session.len(), messages)
+        })
+        .collect::<Vec<_>>();
+    let distinct_sessions = days
+        .values()
+        .flat_map(|per_session| per_session.keys())
+        .collect::<HashSet<_>>()
+        .len();
+    let total_messages = totals.iter().map(|(_, _, m)| m).sum::<u64>();
+    if global.json {
+        let days_json = totals
+            .iter()
+            .map(|(date, sessions, messages)| {
+                json!({ "date": date.to_string(), "sessions": sessions, "messages": messages })
+            })
+            .collect::<Vec<_>>();
+        return print_json(&json!({
+            "month": first_day.format("%Y-%m").to_string(),
+            "sessions": distinct_sessions,
+            "messages": total_messages,
+            "days": days_json,
+        }));
+    }
+
+    let by_date = totals
+        .iter()
+        .map(|(date, sessions, messages)| (*date, (*sessions, *messages)))
+        .collect::<HashMap<_, _>>();
+    println!(
+        "{}",
+        palette()
+            .heading
+            .apply_to(first_day.format("%B %Y").to_string())
+    );
+    println!(
+        "{}",
+        ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"]
+            .map(|d| format!("{d:>9}"))
+            .concat()
+    );
+    let mut week_days = String::new();
+    let mut week_counts = String::new();
+    let lead = first_day.weekday().num_days_from_monday() as usize;
+    week_days.push_str(&" ".repeat(9 * lead));
+    week_counts.push_str(&" ".repeat(9 * lead));
+    for date in first_day.iter_days().take_while(|d| *d <= last_day) {
+        week_days.push_str(&format!("{:>9}", date.day()));
+        let counts = match by_date.get(&date) {
+            Some((sessions, messages)) => format!("{sessions}s/{messages}m"),
+            None => "-".to_string(),
+        };
+        week_counts.push_str(&format!("{counts:>9}"));
+        if date.weekday() == chrono::Weekday::Sun || date == last_day {
+            println!("{week_days}");
+            println!("{}", palette().meta.apply_to(&week_counts));
+            week_days.clear();
+            week_counts.clear();
+        }
+    }
+    println!();
+    println!(
+        "{} session(s), {} message(s) on {} day(s). Drill down with `cc-convo calendar --day YYYY-MM-DD`.",
+        distinct_sessions,
+        total_messages,
+        totals.len()
+    );
+    Ok(())
+}
+
+/// One session's messages in a timeline slot.
+#[derive(Debug, Serialize)]
+struct TimelineSpan<'a> {
+    session_id: &'a str,
+    id_short: &'a str,
+    project: &'a str,
+    title: Option<&'a str>,
+    messages: u64,
+    first: String,
+    last: String,
+}
+
+/// One message on the timeline.
+#[derive(Debug, Serialize)]
+struct TimelineEvent<'a> {
+    at: String,
+    session_id: &'a str,
+    project: &'a str,
+    role: String,
+    preview: String,
+}
+
+/// A message on the timeline: its local time, its session's index, and itself.
+type TimedEvent = (DateTime<Local>, usize, NormalizedEvent);
+
+/// Characters of a message shown by `timeline --events`.
+const TIMELINE_PREVIEW_LEN: usize = 80;
+
+fn cmd_timeline(
+    claude_dir: &Path,
+    time_window: &TimeWindow,
+    global: &GlobalArgs,
+    args: TimelineArgs,
+) -> Result<()> {
+    let window = if time_window.is_open() {
+        let days = args.since_days.unwrap_or(7);
+        if days == 0 {
+            bail!("--since-days must be > 0");
+        }
+        TimeWindow {
+            since: Some(Utc::now() - chrono::Duration::days(days as i64)),
+            ..time_window.clone()
+        }
+    } else if args.since_days.is_some() {
+        bail!("Give the time window either before `timeline` or with its --since-days, not both");
+    } else {
+        time_window.clone()
+    };
+    let mut sessions = discover_sessions(claude_dir, &window, global)?;
+    if let Some(filter) = &args.project {
+        let filter = filter.to_lowercase();
+        sessions.retain(|s| {
+            s.project.to_lowercase().contains(&filter)
+                || s.path.to_string_lossy().to_lowercase().contains(&filter)
+        });
+    }
+
+    let progress = Progress::json_only(global, "timeline", "files_done", sessions.len());
+    let mut titles = Vec::with_capacity(sessions.len());
+    let mut events = Vec::new();
+    for (i, session) in sessions.iter().enumerate() {
+        progress.inc(&session.id_short);
+        let parsed = parse_session(session, false)?;
+        titles.push(parsed.title.title().map(str::to_string));
+        for event in parsed.events {
+            let Some(at) = event
+                .timestamp
+                .as_deref()
+                .and_then(|ts| DateTime::parse_from_rfc3339(ts).ok())
+            else {
+                continue;
+            };
+            if window.contains(at.with_timezone(&Utc)) {
+                events.push((at.with_timezone(&Local), i, event));
+            }
+        }
+    }
+    progress.finish();
+    events.sort_by_key(|(at, i, _)| (*at, *i));
+
+    // Slots in time order, each holding its events in time order.
+    let mut slots: BTreeMap<chrono::NaiveDateTime, Vec<&TimedEvent>> = BTreeMap::new();
+    for entry in &events {
+        let start = match args.by {
+            TimelineBucket::Hour => {
+                entry
+                    .0
+                    .date_naive()
+                    .and_hms_opt(chrono::Timelike::hour(&entry.0), 0, 0)
+            }
+            TimelineBucket::Day => entry.0.date_naive().and_hms_opt(0, 0, 0),
+        };
+        slots
+            .entry(start.expect("valid slot start"))
+            .or_default()
+            .push(entry);
+    }
+    let spans_of = |slot: &[&TimedEvent]| {
+        let mut spans: Vec<TimelineSpan> = Vec::new();
+        let mut order: HashMap<usize, usize> = HashMap::new();
+        for (at, i, _) in slot.iter().copied() {
+            let time = at.format("%H:%M").to_string();
+            match order.get(i) {
+                Some(&k) => {
+                    spans[k].messages += 1;
+                    spans[k].last = time;
+                }
+                None => {
+                    order.insert(*i, spans.len());
+                    let session = &sessions[*i];
+                    spans.push(TimelineSpan {
+                        session_id: &session.id,
+                        id_short: &session.id_short,
+                        project: &session.project,
+                        title: titles[*i].as_deref(),
+                        messages: 1,
+                        first: time.clone(),
+                        last: time,
+                    });
+                }
+            }
+        }
+        spans
+    };
+    let events_of = |slot: &[&TimedEvent]| {
+        slot.iter()
+            .map(|(at, i, event)| TimelineEvent {
+                at: at.to_rfc3339_opts(SecondsFormat::Secs, false),
+                session_id: &sessions[*i].id,
+                project: &sessions[*i].project,
+                role: event.role.clone(),
+                preview: ellipsize(&clean_preview(&event.content), TIMELINE_PREVIEW_LEN),
+            })
+            .collect::<Vec<_>>()
+    };
+
+    if global.json {
+        let slots_json = slots
+            .iter()
+            .map(|(start, slot)| {
+                let mut entry = json!({
+                    "start": start.format("%Y-%m-%dT%H:%M").to_string(),
+                    "sessions": spans_of(slot),
+                });
+                if args.events {
+                    entry["events"] = json!(events_of(slot));
+                }
+                entry
+            })
+            .collect::<Vec<_>>();
+        return print_json(&json!({
+            "since": window.since.map(|t| t.to_rfc3339_opts(SecondsFormat::Secs, true)),
+            "until": window.until.map(|t| t.to_rfc3339_opts(SecondsFormat::Secs, true)),
+            "by": match args.by {
+                TimelineBucket::Hour => "hour",
+                TimelineBucket::Day => "day",
+            },
+            "messages": events.len(),
+            "slots": slots_json,
+        }));
     }
+
+    if slots.is_empty() {
+        println!("No messages in this window.");
+        return Ok(());
+    }
+    let mut day = None;
+    for (start, slot) in &slots {
+        if day != Some(start.date()) {
+            if day.is_some() {
+                println!();
+            }
+            day = Some(start.date());
+            println!(
+                "{}",
+                palette()
+                    .heading
+                    .apply_to(start.format("%A %Y-%m-%d").to_string())
+            );
+        }
+        let indent = match args.by {
+            TimelineBucket::Hour => {
+                println!("  {}", palette().accent.apply_to(start.format("%H:00")));
+                "    "
+            }
+            TimelineBucket::Day => "  ",
+        };
+        if args.events {
+            for event in events_of(slot) {
+                println!(
+                    "{indent}{} {:<10} {:<12}{}",
+                    palette().meta.apply_to(&event.at[11..19]),
+                    short_id(event.session_id),
+                    format!("[{}]", event.role),
+                    event.preview
+                );
+            }
+            continue;
+        }
+        for span in spans_of(slot) {
+            println!(
+                "{indent}{:<10} {}-{} {:>4} msgs  {}  {}",
+                span.id_short,
+                span.first,
+                span.last,
+                span.messages,
+                span.project,
+                span.title.unwrap_or_default()
+            );
+        }
+    }
+    println!();
+    let distinct = events.iter().map(|(_, i, _)| i).collect::<HashSet<_>>();
+    let projects = distinct
+        .iter()
+        .map(|i| sessions[**i].project.as_str())
+        .collect::<HashSet<_>>();
+    println!(
+        "{} message(s) in {} session(s) across {} project(s).",
+        events.len(),
+        distinct.len(),
+        projects.len()
+    );
+    Ok(())
+}
+
+/// Tools whose `file_path` (or `notebook_path`) input is a file they change.
+const FILE_EDIT_TOOLS: &[&str] = &["Edit", "MultiEdit", "Write", "NotebookEdit"];
+
+/// One session in `cc-convo log`.
+#[derive(Debug, Default, Serialize)]
+struct LogEntry {
+    session_id: String,
+    project: String,
+    /// The custom title, else the summary, else the first prompt.
+    title: Option<String>,
+    /// Claude Code's summary, when it differs from the title.
+    summary: Option<String>,
+    started: Option<String>,
+    ended: Option<String>,
+    duration_secs: Option<i64>,
+    branch: Option<String>,
+    /// Files changed through edit tools, relative to the session's cwd.
+    files: Vec<String>,
+    commits: Vec<LinkedCommit>,
+    pull_requests: Vec<String>,
+}
+
+/// A commit made during the session, read from `git commit` output.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+struct LinkedCommit {
+    sha: String,
+    message: String,
+}
+
+fn cmd_log(
+    claude_dir: &Path,
+    time_window: &TimeWindow,
+    global: &GlobalArgs,
+    args: LogArgs,
+) -> Result<()> {
+    let mut sessions = discover_sessions(claude_dir, time_window, global)?;
+    if let Some(filter) = &args.project {
+        let filter = filter.to_lowercase();
+        sessions.retain(|s| {
+            s.project.to_lowercase().contains(&filter)
+                || s.path.to_string_lossy().to_lowercase().contains(&filter)
+        });
+    }
+    let mut entries = sessions
+        .iter()
+        .map(|session| build_log_entry(session, time_window))
+        .collect::<Result<Vec<_>>>()?;
+    entries.retain(|e| e.started.is_some());
+    entries.sort_by(|a, b| b.started.cmp(&a.started));
+    entries.truncate(args.limit);
+
+    if global.json {
+        return print_json(&entries);
+    }
+    if entries.is_empty() {
+        println!("No sessions found.");
+    }
+    for (i, entry) in entries.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        print_log_entry(entry);
+    }
+    Ok(())
+}
+
+fn cmd_facets(
+    claude_dir: &Path,
+    time_window: &TimeWindow,
+    global: &GlobalArgs,
+    args: FacetsArgs,
+) -> Result<()> {
+    let mut sessions = discover_sessions(claude_dir, time_window, global)?;
+    if let Some(filter) = &args.project {
+        let filter = filter.to_lowercase();
+        sessions.retain(|s| {
+            s.project.to_lowercase().contains(&filter)
+                || s.path.to_string_lossy().to_lowercase().contains(&filter)
+        });
+    }
+    let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+    if args.facet == Facet::Tags {
+        let store = TagStore::load(&tags_path(global)?)?;
+        for session in &sessions {
+            for tag in store.tags(&session.id) {
+                *counts.entry(tag).or_default() += 1;
+            }
+        }
+    } else {
+        let mut cache = CountsCache::open(global, time_window)?;
+        for session in &sessions {
+            let session_counts = cache.counts(session, time_window)?;
+            let values = match args.facet {
+                Facet::Models => session_counts.models,
+                Facet::Tools => session_counts.tools,
+                Facet::Branches => session_counts.branches,
+                Facet::Files => session_counts.files,
+                Facet::Tags => unreachable!("tags come from the tag store"),
+            };
+            for value in values {
+                *counts.entry(value).or_default() += 1;
+            }
+        }
+        cache.save()?;
+    }
+    // Most sessions first; the map already ordered ties by value.
+    let mut ranked = counts.into_iter().collect::<Vec<_>>();
+    ranked.sort_by_key(|(_, sessions)| Reverse(*sessions));
+    let distinct = ranked.len();
+    if let Some(limit) = args.limit {
+        ranked.truncate(limit);
+    }
+
+    if global.json {
+        let rows = ranked
+            .iter()
+            .map(|(value, sessions)| json!({"value": value, "sessions": sessions}))
+            .collect::<Vec<_>>();
+        return print_json(&rows);
+    }
+    println!(
+        "{}",
+        style(format!(
+            "{} ({distinct} distinct, in {} session(s))",
+            args.facet.title(),
+            sessions.len()
+        ))
+        .bold()
+    );
+    if ranked.is_empty() {
+        println!("  {}", tr!("none"));
+    }
+    for (value, count) in &ranked {
+        println!("  {count:>7}  {value}");
+    }
+    if ranked.len() < distinct {
+        println!(
+            "{}",
+            palette()
+                .meta
+                .apply_to(format!("... and {} more", distinct - ranked.len()))
+        );
+    }
+    if let (Some(hint), false) = (args.facet.filter_hint(), ranked.is_empty()) {
+        println!(
+            "{}",
+            palette().meta.apply_to(format!("Filter with {hint}."))
+        );
+    }
+    Ok(())
+}
+
+fn build_log_entry(session: &Session, time_window: &TimeWindow) -> Result<LogEntry> {
+    let mut entry = LogEntry {
+        session_id: session.id.clone(),
+        project: session.project.clone(),
+        ..LogEntry::default()
+    };
+    let mut title = SessionTitle::default();
+    let mut cwd: Option<String> = None;
+    let mut first: Option<DateTime<chrono::FixedOffset>> = None;
+    let mut last: Option<DateTime<chrono::FixedOffset>> = None;
+    let file = open_transcript(&session.path)
+        .with_context(|| format!("Failed to open {}", session.path.display()))?;
+    for record in jsonl_records(BufReader::new(file)) {
+        let Ok(value) = record? else {
+            continue;
+        };
+        let timestamp = value.get("timestamp").and_then(Value::as_str);
+        if !time_window.admits(timestamp) {
+            continue;
+        }
+        if let Ok(record) = Record::from_value(&value) {
+            title.observe(&record);
+        }
+        let field = |key: &str| value.get(key).and_then(Value::as_str);
+        match field("type") {
+            Some("pr-link") => {
+                if let (Some(repo), Some(number)) = (
+                    field("prRepository"),
+                    value.get("prNumber").and_then(Value::as_u64),
+                ) {
+                    entry.pull_requests.push(format!("{repo}#{number}"));
+                }
+            }
+            Some("user" | "assistant") => {
+                if let Some(at) = timestamp.and_then(|ts| DateTime::parse_from_rfc3339(ts).ok()) {
+                    first = Some(first.map_or(at, |f| f.min(at)));
+                    last = Some(last.map_or(at, |l| l.max(at)));
+                }
+                if let Some(branch) = field("gitBranch") {
+                    entry.branch = Some(branch.to_string());
+                }
+                if let Some(dir) = field("cwd") {
+                    cwd = Some(dir.to_string());
+                }
+                let blocks = value
+                    .get("message")
+                    .and_then(|m| m.get("content"))
+                    .and_then(Value::as_array);
+                for block in blocks.into_iter().flatten() {
+                    match block.get("type").and_then(Value::as_str) {
+                        Some("tool_use") => {
+                            let name = block.get("name").and_then(Value::as_str);
+                            if !name.is_some_and(|n| FILE_EDIT_TOOLS.contains(&n)) {
+                                continue;
+                            }
+                            let input = block.get("input");
+                            let path = ["file_path", "notebook_path"].iter().find_map(|key| {
+                                input.and_then(|i| i.get(*key)).and_then(Value::as_str)
+                            });
+                            if let Some(path) = path {
+                                let shown = relative_to(path, cwd.as_deref());
+                                if !entry.files.contains(&shown) {
+                                    entry.files.push(shown);
+                                }
+                            }
+                        }
+                        Some("tool_result") => {
+                            let output = block
+                                .get("content")
+                                .map(|c| extract_content_text(c, false))
+                                .unwrap_or_default();
+                            for commit in output.lines().filter_map(parse_commit_line) {
+                                if !entry.commits.contains(&commit) {
+                                    entry.commits.push(commit);
+                                }
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    entry.title = title.title().map(str::to_string);
+    entry.summary = title.summary.filter(|s| entry.title.as_ref() != Some(s));
+    let iso = |at: DateTime<chrono::FixedOffset>| {
+        at.with_timezone(&Utc)
+            .to_rfc3339_opts(SecondsFormat::Secs, true)
+    };
+    entry.started = first.map(iso);
+    entry.ended = last.map(iso);
+    entry.duration_secs = first.zip(last).map(|(f, l)| (l - f).num_seconds());
+    Ok(entry)
+}
+
+/// `path` relative to `cwd` when it lies below it.
+fn relative_to(path: &str, cwd: Option<&str>) -> String {
+    cwd.and_then(|dir| Path::new(path).strip_prefix(dir).ok())
+        .map_or_else(|| path.to_string(), |rel| rel.display().to_string())
+}
+
+/// A `git commit` summary line such as `[main 1a2b3c4] Fix the race`, also
+/// with `(root-commit)` or a detached `HEAD` description before the hash.
+fn parse_commit_line(line: &str) -> Option<LinkedCommit> {
+    let rest = line.trim().strip_prefix('[')?;
+    let (inside, message) = rest.split_once("] ")?;
+    let words = inside.split_whitespace().collect::<Vec<_>>();
+    let sha = *words.get(1..)?.last()?;
+    let is_sha = (7..=40).contains(&sha.len()) && sha.chars().all(|c| c.is_ascii_hexdigit());
+    is_sha.then(|| LinkedCommit {
+        sha: sha.to_string(),
+        message: message.trim().to_string(),
+    })
+}
+
+fn print_log_entry(entry: &LogEntry) {
+    println!(
+        "{}",
+        palette()
+            .highlight
+            .apply_to(format!("session {} ({})", entry.session_id, entry.project))
+    );
+    if let Some(branch) = &entry.branch {
+        println!("Branch:   {branch}");
+    }
+    if let (Some(started), Some(duration)) = (&entry.started, entry.duration_secs) {
+        println!("Date:     {started} ({})", format_age(duration));
+    }
+    if let Some(title) = &entry.title {
+        println!();
+        println!("    {title}");
+    }
+    if let Some(summary) = &entry.summary {
+        println!();
+        println!("    {summary}");
+    }
+    if !entry.files.is_empty() || !entry.commits.is_empty() || !entry.pull_requests.is_empty() {
+        println!();
+    }
+    if !entry.files.is_empty() {
+        println!("    Files:   {}", entry.files.join(", "));
+    }
+    for commit in &entry.commits {
+        println!(
+            "    Commit:  {} {}",
+            palette()
+                .highlight
+                .apply_to(&commit.sha[..commit.sha.len().min(7)]),
+            commit.message
+        );
+    }
+    for pr in &entry.pull_requests {
+        println!("    PR:      {pr}");
+    }
+}
+
+fn cmd_faq(
+    claude_dir: &Path,
+    time_window: &TimeWindow,
+    global: &GlobalArgs,
+    args: FaqArgs,
+) -> Result<()> {
+    if !(0.0..=1.0).contains(&args.similarity) {
+        bail!("--similarity must be between 0 and 1");
+    }
+    let redactor = args.redact.redactor()?;
+    let mut sessions = discover_sessions(claude_dir, time_window, global)?;
+    if let Some(filter) = &args.project {
+        let filter = filter.to_lowercase();
+        sessions.retain(|s| {
+            s.project.to_lowercase().contains(&filter)
+                || s.path.to_string_lossy().to_lowercase().contains(&filter)
+        });
+    }
+    let progress = Progress::json_only(global, "faq", "files_done", sessions.len());
+    let mut exchanges = Vec::new();
+    let mut redactions = 0;
+    for session in &sessions {
+        progress.inc(&session.id_short);
+        let mut parsed = parse_session(session, false)?;
+        time_window.retain_events(&mut parsed.events);
+        if let Some(redactor) = &redactor {
+            redactions += redactor.redact_events(&mut parsed.events);
+        }
+        exchanges.extend(pair_exchanges(&parsed.events).into_iter().map(|exchange| {
+            SourcedExchange {
+                session_id: session.id.clone(),
+                exchange,
+            }
+        }));
+    }
+    progress.finish();
+
+    let mut embedder = args.embed.embedder();
+    let mut entries = build_faq(
+        &exchanges,
+        embedder.as_mut(),
+        args.similarity,
+        args.min_asked,
+    )?;
+    entries.truncate(args.max_entries);
+
+    let title = match &args.project {
+        Some(project) => format!("FAQ: {project}"),
+        None => "FAQ".to_string(),
+    };
+    let body = if global.json {
+        serde_json::to_string_pretty(&json!({
+            "title": title,
+            "questions": exchanges.len(),
+            "entries": entries,
+        }))? + "\n"
+    } else {
+        render_faq_markdown(&title, &entries)
+    };
+    match &args.output {
+        Some(path) => {
+            if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+                fs::create_dir_all(parent)
+                    .with_context(|| format!("Failed to create {}", parent.display()))?;
+            }
+            write_atomic(path, &body)?;
+            eprintln!(
+                "Wrote {} FAQ entries from {} questions to {}",
+                entries.len(),
+                exchanges.len(),
+                path.display()
+            );
+        }
+        None => print!("{body}"),
+    }
+    report_redactions(redactor.is_some(), redactions);
+    Ok(())
+}
+
+/// Write each conversation to `<claude-dir>/<project>/<id>.jsonl`, dated by
+/// its last update so listings order it among the Claude sessions.
+/// Re-importing a newer export overwrites the earlier copies.
+fn cmd_import_chatgpt(
+    claude_dir: &Path,
+    global: &GlobalArgs,
+    args: ImportChatgptArgs,
+) -> Result<()> {
+    let import = read_chatgpt_export(&args.export)?;
+    let written = write_imported_sessions(claude_dir, &args.project, &import.sessions, global)?;
+    if global.json {
+        return print_json(&json!({
+            "directory": written.dir,
+            "imported": import.sessions.len(),
+            "created": written.created,
+            "updated": written.updated,
+            "skipped": import.skipped,
+        }));
+    }
+    println!(
+        "Imported {} ChatGPT conversation(s) into {} ({} new, {} updated).",
+        import.sessions.len(),
+        written.dir.display(),
+        written.created,
+        written.updated
+    );
+    if import.skipped > 0 {
+        println!(
+            "Skipped {} conversation(s) with no user or assistant text.",
+            import.skipped
+        );
+    }
+    Ok(())
+}
+
+fn cmd_import_codex(claude_dir: &Path, global: &GlobalArgs, args: ImportCodexArgs) -> Result<()> {
+    let import = read_codex_sessions(&expand_tilde_path(&args.dir)?)?;
+    let written = write_imported_sessions(claude_dir, &args.project, &import.sessions, global)?;
+    if global.json {
+        return print_json(&json!({
+            "directory": written.dir,
+            "imported": import.sessions.len(),
+            "created": written.created,
+            "updated": written.updated,
+            "skipped": import.skipped,
+        }));
+    }
+    println!(
+        "Imported {} Codex session(s) into {} ({} new, {} updated).",
+        import.sessions.len(),
+        written.dir.display(),
+        written.created,
+        written.updated
+    );
+    if import.skipped > 0 {
+        println!(
+            "Skipped {} rollout(s) with no user or assistant messages.",
+            import.skipped
+        );
+    }
+    Ok(())
+}
+
+/// Where an import wrote its sessions.
+struct ImportWritten {
+    dir: PathBuf,
+    created: usize,
+    updated: usize,
+}
+
+/// Write `sessions` into the `project` dir under `claude_dir`, one transcript
+/// each, dated by their last activity so listings sort them among the rest.
+fn write_imported_sessions(
+    claude_dir: &Path,
+    project: &str,
+    sessions: &[ImportedSession],
+    global: &GlobalArgs,
+) -> Result<ImportWritten> {
+    let project = project.trim();
+    if project.is_empty() || project.contains(['/', '\\']) || project.starts_with('.') {
+        bail!("--project must be a plain directory name");
+    }
+    let dir = claude_dir.join(project);
+    fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    let progress = Progress::json_only(global, "import", "sessions_done", sessions.len());
+    let (mut created, mut updated) = (0, 0);
+    for session in sessions {
+        progress.inc(&short_id(&session.id));
+        let path = dir.join(format!("{}.jsonl", session.id));
+        if path.exists() {
+            updated += 1;
+        } else {
+            created += 1;
+        }
+        write_atomic(&path, session.to_jsonl())?;
+        if let Some(at) = session.updated_at {
+            File::options()
+                .write(true)
+                .open(&path)
+                .and_then(|f| f.set_modified(at.into()))
+                .with_context(|| format!("Failed to date {}", path.display()))?;
+        }
+    }
+    progress.finish();
+    Ok(ImportWritten {
+        dir,
+        created,
+        updated,
+    })
+}
+
+/// Per-file tallies kept between `status` runs next to `--cache-file`, so a
+/// prompt redraw only reads what was appended since the last one.
+const STATUS_CACHE_NAME: &str = "status.json";
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct StatusCache {
+    /// Local date the tallies count; a new day starts from scratch.
+    date: String,
+    files: BTreeMap<PathBuf, StatusTally>,
+}
+
+/// Today's records and tokens in one transcript, read up to `offset`.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct StatusTally {
+    /// Bytes consumed so far, always at a line boundary.
+    offset: u64,
+    records_today: u64,
+    tokens_today: u64,
+    /// Id and tokens of the last message counted. Streamed replies repeat a
+    /// message's usage on consecutive records, and only the last one counts.
+    last_message: Option<(String, u64)>,
+}
+
+impl StatusTally {
+    /// Fold in the complete lines appended to `path` since the last call.
+    /// A file that shrank was rewritten and is read again from the start.
+    fn advance(&mut self, path: &Path, today: NaiveDate) -> Result<()> {
+        let appended = match read_complete_lines(path, self.offset)? {
+            Some(bytes) => bytes,
+            None => {
+                *self = Self::default();
+                read_complete_lines(path, 0)?.unwrap_or_default()
+            }
+        };
+        for line in appended.split(|&b| b == b'\n') {
+            let Ok(value) = serde_json::from_slice::<Value>(line) else {
+                continue;
+            };
+            let is_today = value
+                .get("timestamp")
+                .and_then(Value::as_str)
+                .and_then(|ts| DateTime::parse_from_rfc3339(ts).ok())
+                .is_some_and(|ts| ts.with_timezone(&Local).date_naive() == today);
+            if !is_today {
+                continue;
+            }
+            self.records_today += 1;
+            let Some(usage) = TokenUsage::from_record(&value) else {
+                continue;
+            };
+            let id = value
+                .get("message")
+                .and_then(|m| m.get("id"))
+                .and_then(Value::as_str);
+            let tokens = usage.total();
+            match (&mut self.last_message, id) {
+                (Some((last_id, last_tokens)), Some(id)) if last_id == id => {
+                    self.tokens_today = self.tokens_today - *last_tokens + tokens;
+                    *last_tokens = tokens;
+                }
+                _ => {
+                    self.tokens_today += tokens;
+                    self.last_message = id.map(|id| (id.to_string(), tokens));
+                }
+            }
+        }
+        self.offset += appended.len() as u64;
+        Ok(())
+    }
+}
+
+/// The complete lines of `path` past `offset`, or `None` if the file is now
+/// shorter than `offset`, i.e. it was rewritten. A half-written last line is
+/// left for the next call. Offsets into a compressed transcript count its
+/// decompressed bytes.
+fn read_complete_lines(path: &Path, offset: u64) -> Result<Option<Vec<u8>>> {
+    let mut appended = Vec::new();
+    if Compression::of(path).is_some() {
+        open_transcript(path)
+            .and_then(|mut reader| reader.read_to_end(&mut appended))
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        if (appended.len() as u64) < offset {
+            return Ok(None);
+        }
+        appended.drain(..offset as usize);
+    } else {
+        let mut file =
+            File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+        if file.metadata()?.len() < offset {
+            return Ok(None);
+        }
+        file.seek(SeekFrom::Start(offset))?;
+        file.read_to_end(&mut appended)?;
+    }
+    let complete = appended
+        .iter()
+        .rposition(|&b| b == b'\n')
+        .map_or(0, |i| i + 1);
+    appended.truncate(complete);
+    Ok(Some(appended))
+}
+
+fn cmd_status(claude_dir: &Path, global: &GlobalArgs, args: StatusArgs) -> Result<()> {
+    let now = Local::now();
+    let today = now.date_naive();
+    let midnight = today
+        .and_hms_opt(0, 0, 0)
+        .and_then(|t| t.and_local_timezone(Local).earliest())
+        .map_or(0, |t| t.timestamp());
+    // Unreadable files are left to other commands to report; a prompt
+    // should not print warnings.
+    let sessions =
+        discover_with(claude_dir, &TimeWindow::default(), &walk_limits(global))?.sessions;
+
+    let cache_path = match global.no_cache {
+        true => None,
+        false => Some(expand_tilde_path(&global.cache_file)?.with_file_name(STATUS_CACHE_NAME)),
+    };
+    // Held until the tallies are written back, so prompts redrawing at once
+    // don't drop each other's offsets. Without it the cache is left alone.
+    let lock = cache_path.as_ref().and_then(|path| {
+        fs::create_dir_all(path.parent()?).ok()?;
+        FileLock::acquire(&lock_path(path), |_| {}).ok()
+    });
+    let cache_path = cache_path.filter(|_| lock.is_some());
+    let mut cache = cache_path
+        .as_ref()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|raw| serde_json::from_str::<StatusCache>(&raw).ok())
+        .filter(|cache| cache.date == today.to_string())
+        .unwrap_or_default();
+    let mut files = BTreeMap::new();
+    for session in sessions.iter().filter(|s| s.modified_epoch >= midnight) {
+        let mut tally = cache.files.remove(&session.path).unwrap_or_default();
+        if tally.advance(&session.path, today).is_ok() {
+            files.insert(session.path.clone(), tally);
+        }
+    }
+    cache = StatusCache {
+        date: today.to_string(),
+        files,
+    };
+    // The cache only saves time; failing to write it must not fail the prompt.
+    if let Some(path) = &cache_path {
+        let _ = serde_json::to_string(&cache)
+            .map_err(anyhow::Error::from)
+            .and_then(|raw| write_atomic(path, raw));
+    }
+    drop(lock);
+
+    let active = cache.files.values().filter(|t| t.records_today > 0).count();
+    let tokens = cache.files.values().map(|t| t.tokens_today).sum::<u64>();
+    let last_age = sessions
+        .iter()
+        .map(|s| s.modified_epoch)
+        .max()
+        .map(|mtime| (now.timestamp() - mtime).max(0));
+
+    if args.porcelain {
+        let age = last_age.map_or_else(|| "-".to_string(), |age| age.to_string());
+        println!("sessions={active} tokens={tokens} last_age={age}");
+        return Ok(());
+    }
+    if global.json {
+        return print_json(&json!({
+            "date": today.to_string(),
+            "sessions_today": active,
+            "tokens_today": tokens,
+            "last_session_age_secs": last_age,
+        }));
+    }
+    let last = last_age.map_or_else(
+        || "never".to_string(),
+        |age| format!("{} ago", format_age(age)),
+    );
+    println!("{active} session(s) today, {tokens} token(s), last activity {last}");
+    Ok(())
+}
+
+/// How often `tail --tmux` looks for a newer session in the followed project.
+const TAIL_RESCAN_SECS: u64 = 5;
+
+fn cmd_tail(
+    claude_dir: &Path,
+    time_window: &TimeWindow,
+    global: &GlobalArgs,
+    args: TailArgs,
+) -> Result<()> {
+    let tmux_project = if args.tmux {
+        Some(tmux_claude_project()?)
+    } else {
+        None
+    };
+    let pick = |sessions: &[Session]| -> Result<Session> {
+        let session = match (&tmux_project, &args.target) {
+            (Some(project), _) => sessions
+                .iter()
+                .find(|s| &s.project == project)
+                .ok_or_else(|| anyhow!("No sessions found for project {project}"))?,
+            (None, Some(target)) => resolve_session_target(sessions, target, None)?,
+            (None, None) => sessions
+                .first()
+                .ok_or_else(|| anyhow!("No sessions found"))?,
+        };
+        Ok(session.clone())
+    };
+    let mut session = pick(&discover_sessions(claude_dir, time_window, global)?)?;
+
+    install_interrupt_handler()?;
+    let bytes = read_complete_lines(&session.path, 0)?.unwrap_or_default();
+    let mut offset = bytes.len() as u64;
+    let mut events =
+        parse_session_reader_with(&bytes[..], &global.parse_options(args.detailed))?.events;
+    time_window.retain_events(&mut events);
+    let mut seen = events.len();
+    announce_tail(&session, global);
+    for event in events.iter().skip(events.len().saturating_sub(args.lines)) {
+        print_tail_event(event, global)?;
+    }
+    if args.tmux {
+        set_tmux_pane_title(&session, seen);
+    }
+
+    let mut last_scan = std::time::Instant::now();
+    while !interrupted() {
+        std::thread::sleep(std::time::Duration::from_millis(args.poll_ms));
+        if args.tmux && last_scan.elapsed().as_secs() >= TAIL_RESCAN_SECS {
+            last_scan = std::time::Instant::now();
+            // `/clear` and restarts begin a new transcript in the same project.
+            let newest = discover_with(claude_dir, time_window, &walk_limits(global))
+                .ok()
+                .and_then(|d| pick(&d.sessions).ok());
+            if let Some(newest) = newest.filter(|s| s.path != session.path) {
+                session = newest;
+                offset = 0;
+                seen = 0;
+                announce_tail(&session, global);
+            }
+        }
+        // Only `compact` writes compressed transcripts, and never appends to
+        // them, so there is nothing to follow past the first read.
+        if offset > 0 && Compression::of(&session.path).is_some() {
+            continue;
+        }
+        let appended = match read_complete_lines(&session.path, offset)? {
+            Some(bytes) => bytes,
+            None => {
+                offset = 0;
+                continue;
+            }
+        };
+        if appended.is_empty() {
+            continue;
+        }
+        offset += appended.len() as u64;
+        let mut events =
+            parse_session_reader_with(&appended[..], &global.parse_options(args.detailed))?.events;
+        time_window.retain_events(&mut events);
+        for event in &events {
+            print_tail_event(event, global)?;
+        }
+        seen += events.len();
+        if args.tmux && !events.is_empty() {
+            set_tmux_pane_title(&session, seen);
+        }
+    }
+    Ok(())
+}
+
+fn announce_tail(session: &Session, global: &GlobalArgs) {
+    if !global.json {
+        eprintln!(
+            "{}",
+            palette().meta.apply_to(format!(
+                "Following {} ({}); Ctrl-C to stop.",
+                session.id, session.project
+            ))
+        );
+    }
+}
+
+/// One event per line; NDJSON with `--json`.
+fn print_tail_event(event: &NormalizedEvent, global: &GlobalArgs) -> Result<()> {
+    if global.json {
+        println!("{}", serde_json::to_string(event)?);
+    } else {
+        println!(
+            "{} {} {}",
+            palette()
+                .meta
+                .apply_to(event.timestamp.as_deref().unwrap_or("-")),
+            palette().role_label(&event.role),
+            event.content
+        );
+    }
+    std::io::stdout().flush()?;
+    Ok(())
+}
+
+fn set_tmux_pane_title(session: &Session, events: usize) {
+    let Ok(pane) = std::env::var("TMUX_PANE") else {
+        return;
+    };
+    let title = format!(
+        "claude {} {} ({events} events)",
+        project_label(&session.project),
+        session.id_short
+    );
+    // A missing or old tmux only costs the title.
+    let _ = std::process::Command::new("tmux")
+        .args(["select-pane", "-t", &pane, "-T", &title])
+        .status();
+}
+
+/// The last path component of an encoded project directory name, e.g.
+/// `alpha` for `-home-user-alpha`.
+fn project_label(project: &str) -> &str {
+    project
+        .rsplit('-')
+        .find(|part| !part.is_empty())
+        .unwrap_or(project)
+}
+
+/// The project directory Claude Code uses for `cwd`: every character other
+/// than an ASCII letter or digit becomes `-`.
+fn claude_project_dir_name(cwd: &Path) -> String {
+    cwd.to_string_lossy()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect()
+}
+
+/// The project directory of the Claude process running in a pane of the
+/// current tmux window.
+fn tmux_claude_project() -> Result<String> {
+    let pane = std::env::var("TMUX_PANE")
+        .context("--tmux must run inside tmux ($TMUX_PANE is not set)")?;
+    let panes = command_output(
+        "tmux",
+        &[
+            "list-panes",
+            "-t",
+            &pane,
+            "-F",
+            "#{pane_pid} #{pane_current_path}",
+        ],
+    )?;
+    let processes = parse_ps_table(&command_output("ps", &["-A", "-o", "pid=,ppid=,args="])?);
+    for line in panes.lines() {
+        let Some((pid, pane_path)) = line.split_once(' ') else {
+            continue;
+        };
+        let Ok(pid) = pid.parse::<u32>() else {
+            continue;
+        };
+        if let Some(claude) = find_claude_process(&processes, pid) {
+            // The pane's path is the shell's; Claude may have been started elsewhere.
+            let cwd = fs::read_link(format!("/proc/{claude}/cwd"))
+                .unwrap_or_else(|_| PathBuf::from(pane_path));
+            return Ok(claude_project_dir_name(&cwd));
+        }
+    }
+    bail!("No Claude process found in this tmux window")
+}
+
+fn command_output(program: &str, args: &[&str]) -> Result<String> {
+    let out = std::process::Command::new(program)
+        .args(args)
+        .output()
+        .with_context(|| format!("Failed to run `{program}`"))?;
+    if !out.status.success() {
+        bail!(
+            "`{program} {}` exited with {}: {}",
+            args.join(" "),
+            out.status,
+            String::from_utf8_lossy(&out.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&out.stdout).into_owned())
+}
+
+/// A process from `ps -o pid=,ppid=,args=`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ProcessEntry {
+    pid: u32,
+    ppid: u32,
+    args: String,
+}
+
+fn parse_ps_table(raw: &str) -> Vec<ProcessEntry> {
+    raw.lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let pid = fields.next()?.parse().ok()?;
+            let ppid = fields.next()?.parse().ok()?;
+            Some(ProcessEntry {
+                pid,
+                ppid,
+                args: fields.collect::<Vec<_>>().join(" "),
+            })
+        })
+        .collect()
+}
+
+/// `root` or its nearest descendant running `claude`, either directly or as
+/// the script of an interpreter such as `node`.
+fn find_claude_process(processes: &[ProcessEntry], root: u32) -> Option<u32> {
+    let is_claude = |p: &ProcessEntry| {
+        p.args
+            .split_whitespace()
+            .take(2)
+            .any(|arg| Path::new(arg).file_name().and_then(|n| n.to_str()) == Some("claude"))
+    };
+    let mut queue = std::collections::VecDeque::from([root]);
+    while let Some(pid) = queue.pop_front() {
+        if processes.iter().any(|p| p.pid == pid && is_claude(p)) {
+            return Some(pid);
+        }
+        queue.extend(
+            processes
+                .iter()
+                .filter(|p| p.ppid == pid && p.pid != pid)
+                .map(|p| p.pid),
+        );
+    }
+    None
+}
+
+/// `age` in seconds as the largest whole unit, e.g. `42s`, `7m`, `3h`, `12d`.
+fn format_age(age: i64) -> String {
+    match age {
+        ..=59 => format!("{age}s"),
+        60..=3599 => format!("{}m", age / 60),
+        3600..=86_399 => format!("{}h", age / 3600),
+        _ => format!("{}d", age / 86_400),
+    }
+}
+
+/// How a record or block fares in normal and `--detailed` parsing.
+#[derive(Serialize)]
+struct Rendering {
+    normal: Option<String>,
+    detailed: Option<String>,
+}
+
+impl Rendering {
+    fn print(&self, indent: &str) {
+        for (mode, text) in [("normal", &self.normal), ("detailed", &self.detailed)] {
+            match text {
+                Some(text) => println!(
+                    "{indent}{mode:<9} {}",
+                    ellipsize(&text.replace('\n', " "), 100)
+                ),
+                None => println!("{indent}{mode:<9} {}", palette().meta.apply_to("(dropped)")),
+            }
+        }
+    }
+}
+
+fn cmd_explain_record(global: &GlobalArgs, args: ExplainRecordArgs) -> Result<()> {
+    if args.line == 0 {
+        bail!("--line is 1-based; got 0");
+    }
+    let mut bytes = Vec::new();
+    open_transcript(&args.file)
+        .and_then(|mut reader| reader.read_to_end(&mut bytes))
+        .with_context(|| format!("Failed to read {}", args.file.display()))?;
+    let lines = bytes.split(|b| *b == b'\n').collect::<Vec<_>>();
+    let line_count = lines.len() - usize::from(bytes.ends_with(b"\n"));
+    let Some(raw) = lines.get(args.line - 1).filter(|_| args.line <= line_count) else {
+        bail!("{} has {line_count} line(s)", args.file.display());
+    };
+    let Some(record) = jsonl_records(*raw).next() else {
+        bail!("Line {} of {} is blank", args.line, args.file.display());
+    };
+    let record = match record? {
+        Ok(record) => record,
+        Err(diagnostic) => bail!(
+            "Line {} of {} is not a record ({}): {}",
+            args.line,
+            args.file.display(),
+            diagnostic.kind.as_str(),
+            diagnostic.message
+        ),
+    };
+
+    let record_type = record
+        .get("type")
+        .and_then(Value::as_str)
+        .unwrap_or("<missing>");
+    let known_type = KNOWN_RECORD_TYPES.contains(&record_type);
+    let event = |detailed: bool| -> Result<Option<String>> {
+        let parsed = parse_session_reader_with(*raw, &global.parse_options(detailed))?;
+        Ok(parsed
+            .events
+            .first()
+            .map(|e| format!("[{}] {}", e.role, e.content)))
+    };
+    let event = Rendering {
+        normal: event(false)?,
+        detailed: event(true)?,
+    };
+    let content = record.get("message").and_then(|m| m.get("content"));
+    let blocks = match content {
+        Some(Value::Array(items)) => items
+            .iter()
+            .map(|item| {
+                let kind = match item {
+                    Value::String(_) => "string",
+                    _ => item
+                        .get("type")
+                        .and_then(Value::as_str)
+                        .unwrap_or("<untyped>"),
+                };
+                let render = |detailed: bool| {
+                    let text = extract_content_text(&Value::Array(vec![item.clone()]), detailed);
+                    (!text.is_empty()).then_some(text)
+                };
+                let rendering = Rendering {
+                    normal: render(false),
+                    detailed: render(true),
+                };
+                (kind.to_string(), rendering)
+            })
+            .collect::<Vec<_>>(),
+        _ => Vec::new(),
+    };
+    let mut inventory = SchemaInventory::default();
+    inventory.observe(&record);
+    let drift = SchemaBaseline::bundled().drift(&inventory);
+
+    if global.json {
+        return print_json(&json!({
+            "file": args.file,
+            "line": args.line,
+            "record_type": record_type,
+            "known_record_type": known_type,
+            "record": record,
+            "event": event,
+            "content_blocks": blocks.iter().enumerate().map(|(i, (kind, rendering))| json!({
+                "index": i,
+                "type": kind,
+                "normal": rendering.normal,
+                "detailed": rendering.detailed,
+            })).collect::<Vec<_>>(),
+            "unknown_record_type": !drift.new_record_types.is_empty(),
+            "unknown_fields": drift.new_fields,
+        }));
+    }
+
+    println!(
+        "{}",
+        palette()
+            .heading
+            .apply_to(format!("Line {} of {}", args.line, args.file.display()))
+    );
+    println!(
+        "Type: {record_type} ({})",
+        if known_type {
+            "known"
+        } else {
+            "not a known record type"
+        }
+    );
+    println!();
+    println!("{}", style("Record").bold());
+    println!("{}", serde_json::to_string_pretty(&record)?);
+    println!();
+    println!("{}", style("Event").bold());
+    event.print("  ");
+    if !blocks.is_empty() {
+        println!();
+        println!("{}", style("Content blocks").bold());
+        for (i, (kind, rendering)) in blocks.iter().enumerate() {
+            println!("  [{i}] {kind}");
+            rendering.print("      ");
+        }
+    }
+    println!();
+    println!(
+        "{}",
+        style("Fields the bundled schema inventory lacks").bold()
+    );
+    if drift.new_fields.is_empty() && drift.new_record_types.is_empty() {
+        println!("  {}", tr!("none"));
+    }
+    for record_type in &drift.new_record_types {
+        println!("  {} type {record_type}", palette().warn.apply_to("!"));
+    }
+    for field in &drift.new_fields {
+        println!("  {} {field}", palette().warn.apply_to("!"));
+    }
+    Ok(())
+}
+
+/// Sessions a job runs between saves of its progress. Export and backup
+/// files are written whole, so redoing a few after a crash is harmless; the
+/// semantic index is rewritten whole, so saving it per session would be slow.
+const JOB_CHECKPOINT_EVERY: usize = 25;
+
+fn cmd_jobs(
+    claude_dir: &Path,
+    time_window: &TimeWindow,
+    global: &GlobalArgs,
+    command: JobsCommand,
+) -> Result<()> {
+    let store = JobStore::new(expand_tilde_path(&global.jobs_dir)?);
+    match command {
+        JobsCommand::Start { kind } => {
+            let (spec, run) = match kind {
+                JobKind::ExportAll(args) => {
+                    if matches!(args.format, ExportFormat::Obsidian) {
+                        bail!("Jobs write one file per session; use `export --all --format obsidian` for a vault");
+                    }
+                    let spec = JobSpec::ExportAll {
+                        output: std::path::absolute(&args.output)?,
+                        format: args.format,
+                        detailed: args.detailed,
+                    };
+                    (spec, args.run)
+                }
+                JobKind::Reindex(args) => {
+                    // Job files are plain JSON, so the key is read from the
+                    // environment by whichever process runs the job.
+                    if args.embed.embed_api_key.is_some() {
+                        bail!(
+                            "Jobs don't store --embed-api-key; set $CC_CONVO_EMBED_API_KEY instead"
+                        );
+                    }
+                    let spec = JobSpec::Reindex {
+                        index: std::path::absolute(expand_tilde_path(&args.index)?)?,
+                        embed_url: args.embed.embed_url,
+                        embed_model: args.embed.embed_model,
+                    };
+                    (spec, args.run)
+                }
+                JobKind::Backup(args) => {
+                    let spec = JobSpec::Backup {
+                        output: std::path::absolute(&args.output)?,
+                    };
+                    (spec, args.run)
+                }
+            };
+            let mut sessions = discover_sessions(claude_dir, time_window, global)?;
+            if let Some(filter) = &run.project {
+                let filter = filter.to_lowercase();
+                sessions.retain(|s| {
+                    s.project.to_lowercase().contains(&filter)
+                        || s.path.to_string_lossy().to_lowercase().contains(&filter)
+                });
+            }
+            if sessions.is_empty() {
+                bail!("No sessions selected for the job.");
+            }
+            let mut job = store.create(
+                spec,
+                &std::path::absolute(claude_dir)?,
+                sessions.iter().map(Session::qualified_id).collect(),
+            )?;
+            if run.detach {
+                spawn_job(&store, &mut job, global)
+            } else {
+                run_job(&store, job.id, global)
+            }
+        }
+        JobsCommand::List => {
+            let jobs = store.list()?;
+            if global.json {
+                return print_json(&jobs);
+            }
+            if jobs.is_empty() {
+                println!("No jobs in {}.", store.dir().display());
+                return Ok(());
+            }
+            println!(
+                "{}",
+                palette().heading.apply_to(format!(
+                    "{:<4} {:<11} {:<12} {:>11}  Target",
+                    "ID", "Kind", "State", "Sessions"
+                ))
+            );
+            for job in &jobs {
+                println!(
+                    "{:<4} {:<11} {:<12} {:>11}  {}",
+                    job.id,
+                    job.spec.kind(),
+                    job.state.as_str(),
+                    format!("{}/{}", job.done, job.sessions.len()),
+                    job.spec.target().display()
+                );
+            }
+            Ok(())
+        }
+        JobsCommand::Status(args) => {
+            let job = store.load(args.id)?;
+            if global.json {
+                return print_json(&job);
+            }
+            print_job_status(&job);
+            Ok(())
+        }
+        JobsCommand::Resume(args) => {
+            let mut job = store.load(args.id)?;
+            ensure_resumable(&store, &job)?;
+            if args.detach {
+                spawn_job(&store, &mut job, global)
+            } else {
+                run_job(&store, job.id, global)
+            }
+        }
+        JobsCommand::Cancel(args) => {
+            let mut job = store.load(args.id)?;
+            if job.is_finished() {
+                bail!("Job {} is already {}", job.id, job.state.as_str());
+            }
+            let running = store.is_running(job.id)?;
+            if running {
+                store.request_cancel(job.id)?;
+            } else {
+                job.state = JobState::Cancelled;
+                job.touch();
+                store.save(&job)?;
+            }
+            if global.json {
+                return print_json(&json!({ "id": job.id, "stopping": running }));
+            }
+            if running {
+                println!("Job {} will stop after its current session.", job.id);
+            } else {
+                println!("Cancelled job {}.", job.id);
+            }
+            Ok(())
+        }
+    }
+}
+
+fn ensure_resumable(store: &JobStore, job: &Job) -> Result<()> {
+    if job.is_finished() {
+        bail!("Job {} is already {}", job.id, job.state.as_str());
+    }
+    if store.is_running(job.id)? {
+        bail!("Job {} is already running", job.id);
+    }
+    Ok(())
+}
+
+fn print_job_status(job: &Job) {
+    let p = palette();
+    println!(
+        "{} {}",
+        p.heading
+            .apply_to(format!("Job {}: {}", job.id, job.spec.kind())),
+        p.meta.apply_to(format!("({})", job.state.as_str()))
+    );
+    match &job.spec {
+        JobSpec::ExportAll {
+            output,
+            format,
+            detailed,
+        } => {
+            let format = format.to_possible_value().expect("formats are named");
+            let detail = if *detailed { ", detailed" } else { "" };
+            println!(
+                "Output: {} ({}{detail})",
+                output.display(),
+                format.get_name()
+            );
+        }
+        JobSpec::Reindex {
+            index,
+            embed_url,
+            embed_model,
+        } => {
+            println!("Index: {}", index.display());
+            if let Some(url) = embed_url {
+                println!("Embeddings: {embed_model} ({url})");
+            }
+        }
+        JobSpec::Backup { output } => println!("Output: {}", output.display()),
+    }
+    println!("Claude dir: {}", job.claude_dir.display());
+    let total = job.sessions.len();
+    let percent = (job.done * 100).checked_div(total).unwrap_or(100);
+    println!("Progress: {} of {total} session(s) ({percent}%)", job.done);
+    if !job.missing.is_empty() {
+        println!(
+            "{}",
+            p.warn.apply_to(format!(
+                "Missing: {} session(s) no longer on disk",
+                job.missing.len()
+            ))
+        );
+    }
+    println!("Created: {}", job.created);
+    println!("Updated: {}", job.updated);
+    if let Some(pid) = job.pid {
+        println!("Process: {pid}");
+    }
+    if let Some(log) = &job.log {
+        println!("Log: {}", log.display());
+    }
+    if let Some(error) = &job.error {
+        println!("{}", p.warn.apply_to(format!("Error: {error}")));
+    }
+}
+
+/// Start `cc-convo jobs resume` for `job` as a background process that
+/// outlives this one, with its output going to the job's log.
+fn spawn_job(store: &JobStore, job: &mut Job, global: &GlobalArgs) -> Result<()> {
+    let log_path = store.log_path(job.id);
+    let log = File::create(&log_path)
+        .with_context(|| format!("Failed to create {}", log_path.display()))?;
+    job.log = Some(log_path.clone());
+    job.touch();
+    store.save(job)?;
+
+    let mut command = std::process::Command::new(
+        std::env::current_exe().context("Failed to locate the cc-convo executable")?,
+    );
+    command
+        .arg("--claude-dir")
+        .arg(&job.claude_dir)
+        .arg("--jobs-dir")
+        .arg(store.dir())
+        .arg("--stitches-file")
+        .arg(stitches_path(global)?)
+        .arg("--config")
+        .arg(expand_tilde_path(&global.config)?)
+        .arg("--max-depth")
+        .arg(global.max_depth.to_string())
+        .arg("--no-color");
+    if global.one_file_system {
+        command.arg("--one-file-system");
+    }
+    if global.keep_partials {
+        command.arg("--keep-partials");
+    }
+    if let Some(policy) = global.unknown_records.and_then(|u| u.to_possible_value()) {
+        command.arg("--unknown-records").arg(policy.get_name());
+    }
+    if let Some(theme) = global.theme.and_then(|t| t.to_possible_value()) {
+        command.arg("--theme").arg(theme.get_name());
+    }
+    if global.icons {
+        command.arg("--icons");
+    }
+    command
+        .args(["jobs", "resume", &job.id.to_string()])
+        .stdin(std::process::Stdio::null())
+        .stdout(log.try_clone()?)
+        .stderr(log);
+    #[cfg(unix)]
+    {
+        // Its own process group, so Ctrl-C in this terminal doesn't reach it.
+        use std::os::unix::process::CommandExt;
+        command.process_group(0);
+    }
+    let child = command
+        .spawn()
+        .context("Failed to start the background job")?;
+
+    if global.json {
+        return print_json(&json!({
+            "id": job.id,
+            "kind": job.spec.kind(),
+            "pid": child.id(),
+            "log": log_path,
+        }));
+    }
+    println!(
+        "{}",
+        palette().success.apply_to(format!(
+            "Started job {} ({}) in the background.",
+            job.id,
+            job.spec.kind()
+        ))
+    );
+    println!("Log: {}", log_path.display());
+    println!("Follow with: cc-convo jobs status {}", job.id);
+    Ok(())
+}
+
+/// What a running job does with each session.
+enum JobWorker<'a> {
+    Export {
+        output: &'a Path,
+        format: ExportFormat,
+        options: ParseOptions,
+        style: ExportStyle,
+        short_id_counts: HashMap<&'a str, usize>,
+    },
+    Reindex {
+        path: &'a Path,
+        index: VectorIndex,
+        embedder: Box<dyn Embedder>,
+        embedded: usize,
+    },
+    Backup {
+        claude_dir: &'a Path,
+        output: &'a Path,
+        copied: u64,
+    },
+}
+
+impl<'a> JobWorker<'a> {
+    fn new(job: &'a Job, sessions: &'a [Session], global: &GlobalArgs) -> Result<Self> {
+        Ok(match &job.spec {
+            JobSpec::ExportAll {
+                output,
+                format,
+                detailed,
+            } => {
+                fs::create_dir_all(output)
+                    .with_context(|| format!("Failed to create output dir {}", output.display()))?;
+                let mut short_id_counts: HashMap<&str, usize> = HashMap::new();
+                for session in sessions {
+                    *short_id_counts.entry(&session.id_short).or_default() += 1;
+                }
+                Self::Export {
+                    output,
+                    format: *format,
+                    options: global.parse_options(*detailed),
+                    style: global.export_style(),
+                    short_id_counts,
+                }
+            }
+            JobSpec::Reindex {
+                index,
+                embed_url,
+                embed_model,
+            } => {
+                let embedder = EmbedArgs {
+                    embed_url: embed_url.clone(),
+                    embed_model: embed_model.clone(),
+                    embed_api_key: None,
+                }
+                .embedder();
+                Self::Reindex {
+                    path: index,
+                    index: VectorIndex::load(index, &embedder.model_id())?,
+                    embedder,
+                    embedded: 0,
+                }
+            }
+            JobSpec::Backup { output } => Self::Backup {
+                claude_dir: &job.claude_dir,
+                output,
+                copied: 0,
+            },
+        })
+    }
+
+    fn step(&mut self, session: &Session) -> Result<()> {
+        match self {
+            Self::Export {
+                output,
+                format,
+                options,
+                style,
+                short_id_counts,
+            } => {
+                let parsed = parse_session_with(session, options)?;
+                let doc = build_export_document(session, &parsed.events).titled(&parsed.title);
+                let shared_id = short_id_counts
+                    .get(session.id_short.as_str())
+                    .is_some_and(|&n| n > 1);
+                let name = default_export_name(&doc, *format, shared_id);
+                write_single_export(output, &doc, *format, *style, &name)?;
+            }
+            Self::Reindex {
+                index,
+                embedder,
+                embedded,
+                ..
+            } => {
+                *embedded +=
+                    index.update(std::slice::from_ref(session), embedder.as_mut(), |_| {
+                        ControlFlow::Continue(())
+                    })?;
+            }
+            Self::Backup {
+                claude_dir,
+                output,
+                copied,
+            } => *copied += backup_session(claude_dir, session, output)?,
+        }
+        Ok(())
+    }
+
+    /// Make the work done so far durable.
+    fn checkpoint(&mut self) -> Result<()> {
+        if let Self::Reindex {
+            path,
+            index,
+            embedded,
+            ..
+        } = self
+        {
+            if *embedded > 0 {
+                index.save(path)?;
+                *embedded = 0;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Run job `id` from where it stopped until it finishes, fails, is
+/// interrupted, or is cancelled, saving its progress along the way.
+fn run_job(store: &JobStore, id: u64, global: &GlobalArgs) -> Result<()> {
+    let Some(_running) = store.claim(id)? else {
+        bail!("Job {id} is already running");
+    };
+    let mut job = store.load(id)?;
+    if job.is_finished() {
+        bail!("Job {id} is already {}", job.state.as_str());
+    }
+    store.clear_cancel(id)?;
+    install_interrupt_handler()?;
+    let sessions = discover_sessions(&job.claude_dir, &TimeWindow::default(), global)?;
+    job.state = JobState::Running;
+    job.pid = Some(std::process::id());
+    job.error = None;
+    job.touch();
+    store.save(&job)?;
+
+    let started_at = job.done;
+    let snapshot = job.clone();
+    let mut worker = JobWorker::new(&snapshot, &sessions, global)?;
+    let progress = Progress::new(global, "jobs", "files_done", job.remaining().len());
+    let mut result = Ok(());
+    let mut since_checkpoint = 0;
+    while job.done < job.sessions.len() {
+        if interrupted() || store.cancel_requested(id) {
+            break;
+        }
+        let reference = &job.sessions[job.done];
+        match find_session(&sessions, reference) {
+            Ok(session) => {
+                if let Err(err) = worker.step(session) {
+                    result = Err(err.context(format!("Job {id} failed on {reference}")));
+                    break;
+                }
+                progress.inc(&session.id_short);
+            }
+            Err(_) => job.missing.push(reference.clone()),
+        }
+        job.done += 1;
+        since_checkpoint += 1;
+        if since_checkpoint == JOB_CHECKPOINT_EVERY {
+            worker.checkpoint()?;
+            job.touch();
+            store.save(&job)?;
+            since_checkpoint = 0;
+        }
+    }
+    progress.finish();
+    let result = result.and(worker.checkpoint());
+    job.state = if result.is_err() {
+        JobState::Failed
+    } else if job.done == job.sessions.len() {
+        JobState::Done
+    } else if store.cancel_requested(id) {
+        JobState::Cancelled
+    } else {
+        JobState::Interrupted
+    };
+    if let Err(err) = &result {
+        job.error = Some(format!("{err:#}"));
+    }
+    store.clear_cancel(id)?;
+    job.touch();
+    store.save(&job)?;
+    result?;
+
+    if global.json {
+        return print_json(&job);
+    }
+    let message = format!(
+        "Job {id} ({}) {}: {} of {} session(s), {} in this run.",
+        job.spec.kind(),
+        job.state.as_str(),
+        job.done,
+        job.sessions.len(),
+        job.done - started_at
+    );
+    match job.state {
+        JobState::Done => println!("{}", palette().success.apply_to(message)),
+        _ => eprintln!("{}", palette().warn.apply_to(message)),
+    }
+    if !job.missing.is_empty() {
+        eprintln!(
+            "{}",
+            palette().warn.apply_to(format!(
+                "Skipped {} session(s) no longer on disk.",
+                job.missing.len()
+            ))
+        );
+    }
+    if job.state == JobState::Interrupted {
+        eprintln!("Resume with: cc-convo jobs resume {id}");
+    }
+    Ok(())
+}
+
+/// `args` with an alias in the command position replaced by the command line
+/// it names, as git does. Built-in commands win over aliases, and expansions
+/// aren't expanded again.
+fn expand_alias(mut args: Vec<OsString>) -> Result<Vec<OsString>> {
+    let cmd = Cli::command();
+    let Some(at) = command_position(&cmd, &args) else {
+        return Ok(args);
+    };
+    let Some(name) = args[at].to_str().filter(|name| !is_builtin_command(name)) else {
+        return Ok(args);
+    };
+    let config = option_value(&args[..at], "--config").unwrap_or(DEFAULT_CONFIG_PATH);
+    let settings = Config::load(&expand_tilde_path(config)?)?;
+    let Some(expansion) = settings.aliases.get(name) else {
+        return Ok(args);
+    };
+    let words = split_alias(name, expansion)?;
+    args.splice(at..=at, words.into_iter().map(OsString::from));
+    Ok(args)
+}
+
+/// Index of the first argument that isn't a global option or its value.
+fn command_position(cmd: &clap::Command, args: &[OsString]) -> Option<usize> {
+    let mut i = 1;
+    while let Some(arg) = args.get(i) {
+        let arg = arg.to_str()?;
+        if arg == "--" {
+            return None;
+        }
+        if !arg.starts_with('-') || arg == "-" {
+            return Some(i);
+        }
+        // `--opt value` and `-o value` carry their value in the next argument.
+        let takes_value = |found: &clap::Arg| found.get_action().takes_values();
+        let separate_value = match arg.strip_prefix("--") {
+            Some(long) => {
+                !long.contains('=')
+                    && cmd
+                        .get_arguments()
+                        .any(|a| a.get_long() == Some(long) && takes_value(a))
+            }
+            None => {
+                let short = arg.chars().nth(1);
+                arg.len() == 2
+                    && cmd
+                        .get_arguments()
+                        .any(|a| a.get_short() == short && takes_value(a))
+            }
+        };
+        i += 1 + usize::from(separate_value);
+    }
+    None
+}
+
+/// The value of the last `--name value` or `--name=value` in `args`.
+fn option_value<'a>(args: &'a [OsString], name: &str) -> Option<&'a str> {
+    let mut value = None;
+    let mut args = args.iter().filter_map(|arg| arg.to_str());
+    while let Some(arg) = args.next() {
+        if arg == name {
+            value = args.next();
+        } else if let Some(v) = arg
+            .strip_prefix(name)
+            .and_then(|rest| rest.strip_prefix('='))
+        {
+            value = Some(v);
+        }
+    }
+    value
+}
+
+fn is_builtin_command(name: &str) -> bool {
+    let mut cmd = Cli::command();
+    cmd.build();
+    cmd.find_subcommand(name).is_some()
+}
+
+/// The words of an alias's command line, split as a POSIX shell would.
+fn split_alias(name: &str, expansion: &str) -> Result<Vec<String>> {
+    match shlex::split(expansion) {
+        Some(words) if !words.is_empty() => Ok(words),
+        Some(_) => bail!("Alias {name} is empty"),
+        None => bail!("Alias {name} has an unclosed quote or trailing backslash: {expansion}"),
+    }
+}
+
+fn cmd_alias(global: &GlobalArgs, command: AliasCommand) -> Result<()> {
+    let path = expand_tilde_path(&global.config)?;
+    match command {
+        AliasCommand::Set(args) => {
+            if args.name.is_empty()
+                || args.name.starts_with('-')
+                || args.name.contains(char::is_whitespace)
+            {
+                bail!("Invalid alias name {:?}", args.name);
+            }
+            if is_builtin_command(&args.name) {
+                bail!(
+                    "{} is a cc-convo command; pick another alias name",
+                    args.name
+                );
+            }
+            split_alias(&args.name, &args.expansion)?;
+            let previous = config::set_alias(&path, &args.name, Some(&args.expansion))?;
+            if global.json {
+                return print_json(&json!({
+                    "alias": args.name,
+                    "expansion": args.expansion,
+                    "replaced": previous,
+                    "config": path,
+                }));
+            }
+            let verb = if previous.is_some() {
+                "Updated"
+            } else {
+                "Added"
+            };
+            println!(
+                "{}",
+                palette()
+                    .success
+                    .apply_to(format!("{verb} alias {} = {}", args.name, args.expansion))
+            );
+            println!(
+                "{}",
+                palette()
+                    .meta
+                    .apply_to(format!("Saved in {}", path.display()))
+            );
+        }
+        AliasCommand::Remove(args) => {
+            if !global.settings.aliases.contains_key(&args.name) {
+                bail!("No alias named {} in {}", args.name, path.display());
+            }
+            let removed = config::set_alias(&path, &args.name, None)?;
+            if global.json {
+                return print_json(&json!({
+                    "alias": args.name,
+                    "removed": removed,
+                    "config": path,
+                }));
+            }
+            println!("Removed alias {}.", args.name);
+        }
+        AliasCommand::List => {
+            let aliases = &global.settings.aliases;
+            if global.json {
+                let rows = aliases
+                    .iter()
+                    .map(|(name, expansion)| json!({"alias": name, "expansion": expansion}))
+                    .collect::<Vec<_>>();
+                return print_json(&rows);
+            }
+            println!("{}", style("Aliases").bold());
+            if aliases.is_empty() {
+                println!("  {}", tr!("none"));
+            }
+            let width = aliases.keys().map(|name| name.chars().count()).max();
+            for (name, expansion) in aliases {
+                println!("  {name:<width$}  {expansion}", width = width.unwrap_or(0));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Environment variable that switches cc-convo into answering a dynamic
+/// completion request.
+const COMPLETE_ENV_VAR: &str = "CC_CONVO_COMPLETE";
+
+fn cmd_completions(args: CompletionsArgs) -> Result<()> {
+    write_completions(args.shell, args.dynamic, &mut std::io::stdout())
+}
+
+fn write_completions(shell: Shell, dynamic: bool, out: &mut dyn Write) -> Result<()> {
+    let mut cmd = Cli::command();
+    let bin_name = cmd.get_name().to_string();
+    if !dynamic {
+        generate(shell, &mut cmd, bin_name, out);
+        return Ok(());
+    }
+    let shell: &dyn EnvCompleter = match shell {
+        Shell::Bash => &clap_complete::env::Bash,
+        Shell::Elvish => &clap_complete::env::Elvish,
+        Shell::Fish => &clap_complete::env::Fish,
+        Shell::PowerShell => &clap_complete::env::Powershell,
+        Shell::Zsh => &clap_complete::env::Zsh,
+        other => bail!("No dynamic completions for {other}"),
+    };
+    shell
+        .write_registration(COMPLETE_ENV_VAR, &bin_name, &bin_name, &bin_name, out)
+        .context("Failed to write the completion script")
+}
+
+/// Sessions whose records `init` reads to check the transcripts.
+const INIT_PROBE_SESSIONS: usize = 20;
+
+/// What `init` found in the newest transcripts.
+#[derive(Debug, Default, Serialize)]
+struct TranscriptProbe {
+    sessions: usize,
+    records: u64,
+    parse_errors: u64,
+    /// Record types cc-convo doesn't know, with how many records have each.
+    unknown_record_types: BTreeMap<String, u64>,
+}
+
+impl TranscriptProbe {
+    fn read(sessions: &[Session]) -> Result<Self> {
+        let mut probe = Self::default();
+        for session in sessions.iter().take(INIT_PROBE_SESSIONS) {
+            probe.sessions += 1;
+            for path in session.paths() {
+                let file = open_transcript(path)
+                    .with_context(|| format!("Failed to open {}", path.display()))?;
+                for record in jsonl_records(BufReader::new(file)) {
+                    let Ok(value) = record? else {
+                        probe.parse_errors += 1;
+                        continue;
+                    };
+                    probe.records += 1;
+                    let kind = value
+                        .get("type")
+                        .and_then(Value::as_str)
+                        .unwrap_or_default();
+                    if !kind.is_empty() && !KNOWN_RECORD_TYPES.contains(&kind) {
+                        *probe
+                            .unknown_record_types
+                            .entry(kind.to_string())
+                            .or_default() += 1;
+                    }
+                }
+            }
+        }
+        Ok(probe)
+    }
+}
+
+/// Where `init` installs completions for `shell`, and what else the shell
+/// needs to load them; `None` for shells without a per-user directory.
+fn completion_install_path(shell: Shell) -> Result<Option<(PathBuf, Option<&'static str>)>> {
+    let data = match std::env::var("XDG_DATA_HOME") {
+        Ok(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => home_dir()?.join(".local/share"),
+    };
+    let config = match std::env::var("XDG_CONFIG_HOME") {
+        Ok(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => home_dir()?.join(".config"),
+    };
+    Ok(match shell {
+        Shell::Bash => Some((data.join("bash-completion/completions/cc-convo"), None)),
+        Shell::Fish => Some((config.join("fish/completions/cc-convo.fish"), None)),
+        Shell::Zsh => Some((
+            home_dir()?.join(".zfunc/_cc-convo"),
+            Some("Add `fpath+=~/.zfunc` before `compinit` in ~/.zshrc."),
+        )),
+        _ => None,
+    })
+}
+
+/// The search language matching the system locale, when it isn't English.
+fn locale_search_language() -> Option<Language> {
+    let locale = sys_locale::get_locale()?;
+    match locale.get(..2)? {
+        "fr" => Some(Language::French),
+        "de" => Some(Language::German),
+        "es" => Some(Language::Spanish),
+        _ => None,
+    }
+}
+
+/// The config `init` writes, with each setting explained and the ones left
+/// at their defaults commented out.
+fn initial_config(claude_dir: &Path, probe: &TranscriptProbe) -> String {
+    let quoted = |value: &str| toml_edit::Value::from(value).to_string();
+    let setting = |on: bool, line: String| match on {
+        true => line,
+        false => format!("# {line}"),
+    };
+    let language = locale_search_language();
+    let language_name = match language.unwrap_or_default() {
+        Language::English => "english",
+        Language::French => "french",
+        Language::German => "german",
+        Language::Spanish => "spanish",
+    };
+    let lines = [
+        "# cc-convo settings, written by `cc-convo init`. A flag given on the".to_string(),
+        "# command line wins over the same setting here.".to_string(),
+        String::new(),
+        "# Where Claude Code keeps project transcripts.".to_string(),
+        format!("claude_dir = {}", quoted(&claude_dir.to_string_lossy())),
+        String::new(),
+        "# Colors of terminal and HTML output: auto, light, dark, or solarized.".to_string(),
+        "theme = \"auto\"".to_string(),
+        String::new(),
+        "# Mark each message's role with an icon.".to_string(),
+        "icons = false".to_string(),
+        String::new(),
+        "# Records of types cc-convo doesn't know: keep, summarize, or drop.".to_string(),
+        setting(
+            !probe.unknown_record_types.is_empty(),
+            "unknown_records = \"summarize\"".to_string(),
+        ),
+        String::new(),
+        "[search]".to_string(),
+        "# Stopwords and stemming of smart and query searches.".to_string(),
+        setting(
+            language.is_some(),
+            format!("language = \"{language_name}\""),
+        ),
+        "# stem = true".to_string(),
+    ];
+    let mut config = lines.join("\n");
+    config.push('\n');
+    config
+}
+
+fn cmd_init(global: &GlobalArgs, args: InitArgs) -> Result<()> {
+    if global.json && !args.yes && !args.print {
+        bail!("init --json cannot ask questions; pass --yes or --print");
+    }
+    let config_path = expand_tilde_path(&global.config)?;
+    if config_path.exists() && !args.force && !args.print {
+        bail!(
+            "{} already exists; pass --force to replace it",
+            config_path.display()
+        );
+    }
+    let interactive = !args.yes && !global.json && console::user_attended();
+
+    // A --claude-dir on the command line is taken as is.
+    let candidates = match &global.claude_dir {
+        Some(dir) => vec![expand_tilde_path(dir)?],
+        None => claude_dir_candidates(),
+    };
+    let found = candidates
+        .iter()
+        .filter(|dir| dir.is_dir())
+        .collect::<Vec<_>>();
+    let claude_dir = match found.as_slice() {
+        [] => {
+            let dir = candidates.first().cloned().ok_or_else(|| {
+                anyhow!("No home directory to look for Claude Code in; pass --claude-dir")
+            })?;
+            eprintln!(
+                "{}",
+                palette().warn.apply_to(format!(
+                    "No Claude Code transcripts found; using {} until there are some.",
+                    dir.display()
+                ))
+            );
+            dir
+        }
+        [only] => only.to_path_buf(),
+        several if interactive => {
+            let labels = several
+                .iter()
+                .map(|dir| dir.display().to_string())
+                .collect::<Vec<_>>();
+            let picked = dialoguer::Select::new()
+                .with_prompt("Claude Code transcripts were found in several places; use")
+                .items(&labels)
+                .default(0)
+                .interact()
+                .context("Failed to read the choice")?;
+            several[picked].to_path_buf()
+        }
+        several => several[0].to_path_buf(),
+    };
+    let sessions = if claude_dir.is_dir() {
+        discover_with(&claude_dir, &TimeWindow::default(), &walk_limits(global))?.sessions
+    } else {
+        Vec::new()
+    };
+    let projects = sessions
+        .iter()
+        .map(|s| s.project.as_str())
+        .collect::<BTreeSet<_>>()
+        .len();
+    let probe = TranscriptProbe::read(&sessions)?;
+    let config = initial_config(&claude_dir, &probe);
+
+    if args.print {
+        if global.json {
+            return print_json(&json!({
+                "claude_dir": claude_dir,
+                "candidates": candidates,
+                "sessions": sessions.len(),
+                "projects": projects,
+                "probe": probe,
+                "config": config,
+            }));
+        }
+        print!("{config}");
+        return Ok(());
+    }
+    if let Some(dir) = config_path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+    {
+        fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+    write_atomic(&config_path, &config)?;
+
+    let shell = Shell::from_env();
+    let install = match shell {
+        Some(shell) if !args.no_completions => completion_install_path(shell)?,
+        _ => None,
+    };
+    let wanted = match &install {
+        Some((path, _)) if interactive => Confirm::new()
+            .with_prompt(format!("Install shell completions to {}?", path.display()))
+            .default(true)
+            .interact()
+            .context("Failed to read confirmation input")?,
+        Some(_) => args.yes,
+        None => false,
+    };
+    let installed = match (&install, shell) {
+        (Some((path, _)), Some(shell)) if wanted => {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)
+                    .with_context(|| format!("Failed to create {}", dir.display()))?;
+            }
+            let mut script = Vec::new();
+            write_completions(shell, false, &mut script)?;
+            write_atomic(path, script)?;
+            Some(path.clone())
+        }
+        _ => None,
+    };
+
+    if global.json {
+        return print_json(&json!({
+            "config_path": config_path,
+            "claude_dir": claude_dir,
+            "candidates": candidates,
+            "sessions": sessions.len(),
+            "projects": projects,
+            "probe": probe,
+            "completions": installed,
+        }));
+    }
+    println!("{}", style("cc-convo is set up").bold());
+    println!(
+        "  Transcripts: {} ({} session(s) in {projects} project(s))",
+        claude_dir.display(),
+        sessions.len()
+    );
+    let unknown = match probe.unknown_record_types.is_empty() {
+        true => tr!("none"),
+        false => probe
+            .unknown_record_types
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>()
+            .join(", "),
+    };
+    println!(
+        "  Checked the {} newest: {} record(s), {} unreadable line(s), unknown record types: {unknown}",
+        probe.sessions, probe.records, probe.parse_errors
+    );
+    println!("  Config: {}", config_path.display());
+    match (&installed, &install, shell) {
+        (Some(path), Some((_, note)), _) => {
+            println!("  Completions: {}", path.display());
+            if let Some(note) = note {
+                println!("{}", palette().meta.apply_to(format!("  {note}")));
+            }
+        }
+        (None, _, Some(shell)) if !args.no_completions => println!(
+            "{}",
+            palette().meta.apply_to(format!(
+                "  Completions: not installed; see `cc-convo completions {shell}`"
+            ))
+        ),
+        _ => {}
+    }
+    println!(
+        "{}",
+        palette()
+            .success
+            .apply_to("Next: cc-convo sessions list, or cc-convo search \"...\"")
+    );
+    Ok(())
+}
+
+/// Short ids of the sessions under the claude dir, for completing session
+/// targets. The dir comes from a `--claude-dir` on the line being completed,
+/// else the config's `claude_dir`, else [`default_claude_dir`]; time window
+/// options are not applied.
+fn session_candidates() -> Vec<CompletionCandidate> {
+    let args = std::env::args_os().collect::<Vec<_>>();
+    let config = option_value(&args, "--config").unwrap_or(DEFAULT_CONFIG_PATH);
+    let configured = expand_tilde_path(config)
+        .and_then(|path| Config::load(&path))
+        .ok()
+        .and_then(|settings| settings.claude_dir);
+    let claude_dir = option_value(&args, "--claude-dir")
+        .map(str::to_string)
+        .or(configured)
+        .map_or_else(default_claude_dir, |dir| expand_tilde_path(&dir));
+    let Ok(claude_dir) = claude_dir else {
+        return Vec::new();
+    };
+    let sessions =
+        cc_convo_core::discover_sessions(&claude_dir, &TimeWindow::default()).unwrap_or_default();
+    sessions
+        .into_iter()
+        .map(|s| {
+            let date = s.modified_iso.split('T').next().unwrap_or_default();
+            CompletionCandidate::new(&s.id_short).help(Some(format!("{} {date}", s.project).into()))
+        })
+        .collect()
+}
+
+fn cmd_man(args: ManArgs) -> Result<()> {
+    let cmd = Cli::command();
+    match args.out_dir {
+        Some(dir) => {
+            fs::create_dir_all(&dir)
+                .with_context(|| format!("Failed to create {}", dir.display()))?;
+            clap_mangen::generate_to(cmd, &dir)
+                .with_context(|| format!("Failed to write man pages into {}", dir.display()))?;
+            println!("Man pages written to {}", dir.display());
+        }
+        None => clap_mangen::Man::new(cmd)
+            .render(&mut std::io::stdout())
+            .context("Failed to write the man page")?,
+    }
+    Ok(())
+}
+
+fn cmd_sessions_list(
+    claude_dir: &Path,
+    time_window: &TimeWindow,
+    global: &GlobalArgs,
+    args: SessionsListArgs,
+) -> Result<()> {
+    let mut sessions = discover_sessions(claude_dir, time_window, global)?;
+    if let Some(project_filter) = args.project {
+        let project_filter = project_filter.to_lowercase();
+        sessions.retain(|s| {
+            s.project.to_lowercase().contains(&project_filter)
+                || s.path
+                    .to_string_lossy()
+                    .to_lowercase()
+                    .contains(&project_filter)
+        });
+    }
+    let now = Utc::now().timestamp();
+    if args.active {
+        sessions.retain(|s| s.is_active(now, args.active_within));
+    }
+    if let Some(ended_by) = args.ended_by {
+        sessions.retain(|s| session_end(&s.path) == Some(ended_by));
+    }
+    let tags = TagStore::load(&tags_path(global)?)?;
+    if !args.tags.is_empty() {
+        sessions.retain(|s| tags.has_all(&s.id, &args.tags));
+    }
+
+    let sessions = sessions.into_iter().take(args.limit).collect::<Vec<_>>();
+    if args.interactive && global.json {
+        bail!("--interactive can't be combined with --json or --output-format");
+    }
+    if args.preview_chars == Some(0) || args.preview_lines == Some(0) {
+        bail!("--preview-chars and --preview-lines must be > 0");
+    }
+    let preview_chars = args
+        .preview_chars
+        .unwrap_or(PREVIEW_WIDTH)
+        .min(PREVIEW_MAX_WIDTH);
+    let preview_mode = if args.with_preview
+        || args.preview_mode.is_some()
+        || args.preview_chars.is_some()
+        || args.preview_lines.is_some()
+    {
+        Some(args.preview_mode.unwrap_or_default())
+    } else {
+        None
+    };
+    let mut cache = CountsCache::open(global, time_window)?;
+    let mut summaries = Vec::with_capacity(sessions.len());
+    // Rows go out as they're counted when the format allows.
+    let mut rows = output::streams().then(RowWriter::new);
+    for session in sessions {
+        let counts = cache.counts(&session, time_window)?;
+        let mut summary = session_summary(&session, counts, preview_mode);
+        summary.preview = summary.preview.map(|p| ellipsize(&p, preview_chars));
+        summary.active = args.active || session.is_active(now, args.active_within);
+        summary.ended_by = args.ended_by.or_else(|| session_end(&session.path));
+        summary.tags = tags.tags(&session.id);
+        if args.health {
+            summary.health = Some(scan_session_health(&session.path)?);
+        }
+        match &mut rows {
+            Some(rows) => rows.row(&summary)?,
+            None => summaries.push(summary),
+        }
+    }
+    cache.save()?;
+
+    if let Some(rows) = rows {
+        return rows.finish();
+    }
+    if global.json {
+        print_json(&summaries)?;
+        return Ok(());
+    }
+    if args.interactive {
+        return browse_sessions(claude_dir, time_window, global, &summaries);
+    }
+
+    print_sessions_table(
+        &summaries,
+        SessionsTableColumns {
+            preview: preview_mode.is_some(),
+            preview_lines: args.preview_lines.filter(|&n| n > 1),
+            health: args.health,
+        },
+    );
+    Ok(())
+}
+
+/// `sessions list --interactive`: the table to pick from, then the command
+/// for the picked session.
+fn browse_sessions(
+    claude_dir: &Path,
+    time_window: &TimeWindow,
+    global: &GlobalArgs,
+    summaries: &[SessionSummary],
+) -> Result<()> {
+    let term = console::Term::stderr();
+    if !term.is_term() {
+        bail!("--interactive needs a terminal; drop it to print the table");
+    }
+    let columns = [
+        (tr!("col-idx"), 5, true),
+        (tr!("col-modified"), 16, false),
+        (tr!("col-project"), 18, false),
+        (tr!("col-user"), 5, true),
+        (tr!("col-asst"), 5, true),
+        (tr!("col-size-kb"), 8, true),
+        (tr!("col-title"), SESSION_TITLE_WIDTH, false),
+    ]
+    .into_iter()
+    .map(|(title, width, numeric)| browse::Column {
+        title,
+        width,
+        numeric,
+    })
+    .collect();
+    let rows = summaries
+        .iter()
+        .map(|s| {
+            let title = s.title.clone().unwrap_or_else(|| "-".to_string());
+            let modified = s.session.modified_iso.replace('T', " ");
+            let modified = modified.get(..16).unwrap_or(&modified).to_string();
+            browse::Row {
+                cells: vec![
+                    format!("{}{}", s.session.index, if s.active { "*" } else { "" }),
+                    modified.clone(),
+                    s.session.project.clone(),
+                    s.user_messages.to_string(),
+                    s.assistant_messages.to_string(),
+                    format!("{:.1}", s.session.size_bytes as f64 / 1024.0),
+                    title.clone(),
+                ],
+                keys: vec![
+                    browse::SortKey::Number(s.session.index as f64),
+                    browse::SortKey::Text(modified),
+                    browse::SortKey::Text(s.session.project.to_lowercase()),
+                    browse::SortKey::Number(s.user_messages as f64),
+                    browse::SortKey::Number(s.assistant_messages as f64),
+                    browse::SortKey::Number(s.session.size_bytes as f64),
+                    browse::SortKey::Text(title.to_lowercase()),
+                ],
+            }
+        })
+        .collect();
+    // The listing's own order: newest first.
+    let mut view = browse::TableView::new(columns, rows, 1, true);
+    let (row, export) = match view.run(&term)? {
+        browse::Action::Show(row) => (row, false),
+        browse::Action::Export(row) => (row, true),
+        browse::Action::Quit => return Ok(()),
+    };
+    let index = summaries[row].session.index.to_string();
+    let args = if export {
+        vec!["export", "--index", &index]
+    } else {
+        vec!["sessions", "show", &index]
+    };
+    match subcommand(&args)? {
+        Command::Export(args) => cmd_export(claude_dir, time_window, global, args),
+        Command::Sessions {
+            command: SessionsCommand::Show(args),
+        } => cmd_sessions_show(claude_dir, time_window, global, args),
+        _ => unreachable!("parsed from a fixed command line"),
+    }
+}
+
+/// A command's arguments as clap parses them from `args`, so one command
+/// can run another with that command's defaults.
+fn subcommand(args: &[&str]) -> Result<Command> {
+    let cli = Cli::try_parse_from(std::iter::once("cc-convo").chain(args.iter().copied()))?;
+    Ok(cli.command)
+}
+
+fn cmd_sessions_show(
+    claude_dir: &Path,
+    time_window: &TimeWindow,
+    global: &GlobalArgs,
+    args: SessionsShowArgs,
+) -> Result<()> {
+    let sessions = discover_sessions(claude_dir, time_window, global)?;
+    let session = match resolve_session_target(&sessions, &args.target, args.nth) {
+        Ok(session) => session,
+        // Neither an index, an id, nor a project: try it as part of a title.
+        Err(err) if args.nth.is_none() && args.target.parse::<usize>().is_err() => {
+            let mut cache = CountsCache::open(global, time_window)?;
+            let found = find_by_title(&sessions, &args.target, |session| {
+                let counts = cache.counts(session, time_window)?;
+                Ok(counts.title.title().map(str::to_string))
+            })?;
+            cache.save()?;
+            found.ok_or(err)?
+        }
+        Err(err) => return Err(err),
+    };
+    let redactor = args.redact.redactor()?;
+
+    if args.raw {
+        let mut records = read_raw_lines(&session.path)?;
+        let mut redactions = 0;
+        if let Some(redactor) = &redactor {
+            for record in &mut records {
+                let (masked, count) = redactor.redact(record);
+                *record = masked;
+                redactions += count;
+            }
+        }
+        if global.json {
+            let mut out = json!({
+                "session": session,
+                "records": records,
+            });
+            if redactor.is_some() {
+                out["redactions"] = json!(redactions);
+            }
+            print_json(&out)?;
+        } else {
+            println!(
+                "{}",
+                palette()
+                    .heading
+                    .apply_to(format!("Session {}", session.id))
+            );
+            for record in records {
+                println!("{record}");
+            }
+            report_redactions(redactor.is_some(), redactions);
+        }
+        return Ok(());
+    }
+
+    let mut parsed = parse_session_with(session, &global.parse_options(args.detailed))?;
+    time_window.retain_events(&mut parsed.events);
+    let redactions = redactor
+        .as_ref()
+        .map_or(0, |r| r.redact_events(&mut parsed.events));
+    let events = if let Some(max) = args.max_lines {
+        parsed.events.into_iter().take(max).collect::<Vec<_>>()
+    } else {
+        parsed.events
+    };
+
+    let tree = args.tree.then(|| ConversationTree::build(&events));
+    let ops = if args.ops {
+        Some(ApiErrorScan::read(session, time_window)?)
+    } else {
+        None
+    };
+
+    if global.json {
+        let mut out = json!({
+            "session": session,
+            "parse_errors": parsed.parse_errors,
+            "parse_error_details": parsed.diagnostics,
+            "events": events,
+        });
+        if let Some(tree) = &tree {
+            out["tree"] = json!(tree);
+        }
+        if let Some(ops) = &ops {
+            out["api_errors"] = ops.to_json();
+        }
+        if redactor.is_some() {
+            out["redactions"] = json!(redactions);
+        }
+        print_json(&out)?;
+        return Ok(());
+    }
+
+    println!(
+        "{}",
+        palette()
+            .heading
+            .apply_to(format!("Session {}", session.id))
+    );
+    println!("Project: {}", session.project);
+    println!("Modified: {}", session.modified_iso);
+    println!("Path: {}", session.path.display());
+    for path in &session.stitched {
+        println!("Stitched: {}", path.display());
+    }
+    if let Some(tree) = &tree {
+        println!("Branch points: {}", tree.branch_points());
+    }
+    println!();
+    let order = match &tree {
+        Some(tree) => tree.walk(),
+        None => (0..events.len()).collect(),
+    };
+    let renderer = args
+        .render
+        .then(|| markdown::Renderer::new(global.theme(), console::colors_enabled()));
+    for i in order {
+        let event = &events[i];
+        let node = tree.as_ref().map(|t| &t.nodes[i]);
+        let indent = "  ".repeat(node.map_or(0, |n| n.depth));
+        if let Some(branch) = node.and_then(|n| n.branch) {
+            let label = format!("{indent}-- branch {} of {} --", branch.index, branch.of);
+            println!("{}", palette().highlight.apply_to(label));
+        }
+        let ts = event.timestamp.as_deref().unwrap_or("-");
+        let Some(renderer) = &renderer else {
+            println!(
+                "{indent}{} {} {}",
+                palette().meta.apply_to(ts),
+                palette().role_label(&event.role),
+                event.content
+            );
+            continue;
+        };
+        // The rendered body goes under its header, where block layout holds.
+        println!(
+            "{indent}{} {}",
+            palette().meta.apply_to(ts),
+            palette().role_label(&event.role)
+        );
+        for line in renderer.render(&event.content, indent.len() + 2).lines() {
+            println!("{indent}  {line}");
+        }
+        println!();
+    }
+    if let Some(ops) = &ops {
+        println!();
+        ops.print();
+    }
+    report_parse_errors(
+        parsed.parse_errors,
+        &parsed.diagnostics,
+        args.show_parse_errors,
+    );
+    report_redactions(redactor.is_some(), redactions);
+    Ok(())
+}
+
+/// Note on stderr how many secrets `--redact` masked.
+fn report_redactions(enabled: bool, count: usize) {
+    if enabled {
+        eprintln!(
+            "{}",
+            palette()
+                .warn
+                .apply_to(tr!("redacted-secrets", count = count))
+        );
+    }
+}
+
+fn cmd_sessions_tag(
+    claude_dir: &Path,
+    time_window: &TimeWindow,
+    global: &GlobalArgs,
+    command: TagCommand,
+) -> Result<()> {
+    let path = tags_path(global)?;
+    let sessions = discover_sessions(claude_dir, time_window, global)?;
+    match command {
+        TagCommand::Add(args) => {
+            let session = resolve_session_target(&sessions, &args.target, None)?;
+            let added = TagStore::update(&path, report_lock_wait, |store| {
+                store.add(&session.id, &args.tags)
+            })?;
+            report_tag_edit(global, session, ("added", "Tagged"), &added)
+        }
+        TagCommand::Remove(args) => {
+            let session = resolve_session_target(&sessions, &args.target, None)?;
+            let removed = TagStore::update(&path, report_lock_wait, |store| {
+                Ok(store.remove(&session.id, &args.tags))
+            })?;
+            report_tag_edit(global, session, ("removed", "Untagged"), &removed)
+        }
+        TagCommand::List(args) => {
+            let store = TagStore::load(&path)?;
+            if let Some(target) = args.target {
+                let session = resolve_session_target(&sessions, &target, None)?;
+                let tags = store.tags(&session.id);
+                if global.json {
+                    return print_json(&json!({ "session_id": session.id, "tags": tags }));
+                }
+                if tags.is_empty() {
+                    println!("Session {} has no tags.", session.id);
+                } else {
+                    println!("{}", tags.join(" "));
+                }
+                return Ok(());
+            }
+            let counts = store.counts();
+            if global.json {
+                return print_json(&counts);
+            }
+            if counts.is_empty() {
+                println!("No tags yet. Add one with `cc-convo sessions tag add <session> <tag>`.");
+            }
+            for (tag, count) in counts {
+                println!("  {count:>7}  {tag}");
+            }
+            Ok(())
+        }
+    }
+}
+
+/// `key` names the changed tags in JSON; `label` heads the text line.
+fn report_tag_edit(
+    global: &GlobalArgs,
+    session: &Session,
+    (key, label): (&str, &str),
+    tags: &[String],
+) -> Result<()> {
+    if global.json {
+        return print_json(&json!({ "session_id": session.id, key: tags }));
+    }
+    if tags.is_empty() {
+        println!("No tags changed on {}.", session.id);
+    } else {
+        println!("{label} {}: {}", session.id, tags.join(" "));
+    }
+    Ok(())
+}
+
+fn tags_path(global: &GlobalArgs) -> Result<PathBuf> {
+    expand_tilde_path(&global.tags_file)
+}
+
+fn cmd_export(
+    claude_dir: &Path,
+    time_window: &TimeWindow,
+    global: &GlobalArgs,
+    mut args: ExportArgs,
+) -> Result<()> {
+    if args.resume {
+        read_export_checkpoint(&args.output)?.apply_to(&mut args)?;
+    }
+    if let Some(fraction) = args.validation_split {
+        if !args.format.is_dataset() {
+            bail!("--validation-split needs --format openai-jsonl or anthropic-messages");
+        }
+        if !(fraction > 0.0 && fraction < 1.0) {
+            bail!("--validation-split must be between 0 and 1");
+        }
+    }
+    // Splitting needs every example before either file is written.
+    let bundle = args.single_file || args.validation_split.is_some();
+    let obsidian = matches!(args.format, ExportFormat::Obsidian);
+    if obsidian && bundle {
+        bail!("--format obsidian writes a note per session; drop --single-file");
+    }
+    if args.dedupe_content && !matches!(args.format, ExportFormat::Markdown | ExportFormat::Html) {
+        bail!("--dedupe-content needs --format markdown or html");
+    }
+    if obsidian && args.incremental {
+        bail!("--format obsidian rewrites the whole vault; drop --incremental");
+    }
+    let sessions = discover_sessions(claude_dir, time_window, global)?;
+    // Hold the output dir lock from reading the checkpoint until it is rewritten,
+    // so two resumes of the same export cannot both claim the remaining sessions.
+    // Incremental runs take it before loading their state for the same reason.
+    let mut output_lock = if args.resume && args.output.is_dir() {
+        Some(FileLock::acquire(
+            &args.output.join(EXPORT_LOCK_FILE),
+            report_lock_wait,
+        )?)
+    } else {
+        None
+    };
+    let mut selected = if args.resume {
+        resume_export_selection(&sessions, &args.output, bundle)?
+    } else if args.interactive {
+        match pick_sessions(&sessions, time_window)? {
+            Some(picked) => picked,
+            None => {
+                println!("{}", tr!("cancelled"));
+                return Ok(());
+            }
+        }
+    } else {
+        select_sessions(&sessions, &args.selection, time_window, global)?
+    };
+
+    if selected.is_empty() {
+        bail!("No sessions selected for export.");
+    }
+    let slice_window = export_slice_window(&args)?;
+    if (args.messages.is_some() || !slice_window.is_open()) && selected.len() > 1 {
+        bail!(
+            "--messages, --from-timestamp, and --to-timestamp slice a single session; {} are selected",
+            selected.len()
+        );
+    }
+    args.order.sort(&mut selected);
+    let mut state = if args.incremental {
+        fs::create_dir_all(&args.output)
+            .with_context(|| format!("Failed to create output dir {}", args.output.display()))?;
+        if output_lock.is_none() {
+            output_lock = Some(FileLock::acquire(
+                &args.output.join(EXPORT_LOCK_FILE),
+                report_lock_wait,
+            )?);
+        }
+        Some(ExportState::load(&args.output, ExportSettings::of(&args))?)
+    } else {
+        None
+    };
+    let mut unchanged = 0usize;
+    if let Some(state) = &state {
+        let before = selected.len();
+        selected.retain(|session| !state.is_current(session));
+        unchanged = before - selected.len();
+    }
+    let bundle_style = ExportStyle {
+        group_by_project: args.group_by == Some(BundleGroup::Project),
+        ..global.export_style()
+    };
+
+    if args.selection.all && !args.yes && !global.json && !selected.is_empty() {
+        let proceed = Confirm::new()
+            .with_prompt(format!("Export all {} sessions?", selected.len()))
+            .default(false)
+            .interact()
+            .context("Failed to read confirmation input")?;
+        if !proceed {
+            println!("{}", tr!("cancelled"));
+            return Ok(());
+        }
+    }
+
+    let encryption = export_encryption(&args.encrypt)?;
+    // Files are written in plaintext to a private staging dir and only their
+    // encrypted copies go to --output.
+    let staging = encryption
+        .as_ref()
+        .map(|_| StagingDir::new("cc-convo-export"))
+        .transpose()?;
+    fs::create_dir_all(&args.output)
+        .with_context(|| format!("Failed to create output dir {}", args.output.display()))?;
+    // The lock stays on --output even while files are staged, so encrypted and
+    // plain exports into one directory still take turns.
+    if output_lock.is_none() {
+        output_lock = Some(FileLock::acquire(
+            &args.output.join(EXPORT_LOCK_FILE),
+            report_lock_wait,
+        )?);
+    }
+    let output = args.output.clone();
+    if let Some(staging) = &staging {
+        args.output = staging.path().to_path_buf();
+    }
+
+    let redactor = args.redact.redactor()?;
+    install_interrupt_handler()?;
+    let progress = Progress::new(global, "export", "files_done", selected.len());
+
+    let mut short_id_counts: HashMap<&str, usize> = HashMap::new();
+    for session in &sessions {
+        *short_id_counts.entry(&session.id_short).or_default() += 1;
+    }
+
+    let mut output_files = Vec::new();
+    let mut written_names = HashSet::new();
+    let mut bundled_docs = Vec::new();
+    // Vault notes link to each other, so they are written together at the end.
+    let mut vault_notes = Vec::new();
+    let tag_store = if obsidian {
+        Some(TagStore::load(&tags_path(global)?)?)
+    } else {
+        None
+    };
+    let mut total_parse_errors = 0u64;
+    let mut parse_diagnostics = Vec::new();
+    let mut redactions = 0usize;
+    let mut assets_written = 0usize;
+    let mut exported = 0usize;
+    // What earlier runs of a resumed export wrote; their files stay on its
+    // index page.
+    let previous = if args.resume {
+        Some(read_export_checkpoint(&args.output)?)
+    } else {
+        None
+    };
+    let mut index_entries = previous
+        .as_ref()
+        .map(|previous| previous.index.clone())
+        .unwrap_or_default();
+    // So do the files of earlier incremental exports not written again.
+    if let (Some(state), false) = (&state, args.no_index) {
+        index_entries.extend(state.index_entries(&selected));
+    }
+
+    for session in &selected {
+        if interrupted() {
+            break;
+        }
+        // Stat before reading, so a session that grows mid-export is exported again.
+        let stamps = session_stamps(session);
+        let options = ParseOptions {
+            source_refs: args.source_refs,
+            assets: args.assets,
+            ..global.parse_options(args.detailed)
+        };
+        let mut parsed = parse_session_with(session, &options)?;
+        time_window.retain_events(&mut parsed.events);
+        if let Some(range) = &args.messages {
+            range.retain(&mut parsed.events)?;
+        }
+        slice_window.retain_events(&mut parsed.events);
+        if let Some(redactor) = &redactor {
+            redactions += redactor.redact_events(&mut parsed.events);
+            redactor.redact_title(&mut parsed.title);
+        }
+        if args.fold_pastes {
+            for event in &mut parsed.events {
+                if event.role == "user" && !event.content.starts_with("[tool_result] ") {
+                    event.content = fold_pastes(&event.content);
+                }
+            }
+        }
+        if args.drop_tools || args.drop_thinking {
+            for event in &mut parsed.events {
+                event.content =
+                    drop_content_blocks(&event.content, args.drop_tools, args.drop_thinking);
+            }
+            parsed.events.retain(|e| !e.content.trim().is_empty());
+        }
+        total_parse_errors += parsed.parse_errors;
+        parse_diagnostics.extend(parsed.diagnostics);
+        let mut doc = build_export_document(session, &parsed.events).titled(&parsed.title);
+        if args.tree {
+            doc = doc.threaded();
+        }
+        if bundle {
+            assets_written += write_assets(&args.output, &parsed.assets)?.len();
+            bundled_docs.push(doc);
+        } else {
+            let name = match &args.name_template {
+                Some(template) => template.render(&doc, session.index, args.format),
+                None => {
+                    let shared_id = short_id_counts
+                        .get(session.id_short.as_str())
+                        .is_some_and(|&n| n > 1);
+                    default_export_name(&doc, args.format, shared_id)
+                }
+            };
+            if !written_names.insert(name.clone()) {
+                bail!(
+                    "--name-template gives {} for more than one session; add {{short_id}} or {{index}}",
+                    name.display()
+                );
+            }
+            if let Some(dir) = args.output.join(&name).parent() {
+                assets_written += write_assets(dir, &parsed.assets)?.len();
+            }
+            if let Some(tag_store) = &tag_store {
+                let mut note = VaultNote::new(doc, name);
+                note.tags = tag_store.tags(&session.id);
+                note.models = summarize_session(session, None, time_window)?.models;
+                vault_notes.push(note);
+            } else {
+                let path = write_single_export(
+                    &args.output,
+                    &doc,
+                    args.format,
+                    global.export_style(),
+                    &name,
+                )?;
+                output_files.push(path);
+                let entry = IndexEntry::new(&doc, name, &parsed.title);
+                if !args.no_index {
+                    index_entries.push(entry.clone());
+                }
+                if let Some(state) = &mut state {
+                    // The state file stays in plaintext, so it only keeps titles
+                    // of unencrypted exports.
+                    state.record(session, stamps, encryption.is_none().then_some(entry));
+                }
+            }
+        }
+        exported += 1;
+        progress.inc(&session.id_short);
+    }
+    progress.finish();
+
+    if !vault_notes.is_empty() {
+        output_files.extend(write_obsidian_vault(
+            &args.output,
+            &vault_notes,
+            global.export_style(),
+        )?);
+    }
+    let deduped = if args.dedupe_content {
+        dedupe_content(&mut bundled_docs, SHARED_BLOCK_MIN_BYTES)
+    } else {
+        Deduped::default()
+    };
+    if !bundled_docs.is_empty() {
+        match args.validation_split {
+            Some(fraction) => output_files.extend(write_dataset_split(
+                &args.output,
+                &bundled_docs,
+                args.format,
+                fraction,
+            )?),
+            None => output_files.push(write_bundle_export(
+                &args.output,
+                &bundled_docs,
+                &deduped.blocks,
+                args.format,
+                bundle_style,
+            )?),
+        }
+    }
+    // A resumed bundle is written again whole, so its partial files from
+    // earlier runs are replaced; their sessions are in this run's selection.
+    let (mut earlier_completed, mut earlier_files) = previous
+        .map(|previous| (previous.completed, previous.output_files))
+        .unwrap_or_default();
+    if bundle {
+        earlier_completed.clear();
+        if !bundled_docs.is_empty() {
+            for stale in earlier_files.drain(..) {
+                if !output_files.contains(&stale) && stale.exists() {
+                    fs::remove_file(&stale)
+                        .with_context(|| format!("Failed to remove {}", stale.display()))?;
+                }
+            }
+        }
+    }
+
+    // An encrypted export leaves nothing to resume from.
+    let checkpoint = if exported < selected.len() && staging.is_none() {
+        let mut seen_files = HashSet::new();
+        let checkpoint = ExportCheckpoint {
+            interrupted_at: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+            settings: ExportSettings::of(&args),
+            redact_patterns: args.redact.patterns.clone(),
+            single_file: args.single_file,
+            validation_split: args.validation_split,
+            order: args.order,
+            group_by: args.group_by,
+            dedupe_content: args.dedupe_content,
+            completed: earlier_completed
+                .into_iter()
+                .chain(selected[..exported].iter().map(Session::qualified_id))
+                .collect(),
+            remaining: selected[exported..]
+                .iter()
+                .map(Session::qualified_id)
+                .collect(),
+            output_files: earlier_files
+                .into_iter()
+                .chain(output_files.iter().cloned())
+                .filter(|file| seen_files.insert(file.clone()))
+                .collect(),
+            index: index_entries.clone(),
+        };
+        Some(write_export_checkpoint(&args.output, &checkpoint)?)
+    } else {
+        let stale = args.output.join(EXPORT_CHECKPOINT_FILE);
+        if stale.exists() {
+            fs::remove_file(&stale)
+                .with_context(|| format!("Failed to remove {}", stale.display()))?;
+        }
+        None
+    };
+    let mut index = if index_entries.len() > 1 {
+        Some(write_export_index(
+            &args.output,
+            &index_entries,
+            args.format,
+            global.export_style(),
+        )?)
+    } else {
+        None
+    };
+
+    if let (Some(encryption), Some(staging)) = (&encryption, &staging) {
+        let encrypted = encryption.encrypt_tree(staging.path(), &output, |_| false)?;
+        let moved = |path: &PathBuf| {
+            encrypted
+                .iter()
+                .find(|(plain, _)| plain == path)
+                .map_or_else(|| path.clone(), |(_, sealed)| sealed.clone())
+        };
+        output_files = output_files.iter().map(moved).collect();
+        index = index.as_ref().map(moved);
+        args.output = output;
+    }
+    let state_file = state.map(|state| state.write(&args.output)).transpose()?;
+    drop(output_lock);
+
+    if global.json {
+        let mut report = json!({
+            "exported_sessions": exported,
+            "output_files": output_files,
+            "parse_errors": total_parse_errors,
+            "parse_error_details": parse_diagnostics,
+            "format": args.format,
+            "detailed": args.detailed,
+            "single_file": args.single_file
+        });
+        if redactor.is_some() {
+            report["redactions"] = json!(redactions);
+        }
+        if args.assets {
+            report["assets_written"] = json!(assets_written);
+        }
+        if let Some(path) = &index {
+            report["index"] = json!(path);
+        }
+        if encryption.is_some() {
+            report["encrypted"] = json!(true);
+        }
+        if args.dedupe_content {
+            report["shared_blocks"] = json!(deduped.blocks.len());
+            report["deduped_bytes"] = json!(deduped.saved_bytes);
+        }
+        if let Some(path) = &state_file {
+            report["unchanged_sessions"] = json!(unchanged);
+            report["state_file"] = json!(path);
+        }
+        if let Some(path) = &checkpoint {
+            report["interrupted"] = json!(true);
+            report["remaining_sessions"] = json!(selected.len() - exported);
+            report["checkpoint"] = json!(path);
+        }
+        print_json(&report)?;
+        return Ok(());
+    }
+
+    println!(
+        "{}",
+        palette()
+            .success
+            .apply_to(format!("Exported {} session(s).", exported))
+    );
+    if state_file.is_some() {
+        println!("Skipped {unchanged} unchanged session(s).");
+    }
+    println!("Output:");
+    for p in &output_files {
+        println!("  {}", p.display());
+    }
+    if let Some(path) = &index {
+        println!("Index: {}", path.display());
+    }
+    if args.dedupe_content {
+        println!(
+            "Shared blocks: {} ({} saved)",
+            deduped.blocks.len(),
+            format_size(deduped.saved_bytes as u64)
+        );
+    }
+    if args.assets {
+        println!("Assets written: {assets_written}");
+    }
+    report_parse_errors(
+        total_parse_errors,
+        &parse_diagnostics,
+        args.show_parse_errors,
+    );
+    report_redactions(redactor.is_some(), redactions);
+    if let Some(path) = checkpoint {
+        eprintln!(
+            "{}",
+            palette().warn.apply_to(format!(
+                "Interrupted after {exported} of {} session(s); checkpoint written to {}.",
+                selected.len(),
+                path.display()
+            ))
+        );
+        eprintln!(
+            "Resume with: cc-convo export --resume --output {}",
+            args.output.display()
+        );
+    }
+    Ok(())
+}
+
+/// Written into the export directory when an export is interrupted.
+const EXPORT_CHECKPOINT_FILE: &str = ".cc-convo-export-checkpoint.json";
+
+/// Written into the export directory by `export --incremental`.
+const EXPORT_STATE_FILE: &str = ".cc-convo-export-state.json";
+
+/// What an incremental export into a directory has written so far.
+#[derive(Debug, Serialize, Deserialize)]
+struct ExportState {
+    settings: ExportSettings,
+    /// Keyed by qualified session id.
+    sessions: BTreeMap<String, ExportedSession>,
+}
+
+/// The export options that change what a session's file holds; exporting
+/// with different ones writes every session again.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct ExportSettings {
+    format: ExportFormat,
+    detailed: bool,
+    #[serde(default)]
+    tree: bool,
+    #[serde(default)]
+    redact: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    name_template: Option<String>,
+    #[serde(default)]
+    fold_pastes: bool,
+    #[serde(default)]
+    source_refs: bool,
+    #[serde(default)]
+    assets: bool,
+    #[serde(default)]
+    drop_tools: bool,
+    #[serde(default)]
+    drop_thinking: bool,
+}
+
+impl ExportSettings {
+    fn of(args: &ExportArgs) -> Self {
+        Self {
+            format: args.format,
+            detailed: args.detailed,
+            tree: args.tree,
+            redact: args.redact.redact || !args.redact.patterns.is_empty(),
+            name_template: args.name_template.as_ref().map(ToString::to_string),
+            fold_pastes: args.fold_pastes,
+            source_refs: args.source_refs,
+            assets: args.assets,
+            drop_tools: args.drop_tools,
+            drop_thinking: args.drop_thinking,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ExportedSession {
+    /// Path, size, and mtime of each of the session's files when exported.
+    files: Vec<FileStamp>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    index: Option<IndexEntry>,
+}
+
+impl ExportState {
+    /// The state in `output_dir`, or an empty one if there is none yet or it
+    /// was written with other `settings`.
+    fn load(output_dir: &Path, settings: ExportSettings) -> Result<Self> {
+        let path = output_dir.join(EXPORT_STATE_FILE);
+        let raw = match fs::read_to_string(&path) {
+            Ok(raw) => raw,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(Self {
+                    settings,
+                    sessions: BTreeMap::new(),
+                })
+            }
+            Err(err) => {
+                return Err(err).with_context(|| format!("Failed to read {}", path.display()))
+            }
+        };
+        let state: Self = serde_json::from_str(&raw)
+            .with_context(|| format!("Invalid export state {}", path.display()))?;
+        if state.settings == settings {
+            return Ok(state);
+        }
+        eprintln!(
+            "{}",
+            palette()
+                .warn
+                .apply_to("Export options changed since the last incremental export; exporting every session again.")
+        );
+        Ok(Self {
+            settings,
+            sessions: BTreeMap::new(),
+        })
+    }
+
+    /// Whether `session`'s files are as they were when it was last exported.
+    fn is_current(&self, session: &Session) -> bool {
+        self.sessions
+            .get(&session.qualified_id())
+            .is_some_and(|exported| exported.files == session_stamps(session))
+    }
+
+    /// Index entries of the exported sessions that aren't in `exporting`.
+    fn index_entries(&self, exporting: &[Session]) -> Vec<IndexEntry> {
+        let exporting = exporting
+            .iter()
+            .map(Session::qualified_id)
+            .collect::<HashSet<_>>();
+        self.sessions
+            .iter()
+            .filter(|(id, _)| !exporting.contains(*id))
+            .filter_map(|(_, exported)| exported.index.clone())
+            .collect()
+    }
+
+    fn record(&mut self, session: &Session, files: Vec<FileStamp>, index: Option<IndexEntry>) {
+        self.sessions
+            .insert(session.qualified_id(), ExportedSession { files, index });
+    }
+
+    fn write(&self, output_dir: &Path) -> Result<PathBuf> {
+        let path = output_dir.join(EXPORT_STATE_FILE);
+        write_atomic(&path, serde_json::to_string_pretty(self)?)?;
+        Ok(path)
+    }
+}
+
+/// Stamps of every file of `session` that can be stat'ed.
+fn session_stamps(session: &Session) -> Vec<FileStamp> {
+    session.paths().filter_map(FileStamp::of).collect()
+}
+
+/// Serializes concurrent exports into the same directory.
+const EXPORT_LOCK_FILE: &str = ".cc-convo-export.lock";
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ExportCheckpoint {
+    interrupted_at: String,
+    #[serde(flatten)]
+    settings: ExportSettings,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    redact_patterns: Vec<String>,
+    single_file: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    validation_split: Option<f64>,
+    #[serde(default)]
+    order: BundleOrder,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    group_by: Option<BundleGroup>,
+    #[serde(default)]
+    dedupe_content: bool,
+    completed: Vec<String>,
+    remaining: Vec<String>,
+    output_files: Vec<PathBuf>,
+    /// Index page entries of the files already written.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    index: Vec<IndexEntry>,
+}
+
+impl ExportCheckpoint {
+    /// Set `args` to the settings of the interrupted export, so the rest of
+    /// it is written the same way. Flags on the resume line may repeat those
+    /// settings but not change them.
+    fn apply_to(&self, args: &mut ExportArgs) -> Result<()> {
+        let settings = &self.settings;
+        let name_template = settings
+            .name_template
+            .as_deref()
+            .map(str::parse::<NameTemplate>)
+            .transpose()
+            .context("Invalid --name-template in the export checkpoint")?;
+        let differs = (args.format != ExportFormat::Markdown && args.format != settings.format)
+            || (args.detailed && !settings.detailed)
+            || (args.tree && !settings.tree)
+            || (args.redact.redact && !settings.redact)
+            || (!args.redact.patterns.is_empty() && args.redact.patterns != self.redact_patterns)
+            || (args.name_template.is_some() && args.name_template != name_template)
+            || (args.fold_pastes && !settings.fold_pastes)
+            || (args.source_refs && !settings.source_refs)
+            || (args.assets && !settings.assets)
+            || (args.drop_tools && !settings.drop_tools)
+            || (args.drop_thinking && !settings.drop_thinking)
+            || (args.single_file && !self.single_file)
+            || (args.validation_split.is_some() && args.validation_split != self.validation_split)
+            || (args.order != BundleOrder::default() && args.order != self.order)
+            || (args.group_by.is_some() && args.group_by != self.group_by)
+            || (args.dedupe_content && !self.dedupe_content);
+        if differs {
+            bail!(
+                "--resume continues the interrupted export with its own settings; drop the flags that change them"
+            );
+        }
+        args.format = settings.format;
+        args.detailed = settings.detailed;
+        args.tree = settings.tree;
+        args.redact.redact = settings.redact;
+        args.redact.patterns = self.redact_patterns.clone();
+        args.name_template = name_template;
+        args.fold_pastes = settings.fold_pastes;
+        args.source_refs = settings.source_refs;
+        args.assets = settings.assets;
+        args.drop_tools = settings.drop_tools;
+        args.drop_thinking = settings.drop_thinking;
+        args.single_file = self.single_file;
+        args.validation_split = self.validation_split;
+        args.order = self.order;
+        args.group_by = self.group_by;
+        args.dedupe_content = self.dedupe_content;
+        Ok(())
+    }
+}
+
+fn write_export_checkpoint(output_dir: &Path, checkpoint: &ExportCheckpoint) -> Result<PathBuf> {
+    let path = output_dir.join(EXPORT_CHECKPOINT_FILE);
+    write_atomic(&path, serde_json::to_string_pretty(checkpoint)?)?;
+    Ok(path)
+}
+
+fn read_export_checkpoint(output_dir: &Path) -> Result<ExportCheckpoint> {
+    let path = output_dir.join(EXPORT_CHECKPOINT_FILE);
+    let raw = fs::read_to_string(&path)
+        .with_context(|| format!("No export checkpoint at {}", path.display()))?;
+    serde_json::from_str(&raw)
+        .with_context(|| format!("Invalid export checkpoint {}", path.display()))
+}
+
+/// The sessions an interrupted export in `output_dir` had not written yet.
+/// A `bundle` is one file written again on resume, so it takes the sessions
+/// already written too, in their place before the rest.
+fn resume_export_selection(
+    sessions: &[Session],
+    output_dir: &Path,
+    bundle: bool,
+) -> Result<Vec<Session>> {
+    let checkpoint = read_export_checkpoint(output_dir)?;
+    let completed = match bundle {
+        true => checkpoint.completed.as_slice(),
+        false => &[],
+    };
+    let mut selected = Vec::with_capacity(completed.len() + checkpoint.remaining.len());
+    for id in completed.iter().chain(&checkpoint.remaining) {
+        match find_session(sessions, id) {
+            Ok(s) => selected.push(s.clone()),
+            Err(err) => eprintln!(
+                "{}",
+                palette()
+                    .warn
+                    .apply_to(format!("Skipping checkpointed session: {err}"))
+            ),
+        }
+    }
+    Ok(selected)
+}
+
+/// Bring the vector index up to date with `sessions`, then rank its chunks.
+fn search_semantic(
+    sessions: &[Session],
+    args: &SearchArgs,
+    options: &SearchOptions,
+    after_file: impl FnMut(&Session) -> ControlFlow<()>,
+) -> Result<Vec<SearchHit>> {
+    let mut embedder = args.embed.embedder();
+    let index_path = expand_tilde_path(&args.index)?;
+    let mut index = VectorIndex::load(&index_path, &embedder.model_id())?;
+    if index.update(sessions, embedder.as_mut(), after_file)? > 0 {
+        index.save(&index_path)?;
+    }
+    index.search(sessions, options, embedder.as_mut())
+}
+
+/// Embeddings from an OpenAI-compatible `/v1/embeddings` endpoint.
+struct ApiEmbedder {
+    url: String,
+    model: String,
+    api_key: Option<String>,
+}
+
+impl Embedder for ApiEmbedder {
+    fn model_id(&self) -> String {
+        format!("{} ({})", self.model, self.url)
+    }
+
+    fn embed(&mut self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+        let body = json!({ "model": self.model, "input": texts });
+        let mut request = ureq::post(&self.url).set("content-type", "application/json");
+        if let Some(key) = &self.api_key {
+            request = request.set("authorization", &format!("Bearer {key}"));
+        }
+        let response = match request.send_string(&body.to_string()) {
+            Ok(r) => r,
+            Err(ureq::Error::Status(code, r)) => {
+                let detail = r.into_string().unwrap_or_default();
+                bail!(
+                    "Embeddings API returned HTTP {code}: {}",
+                    ellipsize(&detail, 300)
+                );
+            }
+            Err(err) => return Err(anyhow!(err).context("Embeddings request failed")),
+        };
+        let payload: Value = serde_json::from_str(&response.into_string()?)
+            .context("Embeddings response was not valid JSON")?;
+        let mut data = payload
+            .get("data")
+            .and_then(Value::as_array)
+            .context("Embeddings response has no data array")?
+            .iter()
+            .map(|item| {
+                let index = item.get("index").and_then(Value::as_u64).unwrap_or(0);
+                let vector = serde_json::from_value::<Vec<f32>>(
+                    item.get("embedding").cloned().unwrap_or_default(),
+                )
+                .context("Embeddings response has a malformed embedding")?;
+                Ok((index, vector))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        data.sort_by_key(|(index, _)| *index);
+        Ok(data.into_iter().map(|(_, vector)| vector).collect())
+    }
+}
+
+fn cmd_split(
+    claude_dir: &Path,
+    time_window: &TimeWindow,
+    global: &GlobalArgs,
+    args: SplitArgs,
+) -> Result<()> {
+    if !(0.0..=1.0).contains(&args.similarity) {
+        bail!("--similarity must be between 0 and 1");
+    }
+    let sessions = discover_sessions(claude_dir, time_window, global)?;
+    let session = resolve_session_target(&sessions, &args.target, None)?;
+    let mut parsed = parse_session_with(session, &global.parse_options(args.detailed))?;
+    time_window.retain_events(&mut parsed.events);
+    let redactor = args.redact.redactor()?;
+    let redactions = match &redactor {
+        Some(redactor) => {
+            redactor.redact_title(&mut parsed.title);
+            redactor.redact_events(&mut parsed.events)
+        }
+        None => 0,
+    };
+    let starts = match args.by {
+        SplitBy::Gap(gap) => split_by_gap(&parsed.events, gap),
+        SplitBy::Topic => split_by_topic(
+            &parsed.events,
+            args.embed.embedder().as_mut(),
+            args.similarity,
+            args.min_prompts,
+        )?,
+    };
+    let doc = build_export_document(session, &parsed.events).titled(&parsed.title);
+    let parts = split_document(&doc, &starts);
+
+    let mut paths = Vec::new();
+    if !args.list {
+        fs::create_dir_all(&args.output)
+            .with_context(|| format!("Failed to create output dir {}", args.output.display()))?;
+        for part in &parts {
+            let name = default_export_name(part, args.format, false);
+            paths.push(write_single_export(
+                &args.output,
+                part,
+                args.format,
+                global.export_style(),
+                &name,
+            )?);
+        }
+    }
+    let summaries = parts
+        .iter()
+        .enumerate()
+        .map(|(i, part)| {
+            let first_prompt = pair_exchanges(&part.events)
+                .first()
+                .map(|e| ellipsize(&clean_preview(&e.question), 80));
+            json!({
+                "part": i + 1,
+                "start": part.events.iter().find_map(|e| e.timestamp.clone()),
+                "end": part.events.iter().rev().find_map(|e| e.timestamp.clone()),
+                "events": part.event_count,
+                "first_prompt": first_prompt,
+                "path": paths.get(i),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    if global.json {
+        print_json(&json!({
+            "session_id": session.id,
+            "project": session.project,
+            "by": args.by.to_string(),
+            "parts": summaries,
+        }))?;
+    } else {
+        println!(
+            "{}",
+            palette().heading.apply_to(format!(
+                "Session {}: {} part(s) by {}",
+                session.id,
+                parts.len(),
+                args.by
+            ))
+        );
+        for summary in &summaries {
+            println!(
+                "{:<7} {} .. {}  {:>4} events  {}",
+                format!("part{}", summary["part"]),
+                summary["start"].as_str().unwrap_or("-"),
+                summary["end"].as_str().unwrap_or("-"),
+                summary["events"],
+                summary["first_prompt"].as_str().unwrap_or("(no prompt)")
+            );
+            if let Some(path) = summary["path"].as_str() {
+                println!("        {}", palette().meta.apply_to(path));
+            }
+        }
+    }
+    report_redactions(redactor.is_some(), redactions);
+    Ok(())
+}
+
+fn cmd_decrypt(global: &GlobalArgs, args: DecryptArgs) -> Result<()> {
+    let mut targets = Vec::with_capacity(args.files.len());
+    for file in &args.files {
+        let name = file.file_name().unwrap_or_default().to_string_lossy();
+        let plain_name = name
+            .strip_suffix(&format!(".{AGE_EXTENSION}"))
+            .filter(|stem| !stem.is_empty())
+            .ok_or_else(|| anyhow!("{} doesn't end in .{AGE_EXTENSION}", file.display()))?;
+        let target = match &args.output {
+            Some(dir) => dir.join(plain_name),
+            None => file.with_file_name(plain_name),
+        };
+        if target.exists() && !args.force {
+            bail!(
+                "{} already exists; pass --force to overwrite it",
+                target.display()
+            );
+        }
+        targets.push(target);
+    }
+    let key = if args.identity.is_empty() {
+        // Only ask for a passphrase once a file turns out to need one.
+        let first = fs::read(&args.files[0])
+            .with_context(|| format!("Failed to read {}", args.files[0].display()))?;
+        if !is_passphrase_encrypted(&first)? {
+            bail!(
+                "{} is encrypted to a key; pass its identity file with --identity",
+                args.files[0].display()
+            );
+        }
+        Decryption::passphrase(read_passphrase(false)?)
+    } else {
+        Decryption::identity_files(&args.identity)?
+    };
+    if let Some(dir) = &args.output {
+        fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create output dir {}", dir.display()))?;
+    }
+    for (file, target) in args.files.iter().zip(&targets) {
+        let encrypted =
+            fs::read(file).with_context(|| format!("Failed to read {}", file.display()))?;
+        let plain = key
+            .decrypt(&encrypted)
+            .with_context(|| format!("Failed to decrypt {}", file.display()))?;
+        write_atomic(target, plain)?;
+    }
+
+    if global.json {
+        return print_json(&json!({ "decrypted": targets }));
+    }
+    println!(
+        "{}",
+        palette()
+            .success
+            .apply_to(format!("Decrypted {} file(s).", targets.len()))
+    );
+    for target in &targets {
+        println!("  {}", target.display());
+    }
+    Ok(())
+}
+
+fn cmd_export_diff(
+    claude_dir: &Path,
+    time_window: &TimeWindow,
+    global: &GlobalArgs,
+    args: ExportDiffArgs,
+) -> Result<()> {
+    let raw = fs::read_to_string(&args.previous)
+        .with_context(|| format!("Failed to read {}", args.previous.display()))?;
+    let previous: ExportDocument = serde_json::from_str(&raw).with_context(|| {
+        format!(
+            "{} is not a single-session JSON export",
+            args.previous.display()
+        )
+    })?;
+
+    let sessions = discover_sessions(claude_dir, time_window, global)?;
+    let session = match &args.target {
+        Some(target) => resolve_session_target(&sessions, target, None)?,
+        None => find_session(
+            &sessions,
+            &format!("{}/{}", previous.project, previous.session_id),
+        )
+        .or_else(|_| find_session(&sessions, &previous.session_id))?,
+    };
+    // A detailed export is the only kind with non-dialog events.
+    let detailed = args.detailed
+        || previous
+            .events
+            .iter()
+            .any(|e| e.role != "user" && e.role != "assistant");
+    let mut parsed = parse_session_with(session, &global.parse_options(detailed))?;
+    time_window.retain_events(&mut parsed.events);
+    if let Some(redactor) = args.redact.redactor()? {
+        redactor.redact_events(&mut parsed.events);
+    }
+    let mut current = build_export_document(session, &parsed.events);
+    if previous.tree.is_some() {
+        current = current.threaded();
+    }
+    let diff = diff_exports(&previous, &current);
+    if args.update {
+        write_atomic(&args.previous, serde_json::to_string_pretty(&current)?)?;
+    }
+
+    if global.json {
+        return print_json(&json!({
+            "session_id": session.id,
+            "project": session.project,
+            "previous": args.previous,
+            "unchanged": diff.unchanged,
+            "removed": diff.removed,
+            "appended": diff.appended,
+            "updated": args.update,
+        }));
+    }
+
+    println!(
+        "{}",
+        palette()
+            .heading
+            .apply_to(format!("Session {}", session.id))
+    );
+    println!(
+        "{} new event(s) since {} ({} unchanged, {} removed).",
+        diff.appended.len(),
+        args.previous.display(),
+        diff.unchanged,
+        diff.removed
+    );
+    if !diff.appended.is_empty() {
+        println!();
+    }
+    for event in &diff.appended {
+        println!(
+            "{} {} {}",
+            palette()
+                .meta
+                .apply_to(event.timestamp.as_deref().unwrap_or("-")),
+            palette().role_label(&event.role),
+            event.content
+        );
+    }
+    if args.update {
+        println!("Updated {}.", args.previous.display());
+    }
+    Ok(())
+}
+
+fn cmd_diff(
+    claude_dir: &Path,
+    time_window: &TimeWindow,
+    global: &GlobalArgs,
+    args: DiffArgs,
+) -> Result<()> {
+    let sessions = discover_sessions(claude_dir, time_window, global)?;
+    let options = global.parse_options(args.detailed);
+    let load = |target: &str| -> Result<(&Session, Vec<NormalizedEvent>)> {
+        let session = resolve_session_target(&sessions, target, None)?;
+        let mut parsed = parse_session_with(session, &options)?;
+        time_window.retain_events(&mut parsed.events);
+        Ok((session, parsed.events))
+    };
+    let (session_a, a) = load(&args.a)?;
+    let (session_b, b) = load(&args.b)?;
+    let rows = align_events(&a, &b);
+    let count = |op: DiffOp| rows.iter().filter(|row| row.op == op).count();
+
+    if global.json {
+        return print_json(&json!({
+            "a": session_a,
+            "b": session_b,
+            "same": count(DiffOp::Same),
+            "changed": count(DiffOp::Changed),
+            "removed": count(DiffOp::Removed),
+            "added": count(DiffOp::Added),
+            "rows": rows.iter().map(|row| json!({
+                "op": row.op,
+                "a": row.a.map(|i| &a[i]),
+                "b": row.b.map(|j| &b[j]),
+            })).collect::<Vec<_>>(),
+        }));
+    }
+
+    println!(
+        "{}",
+        palette()
+            .error
+            .apply_to(format!("--- {}", session_a.qualified_id()))
+    );
+    println!(
+        "{}",
+        palette()
+            .ok
+            .apply_to(format!("+++ {}", session_b.qualified_id()))
+    );
+    println!(
+        "{} same, {} changed, {} removed, {} added",
+        count(DiffOp::Same),
+        count(DiffOp::Changed),
+        count(DiffOp::Removed),
+        count(DiffOp::Added)
+    );
+    let width = args
+        .width
+        .or_else(|| {
+            console::Term::stdout()
+                .size_checked()
+                .map(|(_, w)| w as usize)
+        })
+        .unwrap_or(120);
+    // Identical events far from any difference are folded into a count.
+    let near_change = |k: usize| {
+        let lo = k.saturating_sub(args.context);
+        let hi = (k + args.context + 1).min(rows.len());
+        rows[lo..hi].iter().any(|row| row.op != DiffOp::Same)
+    };
+    let mut folded = 0;
+    for (k, row) in rows.iter().enumerate() {
+        if row.op == DiffOp::Same && !near_change(k) {
+            folded += 1;
+            continue;
+        }
+        print_folded(folded);
+        folded = 0;
+        let a = row.a.map(|i| &a[i]);
+        let b = row.b.map(|j| &b[j]);
+        if args.side_by_side {
+            print_diff_columns(row.op, a, b, width);
+        } else {
+            print_diff_unified(row.op, a, b);
+        }
+    }
+    print_folded(folded);
+    Ok(())
+}
+
+fn print_folded(count: usize) {
+    if count > 0 {
+        println!(
+            "{}",
+            palette()
+                .meta
+                .apply_to(format!("  ... {count} identical event(s)"))
+        );
+    }
+}
+
+fn print_diff_unified(op: DiffOp, a: Option<&NormalizedEvent>, b: Option<&NormalizedEvent>) {
+    let p = palette();
+    match (op, a, b) {
+        (DiffOp::Changed, Some(a), Some(b)) => {
+            println!(
+                "{}",
+                p.warn.apply_to(format!("~ {}", p.role_label(&a.role)))
+            );
+            for (mark, line) in diff_lines(&a.content, &b.content) {
+                let line = format!("{mark} {line}");
+                let line = line.trim_end();
+                match mark {
+                    '-' => println!("{}", p.error.apply_to(line)),
+                    '+' => println!("{}", p.ok.apply_to(line)),
+                    _ => println!("{line}"),
+                }
+            }
+        }
+        (DiffOp::Removed, Some(event), _) | (DiffOp::Added, _, Some(event)) => {
+            let (mark, style) = if op == DiffOp::Removed {
+                ('-', &p.error)
+            } else {
+                ('+', &p.ok)
+            };
+            println!(
+                "{}",
+                style.apply_to(format!("{mark} {}", p.role_label(&event.role)))
+            );
+            for line in event.content.lines() {
+                println!("{}", style.apply_to(format!("{mark} {line}").trim_end()));
+            }
+        }
+        (_, Some(event), _) => {
+            let first = event.content.lines().next().unwrap_or_default();
+            println!("  {} {}", p.role_label(&event.role), ellipsize(first, 100));
+        }
+        _ => {}
+    }
+}
+
+/// One aligned row as two columns with a marker between them: `|` changed,
+/// `<` only on the left, `>` only on the right.
+fn print_diff_columns(
+    op: DiffOp,
+    a: Option<&NormalizedEvent>,
+    b: Option<&NormalizedEvent>,
+    width: usize,
+) {
+    let p = palette();
+    let column = width.saturating_sub(3).max(20) / 2;
+    let lines = |event: Option<&NormalizedEvent>| -> Vec<String> {
+        let Some(event) = event else {
+            return Vec::new();
+        };
+        let text = if op == DiffOp::Same {
+            event.content.lines().next().unwrap_or_default()
+        } else {
+            &event.content
+        };
+        let mut lines = vec![format!("[{}]", event.role)];
+        lines.extend(text.lines().flat_map(|line| wrap_columns(line, column)));
+        lines
+    };
+    let (left, right) = (lines(a), lines(b));
+    let (mark, style) = match op {
+        DiffOp::Same => (' ', None),
+        DiffOp::Changed => ('|', Some(&p.warn)),
+        DiffOp::Removed => ('<', Some(&p.error)),
+        DiffOp::Added => ('>', Some(&p.ok)),
+    };
+    for k in 0..left.len().max(right.len()) {
+        let cell = |lines: &[String]| {
+            let text = lines.get(k).map(String::as_str).unwrap_or_default();
+            let pad = column.saturating_sub(console::measure_text_width(text));
+            format!("{text}{}", " ".repeat(pad))
+        };
+        let row = format!("{} {mark} {}", cell(&left), cell(&right));
+        let row = row.trim_end();
+        match style {
+            Some(style) => println!("{}", style.apply_to(row)),
+            None => println!("{row}"),
+        }
+    }
+}
+
+/// `line` cut into pieces at most `width` terminal columns wide.
+fn wrap_columns(line: &str, width: usize) -> Vec<String> {
+    let mut pieces = vec![String::new()];
+    let mut used = 0;
+    for c in line.chars() {
+        let w = console::measure_text_width(c.encode_utf8(&mut [0; 4]));
+        if used + w > width {
+            pieces.push(String::new());
+            used = 0;
+        }
+        pieces.last_mut().expect("a piece").push(c);
+        used += w;
+    }
+    pieces
+}
+
+fn cmd_search(
+    claude_dir: &Path,
+    time_window: &TimeWindow,
+    global: &GlobalArgs,
+    args: SearchArgs,
+) -> Result<()> {
+    if args.format == SearchFormat::Html && global.json {
+        bail!("--format html can't be combined with --json or --output-format");
+    }
+    if args.format == SearchFormat::Text && args.output.is_some() {
+        bail!("--output needs --format html");
+    }
+    if args.explain && args.format == SearchFormat::Html {
+        bail!("--explain can't be combined with --format html");
+    }
+    if args.explain && matches!(args.mode, SearchMode::Semantic) {
+        bail!("--explain doesn't apply to --mode semantic, which ranks by embedding similarity");
+    }
+    if args.include_thinking {
+        if global.settings.search.disable_thinking {
+            bail!("--include-thinking is turned off by disable_thinking in the config's [search] table");
+        }
+        if matches!(args.mode, SearchMode::Semantic) {
+            bail!("--include-thinking doesn't apply to --mode semantic; the vector index holds no thinking");
+        }
+    }
+    if args.query.is_none() && (args.explain || args.format == SearchFormat::Html) {
+        bail!("--explain and --format html need a query");
+    }
+    let sessions = discover_sessions(claude_dir, time_window, global)?;
+    let filter = args.filter.filter();
+    let sessions = filter_sessions(sessions, &filter, time_window, global)?;
+    let Some(query) = &args.query else {
+        return print_file_sessions(&sessions, &filter, &args, time_window, global);
+    };
+    install_interrupt_handler()?;
+    let progress = Progress::json_only(global, "search", "files_done", sessions.len());
+    let after_file = |session: &Session| {
+        progress.inc(&session.id_short);
+        if interrupted() {
+            ControlFlow::Break(())
+        } else {
+            ControlFlow::Continue(())
+        }
+    };
+    let options = args.options(global, time_window);
+    let (hits, near_misses) = if matches!(args.mode, SearchMode::Semantic) {
+        (
+            search_semantic(&sessions, &args, &options, after_file)?,
+            Vec::new(),
+        )
+    } else if args.explain {
+        let explained = search_sessions_explained(&sessions, &options, after_file)?;
+        (explained.hits, explained.near_misses)
+    } else {
+        (
+            search_sessions_with(&sessions, &options, after_file)?,
+            Vec::new(),
+        )
+    };
+    progress.finish();
+    if interrupted() {
+        eprintln!(
+            "{}",
+            palette().warn.apply_to(format!(
+                "Search interrupted after {} of {} file(s); results are partial.",
+                progress.done(),
+                sessions.len()
+            ))
+        );
+    }
+
+    let hits = hits.into_iter().take(args.max_results).collect::<Vec<_>>();
+    let near_misses = near_misses
+        .into_iter()
+        .take(EXPLAIN_NEAR_MISSES)
+        .collect::<Vec<_>>();
+    if global.json {
+        if args.explain {
+            print_json(&json!({"hits": hits, "near_misses": near_misses}))?;
+        } else {
+            print_json(&hits)?;
+        }
+        return Ok(());
+    }
+    if args.format == SearchFormat::Html {
+        let page = render_search_html(query, &hits, global.export_style());
+        match &args.output {
+            Some(path) => {
+                if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+                    fs::create_dir_all(parent)
+                        .with_context(|| format!("Failed to create {}", parent.display()))?;
+                }
+                write_atomic(path, &page)?;
+                eprintln!("Wrote {} result(s) to {}", hits.len(), path.display());
+            }
+            None => print!("{page}"),
+        }
+        return Ok(());
+    }
+
+    println!(
+        "{}",
+        palette()
+            .heading
+            .apply_to(format!("Found {} result(s).", hits.len()))
+    );
+    for (i, hit) in hits.iter().enumerate() {
+        println!();
+        println!(
+            "{} {} {}{}",
+            style(format!("#{}", i + 1)).bold(),
+            palette().accent.apply_to(&hit.session_id),
+            palette().meta.apply_to(format!("({})", hit.project)),
+            hit.title
+                .as_ref()
+                .map(|t| format!(" {}", style(t).bold()))
+                .unwrap_or_default()
+        );
+        println!(
+            "{} {} {:.2}",
+            palette()
+                .meta
+                .apply_to(hit.timestamp.clone().unwrap_or_else(|| "-".into())),
+            palette().role_label(&hit.speaker),
+            hit.relevance
+        );
+        println!("{}", hit.preview);
+        if let Some(explanation) = &hit.explanation {
+            print_explanation(explanation);
+        }
+    }
+    if args.explain {
+        println!();
+        println!("{}", style("Near misses").bold());
+        if near_misses.is_empty() {
+            println!("  {}", tr!("none"));
+        }
+        for miss in &near_misses {
+            let Some(explanation) = &miss.explanation else {
+                continue;
+            };
+            println!(
+                "{} {} {} {:.2}",
+                palette().accent.apply_to(short_id(&miss.session_id)),
+                palette()
+                    .meta
+                    .apply_to(miss.timestamp.as_deref().unwrap_or("-")),
+                palette().role_label(&miss.speaker),
+                miss.relevance
+            );
+            if let Some(reason) = &explanation.excluded {
+                println!("  {}", palette().warn.apply_to(reason));
+            }
+            println!("  {}", miss.preview);
+            print_explanation(explanation);
+        }
+    }
+    Ok(())
+}
+
+/// How many near misses `search --explain` shows.
+const EXPLAIN_NEAR_MISSES: usize = 10;
+
+/// The words found and the score parts, under a hit or near miss.
+fn print_explanation(explanation: &Explanation) {
+    let words = |words: &[String]| {
+        if words.is_empty() {
+            "-".to_string()
+        } else {
+            words.join(", ")
+        }
+    };
+    let mut found = format!(
+        "matched: {}; missing: {}",
+        words(&explanation.matched_words),
+        words(&explanation.missing_words)
+    );
+    if !explanation.ignored_words.is_empty() {
+        found.push_str(&format!(
+            "; stopwords: {}",
+            words(&explanation.ignored_words)
+        ));
+    }
+    println!("  {}", palette().meta.apply_to(found));
+    for part in &explanation.components {
+        println!(
+            "  {}",
+            palette()
+                .meta
+                .apply_to(format!("+{:.2} {}: {}", part.score, part.name, part.detail))
+        );
+    }
+}
+
+fn cmd_stats(
+    claude_dir: &Path,
+    time_window: &TimeWindow,
+    global: &GlobalArgs,
+    args: StatsArgs,
+) -> Result<()> {
+    let pricing = match &args.pricing {
+        Some(path) => Some(load_pricing(path.as_deref())?),
+        None => None,
+    };
+    if let Some(compare) = &args.compare {
+        if time_window.since.is_some() || time_window.until.is_some() {
+            bail!("--compare sets its own time windows; drop --since/--until");
+        }
+        return cmd_stats_compare(claude_dir, global, compare, pricing.as_ref());
+    }
+    let sessions = discover_sessions(claude_dir, time_window, global)?;
+    let sessions = filter_sessions(sessions, &args.filter.filter(), time_window, global)?;
+    if args.usage_only {
+        return cmd_stats_usage(&sessions, time_window, global, &args, pricing.as_ref());
+    }
+    let progress = Progress::json_only(global, "stats", "files_done", sessions.len());
+    let per_file = sessions
+        .par_iter()
+        .map(|session| {
+            let stats = FileStats::scan(session, time_window)?;
+            progress.inc(&session.id_short);
+            Ok(stats)
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut record_type_counts: HashMap<String, u64> = HashMap::new();
+    let mut block_type_counts: HashMap<String, u64> = HashMap::new();
+    let mut model_counts: HashMap<String, u64> = HashMap::new();
+    let mut parse_errors: u64 = 0;
+    let mut parse_diagnostics = Vec::new();
+    let mut total_records: u64 = 0;
+    let mut usage_by_model: HashMap<String, TokenUsage> = HashMap::new();
+    let mut spend = SpendTotals::default();
+    let mut endings = SessionEndings::default();
+    let mut interruptions = InterruptionTotals::default();
+    let mut pastes = PasteTotals::default();
+    let mut projects = ProjectTotals::default();
+    let mut tools = ToolTotals::default();
+    let mut api_errors = ApiErrorTotals::default();
+    let mut latency = LatencyTotals::default();
+    for (session, stats) in sessions.iter().zip(per_file) {
+        projects.add(session, &stats);
+        tools.add(stats.tools);
+        api_errors.add(stats.api_errors);
+        latency.add(session, stats.latency);
+        endings.add(stats.ended_by);
+        interruptions.add(session, stats.interruptions);
+        pastes.add(session, stats.pastes);
+        total_records += stats.records;
+        parse_errors += stats.parse_errors;
+        parse_diagnostics.extend(stats.diagnostics);
+        merge_counts(&mut record_type_counts, stats.record_types);
+        merge_counts(&mut block_type_counts, stats.block_types);
+        merge_counts(&mut model_counts, stats.models);
+        for (model, usage) in stats.usage {
+            if let Some(pricing) = &pricing {
+                spend.add(pricing, session, &model, &usage);
+            }
+            *usage_by_model.entry(model).or_default() += usage;
+        }
+    }
+
+    progress.finish();
+
+    let record_type_top = top_n_sorted_map(record_type_counts, args.top);
+    let block_type_top = top_n_sorted_map(block_type_counts, args.top);
+    let model_top = top_n_sorted_map(model_counts, args.top);
+    let usage_top = top_usage(usage_by_model, args.top);
+
+    if global.json {
+        let usage_rows = usage_top
+            .iter()
+            .map(|(model, usage)| ModelUsage { model, usage })
+            .collect::<Vec<_>>();
+        print_json(&json!({
+            "sessions": sessions.len(),
+            "total_records": total_records,
+            "parse_errors": parse_errors,
+            "parse_error_details": parse_diagnostics,
+            "record_types": record_type_top,
+            "content_block_types": block_type_top,
+            "models": model_top,
+            "usage_by_model": usage_rows,
+            "ended_by": endings,
+            "interruptions": args.interruptions.then(|| interruptions.to_json(args.top)),
+            "pastes": args.pastes.then(|| pastes.to_json(args.top)),
+            "projects": args.by_project.then(|| projects.to_json(args.top)),
+            "tools": args.tools.then(|| tools.to_json(args.top)),
+            "api_errors": args.errors.then(|| api_errors.to_json()),
+            "latency": args.latency.then(|| latency.to_json(args.top)),
+            "cost": pricing.is_some().then(|| spend.to_json(args.top)),
+        }))?;
+        return Ok(());
+    }
+
+    println!("{}", palette().heading.apply_to(tr!("stats-title")));
+    println!(
+        "{}",
+        tr!(
+            "stats-sessions",
+            count = i18n::number(sessions.len() as u64)
+        )
+    );
+    println!(
+        "{}",
+        tr!("stats-records", count = i18n::number(total_records))
+    );
+    println!(
+        "{}",
+        tr!("stats-parse-errors", count = i18n::number(parse_errors))
+    );
+    println!();
+    print_ranked_map(&tr!("stats-top-record-types"), &record_type_top);
+    println!();
+    print_ranked_map(&tr!("stats-top-block-types"), &block_type_top);
+    println!();
+    print_ranked_map(&tr!("stats-top-models"), &model_top);
+    println!();
+    endings.print();
+    println!();
+    print_token_usage(&usage_top);
+    if pricing.is_some() {
+        println!();
+        spend.print(args.top);
+    }
+    if args.interruptions {
+        println!();
+        interruptions.print(args.top);
+    }
+    if args.pastes {
+        println!();
+        pastes.print(args.top);
+    }
+    if args.by_project {
+        println!();
+        projects.print(args.top);
+    }
+    if args.tools {
+        println!();
+        tools.print(args.top);
+    }
+    if args.errors {
+        println!();
+        api_errors.print(args.top);
+    }
+    if args.latency {
+        println!();
+        latency.print(args.top);
+    }
+    if args.show_parse_errors {
+        report_parse_errors(parse_errors, &parse_diagnostics, true);
+    }
+    Ok(())
+}
+
+/// `stats --usage-only`: the token table and spend, reading only the lines
+/// that report usage.
+fn cmd_stats_usage(
+    sessions: &[Session],
+    time_window: &TimeWindow,
+    global: &GlobalArgs,
+    args: &StatsArgs,
+    pricing: Option<&Pricing>,
+) -> Result<()> {
+    let progress = Progress::json_only(global, "stats", "files_done", sessions.len());
+    let per_file = sessions
+        .par_iter()
+        .map(|session| {
+            let scan = session_usage(session, time_window)?;
+            progress.inc(&session.id_short);
+            Ok(scan)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    progress.finish();
+
+    let mut usage_by_model: HashMap<String, TokenUsage> = HashMap::new();
+    let mut spend = SpendTotals::default();
+    let (mut lines, mut parsed) = (0u64, 0u64);
+    for (session, scan) in sessions.iter().zip(per_file) {
+        lines += scan.lines;
+        parsed += scan.parsed;
+        for (model, usage) in scan.into_usage() {
+            if let Some(pricing) = pricing {
+                spend.add(pricing, session, &model, &usage);
+            }
+            *usage_by_model.entry(model).or_default() += usage;
+        }
+    }
+    if global.verbose {
+        eprintln!(
+            "{}",
+            palette().meta.apply_to(format!(
+                "Parsed {parsed} of {lines} line(s); the rest don't mention usage."
+            ))
+        );
+    }
+    let usage_top = top_usage(usage_by_model, args.top);
+
+    if global.json {
+        let usage_rows = usage_top
+            .iter()
+            .map(|(model, usage)| ModelUsage { model, usage })
+            .collect::<Vec<_>>();
+        return print_json(&json!({
+            "sessions": sessions.len(),
+            "usage_by_model": usage_rows,
+            "cost": pricing.is_some().then(|| spend.to_json(args.top)),
+        }));
+    }
+    println!("{}", palette().heading.apply_to(tr!("stats-title")));
+    println!(
+        "{}",
+        tr!(
+            "stats-sessions",
+            count = i18n::number(sessions.len() as u64)
+        )
+    );
+    println!();
+    print_token_usage(&usage_top);
+    if pricing.is_some() {
+        println!();
+        spend.print(args.top);
+    }
+    Ok(())
+}
+
+/// The `n` models with the most tokens.
+fn top_usage(usage_by_model: HashMap<String, TokenUsage>, n: usize) -> Vec<(String, TokenUsage)> {
+    let mut usage_top = usage_by_model.into_iter().collect::<Vec<_>>();
+    usage_top.sort_by(|a, b| b.1.total().cmp(&a.1.total()).then_with(|| a.0.cmp(&b.0)));
+    usage_top.truncate(n);
+    usage_top
+}
+
+fn print_token_usage(usage_top: &[(String, TokenUsage)]) {
+    println!("{}", style(tr!("stats-token-usage")).bold());
+    if usage_top.is_empty() {
+        println!("  {}", tr!("none"));
+    } else {
+        println!(
+            "  {:>12} {:>12} {:>12} {:>12}  model",
+            "input", "output", "cache_write", "cache_read"
+        );
+        for (model, usage) in usage_top {
+            println!(
+                "  {:>12} {:>12} {:>12} {:>12}  {}",
+                i18n::number(usage.input_tokens),
+                i18n::number(usage.output_tokens),
+                i18n::number(usage.cache_creation_input_tokens),
+                i18n::number(usage.cache_read_input_tokens),
+                model
+            );
+        }
+    }
+}
+
+/// Counts from one transcript, summed over the corpus by `stats`.
+#[derive(Default)]
+struct FileStats {
+    records: u64,
+    record_types: HashMap<String, u64>,
+    block_types: HashMap<String, u64>,
+    models: HashMap<String, u64>,
+    parse_errors: u64,
+    diagnostics: Vec<ParseDiagnostic>,
+    /// `(model, usage)` per assistant message.
+    usage: Vec<(String, TokenUsage)>,
+    ended_by: Option<SessionEnd>,
+    interruptions: InterruptionScan,
+    pastes: PasteScan,
+    tools: ToolScan,
+    api_errors: ApiErrorScan,
+    latency: LatencyScan,
+}
+
+impl FileStats {
+    fn scan(session: &Session, time_window: &TimeWindow) -> Result<Self> {
+        let mut stats = Self {
+            ended_by: session_end(&session.path),
+            ..Self::default()
+        };
+        let mut usage_scan = UsageScan::default();
+        // A stitched session's later files may repeat records of the earlier ones.
+        let mut seen = HashSet::new();
+        for path in session.paths() {
+            let f = open_transcript(path)?;
+            let mut records = jsonl_records(BufReader::new(f));
+            while let Some(line) = records.next() {
+                let record = line?.and_then(|value| {
+                    Record::from_value(&value).map_err(|err| records.schema_diagnostic(&err))
+                });
+                let record = match record {
+                    Ok(record) => record,
+                    Err(mut diagnostic) => {
+                        stats.parse_errors += 1;
+                        if stats.diagnostics.len() < MAX_PARSE_DIAGNOSTICS {
+                            diagnostic.file = Some(path.to_path_buf());
+                            stats.diagnostics.push(diagnostic);
+                        }
+                        continue;
+                    }
+                };
+                if !time_window.admits(record.head.timestamp.as_deref()) {
+                    continue;
+                }
+                if let Some(uuid) = &record.head.uuid {
+                    if !seen.insert(uuid.clone()) {
+                        continue;
+                    }
+                }
+                stats.records += 1;
+                stats.interruptions.observe(&record);
+                stats.pastes.observe(&record);
+                stats.tools.observe(&record);
+                stats.api_errors.observe(&record);
+                stats.latency.observe(&record);
+                *stats
+                    .record_types
+                    .entry(
+                        record
+                            .head
+                            .record_type
+                            .as_deref()
+                            .unwrap_or("<missing>")
+                            .to_string(),
+                    )
+                    .or_insert(0) += 1;
+                let Some(message) = record.message() else {
+                    continue;
+                };
+                if let Some(usage) = message.usage {
+                    usage_scan.add(message.id.clone(), message.model.clone(), usage);
+                }
+                if let (RecordBody::Assistant(_), Some(model)) = (&record.body, &message.model) {
+                    *stats.models.entry(model.clone()).or_insert(0) += 1;
+                }
+                if let MessageContent::Items(items) = &message.content {
+                    for t in items.iter().filter_map(ContentItem::block_type) {
+                        *stats.block_types.entry(t.to_string()).or_insert(0) += 1;
+                    }
+                }
+            }
+        }
+        stats.usage = usage_scan.into_usage();
+        Ok(stats)
+    }
+}
+
+/// Prompts opening with one of these are counted as corrections.
+const CORRECTION_OPENERS: &[&str] = &[
+    "no",
+    "nope",
+    "wait",
+    "stop",
+    "actually",
+    "undo",
+    "revert",
+    "wrong",
+    "that's wrong",
+    "that's not",
+    "that is not",
+    "don't",
+    "do not",
+    "not what",
+    "i said",
+    "i meant",
+];
+
+/// Interrupt and correction counts for one model's replies.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+struct InterruptionTally {
+    /// Prompts the model answered.
+    turns: u64,
+    /// Replies stopped with Esc or Ctrl-C.
+    interrupts: u64,
+    /// Prompts right after an interrupt, or opening like a correction.
+    corrections: u64,
+}
+
+impl InterruptionTally {
+    fn add(&mut self, other: Self) {
+        self.turns += other.turns;
+        self.interrupts += other.interrupts;
+        self.corrections += other.corrections;
+    }
+
+    /// Interrupts and corrections per answered prompt.
+    fn rate(&self) -> f64 {
+        if self.turns == 0 {
+            0.0
+        } else {
+            (self.interrupts + self.corrections) as f64 / self.turns as f64
+        }
+    }
+}
+
+/// Walks one transcript's records for `stats --interruptions`. Interrupts
+/// and corrections are charged to the model whose reply they cut short.
+#[derive(Default)]
+struct InterruptionScan {
+    by_model: HashMap<String, InterruptionTally>,
+    last_model: Option<String>,
+    /// Prompts waiting for the reply that tells which model answered them.
+    unanswered: u64,
+    after_interrupt: bool,
+}
+
+impl InterruptionScan {
+    fn observe(&mut self, record: &Record) {
+        if record.is_meta() {
+            return;
+        }
+        match &record.body {
+            RecordBody::Assistant(assistant) => {
+                let model = assistant
+                    .message
+                    .model
+                    .as_deref()
+                    .filter(|m| *m != "<synthetic>");
+                if let Some(model) = model {
+                    self.by_model.entry(model.to_string()).or_default().turns += self.unanswered;
+                    self.unanswered = 0;
+                    self.last_model = Some(model.to_string());
+                }
+            }
+            RecordBody::User(user) => {
+                let text = user.message.content.text(false);
+                let text = text.trim();
+                if text.starts_with("[Request interrupted") {
+                    if let Some(tally) = self.last_tally() {
+                        tally.interrupts += 1;
+                    }
+                    self.after_interrupt = true;
+                } else if is_typed_prompt(text) {
+                    let corrects = self.after_interrupt || is_correction(text);
+                    if corrects {
+                        if let Some(tally) = self.last_tally() {
+                            tally.corrections += 1;
+                        }
+                    }
+                    self.unanswered += 1;
+                    self.after_interrupt = false;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn last_tally(&mut self) -> Option<&mut InterruptionTally> {
+        let model = self.last_model.clone()?;
+        Some(self.by_model.entry(model).or_default())
+    }
+}
+
+/// Whether a user record's text is something the user typed, rather than
+/// a tool result (no text) or the output of a slash command.
+fn is_typed_prompt(text: &str) -> bool {
+    !text.is_empty() && !text.starts_with("<command-") && !text.starts_with("<local-command-")
+}
+
+fn is_correction(prompt: &str) -> bool {
+    let lower = prompt.to_lowercase().replace('\u{2019}', "'");
+    CORRECTION_OPENERS.iter().any(|opener| {
+        lower
+            .strip_prefix(opener)
+            .is_some_and(|rest| !rest.starts_with(|c: char| c.is_alphanumeric()))
+    })
+}
+
+/// `stats --interruptions` totals per project and per model.
+#[derive(Default)]
+struct InterruptionTotals {
+    by_model: HashMap<String, InterruptionTally>,
+    by_project: HashMap<String, InterruptionTally>,
+}
+
+impl InterruptionTotals {
+    fn add(&mut self, session: &Session, scan: InterruptionScan) {
+        let project = self.by_project.entry(session.project.clone()).or_default();
+        for (model, tally) in scan.by_model {
+            project.add(tally);
+            self.by_model.entry(model).or_default().add(tally);
+        }
+    }
+
+    fn to_json(&self, top: usize) -> Value {
+        let ranked = |map: &HashMap<String, InterruptionTally>| {
+            top_n_interruptions(map, top)
+                .into_iter()
+                .map(|(name, tally)| {
+                    json!({
+                        "name": name,
+                        "turns": tally.turns,
+                        "interrupts": tally.interrupts,
+                        "corrections": tally.corrections,
+                        "rate": (tally.rate() * 1000.0).round() / 1000.0,
+                    })
+                })
+                .collect::<Vec<_>>()
+        };
+        json!({
+            "by_project": ranked(&self.by_project),
+            "by_model": ranked(&self.by_model),
+        })
+    }
+
+    fn print(&self, top: usize) {
+        for (title, map) in [
+            ("Interruptions by project", &self.by_project),
+            ("Interruptions by model", &self.by_model),
+        ] {
+            println!("{}", style(title).bold());
+            let rows = top_n_interruptions(map, top);
+            if rows.is_empty() {
+                println!("  (none)");
+                continue;
+            }
+            println!(
+                "  {:>7} {:>10} {:>11} {:>6}  name",
+                "turns", "interrupts", "corrections", "rate"
+            );
+            for (name, tally) in rows {
+                println!(
+                    "  {:>7} {:>10} {:>11} {:>5.1}%  {}",
+                    tally.turns,
+                    tally.interrupts,
+                    tally.corrections,
+                    tally.rate() * 100.0,
+                    name
+                );
+            }
+        }
+    }
+}
+
+/// Walks one transcript's records for `stats --latency`: the wait from each
+/// prompt to the first reply after it, charged to the model that replied.
+#[derive(Default)]
+struct LatencyScan {
+    /// When the oldest prompt still waiting for a reply was sent.
+    waiting_since: Option<DateTime<Utc>>,
+    /// `(model, milliseconds)` per answered prompt.
+    waits: Vec<(String, u64)>,
+}
+
+impl LatencyScan {
+    fn observe(&mut self, record: &Record) {
+        if record.is_meta() {
+            return;
+        }
+        let at = record
+            .head
+            .timestamp
+            .as_deref()
+            .and_then(|ts| DateTime::parse_from_rfc3339(ts).ok())
+            .map(|at| at.with_timezone(&Utc));
+        match &record.body {
+            RecordBody::User(user) => {
+                let text = user.message.content.text(false);
+                let text = text.trim();
+                if text.starts_with("[Request interrupted") {
+                    // The prompt was given up on; its reply never came.
+                    self.waiting_since = None;
+                } else if is_typed_prompt(text) {
+                    self.waiting_since = self.waiting_since.or(at);
+                }
+            }
+            RecordBody::Assistant(reply) => {
+                // Error stand-ins aren't replies; the user is still waiting.
+                let Some(model) = reply
+                    .message
+                    .model
+                    .as_deref()
+                    .filter(|m| *m != "<synthetic>")
+                else {
+                    return;
+                };
+                if let (Some(since), Some(at)) = (self.waiting_since.take(), at) {
+                    if let Ok(wait) = u64::try_from((at - since).num_milliseconds()) {
+                        self.waits.push((model.to_string(), wait));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The spread of a group's waits, in milliseconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+struct LatencySpread {
+    prompts: usize,
+    p50_ms: u64,
+    p90_ms: u64,
+    p99_ms: u64,
+    max_ms: u64,
+}
+
+impl LatencySpread {
+    /// `None` for no waits.
+    fn of(waits: &[u64]) -> Option<Self> {
+        if waits.is_empty() {
+            return None;
+        }
+        let mut sorted = waits.to_vec();
+        sorted.sort_unstable();
+        // Nearest rank: the smallest wait at least `p` percent of waits reach.
+        let rank = |p: usize| sorted[(sorted.len() * p).div_ceil(100).max(1) - 1];
+        Some(Self {
+            prompts: sorted.len(),
+            p50_ms: rank(50),
+            p90_ms: rank(90),
+            p99_ms: rank(99),
+            max_ms: sorted[sorted.len() - 1],
+        })
+    }
+}
+
+/// `stats --latency` waits per model and per project.
+#[derive(Default)]
+struct LatencyTotals {
+    all: Vec<u64>,
+    by_model: HashMap<String, Vec<u64>>,
+    by_project: HashMap<String, Vec<u64>>,
+}
+
+impl LatencyTotals {
+    fn add(&mut self, session: &Session, scan: LatencyScan) {
+        for (model, wait) in scan.waits {
+            self.all.push(wait);
+            self.by_model.entry(model).or_default().push(wait);
+            self.by_project
+                .entry(session.project.clone())
+                .or_default()
+                .push(wait);
+        }
+    }
+
+    /// The `top` groups with the most prompts.
+    fn ranked(map: &HashMap<String, Vec<u64>>, top: usize) -> Vec<(&String, LatencySpread)> {
+        let mut rows = map
+            .iter()
+            .filter_map(|(name, waits)| Some((name, LatencySpread::of(waits)?)))
+            .collect::<Vec<_>>();
+        rows.sort_by(|a, b| b.1.prompts.cmp(&a.1.prompts).then_with(|| a.0.cmp(b.0)));
+        rows.truncate(top);
+        rows
+    }
+
+    fn to_json(&self, top: usize) -> Value {
+        let ranked = |map| {
+            Self::ranked(map, top)
+                .into_iter()
+                .map(|(name, spread)| {
+                    let mut row = json!(spread);
+                    row["name"] = json!(name);
+                    row
+                })
+                .collect::<Vec<_>>()
+        };
+        json!({
+            "all": LatencySpread::of(&self.all),
+            "by_model": ranked(&self.by_model),
+            "by_project": ranked(&self.by_project),
+        })
+    }
+
+    fn print(&self, top: usize) {
+        println!("{}", style("Time to first reply").bold());
+        let Some(all) = LatencySpread::of(&self.all) else {
+            println!("  {}", tr!("none"));
+            return;
+        };
+        let line = |spread: &LatencySpread, name: &str| {
+            println!(
+                "  {:>7} {:>8} {:>8} {:>8} {:>8}  {}",
+                i18n::number(spread.prompts as u64),
+                format_wait(spread.p50_ms),
+                format_wait(spread.p90_ms),
+                format_wait(spread.p99_ms),
+                format_wait(spread.max_ms),
+                name
+            );
+        };
+        println!(
+            "  {:>7} {:>8} {:>8} {:>8} {:>8}  name",
+            "prompts", "p50", "p90", "p99", "max"
+        );
+        line(&all, "(all)");
+        for (title, map) in [
+            ("By model", &self.by_model),
+            ("By project", &self.by_project),
+        ] {
+            println!("{}", style(title).bold());
+            for (name, spread) in Self::ranked(map, top) {
+                line(&spread, name);
+            }
+        }
+    }
+}
+
+/// A wait to a tenth of a second under a minute, and to the second above.
+fn format_wait(ms: u64) -> String {
+    let secs = ms / 1000;
+    match secs {
+        ..=59 => format!("{}s", i18n::decimal(ms as f64 / 1000.0, 1)),
+        60..=3599 => format!("{}m{:02}s", secs / 60, secs % 60),
+        _ => format!("{}h{:02}m", secs / 3600, secs % 3600 / 60),
+    }
+}
+
+/// One project's line in `stats --by-project`.
+#[derive(Debug, Clone, Default)]
+struct ProjectTally {
+    sessions: u64,
+    messages: u64,
+    usage: TokenUsage,
+    last_activity_epoch: i64,
+    last_activity: String,
+}
+
+/// `stats --by-project` totals.
+#[derive(Default)]
+struct ProjectTotals {
+    by_project: HashMap<String, ProjectTally>,
+}
+
+impl ProjectTotals {
+    fn add(&mut self, session: &Session, stats: &FileStats) {
+        let tally = self.by_project.entry(session.project.clone()).or_default();
+        tally.sessions += 1;
+        tally.messages += ["user", "assistant"]
+            .iter()
+            .filter_map(|kind| stats.record_types.get(*kind))
+            .sum::<u64>();
+        for (_, usage) in &stats.usage {
+            tally.usage += *usage;
+        }
+        if tally.last_activity.is_empty() || session.modified_epoch > tally.last_activity_epoch {
+            tally.last_activity_epoch = session.modified_epoch;
+            tally.last_activity = session.modified_iso.clone();
+        }
+    }
+
+    /// Most recently active first.
+    fn ranked(&self, top: usize) -> Vec<(&String, &ProjectTally)> {
+        let mut rows = self.by_project.iter().collect::<Vec<_>>();
+        rows.sort_by(|a, b| {
+            b.1.last_activity_epoch
+                .cmp(&a.1.last_activity_epoch)
+                .then_with(|| a.0.cmp(b.0))
+        });
+        rows.truncate(top);
+        rows
+    }
+
+    fn to_json(&self, top: usize) -> Value {
+        self.ranked(top)
+            .into_iter()
+            .map(|(project, tally)| {
+                json!({
+                    "project": project,
+                    "sessions": tally.sessions,
+                    "messages": tally.messages,
+                    "tokens": tally.usage.total(),
+                    "usage": tally.usage,
+                    "last_activity": tally.last_activity,
+                })
+            })
+            .collect()
+    }
+
+    fn print(&self, top: usize) {
+        println!("{}", style(tr!("stats-by-project")).bold());
+        let rows = self.ranked(top);
+        if rows.is_empty() {
+            println!("  {}", tr!("none"));
+            return;
+        }
+        println!(
+            "  {:>8} {:>8} {:>12}  {:<20}  project",
+            "sessions", "messages", "tokens", "last_activity"
+        );
+        for (project, tally) in rows {
+            println!(
+                "  {:>8} {:>8} {:>12}  {:<20}  {}",
+                i18n::number(tally.sessions),
+                i18n::number(tally.messages),
+                i18n::number(tally.usage.total()),
+                i18n::datetime(&tally.last_activity),
+                project
+            );
+        }
+    }
+}
+
+/// Spend of one `cost-report` row.
+#[derive(Debug, Clone, Default)]
+struct CostTally {
+    sessions: HashSet<String>,
+    usage: TokenUsage,
+    usd: f64,
+}
+
+/// Where an assistant message's tokens are charged: the repo and branch of
+/// the record it came in, so a session that switched branches is split.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct CostKey {
+    repo: String,
+    branch: String,
+    ticket: String,
+    project: String,
+    model: String,
+}
+
+impl CostKey {
+    fn column(&self, group: CostGroup) -> &str {
+        match group {
+            CostGroup::Repo => &self.repo,
+            CostGroup::Branch => &self.branch,
+            CostGroup::Ticket => &self.ticket,
+            CostGroup::Project => &self.project,
+            CostGroup::Model => &self.model,
+        }
+    }
+}
+
+/// Label for spend without a repo, branch, or ticket.
+const UNATTRIBUTED: &str = "(none)";
+
+/// The name of the git repository containing `cwd`: its top-level directory,
+/// found by the nearest `.git` above it, else `cwd`'s own last component
+/// when the directory is gone or not a repo.
+fn repo_name(cwd: &str) -> String {
+    let path = Path::new(cwd);
+    let root = path
+        .ancestors()
+        .find(|dir| dir.join(".git").exists())
+        .unwrap_or(path);
+    root.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| cwd.to_string())
+}
+
+/// The ticket id in `branch`: `pattern`'s first capture group, else its match.
+fn ticket_id(pattern: &regex::Regex, branch: &str) -> Option<String> {
+    let captures = pattern.captures(branch)?;
+    captures
+        .get(1)
+        .or_else(|| captures.get(0))
+        .map(|m| m.as_str().to_string())
+}
+
+/// `(key, model, usage)` per assistant message of `session`, charged to the
+/// cwd and branch last seen before it.
+fn session_costs(
+    session: &Session,
+    time_window: &TimeWindow,
+    ticket_pattern: &regex::Regex,
+    repo_names: &mut HashMap<String, String>,
+) -> Result<Vec<(CostKey, TokenUsage)>> {
+    let mut cwd: Option<String> = None;
+    let mut branch: Option<String> = None;
+    // Streamed replies repeat one message's usage on every content-block
+    // record, so keep one entry per message id.
+    let mut by_message: HashMap<String, (CostKey, TokenUsage)> = HashMap::new();
+    let mut seen = HashSet::new();
+    let mut index = 0usize;
+    for path in session.paths() {
+        let file =
+            open_transcript(path).with_context(|| format!("Failed to open {}", path.display()))?;
+        for line in jsonl_records(BufReader::new(file)) {
+            let Ok(value) = line? else {
+                continue;
+            };
+            let Ok(record) = Record::from_value(&value) else {
+                continue;
+            };
+            if !time_window.admits(record.head.timestamp.as_deref()) {
+                continue;
+            }
+            if let Some(uuid) = &record.head.uuid {
+                if !seen.insert(uuid.clone()) {
+                    continue;
+                }
+            }
+            if let Some(dir) = &record.head.cwd {
+                cwd = Some(dir.clone());
+            }
+            if let Some(name) = &record.head.git_branch {
+                branch = Some(name.clone()).filter(|b| !b.is_empty() && b != "HEAD");
+            }
+            let (RecordBody::Assistant(message), Some(usage)) =
+                (&record.body, record.message().and_then(|m| m.usage))
+            else {
+                continue;
+            };
+            index += 1;
+            let repo = match &cwd {
+                Some(dir) => repo_names
+                    .entry(dir.clone())
+                    .or_insert_with(|| repo_name(dir))
+                    .clone(),
+                None => UNATTRIBUTED.to_string(),
+            };
+            let key = CostKey {
+                repo,
+                ticket: branch
+                    .as_deref()
+                    .and_then(|b| ticket_id(ticket_pattern, b))
+                    .unwrap_or_else(|| UNATTRIBUTED.to_string()),
+                branch: branch.clone().unwrap_or_else(|| UNATTRIBUTED.to_string()),
+                project: session.project.clone(),
+                model: message
+                    .message
+                    .model
+                    .clone()
+                    .unwrap_or_else(|| "<unknown>".to_string()),
+            };
+            let id = message
+                .message
+                .id
+                .clone()
+                .unwrap_or_else(|| format!("#{index}"));
+            by_message.insert(id, (key, usage));
+        }
+    }
+    Ok(by_message.into_values().collect())
+}
+
+fn cmd_cost_report(
+    claude_dir: &Path,
+    time_window: &TimeWindow,
+    global: &GlobalArgs,
+    args: CostReportArgs,
+) -> Result<()> {
+    let ticket_pattern = regex::Regex::new(&args.ticket_pattern)
+        .with_context(|| format!("Invalid --ticket-pattern {}", args.ticket_pattern))?;
+    let pricing = load_pricing(args.pricing.as_deref())?;
+    let sessions = discover_sessions(claude_dir, time_window, global)?;
+    let mut repo_names = HashMap::new();
+    let mut rows: HashMap<Vec<String>, CostTally> = HashMap::new();
+    let mut unpriced: BTreeMap<String, u64> = BTreeMap::new();
+    let mut total = 0.0;
+    for session in &sessions {
+        for (key, usage) in session_costs(session, time_window, &ticket_pattern, &mut repo_names)? {
+            let columns = args
+                .group_by
+                .iter()
+                .map(|&group| key.column(group).to_string())
+                .collect::<Vec<_>>();
+            let tally = rows.entry(columns).or_default();
+            tally.sessions.insert(session.qualified_id());
+            tally.usage += usage;
+            match pricing.cost(&key.model, &usage) {
+                Some(usd) => {
+                    tally.usd += usd;
+                    total += usd;
+                }
+                None => *unpriced.entry(key.model.clone()).or_default() += usage.total(),
+            }
+        }
+    }
+    let mut rows = rows.into_iter().collect::<Vec<_>>();
+    rows.sort_by(|a, b| b.1.usd.total_cmp(&a.1.usd).then_with(|| a.0.cmp(&b.0)));
+    rows.truncate(args.top);
+
+    if global.json {
+        let rows = rows
+            .iter()
+            .map(|(columns, tally)| {
+                let mut row = serde_json::Map::new();
+                for (group, value) in args.group_by.iter().zip(columns) {
+                    row.insert(group.name().to_string(), json!(value));
+                }
+                row.insert("sessions".to_string(), json!(tally.sessions.len()));
+                row.insert("tokens".to_string(), json!(tally.usage.total()));
+                row.insert("usage".to_string(), json!(tally.usage));
+                row.insert("usd".to_string(), json!(round_usd(tally.usd)));
+                Value::Object(row)
+            })
+            .collect::<Vec<_>>();
+        return print_json(&json!({
+            "currency": "USD",
+            "group_by": args.group_by.iter().map(|g| g.name()).collect::<Vec<_>>(),
+            "total": round_usd(total),
+            "rows": rows,
+            "unpriced_models": unpriced.keys().collect::<Vec<_>>(),
+        }));
+    }
+
+    println!("{} ${total:.2}", style("Estimated cost (USD):").bold());
+    if rows.is_empty() {
+        println!("  {}", tr!("none"));
+        return Ok(());
+    }
+    let widths = args
+        .group_by
+        .iter()
+        .enumerate()
+        .map(|(i, group)| {
+            rows.iter()
+                .map(|(columns, _)| columns[i].chars().count())
+                .chain([group.name().len()])
+                .max()
+                .unwrap_or(0)
+                .min(40)
+        })
+        .collect::<Vec<_>>();
+    let mut header = String::from(" ");
+    for (group, width) in args.group_by.iter().zip(&widths) {
+        header.push_str(&format!(" {:<width$}", group.name()));
+    }
+    println!("{header} {:>8} {:>12} {:>10}", "sessions", "tokens", "usd");
+    for (columns, tally) in &rows {
+        let mut line = String::from(" ");
+        for (value, width) in columns.iter().zip(&widths) {
+            line.push_str(&format!(" {:<width$}", ellipsize(value, *width)));
+        }
+        println!(
+            "{line} {:>8} {:>12} {:>10.2}",
+            i18n::number(tally.sessions.len() as u64),
+            i18n::number(tally.usage.total()),
+            tally.usd
+        );
+    }
+    if !unpriced.is_empty() {
+        let listed = unpriced
+            .iter()
+            .map(|(model, tokens)| format!("{model} ({tokens} tokens)"))
+            .collect::<Vec<_>>();
+        println!("Unpriced models: {}", listed.join(", "));
+    }
+    Ok(())
+}
+
+/// The two periods of `stats --compare`: each `last-<N><unit>` (ending now),
+/// `<since>..<until>`, or `prior-<N><unit>` (ending where the other one
+/// starts), with units `h`, `d`, or `w`.
+#[derive(Debug, Clone)]
+struct ComparePeriods {
+    a: (String, PeriodSpec),
+    b: (String, PeriodSpec),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum PeriodSpec {
+    Last(chrono::Duration),
+    Prior(chrono::Duration),
+    Range(String, String),
+}
+
+/// A length like `36h`, `90d`, or `2w`; `raw` is the whole argument, for errors.
+fn parse_span(span: &str, raw: &str) -> Result<chrono::Duration> {
+    let unit_at = span.len().saturating_sub(1);
+    let amount = span
+        .get(..unit_at)
+        .and_then(|n| n.parse::<i64>().ok())
+        .filter(|&n| n > 0)
+        .ok_or_else(|| anyhow!("Invalid period length in {raw}"))?;
+    match &span[unit_at..] {
+        "h" => Ok(chrono::Duration::hours(amount)),
+        "d" => Ok(chrono::Duration::days(amount)),
+        "w" => Ok(chrono::Duration::weeks(amount)),
+        _ => bail!("Period {raw} needs a unit of h, d, or w"),
+    }
+}
+
+impl std::str::FromStr for PeriodSpec {
+    type Err = anyhow::Error;
+
+    fn from_str(raw: &str) -> Result<Self> {
+        if let Some((since, until)) = raw.split_once("..") {
+            return Ok(Self::Range(since.to_string(), until.to_string()));
+        }
+        if let Some(rest) = raw.strip_prefix("last-") {
+            Ok(Self::Last(parse_span(rest, raw)?))
+        } else if let Some(rest) = raw.strip_prefix("prior-") {
+            Ok(Self::Prior(parse_span(rest, raw)?))
+        } else {
+            bail!("Invalid period {raw}; use last-30d, prior-30d, or <since>..<until>")
+        }
+    }
+}
+
+impl std::str::FromStr for ComparePeriods {
+    type Err = anyhow::Error;
+
+    fn from_str(raw: &str) -> Result<Self> {
+        let (a, b) = raw.split_once(" vs ").ok_or_else(|| {
+            anyhow!("Expected two periods joined by \" vs \", e.g. \"last-30d vs prior-30d\"")
+        })?;
+        let (a, b) = (a.trim(), b.trim());
+        let periods = Self {
+            a: (a.to_string(), a.parse()?),
+            b: (b.to_string(), b.parse()?),
+        };
+        if matches!(
+            (&periods.a.1, &periods.b.1),
+            (PeriodSpec::Prior(_), PeriodSpec::Prior(_))
+        ) {
+            bail!("At most one period can be prior-…; it is counted back from the other one");
+        }
+        Ok(periods)
+    }
+}
+
+impl ComparePeriods {
+    /// Both periods as time windows compared against `by`.
+    fn windows(&self, now: DateTime<Local>, by: WindowBy) -> Result<(TimeWindow, TimeWindow)> {
+        let fixed = |spec: &PeriodSpec| -> Result<Option<TimeWindow>> {
+            let (since, until) = match spec {
+                PeriodSpec::Last(span) => {
+                    (now.with_timezone(&Utc) - *span, now.with_timezone(&Utc))
+                }
+                PeriodSpec::Range(since, until) => (
+                    parse_time_bound("--compare", since, now)?,
+                    parse_time_bound("--compare", until, now)?,
+                ),
+                PeriodSpec::Prior(_) => return Ok(None),
+            };
+            if since > until {
+                bail!("Period {} ends before it starts", since.to_rfc3339());
+            }
+            Ok(Some(TimeWindow {
+                since: Some(since),
+                until: Some(until),
+                by,
+            }))
+        };
+        let prior = |spec: &PeriodSpec, other: &TimeWindow| {
+            let PeriodSpec::Prior(span) = spec else {
+                unreachable!("fixed periods resolve on their own");
+            };
+            let until = other.since.expect("fixed periods have a start");
+            TimeWindow {
+                since: Some(until - *span),
+                until: Some(until),
+                by,
+            }
+        };
+        match (fixed(&self.a.1)?, fixed(&self.b.1)?) {
+            (Some(a), Some(b)) => Ok((a, b)),
+            (Some(a), None) => {
+                let b = prior(&self.b.1, &a);
+                Ok((a, b))
+            }
+            (None, Some(b)) => Ok((prior(&self.a.1, &b), b)),
+            (None, None) => unreachable!("rejected when parsed"),
+        }
+    }
+}
+
+/// The headline `stats` numbers for one period of `stats --compare`.
+#[derive(Debug, Clone, Default, Serialize)]
+struct PeriodStats {
+    sessions: u64,
+    records: u64,
+    prompts: u64,
+    usage: TokenUsage,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cost_usd: Option<f64>,
+    /// Share of tokens per model family (opus, sonnet, haiku, or the model name).
+    model_shares: BTreeMap<String, f64>,
+}
+
+impl PeriodStats {
+    fn collect(
+        claude_dir: &Path,
+        window: &TimeWindow,
+        global: &GlobalArgs,
+        pricing: Option<&Pricing>,
+    ) -> Result<Self> {
+        let sessions = discover_sessions(claude_dir, window, global)?;
+        let per_file = sessions
+            .par_iter()
+            .map(|session| FileStats::scan(session, window))
+            .collect::<Result<Vec<_>>>()?;
+        let mut stats = Self {
+            sessions: sessions.len() as u64,
+            ..Self::default()
+        };
+        let mut spend = SpendTotals::default();
+        let mut family_tokens: BTreeMap<String, u64> = BTreeMap::new();
+        for (session, file) in sessions.iter().zip(per_file) {
+            stats.records += file.records;
+            stats.prompts += file.pastes.prompts;
+            for (model, usage) in &file.usage {
+                if let Some(pricing) = pricing {
+                    spend.add(pricing, session, model, usage);
+                }
+                stats.usage += *usage;
+                *family_tokens.entry(model_family(model)).or_default() += usage.total();
+            }
+        }
+        let total = stats.usage.total();
+        if total > 0 {
+            stats.model_shares = family_tokens
+                .into_iter()
+                .map(|(family, tokens)| (family, tokens as f64 / total as f64))
+                .collect();
+        }
+        stats.cost_usd = pricing.is_some().then(|| round_usd(spend.total));
+        Ok(stats)
+    }
+
+    /// `(metric, value here, value in other, change)` rows, shares last.
+    fn compare(&self, other: &Self) -> Vec<(String, String, String, String)> {
+        let count = |name: &str, a: u64, b: u64| {
+            (
+                name.to_string(),
+                i18n::number(a),
+                i18n::number(b),
+                percent_change(a as f64, b as f64),
+            )
+        };
+        let mut rows = vec![
+            count("sessions", self.sessions, other.sessions),
+            count("prompts", self.prompts, other.prompts),
+            count("records", self.records, other.records),
+            count("tokens", self.usage.total(), other.usage.total()),
+            count(
+                "output tokens",
+                self.usage.output_tokens,
+                other.usage.output_tokens,
+            ),
+        ];
+        if let (Some(a), Some(b)) = (self.cost_usd, other.cost_usd) {
+            rows.push((
+                "cost (USD)".to_string(),
+                format!("{a:.2}"),
+                format!("{b:.2}"),
+                percent_change(a, b),
+            ));
+        }
+        let families = self
+            .model_shares
+            .keys()
+            .chain(other.model_shares.keys())
+            .collect::<std::collections::BTreeSet<_>>();
+        for family in families {
+            let a = self.model_shares.get(family).copied().unwrap_or(0.0);
+            let b = other.model_shares.get(family).copied().unwrap_or(0.0);
+            rows.push((
+                format!("{family} share"),
+                format!("{:.0}%", a * 100.0),
+                format!("{:.0}%", b * 100.0),
+                format!("{:+.0}pp", (a - b) * 100.0),
+            ));
+        }
+        rows
+    }
+}
+
+/// How much `now` grew or shrank against `before`, e.g. `+34%`.
+fn percent_change(now: f64, before: f64) -> String {
+    if before == 0.0 {
+        return if now == 0.0 { "0%" } else { "new" }.to_string();
+    }
+    format!("{:+.0}%", (now - before) / before * 100.0)
+}
+
+fn cmd_stats_compare(
+    claude_dir: &Path,
+    global: &GlobalArgs,
+    compare: &ComparePeriods,
+    pricing: Option<&Pricing>,
+) -> Result<()> {
+    let (window_a, window_b) = compare.windows(Local::now(), global.window_by)?;
+    let a = PeriodStats::collect(claude_dir, &window_a, global, pricing)?;
+    let b = PeriodStats::collect(claude_dir, &window_b, global, pricing)?;
+    let rows = a.compare(&b);
+    let bound =
+        |at: Option<DateTime<Utc>>| at.map(|at| at.to_rfc3339_opts(SecondsFormat::Secs, true));
+
+    if global.json {
+        let period =
+            |(label, _): &(String, PeriodSpec), window: &TimeWindow, stats: &PeriodStats| {
+                json!({
+                    "label": label,
+                    "since": bound(window.since),
+                    "until": bound(window.until),
+                    "stats": stats,
+                })
+            };
+        let changes = rows
+            .iter()
+            .map(|(metric, _, _, change)| json!({ "metric": metric, "change": change }))
+            .collect::<Vec<_>>();
+        return print_json(&json!({
+            "periods": [
+                period(&compare.a, &window_a, &a),
+                period(&compare.b, &window_b, &b),
+            ],
+            "changes": changes,
+        }));
+    }
+
+    println!("{}", palette().heading.apply_to(tr!("stats-compare")));
+    for ((label, _), window) in [(&compare.a, &window_a), (&compare.b, &window_b)] {
+        println!(
+            "  {label}: {} .. {}",
+            i18n::datetime(&bound(window.since).unwrap_or_default()),
+            i18n::datetime(&bound(window.until).unwrap_or_default())
+        );
+    }
+    println!();
+    let (label_a, label_b) = (&compare.a.0, &compare.b.0);
+    let width_a = label_a.len().max(12);
+    let width_b = label_b.len().max(12);
+    println!(
+        "  {:<16} {:>width_a$} {:>width_b$} {:>8}",
+        "metric", label_a, label_b, "change"
+    );
+    for (metric, value_a, value_b, change) in rows {
+        println!("  {metric:<16} {value_a:>width_a$} {value_b:>width_b$} {change:>8}");
+    }
+    Ok(())
+}
+
+/// Highest interruption rate first; models and projects without answered
+/// prompts are left out.
+fn top_n_interruptions(
+    map: &HashMap<String, InterruptionTally>,
+    n: usize,
+) -> Vec<(String, InterruptionTally)> {
+    let mut vec = map
+        .iter()
+        .filter(|(_, t)| t.turns > 0)
+        .map(|(k, v)| (k.clone(), *v))
+        .collect::<Vec<_>>();
+    vec.sort_by(|a, b| {
+        b.1.rate()
+            .total_cmp(&a.1.rate())
+            .then_with(|| b.1.turns.cmp(&a.1.turns))
+            .then_with(|| a.0.cmp(&b.0))
+    });
+    vec.into_iter().take(n).collect()
+}
+
+/// Pasted blocks in one transcript's prompts, for `stats --pastes`.
+#[derive(Default)]
+struct PasteScan {
+    prompts: u64,
+    prompt_bytes: u64,
+    prompts_with_pastes: u64,
+    pastes: Vec<PasteSighting>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct PasteSighting {
+    kind: PasteKind,
+    lines: usize,
+    bytes: usize,
+    timestamp: Option<String>,
+}
+
+impl PasteScan {
+    fn observe(&mut self, record: &Record) {
+        let (RecordBody::User(user), false) = (&record.body, record.is_meta()) else {
+            return;
+        };
+        let text = user.message.content.text(false);
+        let text = text.trim();
+        if text.is_empty() || text.starts_with('<') || text.starts_with("[Request interrupted") {
+            return;
+        }
+        self.prompts += 1;
+        self.prompt_bytes += text.len() as u64;
+        let blocks = detect_pastes(text);
+        if !blocks.is_empty() {
+            self.prompts_with_pastes += 1;
+        }
+        self.pastes.extend(blocks.iter().map(|block| PasteSighting {
+            kind: block.kind,
+            lines: block.lines,
+            bytes: block.bytes(),
+            timestamp: record.head.timestamp.clone(),
+        }));
+    }
+}
+
+/// `stats --pastes` totals, and the largest pastes with their sessions.
+#[derive(Default)]
+struct PasteTotals {
+    prompts: u64,
+    prompt_bytes: u64,
+    prompts_with_pastes: u64,
+    by_kind: BTreeMap<PasteKind, (u64, u64)>,
+    largest: Vec<(String, String, PasteSighting)>,
+}
+
+impl PasteTotals {
+    fn add(&mut self, session: &Session, scan: PasteScan) {
+        self.prompts += scan.prompts;
+        self.prompt_bytes += scan.prompt_bytes;
+        self.prompts_with_pastes += scan.prompts_with_pastes;
+        for paste in scan.pastes {
+            let (count, bytes) = self.by_kind.entry(paste.kind).or_default();
+            *count += 1;
+            *bytes += paste.bytes as u64;
+            self.largest
+                .push((session.id.clone(), session.project.clone(), paste));
+        }
+    }
+
+    fn pasted_bytes(&self) -> u64 {
+        self.by_kind.values().map(|(_, bytes)| bytes).sum()
+    }
+
+    fn top(&self, top: usize) -> Vec<&(String, String, PasteSighting)> {
+        let mut largest = self.largest.iter().collect::<Vec<_>>();
+        largest.sort_by(|a, b| b.2.bytes.cmp(&a.2.bytes).then_with(|| a.0.cmp(&b.0)));
+        largest.truncate(top);
+        largest
+    }
+
+    fn to_json(&self, top: usize) -> Value {
+        json!({
+            "prompts": self.prompts,
+            "prompts_with_pastes": self.prompts_with_pastes,
+            "prompt_bytes": self.prompt_bytes,
+            "pasted_bytes": self.pasted_bytes(),
+            "by_kind": self.by_kind.iter().map(|(kind, (count, bytes))| json!({
+                "kind": kind,
+                "count": count,
+                "bytes": bytes,
+            })).collect::<Vec<_>>(),
+            "largest": self.top(top).into_iter().map(|(session_id, project, paste)| json!({
+                "session_id": session_id,
+                "project": project,
+                "kind": paste.kind,
+                "lines": paste.lines,
+                "bytes": paste.bytes,
+                "timestamp": paste.timestamp,
+            })).collect::<Vec<_>>(),
+        })
+    }
+
+    fn print(&self, top: usize) {
+        let percent = |part: u64, whole: u64| {
+            if whole == 0 {
+                0.0
+            } else {
+                part as f64 * 100.0 / whole as f64
+            }
+        };
+        println!("{}", style("Pasted content in prompts").bold());
+        println!(
+            "  Prompts with pastes: {} of {} ({:.1}%)",
+            self.prompts_with_pastes,
+            self.prompts,
+            percent(self.prompts_with_pastes, self.prompts)
+        );
+        println!(
+            "  Pasted bytes: {} of {} prompt bytes ({:.1}%)",
+            self.pasted_bytes(),
+            self.prompt_bytes,
+            percent(self.pasted_bytes(), self.prompt_bytes)
+        );
+        if self.by_kind.is_empty() {
+            return;
+        }
+        println!("  {:>7} {:>10}  kind", "count", "bytes");
+        for (kind, (count, bytes)) in &self.by_kind {
+            println!("  {count:>7} {bytes:>10}  {}", kind.as_str());
+        }
+        println!();
+        println!("{}", style("Largest pastes").bold());
+        for (session_id, project, paste) in self.top(top) {
+            println!(
+                "  {:>10} {:>6} lines  {:<11}  {} ({project}) {}",
+                paste.bytes,
+                paste.lines,
+                paste.kind.as_str(),
+                short_id(session_id),
+                paste.timestamp.as_deref().unwrap_or("-")
+            );
+        }
+    }
+}
+
+/// Calls of one tool, for `stats --tools`.
+#[derive(Debug, Default, Clone, Copy)]
+struct ToolTally {
+    calls: u64,
+    /// Results seen; a call cut off by an interrupt has none.
+    results: u64,
+    errors: u64,
+    output_bytes: u64,
+}
+
+impl ToolTally {
+    fn add(&mut self, other: Self) {
+        self.calls += other.calls;
+        self.results += other.results;
+        self.errors += other.errors;
+        self.output_bytes += other.output_bytes;
+    }
+
+    /// Share of results flagged `is_error`.
+    fn error_rate(&self) -> f64 {
+        if self.results == 0 {
+            0.0
+        } else {
+            self.errors as f64 / self.results as f64
+        }
+    }
+
+    fn avg_output_bytes(&self) -> u64 {
+        self.output_bytes.checked_div(self.results).unwrap_or(0)
+    }
+}
+
+/// Walks one transcript's records for `stats --tools`, pairing each result
+/// with its call by `tool_use_id`.
+#[derive(Default)]
+struct ToolScan {
+    by_tool: HashMap<String, ToolTally>,
+    names: HashMap<String, String>,
+    commands: HashMap<String, u64>,
+}
+
+impl ToolScan {
+    fn observe(&mut self, record: &Record) {
+        for call in tool_calls(record) {
+            self.by_tool.entry(call.name.to_string()).or_default().calls += 1;
+            if let Some(id) = call.id {
+                self.names.insert(id.to_string(), call.name.to_string());
+            }
+            let command = call
+                .input
+                .and_then(|input| input.get("command"))
+                .and_then(Value::as_str);
+            if let (Some(command), "Bash") = (command, call.name) {
+                for name in command_names(command) {
+                    *self.commands.entry(name).or_default() += 1;
+                }
+            }
+        }
+        for result in tool_results(record) {
+            let name = result
+                .tool_use_id
+                .and_then(|id| self.names.get(id))
+                .map_or("<unknown>", String::as_str);
+            let tally = self.by_tool.entry(name.to_string()).or_default();
+            tally.results += 1;
+            tally.errors += u64::from(result.is_error);
+            tally.output_bytes += result.output_bytes as u64;
+        }
+    }
+}
+
+/// `stats --tools` totals over the corpus.
+#[derive(Default)]
+struct ToolTotals {
+    by_tool: HashMap<String, ToolTally>,
+    commands: HashMap<String, u64>,
+}
+
+impl ToolTotals {
+    fn add(&mut self, scan: ToolScan) {
+        for (name, tally) in scan.by_tool {
+            self.by_tool.entry(name).or_default().add(tally);
+        }
+        merge_counts(&mut self.commands, scan.commands);
+    }
+
+    /// Most called first.
+    fn ranked(&self, top: usize) -> Vec<(&String, &ToolTally)> {
+        let mut rows = self.by_tool.iter().collect::<Vec<_>>();
+        rows.sort_by(|a, b| b.1.calls.cmp(&a.1.calls).then_with(|| a.0.cmp(b.0)));
+        rows.truncate(top);
+        rows
+    }
+
+    fn to_json(&self, top: usize) -> Value {
+        json!({
+            "by_tool": self.ranked(top).into_iter().map(|(tool, tally)| json!({
+                "tool": tool,
+                "calls": tally.calls,
+                "results": tally.results,
+                "errors": tally.errors,
+                "error_rate": tally.error_rate(),
+                "avg_output_bytes": tally.avg_output_bytes(),
+            })).collect::<Vec<_>>(),
+            "bash_commands": top_n_sorted_map(self.commands.clone(), top)
+                .into_iter()
+                .map(|(command, count)| json!({"command": command, "count": count}))
+                .collect::<Vec<_>>(),
+        })
+    }
+
+    fn print(&self, top: usize) {
+        println!("{}", style(tr!("stats-tools")).bold());
+        let rows = self.ranked(top);
+        if rows.is_empty() {
+            println!("  {}", tr!("none"));
+        } else {
+            println!(
+                "  {:>8} {:>8} {:>7} {:>11}  tool",
+                "calls", "errors", "error%", "avg_output"
+            );
+            for (tool, tally) in rows {
+                println!(
+                    "  {:>8} {:>8} {:>7} {:>11}  {}",
+                    i18n::number(tally.calls),
+                    i18n::number(tally.errors),
+                    i18n::decimal(tally.error_rate() * 100.0, 1),
+                    i18n::number(tally.avg_output_bytes()),
+                    tool
+                );
+            }
+        }
+        println!();
+        print_ranked_map(
+            &tr!("stats-bash-commands"),
+            &top_n_sorted_map(self.commands.clone(), top),
+        );
+    }
+}
+
+/// Calls to one model on one day, for `stats --errors`.
+#[derive(Debug, Default, Clone, Copy)]
+struct ApiCallTally {
+    replies: u64,
+    retried: u64,
+    gave_up: u64,
+}
+
+impl ApiCallTally {
+    fn add(&mut self, other: Self) {
+        self.replies += other.replies;
+        self.retried += other.retried;
+        self.gave_up += other.gave_up;
+    }
+
+    fn errors(&self) -> u64 {
+        self.retried + self.gave_up
+    }
+
+    /// Share of calls that failed: a retried call counts once per failed
+    /// attempt, and the attempt that succeeded is the reply.
+    fn error_rate(&self) -> f64 {
+        let calls = self.replies + self.errors();
+        if calls == 0 {
+            0.0
+        } else {
+            self.errors() as f64 / calls as f64
+        }
+    }
+
+    fn count(&mut self, error: &ApiError) {
+        match error.retry {
+            Some(_) => self.retried += 1,
+            None => self.gave_up += 1,
+        }
+    }
+}
+
+/// Walks one transcript's records for `stats --errors` and `sessions show
+/// --ops`. A failed call names no model, so it is charged to the model of
+/// the last reply before it.
+#[derive(Default)]
+struct ApiErrorScan {
+    /// `(model, error)` in transcript order.
+    errors: Vec<(String, ApiError)>,
+    by_day: HashMap<(String, String), ApiCallTally>,
+    message_ids: HashSet<String>,
+    model: Option<String>,
+    first: Option<DateTime<Utc>>,
+    last: Option<DateTime<Utc>>,
+}
+
+impl ApiErrorScan {
+    fn read(session: &Session, time_window: &TimeWindow) -> Result<Self> {
+        let mut scan = Self::default();
+        for path in session.paths() {
+            let f = open_transcript(path)?;
+            for line in jsonl_records(BufReader::new(f)) {
+                let Ok(Ok(record)) = line?.map(|value| Record::from_value(&value)) else {
+                    continue;
+                };
+                if time_window.admits(record.head.timestamp.as_deref()) {
+                    scan.observe(&record);
+                }
+            }
+        }
+        Ok(scan)
+    }
+
+    fn observe(&mut self, record: &Record) {
+        let timestamp = record.head.timestamp.as_deref();
+        if let Some(at) = timestamp.and_then(|ts| DateTime::parse_from_rfc3339(ts).ok()) {
+            let at = at.with_timezone(&Utc);
+            self.first = Some(self.first.map_or(at, |first| first.min(at)));
+            self.last = Some(self.last.map_or(at, |last| last.max(at)));
+        }
+        let day = timestamp.and_then(|ts| ts.get(..10)).unwrap_or("-");
+        if let Some(error) = ApiError::from_record(record) {
+            let model = self.model.as_deref().unwrap_or("<unknown>").to_string();
+            self.by_day
+                .entry((day.to_string(), model.clone()))
+                .or_default()
+                .count(&error);
+            self.errors.push((model, error));
+            return;
+        }
+        let RecordBody::Assistant(reply) = &record.body else {
+            return;
+        };
+        let Some(model) = reply.message.model.as_ref().filter(|m| *m != "<synthetic>") else {
+            return;
+        };
+        self.model = Some(model.clone());
+        // Streamed replies repeat the message id on every content-block record.
+        let new = match &reply.message.id {
+            Some(id) => self.message_ids.insert(id.clone()),
+            None => true,
+        };
+        if new {
+            self.by_day
+                .entry((day.to_string(), model.clone()))
+                .or_default()
+                .replies += 1;
+        }
+    }
+
+    fn retry_wait_ms(&self) -> u64 {
+        self.errors.iter().map(|(_, error)| error.wait_ms()).sum()
+    }
+
+    /// Wall time from the first record to the last.
+    fn span_ms(&self) -> u64 {
+        self.first.zip(self.last).map_or(0, |(first, last)| {
+            (last - first).num_milliseconds().max(0) as u64
+        })
+    }
+
+    fn to_json(&self) -> Value {
+        let errors = self
+            .errors
+            .iter()
+            .map(|(model, error)| {
+                let mut row = json!(error);
+                row["model"] = json!(model);
+                row
+            })
+            .collect::<Vec<_>>();
+        json!({
+            "retried": self.errors.iter().filter(|(_, e)| e.retry.is_some()).count(),
+            "gave_up": self.errors.iter().filter(|(_, e)| e.retry.is_none()).count(),
+            "retry_wait_ms": self.retry_wait_ms(),
+            "session_ms": self.span_ms(),
+            "errors": errors,
+        })
+    }
+
+    /// The session's failed calls, one per line, for `sessions show --ops`.
+    fn print(&self) {
+        println!("{}", style(tr!("stats-api-errors")).bold());
+        if self.errors.is_empty() {
+            println!("  {}", tr!("none"));
+            return;
+        }
+        for (model, error) in &self.errors {
+            let outcome = match &error.retry {
+                Some(retry) => {
+                    let attempt = match (retry.attempt, retry.max_retries) {
+                        (Some(n), Some(max)) => format!("retry {n}/{max}"),
+                        (Some(n), None) => format!("retry {n}"),
+                        _ => "retry".to_string(),
+                    };
+                    match retry.wait_ms {
+                        Some(ms) => {
+                            format!("{attempt} in {}s", i18n::decimal(ms as f64 / 1000.0, 1))
+                        }
+                        None => attempt,
+                    }
+                }
+                None => "gave up".to_string(),
+            };
+            let status = error.status.map(|s| format!(" {s}")).unwrap_or_default();
+            println!(
+                "  {} {}{} {} [{}] {}",
+                palette()
+                    .meta
+                    .apply_to(error.timestamp.as_deref().unwrap_or("-")),
+                palette().warn.apply_to(error.kind.as_str()),
+                status,
+                outcome,
+                model,
+                error.message
+            );
+        }
+        println!(
+            "  Waiting on retries: {} of {}",
+            format_age((self.retry_wait_ms() / 1000) as i64),
+            format_age((self.span_ms() / 1000) as i64)
+        );
+    }
+}
+
+/// `stats --errors` totals over the corpus.
+#[derive(Default)]
+struct ApiErrorTotals {
+    by_kind: BTreeMap<ApiErrorKind, ApiCallTally>,
+    by_day: BTreeMap<(String, String), ApiCallTally>,
+    sessions_hit: u64,
+    retry_wait_ms: u64,
+    session_ms: u64,
+}
+
+impl ApiErrorTotals {
+    fn add(&mut self, scan: ApiErrorScan) {
+        self.sessions_hit += u64::from(!scan.errors.is_empty());
+        self.retry_wait_ms += scan.retry_wait_ms();
+        self.session_ms += scan.span_ms();
+        for (_, error) in &scan.errors {
+            self.by_kind.entry(error.kind).or_default().count(error);
+        }
+        for (key, tally) in scan.by_day {
+            self.by_day.entry(key).or_default().add(tally);
+        }
+    }
+
+    fn total(&self) -> ApiCallTally {
+        let mut total = ApiCallTally::default();
+        self.by_kind.values().for_each(|tally| total.add(*tally));
+        total
+    }
+
+    fn retry_wait_share(&self) -> f64 {
+        if self.session_ms == 0 {
+            0.0
+        } else {
+            self.retry_wait_ms as f64 / self.session_ms as f64
+        }
+    }
+
+    /// Days with errors, newest first.
+    fn error_days(&self, top: usize) -> Vec<(&(String, String), &ApiCallTally)> {
+        let mut rows = self
+            .by_day
+            .iter()
+            .filter(|(_, tally)| tally.errors() > 0)
+            .collect::<Vec<_>>();
+        rows.sort_by(|a, b| b.0 .0.cmp(&a.0 .0).then_with(|| a.0 .1.cmp(&b.0 .1)));
+        rows.truncate(top);
+        rows
+    }
+
+    fn to_json(&self) -> Value {
+        let total = self.total();
+        json!({
+            "retried": total.retried,
+            "gave_up": total.gave_up,
+            "sessions_hit": self.sessions_hit,
+            "retry_wait_ms": self.retry_wait_ms,
+            "session_ms": self.session_ms,
+            "retry_wait_share": self.retry_wait_share(),
+            "by_kind": self.by_kind.iter().map(|(kind, tally)| json!({
+                "kind": kind,
+                "retried": tally.retried,
+                "gave_up": tally.gave_up,
+            })).collect::<Vec<_>>(),
+            "by_day": self.by_day.iter().map(|((day, model), tally)| json!({
+                "day": day,
+                "model": model,
+                "replies": tally.replies,
+                "errors": tally.errors(),
+                "error_rate": tally.error_rate(),
+            })).collect::<Vec<_>>(),
+        })
+    }
+
+    fn print(&self, top: usize) {
+        println!("{}", style(tr!("stats-api-errors")).bold());
+        let total = self.total();
+        if total.errors() == 0 {
+            println!("  {}", tr!("none"));
+            return;
+        }
+        println!(
+            "  Retried: {}  Gave up: {}  Sessions hit: {}",
+            i18n::number(total.retried),
+            i18n::number(total.gave_up),
+            i18n::number(self.sessions_hit)
+        );
+        println!(
+            "  Waiting on retries: {} of {} in sessions ({}%)",
+            format_age((self.retry_wait_ms / 1000) as i64),
+            format_age((self.session_ms / 1000) as i64),
+            i18n::decimal(self.retry_wait_share() * 100.0, 2)
+        );
+        println!();
+        println!("  {:>8} {:>8}  kind", "retried", "gave_up");
+        for (kind, tally) in &self.by_kind {
+            println!(
+                "  {:>8} {:>8}  {}",
+                i18n::number(tally.retried),
+                i18n::number(tally.gave_up),
+                kind.as_str()
+            );
+        }
+        println!();
+        println!(
+            "  {:<10} {:>8} {:>8} {:>7}  model",
+            "day", "replies", "errors", "error%"
+        );
+        for ((day, model), tally) in self.error_days(top) {
+            println!(
+                "  {:<10} {:>8} {:>8} {:>7}  {}",
+                day,
+                i18n::number(tally.replies),
+                i18n::number(tally.errors()),
+                i18n::decimal(tally.error_rate() * 100.0, 1),
+                model
+            );
+        }
+    }
+}
+
+/// How sessions ended, for `stats`. See [`session_end`].
+#[derive(Default, Serialize)]
+struct SessionEndings {
+    completed: u64,
+    error: u64,
+    interrupt: u64,
+    limit: u64,
+}
+
+impl SessionEndings {
+    fn add(&mut self, ended_by: Option<SessionEnd>) {
+        *match ended_by {
+            None => &mut self.completed,
+            Some(SessionEnd::Error) => &mut self.error,
+            Some(SessionEnd::Interrupt) => &mut self.interrupt,
+            Some(SessionEnd::Limit) => &mut self.limit,
+        } += 1;
+    }
+
+    fn print(&self) {
+        println!("{}", style(tr!("stats-endings")).bold());
+        for (label, count) in [
+            ("completed", self.completed),
+            ("error", self.error),
+            ("interrupt", self.interrupt),
+            ("limit", self.limit),
+        ] {
+            println!("  {count:>7}  {label}");
+        }
+    }
+}
+
+fn merge_counts(into: &mut HashMap<String, u64>, from: HashMap<String, u64>) {
+    for (key, count) in from {
+        *into.entry(key).or_insert(0) += count;
+    }
+}
+
+/// Built-in prices, with any entries from the `--pricing` TOML file on top.
+fn load_pricing(path: Option<&Path>) -> Result<Pricing> {
+    let mut pricing = Pricing::default();
+    if let Some(path) = path {
+        let raw = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read pricing file {}", path.display()))?;
+        let overrides: Pricing = toml::from_str(&raw)
+            .with_context(|| format!("Invalid pricing file {}", path.display()))?;
+        pricing.merge(overrides);
+    }
+    Ok(pricing)
+}
+
+#[derive(Serialize)]
+struct ModelUsage<'a> {
+    model: &'a str,
+    #[serde(flatten)]
+    usage: &'a TokenUsage,
+}
+
+/// Estimated USD spend for `stats --pricing`.
+#[derive(Default)]
+struct SpendTotals {
+    total: f64,
+    by_model: HashMap<String, f64>,
+    by_project: HashMap<String, f64>,
+    by_session: HashMap<String, f64>,
+    unpriced_models: BTreeMap<String, u64>,
+}
+
+impl SpendTotals {
+    fn add(&mut self, pricing: &Pricing, session: &Session, model: &str, usage: &TokenUsage) {
+        let Some(cost) = pricing.cost(model, usage) else {
+            *self.unpriced_models.entry(model.to_string()).or_default() += usage.total();
+            return;
+        };
+        self.total += cost;
+        *self.by_model.entry(model.to_string()).or_default() += cost;
+        *self.by_project.entry(session.project.clone()).or_default() += cost;
+        *self.by_session.entry(session.qualified_id()).or_default() += cost;
+    }
+
+    fn to_json(&self, top: usize) -> Value {
+        let ranked = |map: &HashMap<String, f64>| {
+            top_n_costs(map, top)
+                .into_iter()
+                .map(|(name, usd)| json!({ "name": name, "usd": round_usd(usd) }))
+                .collect::<Vec<_>>()
+        };
+        json!({
+            "currency": "USD",
+            "total": round_usd(self.total),
+            "by_model": ranked(&self.by_model),
+            "by_project": ranked(&self.by_project),
+            "by_session": ranked(&self.by_session),
+            "unpriced_models": self.unpriced_models.keys().collect::<Vec<_>>(),
+        })
+    }
+
+    fn print(&self, top: usize) {
+        println!(
+            "{} ${:.4}",
+            style("Estimated cost (USD):").bold(),
+            self.total
+        );
+        for (title, map) in [
+            ("By model", &self.by_model),
+            ("By project", &self.by_project),
+            ("By session", &self.by_session),
+        ] {
+            println!("{}", style(title).bold());
+            for (name, usd) in top_n_costs(map, top) {
+                println!("  {:>10}  {}", format!("${usd:.4}"), name);
+            }
+        }
+        if !self.unpriced_models.is_empty() {
+            let listed = self
+                .unpriced_models
+                .iter()
+                .map(|(model, tokens)| format!("{model} ({tokens} tokens)"))
+                .collect::<Vec<_>>();
+            println!("Unpriced models: {}", listed.join(", "));
+        }
+    }
+}
+
+fn top_n_costs(map: &HashMap<String, f64>, n: usize) -> Vec<(String, f64)> {
+    let mut vec = map.iter().map(|(k, v)| (k.clone(), *v)).collect::<Vec<_>>();
+    vec.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    vec.into_iter().take(n).collect()
 }
 
-fn cmd_completions(args: CompletionsArgs) -> Result<()> {
-    let mut cmd = Cli::command();
-    let bin_name = cmd.get_name().to_string();
-    generate(args.shell, &mut cmd, bin_name, &mut std::io::stdout());
-    Ok(())
+fn round_usd(usd: f64) -> f64 {
+    (usd * 1_000_000.0).round() / 1_000_000.0
 }
 
-fn cmd_sessions_list(
+fn cmd_doctor(
     claude_dir: &Path,
     time_window: &TimeWindow,
     global: &GlobalArgs,
-    args: SessionsListArgs,
+    args: DoctorArgs,
 ) -> Result<()> {
-    let mut sessions = discover_sessions(claude_dir, time_window)?;
-    if let Some(project_filter) = args.project {
-        let project_filter = project_filter.to_lowercase();
-        sessions.retain(|s| {
-            s.project.to_lowercase().contains(&project_filter)
-                || s.path
-                    .to_string_lossy()
-                    .to_lowercase()
-                    .contains(&project_filter)
-        });
+    let mut checks = Vec::new();
+    checks.push(check_path_exists("claude_dir_exists", claude_dir));
+    checks.push(check_path_readable("claude_dir_readable", claude_dir));
+
+    let discovery =
+        discover_with(claude_dir, time_window, &walk_limits(global)).unwrap_or_default();
+    let sessions = discovery.sessions;
+    checks.push(CheckResult::new(
+        "jsonl_files_found",
+        !sessions.is_empty(),
+        format!("found {}", sessions.len()),
+    ));
+    checks.push(if discovery.skipped.is_empty() {
+        CheckResult::new("unreadable_files", true, "none")
+    } else {
+        let listed = discovery
+            .skipped
+            .iter()
+            .map(|s| format!("{} ({})", s.path.display(), s.error))
+            .collect::<Vec<_>>();
+        CheckResult::warn("unreadable_files", false, listed.join(", "))
+    });
+
+    let mut by_id: BTreeMap<&str, Vec<&Session>> = BTreeMap::new();
+    for session in &sessions {
+        by_id.entry(&session.id).or_default().push(session);
+    }
+    let duplicated = by_id
+        .values()
+        .filter(|group| group.len() > 1)
+        .flatten()
+        .map(|s| s.qualified_id())
+        .collect::<Vec<_>>();
+    checks.push(if duplicated.is_empty() {
+        CheckResult::new("duplicate_session_ids", true, "none")
+    } else {
+        CheckResult::warn("duplicate_session_ids", false, duplicated.join(", "))
+    });
+
+    let sample = sessions.iter().take(args.sample_files).collect::<Vec<_>>();
+    let mut sample_parse_errors = 0u64;
+    let mut sample_records = 0u64;
+    let mut inventory = SchemaInventory::default();
+    for session in sample {
+        let f = open_transcript(&session.path)
+            .with_context(|| format!("Failed to open {}", session.path.display()))?;
+        let reader = BufReader::new(f);
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            sample_records += 1;
+            match serde_json::from_str::<Value>(&line) {
+                Ok(record) => inventory.observe(&record),
+                Err(_) => sample_parse_errors += 1,
+            }
+        }
+    }
+    checks.push(CheckResult::new(
+        "sample_parse",
+        sample_records > 0 && sample_parse_errors == 0,
+        format!(
+            "records={} parse_errors={}",
+            sample_records, sample_parse_errors
+        ),
+    ));
+    if inventory.records > 0 {
+        checks.push(check_schema_drift(
+            &SchemaBaseline::bundled().drift(&inventory),
+        ));
     }
 
-    let sessions = sessions.into_iter().take(args.limit).collect::<Vec<_>>();
-    let mut summaries = Vec::with_capacity(sessions.len());
-    for session in sessions {
-        let summary = summarize_session(&session, args.with_preview)?;
-        summaries.push(summary);
+    let writable = ensure_output_dir_writable(&args.output).is_ok();
+    checks.push(CheckResult::new(
+        "output_dir_writable",
+        writable,
+        args.output.display().to_string(),
+    ));
+
+    if args.deep {
+        let mut files = Vec::with_capacity(sessions.len());
+        let progress = Progress::json_only(global, "doctor", "files_done", sessions.len());
+        for session in &sessions {
+            let health = scan_session_health(&session.path)
+                .with_context(|| format!("Failed to scan {}", session.path.display()))?;
+            files.push(DeepScanFile {
+                path: session.path.clone(),
+                project: session.project.clone(),
+                size_bytes: session.size_bytes,
+                health,
+            });
+            progress.inc(&session.id_short);
+        }
+        progress.finish();
+        let flagged = files.iter().filter(|f| !f.health.flags.is_empty()).count();
+        let details = if writable {
+            let report = write_deep_scan_report(&args.output, &files, args.report_format)?;
+            format!(
+                "files={} flagged={} report={}",
+                files.len(),
+                flagged,
+                report.display()
+            )
+        } else {
+            format!("files={} flagged={}", files.len(), flagged)
+        };
+        checks.push(CheckResult::new("deep_scan", flagged == 0, details));
+    }
+
+    if !args.skip_env {
+        let claude_home = claude_home_for(claude_dir)?;
+        checks.push(check_claude_binary());
+        for name in ["settings.json", "settings.local.json"] {
+            checks.push(check_settings_file(&claude_home.join(name)));
+        }
+        checks.push(check_disk_free(&claude_home));
+        checks.push(check_large_dirs(&claude_home, args.large_dir_mb));
     }
 
     if global.json {
-        print_json(&summaries)?;
+        print_json(&checks)?;
         return Ok(());
     }
 
-    print_sessions_table(&summaries, args.with_preview);
+    println!("{}", palette().heading.apply_to(tr!("doctor-title")));
+    for c in &checks {
+        let status = if c.ok {
+            palette().ok.apply_to(tr!("status-ok"))
+        } else if c.warning {
+            palette().warn.apply_to(tr!("status-warn"))
+        } else {
+            palette().error.apply_to(tr!("status-fail"))
+        };
+        println!("{status} {:<24} {}", c.name, c.details);
+    }
+
+    let failed = checks.iter().filter(|c| !c.ok && !c.warning).count();
+    if failed > 0 {
+        bail!(tr!("doctor-failed", count = failed));
+    }
     Ok(())
 }
 
-fn cmd_sessions_show(
-    claude_dir: &Path,
-    time_window: &TimeWindow,
-    global: &GlobalArgs,
-    args: SessionsShowArgs,
-) -> Result<()> {
-    let sessions = discover_sessions(claude_dir, time_window)?;
-    let session = resolve_session_target(&sessions, &args.target)?;
+#[derive(Debug, Serialize)]
+struct CheckResult {
+    name: String,
+    ok: bool,
+    details: String,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    warning: bool,
+}
 
-    if args.raw {
-        let records = read_raw_lines(&session.path)?;
-        if global.json {
-            print_json(&json!({
-                "session": session,
-                "records": records,
-            }))?;
-        } else {
-            println!("{}", style(format!("Session {}", session.id)).bold().cyan());
-            for record in records {
-                println!("{record}");
-            }
+impl CheckResult {
+    fn new(name: impl Into<String>, ok: bool, details: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            ok,
+            details: details.into(),
+            warning: false,
         }
-        return Ok(());
     }
 
-    let parsed = parse_session_events(&session.path, args.detailed)?;
-    let events = if let Some(max) = args.max_lines {
-        parsed.events.into_iter().take(max).collect::<Vec<_>>()
-    } else {
-        parsed.events
+    /// A check whose failure is reported but does not fail the doctor run.
+    fn warn(name: impl Into<String>, ok: bool, details: impl Into<String>) -> Self {
+        Self {
+            warning: !ok,
+            ..Self::new(name, ok, details)
+        }
+    }
+}
+
+/// Fields and record types the bundled baseline doesn't account for, with
+/// long lists cut short so the check stays on one line.
+fn check_schema_drift(drift: &SchemaDrift) -> CheckResult {
+    if drift.is_empty() {
+        return CheckResult::new("schema_drift", true, "matches bundled baseline");
+    }
+    let list = |items: &[String]| {
+        const SHOWN: usize = 8;
+        let mut listed = items.iter().take(SHOWN).cloned().collect::<Vec<_>>();
+        if items.len() > SHOWN {
+            listed.push(format!("+{} more", items.len() - SHOWN));
+        }
+        listed.join(", ")
+    };
+    let mut parts = Vec::new();
+    for (label, items) in [
+        ("new record types", &drift.new_record_types),
+        ("new fields", &drift.new_fields),
+        ("missing fields", &drift.missing_fields),
+    ] {
+        if !items.is_empty() {
+            parts.push(format!("{label}: {}", list(items)));
+        }
+    }
+    CheckResult::warn("schema_drift", false, parts.join("; "))
+}
+
+fn check_path_exists(name: &str, path: &Path) -> CheckResult {
+    CheckResult::new(name, path.exists(), path.display().to_string())
+}
+
+fn check_path_readable(name: &str, path: &Path) -> CheckResult {
+    let ok = fs::read_dir(path).is_ok();
+    CheckResult::new(name, ok, path.display().to_string())
+}
+
+/// The Claude Code home directory that owns the transcript root.
+fn claude_home_for(claude_dir: &Path) -> Result<PathBuf> {
+    if claude_dir.file_name().and_then(|n| n.to_str()) == Some("projects") {
+        if let Some(parent) = claude_dir.parent() {
+            return Ok(parent.to_path_buf());
+        }
+    }
+    expand_tilde_path("~/.claude")
+}
+
+fn check_claude_binary() -> CheckResult {
+    match std::process::Command::new("claude")
+        .arg("--version")
+        .output()
+    {
+        Ok(out) if out.status.success() => CheckResult::new(
+            "claude_binary",
+            true,
+            String::from_utf8_lossy(&out.stdout).trim().to_string(),
+        ),
+        Ok(out) => CheckResult::warn(
+            "claude_binary",
+            false,
+            format!("`claude --version` exited with {}", out.status),
+        ),
+        Err(_) => CheckResult::warn("claude_binary", false, "`claude` not found on PATH"),
+    }
+}
+
+fn check_settings_file(path: &Path) -> CheckResult {
+    let name = format!(
+        "config_{}",
+        path.file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("settings")
+            .trim_end_matches(".json")
+            .replace('.', "_")
+    );
+    if !path.exists() {
+        return CheckResult::new(name, true, format!("{} (not present)", path.display()));
+    }
+    let raw = match fs::read_to_string(path) {
+        Ok(raw) => raw,
+        Err(err) => return CheckResult::new(name, false, format!("{}: {err}", path.display())),
+    };
+    let value: Value = match serde_json::from_str(&raw) {
+        Ok(v) => v,
+        Err(err) => {
+            return CheckResult::new(
+                name,
+                false,
+                format!("{}: invalid JSON: {err}", path.display()),
+            )
+        }
     };
+    match validate_hooks(&value) {
+        Ok(hooks) => CheckResult::new(name, true, format!("{} hooks={hooks}", path.display())),
+        Err(problem) => CheckResult::new(name, false, format!("{}: {problem}", path.display())),
+    }
+}
 
-    if global.json {
-        print_json(&json!({
-            "session": session,
-            "parse_errors": parsed.parse_errors,
-            "events": events,
-        }))?;
-        return Ok(());
+/// Validate the `hooks` section of a settings file, returning the hook count.
+fn validate_hooks(settings: &Value) -> std::result::Result<usize, String> {
+    let Some(hooks) = settings.get("hooks") else {
+        return Ok(0);
+    };
+    let events = hooks
+        .as_object()
+        .ok_or_else(|| "`hooks` must be an object keyed by event name".to_string())?;
+    let mut count = 0;
+    for (event, matchers) in events {
+        let matchers = matchers
+            .as_array()
+            .ok_or_else(|| format!("`hooks.{event}` must be an array"))?;
+        for matcher in matchers {
+            let entries = matcher
+                .get("hooks")
+                .and_then(Value::as_array)
+                .ok_or_else(|| format!("`hooks.{event}[]` entries need a `hooks` array"))?;
+            for entry in entries {
+                let kind = entry.get("type").and_then(Value::as_str);
+                if kind == Some("command") && entry.get("command").and_then(Value::as_str).is_none()
+                {
+                    return Err(format!("`hooks.{event}` command hook is missing `command`"));
+                }
+                if kind.is_none() {
+                    return Err(format!("`hooks.{event}` hook is missing `type`"));
+                }
+                count += 1;
+            }
+        }
     }
+    Ok(count)
+}
 
-    println!("{}", style(format!("Session {}", session.id)).bold().cyan());
-    println!("Project: {}", session.project);
-    println!("Modified: {}", session.modified_iso);
-    println!("Path: {}", session.path.display());
-    println!();
-    for event in events {
-        let ts = event.timestamp.unwrap_or_else(|| "-".to_string());
-        println!(
-            "{} {} {}",
-            style(ts).dim(),
-            style(format!("[{}]", event.role)).bold(),
-            event.content
-        );
+/// Below this much free space the disk check warns.
+const MIN_FREE_DISK_BYTES: u64 = 1024 * 1024 * 1024;
+
+fn check_disk_free(path: &Path) -> CheckResult {
+    let output = std::process::Command::new("df")
+        .arg("-Pk")
+        .arg(path)
+        .output();
+    let available_kb = output.ok().filter(|o| o.status.success()).and_then(|o| {
+        String::from_utf8_lossy(&o.stdout)
+            .lines()
+            .nth(1)
+            .and_then(|line| line.split_whitespace().nth(3))
+            .and_then(|kb| kb.parse::<u64>().ok())
+    });
+    match available_kb {
+        Some(kb) => {
+            let bytes = kb * 1024;
+            CheckResult::warn(
+                "disk_free",
+                bytes >= MIN_FREE_DISK_BYTES,
+                format!(
+                    "{:.1} GiB free under {}",
+                    bytes as f64 / 1073741824.0,
+                    path.display()
+                ),
+            )
+        }
+        None => CheckResult::warn(
+            "disk_free",
+            false,
+            "could not determine free space (df failed)",
+        ),
     }
-    if parsed.parse_errors > 0 {
-        eprintln!(
-            "{}",
-            style(format!(
-                "Skipped {} malformed JSON lines.",
-                parsed.parse_errors
-            ))
-            .yellow()
+}
+
+fn check_large_dirs(claude_home: &Path, threshold_mb: u64) -> CheckResult {
+    let Ok(entries) = fs::read_dir(claude_home) else {
+        return CheckResult::warn(
+            "large_dirs",
+            false,
+            format!("cannot read {}", claude_home.display()),
         );
+    };
+    let mut dirs = Vec::new();
+    for entry in entries.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        if !path.is_dir() {
+            continue;
+        }
+        if path.file_name().and_then(|n| n.to_str()) == Some("projects") {
+            for project in fs::read_dir(&path)
+                .into_iter()
+                .flatten()
+                .filter_map(|e| e.ok())
+            {
+                if project.path().is_dir() {
+                    dirs.push(project.path());
+                }
+            }
+        } else {
+            dirs.push(path);
+        }
+    }
+    let threshold = threshold_mb * 1024 * 1024;
+    let mut large = dirs
+        .into_iter()
+        .map(|d| (dir_size(&d), d))
+        .filter(|(size, _)| *size > threshold)
+        .collect::<Vec<_>>();
+    large.sort_by_key(|(size, _)| Reverse(*size));
+    if large.is_empty() {
+        return CheckResult::new("large_dirs", true, format!("none above {threshold_mb} MB"));
+    }
+    let listed = large
+        .iter()
+        .map(|(size, d)| format!("{} ({} MB)", d.display(), size / (1024 * 1024)))
+        .collect::<Vec<_>>();
+    CheckResult::warn("large_dirs", false, listed.join(", "))
+}
+
+fn dir_size(path: &Path) -> u64 {
+    WalkDir::new(path)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| e.metadata().ok())
+        .map(|m| m.len())
+        .sum()
+}
+
+#[derive(Debug, Serialize)]
+struct DeepScanFile {
+    path: PathBuf,
+    project: String,
+    size_bytes: u64,
+    health: SessionHealth,
+}
+
+fn write_deep_scan_report(
+    output_dir: &Path,
+    files: &[DeepScanFile],
+    format: ReportFormat,
+) -> Result<PathBuf> {
+    let now = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true);
+    let date = now.split('T').next().unwrap_or("unknown-date");
+    let (ext, body) = match format {
+        ReportFormat::Json => (
+            "json",
+            serde_json::to_string_pretty(&json!({
+                "generated_at": now,
+                "files": files,
+            }))?,
+        ),
+        ReportFormat::Markdown => ("md", render_deep_scan_markdown(&now, files)),
+    };
+    let path = output_dir.join(format!("cc-convo-doctor-{date}.{ext}"));
+    write_atomic(&path, body)?;
+    Ok(path)
+}
+
+fn render_deep_scan_markdown(generated_at: &str, files: &[DeepScanFile]) -> String {
+    let flagged = files
+        .iter()
+        .filter(|f| !f.health.flags.is_empty())
+        .collect::<Vec<_>>();
+    let mut out = String::new();
+    out.push_str("# cc-convo doctor deep scan\n\n");
+    out.push_str(&format!("- Generated: `{generated_at}`\n"));
+    out.push_str(&format!("- Files scanned: {}\n", files.len()));
+    out.push_str(&format!("- Files flagged: {}\n\n", flagged.len()));
+
+    out.push_str("## Files\n\n");
+    out.push_str("| File | Project | Size KB | Records | Parse errors | Flags |\n");
+    out.push_str("|---|---|---:|---:|---:|---|\n");
+    for f in files {
+        out.push_str(&format!(
+            "| `{}` | `{}` | {:.1} | {} | {} | {} |\n",
+            f.path.file_name().and_then(|n| n.to_str()).unwrap_or("?"),
+            f.project,
+            f.size_bytes as f64 / 1024.0,
+            f.health.records,
+            f.health.parse_errors,
+            if f.health.flags.is_empty() {
+                "ok".to_string()
+            } else {
+                f.health.flags.join(", ")
+            }
+        ));
+    }
+
+    if !flagged.is_empty() {
+        out.push_str("\n## Findings\n");
+        for f in flagged {
+            out.push_str(&format!("\n### `{}`\n\n", f.path.display()));
+            if !f.health.parse_error_lines.is_empty() {
+                let lines = f
+                    .health
+                    .parse_error_lines
+                    .iter()
+                    .map(|n| n.to_string())
+                    .collect::<Vec<_>>();
+                out.push_str(&format!("- Parse errors on lines: {}\n", lines.join(", ")));
+            }
+            if f.health.truncated_last_line {
+                out.push_str("- Last line is truncated (partial write).\n");
+            }
+            if f.health.missing_timestamps > 0 {
+                out.push_str(&format!(
+                    "- {} message record(s) without a timestamp.\n",
+                    f.health.missing_timestamps
+                ));
+            }
+            if f.health.out_of_order_timestamps > 0 {
+                out.push_str(&format!(
+                    "- {} record(s) with timestamps earlier than the previous record.\n",
+                    f.health.out_of_order_timestamps
+                ));
+            }
+            if let Some(gap) = f.health.max_gap_secs.filter(|g| *g > ABNORMAL_GAP_SECS) {
+                out.push_str(&format!(
+                    "- Largest gap between records: {:.1}h.\n",
+                    gap as f64 / 3600.0
+                ));
+            }
+            for anomaly in &f.health.schema_anomalies {
+                out.push_str(&format!("- Schema: {anomaly}.\n"));
+            }
+        }
     }
+    out
+}
+
+fn ensure_output_dir_writable(path: &Path) -> Result<()> {
+    fs::create_dir_all(path)?;
+    let test = path.join(".cc-convo-write-test");
+    fs::write(&test, b"ok")?;
+    fs::remove_file(test)?;
     Ok(())
 }
 
-fn cmd_export(
+fn cmd_replay(
     claude_dir: &Path,
     time_window: &TimeWindow,
     global: &GlobalArgs,
-    args: ExportArgs,
+    args: ReplayArgs,
 ) -> Result<()> {
-    let sessions = discover_sessions(claude_dir, time_window)?;
-    let selected = select_sessions_for_export(&sessions, &args)?;
+    if args.format.is_dataset() {
+        bail!("Replays are written as markdown, json, or html; use `export` for datasets");
+    }
+    let sessions = discover_sessions(claude_dir, time_window, global)?;
+    let selected = select_sessions(&sessions, &args.selection, time_window, global)?;
 
-    if selected.is_empty() {
-        bail!("No sessions selected for export.");
+    let mut plans = Vec::with_capacity(selected.len());
+    for session in &selected {
+        let mut parsed = parse_session(session, false)?;
+        time_window.retain_events(&mut parsed.events);
+        let mut exchanges = replay_exchanges(&parsed.events);
+        if let Some(max) = args.max_prompts {
+            exchanges.truncate(max);
+        }
+        plans.push((session, exchanges));
     }
+    let total_prompts: usize = plans.iter().map(|(_, e)| e.len()).sum();
 
-    if args.all && !args.yes && !global.json {
-        let proceed = Confirm::new()
-            .with_prompt(format!("Export all {} sessions?", selected.len()))
-            .default(false)
-            .interact()
-            .context("Failed to read confirmation input")?;
-        if !proceed {
-            println!("Cancelled.");
+    if args.dry_run {
+        if global.json {
+            let preview = plans
+                .iter()
+                .map(|(s, e)| {
+                    json!({
+                        "session_id": s.id,
+                        "prompts": e.iter().map(|x| &x.prompt).collect::<Vec<_>>(),
+                    })
+                })
+                .collect::<Vec<_>>();
+            print_json(&preview)?;
             return Ok(());
         }
+        for (session, exchanges) in &plans {
+            println!(
+                "{} {}",
+                palette().accent.apply_to(&session.id),
+                palette()
+                    .meta
+                    .apply_to(format!("({} prompt(s))", exchanges.len()))
+            );
+            for ex in exchanges {
+                println!("  #{} {}", ex.prompt_index, clean_preview(&ex.prompt));
+            }
+        }
+        return Ok(());
     }
 
+    let api_key = match args.api_key.clone() {
+        Some(key) => key,
+        None => std::env::var("ANTHROPIC_API_KEY")
+            .context("No API key: pass --api-key or set ANTHROPIC_API_KEY")?,
+    };
+
     fs::create_dir_all(&args.output)
         .with_context(|| format!("Failed to create output dir {}", args.output.display()))?;
 
-    let pb = if !global.json && selected.len() > 1 {
-        let pb = ProgressBar::new(selected.len() as u64);
-        pb.set_style(
-            ProgressStyle::with_template(
-                "{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} {msg}",
-            )
-            .unwrap_or_else(|_| ProgressStyle::default_bar()),
-        );
-        Some(pb)
-    } else {
-        None
-    };
+    let progress = Progress::new(global, "replay", "prompts_done", total_prompts);
 
+    let replayed_at = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true);
     let mut output_files = Vec::new();
-    let mut bundled_docs = Vec::new();
-    let mut total_parse_errors = 0u64;
-    let mut exported = 0usize;
-
-    for session in &selected {
-        let parsed = parse_session_events(&session.path, args.detailed)?;
-        total_parse_errors += parsed.parse_errors;
-        let doc = build_export_document(session, &parsed.events);
-        if args.single_file {
-            bundled_docs.push(doc);
-        } else {
-            let path = write_single_export(&args.output, &doc, args.format)?;
-            output_files.push(path);
-        }
-        exported += 1;
-        if let Some(pb) = &pb {
-            pb.set_message(session.id_short.clone());
-            pb.inc(1);
+    let mut failures = 0usize;
+    for (session, mut exchanges) in plans {
+        for i in 0..exchanges.len() {
+            let messages = build_replay_messages(&exchanges, i, args.with_history);
+            let started = std::time::Instant::now();
+            match send_messages_request(
+                &args.api_url,
+                &api_key,
+                &args.model,
+                args.max_tokens,
+                &messages,
+            ) {
+                Ok(reply) => {
+                    let ex = &mut exchanges[i];
+                    ex.replay_response = Some(reply.text);
+                    ex.input_tokens = reply.input_tokens;
+                    ex.output_tokens = reply.output_tokens;
+                    ex.latency_ms = Some(started.elapsed().as_millis() as u64);
+                }
+                Err(err) => {
+                    failures += 1;
+                    exchanges[i].replay_error = Some(format!("{err:#}"));
+                }
+            }
+            progress.inc(&session.id_short);
         }
-    }
 
-    if let Some(pb) = &pb {
-        pb.finish_with_message("done");
+        let doc = ReplayDocument {
+            session_id: session.id.clone(),
+            session_short: session.id_short.clone(),
+            project: session.project.clone(),
+            source_path: session.path.clone(),
+            model: args.model.clone(),
+            replayed_at: replayed_at.clone(),
+            exchanges,
+        };
+        output_files.push(write_replay_export(&args.output, &doc, args.format)?);
     }
 
-    if args.single_file {
-        let path = write_bundle_export(&args.output, &bundled_docs, args.format)?;
-        output_files.push(path);
-    }
+    progress.finish();
 
     if global.json {
         print_json(&json!({
-            "exported_sessions": exported,
+            "replayed_sessions": output_files.len(),
+            "prompts": total_prompts,
+            "failed_prompts": failures,
+            "model": args.model,
             "output_files": output_files,
-            "parse_errors": total_parse_errors,
             "format": args.format,
-            "detailed": args.detailed,
-            "single_file": args.single_file
         }))?;
         return Ok(());
     }
 
     println!(
         "{}",
-        style(format!("Exported {} session(s).", exported))
-            .bold()
-            .green()
+        palette().success.apply_to(format!(
+            "Replayed {} prompt(s) from {} session(s) against {}.",
+            total_prompts,
+            output_files.len(),
+            args.model
+        ))
     );
     println!("Output:");
     for p in &output_files {
         println!("  {}", p.display());
     }
-    if total_parse_errors > 0 {
+    if failures > 0 {
         eprintln!(
             "{}",
-            style(format!(
-                "Skipped {} malformed JSON lines.",
-                total_parse_errors
+            palette().warn.apply_to(format!(
+                "{failures} prompt(s) failed; see replay_error in the output."
             ))
-            .yellow()
         );
     }
     Ok(())
 }
 
-fn cmd_search(
+/// Discover sessions, warning on stderr about files that had to be skipped.
+/// With `--json` the warning is a single NDJSON line so wrappers can parse it.
+fn discover_sessions(
     claude_dir: &Path,
     time_window: &TimeWindow,
     global: &GlobalArgs,
-    args: SearchArgs,
-) -> Result<()> {
-    let sessions = discover_sessions(claude_dir, time_window)?;
-    let hits = search_sessions(&sessions, &args)?;
+) -> Result<Vec<Session>> {
+    let discovery = discover_with(claude_dir, time_window, &walk_limits(global))?;
+    if !discovery.skipped.is_empty() {
+        if global.json {
+            let line = json!({ "skipped_files": discovery.skipped }).to_string();
+            eprintln!("{line}");
+        } else {
+            let first = &discovery.skipped[0];
+            eprintln!(
+                "{}",
+                palette().warn.apply_to(format!(
+                    "Skipped {} unreadable path(s), e.g. {} ({}); run `cc-convo doctor` for the full list.",
+                    discovery.skipped.len(),
+                    first.path.display(),
+                    first.error
+                ))
+            );
+        }
+    }
+    let stitches = StitchStore::load(&stitches_path(global)?)?;
+    Ok(stitches.apply(discovery.sessions))
+}
+
+fn stitches_path(global: &GlobalArgs) -> Result<PathBuf> {
+    expand_tilde_path(&global.stitches_file)
+}
+
+fn cmd_stitch(claude_dir: &Path, global: &GlobalArgs, args: StitchArgs) -> Result<()> {
+    let path = stitches_path(global)?;
+    let rescan = || -> Result<StitchStore> {
+        if args.clear {
+            return Ok(StitchStore::default());
+        }
+        // Every file, so links to sessions outside --since/--until survive.
+        let discovery = discover_with(claude_dir, &TimeWindow::default(), &walk_limits(global))?;
+        match args.by {
+            StitchBy::SessionId => StitchStore::scan(&discovery.sessions),
+        }
+    };
+    let store = if args.dry_run {
+        rescan()?
+    } else {
+        StitchStore::update(&path, report_lock_wait, |store| {
+            *store = rescan()?;
+            Ok(store.clone())
+        })?
+    };
 
-    let hits = hits.into_iter().take(args.max_results).collect::<Vec<_>>();
     if global.json {
-        print_json(&hits)?;
-        return Ok(());
+        return print_json(&json!({
+            "store": path,
+            "saved": !args.dry_run,
+            "sessions": store.sessions,
+        }));
+    }
+    if store.sessions.is_empty() {
+        println!("No session has files in more than one project dir.");
+    }
+    for (id, paths) in &store.sessions {
+        println!("{}", style(id).bold());
+        for file in paths {
+            println!("  {}", file.display());
+        }
     }
+    if args.dry_run {
+        println!("Dry run; nothing saved to {}.", path.display());
+    } else {
+        println!(
+            "Stitched {} session(s); links saved to {}.",
+            store.sessions.len(),
+            path.display()
+        );
+    }
+    Ok(())
+}
 
-    println!(
-        "{}",
-        style(format!("Found {} result(s).", hits.len()))
-            .bold()
-            .cyan()
+/// The files `prune` removes for one session: its transcripts and the
+/// `<id>/` dir beside them that holds subagent transcripts and tool output.
+struct PruneTarget<'a> {
+    session: &'a Session,
+    paths: Vec<PathBuf>,
+    bytes: u64,
+}
+
+fn cmd_compact(claude_dir: &Path, global: &GlobalArgs, args: CompactArgs) -> Result<()> {
+    // Every file: the window replaces --since/--until, as in prune.
+    let discovery = discover_with(claude_dir, &TimeWindow::default(), &walk_limits(global))?;
+    let (cutoff, mut stale) = stale_sessions(
+        &discovery.sessions,
+        args.older_than,
+        args.project.as_deref(),
     );
-    for (i, hit) in hits.iter().enumerate() {
-        println!();
+    stale.retain(|s| Compression::of(&s.path).is_none());
+
+    if !global.json {
         println!(
-            "{} {} {}",
-            style(format!("#{}", i + 1)).bold(),
-            style(&hit.session_id).green(),
-            style(format!("({})", hit.project)).dim()
+            "{}",
+            style(format!(
+                "Sessions last written more than {} ago:",
+                format_age(args.older_than.num_seconds())
+            ))
+            .bold()
+        );
+        if stale.is_empty() {
+            println!("  {}", tr!("none"));
+        }
+    }
+    let sessions = stale.len();
+    let (mut bytes, mut compressed_bytes) = (0, 0);
+    let mut rows = Vec::new();
+    for session in stale {
+        let mut paths = Vec::new();
+        let (mut before, mut after) = (0, 0);
+        for path in session.paths() {
+            if Compression::of(path).is_some() {
+                continue;
+            }
+            before += dir_size(path);
+            if args.dry_run {
+                paths.push(path.to_path_buf());
+                continue;
+            }
+            let packed = compress_transcript(path, args.compression)?;
+            after += dir_size(&packed);
+            paths.push(packed);
+        }
+        bytes += before;
+        compressed_bytes += after;
+        if global.json {
+            let mut row = json!({
+                "id": session.id,
+                "project": session.project,
+                "modified": session.modified_iso,
+                "paths": paths,
+                "bytes": before,
+            });
+            if !args.dry_run {
+                row["compressed_bytes"] = json!(after);
+            }
+            rows.push(row);
+            continue;
+        }
+        let sizes = match args.dry_run {
+            true => format_size(before),
+            false => format!("{} -> {}", format_size(before), format_size(after)),
+        };
+        println!(
+            "  {}  {}  {sizes}",
+            session.qualified_id(),
+            palette().meta.apply_to(&session.modified_iso)
+        );
+    }
+
+    if global.json {
+        let mut out = json!({
+            "cutoff": cutoff.to_rfc3339_opts(SecondsFormat::Secs, true),
+            "dry_run": args.dry_run,
+            "compression": args.compression.extension(),
+            "sessions": rows,
+            "bytes": bytes,
+        });
+        if !args.dry_run {
+            out["compressed_bytes"] = json!(compressed_bytes);
+        }
+        return print_json(&out);
+    }
+    if args.dry_run {
+        println!(
+            "{sessions} session(s), {}. Dry run; nothing compressed.",
+            format_size(bytes)
         );
+    } else if sessions > 0 {
         println!(
-            "{} {} {:.2}",
-            style(hit.timestamp.clone().unwrap_or_else(|| "-".into())).dim(),
-            style(format!("[{}]", hit.speaker)).bold(),
-            hit.relevance
+            "{}",
+            palette().success.apply_to(format!(
+                "Compressed {sessions} session(s): {} -> {}.",
+                format_size(bytes),
+                format_size(compressed_bytes)
+            ))
         );
-        println!("{}", hit.preview);
     }
     Ok(())
 }
 
-fn cmd_stats(
-    claude_dir: &Path,
-    time_window: &TimeWindow,
-    global: &GlobalArgs,
-    args: StatsArgs,
-) -> Result<()> {
-    let sessions = discover_sessions(claude_dir, time_window)?;
-    let mut record_type_counts: HashMap<String, u64> = HashMap::new();
-    let mut block_type_counts: HashMap<String, u64> = HashMap::new();
-    let mut model_counts: HashMap<String, u64> = HashMap::new();
-    let mut parse_errors: u64 = 0;
-    let mut total_records: u64 = 0;
+/// The cutoff `older_than` ago and the sessions last written before it,
+/// narrowed to `project` the way `--project` matches elsewhere.
+fn stale_sessions<'a>(
+    sessions: &'a [Session],
+    older_than: chrono::Duration,
+    project: Option<&str>,
+) -> (DateTime<Utc>, Vec<&'a Session>) {
+    let cutoff = Utc::now() - older_than;
+    let filter = project.map(str::to_lowercase);
+    let stale = sessions
+        .iter()
+        .filter(|s| s.modified_epoch < cutoff.timestamp())
+        .filter(|s| {
+            filter.as_ref().is_none_or(|f| {
+                s.project.to_lowercase().contains(f)
+                    || s.path.to_string_lossy().to_lowercase().contains(f)
+            })
+        })
+        .collect();
+    (cutoff, stale)
+}
 
-    for session in &sessions {
-        let f = File::open(&session.path)?;
-        let reader = BufReader::new(f);
-        for line in reader.lines() {
-            let line = line?;
-            if line.trim().is_empty() {
-                continue;
+fn cmd_prune(claude_dir: &Path, global: &GlobalArgs, args: PruneArgs) -> Result<()> {
+    if global.json && !args.yes && !args.dry_run {
+        bail!("prune --json cannot ask for confirmation; pass --yes or --dry-run");
+    }
+    // Every file: the retention window replaces --since/--until.
+    let discovery = discover_with(claude_dir, &TimeWindow::default(), &walk_limits(global))?;
+    let (cutoff, stale) = stale_sessions(
+        &discovery.sessions,
+        args.older_than,
+        args.project.as_deref(),
+    );
+    let sidecars = stale
+        .iter()
+        .map(|s| s.path.with_file_name(&s.id))
+        .filter(|dir| dir.is_dir())
+        .collect::<Vec<_>>();
+    let targets = stale
+        .into_iter()
+        .filter_map(|session| {
+            // A subagent transcript found as a session of its own goes with
+            // the sidecar dir of its parent.
+            let mut paths = session
+                .paths()
+                .filter(|p| !sidecars.iter().any(|dir| p.starts_with(dir)))
+                .map(Path::to_path_buf)
+                .collect::<Vec<_>>();
+            if paths.is_empty() {
+                return None;
             }
-            let value: Value = match serde_json::from_str(&line) {
-                Ok(v) => v,
-                Err(_) => {
-                    parse_errors += 1;
-                    continue;
-                }
-            };
-            total_records += 1;
-            let record_type = value
-                .get("type")
-                .and_then(Value::as_str)
-                .unwrap_or("<missing>")
-                .to_string();
-            *record_type_counts.entry(record_type).or_insert(0) += 1;
+            let sidecar = session.path.with_file_name(&session.id);
+            if sidecars.contains(&sidecar) {
+                paths.push(sidecar);
+            }
+            let bytes = paths.iter().map(|p| dir_size(p)).sum();
+            Some(PruneTarget {
+                session,
+                paths,
+                bytes,
+            })
+        })
+        .collect::<Vec<_>>();
+    let bytes = targets.iter().map(|t| t.bytes).sum::<u64>();
+    let files = targets.iter().map(|t| t.paths.len()).sum::<usize>();
+    let size = format_size(bytes);
 
-            if value.get("type").and_then(Value::as_str) == Some("assistant") {
-                if let Some(model) = value
-                    .get("message")
-                    .and_then(|m| m.get("model"))
-                    .and_then(Value::as_str)
-                {
-                    *model_counts.entry(model.to_string()).or_insert(0) += 1;
+    if !global.json {
+        println!(
+            "{}",
+            style(format!(
+                "Sessions last written more than {} ago:",
+                format_age(args.older_than.num_seconds())
+            ))
+            .bold()
+        );
+        for target in &targets {
+            println!(
+                "  {}  {}  {}",
+                target.session.qualified_id(),
+                palette().meta.apply_to(&target.session.modified_iso),
+                format_size(target.bytes)
+            );
+        }
+        println!("{} session(s), {} file(s), {size}.", targets.len(), files);
+    }
+    let act = !args.dry_run && !targets.is_empty();
+    if act && !args.yes {
+        let prompt = match &args.archive {
+            Some(dir) => format!(
+                "Move {} session(s) ({size}) to {}?",
+                targets.len(),
+                dir.display()
+            ),
+            None => format!(
+                "Delete {} session(s) ({size})? This cannot be undone",
+                targets.len()
+            ),
+        };
+        let proceed = Confirm::new()
+            .with_prompt(prompt)
+            .default(false)
+            .interact()
+            .context("Failed to read confirmation input")?;
+        if !proceed {
+            println!("{}", tr!("cancelled"));
+            return Ok(());
+        }
+    }
+    if act {
+        for path in targets.iter().flat_map(|t| &t.paths) {
+            match &args.archive {
+                Some(dir) => {
+                    let relative = path.strip_prefix(claude_dir).unwrap_or(path);
+                    move_path(path, &dir.join(relative))?
                 }
+                None if path.is_dir() => fs::remove_dir_all(path)
+                    .with_context(|| format!("Failed to remove {}", path.display()))?,
+                None => fs::remove_file(path)
+                    .with_context(|| format!("Failed to remove {}", path.display()))?,
             }
-
-            if let Some(content) = value
-                .get("message")
-                .and_then(|m| m.get("content"))
-                .and_then(Value::as_array)
-            {
-                for item in content {
-                    if let Some(t) = item.get("type").and_then(Value::as_str) {
-                        *block_type_counts.entry(t.to_string()).or_insert(0) += 1;
-                    }
+        }
+        // Project dirs left empty go too; remove_dir refuses any that aren't.
+        for target in &targets {
+            if let Some(project_dir) = target.session.path.parent() {
+                if project_dir != claude_dir && project_dir.starts_with(claude_dir) {
+                    let _ = fs::remove_dir(project_dir);
                 }
             }
         }
     }
 
-    let record_type_top = top_n_sorted_map(record_type_counts, args.top);
-    let block_type_top = top_n_sorted_map(block_type_counts, args.top);
-    let model_top = top_n_sorted_map(model_counts, args.top);
-
     if global.json {
-        print_json(&json!({
-            "sessions": sessions.len(),
-            "total_records": total_records,
-            "parse_errors": parse_errors,
-            "record_types": record_type_top,
-            "content_block_types": block_type_top,
-            "models": model_top
-        }))?;
-        return Ok(());
+        let sessions = targets
+            .iter()
+            .map(|t| {
+                json!({
+                    "id": t.session.id,
+                    "project": t.session.project,
+                    "modified": t.session.modified_iso,
+                    "paths": t.paths,
+                    "bytes": t.bytes,
+                })
+            })
+            .collect::<Vec<_>>();
+        return print_json(&json!({
+            "cutoff": cutoff.to_rfc3339_opts(SecondsFormat::Secs, true),
+            "dry_run": args.dry_run,
+            "archive": args.archive,
+            "sessions": sessions,
+            "files": files,
+            "bytes": bytes,
+            "reclaimed_bytes": if act { bytes } else { 0 },
+        }));
+    }
+    if args.dry_run {
+        println!("Dry run; nothing deleted or moved.");
+    } else if act {
+        match &args.archive {
+            Some(dir) => println!(
+                "Moved {} session(s) to {}; reclaimed {size}.",
+                targets.len(),
+                dir.display()
+            ),
+            None => println!("Deleted {} session(s); reclaimed {size}.", targets.len()),
+        }
     }
-
-    println!("{}", style("Corpus stats").bold().cyan());
-    println!("Sessions: {}", sessions.len());
-    println!("Records: {}", total_records);
-    println!("Parse errors: {}", parse_errors);
-    println!();
-    print_ranked_map("Top record types", &record_type_top);
-    println!();
-    print_ranked_map("Top content block types", &block_type_top);
-    println!();
-    print_ranked_map("Top models", &model_top);
     Ok(())
 }
 
-fn cmd_doctor(
-    claude_dir: &Path,
-    time_window: &TimeWindow,
-    global: &GlobalArgs,
-    args: DoctorArgs,
-) -> Result<()> {
-    let mut checks = Vec::new();
-    checks.push(check_path_exists("claude_dir_exists", claude_dir));
-    checks.push(check_path_readable("claude_dir_readable", claude_dir));
-
-    let sessions = discover_sessions(claude_dir, time_window).unwrap_or_default();
-    checks.push(CheckResult::new(
-        "jsonl_files_found",
-        !sessions.is_empty(),
-        format!("found {}", sessions.len()),
-    ));
+/// `bytes` in KB, MB, or GB, whichever keeps the number below 1024.
+fn format_size(bytes: u64) -> String {
+    let mut size = bytes as f64 / 1024.0;
+    for unit in ["KB", "MB"] {
+        if size < 1024.0 {
+            return format!("{} {unit}", i18n::decimal(size, 1));
+        }
+        size /= 1024.0;
+    }
+    format!("{} GB", i18n::decimal(size, 1))
+}
 
-    let sample = sessions.iter().take(args.sample_files).collect::<Vec<_>>();
-    let mut sample_parse_errors = 0u64;
-    let mut sample_records = 0u64;
-    for session in sample {
-        let f = File::open(&session.path)
-            .with_context(|| format!("Failed to open {}", session.path.display()))?;
-        let reader = BufReader::new(f);
-        for line in reader.lines() {
-            let line = line?;
-            if line.trim().is_empty() {
-                continue;
-            }
-            sample_records += 1;
-            if serde_json::from_str::<Value>(&line).is_err() {
-                sample_parse_errors += 1;
-            }
+/// Rename `from` to `to`, or copy and delete when they are on different
+/// file systems.
+fn move_path(from: &Path, to: &Path) -> Result<()> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    for entry in WalkDir::new(from) {
+        let entry = entry.with_context(|| format!("Failed to read {}", from.display()))?;
+        let target = to.join(entry.path().strip_prefix(from).unwrap_or(entry.path()));
+        if entry.file_type().is_dir() {
+            fs::create_dir_all(&target)
+                .with_context(|| format!("Failed to create {}", target.display()))?;
+        } else {
+            fs::copy(entry.path(), &target).with_context(|| {
+                format!(
+                    "Failed to copy {} to {}",
+                    entry.path().display(),
+                    target.display()
+                )
+            })?;
         }
     }
-    checks.push(CheckResult::new(
-        "sample_parse",
-        sample_records > 0 && sample_parse_errors == 0,
-        format!(
-            "records={} parse_errors={}",
-            sample_records, sample_parse_errors
-        ),
-    ));
+    if from.is_dir() {
+        fs::remove_dir_all(from)
+    } else {
+        fs::remove_file(from)
+    }
+    .with_context(|| format!("Failed to remove {}", from.display()))
+}
 
-    let writable = ensure_output_dir_writable(&args.output).is_ok();
-    checks.push(CheckResult::new(
-        "output_dir_writable",
-        writable,
-        args.output.display().to_string(),
-    ));
+/// Longest preview `--preview-chars` can show; previews are cached cut to this.
+const PREVIEW_MAX_WIDTH: usize = 1000;
 
-    if global.json {
-        print_json(&checks)?;
-        return Ok(());
+/// What `sessions list` reads out of a session's files, before picking a
+/// preview. Kept in the [`MetadataCache`] between runs.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct SessionCounts {
+    user_messages: u64,
+    assistant_messages: u64,
+    other_records: u64,
+    tool_uses: u64,
+    images: u64,
+    models: Vec<String>,
+    /// Paths named in tool inputs, sorted.
+    files: Vec<String>,
+    /// Names of the tools called, sorted.
+    tools: Vec<String>,
+    /// Git branches the session's records were written on, sorted.
+    branches: Vec<String>,
+    /// Up to [`PREVIEW_MAX_WIDTH`] columns; the title keeps a shorter one.
+    first_prompt: Option<String>,
+    last_answer: Option<String>,
+    title: SessionTitle,
+}
+
+impl SessionCounts {
+    /// The preview for `mode`, falling back to the first prompt.
+    fn preview(&self, mode: PreviewMode) -> Option<String> {
+        let preview = match mode {
+            PreviewMode::FirstPrompt => None,
+            PreviewMode::LastAnswer => self.last_answer.clone(),
+            PreviewMode::Summary => self.title.summary.clone(),
+        };
+        preview.or_else(|| self.first_prompt.clone())
     }
+}
 
-    println!("{}", style("Doctor").bold().cyan());
-    for c in &checks {
-        let status = if c.ok {
-            style("OK").green()
+/// `--cache-file` for [`SessionCounts`], unless `--no-cache` turns it off.
+struct CountsCache {
+    store: Option<(PathBuf, MetadataCache<SessionCounts>)>,
+}
+
+impl CountsCache {
+    fn open(global: &GlobalArgs, time_window: &TimeWindow) -> Result<Self> {
+        // Counts depend on which records an event window admits, so only
+        // whole-file counts are cached.
+        let cached =
+            !global.no_cache && (time_window.by == WindowBy::Mtime || time_window.is_open());
+        let store = if cached {
+            let path = expand_tilde_path(&global.cache_file)?;
+            let cache = MetadataCache::load(&path);
+            Some((path, cache))
         } else {
-            style("FAIL").red()
+            None
         };
-        println!("{status} {:<24} {}", c.name, c.details);
+        Ok(Self { store })
     }
 
-    let failed = checks.iter().filter(|c| !c.ok).count();
-    if failed > 0 {
-        bail!("Doctor found {failed} failing checks.");
+    fn counts(&mut self, session: &Session, time_window: &TimeWindow) -> Result<SessionCounts> {
+        match &mut self.store {
+            Some((_, cache)) => {
+                cache.get_or_insert_with(session, || count_session(session, time_window))
+            }
+            None => count_session(session, time_window),
+        }
     }
-    Ok(())
-}
 
-#[derive(Debug, Serialize)]
-struct CheckResult {
-    name: String,
-    ok: bool,
-    details: String,
-}
+    /// Counts cached for `session`, if its files haven't changed since.
+    fn get(&self, session: &Session) -> Option<&SessionCounts> {
+        self.store
+            .as_ref()
+            .and_then(|(_, cache)| cache.get(session))
+    }
 
-impl CheckResult {
-    fn new(name: impl Into<String>, ok: bool, details: impl Into<String>) -> Self {
-        Self {
-            name: name.into(),
-            ok,
-            details: details.into(),
+    /// Cache `counts`, computed elsewhere, for `session`.
+    fn insert(&mut self, session: &Session, counts: SessionCounts) -> Result<SessionCounts> {
+        match &mut self.store {
+            Some((_, cache)) => cache.get_or_insert_with(session, || Ok(counts)),
+            None => Ok(counts),
         }
     }
-}
 
-fn check_path_exists(name: &str, path: &Path) -> CheckResult {
-    CheckResult::new(name, path.exists(), path.display().to_string())
+    fn save(&mut self) -> Result<()> {
+        match &mut self.store {
+            Some((path, cache)) => cache.save(path, report_lock_wait),
+            None => Ok(()),
+        }
+    }
 }
 
-fn check_path_readable(name: &str, path: &Path) -> CheckResult {
-    let ok = fs::read_dir(path).is_ok();
-    CheckResult::new(name, ok, path.display().to_string())
-}
+/// `search --file PATH` without a query: the sessions that touched the path
+/// in a tool call, then those that only mention it.
+fn print_file_sessions(
+    sessions: &[Session],
+    filter: &SessionFilter,
+    args: &SearchArgs,
+    time_window: &TimeWindow,
+    global: &GlobalArgs,
+) -> Result<()> {
+    let mut cache = CountsCache::open(global, time_window)?;
+    let mut rows = Vec::new();
+    for session in sessions {
+        let counts = cache.counts(session, time_window)?;
+        let touched = filter
+            .touched(&counts.files)
+            .into_iter()
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        rows.push((session, counts.title.title().map(str::to_string), touched));
+    }
+    cache.save()?;
+    rows.sort_by_key(|(_, _, touched)| touched.is_empty());
+    rows.truncate(args.max_results);
+    let wanted = args.filter.file.as_deref().unwrap_or_default();
 
-fn ensure_output_dir_writable(path: &Path) -> Result<()> {
-    fs::create_dir_all(path)?;
-    let test = path.join(".cc-convo-write-test");
-    fs::write(&test, b"ok")?;
-    fs::remove_file(test)?;
+    if global.json {
+        let rows = rows
+            .iter()
+            .map(|(session, title, touched)| {
+                json!({
+                    "session_id": session.id,
+                    "project": session.project,
+                    "path": session.path,
+                    "title": title,
+                    "match": if touched.is_empty() { "discussed" } else { "touched" },
+                    "files": touched,
+                })
+            })
+            .collect::<Vec<_>>();
+        return print_json(&rows);
+    }
+    println!(
+        "{}",
+        palette()
+            .heading
+            .apply_to(format!("Found {} session(s) for {wanted}.", rows.len()))
+    );
+    for (i, (session, title, touched)) in rows.iter().enumerate() {
+        println!();
+        println!(
+            "{} {} {}{}",
+            style(format!("#{}", i + 1)).bold(),
+            palette().accent.apply_to(&session.id),
+            palette().meta.apply_to(format!("({})", session.project)),
+            title
+                .as_ref()
+                .map(|t| format!(" {}", style(t).bold()))
+                .unwrap_or_default()
+        );
+        if touched.is_empty() {
+            println!("{}", palette().meta.apply_to("discussed"));
+        } else {
+            println!("touched: {}", touched.join(", "));
+        }
+    }
     Ok(())
 }
 
-fn discover_sessions(claude_dir: &Path, time_window: &TimeWindow) -> Result<Vec<Session>> {
-    if !claude_dir.exists() {
-        bail!("Claude directory does not exist: {}", claude_dir.display());
+/// Sessions passing `filter`. Cached counts decide first, then the cheap
+/// [`SessionFilter::rule_out`] checks, and only what's left gets counted;
+/// `--verbose` reports how many sessions each step settled.
+fn filter_sessions(
+    sessions: Vec<Session>,
+    filter: &SessionFilter,
+    time_window: &TimeWindow,
+    global: &GlobalArgs,
+) -> Result<Vec<Session>> {
+    if filter.is_empty() {
+        return Ok(sessions);
     }
-    let mut sessions = Vec::new();
-    for entry in WalkDir::new(claude_dir)
-        .into_iter()
-        .filter_map(|e| e.ok())
-        .filter(|e| e.file_type().is_file())
-    {
-        let path = entry.path();
-        if path.extension().and_then(|s| s.to_str()) != Some("jsonl") {
-            continue;
-        }
-        let metadata = fs::metadata(path)?;
-        let modified = metadata
-            .modified()
-            .with_context(|| format!("Failed to get mtime for {}", path.display()))?;
-        let modified_dt: DateTime<Utc> = modified.into();
-        if let Some(since) = time_window.since {
-            if modified_dt < since {
-                continue;
+    let started = std::time::Instant::now();
+    let total = sessions.len();
+    let mut cache = CountsCache::open(global, time_window)?;
+    let matches = |session: &Session, counts: &SessionCounts| -> Result<bool> {
+        Ok(filter.matches(
+            counts.user_messages + counts.assistant_messages,
+            &counts.models,
+        ) && (!filter.touched(&counts.files).is_empty() || filter.mentions(session)?))
+    };
+    let mut kept = Vec::new();
+    let mut uncached = Vec::new();
+    let mut cached = 0usize;
+    for session in sessions {
+        match cache.get(&session) {
+            Some(counts) => {
+                cached += 1;
+                if matches(&session, counts)? {
+                    kept.push(session);
+                }
             }
+            None => uncached.push(session),
         }
-        if let Some(until) = time_window.until {
-            if modified_dt > until {
-                continue;
-            }
+    }
+    let verdicts = uncached
+        .par_iter()
+        .map(|session| filter.rule_out(session))
+        .collect::<Result<Vec<_>>>()?;
+    let (mut size, mut peek, mut scan) = (0usize, 0usize, 0usize);
+    let mut survivors = Vec::new();
+    for (session, verdict) in uncached.into_iter().zip(verdicts) {
+        match verdict {
+            Some(PrefilterStage::Size) => size += 1,
+            Some(PrefilterStage::Peek) => peek += 1,
+            Some(PrefilterStage::Scan) => scan += 1,
+            None => survivors.push(session),
         }
-
-        let stem = path
-            .file_stem()
-            .and_then(|s| s.to_str())
-            .ok_or_else(|| anyhow!("Invalid file stem for {}", path.display()))?
-            .to_string();
-        let project = path
-            .parent()
-            .and_then(|p| p.file_name())
-            .and_then(|s| s.to_str())
-            .unwrap_or("unknown")
-            .to_string();
-        let modified_iso = modified_dt.to_rfc3339_opts(SecondsFormat::Secs, true);
-        let modified_epoch = modified_dt.timestamp();
-        let size_bytes = metadata.len();
-
-        sessions.push(Session {
-            index: 0,
-            id_short: short_id(&stem),
-            id: stem,
-            project,
-            path: path.to_path_buf(),
-            modified_iso,
-            modified_epoch,
-            size_bytes,
-        });
     }
-
-    sessions.sort_by_key(|s| (Reverse(s.modified_epoch), s.path.clone()));
-    for (i, session) in sessions.iter_mut().enumerate() {
-        session.index = i + 1;
+    let counted = survivors
+        .par_iter()
+        .map(|session| count_session(session, time_window))
+        .collect::<Result<Vec<_>>>()?;
+    let parsed = survivors.len();
+    for (session, counts) in survivors.into_iter().zip(counted) {
+        let counts = cache.insert(&session, counts)?;
+        if matches(&session, &counts)? {
+            kept.push(session);
+        }
+    }
+    cache.save()?;
+    // Cached sessions were pulled out ahead of the rest.
+    kept.sort_by_key(|session| session.index);
+    if global.verbose {
+        eprintln!(
+            "{}",
+            palette().meta.apply_to(format!(
+                "Filtered {total} session(s) to {} in {} ms: {cached} from cache, {size} skipped by size, {peek} by peek, {scan} by scan, {parsed} parsed.",
+                kept.len(),
+                started.elapsed().as_millis()
+            ))
+        );
     }
-    Ok(sessions)
+    Ok(kept)
 }
 
-fn summarize_session(session: &Session, with_preview: bool) -> Result<SessionSummary> {
-    let mut user = 0u64;
-    let mut assistant = 0u64;
-    let mut other = 0u64;
+/// Counts and preview for `sessions list`, over the records `time_window` admits.
+fn summarize_session(
+    session: &Session,
+    preview_mode: Option<PreviewMode>,
+    time_window: &TimeWindow,
+) -> Result<SessionSummary> {
+    let counts = count_session(session, time_window)?;
+    Ok(session_summary(session, counts, preview_mode))
+}
 
-    let mut preview = None;
-    let f = File::open(&session.path)?;
-    let reader = BufReader::new(f);
-    for line in reader.lines() {
-        let line = line?;
-        if line.trim().is_empty() {
-            continue;
-        }
-        let value: Value = match serde_json::from_str(&line) {
-            Ok(v) => v,
-            Err(_) => continue,
-        };
-        match value.get("type").and_then(Value::as_str) {
-            Some("user") => {
-                user += 1;
-                if with_preview && preview.is_none() {
-                    let p = extract_message_text(&value, false);
+fn session_summary(
+    session: &Session,
+    counts: SessionCounts,
+    preview_mode: Option<PreviewMode>,
+) -> SessionSummary {
+    SessionSummary {
+        session: session.clone(),
+        title: counts.title.title().map(str::to_string),
+        preview: preview_mode.and_then(|mode| counts.preview(mode)),
+        user_messages: counts.user_messages,
+        assistant_messages: counts.assistant_messages,
+        other_records: counts.other_records,
+        tool_uses: counts.tool_uses,
+        images: counts.images,
+        models: counts.models,
+        files: counts.files,
+        active: false,
+        ended_by: None,
+        tags: Vec::new(),
+        health: None,
+    }
+}
+
+fn count_session(session: &Session, time_window: &TimeWindow) -> Result<SessionCounts> {
+    let mut counts = SessionCounts::default();
+    let mut first_prompt = None;
+    let mut last_answer = None;
+    let mut files = BTreeSet::new();
+    let mut tools = BTreeSet::new();
+    let mut branches = BTreeSet::new();
+    // A stitched session's later files may repeat records of the earlier ones.
+    let mut seen = HashSet::new();
+    for path in session.paths() {
+        let reader = BufReader::new(open_transcript(path)?);
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let Ok(record) = serde_json::from_str(&line).and_then(|v| Record::from_value(&v))
+            else {
+                continue;
+            };
+            if !time_window.admits(record.head.timestamp.as_deref()) {
+                continue;
+            }
+            if let Some(uuid) = &record.head.uuid {
+                if !seen.insert(uuid.clone()) {
+                    continue;
+                }
+            }
+            counts.title.observe(&record);
+            files.extend(
+                tool_calls(&record)
+                    .flat_map(|call| call.paths())
+                    .map(str::to_string),
+            );
+            tools.extend(tool_calls(&record).map(|call| call.name.to_string()));
+            if let Some(branch) = record.head.git_branch.as_ref().filter(|b| !b.is_empty()) {
+                branches.insert(branch.clone());
+            }
+            for block in record.message().into_iter().flat_map(Message::blocks) {
+                match block {
+                    ContentBlock::ToolUse { .. } => counts.tool_uses += 1,
+                    ContentBlock::Image { .. } => counts.images += 1,
+                    _ => {}
+                }
+            }
+            match &record.body {
+                RecordBody::User(message) => {
+                    counts.user_messages += 1;
+                    if first_prompt.is_none() {
+                        first_prompt = Some(message.message.content.text(false))
+                            .filter(|p| !p.trim().is_empty());
+                    }
+                }
+                RecordBody::Assistant(message) => {
+                    counts.assistant_messages += 1;
+                    let p = message.message.content.text(false);
                     if !p.trim().is_empty() {
-                        preview = Some(clean_preview(&p));
+                        last_answer = Some(p);
+                    }
+                    if let Some(model) = &message.message.model {
+                        if !counts.models.contains(model) {
+                            counts.models.push(model.clone());
+                        }
                     }
                 }
+                _ => counts.other_records += 1,
             }
-            Some("assistant") => assistant += 1,
-            _ => other += 1,
         }
     }
-
-    Ok(SessionSummary {
-        session: session.clone(),
-        user_messages: user,
-        assistant_messages: assistant,
-        other_records: other,
-        preview,
-    })
+    counts.files = files.into_iter().collect();
+    counts.tools = tools.into_iter().collect();
+    counts.branches = branches.into_iter().collect();
+    counts.first_prompt = first_prompt.map(|p| clean_preview_to(&p, PREVIEW_MAX_WIDTH));
+    counts.last_answer = last_answer.map(|p| clean_preview_to(&p, PREVIEW_MAX_WIDTH));
+    Ok(counts)
 }
 
-fn parse_session_events(path: &Path, detailed: bool) -> Result<ParseOutput> {
-    let f = File::open(path)?;
-    let reader = BufReader::new(f);
-    let mut events = Vec::new();
-    let mut parse_errors = 0u64;
+/// Gaps between consecutive record timestamps longer than this are flagged.
+const ABNORMAL_GAP_SECS: i64 = 12 * 3600;
 
-    for line in reader.lines() {
+fn scan_session_health(path: &Path) -> Result<SessionHealth> {
+    let f = open_transcript(path)?;
+    let reader = BufReader::new(f);
+    let mut health = SessionHealth::default();
+    let mut last_line_ok = true;
+    let mut prev_ts: Option<DateTime<Utc>> = None;
+    let mut missing_type = 0u64;
+    let mut mistyped = 0u64;
+    let mut unknown_types: HashMap<String, u64> = HashMap::new();
+
+    for (i, line) in reader.lines().enumerate() {
         let line = line?;
         if line.trim().is_empty() {
             continue;
@@ -821,305 +9765,100 @@ fn parse_session_events(path: &Path, detailed: bool) -> Result<ParseOutput> {
         let value: Value = match serde_json::from_str(&line) {
             Ok(v) => v,
             Err(_) => {
-                parse_errors += 1;
+                health.parse_errors += 1;
+                health.parse_error_lines.push(i + 1);
+                last_line_ok = false;
                 continue;
             }
         };
-        let record_type = value
-            .get("type")
-            .and_then(Value::as_str)
-            .unwrap_or("unknown");
-        let timestamp = value
+        last_line_ok = true;
+        health.records += 1;
+        mistyped += u64::from(Record::from_value(&value).is_err());
+
+        let record_type = value.get("type").and_then(Value::as_str).unwrap_or("");
+        if record_type.is_empty() {
+            missing_type += 1;
+        } else if !KNOWN_RECORD_TYPES.contains(&record_type) {
+            *unknown_types.entry(record_type.to_string()).or_insert(0) += 1;
+        }
+        let ts = value
             .get("timestamp")
             .and_then(Value::as_str)
-            .map(|s| s.to_string());
-
-        match record_type {
-            "user" => {
-                let text = extract_message_text(&value, detailed);
-                if !text.trim().is_empty() {
-                    events.push(NormalizedEvent {
-                        role: "user".to_string(),
-                        source_type: "user".to_string(),
-                        timestamp,
-                        content: text,
-                    });
-                }
-            }
-            "assistant" => {
-                let text = extract_message_text(&value, detailed);
-                if !text.trim().is_empty() {
-                    events.push(NormalizedEvent {
-                        role: "assistant".to_string(),
-                        source_type: "assistant".to_string(),
-                        timestamp,
-                        content: text,
-                    });
-                }
-            }
-            "system" | "progress" | "queue-operation" | "file-history-snapshot" => {
-                if detailed {
-                    let short = summarize_non_dialog_record(&value);
-                    events.push(NormalizedEvent {
-                        role: record_type.to_string(),
-                        source_type: record_type.to_string(),
-                        timestamp,
-                        content: short,
-                    });
+            .and_then(|raw| DateTime::parse_from_rfc3339(raw).ok())
+            .map(|dt| dt.with_timezone(&Utc));
+        match ts {
+            Some(ts) => {
+                if let Some(prev) = prev_ts {
+                    let gap = (ts - prev).num_seconds();
+                    if gap < 0 {
+                        health.out_of_order_timestamps += 1;
+                    } else if health.max_gap_secs.is_none_or(|m| gap > m) {
+                        health.max_gap_secs = Some(gap);
+                    }
                 }
+                prev_ts = Some(ts);
             }
-            _ => {
-                if detailed {
-                    events.push(NormalizedEvent {
-                        role: record_type.to_string(),
-                        source_type: record_type.to_string(),
-                        timestamp,
-                        content: truncate_value(&value, 500),
-                    });
+            None => {
+                if matches!(record_type, "user" | "assistant") {
+                    health.missing_timestamps += 1;
                 }
             }
         }
     }
 
-    Ok(ParseOutput {
-        events,
-        parse_errors,
-    })
-}
-
-fn extract_message_text(record: &Value, detailed: bool) -> String {
-    let Some(message) = record.get("message") else {
-        return String::new();
-    };
-    let Some(content) = message.get("content") else {
-        return String::new();
-    };
-    extract_content_text(content, detailed)
-}
-
-fn extract_content_text(content: &Value, detailed: bool) -> String {
-    if let Some(s) = content.as_str() {
-        return s.to_string();
+    health.truncated_last_line = !last_line_ok;
+    if missing_type > 0 {
+        health
+            .schema_anomalies
+            .push(format!("{missing_type} record(s) without a type field"));
     }
-    if let Some(arr) = content.as_array() {
-        let mut parts = Vec::new();
-        for item in arr {
-            if let Some(obj) = item.as_object() {
-                let item_type = obj.get("type").and_then(Value::as_str).unwrap_or("");
-                match item_type {
-                    "text" => {
-                        if let Some(txt) = obj.get("text").and_then(Value::as_str) {
-                            parts.push(txt.to_string());
-                        }
-                    }
-                    "thinking" if detailed => {
-                        let thinking = obj.get("thinking").and_then(Value::as_str).unwrap_or("");
-                        parts.push(format!("[thinking]\n{thinking}"));
-                    }
-                    "tool_use" if detailed => {
-                        let name = obj.get("name").and_then(Value::as_str).unwrap_or("unknown");
-                        let input = obj.get("input").cloned().unwrap_or_else(|| json!({}));
-                        parts.push(format!(
-                            "[tool_use] {}\n{}",
-                            name,
-                            serde_json::to_string_pretty(&input)
-                                .unwrap_or_else(|_| "{}".to_string())
-                        ));
-                    }
-                    "tool_result" if detailed => {
-                        let tool_use_id = obj
-                            .get("tool_use_id")
-                            .and_then(Value::as_str)
-                            .unwrap_or("unknown");
-                        let result_content = obj.get("content").cloned().unwrap_or(Value::Null);
-                        parts.push(format!(
-                            "[tool_result] {}\n{}",
-                            tool_use_id,
-                            truncate_value(&result_content, 1200)
-                        ));
-                    }
-                    "image" if detailed => parts.push("[image omitted]".to_string()),
-                    "document" if detailed => parts.push("[document omitted]".to_string()),
-                    _ => {}
-                }
-            } else if let Some(s) = item.as_str() {
-                parts.push(s.to_string());
-            }
-        }
-        return parts.join("\n");
+    if mistyped > 0 {
+        health.schema_anomalies.push(format!(
+            "{mistyped} record(s) with fields of an unexpected type"
+        ));
     }
-    truncate_value(content, 1200)
-}
-
-fn summarize_non_dialog_record(value: &Value) -> String {
-    let record_type = value
-        .get("type")
-        .and_then(Value::as_str)
-        .unwrap_or("unknown");
-    match record_type {
-        "progress" => {
-            let ptype = value
-                .get("data")
-                .and_then(|d| d.get("type"))
-                .and_then(Value::as_str)
-                .unwrap_or("unknown");
-            let hook = value
-                .get("data")
-                .and_then(|d| d.get("hookName"))
-                .and_then(Value::as_str)
-                .unwrap_or("");
-            let cmd = value
-                .get("data")
-                .and_then(|d| d.get("command"))
-                .and_then(Value::as_str)
-                .unwrap_or("");
-            let mut s = format!("progress:{ptype}");
-            if !hook.is_empty() {
-                s.push_str(&format!(" hook={hook}"));
-            }
-            if !cmd.is_empty() {
-                s.push_str(&format!(" cmd={}", ellipsize(cmd, 120)));
-            }
-            s
-        }
-        "system" => {
-            let subtype = value
-                .get("subtype")
-                .and_then(Value::as_str)
-                .unwrap_or("unknown");
-            format!("system:{subtype}")
-        }
-        "queue-operation" => {
-            let op = value
-                .get("operation")
-                .and_then(Value::as_str)
-                .unwrap_or("unknown");
-            format!("queue-operation:{op}")
-        }
-        "file-history-snapshot" => "file-history-snapshot".to_string(),
-        _ => truncate_value(value, 300),
+    for (record_type, count) in top_n_sorted_map(unknown_types, usize::MAX) {
+        health
+            .schema_anomalies
+            .push(format!("{count} record(s) of unknown type `{record_type}`"));
     }
-}
-
-fn search_sessions(sessions: &[Session], args: &SearchArgs) -> Result<Vec<SearchHit>> {
-    let regex = if matches!(args.mode, SearchMode::Regex) {
-        Some(
-            RegexBuilder::new(&args.query)
-                .case_insensitive(!args.case_sensitive)
-                .build()
-                .with_context(|| format!("Invalid regex: {}", args.query))?,
-        )
-    } else {
-        None
-    };
-
-    let query_normalized = if args.case_sensitive {
-        args.query.clone()
-    } else {
-        args.query.to_lowercase()
-    };
-    let query_tokens = query_normalized
-        .split_whitespace()
-        .filter(|t| !t.is_empty())
-        .collect::<Vec<_>>();
-
-    let mut hits = Vec::new();
-    for session in sessions {
-        let parsed = parse_session_events(&session.path, false)?;
-        for event in parsed.events {
-            if args.speaker != SpeakerFilter::Both {
-                if args.speaker == SpeakerFilter::User && event.role != "user" {
-                    continue;
-                }
-                if args.speaker == SpeakerFilter::Assistant && event.role != "assistant" {
-                    continue;
-                }
-            }
-
-            let haystack = if args.case_sensitive {
-                event.content.clone()
-            } else {
-                event.content.to_lowercase()
-            };
-            let (matched, relevance) = match args.mode {
-                SearchMode::Exact => {
-                    if haystack.contains(&query_normalized) {
-                        let count = haystack.matches(&query_normalized).count() as f64;
-                        (true, (0.5 + (count * 0.1)).min(1.0))
-                    } else {
-                        (false, 0.0)
-                    }
-                }
-                SearchMode::Regex => {
-                    let re = regex.as_ref().expect("regex compiled");
-                    let m = re.find(&event.content);
-                    if m.is_some() {
-                        (true, 0.8)
-                    } else {
-                        (false, 0.0)
-                    }
-                }
-                SearchMode::Smart => {
-                    let mut score = 0.0;
-                    if haystack.contains(&query_normalized) {
-                        score += 0.6;
-                    }
-                    if !query_tokens.is_empty() {
-                        let overlap = query_tokens
-                            .iter()
-                            .filter(|tok| haystack.contains(**tok))
-                            .count() as f64;
-                        score += (overlap / query_tokens.len() as f64) * 0.4;
-                    }
-                    (score > 0.15, score.min(1.0))
-                }
-            };
-
-            if matched {
-                let preview = build_context_preview(
-                    &event.content,
-                    &args.query,
-                    args.context_chars,
-                    args.case_sensitive,
-                );
-                hits.push(SearchHit {
-                    session_id: session.id.clone(),
-                    project: session.project.clone(),
-                    path: session.path.clone(),
-                    speaker: event.role,
-                    timestamp: event.timestamp,
-                    relevance,
-                    preview,
-                });
-            }
-        }
+    if health.parse_errors > 0 {
+        health.flags.push("parse-errors".to_string());
     }
-
-    hits.sort_by(|a, b| {
-        b.relevance
-            .partial_cmp(&a.relevance)
-            .unwrap_or(std::cmp::Ordering::Equal)
-            .then_with(|| a.session_id.cmp(&b.session_id))
-    });
-    Ok(hits)
+    if health.truncated_last_line {
+        health.flags.push("truncated".to_string());
+    }
+    if health.missing_timestamps > 0 {
+        health.flags.push("missing-ts".to_string());
+    }
+    if health.out_of_order_timestamps > 0 {
+        health.flags.push("out-of-order".to_string());
+    }
+    if health.max_gap_secs.is_some_and(|g| g > ABNORMAL_GAP_SECS) {
+        health.flags.push("gap".to_string());
+    }
+    if !health.schema_anomalies.is_empty() {
+        health.flags.push("schema".to_string());
+    }
+    Ok(health)
 }
 
-fn select_sessions_for_export(sessions: &[Session], args: &ExportArgs) -> Result<Vec<Session>> {
-    let mut selected_by_id: HashSet<String> = HashSet::new();
+fn select_sessions(
+    sessions: &[Session],
+    args: &SelectionArgs,
+    time_window: &TimeWindow,
+    global: &GlobalArgs,
+) -> Result<Vec<Session>> {
+    let mut selected_paths: HashSet<PathBuf> = HashSet::new();
     let mut selected = Vec::new();
     let mut push_unique = |s: &Session| {
-        if selected_by_id.insert(s.id.clone()) {
+        if selected_paths.insert(s.path.clone()) {
             selected.push(s.clone());
         }
     };
 
     for sid in &args.sessions {
-        let found = sessions.iter().find(|s| &s.id == sid || &s.id_short == sid);
-        if let Some(s) = found {
-            push_unique(s);
-        } else {
-            bail!("Session not found: {}", sid);
-        }
+        push_unique(find_session(sessions, sid)?);
     }
 
     for idx in &args.indices {
@@ -1145,15 +9884,13 @@ fn select_sessions_for_export(sessions: &[Session], args: &ExportArgs) -> Result
     }
 
     if let Some(query) = &args.search {
-        let search_args = SearchArgs {
-            query: query.clone(),
-            mode: SearchMode::Smart,
-            speaker: SpeakerFilter::Both,
-            case_sensitive: false,
-            max_results: usize::MAX,
-            context_chars: 150,
-        };
-        let hits = search_sessions(sessions, &search_args)?;
+        let options = SearchOptions {
+            time_window: time_window.clone(),
+            unknown_records: global.unknown_records(),
+            analysis: global.search_analysis(),
+            ..SearchOptions::smart(query.clone())
+        };
+        let hits = search_sessions(sessions, &options)?;
         let hit_sessions: HashSet<String> = hits.into_iter().map(|h| h.session_id).collect();
         for s in sessions {
             if hit_sessions.contains(&s.id) {
@@ -1162,9 +9899,24 @@ fn select_sessions_for_export(sessions: &[Session], args: &ExportArgs) -> Result
         }
     }
 
+    if !args.tags.is_empty() {
+        let store = TagStore::load(&tags_path(global)?)?;
+        for s in sessions {
+            if store.has_all(&s.id, &args.tags) {
+                push_unique(s);
+            }
+        }
+    }
+
+    if let (true, Some(query)) = (selected.is_empty(), &args.search) {
+        bail!("No sessions match --search {query}");
+    }
+    if selected.is_empty() && !args.tags.is_empty() {
+        bail!("No sessions are tagged {}", args.tags.join(" and "));
+    }
     if selected.is_empty() {
         bail!(
-            "No selection flags provided. Use one of: --session, --index, --recent, --all, --search"
+            "No selection flags provided. Use one of: --session, --index, --recent, --all, --search, --tag"
         );
     }
 
@@ -1172,133 +9924,282 @@ fn select_sessions_for_export(sessions: &[Session], args: &ExportArgs) -> Result
     Ok(selected)
 }
 
+/// `export --interactive`: ask for a filter, then let the user check off
+/// sessions among those matching it. `None` if they backed out.
+fn pick_sessions(sessions: &[Session], time_window: &TimeWindow) -> Result<Option<Vec<Session>>> {
+    let term = console::Term::stderr();
+    if !term.is_term() {
+        bail!("--interactive needs a terminal; select sessions with --session, --index, --recent, --search, or --tag instead");
+    }
+    let (rows, columns) = term.size();
+    let labels = sessions
+        .par_iter()
+        .map(|session| {
+            let summary = summarize_session(session, Some(PreviewMode::FirstPrompt), time_window)?;
+            let label = format!(
+                "{:>3}  {}  {}  {}",
+                session.index,
+                session
+                    .modified_iso
+                    .get(..10)
+                    .unwrap_or(&session.modified_iso),
+                session.project,
+                summary.preview.as_deref().unwrap_or("-")
+            );
+            // Room for the checkbox, so long lines don't wrap and break the list.
+            Ok(ellipsize(&label, usize::from(columns).saturating_sub(6)))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let query: String = dialoguer::Input::new()
+        .with_prompt("Filter (words to match, empty for all)")
+        .allow_empty(true)
+        .interact_text_on(&term)
+        .context("Failed to read the filter")?;
+    let candidates = labels
+        .iter()
+        .enumerate()
+        .filter(|(_, label)| fuzzy_matches(label, &query))
+        .collect::<Vec<_>>();
+    if candidates.is_empty() {
+        bail!("No sessions match {query:?}");
+    }
+    let picked = dialoguer::MultiSelect::new()
+        .with_prompt("Sessions to export (space toggles, enter confirms, esc cancels)")
+        .items(candidates.iter().map(|(_, label)| label.as_str()))
+        .max_length(usize::from(rows).saturating_sub(4).max(5))
+        .interact_on_opt(&term)
+        .context("Failed to read the selection")?;
+    Ok(picked.filter(|picked| !picked.is_empty()).map(|picked| {
+        picked
+            .into_iter()
+            .map(|i| sessions[candidates[i].0].clone())
+            .collect()
+    }))
+}
+
+/// Whether each word of `query` appears in `text` as a subsequence,
+/// ignoring case, so "lgn tst" finds "login test".
+fn fuzzy_matches(text: &str, query: &str) -> bool {
+    let text = text.to_lowercase();
+    query.split_whitespace().all(|word| {
+        let mut chars = text.chars();
+        word.to_lowercase().chars().all(|c| chars.any(|t| t == c))
+    })
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct ReplayExchange {
+    prompt_index: usize,
+    timestamp: Option<String>,
+    prompt: String,
+    original_response: String,
+    replay_response: Option<String>,
+    replay_error: Option<String>,
+    input_tokens: Option<u64>,
+    output_tokens: Option<u64>,
+    latency_ms: Option<u64>,
+}
+
 #[derive(Debug, Clone, Serialize)]
-struct ExportDocument {
+struct ReplayDocument {
     session_id: String,
     session_short: String,
     project: String,
     source_path: PathBuf,
-    modified_iso: String,
-    event_count: usize,
-    events: Vec<NormalizedEvent>,
+    model: String,
+    replayed_at: String,
+    exchanges: Vec<ReplayExchange>,
 }
 
-fn build_export_document(session: &Session, events: &[NormalizedEvent]) -> ExportDocument {
-    ExportDocument {
-        session_id: session.id.clone(),
-        session_short: session.id_short.clone(),
-        project: session.project.clone(),
-        source_path: session.path.clone(),
-        modified_iso: session.modified_iso.clone(),
-        event_count: events.len(),
-        events: events.to_vec(),
+struct ReplayReply {
+    text: String,
+    input_tokens: Option<u64>,
+    output_tokens: Option<u64>,
+}
+
+/// The session's prompts and the answers they got, as `pair_exchanges` pairs
+/// them, numbered for the replay.
+fn replay_exchanges(events: &[NormalizedEvent]) -> Vec<ReplayExchange> {
+    pair_exchanges(events)
+        .into_iter()
+        .enumerate()
+        .map(|(i, exchange)| ReplayExchange {
+            prompt_index: i + 1,
+            timestamp: exchange.asked_at,
+            prompt: exchange.question,
+            original_response: exchange.answer,
+            replay_response: None,
+            replay_error: None,
+            input_tokens: None,
+            output_tokens: None,
+            latency_ms: None,
+        })
+        .collect()
+}
+
+fn build_replay_messages(exchanges: &[ReplayExchange], i: usize, with_history: bool) -> Vec<Value> {
+    let mut messages = Vec::new();
+    if with_history {
+        for prior in &exchanges[..i] {
+            messages.push(json!({ "role": "user", "content": prior.prompt }));
+            if !prior.original_response.trim().is_empty() {
+                messages.push(json!({ "role": "assistant", "content": prior.original_response }));
+            }
+        }
     }
+    messages.push(json!({ "role": "user", "content": exchanges[i].prompt }));
+    messages
 }
 
-fn write_single_export(
-    output_dir: &Path,
-    doc: &ExportDocument,
-    format: ExportFormat,
-) -> Result<PathBuf> {
-    let date = doc.modified_iso.split('T').next().unwrap_or("unknown-date");
-    let ext = match format {
-        ExportFormat::Markdown => "md",
-        ExportFormat::Json => "json",
-        ExportFormat::Html => "html",
-    };
-    let filename = format!("cc-convo-{date}-{}.{}", doc.session_short, ext);
-    let path = output_dir.join(filename);
-    let body = match format {
-        ExportFormat::Markdown => render_markdown(std::slice::from_ref(doc)),
-        ExportFormat::Json => serde_json::to_string_pretty(doc)?,
-        ExportFormat::Html => render_html(std::slice::from_ref(doc)),
+fn send_messages_request(
+    api_url: &str,
+    api_key: &str,
+    model: &str,
+    max_tokens: u32,
+    messages: &[Value],
+) -> Result<ReplayReply> {
+    let body = json!({
+        "model": model,
+        "max_tokens": max_tokens,
+        "messages": messages,
+    });
+    let response = ureq::post(api_url)
+        .set("x-api-key", api_key)
+        .set("anthropic-version", "2023-06-01")
+        .set("content-type", "application/json")
+        .send_string(&body.to_string());
+    let response = match response {
+        Ok(r) => r,
+        Err(ureq::Error::Status(code, r)) => {
+            let detail = r.into_string().unwrap_or_default();
+            bail!("API returned HTTP {code}: {}", ellipsize(&detail, 300));
+        }
+        Err(err) => return Err(anyhow!(err).context("API request failed")),
     };
-    fs::write(&path, body)?;
-    Ok(path)
+    let payload: Value = serde_json::from_str(&response.into_string()?)
+        .context("API response was not valid JSON")?;
+    let text = payload
+        .get("content")
+        .map(|c| extract_content_text(c, false))
+        .unwrap_or_default();
+    let usage = payload.get("usage");
+    Ok(ReplayReply {
+        text,
+        input_tokens: usage
+            .and_then(|u| u.get("input_tokens"))
+            .and_then(Value::as_u64),
+        output_tokens: usage
+            .and_then(|u| u.get("output_tokens"))
+            .and_then(Value::as_u64),
+    })
 }
 
-fn write_bundle_export(
+fn write_replay_export(
     output_dir: &Path,
-    docs: &[ExportDocument],
+    doc: &ReplayDocument,
     format: ExportFormat,
 ) -> Result<PathBuf> {
-    let now = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true);
-    let date = now.split('T').next().unwrap_or("unknown-date");
-    let ext = match format {
-        ExportFormat::Markdown => "md",
-        ExportFormat::Json => "json",
-        ExportFormat::Html => "html",
-    };
-    let path = output_dir.join(format!("cc-convo-bundle-{date}.{ext}"));
+    let date = doc.replayed_at.split('T').next().unwrap_or("unknown-date");
+    let ext = format.extension();
+    let path = output_dir.join(format!(
+        "cc-convo-replay-{date}-{}.{ext}",
+        doc.session_short
+    ));
+    // A replay is already prompt/answer pairs.
     let body = match format {
-        ExportFormat::Markdown => render_markdown(docs),
-        ExportFormat::Json => serde_json::to_string_pretty(docs)?,
-        ExportFormat::Html => render_html(docs),
+        ExportFormat::Markdown | ExportFormat::QaMarkdown => render_replay_markdown(doc),
+        ExportFormat::Json | ExportFormat::QaJson => serde_json::to_string_pretty(doc)?,
+        ExportFormat::Html => render_replay_html(doc),
+        ExportFormat::OpenaiJsonl | ExportFormat::AnthropicMessages => {
+            bail!("Replays have no dataset format")
+        }
+        ExportFormat::Obsidian => bail!("Replays have no Obsidian format"),
     };
-    fs::write(&path, body)?;
+    write_atomic(&path, body)?;
     Ok(path)
 }
 
-fn render_markdown(docs: &[ExportDocument]) -> String {
+fn replay_outcome(ex: &ReplayExchange) -> String {
+    match (&ex.replay_response, &ex.replay_error) {
+        (Some(text), _) => text.clone(),
+        (None, Some(err)) => format!("[replay failed] {err}"),
+        (None, None) => "-".to_string(),
+    }
+}
+
+fn render_replay_markdown(doc: &ReplayDocument) -> String {
     let mut out = String::new();
-    for (di, doc) in docs.iter().enumerate() {
-        if di > 0 {
-            out.push_str("\n\n---\n\n");
-        }
-        out.push_str("# cc-convo export\n\n");
-        out.push_str(&format!("- Session: `{}`\n", doc.session_id));
-        out.push_str(&format!("- Project: `{}`\n", doc.project));
-        out.push_str(&format!("- Modified: `{}`\n", doc.modified_iso));
-        out.push_str(&format!("- Source: `{}`\n", doc.source_path.display()));
-        out.push_str(&format!("- Events: `{}`\n\n", doc.event_count));
-        for event in &doc.events {
-            out.push_str(&format!(
-                "## [{}] {}\n\n",
-                event.role,
-                event.timestamp.clone().unwrap_or_else(|| "-".to_string())
-            ));
-            out.push_str(&event.content);
-            out.push_str("\n\n");
+    out.push_str("# cc-convo replay\n\n");
+    out.push_str(&format!("- Session: `{}`\n", doc.session_id));
+    out.push_str(&format!("- Project: `{}`\n", doc.project));
+    out.push_str(&format!("- Replay model: `{}`\n", doc.model));
+    out.push_str(&format!("- Replayed: `{}`\n", doc.replayed_at));
+    out.push_str(&format!("- Prompts: `{}`\n\n", doc.exchanges.len()));
+    for ex in &doc.exchanges {
+        out.push_str(&format!(
+            "## Prompt {} {}\n\n",
+            ex.prompt_index,
+            ex.timestamp.clone().unwrap_or_else(|| "-".to_string())
+        ));
+        out.push_str(&ex.prompt);
+        out.push_str("\n\n### Original\n\n");
+        if ex.original_response.is_empty() {
+            out.push('-');
+        } else {
+            out.push_str(&ex.original_response);
         }
+        out.push_str(&format!("\n\n### Replay ({})\n\n", doc.model));
+        out.push_str(&replay_outcome(ex));
+        out.push_str("\n\n");
     }
     out
 }
 
-fn render_html(docs: &[ExportDocument]) -> String {
+fn render_replay_html(doc: &ReplayDocument) -> String {
     let mut out = String::new();
     out.push_str(
-        "<!doctype html><html><head><meta charset=\"utf-8\"><title>cc-convo export</title>",
+        "<!doctype html><html><head><meta charset=\"utf-8\"><title>cc-convo replay</title>",
     );
-    out.push_str("<style>body{font-family:ui-sans-serif,system-ui;margin:2rem;background:#f7f8fa;color:#1e2430} .card{background:#fff;border-radius:12px;padding:16px 20px;margin:0 0 16px 0;box-shadow:0 1px 2px rgba(0,0,0,.06)} .meta{color:#5c667a;font-size:.92rem} pre{white-space:pre-wrap;word-break:break-word;margin:0} h1,h2{margin:.2rem 0 .8rem} </style>");
-    out.push_str("</head><body><h1>cc-convo export</h1>");
-    for doc in docs {
+    out.push_str("<style>body{font-family:ui-sans-serif,system-ui;margin:2rem;background:#f7f8fa;color:#1e2430} .card{background:#fff;border-radius:12px;padding:16px 20px;margin:0 0 16px 0;box-shadow:0 1px 2px rgba(0,0,0,.06)} .meta{color:#5c667a;font-size:.92rem} .cols{display:grid;grid-template-columns:1fr 1fr;gap:16px} pre{white-space:pre-wrap;word-break:break-word;margin:0} h1,h2,h3{margin:.2rem 0 .8rem} </style>");
+    out.push_str("</head><body><h1>cc-convo replay</h1>");
+    out.push_str(&format!(
+        "<div class=\"card\"><h2>{}</h2><div class=\"meta\">project={} model={} replayed={} prompts={}</div></div>",
+        html_escape(&doc.session_id),
+        html_escape(&doc.project),
+        html_escape(&doc.model),
+        html_escape(&doc.replayed_at),
+        doc.exchanges.len()
+    ));
+    for ex in &doc.exchanges {
         out.push_str("<div class=\"card\">");
         out.push_str(&format!(
-            "<h2>{}</h2><div class=\"meta\">project={} modified={} source={} events={}</div>",
-            html_escape(&doc.session_id),
-            html_escape(&doc.project),
-            html_escape(&doc.modified_iso),
-            html_escape(&doc.source_path.display().to_string()),
-            doc.event_count
+            "<h2>Prompt {} {}</h2><pre>{}</pre>",
+            ex.prompt_index,
+            html_escape(&ex.timestamp.clone().unwrap_or_else(|| "-".to_string())),
+            html_escape(&ex.prompt)
+        ));
+        out.push_str(&format!(
+            "<div class=\"cols\"><div><h3>Original</h3><pre>{}</pre></div><div><h3>Replay ({})</h3><pre>{}</pre></div></div>",
+            html_escape(&ex.original_response),
+            html_escape(&doc.model),
+            html_escape(&replay_outcome(ex))
         ));
         out.push_str("</div>");
-        for event in &doc.events {
-            out.push_str("<div class=\"card\">");
-            out.push_str(&format!(
-                "<h2>[{}] {}</h2><pre>{}</pre>",
-                html_escape(&event.role),
-                html_escape(&event.timestamp.clone().unwrap_or_else(|| "-".to_string())),
-                html_escape(&event.content)
-            ));
-            out.push_str("</div>");
-        }
     }
     out.push_str("</body></html>");
     out
 }
 
-fn resolve_session_target<'a>(sessions: &'a [Session], target: &str) -> Result<&'a Session> {
+fn resolve_session_target<'a>(
+    sessions: &'a [Session],
+    target: &str,
+    nth: Option<usize>,
+) -> Result<&'a Session> {
     if let Ok(index) = target.parse::<usize>() {
+        if nth.is_some() {
+            bail!("--nth picks among a project's sessions, but {target} is a session index");
+        }
         if index == 0 {
             bail!("Session index is 1-based; got 0");
         }
@@ -1306,153 +10207,186 @@ fn resolve_session_target<'a>(sessions: &'a [Session], target: &str) -> Result<&
             .get(index - 1)
             .ok_or_else(|| anyhow!("Invalid session index {}", index));
     }
-    sessions
-        .iter()
-        .find(|s| s.id == target || s.id_short == target)
-        .ok_or_else(|| anyhow!("Session not found: {}", target))
-}
-
-fn short_id(full: &str) -> String {
-    full.chars().take(8).collect()
+    resolve_target(sessions, target, nth)
 }
 
-fn build_context_preview(
-    text: &str,
-    query: &str,
-    context_chars: usize,
-    case_sensitive: bool,
-) -> String {
-    if query.is_empty() {
-        return ellipsize(&text.replace('\n', " "), context_chars.saturating_mul(2));
+fn summary_badges(summary: &SessionSummary) -> String {
+    let mut badges = Vec::new();
+    if summary.tool_uses > 0 {
+        badges.push(format!("[{} tools]", summary.tool_uses));
     }
-
-    let matched_range = if case_sensitive {
-        text.find(query).map(|start| (start, query.len()))
-    } else {
-        let escaped = regex::escape(query);
-        RegexBuilder::new(&escaped)
-            .case_insensitive(true)
-            .build()
-            .ok()
-            .and_then(|re| re.find(text).map(|m| (m.start(), m.end() - m.start())))
-    };
-
-    if let Some((match_start_byte, match_len_bytes)) = matched_range {
-        let total_chars = text.chars().count();
-        let match_start_char = text[..match_start_byte].chars().count();
-        let match_len_char = text[match_start_byte..match_start_byte + match_len_bytes]
-            .chars()
-            .count();
-
-        let start_char = match_start_char.saturating_sub(context_chars);
-        let end_char = (match_start_char + match_len_char + context_chars).min(total_chars);
-        let start_byte = byte_index_for_char_pos(text, start_char);
-        let end_byte = byte_index_for_char_pos(text, end_char);
-        let slice = &text[start_byte..end_byte];
-
-        let mut preview = String::new();
-        if start_char > 0 {
-            preview.push_str("...");
-        }
-        preview.push_str(slice);
-        if end_char < total_chars {
-            preview.push_str("...");
-        }
-        preview.replace('\n', " ")
-    } else {
-        ellipsize(&text.replace('\n', " "), context_chars.saturating_mul(2))
+    if summary.images > 0 {
+        badges.push(format!("[{} imgs]", summary.images));
     }
-}
-
-fn clean_preview(s: &str) -> String {
-    ellipsize(&s.replace('\n', " ").trim().to_string(), 140)
-}
-
-fn ellipsize(s: &str, max: usize) -> String {
-    if s.chars().count() <= max {
-        s.to_string()
-    } else if max <= 3 {
-        ".".repeat(max)
-    } else {
-        let prefix = s.chars().take(max - 3).collect::<String>();
-        format!("{prefix}...")
+    if let Some(ended_by) = summary.ended_by {
+        badges.push(format!("[{}]", ended_by.as_str()));
+    }
+    let mut families = Vec::new();
+    for model in &summary.models {
+        let family = model_family(model);
+        if !families.contains(&family) {
+            families.push(family);
+        }
     }
+    for family in families {
+        badges.push(format!("[{family}]"));
+    }
+    badges.join(" ")
 }
 
-fn byte_index_for_char_pos(s: &str, char_pos: usize) -> usize {
-    if char_pos == 0 {
-        return 0;
+fn model_family(model: &str) -> String {
+    let lower = model.to_lowercase();
+    for family in ["opus", "sonnet", "haiku"] {
+        if lower.contains(family) {
+            return family.to_string();
+        }
     }
-    s.char_indices()
-        .nth(char_pos)
-        .map(|(idx, _)| idx)
-        .unwrap_or_else(|| s.len())
+    model.to_string()
 }
 
-fn truncate_value(v: &Value, max: usize) -> String {
-    let s = serde_json::to_string(v).unwrap_or_else(|_| "<invalid-json>".to_string());
-    ellipsize(&s, max)
+#[derive(Debug, Clone, Copy, Default)]
+struct SessionsTableColumns {
+    preview: bool,
+    /// Wrap the preview onto up to this many lines under the row.
+    preview_lines: Option<usize>,
+    health: bool,
 }
 
-fn print_sessions_table(items: &[SessionSummary], with_preview: bool) {
-    println!("{}", style("Sessions").bold().cyan());
+/// Indent of a preview wrapped under its `sessions list` row.
+const WRAPPED_PREVIEW_INDENT: usize = 6;
+
+/// Columns the title takes in `sessions list`, cut short with `...`.
+const SESSION_TITLE_WIDTH: usize = 32;
+
+fn print_sessions_table(items: &[SessionSummary], columns: SessionsTableColumns) {
+    println!("{}", palette().heading.apply_to(tr!("sessions-title")));
     if items.is_empty() {
-        println!("No sessions found.");
+        println!("{}", tr!("sessions-none"));
         return;
     }
-    if with_preview {
-        println!(
-            "{:<5} {:<10} {:<36} {:<26} {:<20} {:>8} {:>6} {:>6} {:>6}  {}",
-            "Idx",
-            "ShortId",
-            "SessionId",
-            "Project",
-            "Modified",
-            "SizeKB",
-            "User",
-            "Asst",
-            "Other",
-            "Preview"
-        );
-    } else {
-        println!(
-            "{:<5} {:<10} {:<36} {:<26} {:<20} {:>8} {:>6} {:>6} {:>6}",
-            "Idx", "ShortId", "SessionId", "Project", "Modified", "SizeKB", "User", "Asst", "Other"
-        );
+    let mut header = [
+        (tr!("col-idx"), 5),
+        (tr!("col-short-id"), 10),
+        (tr!("col-session-id"), 36),
+        (tr!("col-project"), 26),
+        (tr!("col-modified"), 20),
+    ]
+    .iter()
+    .map(|(title, width)| console::pad_str(title, *width, console::Alignment::Left, None))
+    .chain(
+        [
+            tr!("col-size-kb"),
+            tr!("col-user"),
+            tr!("col-asst"),
+            tr!("col-other"),
+        ]
+        .iter()
+        .zip([8, 6, 6, 6])
+        .map(|(title, width)| console::pad_str(title, width, console::Alignment::Right, None)),
+    )
+    .collect::<Vec<_>>()
+    .join(" ");
+    header.push_str("  ");
+    header.push_str(&console::pad_str(
+        &tr!("col-title"),
+        SESSION_TITLE_WIDTH,
+        console::Alignment::Left,
+        None,
+    ));
+    if columns.health {
+        header.push_str("  ");
+        header.push_str(&console::pad_str(
+            &tr!("col-health"),
+            24,
+            console::Alignment::Left,
+            None,
+        ));
+    }
+    if columns.preview && columns.preview_lines.is_none() {
+        header.push_str("  ");
+        header.push_str(&tr!("col-preview"));
     }
+    println!("{}", header.trim_end());
     for s in items {
-        let modified = &s.session.modified_iso;
-        if with_preview {
-            println!(
-                "{:<5} {:<10} {:<36} {:<26} {:<20} {:>8.1} {:>6} {:>6} {:>6}  {}",
-                s.session.index,
-                s.session.id_short,
-                s.session.id,
-                ellipsize(&s.session.project, 26),
-                modified,
-                s.session.size_bytes as f64 / 1024.0,
-                s.user_messages,
-                s.assistant_messages,
-                s.other_records,
-                s.preview.clone().unwrap_or_else(|| "-".to_string())
-            );
-        } else {
-            println!(
-                "{:<5} {:<10} {:<36} {:<26} {:<20} {:>8.1} {:>6} {:>6} {:>6}",
-                s.session.index,
-                s.session.id_short,
-                s.session.id,
-                ellipsize(&s.session.project, 26),
-                modified,
-                s.session.size_bytes as f64 / 1024.0,
-                s.user_messages,
-                s.assistant_messages,
-                s.other_records
-            );
+        let mut row = format!(
+            "{:<5} {:<10} {:<36} {} {:<20} {:>8} {:>6} {:>6} {:>6}",
+            format!("{}{}", s.session.index, if s.active { "*" } else { "" }),
+            s.session.id_short,
+            s.session.id,
+            // Padded by columns, so wide characters keep the table aligned.
+            console::pad_str(
+                &ellipsize(&s.session.project, 26),
+                26,
+                console::Alignment::Left,
+                None
+            ),
+            i18n::datetime(&s.session.modified_iso),
+            i18n::decimal(s.session.size_bytes as f64 / 1024.0, 1),
+            s.user_messages,
+            s.assistant_messages,
+            s.other_records
+        );
+        row.push_str("  ");
+        row.push_str(&console::pad_str(
+            &ellipsize(s.title.as_deref().unwrap_or("-"), SESSION_TITLE_WIDTH),
+            SESSION_TITLE_WIDTH,
+            console::Alignment::Left,
+            None,
+        ));
+        if columns.health {
+            let flags = s
+                .health
+                .as_ref()
+                .map(|h| h.flags.join(","))
+                .unwrap_or_default();
+            if flags.is_empty() {
+                row.push_str(&format!(
+                    "  {:<24}",
+                    palette().ok.apply_to(tr!("health-ok"))
+                ));
+            } else {
+                row.push_str(&format!(
+                    "  {:<24}",
+                    palette().warn.apply_to(ellipsize(&flags, 24))
+                ));
+            }
+        }
+        match (columns.preview, columns.preview_lines) {
+            (false, _) => println!("{}", row.trim_end()),
+            (true, None) => {
+                row.push_str("  ");
+                row.push_str(&preview_with_badges(s));
+                println!("{}", row.trim_end());
+            }
+            (true, Some(lines)) => {
+                println!("{}", row.trim_end());
+                let width = console::Term::stdout()
+                    .size_checked()
+                    .map_or(100, |(_, w)| w as usize);
+                let preview = s.preview.as_deref().unwrap_or("-");
+                let text = match summary_badges(s) {
+                    badges if badges.is_empty() => preview.to_string(),
+                    badges => format!("{badges} {preview}"),
+                };
+                let indent = " ".repeat(WRAPPED_PREVIEW_INDENT);
+                for line in wrap_words(&text, width.saturating_sub(WRAPPED_PREVIEW_INDENT), lines) {
+                    println!("{indent}{}", palette().meta.apply_to(line));
+                }
+            }
         }
     }
 }
 
+fn preview_with_badges(summary: &SessionSummary) -> String {
+    let badges = summary_badges(summary);
+    let preview = summary.preview.clone().unwrap_or_else(|| "-".to_string());
+    if badges.is_empty() {
+        preview
+    } else {
+        format!("{} {}", palette().meta.apply_to(badges), preview)
+    }
+}
+
 fn top_n_sorted_map(map: HashMap<String, u64>, n: usize) -> Vec<(String, u64)> {
     let mut vec = map.into_iter().collect::<Vec<_>>();
     vec.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
@@ -1462,24 +10396,170 @@ fn top_n_sorted_map(map: HashMap<String, u64>, n: usize) -> Vec<(String, u64)> {
 fn print_ranked_map(title: &str, items: &[(String, u64)]) {
     println!("{}", style(title).bold());
     if items.is_empty() {
-        println!("  (none)");
+        println!("  {}", tr!("none"));
         return;
     }
     for (k, v) in items {
-        println!("  {:>7}  {}", v, k);
+        println!("  {:>7}  {}", i18n::number(*v), k);
+    }
+}
+
+/// Summarize skipped lines on stderr, listing each with `--show-parse-errors`.
+fn report_parse_errors(count: u64, diagnostics: &[ParseDiagnostic], show: bool) {
+    if count == 0 {
+        return;
+    }
+    if !show {
+        eprintln!(
+            "{}",
+            palette().warn.apply_to(format!(
+                "Skipped {count} malformed JSON lines (see --show-parse-errors)."
+            ))
+        );
+        return;
+    }
+    eprintln!(
+        "{}",
+        palette()
+            .warn
+            .apply_to(format!("Skipped {count} malformed JSON lines:"))
+    );
+    for d in diagnostics {
+        let file = d
+            .file
+            .as_ref()
+            .map(|f| f.display().to_string())
+            .unwrap_or_else(|| "<input>".to_string());
+        eprintln!(
+            "  {file}:{} (byte {}) {}: {}",
+            d.line,
+            d.byte_offset,
+            style(d.kind.as_str()).bold(),
+            d.message
+        );
+        eprintln!("    {}", palette().meta.apply_to(&d.snippet));
+    }
+    let listed = diagnostics.len() as u64;
+    if listed < count {
+        eprintln!("  ... and {} more", count - listed);
+    }
+}
+
+fn report_lock_wait(lock_path: &Path) {
+    eprintln!(
+        "{}",
+        palette().meta.apply_to(format!(
+            "Waiting for another cc-convo process to release {}...",
+            lock_path.display()
+        ))
+    );
+}
+
+/// Per-item progress for long-running commands: an indicatif bar on a terminal,
+/// or NDJSON events on stderr with `--progress-json` for GUIs wrapping the CLI.
+struct Progress {
+    bar: Option<ProgressBar>,
+    ndjson: Option<ProgressEvents>,
+    done: AtomicU64,
+}
+
+struct ProgressEvents {
+    command: &'static str,
+    unit: &'static str,
+    total: u64,
+}
+
+impl Progress {
+    fn new(global: &GlobalArgs, command: &'static str, unit: &'static str, total: usize) -> Self {
+        let mut progress = Self::json_only(global, command, unit, total);
+        if progress.ndjson.is_none() && !global.json && total > 1 {
+            let pb = ProgressBar::new(total as u64);
+            pb.set_style(
+                ProgressStyle::with_template(
+                    "{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} {msg}",
+                )
+                .unwrap_or_else(|_| ProgressStyle::default_bar()),
+            );
+            progress.bar = Some(pb);
+        }
+        progress
+    }
+
+    /// Progress for commands that never draw a bar, only `--progress-json` events.
+    fn json_only(
+        global: &GlobalArgs,
+        command: &'static str,
+        unit: &'static str,
+        total: usize,
+    ) -> Self {
+        Self {
+            bar: None,
+            ndjson: global.progress_json.then_some(ProgressEvents {
+                command,
+                unit,
+                total: total as u64,
+            }),
+            done: AtomicU64::new(0),
+        }
+    }
+
+    fn inc(&self, message: &str) {
+        // Held across the count and the event so NDJSON counts stay in order
+        // when files finish on several threads.
+        let _stderr = self.ndjson.is_some().then(|| std::io::stderr().lock());
+        let done = self.done.fetch_add(1, Ordering::Relaxed) + 1;
+        if let Some(pb) = &self.bar {
+            pb.set_message(message.to_string());
+            pb.inc(1);
+        }
+        if let Some(events) = &self.ndjson {
+            events.emit(done, false);
+        }
+    }
+
+    fn done(&self) -> u64 {
+        self.done.load(Ordering::Relaxed)
+    }
+
+    fn finish(&self) {
+        if let Some(pb) = &self.bar {
+            if interrupted() {
+                pb.abandon_with_message("interrupted");
+            } else {
+                pb.finish_with_message("done");
+            }
+        }
+        if let Some(events) = &self.ndjson {
+            events.emit(self.done(), true);
+        }
+    }
+}
+
+impl ProgressEvents {
+    fn emit(&self, done: u64, finished: bool) {
+        let mut progress = serde_json::Map::new();
+        progress.insert("command".to_string(), json!(self.command));
+        progress.insert(self.unit.to_string(), json!(done));
+        progress.insert("total".to_string(), json!(self.total));
+        if finished {
+            progress.insert("finished".to_string(), json!(true));
+            if interrupted() {
+                progress.insert("interrupted".to_string(), json!(true));
+            }
+        }
+        let line = json!({ "progress": progress }).to_string();
+        let stderr = std::io::stderr();
+        let _ = writeln!(stderr.lock(), "{line}");
     }
 }
 
+/// Print a command's report in the `--output-format` the user asked for.
 fn print_json<T: Serialize>(value: &T) -> Result<()> {
-    let stdout = std::io::stdout();
-    let mut lock = stdout.lock();
-    serde_json::to_writer_pretty(&mut lock, value)?;
-    writeln!(lock)?;
-    Ok(())
+    output::print(value)
 }
 
 fn read_raw_lines(path: &Path) -> Result<Vec<String>> {
-    let f = File::open(path)?;
+    let f = open_transcript(path)?;
     let reader = BufReader::new(f);
     let mut out = Vec::new();
     for line in reader.lines() {
@@ -1489,6 +10569,7 @@ fn read_raw_lines(path: &Path) -> Result<Vec<String>> {
 }
 
 fn time_window_from_global(global: &GlobalArgs) -> Result<TimeWindow> {
+    let now = Local::now();
     let since = if let Some(hours) = global.since_hours {
         if hours == 0 {
             bail!("--since-hours must be > 0");
@@ -1499,132 +10580,866 @@ fn time_window_from_global(global: &GlobalArgs) -> Result<TimeWindow> {
             bail!("--since-days must be > 0");
         }
         Some(Utc::now() - chrono::Duration::days(days as i64))
+    } else if let Some(raw) = &global.since {
+        Some(parse_time_bound("--since", raw, now)?)
     } else {
         None
     };
 
-    let until = if let Some(raw) = &global.until {
-        let dt = DateTime::parse_from_rfc3339(raw)
-            .with_context(|| format!("Invalid --until timestamp: {raw}"))?;
-        Some(dt.with_timezone(&Utc))
-    } else {
-        None
+    let until = match &global.until {
+        Some(raw) => Some(parse_time_bound("--until", raw, now)?),
+        None => None,
     };
-    Ok(TimeWindow { since, until })
+    if let (Some(since), Some(until)) = (since, until) {
+        if since > until {
+            bail!(
+                "The --since bound ({}) is after the --until bound ({})",
+                since.to_rfc3339_opts(SecondsFormat::Secs, true),
+                until.to_rfc3339_opts(SecondsFormat::Secs, true)
+            );
+        }
+    }
+    Ok(TimeWindow {
+        since,
+        until,
+        by: global.window_by,
+    })
 }
 
-fn expand_tilde_path(input: &str) -> Result<PathBuf> {
-    if input == "~" {
-        let home = std::env::var("HOME").context("HOME is not set")?;
-        return Ok(PathBuf::from(home));
-    }
-    if let Some(rest) = input.strip_prefix("~/") {
-        let home = std::env::var("HOME").context("HOME is not set")?;
-        return Ok(PathBuf::from(home).join(rest));
+/// RFC3339, or a human phrase ("yesterday", "last monday", "2 weeks ago")
+/// read in local time relative to `now`.
+fn parse_time_bound(flag: &str, raw: &str, now: DateTime<Local>) -> Result<DateTime<Utc>> {
+    if let Ok(dt) = DateTime::parse_from_rfc3339(raw) {
+        return Ok(dt.with_timezone(&Utc));
     }
-    Ok(PathBuf::from(input))
+    interim::parse_date_string(raw, now, interim::Dialect::Us)
+        .map(|dt| dt.with_timezone(&Utc))
+        .map_err(|err| {
+            anyhow!(
+                "Invalid {flag} time {raw:?}: {err}; use RFC3339 or a phrase like \"yesterday\" or \"3 days ago\""
+            )
+        })
 }
 
-fn html_escape(input: &str) -> String {
-    input
-        .replace('&', "&amp;")
-        .replace('<', "&lt;")
-        .replace('>', "&gt;")
-        .replace('"', "&quot;")
-        .replace('\'', "&#39;")
+fn walk_limits(global: &GlobalArgs) -> WalkLimits {
+    WalkLimits {
+        max_depth: global.max_depth,
+        one_file_system: global.one_file_system,
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::time::{SystemTime, UNIX_EPOCH};
+    use crate::testutil::{unique_temp_path, write_jsonl};
+    use cc_convo_core::short_id;
+
+    #[test]
+    fn replay_pairs_prompts_with_following_answers() {
+        let event = |role: &str, content: &str| NormalizedEvent {
+            role: role.to_string(),
+            source_type: role.to_string(),
+            timestamp: None,
+            content: content.to_string(),
+            uuid: None,
+            parent_uuid: None,
+            source: None,
+            model: None,
+        };
+        let events = vec![
+            event("assistant", "orphan"),
+            event("user", "first"),
+            event("assistant", "a1\n[tool_use] Bash\n{}"),
+            event("user", "[tool_result] toolu_1\nok"),
+            event("assistant", "a2"),
+            event("user", "<command-name>/clear</command-name>"),
+            event("user", "second"),
+            event("user", "[Request interrupted by user]"),
+            event("user", "third"),
+            event("assistant", "a3"),
+        ];
+        let exchanges = replay_exchanges(&events);
+        assert_eq!(exchanges.len(), 2);
+        assert_eq!(exchanges[0].prompt, "first");
+        assert_eq!(exchanges[0].original_response, "a2");
+        assert_eq!(exchanges[1].prompt_index, 2);
+        assert_eq!(exchanges[1].prompt, "third");
+
+        let with_history = build_replay_messages(&exchanges, 1, true);
+        assert_eq!(with_history.len(), 3);
+        assert_eq!(with_history[1]["role"], "assistant");
+        let single = build_replay_messages(&exchanges, 1, false);
+        assert_eq!(single.len(), 1);
+        assert_eq!(single[0]["content"], "third");
+    }
+
+    #[test]
+    fn complete_lines_read_through_compression() {
+        let dir = unique_temp_path("cc-convo-test-complete-lines");
+        fs::create_dir_all(&dir).expect("create dir");
+        let plain = dir.join("s.jsonl");
+        fs::write(&plain, "{\"a\":1}\n{\"b\":2}\n{\"c\"").expect("write transcript");
+        for compression in [Compression::Gzip, Compression::Zstd] {
+            let copy = dir.join(format!("{}.jsonl", compression.extension()));
+            fs::copy(&plain, &copy).expect("copy transcript");
+            let packed = compress_transcript(&copy, compression).expect("compress");
+            for path in [&plain, &packed] {
+                let all = read_complete_lines(path, 0).expect("read").expect("lines");
+                assert_eq!(all, b"{\"a\":1}\n{\"b\":2}\n");
+                let rest = read_complete_lines(path, 8).expect("read").expect("lines");
+                assert_eq!(rest, b"{\"b\":2}\n");
+                assert!(read_complete_lines(path, 100).expect("read").is_none());
+            }
+        }
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn session_costs_follow_branch_switches() {
+        let dir = unique_temp_path("cc-convo-test-cost-report");
+        let repo = dir.join("billing");
+        fs::create_dir_all(repo.join(".git")).expect("create repo");
+        fs::create_dir_all(repo.join("src")).expect("create subdir");
+        let file = dir.join("session.jsonl");
+        let src = repo.join("src").display().to_string();
+        let usage = r#""usage":{"input_tokens":100,"output_tokens":10}"#;
+        write_jsonl(
+            &file,
+            &[
+                &format!(
+                    r#"{{"type":"user","cwd":"{src}","gitBranch":"feature/PAY-42-refunds","message":{{"content":"go"}}}}"#
+                ),
+                &format!(
+                    r#"{{"type":"assistant","message":{{"id":"m1","model":"claude-sonnet-4-5",{usage},"content":[{{"type":"text","text":"a"}}]}}}}"#
+                ),
+                &format!(
+                    r#"{{"type":"assistant","message":{{"id":"m1","model":"claude-sonnet-4-5",{usage},"content":[{{"type":"text","text":"b"}}]}}}}"#
+                ),
+                r#"{"type":"user","cwd":"/gone/web","gitBranch":"main","message":{"content":"next"}}"#,
+                &format!(
+                    r#"{{"type":"assistant","message":{{"id":"m2","model":"claude-sonnet-4-5",{usage},"content":[{{"type":"text","text":"c"}}]}}}}"#
+                ),
+            ],
+        );
+        let session = Session {
+            index: 1,
+            id: "session".to_string(),
+            id_short: "session".to_string(),
+            project: "p".to_string(),
+            path: file.clone(),
+            modified_iso: String::new(),
+            modified_epoch: 0,
+            size_bytes: 0,
+            stitched: Vec::new(),
+        };
+        let pattern = regex::Regex::new(r"[A-Z][A-Z0-9]+-[0-9]+").unwrap();
+        let mut costs = session_costs(
+            &session,
+            &TimeWindow::default(),
+            &pattern,
+            &mut HashMap::new(),
+        )
+        .expect("costs");
+        costs.sort_by(|a, b| a.0.branch.cmp(&b.0.branch));
+        let keys = costs
+            .iter()
+            .map(|(key, usage)| {
+                (
+                    key.repo.as_str(),
+                    key.branch.as_str(),
+                    key.ticket.as_str(),
+                    usage.total(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            keys,
+            vec![
+                ("billing", "feature/PAY-42-refunds", "PAY-42", 110),
+                ("web", "main", UNATTRIBUTED, 110),
+            ]
+        );
+
+        fs::remove_dir_all(&dir).expect("cleanup dir");
+    }
+
+    #[test]
+    fn compare_periods_resolve_prior_against_the_other() {
+        let now = "2026-10-16T12:00:00Z"
+            .parse::<DateTime<Utc>>()
+            .unwrap()
+            .with_timezone(&Local);
+        let at = |rfc3339: &str| Some(rfc3339.parse::<DateTime<Utc>>().unwrap());
+
+        let periods = "last-30d vs prior-30d"
+            .parse::<ComparePeriods>()
+            .expect("parse");
+        let (a, b) = periods.windows(now, WindowBy::Mtime).expect("windows");
+        assert_eq!(
+            (a.since, a.until),
+            (at("2026-09-16T12:00:00Z"), at("2026-10-16T12:00:00Z"))
+        );
+        assert_eq!(
+            (b.since, b.until),
+            (at("2026-08-17T12:00:00Z"), at("2026-09-16T12:00:00Z"))
+        );
+
+        let periods = "prior-1w vs 2026-10-01T00:00:00Z..2026-10-08T00:00:00Z"
+            .parse::<ComparePeriods>()
+            .expect("parse");
+        let (a, _) = periods.windows(now, WindowBy::Event).expect("windows");
+        assert_eq!(
+            (a.since, a.until),
+            (at("2026-09-24T00:00:00Z"), at("2026-10-01T00:00:00Z"))
+        );
+        assert_eq!(a.by, WindowBy::Event);
+
+        assert!("last-30d".parse::<ComparePeriods>().is_err());
+        assert!("prior-30d vs prior-30d".parse::<ComparePeriods>().is_err());
+        assert!("last-30x vs prior-30d".parse::<ComparePeriods>().is_err());
+        assert!("last-0d vs prior-30d".parse::<ComparePeriods>().is_err());
+    }
+
+    #[test]
+    fn period_stats_report_percent_and_share_changes() {
+        let usage = |output_tokens| TokenUsage {
+            output_tokens,
+            ..TokenUsage::default()
+        };
+        let now = PeriodStats {
+            sessions: 134,
+            usage: usage(1_000),
+            model_shares: [("opus".to_string(), 0.4), ("sonnet".to_string(), 0.6)].into(),
+            ..PeriodStats::default()
+        };
+        let before = PeriodStats {
+            sessions: 100,
+            usage: usage(0),
+            model_shares: [("opus".to_string(), 0.52), ("sonnet".to_string(), 0.48)].into(),
+            ..PeriodStats::default()
+        };
+        let rows = now.compare(&before);
+        let change = |metric: &str| {
+            rows.iter()
+                .find(|row| row.0 == metric)
+                .map(|row| row.3.as_str())
+                .expect("metric")
+        };
+        assert_eq!(change("sessions"), "+34%");
+        assert_eq!(change("prompts"), "0%");
+        assert_eq!(change("tokens"), "new");
+        assert_eq!(change("opus share"), "-12pp");
+        assert_eq!(change("sonnet share"), "+12pp");
+        assert!(rows.iter().all(|row| row.0 != "cost (USD)"));
+    }
+
+    #[test]
+    fn summarize_session_computes_preview_badges() {
+        let dir = unique_temp_path("cc-convo-test-badges");
+        fs::create_dir_all(&dir).expect("create temp dir");
+        let file = dir.join("session.jsonl");
+        write_jsonl(
+            &file,
+            &[
+                r#"{"type":"user","message":{"content":[{"type":"text","text":"look"},{"type":"image","source":{}}]}}"#,
+                r#"{"type":"assistant","message":{"model":"claude-opus-4-1","content":[{"type":"tool_use","name":"Read","input":{}},{"type":"tool_use","name":"Bash","input":{}}]}}"#,
+                r#"{"type":"assistant","message":{"model":"claude-opus-4-1-20250805","content":[{"type":"text","text":"done"}]}}"#,
+            ],
+        );
+        let session = Session {
+            index: 1,
+            id: "session".to_string(),
+            id_short: "session".to_string(),
+            project: "p".to_string(),
+            path: file.clone(),
+            modified_iso: String::new(),
+            modified_epoch: 0,
+            size_bytes: 0,
+            stitched: Vec::new(),
+        };
 
-    fn unique_temp_path(prefix: &str) -> PathBuf {
-        let nanos = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .expect("clock should be after epoch")
-            .as_nanos();
-        std::env::temp_dir().join(format!("{}-{}-{}", prefix, std::process::id(), nanos))
+        let summary = summarize_session(
+            &session,
+            Some(PreviewMode::FirstPrompt),
+            &TimeWindow::default(),
+        )
+        .expect("summarize");
+        assert_eq!(summary.tool_uses, 2);
+        assert_eq!(summary.images, 1);
+        assert_eq!(summary.models.len(), 2);
+        assert_eq!(summary_badges(&summary), "[2 tools] [1 imgs] [opus]");
+
+        fs::remove_dir_all(&dir).expect("cleanup dir");
     }
 
-    fn write_jsonl(path: &Path, lines: &[&str]) {
-        let body = lines.join("\n");
-        fs::write(path, body).expect("write jsonl");
+    #[test]
+    fn summarize_session_honors_preview_mode() {
+        let dir = unique_temp_path("cc-convo-test-preview-mode");
+        fs::create_dir_all(&dir).expect("create temp dir");
+        let file = dir.join("session.jsonl");
+        write_jsonl(
+            &file,
+            &[
+                r#"{"type":"summary","summary":"Login bug triage"}"#,
+                r#"{"type":"user","message":{"content":"what did I ask"}}"#,
+                r#"{"type":"assistant","message":{"content":[{"type":"text","text":"early answer"}]}}"#,
+                r#"{"type":"assistant","message":{"content":[{"type":"text","text":"where it ended"}]}}"#,
+            ],
+        );
+        let session = Session {
+            index: 1,
+            id: "session".to_string(),
+            id_short: "session".to_string(),
+            project: "p".to_string(),
+            path: file.clone(),
+            modified_iso: String::new(),
+            modified_epoch: 0,
+            size_bytes: 0,
+            stitched: Vec::new(),
+        };
+
+        let preview = |mode| {
+            summarize_session(&session, mode, &TimeWindow::default())
+                .expect("summarize")
+                .preview
+        };
+        assert_eq!(preview(None), None);
+        assert_eq!(
+            preview(Some(PreviewMode::FirstPrompt)).as_deref(),
+            Some("what did I ask")
+        );
+        assert_eq!(
+            preview(Some(PreviewMode::LastAnswer)).as_deref(),
+            Some("where it ended")
+        );
+        assert_eq!(
+            preview(Some(PreviewMode::Summary)).as_deref(),
+            Some("Login bug triage")
+        );
+
+        fs::remove_dir_all(&dir).expect("cleanup dir");
     }
 
     #[test]
-    fn parse_default_mode_extracts_text_only() {
-        let dir = unique_temp_path("cc-convo-test-default");
+    fn scan_session_health_flags_integrity_problems() {
+        let dir = unique_temp_path("cc-convo-test-health");
         fs::create_dir_all(&dir).expect("create temp dir");
         let file = dir.join("session.jsonl");
         write_jsonl(
             &file,
             &[
-                r#"{"type":"user","timestamp":"2026-02-21T00:00:00Z","message":{"content":[{"type":"text","text":"hello user"}]}}"#,
-                r#"{"type":"assistant","timestamp":"2026-02-21T00:00:01Z","message":{"content":[{"type":"thinking","thinking":"private"},{"type":"tool_use","name":"x","input":{"a":1}},{"type":"text","text":"hello assistant"}]}}"#,
-                r#"{"type":"progress","timestamp":"2026-02-21T00:00:02Z","data":{"type":"tool","hookName":"h"}}"#,
+                r#"{"type":"user","timestamp":"2026-02-21T00:00:00Z","message":{"content":"a"}}"#,
+                r#"{"type":"assistant","message":{"content":"b"}}"#,
+                r#"{"type":"user","timestamp":"2026-02-22T00:00:00Z","message":{"content":"c"}}"#,
+                r#"{"type":"mystery"}"#,
+                r#"{"type":"user","timestamp":"2026-02-22T00:00:01Z","mess"#,
             ],
         );
 
-        let parsed = parse_session_events(&file, false).expect("parse");
-        assert_eq!(parsed.parse_errors, 0);
-        assert_eq!(parsed.events.len(), 2);
-        assert_eq!(parsed.events[0].role, "user");
-        assert_eq!(parsed.events[0].content, "hello user");
-        assert_eq!(parsed.events[1].role, "assistant");
-        assert_eq!(parsed.events[1].content, "hello assistant");
+        let health = scan_session_health(&file).expect("scan");
+        assert_eq!(health.records, 4);
+        assert_eq!(health.parse_error_lines, vec![5]);
+        assert!(health.truncated_last_line);
+        assert_eq!(health.missing_timestamps, 1);
+        assert_eq!(
+            health.schema_anomalies,
+            vec!["1 record(s) of unknown type `mystery`"]
+        );
+        assert_eq!(
+            health.flags,
+            vec!["parse-errors", "truncated", "missing-ts", "gap", "schema"]
+        );
 
-        fs::remove_file(&file).expect("cleanup file");
         fs::remove_dir_all(&dir).expect("cleanup dir");
     }
 
     #[test]
-    fn parse_detailed_mode_includes_operational_blocks() {
-        let dir = unique_temp_path("cc-convo-test-detailed");
+    fn tmux_detection_finds_claude_below_the_pane_shell() {
+        let processes = parse_ps_table(
+            "  100     1 -zsh\n  200   100 node /usr/local/bin/claude --resume\n  300   200 /bin/bash -c npm test\n  400     1 -bash\n  500   400 vim claude.md\n",
+        );
+        assert_eq!(processes.len(), 5);
+        assert_eq!(find_claude_process(&processes, 100), Some(200));
+        assert_eq!(find_claude_process(&processes, 400), None);
+
+        let cwd = Path::new("/home/user/my.repo_v2");
+        assert_eq!(claude_project_dir_name(cwd), "-home-user-my-repo-v2");
+        assert_eq!(project_label("-home-user-alpha"), "alpha");
+    }
+
+    #[test]
+    fn log_entry_collects_edited_files_and_commits() {
+        let dir = unique_temp_path("cc-convo-test-log");
         fs::create_dir_all(&dir).expect("create temp dir");
         let file = dir.join("session.jsonl");
         write_jsonl(
             &file,
             &[
-                r#"{"type":"assistant","timestamp":"2026-02-21T00:00:01Z","message":{"content":[{"type":"thinking","thinking":"private"},{"type":"tool_use","name":"x","input":{"a":1}},{"type":"tool_result","tool_use_id":"abc","content":"done"},{"type":"text","text":"visible"}]}}"#,
-                r#"{"type":"progress","timestamp":"2026-02-21T00:00:02Z","data":{"type":"tool","hookName":"h"}}"#,
+                r#"{"type":"user","timestamp":"2026-02-01T10:00:00Z","cwd":"/repo","gitBranch":"fix","message":{"content":"Fix the parser"}}"#,
+                r#"{"type":"assistant","timestamp":"2026-02-01T10:05:00Z","cwd":"/repo","message":{"content":[{"type":"tool_use","name":"Edit","input":{"file_path":"/repo/src/parse.rs"}},{"type":"tool_use","name":"Read","input":{"file_path":"/repo/README.md"}},{"type":"tool_use","name":"Write","input":{"file_path":"/tmp/notes.md"}},{"type":"tool_use","name":"Edit","input":{"file_path":"/repo/src/parse.rs"}}]}}"#,
+                r#"{"type":"user","timestamp":"2026-02-01T10:20:00Z","cwd":"/repo","message":{"content":[{"type":"tool_result","content":"[fix 1a2b3c4] Handle empty lines\n 1 file changed, 2 insertions(+)"}]}}"#,
+                r#"{"type":"summary","summary":"Parser fix"}"#,
             ],
         );
+        let session = Session {
+            index: 1,
+            id: "session".to_string(),
+            id_short: "session".to_string(),
+            project: "p".to_string(),
+            path: file.clone(),
+            modified_iso: String::new(),
+            modified_epoch: 0,
+            size_bytes: 0,
+            stitched: Vec::new(),
+        };
+
+        let entry = build_log_entry(&session, &TimeWindow::default()).expect("log entry");
+        assert_eq!(entry.title.as_deref(), Some("Parser fix"));
+        assert_eq!(entry.summary, None);
+        assert_eq!(entry.duration_secs, Some(1200));
+        assert_eq!(entry.branch.as_deref(), Some("fix"));
+        assert_eq!(entry.files, vec!["src/parse.rs", "/tmp/notes.md"]);
+        assert_eq!(
+            entry.commits,
+            vec![LinkedCommit {
+                sha: "1a2b3c4".to_string(),
+                message: "Handle empty lines".to_string(),
+            }]
+        );
+
+        assert!(parse_commit_line("[main (root-commit) 0f0f0f0] Initial").is_some());
+        assert!(parse_commit_line("[detached HEAD abcdef12] Wip").is_some());
+        assert!(parse_commit_line("[WARN] something happened").is_none());
+        assert!(parse_commit_line("[main] nothing").is_none());
+
+        fs::remove_dir_all(&dir).expect("cleanup dir");
+    }
+
+    #[test]
+    fn interruption_scan_charges_the_interrupted_model() {
+        let records = [
+            json!({"type": "user", "message": {"content": "Refactor the parser"}}),
+            json!({"type": "assistant", "message": {"model": "opus", "content": []}}),
+            json!({"type": "user", "message": {"content": [{"type": "text", "text": "[Request interrupted by user]"}]}}),
+            json!({"type": "user", "message": {"content": "Keep the public API"}}),
+            json!({"type": "assistant", "message": {"model": "opus", "content": []}}),
+            json!({"type": "user", "message": {"content": "No, that\u{2019}s the wrong file"}}),
+            json!({"type": "assistant", "message": {"model": "sonnet", "content": []}}),
+            json!({"type": "user", "isMeta": true, "message": {"content": "No"}}),
+            json!({"type": "user", "message": {"content": "Nothing else, thanks"}}),
+        ];
+        let mut scan = InterruptionScan::default();
+        for record in &records {
+            scan.observe(&Record::from_value(record).expect("record"));
+        }
+        assert_eq!(
+            scan.by_model["opus"],
+            InterruptionTally {
+                turns: 2,
+                interrupts: 1,
+                corrections: 2,
+            }
+        );
+        assert_eq!(scan.by_model["sonnet"].turns, 1);
+        assert_eq!(scan.by_model["sonnet"].corrections, 0);
+        assert_eq!(scan.unanswered, 1);
+    }
+
+    #[test]
+    fn latency_scan_times_prompts_to_the_first_real_reply() {
+        let at = |secs: u32| format!("2026-01-05T10:00:{secs:02}Z");
+        let records = [
+            json!({"type": "user", "timestamp": at(0), "message": {"content": "Refactor the parser"}}),
+            json!({"type": "assistant", "timestamp": at(2), "message": {"model": "<synthetic>", "content": []}}),
+            json!({"type": "assistant", "timestamp": at(5), "message": {"model": "opus", "content": []}}),
+            json!({"type": "assistant", "timestamp": at(9), "message": {"model": "opus", "content": []}}),
+            json!({"type": "user", "timestamp": at(10), "message": {"content": [{"type": "tool_result", "content": "ok"}]}}),
+            json!({"type": "assistant", "timestamp": at(11), "message": {"model": "opus", "content": []}}),
+            json!({"type": "user", "timestamp": at(20), "message": {"content": "Keep the public API"}}),
+            json!({"type": "user", "timestamp": at(21), "message": {"content": "[Request interrupted by user]"}}),
+            json!({"type": "user", "timestamp": at(30), "message": {"content": "Just rename it"}}),
+            json!({"type": "user", "timestamp": at(31), "message": {"content": "and the tests"}}),
+            json!({"type": "assistant", "timestamp": at(33), "message": {"model": "sonnet", "content": []}}),
+        ];
+        let mut scan = LatencyScan::default();
+        for record in &records {
+            scan.observe(&Record::from_value(record).expect("record"));
+        }
+        assert_eq!(
+            scan.waits,
+            [("opus".to_string(), 5000), ("sonnet".to_string(), 3000)]
+        );
+
+        let waits = (1..=200).collect::<Vec<u64>>();
+        let spread = LatencySpread::of(&waits).expect("waits");
+        assert_eq!(
+            (spread.p50_ms, spread.p90_ms, spread.p99_ms, spread.max_ms),
+            (100, 180, 198, 200)
+        );
+        assert_eq!(LatencySpread::of(&[]), None);
+        assert_eq!(format_wait(1234), "1.2s");
+        assert_eq!(format_wait(125_000), "2m05s");
+    }
+
+    #[test]
+    fn paste_scan_measures_prompt_pastes_only() {
+        let log = (0..15)
+            .map(|i| format!("2026-01-05 10:00:{i:02} ERROR upstream timed out"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let records = [
+            json!({"type": "user", "timestamp": "t1", "message": {"content": format!("Why?\n\n{log}")}}),
+            json!({"type": "user", "message": {"content": "Thanks, that fixed it."}}),
+            json!({"type": "user", "message": {"content": [{"type": "tool_result", "content": log}]}}),
+            json!({"type": "assistant", "message": {"content": [{"type": "text", "text": log}]}}),
+        ];
+        let mut scan = PasteScan::default();
+        for record in &records {
+            scan.observe(&Record::from_value(record).expect("record"));
+        }
+        assert_eq!(scan.prompts, 2);
+        assert_eq!(scan.prompts_with_pastes, 1);
+        assert_eq!(scan.pastes.len(), 1);
+        assert_eq!(scan.pastes[0].kind, PasteKind::Log);
+        assert_eq!(scan.pastes[0].bytes, log.len());
+        assert_eq!(scan.pastes[0].timestamp.as_deref(), Some("t1"));
+    }
+
+    #[test]
+    fn status_tally_reads_only_complete_appended_lines() {
+        let path = unique_temp_path("cc-convo-test-status");
+        let today = NaiveDate::from_ymd_opt(2026, 3, 2).unwrap();
+        let at = |h: u32| {
+            today
+                .and_hms_opt(h, 0, 0)
+                .and_then(|t| t.and_local_timezone(Local).earliest())
+                .unwrap()
+                .to_rfc3339()
+        };
+        let reply = |id: &str, output: u64, ts: &str| {
+            json!({"type": "assistant", "timestamp": ts, "message": {
+                "id": id, "usage": {"input_tokens": 10, "output_tokens": output}
+            }})
+            .to_string()
+        };
+        let yesterday = (today.pred_opt().unwrap())
+            .and_hms_opt(23, 0, 0)
+            .and_then(|t| t.and_local_timezone(Local).earliest())
+            .unwrap()
+            .to_rfc3339();
+        fs::write(
+            &path,
+            format!(
+                "{}\n{}\n{}\n{{\"type\":\"user\"",
+                reply("old", 1000, &yesterday),
+                reply("m1", 1, &at(9)),
+                reply("m1", 5, &at(9)),
+            ),
+        )
+        .expect("write transcript");
+
+        let mut tally = StatusTally::default();
+        tally.advance(&path, today).expect("advance");
+        assert_eq!((tally.records_today, tally.tokens_today), (2, 15));
+
+        let mut file = File::options().append(true).open(&path).expect("open");
+        writeln!(file, ",\"timestamp\":\"{}\"}}", at(10)).expect("append");
+        writeln!(file, "{}", reply("m2", 0, &at(11))).expect("append");
+        tally.advance(&path, today).expect("advance");
+        assert_eq!((tally.records_today, tally.tokens_today), (4, 25));
+        assert_eq!(tally.offset, fs::metadata(&path).unwrap().len());
+
+        fs::write(&path, format!("{}\n", reply("m3", 0, &at(12)))).expect("rewrite");
+        tally.advance(&path, today).expect("advance");
+        assert_eq!((tally.records_today, tally.tokens_today), (1, 10));
+        fs::remove_file(&path).expect("cleanup");
+    }
+
+    #[test]
+    fn fuzzy_filter_matches_words_as_subsequences() {
+        let label = "  3  2026-01-05  -home-user-alpha  Why does the login test fail?";
+        assert!(fuzzy_matches(label, ""));
+        assert!(fuzzy_matches(label, "lgn TST"));
+        assert!(fuzzy_matches(label, "alpha 01-05"));
+        assert!(!fuzzy_matches(label, "beta"));
+        assert!(!fuzzy_matches(label, "login zz"));
+    }
+
+    #[test]
+    fn resume_export_selection_picks_remaining_sessions() {
+        let dir = unique_temp_path("cc-convo-test-resume");
+        fs::create_dir_all(&dir).expect("create temp dir");
+        let session = |index: usize, id: &str| Session {
+            index,
+            id: id.to_string(),
+            id_short: short_id(id),
+            project: "p".to_string(),
+            path: dir.join(format!("{id}.jsonl")),
+            modified_iso: String::new(),
+            modified_epoch: 0,
+            size_bytes: 0,
+            stitched: Vec::new(),
+        };
+        let sessions = vec![session(1, "aaa"), session(2, "bbb"), session(3, "ccc")];
+        // As written before the checkpoint held every export setting.
+        let checkpoint: ExportCheckpoint = serde_json::from_value(json!({
+            "interrupted_at": "2026-02-21T00:00:00Z",
+            "format": "markdown",
+            "detailed": false,
+            "single_file": false,
+            "completed": ["aaa"],
+            "remaining": ["bbb", "gone", "ccc"],
+            "output_files": [],
+        }))
+        .expect("old checkpoint");
+        write_export_checkpoint(&dir, &checkpoint).expect("write checkpoint");
+
+        let resumed = resume_export_selection(&sessions, &dir, false).expect("resume");
+        let ids = resumed.iter().map(|s| s.id.as_str()).collect::<Vec<_>>();
+        assert_eq!(ids, vec!["bbb", "ccc"]);
+        let rebundled = resume_export_selection(&sessions, &dir, true).expect("resume");
+        let ids = rebundled.iter().map(|s| s.id.as_str()).collect::<Vec<_>>();
+        assert_eq!(ids, vec!["aaa", "bbb", "ccc"]);
+
+        fs::remove_dir_all(&dir).expect("cleanup dir");
+        assert!(resume_export_selection(&sessions, &dir, false).is_err());
+    }
+
+    #[test]
+    fn resumed_export_keeps_the_interrupted_settings() {
+        let root = unique_temp_path("cc-convo-test-resume-redact");
+        let project = root.join("projects/-home-user-alpha");
+        fs::create_dir_all(&project).expect("create project dir");
+        for id in ["aaaaaaaa-1111", "bbbbbbbb-2222"] {
+            write_jsonl(
+                &project.join(format!("{id}.jsonl")),
+                &[&format!(
+                    r#"{{"type":"user","sessionId":"{id}","timestamp":"2026-01-05T10:00:00Z","message":{{"role":"user","content":"key sk-ant-REDACTED in {id}"}}}}"#
+                )],
+            );
+        }
+        let run_into = |output: &Path, extra: &[&str]| {
+            let claude_dir = root.join("projects");
+            let mut argv = vec![
+                OsString::from("cc-convo"),
+                "--claude-dir".into(),
+                claude_dir.clone().into(),
+                "--stitches-file".into(),
+                root.join("stitches.json").into(),
+                "export".into(),
+                "--yes".into(),
+                "--output".into(),
+                output.into(),
+            ];
+            argv.extend(extra.iter().map(OsString::from));
+            let cli = Cli::parse_from(argv);
+            let Command::Export(args) = cli.command else {
+                panic!("not an export");
+            };
+            cmd_export(&claude_dir, &TimeWindow::default(), &cli.global, args)
+        };
+        let output = root.join("out");
+        let run = |extra: &[&str]| run_into(&output, extra);
+
+        // Interrupted before the first session, so both are left to resume.
+        INTERRUPTED.store(true, Ordering::SeqCst);
+        let first = run(&["--all", "--redact", "--format", "html"]);
+        INTERRUPTED.store(false, Ordering::SeqCst);
+        first.expect("interrupted export");
+        let checkpoint = read_export_checkpoint(&output).expect("checkpoint");
+        assert_eq!(checkpoint.remaining.len(), 2);
+        assert!(checkpoint.settings.redact);
+
+        assert!(run(&["--resume", "--format", "json"]).is_err());
+        run(&["--resume"]).expect("resumed export");
+        let mut written = fs::read_dir(&output)
+            .expect("read output")
+            .map(|entry| entry.expect("entry").path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "html"))
+            .filter(|path| path.file_name().is_some_and(|name| name != "index.html"))
+            .collect::<Vec<_>>();
+        written.sort();
+        assert_eq!(written.len(), 2, "{written:?}");
+        for path in written {
+            let html = fs::read_to_string(&path).expect("read export");
+            assert!(!html.contains("sk-ant-api03"), "{}", path.display());
+        }
+        assert!(!output.join(EXPORT_CHECKPOINT_FILE).exists());
+
+        // A bundle cut short after its first session is written again whole.
+        let bundled = root.join("bundle");
+        run_into(
+            &bundled,
+            &[
+                "--session",
+                "aaaaaaaa-1111",
+                "--single-file",
+                "--order",
+                "time",
+            ],
+        )
+        .expect("partial bundle");
+        let partial = fs::read_dir(&bundled)
+            .expect("read bundle dir")
+            .map(|entry| entry.expect("entry").path())
+            .find(|path| path.extension().is_some_and(|ext| ext == "md"))
+            .expect("bundle file");
+        let checkpoint: ExportCheckpoint = serde_json::from_value(json!({
+            "interrupted_at": "2026-02-21T00:00:00Z",
+            "format": "markdown",
+            "detailed": false,
+            "tree": false,
+            "redact": true,
+            "fold_pastes": true,
+            "single_file": true,
+            "order": "time",
+            "completed": ["-home-user-alpha/aaaaaaaa-1111"],
+            "remaining": ["-home-user-alpha/bbbbbbbb-2222"],
+            "output_files": [partial],
+        }))
+        .expect("bundle checkpoint");
+        write_export_checkpoint(&bundled, &checkpoint).expect("write checkpoint");
+        assert!(run_into(&bundled, &["--resume", "--single-file", "--order", "size"]).is_err());
+        run_into(&bundled, &["--resume"]).expect("resumed bundle");
+        let bundle = fs::read_to_string(&partial).expect("read bundle");
+        assert!(bundle.contains("aaaaaaaa-1111"), "{bundle}");
+        assert!(bundle.contains("bbbbbbbb-2222"), "{bundle}");
+        assert!(!bundle.contains("sk-ant-api03"), "{bundle}");
+        assert!(!bundled.join(EXPORT_CHECKPOINT_FILE).exists());
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn export_state_skips_sessions_until_their_files_change() {
+        let dir = unique_temp_path("cc-convo-test-export-state");
+        fs::create_dir_all(&dir).expect("create temp dir");
+        let path = dir.join("aaa.jsonl");
+        fs::write(&path, "{}\n").expect("write transcript");
+        let session = Session {
+            index: 1,
+            id: "aaa".to_string(),
+            id_short: short_id("aaa"),
+            project: "p".to_string(),
+            path: path.clone(),
+            modified_iso: String::new(),
+            modified_epoch: 0,
+            size_bytes: 0,
+            stitched: Vec::new(),
+        };
+        let args = |extra: &[&str]| {
+            let mut argv = vec!["cc-convo", "export"];
+            argv.extend(extra);
+            match Cli::parse_from(argv).command {
+                Command::Export(args) => ExportSettings::of(&args),
+                _ => unreachable!("parsed an export"),
+            }
+        };
 
-        let parsed = parse_session_events(&file, true).expect("parse");
-        assert_eq!(parsed.parse_errors, 0);
-        assert_eq!(parsed.events.len(), 2);
-        assert_eq!(parsed.events[0].role, "assistant");
-        assert!(parsed.events[0].content.contains("[thinking]"));
-        assert!(parsed.events[0].content.contains("[tool_use] x"));
-        assert!(parsed.events[0].content.contains("[tool_result] abc"));
-        assert!(parsed.events[0].content.contains("visible"));
-        assert_eq!(parsed.events[1].role, "progress");
-        assert!(parsed.events[1].content.contains("progress:tool"));
+        let mut state = ExportState::load(&dir, args(&[])).expect("load");
+        assert!(!state.is_current(&session));
+        state.record(&session, session_stamps(&session), None);
+        state.write(&dir).expect("write state");
 
-        fs::remove_file(&file).expect("cleanup file");
+        let state = ExportState::load(&dir, args(&[])).expect("reload");
+        assert!(state.is_current(&session));
+        assert!(!ExportState::load(&dir, args(&["--detailed"]))
+            .expect("reload")
+            .is_current(&session));
+
+        fs::write(&path, "{}\n{}\n").expect("append to transcript");
+        assert!(!state.is_current(&session));
         fs::remove_dir_all(&dir).expect("cleanup dir");
     }
 
     #[test]
-    fn build_context_preview_falls_back_to_ellipsized_text() {
-        let text = "alpha beta gamma delta epsilon";
-        let preview = build_context_preview(text, "notfound", 5, false);
-        assert!(preview.contains("..."));
-        assert!(preview.len() <= 13);
+    fn message_ranges_count_from_one_and_may_be_open() {
+        let parse = |raw: &str| raw.parse::<MessageRange>();
+        let range = |first, last| MessageRange { first, last };
+        assert_eq!(parse("5..42").expect("closed"), range(5, Some(42)));
+        assert_eq!(parse("5..").expect("open end"), range(5, None));
+        assert_eq!(parse("..3").expect("open start"), range(1, Some(3)));
+        assert_eq!(parse("7").expect("single"), range(7, Some(7)));
+        for bad in ["0..3", "4..2", "a..b"] {
+            assert!(parse(bad).is_err(), "{bad}");
+        }
+
+        let event = |n: usize| NormalizedEvent {
+            role: "user".to_string(),
+            source_type: "user".to_string(),
+            timestamp: None,
+            content: n.to_string(),
+            uuid: None,
+            parent_uuid: None,
+            source: None,
+            model: None,
+        };
+        let mut events = (1..=10).map(event).collect::<Vec<_>>();
+        range(4, Some(20)).retain(&mut events).expect("in range");
+        let kept = events
+            .iter()
+            .map(|e| e.content.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(kept, ["4", "5", "6", "7", "8", "9", "10"]);
+        assert!(range(8, None).retain(&mut events).is_err());
+    }
+
+    #[test]
+    fn parse_time_bound_accepts_rfc3339_and_phrases() {
+        use chrono::TimeZone;
+        // A Wednesday afternoon, away from any DST change.
+        let now = Local.with_ymd_and_hms(2026, 7, 15, 15, 30, 0).unwrap();
+        let local = |d, h, m| {
+            Local
+                .with_ymd_and_hms(2026, 7, d, h, m, 0)
+                .unwrap()
+                .with_timezone(&Utc)
+        };
+
+        let exact = parse_time_bound("--until", "2026-01-02T03:04:05Z", now).expect("rfc3339");
+        assert_eq!(exact.to_rfc3339(), "2026-01-02T03:04:05+00:00");
+        for (phrase, expected) in [
+            ("yesterday", local(14, 15, 30)),
+            ("last monday", local(13, 0, 0)),
+            ("2 weeks ago", local(1, 15, 30)),
+        ] {
+            let got = parse_time_bound("--since", phrase, now).expect(phrase);
+            assert_eq!(got, expected, "{phrase}");
+        }
+        let err = parse_time_bound("--since", "whenever", now).expect_err("gibberish");
+        assert!(err.to_string().contains("--since"), "{err}");
     }
 
     #[test]
-    fn build_context_preview_handles_unicode_without_panicking() {
-        let text = "prefix ══════ suffix";
-        let preview = build_context_preview(text, "notfound", 8, false);
-        assert!(preview.contains("..."));
+    fn validate_hooks_counts_and_rejects_malformed_entries() {
+        let ok = json!({"hooks": {"PreToolUse": [{"matcher": "Bash", "hooks": [
+            {"type": "command", "command": "echo hi"}
+        ]}]}});
+        assert_eq!(validate_hooks(&ok), Ok(1));
+        assert_eq!(validate_hooks(&json!({})), Ok(0));
+
+        let missing_command = json!({"hooks": {"Stop": [{"hooks": [{"type": "command"}]}]}});
+        assert!(validate_hooks(&missing_command).is_err());
+        let not_array = json!({"hooks": {"Stop": {}}});
+        assert!(validate_hooks(&not_array).is_err());
     }
 
     #[test]
-    fn ellipsize_handles_unicode_boundaries() {
-        let s = "ab═cd";
-        let out = ellipsize(s, 4);
-        assert_eq!(out, "a...");
+    fn alias_position_skips_global_options_and_their_values() {
+        let args = |line: &str| line.split(' ').map(OsString::from).collect::<Vec<_>>();
+        let cmd = Cli::command();
+        let line = args("cc-convo --claude-dir /tmp/p --no-color --config=a.toml weekly --x");
+        assert_eq!(command_position(&cmd, &line), Some(5));
+        assert_eq!(option_value(&line[..5], "--config"), Some("a.toml"));
+        let line = args("cc-convo --since 7d --config b.toml weekly");
+        assert_eq!(command_position(&cmd, &line), Some(5));
+        assert_eq!(option_value(&line[..5], "--config"), Some("b.toml"));
+        assert_eq!(
+            command_position(&cmd, &args("cc-convo --json -- weekly")),
+            None
+        );
+        assert_eq!(
+            split_alias("w", "search 'connection reset' --max-results 5").unwrap(),
+            ["search", "connection reset", "--max-results", "5"]
+        );
     }
 }
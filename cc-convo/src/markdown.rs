@@ -0,0 +1,179 @@
+//! Markdown for the terminal, for `sessions show --render`: termimad lays out
+//! prose, headings, lists, and tables, and syntect highlights fenced code.
+
+use cc_convo_core::Theme;
+use std::fmt::Write as _;
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{self, ThemeSet};
+use syntect::parsing::SyntaxSet;
+use syntect::util::{as_24_bit_terminal_escaped, LinesWithEndings};
+use termimad::MadSkin;
+
+/// Width to lay text out to when stdout isn't a terminal.
+const DEFAULT_WIDTH: usize = 100;
+/// Narrowest layout, however deep a `--tree` branch is indented.
+const MIN_WIDTH: usize = 40;
+
+pub struct Renderer {
+    skin: MadSkin,
+    syntaxes: SyntaxSet,
+    /// Highlighting for code blocks; `None` leaves them plain.
+    code_theme: Option<highlighting::Theme>,
+    width: usize,
+}
+
+impl Renderer {
+    /// A renderer in the colors of `theme`, or without styling when `colors`
+    /// is off, fitting text to the terminal.
+    pub fn new(theme: Theme, colors: bool) -> Self {
+        let width = console::Term::stdout()
+            .size_checked()
+            .map_or(DEFAULT_WIDTH, |(_, columns)| usize::from(columns));
+        Self::with_width(theme, colors, width)
+    }
+
+    fn with_width(theme: Theme, colors: bool, width: usize) -> Self {
+        let (skin, code_theme) = if colors {
+            let (skin, name) = match theme {
+                Theme::Auto => (MadSkin::default(), "base16-ocean.dark"),
+                Theme::Light => (MadSkin::default_light(), "InspiredGitHub"),
+                Theme::Dark => (MadSkin::default_dark(), "base16-eighties.dark"),
+                Theme::Solarized => (MadSkin::default_dark(), "Solarized (dark)"),
+            };
+            (skin, ThemeSet::load_defaults().themes.remove(name))
+        } else {
+            (MadSkin::no_style(), None)
+        };
+        Self {
+            skin,
+            syntaxes: SyntaxSet::load_defaults_newlines(),
+            code_theme,
+            width,
+        }
+    }
+
+    /// `markdown` laid out for the terminal, each line ending in a newline,
+    /// leaving room for `indent` columns of margin.
+    pub fn render(&self, markdown: &str, indent: usize) -> String {
+        let width = self.width.saturating_sub(indent).max(MIN_WIDTH);
+        let mut out = String::new();
+        let mut prose = String::new();
+        let mut lines = markdown.trim_matches('\n').lines();
+        while let Some(line) = lines.next() {
+            let Some(fence) = fence(line) else {
+                prose.push_str(line);
+                prose.push('\n');
+                continue;
+            };
+            self.render_prose(&mut out, &prose, width);
+            prose.clear();
+            // An unclosed fence runs to the end of the message.
+            let mut code = String::new();
+            for line in lines.by_ref() {
+                if line.trim_start().starts_with(fence.marker) {
+                    break;
+                }
+                code.push_str(line);
+                code.push('\n');
+            }
+            self.render_code(&mut out, fence.language, &code);
+        }
+        self.render_prose(&mut out, &prose, width);
+        out
+    }
+
+    fn render_prose(&self, out: &mut String, prose: &str, width: usize) {
+        if prose.is_empty() {
+            return;
+        }
+        let text = self.skin.text(prose, Some(width));
+        for line in text.to_string().lines() {
+            out.push_str(line.trim_end());
+            out.push('\n');
+        }
+    }
+
+    fn render_code(&self, out: &mut String, language: &str, code: &str) {
+        let Some(code_theme) = &self.code_theme else {
+            for line in code.lines() {
+                let _ = writeln!(out, "    {line}");
+            }
+            return;
+        };
+        let syntax = self
+            .syntaxes
+            .find_syntax_by_token(language)
+            .unwrap_or_else(|| self.syntaxes.find_syntax_plain_text());
+        let mut highlighter = HighlightLines::new(syntax, code_theme);
+        for line in LinesWithEndings::from(code) {
+            let escaped = match highlighter.highlight_line(line, &self.syntaxes) {
+                Ok(ranges) => as_24_bit_terminal_escaped(&ranges, false),
+                Err(_) => line.to_string(),
+            };
+            let _ = writeln!(out, "    {}\x1b[0m", escaped.trim_end_matches('\n'));
+        }
+    }
+}
+
+/// The opening line of a fenced code block.
+struct Fence<'a> {
+    /// The backticks or tildes that close the block.
+    marker: &'a str,
+    /// The info string's first word, like `rust`; empty without one.
+    language: &'a str,
+}
+
+fn fence(line: &str) -> Option<Fence<'_>> {
+    let line = line.trim_start();
+    let ch = line.chars().next().filter(|c| matches!(c, '`' | '~'))?;
+    let len = line.len() - line.trim_start_matches(ch).len();
+    if len < 3 {
+        return None;
+    }
+    let info = line[len..].trim();
+    // A backtick fence's info string can't hold backticks; this is inline code.
+    if ch == '`' && info.contains('`') {
+        return None;
+    }
+    Some(Fence {
+        marker: &line[..len],
+        language: info.split_whitespace().next().unwrap_or_default(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lays_out_prose_and_indents_fenced_code() {
+        let renderer = Renderer::with_width(Theme::Auto, false, 60);
+        let out = renderer.render(
+            "# Fix\n\n- await the cache\n- retry once\n\n```rust\nfn main() {}\n```\nDone.\n",
+            2,
+        );
+        assert!(out.contains("Fix"), "{out}");
+        assert!(!out.contains("# Fix"), "{out}");
+        assert!(out.contains("- await the cache"), "{out}");
+        assert!(out.contains("retry once\n\n    fn main() {}\n"), "{out}");
+        assert!(!out.contains("```"), "{out}");
+        assert!(out.ends_with("Done.\n"), "{out}");
+    }
+
+    #[test]
+    fn highlights_code_when_colored() {
+        let renderer = Renderer::with_width(Theme::Dark, true, 60);
+        let out = renderer.render("~~~python\nprint('hi')\n", 0);
+        assert!(out.contains("\x1b[38;2;"), "{out:?}");
+        assert!(out.contains("print"), "{out:?}");
+    }
+
+    #[test]
+    fn inline_backticks_are_not_a_fence() {
+        assert!(fence("```inline``` code").is_none());
+        assert_eq!(
+            fence("  ````sh -x").map(|f| (f.marker, f.language)),
+            Some(("````", "sh"))
+        );
+    }
+}
@@ -0,0 +1,310 @@
+//! Structured output for `--output-format`. Commands build their report as
+//! JSON values; this renders them as pretty JSON, JSON lines, YAML, or CSV.
+
+use anyhow::Result;
+use cc_convo_core::yaml_string;
+use clap::ValueEnum;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::io::Write;
+use std::sync::OnceLock;
+
+static FORMAT: OnceLock<OutputFormat> = OnceLock::new();
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Text for reading in a terminal.
+    #[default]
+    Table,
+    /// One pretty-printed JSON document.
+    Json,
+    /// One compact JSON value per line: each row of a list, or the report.
+    Jsonl,
+    Yaml,
+    /// A header and a row per item of a list; a report as `field,value` pairs.
+    Csv,
+}
+
+/// Set the format for the rest of the run; the first call wins.
+pub fn init(format: OutputFormat) {
+    let _ = FORMAT.set(format);
+}
+
+pub fn format() -> OutputFormat {
+    FORMAT.get().copied().unwrap_or_default()
+}
+
+/// Whether lists print row by row, through a [`RowWriter`].
+pub fn streams() -> bool {
+    matches!(format(), OutputFormat::Jsonl | OutputFormat::Csv)
+}
+
+/// Print `value` in the active format.
+pub fn print<T: Serialize>(value: &T) -> Result<()> {
+    if matches!(format(), OutputFormat::Table | OutputFormat::Json) {
+        // Straight from `value`, so fields keep their declared order.
+        let mut out = std::io::stdout().lock();
+        serde_json::to_writer_pretty(&mut out, value)?;
+        writeln!(out)?;
+        return Ok(());
+    }
+    match (format(), serde_json::to_value(value)?) {
+        (OutputFormat::Jsonl | OutputFormat::Csv, Value::Array(rows)) => {
+            let mut writer = RowWriter::new();
+            if format() == OutputFormat::Csv {
+                writer.columns = Some(csv_columns(&rows));
+            }
+            for row in &rows {
+                writer.row(row)?;
+            }
+            writer.finish()?;
+        }
+        (OutputFormat::Jsonl, value) => emit(&serde_json::to_string(&value)?)?,
+        (OutputFormat::Csv, value) => {
+            let mut fields = Vec::new();
+            flatten("", &value, &mut fields);
+            emit("field,value")?;
+            for (field, value) in fields {
+                emit(&format!("{},{}", csv_field(&field), csv_field(&value)))?;
+            }
+        }
+        (_, value) => print!("{}", to_yaml(&value)),
+    }
+    Ok(())
+}
+
+/// Prints a list in `jsonl` or `csv` a row at a time as the rows arrive, so
+/// a pipe sees each one without waiting for the rest.
+#[derive(Default)]
+pub struct RowWriter {
+    /// CSV columns, from the first row unless set up front.
+    columns: Option<Vec<String>>,
+    header_written: bool,
+}
+
+impl RowWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn row<T: Serialize>(&mut self, row: &T) -> Result<()> {
+        let row = serde_json::to_value(row)?;
+        if format() != OutputFormat::Csv {
+            return emit(&serde_json::to_string(&row)?);
+        }
+        let mut fields = Vec::new();
+        flatten("", &row, &mut fields);
+        let columns = self
+            .columns
+            .get_or_insert_with(|| fields.iter().map(|(name, _)| name.clone()).collect());
+        if !self.header_written {
+            emit(&csv_line(columns.iter().map(String::as_str)))?;
+            self.header_written = true;
+        }
+        let cells = columns.iter().map(|column| {
+            fields
+                .iter()
+                .find(|(name, _)| name == column)
+                .map_or("", |(_, value)| value.as_str())
+        });
+        emit(&csv_line(cells))
+    }
+
+    /// A CSV list with known columns but no rows still gets its header.
+    pub fn finish(self) -> Result<()> {
+        match &self.columns {
+            Some(columns) if !self.header_written && !columns.is_empty() => {
+                emit(&csv_line(columns.iter().map(String::as_str)))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Write one line and flush it. A reader that stopped early, like `head`,
+/// ends the run quietly instead of failing it.
+fn emit(line: &str) -> Result<()> {
+    let mut out = std::io::stdout().lock();
+    match writeln!(out, "{line}").and_then(|()| out.flush()) {
+        Err(err) if err.kind() == std::io::ErrorKind::BrokenPipe => std::process::exit(0),
+        result => Ok(result?),
+    }
+}
+
+/// Every flattened field of `rows`, in first-seen order.
+fn csv_columns(rows: &[Value]) -> Vec<String> {
+    let mut columns: Vec<String> = Vec::new();
+    for row in rows {
+        let mut fields = Vec::new();
+        flatten("", row, &mut fields);
+        for (name, _) in fields {
+            if !columns.contains(&name) {
+                columns.push(name);
+            }
+        }
+    }
+    columns
+}
+
+/// `value` as `(dotted.path, text)` cells: objects are walked, lists of
+/// plain values joined with `;`, and other lists kept as JSON.
+fn flatten(prefix: &str, value: &Value, out: &mut Vec<(String, String)>) {
+    let name = |key: &str| {
+        if prefix.is_empty() {
+            key.to_string()
+        } else {
+            format!("{prefix}.{key}")
+        }
+    };
+    match value {
+        Value::Object(map) if !map.is_empty() => {
+            for (key, value) in map {
+                flatten(&name(key), value, out);
+            }
+        }
+        _ => {
+            let key = if prefix.is_empty() { "value" } else { prefix };
+            out.push((key.to_string(), cell(value)));
+        }
+    }
+}
+
+fn cell(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        Value::Array(items) if items.iter().all(|v| !v.is_object() && !v.is_array()) => {
+            items.iter().map(cell).collect::<Vec<_>>().join(";")
+        }
+        other => other.to_string(),
+    }
+}
+
+fn csv_line<'a>(cells: impl Iterator<Item = &'a str>) -> String {
+    cells.map(csv_field).collect::<Vec<_>>().join(",")
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// `value` as a YAML document. Strings that would read back as anything
+/// else are written double-quoted, which YAML reads the same as JSON.
+pub fn to_yaml(value: &Value) -> String {
+    let mut out = String::new();
+    match value {
+        Value::Object(map) if !map.is_empty() => yaml_map(map, 0, &mut out),
+        Value::Array(items) if !items.is_empty() => yaml_list(items, 0, &mut out),
+        scalar => {
+            out.push_str(&yaml_scalar(scalar));
+            out.push('\n');
+        }
+    }
+    out
+}
+
+fn yaml_map(map: &Map<String, Value>, indent: usize, out: &mut String) {
+    for (key, value) in map {
+        out.push_str(&" ".repeat(indent));
+        out.push_str(&yaml_string(key));
+        out.push(':');
+        yaml_nested(value, indent, out);
+    }
+}
+
+fn yaml_list(items: &[Value], indent: usize, out: &mut String) {
+    for item in items {
+        out.push_str(&" ".repeat(indent));
+        out.push('-');
+        match item {
+            Value::Object(map) if !map.is_empty() => {
+                // The first line shares the dash's; the rest line up under it.
+                let mut nested = String::new();
+                yaml_map(map, indent + 2, &mut nested);
+                out.push(' ');
+                out.push_str(&nested[indent + 2..]);
+            }
+            Value::Array(items) if !items.is_empty() => {
+                let mut nested = String::new();
+                yaml_list(items, indent + 2, &mut nested);
+                out.push(' ');
+                out.push_str(&nested[indent + 2..]);
+            }
+            _ => yaml_nested(item, indent, out),
+        }
+    }
+}
+
+/// The value after a `key:` or `-`: inline if plain, else on the lines below.
+fn yaml_nested(value: &Value, indent: usize, out: &mut String) {
+    match value {
+        Value::Object(map) if !map.is_empty() => {
+            out.push('\n');
+            yaml_map(map, indent + 2, out);
+        }
+        Value::Array(items) if !items.is_empty() => {
+            out.push('\n');
+            yaml_list(items, indent + 2, out);
+        }
+        scalar => {
+            out.push(' ');
+            out.push_str(&yaml_scalar(scalar));
+            out.push('\n');
+        }
+    }
+}
+
+fn yaml_scalar(value: &Value) -> String {
+    match value {
+        Value::Null => "null".to_string(),
+        Value::String(s) => yaml_string(s),
+        Value::Array(_) => "[]".to_string(),
+        Value::Object(_) => "{}".to_string(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn yaml_quotes_only_strings_that_would_read_back_differently() {
+        let value = json!({
+            "title": "login triage",
+            "count": 2,
+            "answer": "yes",
+            "note": "cause: a race",
+            "empty": [],
+            "models": [["opus", 2]],
+            "checks": [{"name": "a", "ok": true}],
+        });
+        assert_eq!(
+            to_yaml(&value),
+            "answer: \"yes\"\nchecks:\n  - name: a\n    ok: true\ncount: 2\nempty: []\n\
+             models:\n  - - opus\n    - 2\nnote: \"cause: a race\"\ntitle: login triage\n"
+        );
+    }
+
+    #[test]
+    fn csv_cells_flatten_objects_and_quote_separators() {
+        let mut fields = Vec::new();
+        flatten(
+            "",
+            &json!({"session": {"id": "s1"}, "models": ["a", "b"], "title": "x, \"y\"", "cost": null}),
+            &mut fields,
+        );
+        let line = csv_line(fields.iter().map(|(_, value)| value.as_str()));
+        let names = fields
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["cost", "models", "session.id", "title"]);
+        assert_eq!(line, ",a;b,s1,\"x, \"\"y\"\"\"");
+    }
+}
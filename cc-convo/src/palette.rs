@@ -0,0 +1,127 @@
+//! Terminal colors for each `--theme`. Text output styles through the active
+//! palette rather than naming colors, so a theme can restyle all of it.
+
+use cc_convo_core::{RoleIcons, Theme};
+use console::{Color, Style, StyledObject};
+use std::env;
+use std::sync::OnceLock;
+
+static PALETTE: OnceLock<Palette> = OnceLock::new();
+
+pub struct Palette {
+    /// Titles of listings and reports.
+    pub heading: Style,
+    /// Summaries of finished work, like "Exported 3 session(s).".
+    pub success: Style,
+    /// Timestamps, paths, and other secondary detail.
+    pub meta: Style,
+    /// Session ids.
+    pub accent: Style,
+    /// Marks on entries, like commit hashes and branch labels.
+    pub highlight: Style,
+    pub ok: Style,
+    pub warn: Style,
+    pub error: Style,
+    user: Style,
+    assistant: Style,
+    other_role: Style,
+    icons: Option<RoleIcons>,
+}
+
+impl Palette {
+    fn new(theme: Theme, icons: Option<RoleIcons>) -> Self {
+        // Light terminals need darker tones than the bright defaults.
+        let (heading, success, accent, highlight, warn, user, assistant) = match theme {
+            Theme::Auto => (
+                Color::Cyan,
+                Color::Green,
+                Color::Green,
+                Color::Yellow,
+                Color::Yellow,
+                Color::Green,
+                Color::Cyan,
+            ),
+            Theme::Light => (
+                Color::Blue,
+                Color::Green,
+                Color::Magenta,
+                Color::Color256(130),
+                Color::Color256(130),
+                Color::Green,
+                Color::Blue,
+            ),
+            Theme::Dark => (
+                Color::Color256(117),
+                Color::Color256(114),
+                Color::Color256(114),
+                Color::Color256(221),
+                Color::Color256(221),
+                Color::Color256(114),
+                Color::Color256(117),
+            ),
+            Theme::Solarized => (
+                Color::Color256(33),
+                Color::Color256(64),
+                Color::Color256(37),
+                Color::Color256(136),
+                Color::Color256(136),
+                Color::Color256(64),
+                Color::Color256(33),
+            ),
+        };
+        let error = match theme {
+            Theme::Solarized => Color::Color256(160),
+            _ => Color::Red,
+        };
+        Self {
+            heading: Style::new().bold().fg(heading),
+            success: Style::new().bold().fg(success),
+            meta: Style::new().dim(),
+            accent: Style::new().fg(accent),
+            highlight: Style::new().fg(highlight),
+            ok: Style::new().fg(success),
+            warn: Style::new().fg(warn),
+            error: Style::new().fg(error),
+            user: Style::new().bold().fg(user),
+            assistant: Style::new().bold().fg(assistant),
+            other_role: Style::new().bold(),
+            icons,
+        }
+    }
+
+    /// `[role]`, styled for the role and after its icon with `--icons`.
+    pub fn role_label(&self, role: &str) -> StyledObject<String> {
+        let style = match role {
+            "user" => &self.user,
+            "assistant" => &self.assistant,
+            _ => &self.other_role,
+        };
+        style.apply_to(RoleIcons::label(self.icons, role))
+    }
+}
+
+/// Use `theme` for the rest of the run, with role icons if `icons` is set.
+pub fn init(theme: Theme, icons: bool) {
+    let icons = icons.then(|| match locale_is_utf8() {
+        true => RoleIcons::Emoji,
+        false => RoleIcons::Ascii,
+    });
+    let _ = PALETTE.set(Palette::new(theme, icons));
+}
+
+/// Whether the locale's character set is UTF-8, going by the variables
+/// that pick it, in order of precedence.
+fn locale_is_utf8() -> bool {
+    ["LC_ALL", "LC_CTYPE", "LANG"]
+        .iter()
+        .find_map(|var| env::var(var).ok().filter(|value| !value.is_empty()))
+        .is_some_and(|locale| {
+            let locale = locale.to_ascii_lowercase();
+            locale.contains("utf-8") || locale.contains("utf8")
+        })
+}
+
+/// The active palette; the `auto` theme's until [`init`] runs.
+pub fn palette() -> &'static Palette {
+    PALETTE.get_or_init(|| Palette::new(Theme::Auto, None))
+}
@@ -0,0 +1,630 @@
+//! `cc-convo serve`: a small read-only web UI over the transcripts. Every
+//! page is rendered from a fresh discovery, so new sessions show up on reload.
+//! The server is deliberately minimal (plain `std::net`, one request at a
+//! time, GET only besides `POST /export`); it is meant for one person's
+//! browser and tools, not the internet.
+//!
+//! `POST /export` takes a JSON selection and format and answers with the
+//! rendered bundle, for scripts that want an export without sharing a file
+//! system. It insists on `Content-Type: application/json`, which browsers
+//! only send cross-origin after a preflight this server never approves.
+
+use crate::{discover_sessions, palette, CountsCache, GlobalArgs, PreviewMode, TagStore};
+use anyhow::{Context, Result};
+use base64::Engine as _;
+use cc_convo_core::theme::{SCHEME_TOGGLE_HTML, SCHEME_TOGGLE_SCRIPT};
+use cc_convo_core::{
+    build_export_document, find_session, html_escape, parse_session_with, render_bundle,
+    render_html, search_sessions, ExportFormat, SearchMode, SearchOptions, Session, TimeWindow,
+};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::Path;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+/// Largest request head read before giving up on a client.
+const MAX_REQUEST_BYTES: usize = 16 * 1024;
+
+/// Largest `POST /export` body accepted.
+const MAX_EXPORT_REQUEST_BYTES: usize = 64 * 1024;
+
+/// The Content-Security-Policy of every response: inline styles, forms back
+/// to this server, and no script but the theme toggle, so markup that slips
+/// through from a transcript can't act on this origin.
+fn content_security_policy() -> &'static str {
+    static POLICY: OnceLock<String> = OnceLock::new();
+    POLICY.get_or_init(|| {
+        let hash = base64::engine::general_purpose::STANDARD
+            .encode(Sha256::digest(SCHEME_TOGGLE_SCRIPT.as_bytes()));
+        format!(
+            "default-src 'none'; style-src 'unsafe-inline'; script-src 'sha256-{hash}'; \
+             form-action 'self'; base-uri 'none'; frame-ancestors 'none'"
+        )
+    })
+}
+
+/// Search hits shown per query.
+const MAX_SEARCH_HITS: usize = 100;
+
+pub struct Viewer<'a> {
+    pub claude_dir: &'a Path,
+    pub time_window: &'a TimeWindow,
+    pub global: &'a GlobalArgs,
+    /// Sessions on the list page.
+    pub limit: usize,
+}
+
+/// A response ready to write: status line, content type, and body.
+#[derive(Debug)]
+pub struct Response {
+    pub status: &'static str,
+    pub content_type: &'static str,
+    pub body: String,
+}
+
+impl Response {
+    fn html(body: String) -> Self {
+        Self {
+            status: "200 OK",
+            content_type: "text/html; charset=utf-8",
+            body,
+        }
+    }
+
+    fn export(format: ExportFormat, body: String) -> Self {
+        let content_type = match format {
+            ExportFormat::Markdown | ExportFormat::QaMarkdown | ExportFormat::Obsidian => {
+                "text/markdown; charset=utf-8"
+            }
+            ExportFormat::Html => "text/html; charset=utf-8",
+            ExportFormat::Json | ExportFormat::QaJson => "application/json",
+            ExportFormat::OpenaiJsonl | ExportFormat::AnthropicMessages => "application/x-ndjson",
+        };
+        Self {
+            status: "200 OK",
+            content_type,
+            body,
+        }
+    }
+
+    fn error(status: &'static str, message: &str) -> Self {
+        Self {
+            status,
+            content_type: "text/plain; charset=utf-8",
+            body: format!("{message}\n"),
+        }
+    }
+}
+
+pub fn serve(viewer: &Viewer, addr: SocketAddr) -> Result<()> {
+    let listener =
+        TcpListener::bind(addr).with_context(|| format!("Failed to listen on {addr}"))?;
+    let local = listener.local_addr()?;
+    println!(
+        "{}",
+        palette().success.apply_to(format!(
+            "Serving transcripts at http://{local}/ (Ctrl-C to stop)"
+        ))
+    );
+    if !local.ip().is_loopback() {
+        eprintln!(
+            "{}",
+            palette().warn.apply_to(
+                "Listening beyond this machine: anyone who can reach this address can read your transcripts."
+            )
+        );
+    }
+    for stream in listener.incoming() {
+        let Ok(stream) = stream else {
+            continue;
+        };
+        if let Err(err) = handle_connection(viewer, stream) {
+            if viewer.global.verbose {
+                eprintln!("{err:#}");
+            }
+        }
+    }
+    Ok(())
+}
+
+fn handle_connection(viewer: &Viewer, mut stream: TcpStream) -> Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(10)))?;
+    // The limit holds while a line is read, so a line without an end can't
+    // grow past it.
+    let mut head = BufReader::new(&stream).take(MAX_REQUEST_BYTES as u64);
+    let mut request_line = String::new();
+    head.read_line(&mut request_line)?;
+    // Only the headers describing a request body matter here.
+    let (mut content_length, mut content_type) = (0usize, None);
+    let complete = loop {
+        let mut header = String::new();
+        if head.read_line(&mut header)? == 0 {
+            break false;
+        }
+        if header == "\r\n" || header == "\n" {
+            break true;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            let value = value.trim();
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.parse().unwrap_or(0);
+            } else if name.eq_ignore_ascii_case("content-type") {
+                content_type = Some(value.to_string());
+            }
+        }
+    };
+    let too_large = !complete && head.limit() == 0;
+    let mut reader = head.into_inner();
+    let response = match request_line.split_whitespace().collect::<Vec<_>>()[..] {
+        _ if too_large => Response::error(
+            "431 Request Header Fields Too Large",
+            "The request head is too large.",
+        ),
+        ["GET", target, _] => viewer.respond(target),
+        ["POST", _, _] if content_length > MAX_EXPORT_REQUEST_BYTES => {
+            Response::error("413 Payload Too Large", "The request body is too large.")
+        }
+        ["POST", target, _] => {
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body)?;
+            viewer.respond_post(target, content_type.as_deref(), &body)
+        }
+        [_, _, _] => Response::error(
+            "405 Method Not Allowed",
+            "Only GET, and POST to /export, are supported.",
+        ),
+        _ => Response::error("400 Bad Request", "Malformed request line."),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Security-Policy: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        response.status,
+        response.content_type,
+        content_security_policy(),
+        response.body.len(),
+        response.body
+    )?;
+    stream.flush()?;
+    Ok(())
+}
+
+impl Viewer<'_> {
+    /// The page for a request target like `/search?q=flaky+test`.
+    pub fn respond(&self, target: &str) -> Response {
+        let (path, query) = split_target(target);
+        let result = match path.as_str() {
+            "/" => self.list_page(&query),
+            "/search" => self.search_page(&query),
+            _ => match path.strip_prefix("/session/") {
+                Some(reference) => self.session_page(reference, &query),
+                None => return Response::error("404 Not Found", "No such page."),
+            },
+        };
+        result
+            .unwrap_or_else(|err| Response::error("500 Internal Server Error", &format!("{err:#}")))
+    }
+
+    /// The answer to a POST of `body` to `target`.
+    pub fn respond_post(&self, target: &str, content_type: Option<&str>, body: &[u8]) -> Response {
+        let (path, _) = split_target(target);
+        if path != "/export" {
+            return Response::error("404 Not Found", "No such endpoint.");
+        }
+        let json = content_type
+            .and_then(|t| t.split(';').next())
+            .is_some_and(|t| t.trim().eq_ignore_ascii_case("application/json"));
+        if !json {
+            return Response::error(
+                "415 Unsupported Media Type",
+                "Send the export request as application/json.",
+            );
+        }
+        let request = match serde_json::from_slice::<ExportRequest>(body) {
+            Ok(request) => request,
+            Err(err) => {
+                return Response::error(
+                    "400 Bad Request",
+                    &format!("Invalid export request: {err}"),
+                )
+            }
+        };
+        self.export(&request)
+            .unwrap_or_else(|err| Response::error("500 Internal Server Error", &format!("{err:#}")))
+    }
+
+    fn export(&self, request: &ExportRequest) -> Result<Response> {
+        let format = request.format.unwrap_or(ExportFormat::Markdown);
+        if matches!(format, ExportFormat::Obsidian) {
+            return Ok(Response::error(
+                "400 Bad Request",
+                "Obsidian exports are one note per session; pick another format.",
+            ));
+        }
+        if request.sessions.is_empty() && request.project.is_none() && request.recent.is_none() {
+            return Ok(Response::error(
+                "400 Bad Request",
+                "Select sessions with \"sessions\", \"project\", or \"recent\".",
+            ));
+        }
+        let sessions = self.sessions()?;
+        let mut selected = Vec::new();
+        for target in &request.sessions {
+            match find_session(&sessions, target) {
+                Ok(session) => selected.push(session),
+                Err(err) => return Ok(Response::error("404 Not Found", &format!("{err:#}"))),
+            }
+        }
+        if request.sessions.is_empty() {
+            selected.extend(&sessions);
+        }
+        if let Some(project) = &request.project {
+            let project = project.to_lowercase();
+            selected.retain(|s| s.project.to_lowercase().contains(&project));
+        }
+        if let Some(recent) = request.recent {
+            selected.truncate(recent);
+        }
+        if selected.is_empty() {
+            return Ok(Response::error("404 Not Found", "No sessions match."));
+        }
+        let mut docs = Vec::new();
+        for session in selected {
+            let mut parsed =
+                parse_session_with(session, &self.global.parse_options(request.detailed))?;
+            self.time_window.retain_events(&mut parsed.events);
+            let doc = build_export_document(session, &parsed.events).titled(&parsed.title);
+            docs.push(if request.tree { doc.threaded() } else { doc });
+        }
+        let body = render_bundle(&docs, &[], format, self.global.export_style())?;
+        Ok(Response::export(format, body))
+    }
+
+    fn sessions(&self) -> Result<Vec<Session>> {
+        discover_sessions(self.claude_dir, self.time_window, self.global)
+    }
+
+    fn list_page(&self, query: &HashMap<String, String>) -> Result<Response> {
+        let mut sessions = self.sessions()?;
+        let project = query.get("project").map(|p| p.trim().to_lowercase());
+        if let Some(project) = project.as_deref().filter(|p| !p.is_empty()) {
+            sessions.retain(|s| s.project.to_lowercase().contains(project));
+        }
+        let tags = TagStore::load(&crate::tags_path(self.global)?)?;
+        let tag = query.get("tag").map(|t| t.trim()).filter(|t| !t.is_empty());
+        if let Some(tag) = tag {
+            sessions.retain(|s| tags.has_all(&s.id, &[tag.to_string()]));
+        }
+        sessions.truncate(self.limit);
+
+        let mut cache = CountsCache::open(self.global, self.time_window)?;
+        let mut rows = String::new();
+        for session in &sessions {
+            let counts = cache.counts(session, self.time_window)?;
+            let session_tags = tags.tags(&session.id);
+            rows.push_str(&format!(
+                "<tr><td>{}</td><td>{}</td><td><a href=\"{}\">{}</a></td><td>{}/{}</td><td>{}{}</td></tr>",
+                html_escape(&session.modified_iso),
+                html_escape(&session.project),
+                session_href(session),
+                html_escape(&session.id_short),
+                counts.user_messages,
+                counts.assistant_messages,
+                html_escape(&counts.preview(PreviewMode::FirstPrompt).unwrap_or_default()),
+                if session_tags.is_empty() {
+                    String::new()
+                } else {
+                    format!(" <span class=\"meta\">{}</span>", html_escape(&session_tags.join(" ")))
+                },
+            ));
+        }
+        cache.save()?;
+        let value = |key: &str| html_escape(query.get(key).map_or("", String::as_str));
+        let body = format!(
+            "<form method=\"get\" action=\"/\"><input name=\"project\" placeholder=\"project\" value=\"{}\"> <input name=\"tag\" placeholder=\"tag\" value=\"{}\"> <button>Filter</button></form>\
+             <p class=\"meta\">{} session(s)</p>\
+             <table><tr><th>modified</th><th>project</th><th>session</th><th>user/assistant</th><th>first prompt</th></tr>{rows}</table>",
+            value("project"),
+            value("tag"),
+            sessions.len(),
+        );
+        Ok(Response::html(self.page("Sessions", &body)))
+    }
+
+    fn search_page(&self, query: &HashMap<String, String>) -> Result<Response> {
+        let text = query.get("q").map(|q| q.trim()).unwrap_or_default();
+        let mode = match query.get("mode").map(String::as_str) {
+            Some("exact") => SearchMode::Exact,
+            Some("regex") => SearchMode::Regex,
+            Some("query") => SearchMode::Query,
+            _ => SearchMode::Smart,
+        };
+        let mut body = String::new();
+        if !text.is_empty() {
+            let sessions = self.sessions()?;
+            let options = SearchOptions {
+                mode,
+                time_window: self.time_window.clone(),
+                unknown_records: self.global.unknown_records(),
+                analysis: self.global.search_analysis(),
+                ..SearchOptions::smart(text)
+            };
+            let hits = search_sessions(&sessions, &options)?;
+            let by_path = sessions
+                .iter()
+                .map(|s| (s.path.as_path(), s))
+                .collect::<HashMap<_, _>>();
+            body.push_str(&format!(
+                "<p class=\"meta\">{} result(s)</p>",
+                hits.len().min(MAX_SEARCH_HITS)
+            ));
+            for hit in hits.iter().take(MAX_SEARCH_HITS) {
+                let link = by_path.get(hit.path.as_path()).map_or_else(
+                    || html_escape(&hit.session_id),
+                    |session| {
+                        format!(
+                            "<a href=\"{}\">{}</a>",
+                            session_href(session),
+                            html_escape(hit.title.as_deref().unwrap_or(&session.id_short))
+                        )
+                    },
+                );
+                body.push_str(&format!(
+                    "<div class=\"card\"><div class=\"meta\">{link} {} {} {} {:.2}</div><div class=\"content\">{}</div></div>",
+                    html_escape(&hit.project),
+                    html_escape(hit.timestamp.as_deref().unwrap_or("-")),
+                    html_escape(&hit.speaker),
+                    hit.relevance,
+                    html_escape(&hit.preview),
+                ));
+            }
+        }
+        Ok(Response::html(self.page(&format!("Search: {text}"), &body)))
+    }
+
+    fn session_page(&self, reference: &str, query: &HashMap<String, String>) -> Result<Response> {
+        let reference = percent_decode(reference);
+        let sessions = self.sessions()?;
+        let Ok(session) = find_session(&sessions, &reference) else {
+            return Ok(Response::error("404 Not Found", "No such session."));
+        };
+        let detailed = query.get("detailed").is_some_and(|v| v == "1");
+        let mut parsed = parse_session_with(session, &self.global.parse_options(detailed))?;
+        self.time_window.retain_events(&mut parsed.events);
+        let doc = build_export_document(session, &parsed.events).titled(&parsed.title);
+        let toggle = if detailed {
+            format!("<a href=\"{}\">dialog only</a>", session_href(session))
+        } else {
+            format!(
+                "<a href=\"{}?detailed=1\">detailed</a>",
+                session_href(session)
+            )
+        };
+        let html = render_html(&[doc], self.global.export_style()).replacen(
+            "<body>",
+            &format!("<body><p><a href=\"/\">Sessions</a> &middot; {toggle}</p>"),
+            1,
+        );
+        Ok(Response::html(html))
+    }
+
+    /// A page in the export's theme, with the search box on top.
+    fn page(&self, title: &str, body: &str) -> String {
+        let theme = self.global.theme();
+        format!(
+            "<!doctype html><html{}><head><meta charset=\"utf-8\"><title>{} - cc-convo</title><style>{}</style></head><body>{SCHEME_TOGGLE_HTML}\
+             <p><a href=\"/\">Sessions</a></p>\
+             <form method=\"get\" action=\"/search\"><input name=\"q\" placeholder=\"search transcripts\" size=\"40\"> <select name=\"mode\"><option>smart</option><option>exact</option><option>regex</option><option>query</option></select> <button>Search</button></form>\
+             <h1>{}</h1>{body}</body></html>",
+            theme.html_attrs(),
+            html_escape(title),
+            theme.html_css(false),
+            html_escape(title),
+        )
+    }
+}
+
+/// The body of `POST /export`. Without `sessions`, the newest sessions in the
+/// time window are taken, narrowed by `project` and `recent`.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ExportRequest {
+    /// Ids, short ids, or `project/id`s, exported in this order.
+    #[serde(default)]
+    sessions: Vec<String>,
+    /// Part of the project name, ignoring case.
+    project: Option<String>,
+    /// At most this many sessions, newest first.
+    recent: Option<usize>,
+    /// Any `export --format` but obsidian; markdown by default.
+    format: Option<ExportFormat>,
+    #[serde(default)]
+    detailed: bool,
+    #[serde(default)]
+    tree: bool,
+}
+
+fn session_href(session: &Session) -> String {
+    format!(
+        "/session/{}/{}",
+        percent_encode(&session.project),
+        percent_encode(&session.id)
+    )
+}
+
+/// The path and decoded query parameters of a request target.
+fn split_target(target: &str) -> (String, HashMap<String, String>) {
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let params = query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (
+                percent_decode(&key.replace('+', " ")),
+                percent_decode(&value.replace('+', " ")),
+            )
+        })
+        .collect();
+    (path.to_string(), params)
+}
+
+fn percent_decode(input: &str) -> String {
+    let bytes = input.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                out.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                out.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+fn percent_encode(input: &str) -> String {
+    input
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::unique_temp_path;
+
+    #[test]
+    fn targets_split_into_path_and_decoded_params() {
+        let (path, query) = split_target("/search?q=flaky+login%20test&mode=exact&empty");
+        assert_eq!(path, "/search");
+        assert_eq!(query["q"], "flaky login test");
+        assert_eq!(query["mode"], "exact");
+        assert_eq!(query["empty"], "");
+        assert_eq!(percent_decode("caf%C3%A9%2"), "café%2");
+        assert_eq!(percent_encode("-home-user/a b"), "-home-user%2Fa%20b");
+    }
+
+    #[test]
+    fn pages_list_render_and_search_sessions() {
+        use clap::Parser;
+
+        let root = unique_temp_path("cc-convo-test-serve");
+        let project = root.join("projects").join("-home-user-app");
+        std::fs::create_dir_all(&project).expect("create temp dir");
+        std::fs::write(
+            project.join("0123456789abcdef.jsonl"),
+            concat!(
+                r#"{"type":"user","message":{"role":"user","content":"Why is <the> login test flaky?"}}"#,
+                "\n",
+                r#"{"type":"assistant","message":{"role":"assistant","content":"A race in setup."}}"#,
+                "\n",
+            ),
+        )
+        .expect("write transcript");
+        let dir = |name: &str| root.join(name).display().to_string();
+        let cli = crate::Cli::parse_from([
+            "cc-convo",
+            "--claude-dir",
+            &dir("projects"),
+            "--cache-file",
+            &dir("cache.json"),
+            "--tags-file",
+            &dir("tags.json"),
+            "--stitches-file",
+            &dir("stitches.json"),
+            "serve",
+        ]);
+        let claude_dir = root.join("projects");
+        let viewer = Viewer {
+            claude_dir: &claude_dir,
+            time_window: &TimeWindow::default(),
+            global: &cli.global,
+            limit: 10,
+        };
+
+        let list = viewer.respond("/?project=APP");
+        assert_eq!(list.status, "200 OK");
+        let href = "/session/-home-user-app/0123456789abcdef";
+        assert!(list.body.contains(href), "{}", list.body);
+        assert!(list.body.contains("Why is &lt;the&gt; login test flaky?"));
+        assert!(!viewer.respond("/?project=other").body.contains(href));
+
+        let page = viewer.respond(href);
+        assert_eq!(page.status, "200 OK");
+        assert!(page.body.contains("A race in setup."));
+
+        let search = viewer.respond("/search?q=race+setup");
+        assert!(search.body.contains(href), "{}", search.body);
+        assert_eq!(viewer.respond("/session/nope").status, "404 Not Found");
+        assert_eq!(viewer.respond("/etc/passwd").status, "404 Not Found");
+
+        let export =
+            |body: &str| viewer.respond_post("/export", Some("application/json"), body.as_bytes());
+        let markdown = export(r#"{"project": "app", "recent": 1}"#);
+        assert_eq!(
+            (markdown.status, markdown.content_type),
+            ("200 OK", "text/markdown; charset=utf-8")
+        );
+        assert!(markdown.body.contains("- Session: `0123456789abcdef`"));
+        let json = export(r#"{"sessions": ["01234567"], "format": "json"}"#);
+        assert_eq!(json.content_type, "application/json");
+        assert!(json.body.contains("A race in setup."));
+        assert_eq!(export("{}").status, "400 Bad Request");
+        assert_eq!(export(r#"{"sessions": ["nope"]}"#).status, "404 Not Found");
+        assert_eq!(
+            export(r#"{"recent": 1, "color": "red"}"#).status,
+            "400 Bad Request"
+        );
+        let form = viewer.respond_post("/export", Some("text/plain"), b"{\"recent\": 1}");
+        assert_eq!(form.status, "415 Unsupported Media Type");
+
+        let over_tcp = |request: Vec<u8>| {
+            let listener = TcpListener::bind("127.0.0.1:0").expect("bind");
+            let addr = listener.local_addr().expect("local addr");
+            let client = std::thread::spawn(move || {
+                let mut stream = TcpStream::connect(addr).expect("connect");
+                stream.write_all(&request).expect("send request");
+                let mut response = String::new();
+                stream.read_to_string(&mut response).expect("read response");
+                response
+            });
+            let (stream, _) = listener.accept().expect("accept");
+            handle_connection(&viewer, stream).expect("handle connection");
+            client.join().expect("client thread")
+        };
+        let found = over_tcp(format!("GET {href} HTTP/1.1\r\nHost: x\r\n\r\n").into_bytes());
+        assert!(found.starts_with("HTTP/1.1 200 OK\r\n"), "{found}");
+        assert!(
+            found.contains("Content-Security-Policy: default-src 'none';"),
+            "{found}"
+        );
+        assert!(found.contains("script-src 'sha256-"), "{found}");
+        // A head with no line end stops being read at the limit.
+        let endless = over_tcp(vec![b'a'; MAX_REQUEST_BYTES]);
+        assert!(
+            endless.starts_with("HTTP/1.1 431 Request Header Fields Too Large"),
+            "{endless}"
+        );
+
+        std::fs::remove_dir_all(&root).expect("cleanup dir");
+    }
+}
@@ -0,0 +1,1429 @@
+//! Golden-file tests: run the CLI against `tests/fixtures` and compare output.
+//!
+//! Set `CC_CONVO_BLESS=1` (or run `cargo xtask bless`) to rewrite the goldens.
+
+use chrono::{SecondsFormat, Utc};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Fixture files get fixed mtimes starting here so listings are stable.
+const FIXTURE_BASE_EPOCH: u64 = 1_767_600_000;
+
+struct Sandbox {
+    root: PathBuf,
+}
+
+impl Sandbox {
+    fn new(case: &str) -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("clock should be after epoch")
+            .as_nanos();
+        let root = std::env::temp_dir().join(format!(
+            "cc-convo-golden-{case}-{}-{nanos}",
+            std::process::id()
+        ));
+        let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/projects");
+        let projects = root.join("projects");
+        let mut files = Vec::new();
+        copy_tree(&fixtures, &projects, &mut files);
+        files.sort();
+        for (i, file) in files.iter().enumerate() {
+            let mtime = UNIX_EPOCH + Duration::from_secs(FIXTURE_BASE_EPOCH + i as u64 * 3600);
+            fs::File::options()
+                .write(true)
+                .open(file)
+                .and_then(|f| f.set_modified(mtime))
+                .expect("set fixture mtime");
+        }
+        Self { root }
+    }
+
+    fn run(&self, args: &[&str]) -> String {
+        let out_dir = self.root.join("out");
+        let args = args
+            .iter()
+            .map(|a| a.replace("{out}", &out_dir.display().to_string()))
+            .collect::<Vec<_>>();
+        let output = Command::new(env!("CARGO_BIN_EXE_cc-convo"))
+            .arg("--claude-dir")
+            .arg(self.root.join("projects"))
+            .arg("--no-color")
+            .args(&args)
+            .env("RUST_BACKTRACE", "0")
+            .env("RUST_LIB_BACKTRACE", "0")
+            .env("HOME", &self.root)
+            .env("TZ", "UTC")
+            .env("LC_ALL", "C")
+            .env_remove("LANGUAGE")
+            .output()
+            .expect("run cc-convo");
+
+        let mut transcript = format!("$ cc-convo {}\n", args.join(" "));
+        transcript.push_str(&format!("exit: {}\n", output.status.code().unwrap_or(-1)));
+        transcript.push_str("--- stdout\n");
+        transcript.push_str(&String::from_utf8_lossy(&output.stdout));
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if !stderr.trim().is_empty() {
+            transcript.push_str("--- stderr\n");
+            transcript.push_str(&stderr);
+        }
+        if out_dir.exists() {
+            let mut produced = Vec::new();
+            copy_tree(&out_dir, &self.root.join("out-snapshot"), &mut produced);
+            produced.sort();
+            for file in produced {
+                let name = file
+                    .strip_prefix(self.root.join("out-snapshot"))
+                    .expect("produced file under snapshot")
+                    .display()
+                    .to_string();
+                transcript.push_str(&format!("--- file: {name}\n"));
+                transcript.push_str(&fs::read_to_string(&file).unwrap_or_default());
+                transcript.push('\n');
+            }
+        }
+        self.normalize(&transcript)
+    }
+
+    fn normalize(&self, text: &str) -> String {
+        let today = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true);
+        let today = today.split('T').next().unwrap_or_default();
+        text.replace(&self.root.display().to_string(), "<TMP>")
+            .replace(today, "<TODAY>")
+    }
+}
+
+impl Drop for Sandbox {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.root);
+    }
+}
+
+fn copy_tree(from: &Path, to: &Path, files: &mut Vec<PathBuf>) {
+    fs::create_dir_all(to).expect("create dir");
+    for entry in fs::read_dir(from).expect("read dir") {
+        let entry = entry.expect("dir entry");
+        let target = to.join(entry.file_name());
+        if entry.path().is_dir() {
+            copy_tree(&entry.path(), &target, files);
+        } else {
+            fs::copy(entry.path(), &target).expect("copy file");
+            files.push(target);
+        }
+    }
+}
+
+fn assert_golden(case: &str, args: &[&str]) {
+    assert_golden_steps(case, &[args]);
+}
+
+/// Run several commands in one sandbox, for state they leave behind.
+fn assert_golden_steps(case: &str, steps: &[&[&str]]) {
+    let sandbox = Sandbox::new(case);
+    let actual = steps
+        .iter()
+        .map(|args| sandbox.run(args))
+        .collect::<String>();
+    let golden = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(format!("{case}.txt"));
+    if std::env::var_os("CC_CONVO_BLESS").is_some() {
+        fs::write(&golden, &actual).expect("write golden");
+        return;
+    }
+    let expected = fs::read_to_string(&golden).unwrap_or_else(|_| {
+        panic!(
+            "missing golden {}; run `cargo xtask bless`",
+            golden.display()
+        )
+    });
+    assert!(
+        expected == actual,
+        "output for `{case}` differs from {}; run `cargo xtask bless` if intended.\n--- expected\n{expected}\n--- actual\n{actual}",
+        golden.display()
+    );
+}
+
+#[test]
+fn sessions_list() {
+    assert_golden("sessions_list", &["sessions", "list", "--with-preview"]);
+}
+
+#[test]
+fn sessions_list_preview_layout() {
+    assert_golden_steps(
+        "sessions_list_preview_layout",
+        &[
+            &["sessions", "list", "--preview-chars", "40"],
+            &[
+                "sessions",
+                "list",
+                "--preview-mode",
+                "last-answer",
+                "--preview-lines",
+                "2",
+            ],
+        ],
+    );
+}
+
+#[test]
+fn sessions_list_ended_by() {
+    assert_golden(
+        "sessions_list_ended_by",
+        &["sessions", "list", "--ended-by", "error"],
+    );
+}
+
+#[test]
+fn sessions_tag() {
+    assert_golden_steps(
+        "sessions_tag",
+        &[
+            &["sessions", "tag", "add", "3", "auth", "flaky"],
+            &["sessions", "tag", "add", "2", "auth"],
+            &["sessions", "tag", "list"],
+            &["sessions", "list", "--tag", "auth", "--tag", "flaky"],
+            &["sessions", "tag", "remove", "3", "flaky", "missing"],
+            &["--json", "sessions", "tag", "list", "3"],
+            &[
+                "export",
+                "--tag",
+                "auth",
+                "--format",
+                "qa-markdown",
+                "--output",
+                "{out}",
+            ],
+        ],
+    );
+}
+
+#[test]
+fn sessions_list_json() {
+    assert_golden(
+        "sessions_list_json",
+        &["--json", "sessions", "list", "--health"],
+    );
+}
+
+#[test]
+fn sessions_show() {
+    assert_golden("sessions_show", &["sessions", "show", "3"]);
+}
+
+#[test]
+fn sessions_show_render() {
+    assert_golden_steps(
+        "sessions_show_render",
+        &[
+            &["sessions", "show", "3", "--render", "--raw"],
+            &["sessions", "show", "3", "--render", "--detailed"],
+        ],
+    );
+}
+
+#[test]
+fn sessions_show_project_nth() {
+    assert_golden(
+        "sessions_show_project_nth",
+        &["sessions", "show", "ALPHA", "--nth", "2"],
+    );
+}
+
+#[test]
+fn sessions_show_by_title() {
+    assert_golden_steps(
+        "sessions_show_by_title",
+        &[
+            &["sessions", "show", "TRIAGE"],
+            &["sessions", "show", "screenshot of"],
+            &["sessions", "show", "the"],
+        ],
+    );
+}
+
+#[test]
+fn jobs_backup() {
+    assert_golden_steps(
+        "jobs_backup",
+        &[
+            &[
+                "jobs",
+                "start",
+                "backup",
+                "--output",
+                "{out}",
+                "--project",
+                "beta",
+            ],
+            &["jobs", "list"],
+            &["jobs", "cancel", "1"],
+        ],
+    );
+}
+
+#[test]
+fn completions_dynamic() {
+    assert_golden("completions_dynamic", &["completions", "bash", "--dynamic"]);
+}
+
+#[test]
+fn sessions_show_ops() {
+    assert_golden("sessions_show_ops", &["sessions", "show", "2", "--ops"]);
+}
+
+#[test]
+fn sessions_show_tree() {
+    assert_golden("sessions_show_tree", &["sessions", "show", "1", "--tree"]);
+}
+
+#[test]
+fn export_markdown_tree() {
+    assert_golden(
+        "export_markdown_tree",
+        &[
+            "export", "--index", "1", "--tree", "--format", "markdown", "--output", "{out}",
+        ],
+    );
+}
+
+#[test]
+fn role_icons() {
+    // The C locale gets the ASCII icons; exports always get emoji.
+    assert_golden_steps(
+        "role_icons",
+        &[
+            &["--icons", "sessions", "show", "3"],
+            &[
+                "--icons", "export", "--index", "3", "--format", "markdown", "--output", "{out}",
+            ],
+        ],
+    );
+}
+
+#[test]
+fn sessions_list_window_by_event() {
+    assert_golden(
+        "sessions_list_window_by_event",
+        &[
+            "--window-by",
+            "event",
+            "--since",
+            "2026-01-06T00:00:00Z",
+            "--until",
+            "2026-01-06T23:59:59Z",
+            "sessions",
+            "list",
+        ],
+    );
+}
+
+#[test]
+fn sessions_show_window_by_event() {
+    assert_golden(
+        "sessions_show_window_by_event",
+        &[
+            "--window-by",
+            "event",
+            "--until",
+            "2026-01-07T08:00:40Z",
+            "sessions",
+            "show",
+            "beta",
+        ],
+    );
+}
+
+#[test]
+fn sessions_list_event_window_counts() {
+    assert_golden(
+        "sessions_list_event_window_counts",
+        &[
+            "--window-by",
+            "event",
+            "--since",
+            "2026-01-05T09:00:06Z",
+            "--until",
+            "2026-01-05T23:59:59Z",
+            "sessions",
+            "list",
+        ],
+    );
+}
+
+#[test]
+fn export_search_event_window() {
+    assert_golden(
+        "export_search_event_window",
+        &[
+            "--window-by",
+            "event",
+            "--since",
+            "2026-01-05T09:00:06Z",
+            "--until",
+            "2026-01-05T23:59:59Z",
+            "export",
+            "--search",
+            "intermittently",
+            "--output",
+            "{out}",
+        ],
+    );
+}
+
+#[test]
+fn sessions_show_detailed_json() {
+    assert_golden(
+        "sessions_show_detailed_json",
+        &["--json", "sessions", "show", "3", "--detailed"],
+    );
+}
+
+#[test]
+fn search() {
+    assert_golden("search", &["search", "login test"]);
+}
+
+#[test]
+fn search_single_job() {
+    assert_golden(
+        "search_single_job",
+        &["--jobs", "1", "search", "login test"],
+    );
+}
+
+#[test]
+fn search_json() {
+    assert_golden(
+        "search_json",
+        &["--json", "search", "nginx", "--mode", "exact"],
+    );
+}
+
+#[test]
+fn search_query() {
+    assert_golden(
+        "search_query",
+        &[
+            "search",
+            "role:assistant (cache OR proxy) -nginx",
+            "--mode",
+            "query",
+        ],
+    );
+}
+
+#[test]
+fn search_html() {
+    assert_golden_steps(
+        "search_html",
+        &[
+            &["--json", "search", "cache", "--format", "html"],
+            &["search", "cache", "--output", "{out}/hits.html"],
+            &[
+                "search",
+                "cache OR proxy",
+                "--mode",
+                "query",
+                "--format",
+                "html",
+                "--output",
+                "{out}/hits.html",
+            ],
+        ],
+    );
+}
+
+#[test]
+fn search_explain() {
+    assert_golden_steps(
+        "search_explain",
+        &[
+            &["search", "flaky login cache", "--explain"],
+            &[
+                "--json",
+                "search",
+                "nginx proxy error",
+                "--mode",
+                "exact",
+                "--explain",
+            ],
+            &["search", "proxy", "--mode", "semantic", "--explain"],
+        ],
+    );
+}
+
+#[test]
+fn search_stopwords_and_stemming() {
+    let query = "how did we configure the nginx proxies";
+    assert_golden_steps(
+        "search_stopwords_and_stemming",
+        &[
+            &["search", query, "--explain"],
+            &["search", query, "--no-stopwords"],
+            &["search", query, "--stem"],
+            &[
+                "--config",
+                "tests/fixtures/search-config.toml",
+                "search",
+                "screenshot proxies",
+                "--mode",
+                "query",
+            ],
+        ],
+    );
+}
+
+#[test]
+fn search_include_thinking() {
+    let query = "test file first";
+    assert_golden_steps(
+        "search_include_thinking",
+        &[
+            &[
+                "--config",
+                "tests/fixtures/no-thinking-config.toml",
+                "search",
+                query,
+                "--include-thinking",
+            ],
+            &["search", query, "--mode", "exact"],
+            &["search", query, "--mode", "exact", "--include-thinking"],
+        ],
+    );
+}
+
+#[test]
+fn search_file() {
+    let file = "src/auth/login.rs";
+    assert_golden_steps(
+        "search_file",
+        &[
+            &["search", "--file", file, "--explain"],
+            &["search", "--file", file],
+            &["--json", "search", "--file", file],
+            &["search", "cache", "--file", file],
+            &["search", "--file", "src/auth/logout.rs"],
+        ],
+    );
+}
+
+#[test]
+fn search_semantic() {
+    assert_golden(
+        "search_semantic",
+        &[
+            "search",
+            "reverse proxy config broken",
+            "--mode",
+            "semantic",
+            "--max-results",
+            "3",
+        ],
+    );
+}
+
+#[test]
+fn stats() {
+    assert_golden("stats", &["stats"]);
+}
+
+#[test]
+fn cost_report_by_repo_branch_ticket() {
+    assert_golden_steps(
+        "cost_report",
+        &[
+            &["cost-report", "--group-by", "repo,branch,ticket"],
+            &["--json", "cost-report", "--group-by", "ticket"],
+        ],
+    );
+}
+
+#[test]
+fn lang_de_translates_text_but_not_json() {
+    assert_golden_steps(
+        "lang_de",
+        &[
+            &["--lang", "de", "sessions", "list"],
+            &["--lang", "de", "stats", "--top", "2"],
+            &["--lang", "de", "--json", "sessions", "list", "--limit", "1"],
+            &["--lang", "fr", "stats"],
+        ],
+    );
+}
+
+#[test]
+fn session_filters() {
+    assert_golden_steps(
+        "session_filters",
+        &[
+            &["stats", "--model", "OPUS", "--top", "3"],
+            &[
+                "--json",
+                "search",
+                "login test",
+                "--min-messages",
+                "4",
+                "--model",
+                "sonnet",
+            ],
+            &["search", "login test", "--max-messages", "6"],
+        ],
+    );
+}
+
+#[test]
+fn stats_usage_only() {
+    assert_golden_steps(
+        "stats_usage_only",
+        &[
+            &["stats", "--usage-only", "--pricing"],
+            &["--json", "stats", "--usage-only"],
+        ],
+    );
+}
+
+#[test]
+fn export_encrypted() {
+    let key = "age:age1qurqvrw6hqp7qqlnmrw47csarprz0dyr6v7g5hrc7qyenyt98uwq6amv0e";
+    assert_golden_steps(
+        "export_encrypted",
+        &[
+            &[
+                "export",
+                "--index",
+                "3",
+                "--output",
+                "{out}",
+                "--encrypt",
+                key,
+            ],
+            &[
+                "decrypt",
+                "{out}/cc-convo-2026-01-05-11111111.md.age",
+                "--identity",
+                "tests/fixtures/age-identity.txt",
+                "--output",
+                "{out}/plain",
+            ],
+            &["decrypt", "{out}/cc-convo-2026-01-05-11111111.md.age"],
+        ],
+    );
+}
+
+#[test]
+fn export_incremental() {
+    assert_golden_steps(
+        "export_incremental",
+        &[
+            &[
+                "export",
+                "--index",
+                "1",
+                "--incremental",
+                "--single-file",
+                "--output",
+                "{out}",
+            ],
+            &[
+                "--json",
+                "export",
+                "--index",
+                "2",
+                "--index",
+                "3",
+                "--incremental",
+                "--output",
+                "{out}",
+            ],
+            &[
+                "export",
+                "--index",
+                "1",
+                "--index",
+                "2",
+                "--index",
+                "3",
+                "--incremental",
+                "--output",
+                "{out}",
+            ],
+        ],
+    );
+}
+
+#[test]
+fn output_formats() {
+    assert_golden_steps(
+        "output_formats",
+        &[
+            &["--output-format", "csv", "sessions", "list"],
+            &[
+                "--output-format",
+                "jsonl",
+                "sessions",
+                "list",
+                "--limit",
+                "2",
+            ],
+            &["--output-format", "yaml", "stats", "--top", "2"],
+            &["--output-format", "csv", "search", "login test"],
+        ],
+    );
+}
+
+#[test]
+fn stats_json() {
+    assert_golden("stats_json", &["--json", "stats"]);
+}
+
+#[test]
+fn doctor_json() {
+    assert_golden(
+        "doctor_json",
+        &["--json", "doctor", "--skip-env", "--output", "{out}"],
+    );
+}
+
+#[test]
+fn export_interactive_needs_a_terminal() {
+    assert_golden_steps(
+        "export_interactive_errors",
+        &[
+            &["export", "--interactive", "--output", "{out}"],
+            &["export", "--interactive", "--all"],
+        ],
+    );
+}
+
+#[test]
+fn sessions_list_interactive_needs_a_terminal() {
+    assert_golden_steps(
+        "sessions_list_interactive_errors",
+        &[
+            &["sessions", "list", "--interactive"],
+            &["--json", "sessions", "list", "--interactive"],
+        ],
+    );
+}
+
+#[test]
+fn export_name_template() {
+    assert_golden(
+        "export_name_template",
+        &[
+            "export",
+            "--recent",
+            "2",
+            "--output",
+            "{out}",
+            "--name-template",
+            "{project}/{date}-{index}-{slug}",
+        ],
+    );
+}
+
+#[test]
+fn export_name_template_rejects_collisions_and_escapes() {
+    assert_golden_steps(
+        "export_name_template_errors",
+        &[
+            &[
+                "export",
+                "--recent",
+                "2",
+                "--output",
+                "{out}",
+                "--name-template",
+                "{date}.md",
+            ],
+            &[
+                "export",
+                "--recent",
+                "1",
+                "--name-template",
+                "../{short_id}",
+            ],
+        ],
+    );
+}
+
+#[test]
+fn export_message_slice() {
+    assert_golden_steps(
+        "export_message_slice",
+        &[
+            &["export", "--recent", "2", "--messages", "1..2"],
+            &[
+                "export",
+                "--session",
+                "11111111",
+                "--detailed",
+                "--messages",
+                "5..9",
+                "--to-timestamp",
+                "2026-01-05T09:00:09Z",
+                "--output",
+                "{out}",
+            ],
+        ],
+    );
+}
+
+#[test]
+fn export_markdown() {
+    assert_golden(
+        "export_markdown",
+        &[
+            "export", "--all", "--yes", "--format", "markdown", "--output", "{out}",
+        ],
+    );
+}
+
+#[test]
+fn export_json_detailed() {
+    assert_golden(
+        "export_json_detailed",
+        &[
+            "export",
+            "--index",
+            "3",
+            "--detailed",
+            "--format",
+            "json",
+            "--output",
+            "{out}",
+        ],
+    );
+}
+
+#[test]
+fn export_qa_markdown() {
+    assert_golden(
+        "export_qa_markdown",
+        &[
+            "export",
+            "--index",
+            "3",
+            "--format",
+            "qa-markdown",
+            "--detailed",
+            "--output",
+            "{out}",
+        ],
+    );
+}
+
+#[test]
+fn export_qa_json() {
+    assert_golden(
+        "export_qa_json",
+        &[
+            "export", "--index", "2", "--format", "qa-json", "--output", "{out}",
+        ],
+    );
+}
+
+#[test]
+fn faq_markdown() {
+    assert_golden(
+        "faq_markdown",
+        &[
+            "faq",
+            "--project",
+            "alpha",
+            "--min-asked",
+            "1",
+            "--output",
+            "{out}/FAQ.md",
+        ],
+    );
+}
+
+#[test]
+fn faq_json() {
+    assert_golden("faq_json", &["--json", "faq", "--min-asked", "1"]);
+}
+
+#[test]
+fn import_chatgpt() {
+    assert_golden_steps(
+        "import_chatgpt",
+        &[
+            &[
+                "import",
+                "chatgpt",
+                "tests/fixtures/chatgpt/conversations.json",
+                "--project",
+                "openai-archive",
+            ],
+            &[
+                "sessions",
+                "list",
+                "--project",
+                "openai-archive",
+                "--with-preview",
+            ],
+            &["sessions", "show", "6a1f0c2e"],
+        ],
+    );
+}
+
+#[test]
+fn import_codex() {
+    assert_golden_steps(
+        "import_codex",
+        &[
+            &["import", "codex", "--dir", "tests/fixtures/codex"],
+            &["sessions", "list", "--project", "codex", "--with-preview"],
+            &["sessions", "show", "0199aaaa", "--detailed"],
+            &["--json", "stats"],
+        ],
+    );
+}
+
+#[test]
+fn stats_pastes() {
+    assert_golden_steps(
+        "stats_pastes",
+        &[
+            &[
+                "import",
+                "chatgpt",
+                "tests/fixtures/chatgpt/pasted-traceback.json",
+                "--project",
+                "pasted",
+            ],
+            &["--json", "stats", "--pastes"],
+            &[
+                "export",
+                "--session",
+                "9d4a3f51",
+                "--fold-pastes",
+                "--output",
+                "{out}",
+            ],
+        ],
+    );
+}
+
+#[test]
+fn split_by_gap_and_topic() {
+    assert_golden_steps(
+        "split_by_gap_and_topic",
+        &[
+            &[
+                "import",
+                "chatgpt",
+                "tests/fixtures/chatgpt/multi-day.json",
+                "--project",
+                "longhaul",
+            ],
+            &["split", "5b1e7c2a", "--by", "gap:2h", "--output", "{out}"],
+            &[
+                "--json",
+                "split",
+                "5b1e7c2a",
+                "--by",
+                "topic",
+                "--min-prompts",
+                "1",
+                "--list",
+            ],
+        ],
+    );
+}
+
+#[test]
+fn stitch_by_session_id() {
+    assert_golden_steps(
+        "stitch_by_session_id",
+        &[
+            &[
+                "import",
+                "chatgpt",
+                "tests/fixtures/chatgpt/multi-day-first.json",
+                "--project=-Users-me-billing",
+            ],
+            &[
+                "import",
+                "chatgpt",
+                "tests/fixtures/chatgpt/multi-day.json",
+                "--project=-home-me-billing",
+            ],
+            &["sessions", "show", "5b1e7c2a"],
+            &["stitch", "--by", "sessionId"],
+            &["sessions", "list"],
+            &["sessions", "show", "5b1e7c2a"],
+        ],
+    );
+}
+
+#[test]
+fn compact_then_read() {
+    assert_golden_steps(
+        "compact_then_read",
+        &[
+            &["compact", "--older-than", "30d", "--dry-run"],
+            &[
+                "compact",
+                "--older-than",
+                "30d",
+                "--project",
+                "alpha",
+                "--compression",
+                "gzip",
+            ],
+            &["compact", "--older-than", "30d"],
+            &["sessions", "list"],
+            &["sessions", "show", "3"],
+            &["search", "login"],
+            &["--json", "stats", "--model", "opus"],
+        ],
+    );
+}
+
+#[test]
+fn prune_dry_run_and_archive() {
+    assert_golden_steps(
+        "prune_dry_run_and_archive",
+        &[
+            &["prune", "--older-than", "30d", "--dry-run"],
+            &["--json", "prune", "--older-than", "30d"],
+            &[
+                "prune",
+                "--older-than",
+                "30d",
+                "--project",
+                "beta",
+                "--archive",
+                "{out}/archive",
+                "--yes",
+            ],
+            &["sessions", "list"],
+        ],
+    );
+}
+
+#[test]
+fn export_openai_jsonl_detailed() {
+    assert_golden(
+        "export_openai_jsonl_detailed",
+        &[
+            "export",
+            "--index",
+            "3",
+            "--detailed",
+            "--format",
+            "openai-jsonl",
+            "--output",
+            "{out}",
+        ],
+    );
+}
+
+#[test]
+fn export_anthropic_messages_split() {
+    assert_golden(
+        "export_anthropic_messages_split",
+        &[
+            "export",
+            "--all",
+            "--yes",
+            "--detailed",
+            "--drop-thinking",
+            "--format",
+            "anthropic-messages",
+            "--validation-split",
+            "0.34",
+            "--output",
+            "{out}",
+        ],
+    );
+}
+
+#[test]
+fn export_html_detailed() {
+    assert_golden(
+        "export_html_detailed",
+        &[
+            "export",
+            "--index",
+            "3",
+            "--format",
+            "html",
+            "--detailed",
+            "--output",
+            "{out}",
+        ],
+    );
+}
+
+#[test]
+fn export_html() {
+    assert_golden(
+        "export_html",
+        &[
+            "export", "--index", "2", "--format", "html", "--output", "{out}",
+        ],
+    );
+}
+
+#[test]
+fn export_obsidian() {
+    assert_golden_steps(
+        "export_obsidian",
+        &[
+            &["sessions", "tag", "add", "3", "auth"],
+            &[
+                "export", "--all", "--yes", "--format", "obsidian", "--output", "{out}",
+            ],
+        ],
+    );
+}
+
+#[test]
+fn export_html_theme_from_config() {
+    assert_golden(
+        "export_html_theme_from_config",
+        &[
+            "--config",
+            "tests/fixtures/config.toml",
+            "export",
+            "--index",
+            "2",
+            "--format",
+            "html",
+            "--output",
+            "{out}",
+        ],
+    );
+}
+
+#[test]
+fn export_html_theme_flag_beats_config() {
+    assert_golden(
+        "export_html_theme_flag_beats_config",
+        &[
+            "--config",
+            "tests/fixtures/config.toml",
+            "--theme",
+            "dark",
+            "export",
+            "--index",
+            "2",
+            "--format",
+            "html",
+            "--output",
+            "{out}",
+        ],
+    );
+}
+
+#[test]
+fn diff_sessions() {
+    assert_golden_steps(
+        "diff_sessions",
+        &[
+            &["diff", "3", "3"],
+            &["diff", "2", "3"],
+            &[
+                "diff",
+                "2",
+                "3",
+                "--detailed",
+                "--side-by-side",
+                "--width",
+                "90",
+            ],
+        ],
+    );
+}
+
+#[test]
+fn explain_record() {
+    assert_golden_steps(
+        "explain_record",
+        &[
+            &[
+                "explain-record",
+                "--file",
+                "tests/fixtures/explain-record.jsonl",
+                "--line",
+                "1",
+            ],
+            &[
+                "--json",
+                "explain-record",
+                "--file",
+                "tests/fixtures/explain-record.jsonl",
+                "--line",
+                "1",
+            ],
+            &[
+                "explain-record",
+                "--file",
+                "tests/fixtures/explain-record.jsonl",
+                "--line",
+                "2",
+            ],
+        ],
+    );
+}
+
+#[test]
+fn unknown_records_policy() {
+    let explain = |policy: &'static str| -> Vec<&'static str> {
+        let mut args = vec![
+            "explain-record",
+            "--file",
+            "tests/fixtures/explain-record.jsonl",
+        ];
+        if !policy.is_empty() {
+            args.splice(0..0, ["--unknown-records", policy]);
+        }
+        args.extend(["--line", "3"]);
+        args
+    };
+    assert_golden_steps(
+        "unknown_records_policy",
+        &[&explain(""), &explain("summarize"), &explain("drop")],
+    );
+}
+
+#[test]
+fn replay_dry_run() {
+    assert_golden(
+        "replay_dry_run",
+        &["replay", "--all", "--model", "claude-test", "--dry-run"],
+    );
+}
+
+#[test]
+fn stats_progress_json() {
+    assert_golden(
+        "stats_progress_json",
+        &["--json", "--progress-json", "stats", "--top", "1"],
+    );
+}
+
+#[test]
+fn stats_show_parse_errors() {
+    assert_golden("stats_show_parse_errors", &["stats", "--show-parse-errors"]);
+}
+
+#[test]
+fn timeline() {
+    assert_golden_steps(
+        "timeline",
+        &[
+            &["--since", "2026-01-01", "timeline"],
+            &[
+                "--since",
+                "2026-01-01",
+                "timeline",
+                "--by",
+                "day",
+                "--events",
+                "--project",
+                "alpha",
+            ],
+            &[
+                "--json",
+                "--window-by",
+                "event",
+                "--since",
+                "2026-01-06",
+                "timeline",
+                "--by",
+                "day",
+            ],
+            &["--since", "2026-01-01", "timeline", "--since-days", "3"],
+        ],
+    );
+}
+
+#[test]
+fn calendar_month() {
+    assert_golden("calendar_month", &["calendar", "--month", "2026-01"]);
+}
+
+#[test]
+fn calendar_day_json() {
+    assert_golden(
+        "calendar_day_json",
+        &["--json", "calendar", "--day", "2026-01-06"],
+    );
+}
+
+#[test]
+fn log_project() {
+    assert_golden("log_project", &["log", "--project", "alpha"]);
+}
+
+#[test]
+fn log_json() {
+    assert_golden("log_json", &["--json", "log", "--limit", "2"]);
+}
+
+#[test]
+fn facets() {
+    assert_golden_steps(
+        "facets",
+        &[
+            &["facets", "models"],
+            &["facets", "tools", "--limit", "2"],
+            &["facets", "branches", "--project", "alpha"],
+            &["--json", "facets", "files"],
+            &["sessions", "tag", "add", "1", "flaky"],
+            &["sessions", "tag", "add", "2", "flaky", "auth"],
+            &["facets", "tags"],
+        ],
+    );
+}
+
+#[test]
+fn alias() {
+    let config = "{out}/config.toml";
+    assert_golden_steps(
+        "alias",
+        &[
+            &["--config", config, "alias", "set", "log", "sessions list"],
+            &[
+                "--config",
+                config,
+                "alias",
+                "set",
+                "bad",
+                "log --project 'alpha",
+            ],
+            &["--config", config, "alias", "remove", "nope"],
+            &[
+                "--config",
+                config,
+                "alias",
+                "set",
+                "recent",
+                "sessions list --limit 2",
+            ],
+            &[
+                "--config",
+                config,
+                "alias",
+                "set",
+                "alpha-log",
+                "log --project alpha",
+            ],
+            &["--config", config, "alias", "list"],
+            &["--config", config, "alpha-log", "--limit", "1"],
+            &["--config", config, "alias", "remove", "recent"],
+            &["--config", config, "--json", "alias", "list"],
+        ],
+    );
+}
+
+#[test]
+fn init() {
+    let config = "{out}/config.toml";
+    assert_golden_steps(
+        "init",
+        &[
+            &["--config", config, "--json", "init"],
+            &["--config", config, "init", "--print"],
+            &["--config", config, "init", "--yes", "--no-completions"],
+            &["--config", config, "init", "--yes", "--no-completions"],
+            &["--config", config, "sessions", "list", "--limit", "1"],
+        ],
+    );
+}
+
+#[test]
+fn stats_by_project() {
+    assert_golden_steps(
+        "stats_by_project",
+        &[
+            &["stats", "--top", "2", "--by-project"],
+            &["--json", "stats", "--top", "1", "--by-project"],
+        ],
+    );
+}
+
+#[test]
+fn stats_tools() {
+    assert_golden_steps(
+        "stats_tools",
+        &[
+            &["stats", "--top", "1", "--tools"],
+            &["--json", "stats", "--top", "1", "--tools"],
+        ],
+    );
+}
+
+#[test]
+fn stats_interruptions_json() {
+    assert_golden(
+        "stats_interruptions_json",
+        &["--json", "stats", "--top", "2", "--interruptions"],
+    );
+}
+
+#[test]
+fn stats_latency() {
+    assert_golden_steps(
+        "stats_latency",
+        &[
+            &["stats", "--top", "2", "--latency"],
+            &["--json", "stats", "--top", "1", "--latency"],
+        ],
+    );
+}
+
+#[test]
+fn stats_pricing() {
+    assert_golden("stats_pricing", &["stats", "--top", "3", "--pricing"]);
+}
+
+#[test]
+fn stats_pricing_override_json() {
+    assert_golden(
+        "stats_pricing_override_json",
+        &[
+            "--json",
+            "stats",
+            "--top",
+            "3",
+            "--pricing",
+            "tests/fixtures/pricing.toml",
+        ],
+    );
+}
+
+#[test]
+fn export_markdown_redact() {
+    assert_golden(
+        "export_markdown_redact",
+        &[
+            "export",
+            "--index",
+            "2",
+            "--redact-pattern",
+            r"https?://[a-z]+/",
+            "--format",
+            "markdown",
+            "--output",
+            "{out}",
+        ],
+    );
+}
+
+#[test]
+fn export_diff() {
+    assert_golden(
+        "export_diff",
+        &["export-diff", "tests/fixtures/export-diff-previous.json"],
+    );
+}
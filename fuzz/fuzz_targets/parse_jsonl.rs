@@ -0,0 +1,17 @@
+#![no_main]
+
+use cc_convo_core::{extract_content_text, parse_session_reader};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    for detailed in [false, true] {
+        let parsed = parse_session_reader(data, detailed)
+            .expect("in-memory reads cannot fail");
+        for event in &parsed.events {
+            assert!(event.content.len() <= data.len() * 16 + 4096);
+        }
+    }
+    if let Ok(value) = serde_json::from_slice::<serde_json::Value>(data) {
+        let _ = extract_content_text(&value, true);
+    }
+});
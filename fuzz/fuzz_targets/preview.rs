@@ -0,0 +1,13 @@
+#![no_main]
+
+use cc_convo_core::text::display_width;
+use cc_convo_core::{build_context_preview, clean_preview, ellipsize, PREVIEW_WIDTH};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: (String, String, u8, bool)| {
+    let (text, query, context, case_sensitive) = input;
+    let context = context as usize;
+    let _ = build_context_preview(&text, &query, context, case_sensitive);
+    assert!(display_width(&ellipsize(&text, context)) <= context);
+    assert!(display_width(&clean_preview(&text)) <= PREVIEW_WIDTH);
+});
@@ -1,8 +1,9 @@
+use cc_convo_core::SchemaInventory;
 use chrono::{SecondsFormat, TimeZone, Utc};
 use clap::{Args, Parser, Subcommand};
 use glob::glob;
-use serde_json::Value;
-use std::collections::{BTreeSet, HashMap};
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
 use std::fs::{self, File};
 use std::io::{BufRead, BufReader, BufWriter, Write};
 use std::path::{Path, PathBuf};
@@ -22,10 +23,70 @@ struct Cli {
 enum Command {
     /// Generate a schema inventory from Claude transcript JSONL files.
     SchemaInventory(SchemaInventoryArgs),
+    /// Compare a fresh inventory against a committed field-stats baseline.
+    SchemaDiff(SchemaDiffArgs),
+    /// Write fixture transcripts covering every record type for integration tests.
+    GenFixtures(GenFixturesArgs),
+    /// Regenerate golden files for the cc-convo integration tests.
+    Bless,
 }
 
 #[derive(Args, Debug)]
 struct SchemaInventoryArgs {
+    #[command(flatten)]
+    selection: TranscriptSelectionArgs,
+
+    /// Output directory for generated inventory artifacts.
+    #[arg(long, default_value = "docs/context")]
+    out_dir: PathBuf,
+
+    /// Baseline bundled into cc-convo for `doctor`'s schema drift check.
+    #[arg(long, default_value = "cc-convo-core/src/schema-baseline.tsv")]
+    baseline_out: PathBuf,
+}
+
+#[derive(Args, Debug)]
+struct SchemaDiffArgs {
+    #[command(flatten)]
+    selection: TranscriptSelectionArgs,
+
+    /// Field-stats CSV produced by `schema-inventory`.
+    #[arg(long, default_value = "docs/context/claude-jsonl-field-stats.csv")]
+    baseline: PathBuf,
+
+    /// Report fields whose prevalence moved by at least this many percentage points.
+    #[arg(long, default_value_t = 5.0)]
+    threshold_pp: f64,
+
+    /// Ignore fields below this prevalence (percent) in both inventories.
+    #[arg(long, default_value_t = 0.1)]
+    min_percent: f64,
+}
+
+#[derive(Args, Debug)]
+struct GenFixturesArgs {
+    #[command(flatten)]
+    selection: TranscriptSelectionArgs,
+
+    /// Sample real transcripts instead of writing the built-in synthetic corpus.
+    #[arg(long)]
+    from_real: bool,
+
+    /// Scrub content, identifiers, and paths from sampled records (required with --from-real).
+    #[arg(long)]
+    redact: bool,
+
+    /// Keep at most N sample records per record/content-block type.
+    #[arg(long, default_value_t = 2)]
+    per_type: usize,
+
+    /// Fixture root; files are written as `<out-dir>/projects/<project>/<session>.jsonl`.
+    #[arg(long, default_value = "cc-convo/tests/fixtures")]
+    out_dir: PathBuf,
+}
+
+#[derive(Args, Debug)]
+struct TranscriptSelectionArgs {
     /// Scan all transcript files (otherwise scans latest N files).
     #[arg(long)]
     all: bool,
@@ -42,10 +103,6 @@ struct SchemaInventoryArgs {
     #[arg(long, conflicts_with = "since_hours")]
     since_days: Option<u64>,
 
-    /// Output directory for generated inventory artifacts.
-    #[arg(long, default_value = "docs/context")]
-    out_dir: PathBuf,
-
     /// Glob for transcript JSONL files.
     #[arg(long, default_value = "~/.claude/projects/*/*.jsonl")]
     glob: String,
@@ -57,26 +114,46 @@ struct FileMeta {
     mtime_epoch: u64,
 }
 
-#[derive(Debug, Clone)]
-enum PathSeg {
-    Key(String),
-    Array,
+#[derive(Debug, Default)]
+struct Inventory {
+    parse_errors: u64,
+    schema: SchemaInventory,
 }
 
 fn main() -> Result<(), DynError> {
     let cli = Cli::parse();
     match cli.command {
         Command::SchemaInventory(args) => run_schema_inventory(args),
+        Command::SchemaDiff(args) => run_schema_diff(args),
+        Command::GenFixtures(args) => run_gen_fixtures(args),
+        Command::Bless => run_bless(),
     }
 }
 
-fn run_schema_inventory(args: SchemaInventoryArgs) -> Result<(), DynError> {
+fn run_bless() -> Result<(), DynError> {
+    let cargo = std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+    let status = std::process::Command::new(cargo)
+        .args(["test", "-p", "cc-convo", "--test", "golden"])
+        .env("CC_CONVO_BLESS", "1")
+        .status()?;
+    if !status.success() {
+        return Err(format!("Golden test run failed: {status}").into());
+    }
+    println!("Golden files updated under cc-convo/tests/golden/.");
+    Ok(())
+}
+
+struct SelectedTranscripts {
+    transcript_glob: String,
+    time_filter_desc: String,
+    files: Vec<FileMeta>,
+}
+
+fn select_transcripts(args: &TranscriptSelectionArgs) -> Result<SelectedTranscripts, DynError> {
     if !args.all && args.latest == 0 {
         return Err("`--latest` must be greater than 0.".into());
     }
 
-    fs::create_dir_all(&args.out_dir)?;
-
     let transcript_glob = expand_tilde(&args.glob)?;
     let mut all_files = collect_files_sorted_by_mtime(&transcript_glob)?;
     if all_files.is_empty() {
@@ -106,58 +183,40 @@ fn run_schema_inventory(args: SchemaInventoryArgs) -> Result<(), DynError> {
         return Err("No transcript files selected after filtering.".into());
     }
 
-    let selected_list_path = args.out_dir.join("claude-jsonl-selected-files.txt");
-    write_selected_files_list(&selected_list_path, &selected_files)?;
-
-    let mut total_records: u64 = 0;
-    let mut type_counts: HashMap<String, u64> = HashMap::new();
-    let mut field_counts: HashMap<String, u64> = HashMap::new();
-    let mut parse_errors: u64 = 0;
-
-    for file in &selected_files {
-        let f = File::open(&file.path)?;
-        let reader = BufReader::new(f);
-
-        for line_result in reader.lines() {
-            let line = line_result?;
-            if line.trim().is_empty() {
-                continue;
-            }
+    Ok(SelectedTranscripts {
+        transcript_glob,
+        time_filter_desc,
+        files: selected_files,
+    })
+}
 
-            let value: Value = match serde_json::from_str(&line) {
-                Ok(v) => v,
-                Err(_) => {
-                    parse_errors += 1;
-                    continue;
-                }
-            };
+fn run_schema_inventory(args: SchemaInventoryArgs) -> Result<(), DynError> {
+    let SelectedTranscripts {
+        transcript_glob,
+        time_filter_desc,
+        files: selected_files,
+    } = select_transcripts(&args.selection)?;
 
-            total_records += 1;
+    fs::create_dir_all(&args.out_dir)?;
 
-            let record_type = value
-                .get("type")
-                .and_then(Value::as_str)
-                .unwrap_or("<missing>")
-                .to_string();
-            increment_count(&mut type_counts, record_type);
+    let selected_list_path = args.out_dir.join("claude-jsonl-selected-files.txt");
+    write_selected_files_list(&selected_list_path, &selected_files)?;
 
-            let mut field_set: BTreeSet<String> = BTreeSet::new();
-            let mut segs: Vec<PathSeg> = Vec::new();
-            collect_field_paths(&value, &mut segs, &mut field_set);
-            for field in field_set {
-                increment_count(&mut field_counts, field);
-            }
-        }
-    }
+    let Inventory {
+        parse_errors,
+        schema,
+    } = collect_inventory(&selected_files)?;
+    let total_records = schema.records;
 
-    if total_records == 0 {
-        return Err("Selected transcript files contain zero parseable JSONL records.".into());
+    if let Some(parent) = args.baseline_out.parent() {
+        fs::create_dir_all(parent)?;
     }
+    fs::write(&args.baseline_out, schema.render_baseline())?;
 
-    let mut sorted_types: Vec<(String, u64)> = type_counts.into_iter().collect();
+    let mut sorted_types: Vec<(String, u64)> = schema.types.into_iter().collect();
     sorted_types.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
 
-    let mut sorted_fields: Vec<(String, u64)> = field_counts.into_iter().collect();
+    let mut sorted_fields: Vec<(String, u64)> = schema.fields.into_iter().collect();
     sorted_fields.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
 
     let type_csv_path = args.out_dir.join("claude-jsonl-type-stats.csv");
@@ -170,7 +229,7 @@ fn run_schema_inventory(args: SchemaInventoryArgs) -> Result<(), DynError> {
         &report_path,
         &args.out_dir,
         &transcript_glob,
-        args.all,
+        args.selection.all,
         &time_filter_desc,
         &selected_files,
         total_records,
@@ -184,10 +243,570 @@ fn run_schema_inventory(args: SchemaInventoryArgs) -> Result<(), DynError> {
     println!("  - {}", field_csv_path.display());
     println!("  - {}", type_csv_path.display());
     println!("  - {}", selected_list_path.display());
+    println!("  - {}", args.baseline_out.display());
+
+    Ok(())
+}
+
+fn collect_inventory(files: &[FileMeta]) -> Result<Inventory, DynError> {
+    let mut inventory = Inventory::default();
+
+    for file in files {
+        let f = File::open(&file.path)?;
+        let reader = BufReader::new(f);
+
+        for line_result in reader.lines() {
+            let line = line_result?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let value: Value = match serde_json::from_str(&line) {
+                Ok(v) => v,
+                Err(_) => {
+                    inventory.parse_errors += 1;
+                    continue;
+                }
+            };
+
+            inventory.schema.observe(&value);
+        }
+    }
+
+    if inventory.schema.records == 0 {
+        return Err("Selected transcript files contain zero parseable JSONL records.".into());
+    }
+    Ok(inventory)
+}
+
+/// Exit code used by `schema-diff` when drift is detected.
+const DRIFT_EXIT_CODE: i32 = 2;
+
+fn run_schema_diff(args: SchemaDiffArgs) -> Result<(), DynError> {
+    let baseline = read_field_stats_csv(&args.baseline)?;
+    if baseline.is_empty() {
+        return Err(format!("Baseline {} has no field rows.", args.baseline.display()).into());
+    }
+
+    let selected = select_transcripts(&args.selection)?;
+    let inventory = collect_inventory(&selected.files)?;
+    let total_records = inventory.schema.records;
+    let fresh: HashMap<String, f64> = inventory
+        .schema
+        .fields
+        .iter()
+        .map(|(field, count)| {
+            let pct = (*count as f64 / total_records as f64) * 100.0;
+            (field.clone(), pct)
+        })
+        .collect();
+
+    let diff = diff_field_stats(&baseline, &fresh, args.threshold_pp, args.min_percent);
+
+    println!("Schema diff against {}", args.baseline.display());
+    println!(
+        "  Scanned {} file(s), {} record(s) ({}, glob `{}`)",
+        selected.files.len(),
+        total_records,
+        selected.time_filter_desc,
+        selected.transcript_glob
+    );
+    print_field_section("Added fields", &diff.added, |(field, pct)| {
+        format!("+ {field} ({pct:.2}%)")
+    });
+    print_field_section("Removed fields", &diff.removed, |(field, pct)| {
+        format!("- {field} (was {pct:.2}%)")
+    });
+    print_field_section("Changed prevalence", &diff.changed, |(field, old, new)| {
+        format!("~ {field} {old:.2}% -> {new:.2}% ({:+.2}pp)", new - old)
+    });
+
+    if diff.is_empty() {
+        println!("No drift detected.");
+        return Ok(());
+    }
+    println!(
+        "Drift detected: {} added, {} removed, {} changed.",
+        diff.added.len(),
+        diff.removed.len(),
+        diff.changed.len()
+    );
+    std::process::exit(DRIFT_EXIT_CODE);
+}
+
+#[derive(Debug, Default)]
+struct FieldStatsDiff {
+    added: Vec<(String, f64)>,
+    removed: Vec<(String, f64)>,
+    changed: Vec<(String, f64, f64)>,
+}
+
+impl FieldStatsDiff {
+    fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+fn diff_field_stats(
+    baseline: &HashMap<String, f64>,
+    fresh: &HashMap<String, f64>,
+    threshold_pp: f64,
+    min_percent: f64,
+) -> FieldStatsDiff {
+    let mut diff = FieldStatsDiff::default();
+    for (field, new_pct) in fresh {
+        match baseline.get(field) {
+            None if *new_pct >= min_percent => diff.added.push((field.clone(), *new_pct)),
+            Some(old_pct)
+                if (*old_pct >= min_percent || *new_pct >= min_percent)
+                    && (new_pct - old_pct).abs() >= threshold_pp =>
+            {
+                diff.changed.push((field.clone(), *old_pct, *new_pct));
+            }
+            _ => {}
+        }
+    }
+    for (field, old_pct) in baseline {
+        if !fresh.contains_key(field) && *old_pct >= min_percent {
+            diff.removed.push((field.clone(), *old_pct));
+        }
+    }
+    diff.added
+        .sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    diff.removed
+        .sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    diff.changed.sort_by(|a, b| {
+        (b.2 - b.1)
+            .abs()
+            .total_cmp(&(a.2 - a.1).abs())
+            .then_with(|| a.0.cmp(&b.0))
+    });
+    diff
+}
+
+fn print_field_section<T>(title: &str, items: &[T], render: impl Fn(&T) -> String) {
+    if items.is_empty() {
+        return;
+    }
+    println!();
+    println!("{title} ({}):", items.len());
+    for item in items {
+        println!("  {}", render(item));
+    }
+}
+
+/// Read `field_path -> percent_of_records` from a field-stats CSV.
+fn read_field_stats_csv(path: &Path) -> Result<HashMap<String, f64>, DynError> {
+    let file = File::open(path).map_err(|e| format!("Cannot open {}: {e}", path.display()))?;
+    let reader = BufReader::new(file);
+    let mut out = HashMap::new();
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        if i == 0 || line.trim().is_empty() {
+            continue;
+        }
+        let cols = parse_csv_line(&line);
+        let (Some(field), Some(pct)) = (cols.first(), cols.get(2)) else {
+            return Err(
+                format!("{}:{}: expected at least 3 columns", path.display(), i + 1).into(),
+            );
+        };
+        let pct: f64 = pct
+            .parse()
+            .map_err(|_| format!("{}:{}: invalid percent `{pct}`", path.display(), i + 1))?;
+        out.insert(field.clone(), pct);
+    }
+    Ok(out)
+}
+
+fn parse_csv_line(line: &str) -> Vec<String> {
+    let mut cols = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                current.push('"');
+                chars.next();
+            }
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => cols.push(std::mem::take(&mut current)),
+            _ => current.push(c),
+        }
+    }
+    cols.push(current);
+    cols
+}
+
+fn run_gen_fixtures(args: GenFixturesArgs) -> Result<(), DynError> {
+    let projects_dir = args.out_dir.join("projects");
+    let fixtures = if args.from_real {
+        if !args.redact {
+            return Err(
+                "`--from-real` requires `--redact`; fixtures must not contain private data.".into(),
+            );
+        }
+        let selected = select_transcripts(&args.selection)?;
+        sample_real_fixtures(&selected.files, args.per_type)?
+    } else {
+        synthetic_fixtures()
+    };
+
+    if projects_dir.exists() {
+        fs::remove_dir_all(&projects_dir)?;
+    }
+    let mut written = Vec::new();
+    for fixture in &fixtures {
+        let dir = projects_dir.join(&fixture.project);
+        fs::create_dir_all(&dir)?;
+        let path = dir.join(format!("{}.jsonl", fixture.session));
+        let mut body = String::new();
+        for line in &fixture.lines {
+            body.push_str(line);
+            body.push('\n');
+        }
+        fs::write(&path, body)?;
+        written.push(path);
+    }
 
+    println!("Wrote {} fixture file(s):", written.len());
+    for path in &written {
+        println!("  - {}", path.display());
+    }
     Ok(())
 }
 
+struct Fixture {
+    project: String,
+    session: String,
+    lines: Vec<String>,
+}
+
+/// Sample records so every record type and content-block type is represented.
+fn sample_real_fixtures(files: &[FileMeta], per_type: usize) -> Result<Vec<Fixture>, DynError> {
+    let mut redactor = Redactor::default();
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    let mut fixtures = Vec::new();
+
+    for file in files {
+        let f = File::open(&file.path)?;
+        let reader = BufReader::new(f);
+        let mut lines = Vec::new();
+        for line_result in reader.lines() {
+            let line = line_result?;
+            let Ok(value) = serde_json::from_str::<Value>(&line) else {
+                continue;
+            };
+            let mut wanted = false;
+            for key in fixture_coverage_keys(&value) {
+                let count = seen.entry(key).or_insert(0);
+                if *count < per_type {
+                    *count += 1;
+                    wanted = true;
+                }
+            }
+            if wanted {
+                lines.push(serde_json::to_string(&redactor.redact(&value))?);
+            }
+        }
+        if !lines.is_empty() {
+            let n = fixtures.len() + 1;
+            fixtures.push(Fixture {
+                project: format!("-home-user-project-{n}"),
+                session: redactor.map_id(
+                    file.path
+                        .file_stem()
+                        .and_then(|s| s.to_str())
+                        .unwrap_or("session"),
+                ),
+                lines,
+            });
+        }
+    }
+
+    if fixtures.is_empty() {
+        return Err("No parseable records found to sample.".into());
+    }
+    Ok(fixtures)
+}
+
+fn fixture_coverage_keys(value: &Value) -> Vec<String> {
+    let record_type = value
+        .get("type")
+        .and_then(Value::as_str)
+        .unwrap_or("<missing>");
+    let mut keys = vec![format!("record:{record_type}")];
+    if let Some(sub) = value.get("subtype").and_then(Value::as_str) {
+        keys.push(format!("subtype:{record_type}:{sub}"));
+    }
+    if let Some(sub) = value
+        .get("data")
+        .and_then(|d| d.get("type"))
+        .and_then(Value::as_str)
+    {
+        keys.push(format!("data:{sub}"));
+    }
+    if let Some(content) = value
+        .get("message")
+        .and_then(|m| m.get("content"))
+        .and_then(Value::as_array)
+    {
+        for block in content {
+            if let Some(t) = block.get("type").and_then(Value::as_str) {
+                keys.push(format!("block:{record_type}:{t}"));
+            }
+        }
+    }
+    keys
+}
+
+/// Keys whose string values are structural and safe to keep verbatim.
+const REDACT_KEEP_KEYS: &[&str] = &[
+    "type",
+    "role",
+    "model",
+    "subtype",
+    "stop_reason",
+    "level",
+    "operation",
+    "version",
+    "media_type",
+    "permissionMode",
+    "hookEvent",
+    "timestamp",
+    "service_tier",
+];
+
+/// Keys holding identifiers that are remapped consistently across a fixture set.
+const REDACT_ID_KEYS: &[&str] = &[
+    "uuid",
+    "parentUuid",
+    "logicalParentUuid",
+    "sessionId",
+    "leafUuid",
+    "messageId",
+    "id",
+    "tool_use_id",
+    "toolUseID",
+    "parentToolUseID",
+    "sourceToolAssistantUUID",
+    "requestId",
+    "agentId",
+    "promptId",
+];
+
+/// Deterministic scrubber for sampled transcript records.
+#[derive(Default)]
+struct Redactor {
+    ids: HashMap<String, String>,
+}
+
+impl Redactor {
+    fn redact(&mut self, value: &Value) -> Value {
+        self.redact_with_key(None, value)
+    }
+
+    fn redact_with_key(&mut self, key: Option<&str>, value: &Value) -> Value {
+        match value {
+            Value::Object(map) => {
+                let tool_use = map.get("type").and_then(Value::as_str) == Some("tool_use");
+                let mut out = Map::new();
+                for (k, v) in map {
+                    let redacted = if k == "name" && tool_use {
+                        v.clone()
+                    } else if k == "trackedFileBackups" {
+                        json!({})
+                    } else {
+                        self.redact_with_key(Some(k), v)
+                    };
+                    out.insert(k.clone(), redacted);
+                }
+                Value::Object(out)
+            }
+            Value::Array(items) => Value::Array(
+                items
+                    .iter()
+                    .map(|item| self.redact_with_key(key, item))
+                    .collect(),
+            ),
+            Value::String(s) => Value::String(self.redact_string(key, s)),
+            other => other.clone(),
+        }
+    }
+
+    fn redact_string(&mut self, key: Option<&str>, s: &str) -> String {
+        match key {
+            Some(k) if REDACT_KEEP_KEYS.contains(&k) => s.to_string(),
+            Some(k) if REDACT_ID_KEYS.contains(&k) => self.map_id(s),
+            Some("cwd") => "/home/user/project".to_string(),
+            Some("gitBranch") => "main".to_string(),
+            Some("data") | Some("signature") => "AAAA".to_string(),
+            _ => scrub_text(s),
+        }
+    }
+
+    fn map_id(&mut self, original: &str) -> String {
+        let next = self.ids.len() + 1;
+        self.ids
+            .entry(original.to_string())
+            .or_insert_with(|| {
+                let prefix = original
+                    .split_once('_')
+                    .map(|(p, _)| format!("{p}_"))
+                    .filter(|p| {
+                        p.len() <= 8 && p.chars().all(|c| c.is_ascii_alphabetic() || c == '_')
+                    })
+                    .unwrap_or_default();
+                format!("{prefix}00000000-0000-4000-8000-{next:012}")
+            })
+            .clone()
+    }
+}
+
+/// Replace text with placeholder words, keeping line structure and capping length.
+fn scrub_text(s: &str) -> String {
+    const WORDS: &[&str] = &["lorem", "ipsum", "dolor", "sit", "amet"];
+    let mut out = String::new();
+    let mut word = 0;
+    for (i, line) in s.lines().take(8).enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+        let words = line.split_whitespace().count().min(24);
+        let scrubbed = (0..words)
+            .map(|_| {
+                word += 1;
+                WORDS[(word - 1) % WORDS.len()]
+            })
+            .collect::<Vec<_>>();
+        out.push_str(&scrubbed.join(" "));
+    }
+    out
+}
+
+/// Built-in corpus covering every record and content-block type cc-convo handles.
+fn synthetic_fixtures() -> Vec<Fixture> {
+    let s1 = "11111111-1111-4111-8111-111111111111";
+    let s2 = "22222222-2222-4222-8222-222222222222";
+    let s3 = "33333333-3333-4333-8333-333333333333";
+    let alpha = vec![
+        json!({"type": "summary", "summary": "Fix flaky login test", "leafUuid": "a-0004"}),
+        json!({"type": "custom-title", "customTitle": "login triage", "sessionId": s1}),
+        json!({"type": "file-history-snapshot", "messageId": "a-0001", "isSnapshotUpdate": false,
+            "snapshot": {"messageId": "a-0001", "timestamp": "2026-01-05T09:00:00.000Z", "trackedFileBackups": {}}}),
+        json!({"type": "queue-operation", "operation": "enqueue", "sessionId": s1,
+            "timestamp": "2026-01-05T09:00:00.000Z", "content": "Why does the login test fail?"}),
+        json!({"type": "user", "uuid": "a-0001", "parentUuid": null, "sessionId": s1,
+            "cwd": "/home/user/alpha", "gitBranch": "feature/AUTH-42-login", "version": "2.0.0",
+            "timestamp": "2026-01-05T09:00:01.000Z",
+            "message": {"role": "user", "content": "Why does the login test fail intermittently?"}}),
+        json!({"type": "assistant", "uuid": "a-0002", "parentUuid": "a-0001", "sessionId": s1,
+            "cwd": "/home/user/alpha", "gitBranch": "feature/AUTH-42-login", "requestId": "req_0001",
+            "timestamp": "2026-01-05T09:00:05.000Z",
+            "message": {"id": "msg_0001", "role": "assistant", "model": "claude-opus-4-1-20250805",
+                "stop_reason": "tool_use",
+                "content": [
+                    {"type": "thinking", "thinking": "Check the test file first.", "signature": "AAAA"},
+                    {"type": "tool_use", "id": "toolu_0001", "name": "Bash",
+                        "input": {"command": "cargo test login -- --nocapture", "description": "Run login tests"}}
+                ],
+                "usage": {"input_tokens": 1200, "output_tokens": 80,
+                    "cache_creation_input_tokens": 300, "cache_read_input_tokens": 900}}}),
+        json!({"type": "progress", "uuid": "a-0003p", "parentUuid": "a-0002", "sessionId": s1,
+            "timestamp": "2026-01-05T09:00:06.000Z", "toolUseID": "toolu_0001",
+            "data": {"type": "bash_progress", "output": "running 1 test", "elapsedTimeSeconds": 1}}),
+        json!({"type": "user", "uuid": "a-0003", "parentUuid": "a-0002", "sessionId": s1,
+        "timestamp": "2026-01-05T09:00:09.000Z",
+        "message": {"role": "user", "content": [
+            {"type": "tool_result", "tool_use_id": "toolu_0001", "is_error": true,
+                "content": "test login ... FAILED\nthread 'login' panicked at src/auth/login.rs:42"}
+        ]}}),
+        json!({"type": "assistant", "uuid": "a-0004", "parentUuid": "a-0003", "sessionId": s1,
+            "timestamp": "2026-01-05T09:00:15.000Z",
+            "message": {"id": "msg_0002", "role": "assistant", "model": "claude-opus-4-1-20250805",
+                "stop_reason": "end_turn",
+                "content": [{"type": "text", "text": "The test races the session cache. Await the cache warm-up in `src/auth/login.rs`."}],
+                "usage": {"input_tokens": 1500, "output_tokens": 40}}}),
+        json!({"type": "system", "subtype": "stop_hook_summary", "uuid": "a-0005", "parentUuid": "a-0004",
+            "sessionId": s1, "timestamp": "2026-01-05T09:00:16.000Z", "level": "suggestion",
+            "hookCount": 1, "hasOutput": false, "preventedContinuation": false}),
+        json!({"type": "pr-link", "sessionId": s1, "prNumber": 42,
+            "prUrl": "https://example.com/acme/alpha/pull/42", "prRepository": "acme/alpha"}),
+    ];
+    let alpha_followup = vec![
+        json!({"type": "user", "uuid": "b-0001", "parentUuid": null, "sessionId": s2,
+        "cwd": "/home/user/alpha", "gitBranch": "main", "timestamp": "2026-01-06T14:30:00.000Z",
+        "message": {"role": "user", "content": [
+            {"type": "text", "text": "Here is a screenshot of the nginx proxy error."},
+            {"type": "image", "source": {"type": "base64", "media_type": "image/png", "data": "iVBORw0KGgo="}}
+        ]}}),
+        json!({"type": "progress", "uuid": "b-0001p", "parentUuid": "b-0001", "sessionId": s2,
+            "timestamp": "2026-01-06T14:30:01.000Z",
+            "data": {"type": "hook_progress", "hookEvent": "UserPromptSubmit", "hookName": "UserPromptSubmit", "command": "echo ok"}}),
+        json!({"type": "assistant", "uuid": "b-0002", "parentUuid": "b-0001", "sessionId": s2,
+            "timestamp": "2026-01-06T14:30:20.000Z",
+            "message": {"id": "msg_0003", "role": "assistant", "model": "claude-sonnet-4-5-20250929",
+                "stop_reason": "end_turn",
+                "content": [{"type": "text", "text": "The proxy_pass target is missing a trailing slash.\n\n```nginx\nlocation /api/ {\n    proxy_pass http://backend/;\n}\n```"}],
+                "usage": {"input_tokens": 800, "output_tokens": 60}}}),
+        json!({"type": "system", "subtype": "api_error", "uuid": "b-0003", "parentUuid": "b-0002",
+            "sessionId": s2, "timestamp": "2026-01-06T14:31:00.000Z", "level": "error",
+            "error": {"status": 529, "message": "Overloaded"}, "retryAttempt": 1}),
+    ];
+    let beta = vec![
+        json!({"type": "user", "uuid": "c-0001", "parentUuid": null, "sessionId": s3,
+            "cwd": "/home/user/beta", "gitBranch": "main", "timestamp": "2026-01-07T08:00:00.000Z",
+            "message": {"role": "user", "content": "Summarise the attached design doc."}}),
+        json!({"type": "user", "uuid": "c-0002", "parentUuid": "c-0001", "sessionId": s3,
+        "timestamp": "2026-01-07T08:00:01.000Z",
+        "message": {"role": "user", "content": [
+            {"type": "document", "source": {"type": "base64", "media_type": "application/pdf", "data": "JVBERi0x"}}
+        ]}}),
+        json!({"type": "assistant", "uuid": "c-0003", "parentUuid": "c-0002", "sessionId": s3,
+            "timestamp": "2026-01-07T08:00:30.000Z",
+            "message": {"id": "msg_0004", "role": "assistant", "model": "claude-haiku-4-5-20251001",
+                "stop_reason": "end_turn",
+                "content": [{"type": "text", "text": "The doc proposes splitting the monolith into three services."}],
+                "usage": {"input_tokens": 3000, "output_tokens": 25}}}),
+        // Regenerated reply: a second child of c-0002, so threading sees a branch.
+        json!({"type": "assistant", "uuid": "c-0004", "parentUuid": "c-0002", "sessionId": s3,
+            "timestamp": "2026-01-07T08:00:50.000Z",
+            "message": {"id": "msg_0005", "role": "assistant", "model": "claude-haiku-4-5-20251001",
+                "stop_reason": "end_turn",
+                "content": [{"type": "text", "text": "It splits the monolith into auth, billing, and search services."}],
+                "usage": {"input_tokens": 3000, "output_tokens": 30}}}),
+    ];
+
+    let to_lines = |records: Vec<Value>| {
+        records
+            .into_iter()
+            .map(|r| serde_json::to_string(&r).expect("fixture record serializes"))
+            .collect::<Vec<_>>()
+    };
+    let mut beta_lines = to_lines(beta);
+    beta_lines
+        .push("{\"type\":\"user\",\"timestamp\":\"2026-01-07T08:01:00.000Z\",\"mess".to_string());
+
+    vec![
+        Fixture {
+            project: "-home-user-alpha".to_string(),
+            session: s1.to_string(),
+            lines: to_lines(alpha),
+        },
+        Fixture {
+            project: "-home-user-alpha".to_string(),
+            session: s2.to_string(),
+            lines: to_lines(alpha_followup),
+        },
+        Fixture {
+            project: "-home-user-beta".to_string(),
+            session: s3.to_string(),
+            lines: beta_lines,
+        },
+    ]
+}
+
 fn build_time_filter(
     since_hours: Option<u64>,
     since_days: Option<u64>,
@@ -237,59 +856,6 @@ fn collect_files_sorted_by_mtime(glob_pattern: &str) -> Result<Vec<FileMeta>, Dy
     Ok(files)
 }
 
-fn collect_field_paths(value: &Value, segs: &mut Vec<PathSeg>, out: &mut BTreeSet<String>) {
-    match value {
-        Value::Object(map) => {
-            for (key, child) in map {
-                segs.push(PathSeg::Key(key.clone()));
-                out.insert(canonical_path(segs));
-                collect_field_paths(child, segs, out);
-                segs.pop();
-            }
-        }
-        Value::Array(items) => {
-            for item in items {
-                segs.push(PathSeg::Array);
-                collect_field_paths(item, segs, out);
-                segs.pop();
-            }
-        }
-        _ => {}
-    }
-}
-
-fn canonical_path(segs: &[PathSeg]) -> String {
-    let mut result = String::new();
-    let mut after_tracked_file_backups = false;
-
-    for seg in segs {
-        match seg {
-            PathSeg::Array => {
-                result.push_str("[]");
-            }
-            PathSeg::Key(key) => {
-                let part = if after_tracked_file_backups {
-                    after_tracked_file_backups = false;
-                    "{path}"
-                } else {
-                    key.as_str()
-                };
-
-                if !result.is_empty() {
-                    result.push('.');
-                }
-                result.push_str(part);
-
-                if part == "trackedFileBackups" {
-                    after_tracked_file_backups = true;
-                }
-            }
-        }
-    }
-
-    result
-}
-
 fn write_selected_files_list(path: &Path, files: &[FileMeta]) -> Result<(), DynError> {
     let file = File::create(path)?;
     let mut writer = BufWriter::new(file);
@@ -310,13 +876,7 @@ fn write_type_csv(
     writeln!(writer, "\"type\",\"count\",\"percent_of_records\"")?;
     for (typ, count) in items {
         let pct = (*count as f64 / total_records as f64) * 100.0;
-        writeln!(
-            writer,
-            "{},{},{}",
-            csv_escape(typ),
-            count,
-            format!("{pct:.6}")
-        )?;
+        writeln!(writer, "{},{},{pct:.6}", csv_escape(typ), count)?;
     }
     writer.flush()?;
     Ok(())
@@ -338,10 +898,9 @@ fn write_field_csv(
         let desc = describe_field(field);
         writeln!(
             writer,
-            "{},{},{},{}",
+            "{},{},{pct:.6},{}",
             csv_escape(field),
             count,
-            format!("{pct:.6}"),
             csv_escape(&desc)
         )?;
     }
@@ -716,10 +1275,6 @@ fn csv_escape(input: &str) -> String {
     format!("\"{}\"", input.replace('\"', "\"\""))
 }
 
-fn increment_count(map: &mut HashMap<String, u64>, key: String) {
-    *map.entry(key).or_insert(0) += 1;
-}
-
 fn epoch_now() -> Result<u64, DynError> {
     to_epoch(SystemTime::now())
 }